@@ -0,0 +1,103 @@
+extern crate flow_rs;
+use flow_rs::exchange::clearing_house::ClearingHouse;
+use flow_rs::players::Player;
+use flow_rs::players::investor::Investor;
+use flow_rs::order::order::DEFAULT_SYMBOL;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::thread;
+use std::env;
+
+// Measures how long the tasks take to read and update hundreds of players in the ClearingHouse's
+// RwLock map compared to the single Mutex map it replaced. Most of the tasks' accesses are lookups.
+// Usage: cargo run --release --example player_contention [num_players] [num_threads] [ops_per_thread]
+
+const WRITES_EVERY: usize = 10;
+
+// The storage the ClearingHouse used before, every lookup serializes on the one lock
+struct MutexPlayers {
+	players: Mutex<HashMap<String, Box<dyn Player + Send>>>,
+}
+
+impl MutexPlayers {
+	fn get_bal_inv(&self, id: &str) -> Option<(f64, f64)> {
+		let players = self.players.lock().unwrap();
+		players.get(id).map(|p| (p.get_bal(), p.get_inv(DEFAULT_SYMBOL)))
+	}
+
+	fn update_player_bal(&self, id: &str, bal_to_add: f64) -> Option<f64> {
+		let mut players = self.players.lock().unwrap();
+		players.get_mut(id).map(|p| {
+			p.update_bal(bal_to_add);
+			p.get_bal()
+		})
+	}
+}
+
+// Runs ops_per_thread lookups on each thread with every WRITES_EVERY'th op an update
+fn run<F>(num_threads: usize, ops_per_thread: usize, ids: Arc<Vec<String>>, op: Arc<F>) -> Duration
+where F: Fn(&str, bool) + Send + Sync + 'static
+{
+	let start = Instant::now();
+	let handles: Vec<_> = (0..num_threads).map(|t| {
+		let ids = Arc::clone(&ids);
+		let op = Arc::clone(&op);
+		thread::spawn(move || {
+			for i in 0..ops_per_thread {
+				let id = &ids[(t * ops_per_thread + i) % ids.len()];
+				op(id, i % WRITES_EVERY == 0);
+			}
+		})
+	}).collect();
+	for h in handles {
+		h.join().unwrap();
+	}
+	start.elapsed()
+}
+
+fn arg_or(args: &mut env::Args, default: usize) -> usize {
+	match args.next() {
+		Some(arg) => arg.parse().expect("Arguments must be integers"),
+		None => default,
+	}
+}
+
+fn main() {
+	let mut args = env::args();
+	args.next(); // consume file name arg[0]
+	let num_players = arg_or(&mut args, 500);
+	let num_threads = arg_or(&mut args, 8);
+	let ops_per_thread = arg_or(&mut args, 200_000);
+
+	let ids: Arc<Vec<String>> = Arc::new((0..num_players).map(|i| format!("investor{}", i)).collect());
+
+	let house = Arc::new(ClearingHouse::new());
+	let baseline = Arc::new(MutexPlayers { players: Mutex::new(HashMap::new()) });
+	for id in ids.iter() {
+		house.reg_investor(Investor::new(id.clone()));
+		baseline.players.lock().unwrap().insert(id.clone(), Box::new(Investor::new(id.clone())));
+	}
+
+	let h = Arc::clone(&house);
+	let rwlock_time = run(num_threads, ops_per_thread, Arc::clone(&ids), Arc::new(move |id: &str, write: bool| {
+		match write {
+			true => { h.update_player_bal(id.to_string(), 1.0); },
+			false => { h.get_bal_inv(id.to_string(), DEFAULT_SYMBOL); },
+		}
+	}));
+
+	let b = Arc::clone(&baseline);
+	let mutex_time = run(num_threads, ops_per_thread, Arc::clone(&ids), Arc::new(move |id: &str, write: bool| {
+		match write {
+			true => { b.update_player_bal(id, 1.0); },
+			false => { b.get_bal_inv(id); },
+		}
+	}));
+
+	println!("{} players, {} threads, {} ops per thread, 1 in {} ops an update", num_players, num_threads, ops_per_thread, WRITES_EVERY);
+	println!("Mutex map:  {:?}", mutex_time);
+	println!("RwLock map: {:?}", rwlock_time);
+	println!("Speedup: {:.2}x", mutex_time.as_secs_f64() / rwlock_time.as_secs_f64());
+}
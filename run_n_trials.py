@@ -22,7 +22,7 @@ def main():
 
 
 	# write the header to the total_results.csv file
-	header = "market type,liquidated?,fund val,total gas,avg gas,total tax,maker profit,investor profit,miner profit,dead weight,volatility,rmsd,aggressive mkr prof,riskaverse mkr prof,random mkr profit,num agg,num riska,num rand,inv_welf,mkr_welf,min_welf,\n"
+	header = "market type,liquidated?,fund val,total gas,avg gas,total tax,maker profit,investor profit,miner profit,dead weight,volatility,rmsd,aggressive mkr prof,riskaverse mkr prof,random mkr profit,num agg,num riska,num rand,inv_welf,mkr_welf,min_welf,exchange_revenue,agg realized pnl,agg unrealized pnl,riskav realized pnl,riskav unrealized pnl,rand realized pnl,rand unrealized pnl,inventory tax,turnover tax,profit tax,skew mkr profit,num skew,skew realized pnl,skew unrealized pnl,gm mkr profit,num gm,gm realized pnl,gm unrealized pnl,\n"
 	f = open("log/results.csv".format(exp_name), "w")# write header to total_results.csv
 	f.write(header)
 	f.close()
@@ -0,0 +1,87 @@
+use crate::order::order::{Order, OrderType};
+use crate::simulation::simulation_config::Constants;
+
+
+/// The gas limit of a block and how much of it each type of order uses up. Cancels are
+/// cheaper than entering a new order by default, so cancel-heavy strategies crowd out less.
+/// limit: f64 -> total gas the orders of one block may use
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockGas {
+	pub limit: f64,
+	pub enter: f64,
+	pub update: f64,
+	pub cancel: f64,
+}
+
+impl BlockGas {
+	/// The block gas limit from the Constants, or block_size orders of any type when
+	/// block_gas_limit isn't set
+	pub fn new(consts: &Constants) -> BlockGas {
+		match consts.block_gas_limit > 0.0 {
+			true => BlockGas {
+				limit: consts.block_gas_limit,
+				enter: consts.enter_gas(),
+				update: consts.update_gas(),
+				cancel: consts.cancel_gas(),
+			},
+			false => BlockGas::orders(consts.block_size),
+		}
+	}
+
+	/// A block fitting n orders whatever their type
+	pub fn orders(n: usize) -> BlockGas {
+		BlockGas { limit: n as f64, enter: 1.0, update: 1.0, cancel: 1.0 }
+	}
+
+	/// The gas the order uses up in a block
+	pub fn used(&self, order: &Order) -> f64 {
+		match order.order_type {
+			OrderType::Enter => self.enter,
+			OrderType::Update => self.update,
+			OrderType::Cancel|OrderType::MassCancel => self.cancel,
+		}
+	}
+
+	/// The gas the orders use up together
+	pub fn total(&self, orders: &[Order]) -> f64 {
+		orders.iter().map(|o| self.used(o)).sum()
+	}
+
+	/// Whether the order still fits in a block with used gas already taken
+	pub fn fits(&self, used: f64, order: &Order) -> bool {
+		used + self.used(order) <= self.limit
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{TradeType, ExchangeType};
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_block_gas() {
+		let order = |ot: OrderType| Order::new(format!("INVa"), ot, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 1.0, 1.0, 1.0);
+		let gas = BlockGas { limit: 10.0, enter: 4.0, update: 3.0, cancel: 1.0 };
+		assert_eq!(gas.used(&order(OrderType::Update)), 3.0);
+		assert_eq!(gas.used(&order(OrderType::MassCancel)), 1.0);
+		assert_eq!(gas.total(&[order(OrderType::Enter), order(OrderType::Cancel)]), 5.0);
+		assert!(gas.fits(6.0, &order(OrderType::Enter)));
+		assert!(!gas.fits(7.0, &order(OrderType::Enter)));
+		assert!(gas.fits(9.0, &order(OrderType::Cancel)));
+
+		let orders = BlockGas::orders(2);
+		assert!(orders.fits(1.0, &order(OrderType::Enter)));
+		assert!(!orders.fits(2.0, &order(OrderType::Cancel)));
+
+		// A config setting only the limit uses the default gas per order type
+		let mut consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		consts.block_gas_limit = 6.0;
+		consts.enter_gas_used = 0.0;
+		consts.update_gas_used = 0.0;
+		consts.cancel_gas_used = 0.0;
+		assert_eq!(BlockGas::new(&consts), BlockGas { limit: 6.0, enter: 3.0, update: 2.0, cancel: 1.0 });
+	}
+}
@@ -0,0 +1,78 @@
+use crate::order::order::Order;
+use crate::blockchain::block_gas::BlockGas;
+
+
+/// An ordered set of orders a searcher pays the miner to include together at the front of its
+/// frame. Orders from other traders are copies of orders waiting in the mempool, which the miner
+/// moves into the bundle.
+/// payment: f64 -> paid by the searcher to the miner only if the bundle is included
+#[derive(Debug, Clone)]
+pub struct Bundle {
+	pub searcher_id: String,
+	pub orders: Vec<Order>,
+	pub payment: f64,
+}
+
+impl Bundle {
+	/// The ids of the bundle's orders that belong to other traders
+	pub fn pending_ids(&self) -> Vec<u64> {
+		self.orders.iter()
+			.filter(|o| o.trader_id != self.searcher_id)
+			.map(|o| o.order_id)
+			.collect()
+	}
+}
+
+/// Greedily picks the bundles paying the most whose orders fit in the block's gas limit together and
+/// whose orders from other traders are still pending and not claimed by a better paying bundle.
+/// Returns the (included, dropped) bundles, the included in the order they go in the frame.
+pub fn select_bundles<F: Fn(u64) -> bool>(mut bundles: Vec<Bundle>, gas: &BlockGas, is_pending: F) -> (Vec<Bundle>, Vec<Bundle>) {
+	bundles.sort_by(|a, b| b.payment.partial_cmp(&a.payment).expect("select_bundles"));
+	let mut included = Vec::new();
+	let mut dropped = Vec::new();
+	let mut claimed = Vec::<u64>::new();
+	let mut used = 0.0;
+	for bundle in bundles {
+		let pending = bundle.pending_ids();
+		let available = pending.iter().all(|id| !claimed.contains(id) && is_pending(*id));
+		let bundle_gas = gas.total(&bundle.orders);
+		if available && used + bundle_gas <= gas.limit {
+			used += bundle_gas;
+			claimed.extend(pending);
+			included.push(bundle);
+		} else {
+			dropped.push(bundle);
+		}
+	}
+	(included, dropped)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+
+	#[test]
+	fn test_select_bundles() {
+		let order = |id: &str| Order::new(id.to_string(), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 1.0, 1.0, 0.0);
+		let victim = order("INVa");
+		let gone = order("INVb");
+		let bundle = |searcher: &str, victim: &Order, payment: f64| Bundle {
+			searcher_id: searcher.to_string(),
+			orders: vec![order(searcher), victim.clone(), order(searcher)],
+			payment,
+		};
+		let bundles = vec![bundle("SRCa", &victim, 1.0), bundle("SRCb", &victim, 3.0), bundle("SRCc", &gone, 5.0)];
+
+		// The best payer for the victim wins it, the bundle whose victim already left is dropped
+		let (included, dropped) = select_bundles(bundles.clone(), &BlockGas::orders(10), |id| id == victim.order_id);
+		assert_eq!(included.iter().map(|b| b.searcher_id.as_str()).collect::<Vec<&str>>(), vec!["SRCb"]);
+		assert_eq!(dropped.len(), 2);
+
+		// Bundles that don't fit the block wait out
+		let (included, _dropped) = select_bundles(bundles, &BlockGas::orders(2), |_id| true);
+		assert!(included.is_empty());
+	}
+}
@@ -0,0 +1,104 @@
+use crate::order::order::Order;
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The header and contents of a mined block
+/// timestamp: u64 -> milliseconds since the UNIX epoch when the block was published
+/// gas_used: f64 -> the block gas its orders took up
+/// orphaned: bool -> whether a reorg dropped the block, its orders being mined again later
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Block {
+	pub number: u64,
+	pub timestamp: u64,
+	pub miner_id: String,
+	pub gas_used: f64,
+	pub order_ids: Vec<u64>,
+	pub orphaned: bool,
+}
+
+impl Block {
+	pub fn new(number: u64, timestamp: Duration, miner_id: String, gas_used: f64, orders: &[Order]) -> Block {
+		Block {
+			number,
+			timestamp: timestamp.as_millis() as u64,
+			miner_id,
+			gas_used,
+			order_ids: orders.iter().map(|o| o.order_id).collect(),
+			orphaned: false,
+		}
+	}
+}
+
+/// Every block the miners published, in the order they were published
+pub struct Chain {
+	pub blocks: Mutex<Vec<Block>>,
+}
+
+impl Chain {
+	pub fn new() -> Chain {
+		Chain {
+			blocks: Mutex::new(Vec::new()),
+		}
+	}
+
+	pub fn append(&self, block: Block) {
+		self.blocks.lock().expect("chain append").push(block);
+	}
+
+	/// Marks the latest block with the number as orphaned
+	pub fn orphan(&self, number: u64) {
+		let mut blocks = self.blocks.lock().expect("chain orphan");
+		if let Some(block) = blocks.iter_mut().rev().find(|b| b.number == number) {
+			block.orphaned = true;
+		}
+	}
+
+	pub fn copy_blocks(&self) -> Vec<Block> {
+		self.blocks.lock().expect("chain copy_blocks").clone()
+	}
+
+	/// The latest block that wasn't orphaned
+	pub fn head(&self) -> Option<Block> {
+		self.blocks.lock().expect("chain head").iter().rev().find(|b| !b.orphaned).cloned()
+	}
+
+	/// Finds the block that mined the order, skipping orphaned blocks
+	pub fn block_of(&self, order_id: u64) -> Option<Block> {
+		self.blocks.lock().expect("chain block_of").iter()
+			.find(|b| !b.orphaned && b.order_ids.contains(&order_id))
+			.cloned()
+	}
+
+	pub fn to_json(&self) -> Result<String, serde_json::Error> {
+		serde_json::to_string_pretty(&*self.blocks.lock().expect("chain to_json"))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+
+	#[test]
+	fn test_chain() {
+		let order = Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 0.0, 0.0, 100.0, 1.0, 0.0, 1.0);
+		let chain = Chain::new();
+		chain.append(Block::new(1, Duration::from_millis(1500), format!("MINa"), 3.0, &[order.clone()]));
+		assert_eq!(chain.head().unwrap().timestamp, 1500);
+		assert_eq!(chain.block_of(order.order_id).unwrap().number, 1);
+
+		// An orphaned block's orders are found in the block that mined them again
+		chain.orphan(1);
+		assert!(chain.head().is_none());
+		assert!(chain.block_of(order.order_id).is_none());
+		chain.append(Block::new(2, Duration::from_millis(2500), format!("MINa"), 3.0, &[order.clone()]));
+		assert_eq!(chain.block_of(order.order_id).unwrap().number, 2);
+		assert_eq!(chain.copy_blocks().len(), 2);
+
+		let json = chain.to_json().unwrap();
+		assert!(json.contains("\"orphaned\": true"));
+		assert!(json.contains("\"miner_id\": \"MINa\""));
+	}
+}
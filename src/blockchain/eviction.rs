@@ -0,0 +1,57 @@
+use crate::order::order::Order;
+use crate::blockchain::pool_index::PoolIndex;
+
+use std::sync::Mutex;
+
+/// Which order a full MemPool drops to make room
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub enum EvictionPolicy {
+	LowestGas,	// The order paying the least gas, which may be the one arriving
+	Oldest,		// The order that arrived first
+}
+
+impl Default for EvictionPolicy {
+	fn default() -> EvictionPolicy {
+		EvictionPolicy::LowestGas
+	}
+}
+
+/// Bounds the MemPool, dropping orders by the policy once it's full
+/// capacity: usize -> the most orders the MemPool holds
+/// policy: EvictionPolicy -> which order makes room
+/// evicted: Mutex<Vec<Order>> -> orders evicted since the last take
+pub struct Eviction {
+	pub capacity: usize,
+	pub policy: EvictionPolicy,
+	pub evicted: Mutex<Vec<Order>>,
+}
+
+impl Eviction {
+	pub fn new(capacity: usize, policy: EvictionPolicy) -> Eviction {
+		Eviction {
+			capacity,
+			policy,
+			evicted: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Drops orders from the items until they're within capacity, returning the orders dropped.
+	/// Of the orders paying the least gas the latest to arrive goes first.
+	pub fn evict(&self, items: &mut PoolIndex) -> Vec<Order> {
+		let mut dropped = Vec::new();
+		while items.len() > self.capacity {
+			let key = match self.policy {
+				EvictionPolicy::LowestGas => items.last_key(),
+				EvictionPolicy::Oldest => items.oldest_key(),
+			};
+			dropped.extend(key.and_then(|key| items.remove(&key)));
+		}
+		self.evicted.lock().expect("eviction evicted").extend(dropped.iter().cloned());
+		dropped
+	}
+
+	/// Takes the orders evicted since the last call
+	pub fn take(&self) -> Vec<Order> {
+		self.evicted.lock().expect("eviction evicted").drain(..).collect()
+	}
+}
@@ -0,0 +1,94 @@
+use crate::order::order::Order;
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// The oracle's read of the gas market, percentiles of the gas paid in recent blocks and
+/// offered by the orders waiting in the mempool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GasEstimate {
+	pub low: f64,		// 25th percentile
+	pub median: f64,	// 50th percentile
+	pub high: f64,		// 75th percentile
+	pub suggested: f64,	// The oracle's configured percentile
+}
+
+/// Suggests gas prices from the gas of the orders mined in the last window blocks and the
+/// orders still waiting, so agents can bid what the market is paying.
+/// percentile: f64 -> in [0, 1], the percentile of gas the oracle suggests
+/// blocks: Mutex<VecDeque<Vec<f64>>> -> the gas of each order mined in the recent blocks, oldest first
+pub struct GasOracle {
+	pub window: usize,
+	pub percentile: f64,
+	pub blocks: Mutex<VecDeque<Vec<f64>>>,
+}
+
+impl GasOracle {
+	pub fn new(window: usize, percentile: f64) -> GasOracle {
+		GasOracle {
+			window,
+			percentile,
+			blocks: Mutex::new(VecDeque::new()),
+		}
+	}
+
+	/// Records the gas of a mined block's orders, forgetting the blocks older than the window.
+	/// The miner's own orders pay no gas and are left out.
+	pub fn record_block(&self, orders: &[Order]) {
+		let mut blocks = self.blocks.lock().expect("gas_oracle record_block");
+		blocks.push_back(orders.iter().map(|o| o.gas).filter(|gas| *gas > 0.0).collect());
+		while blocks.len() > self.window {
+			blocks.pop_front();
+		}
+	}
+
+	/// The nearest-rank percentile p in [0, 1] of the sorted gas
+	pub fn percentile_of(sorted: &[f64], p: f64) -> Option<f64> {
+		if sorted.is_empty() {
+			return None;
+		}
+		let rank = (p * (sorted.len() - 1) as f64).round() as usize;
+		Some(sorted[rank.min(sorted.len() - 1)])
+	}
+
+	/// Estimates the gas market from the recent blocks and the pending orders, None until
+	/// there's any gas to go on
+	pub fn estimate(&self, pending: &[Order]) -> Option<GasEstimate> {
+		let mut gas: Vec<f64> = self.blocks.lock().expect("gas_oracle estimate").iter().flatten().cloned().collect();
+		gas.extend(pending.iter().map(|o| o.gas).filter(|gas| *gas > 0.0));
+		gas.sort_by(|a, b| a.partial_cmp(b).expect("gas_oracle sort"));
+		Some(GasEstimate {
+			low: GasOracle::percentile_of(&gas, 0.25)?,
+			median: GasOracle::percentile_of(&gas, 0.5)?,
+			high: GasOracle::percentile_of(&gas, 0.75)?,
+			suggested: GasOracle::percentile_of(&gas, self.percentile)?,
+		})
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+
+	fn orders(gas: &[f64]) -> Vec<Order> {
+		gas.iter().map(|g| Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 0.0, 0.0, 100.0, 1.0, 0.0, *g)).collect()
+	}
+
+	#[test]
+	fn test_gas_oracle() {
+		let oracle = GasOracle::new(2, 0.9);
+		assert!(oracle.estimate(&[]).is_none());
+
+		oracle.record_block(&orders(&[1.0, 2.0, 0.0]));
+		oracle.record_block(&orders(&[3.0, 4.0]));
+		let estimate = oracle.estimate(&orders(&[5.0])).unwrap();
+		assert_eq!((estimate.low, estimate.median, estimate.high, estimate.suggested), (2.0, 3.0, 4.0, 5.0));
+
+		// Only the last window blocks count
+		oracle.record_block(&orders(&[10.0]));
+		let estimate = oracle.estimate(&[]).unwrap();
+		assert_eq!((estimate.low, estimate.median, estimate.high), (4.0, 4.0, 10.0));
+	}
+}
@@ -0,0 +1,80 @@
+use crate::players::TraderT;
+use crate::simulation::simulation_config::{Constants, Distributions, DistReason};
+use crate::utility::{trader_type, scaled_millis};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+
+/// How long after an order reaches the MemPool each observer sees it, as it gossips through
+/// the network. Each type of observer has its own delay in milliseconds, to which each observer
+/// adds a jitter sampled once from GossipJitter, so observers of the same type needn't agree.
+/// Players of other types see orders as soon as they arrive.
+#[derive(Debug)]
+pub struct GossipModel {
+	pub miner_delay: u64,
+	pub maker_delay: u64,
+	pub sniper_delay: u64,
+	pub dists: Distributions,
+	pub jitter: Mutex<HashMap<String, u64>>,
+}
+
+impl GossipModel {
+	pub fn new(consts: &Constants, dists: &Distributions) -> GossipModel {
+		GossipModel {
+			miner_delay: consts.miner_gossip_delay,
+			maker_delay: consts.maker_gossip_delay,
+			sniper_delay: consts.sniper_gossip_delay,
+			dists: dists.clone(),
+			jitter: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// The delay shared by every observer of the type
+	pub fn type_delay(&self, player_type: Option<TraderT>) -> Duration {
+		let millis = match player_type {
+			Some(TraderT::Miner) => self.miner_delay,
+			Some(TraderT::Maker) => self.maker_delay,
+			Some(TraderT::Sniper) => self.sniper_delay,
+			_ => 0,
+		};
+		Duration::from_millis(scaled_millis(millis))
+	}
+
+	/// The delay before observer_id sees an order, its type's delay plus its own jitter
+	pub fn delay(&self, observer_id: &str) -> Duration {
+		let mut jitter = self.jitter.lock().expect("Error locking gossip jitter");
+		let dists = &self.dists;
+		let own = *jitter.entry(observer_id.to_string())
+			.or_insert_with(|| dists.sample_if_set(DistReason::GossipJitter).unwrap_or(0.0).abs() as u64);
+		self.type_delay(trader_type(observer_id)) + Duration::from_millis(scaled_millis(own))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::DistType;
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_gossip_delay() {
+		let mut consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		consts.miner_gossip_delay = 50;
+		consts.maker_gossip_delay = 20;
+		let dists = Distributions::new(vec![(DistReason::GossipJitter, 5.0, 0.0, 1.0, DistType::Normal)]);
+		let gossip = GossipModel::new(&consts, &dists);
+
+		assert_eq!(gossip.type_delay(Some(TraderT::Miner)), Duration::from_millis(50));
+		assert_eq!(gossip.type_delay(Some(TraderT::Investor)), Duration::from_millis(0));
+		assert_eq!(gossip.delay("MINa"), Duration::from_millis(55));
+		assert_eq!(gossip.delay("MKRa"), Duration::from_millis(25));
+		assert_eq!(gossip.delay("SNPa"), Duration::from_millis(5));
+
+		// Without GossipJitter every observer of a type sees orders at the same time
+		let unset = Distributions::new(vec![(DistReason::MakerSpread, 2.0, 0.0, 1.0, DistType::Normal)]);
+		assert_eq!(GossipModel::new(&consts, &unset).delay("MKRb"), Duration::from_millis(20));
+	}
+}
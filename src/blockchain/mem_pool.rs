@@ -1,51 +1,653 @@
-use crate::order::order::Order;
-use std::sync::Mutex;
+use crate::order::order::{Order, OrderType};
+use crate::blockchain::order_processor::LatencyModel;
+use crate::blockchain::bundle::Bundle;
+use crate::blockchain::block_gas::BlockGas;
+use crate::blockchain::eviction::{Eviction, EvictionPolicy};
+use crate::blockchain::pool_index::{PoolIndex, PoolKey};
+use crate::blockchain::gossip::GossipModel;
+use crate::blockchain::regions::Regions;
+use crate::order::commitment::{CommitmentStore, order_hash};
+use crate::blockchain::gas_oracle::{GasOracle, GasEstimate};
+use crate::blockchain::rate_limit::RateLimit;
+use crate::blockchain::mempool_stats::MemPoolStats;
+use crate::blockchain::mempool_events::{MemPoolEvent, MemPoolSubscribers, PoolWatch};
+use crate::simulation::simulation_config::Distributions;
+use crate::utility::{get_time, sim_rng};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use rand::Rng;
 
 
+// How many ids a trader's index holds before it is pruned of the orders already mined
+const INDEX_PRUNE_LEN: usize = 16;
+
 /// A threadsafe FIFO queue to store unprocessed messages arriving from players.
+/// items: Mutex<PoolIndex> -> the waiting orders, ordered by gas as they arrive
+/// latency: Option<LatencyModel> -> delays orders sent through the OrderProcessor, None delivers them at once
+/// bundles: Mutex<Vec<Bundle>> -> bundles sent privately to the miner, kept apart from the public items
+/// private: Mutex<Vec<Order>> -> orders sent privately to the miner, unseen by anyone watching the items
+/// eviction: Option<Eviction> -> the capacity and which orders make room once it's reached, None for no limit
+/// arrivals: Mutex<u64> -> the orders arrived so far, each order's place breaking ties in gas
+/// by_trader: Mutex<HashMap<String, Vec<u64>>> -> the ids each trader sent, which may since have been mined
+/// nonces: Mutex<HashMap<String, (u64, u64)>> -> each trader's next nonce to send and next nonce to deliver
+/// held: Mutex<Vec<Order>> -> orders that arrived ahead of one of the trader's earlier orders
+/// gossip: Option<GossipModel> -> delays when each observer sees an order, None shows it to all at once
+/// seen_at: Mutex<HashMap<u64, Duration>> -> when each waiting order reached the MemPool
+/// regions: Option<Regions> -> the other regions' mempools when the network is partitioned, this being the miner's
+/// commitments: Mutex<Vec<(u64, String)>> -> the (hash, trader_id) commitments posted since the last block
+/// commit_reveal: Option<CommitmentStore> -> the mined commitments and held orders, None unless orders are committed first
+/// leak_prob: Option<f64> -> the probability an order's contents leak when the MemPool is encrypted, None if it isn't
+/// encryption_totals: Mutex<(usize, usize)> -> the orders sent encrypted and those whose contents leaked
+/// gas_oracle: Option<GasOracle> -> suggests gas to the agents from recent blocks and the pending orders
+/// subscribers: MemPoolSubscribers -> the channels told when orders enter, leave or are evicted
+/// known: Mutex<HashSet<u64>> -> the ids of the open orders sent, which cancels and updates must reference
+/// rate_limit: Option<RateLimit> -> caps the orders each trader has waiting and sends each block
 pub struct MemPool {
-    pub items: Mutex<Vec<Order>>,
+    pub items: Mutex<PoolIndex>,
+    pub latency: Option<LatencyModel>,
+    pub bundles: Mutex<Vec<Bundle>>,
+    pub private: Mutex<Vec<Order>>,
+    pub eviction: Option<Eviction>,
+    pub arrivals: Mutex<u64>,
+    pub by_trader: Mutex<HashMap<String, Vec<u64>>>,
+    pub nonces: Mutex<HashMap<String, (u64, u64)>>,
+    pub held: Mutex<Vec<Order>>,
+    pub gossip: Option<GossipModel>,
+    pub seen_at: Mutex<HashMap<u64, Duration>>,
+    pub regions: Option<Regions>,
+    pub commitments: Mutex<Vec<(u64, String)>>,
+    pub commit_reveal: Option<CommitmentStore>,
+    pub leak_prob: Option<f64>,
+    pub encryption_totals: Mutex<(usize, usize)>,
+    pub gas_oracle: Option<GasOracle>,
+    pub subscribers: MemPoolSubscribers,
+    pub known: Mutex<HashSet<u64>>,
+    pub rate_limit: Option<RateLimit>,
 }
 
 impl MemPool {
 	pub fn new() -> MemPool {
 		MemPool {
-			items: Mutex::new(Vec::<Order>::new()),
+			items: Mutex::new(PoolIndex::new()),
+			latency: None,
+			bundles: Mutex::new(Vec::new()),
+			private: Mutex::new(Vec::new()),
+			eviction: None,
+			arrivals: Mutex::new(0),
+			by_trader: Mutex::new(HashMap::new()),
+			nonces: Mutex::new(HashMap::new()),
+			held: Mutex::new(Vec::new()),
+			gossip: None,
+			seen_at: Mutex::new(HashMap::new()),
+			regions: None,
+			commitments: Mutex::new(Vec::new()),
+			commit_reveal: None,
+			leak_prob: None,
+			encryption_totals: Mutex::new((0, 0)),
+			gas_oracle: None,
+			subscribers: MemPoolSubscribers::new(),
+			known: Mutex::new(HashSet::new()),
+			rate_limit: None,
 		}
 	}
 
-	// New orders are pushed to the end of the MemPool
+	pub fn with_latency(mut self, latency: LatencyModel) -> MemPool {
+		self.latency = Some(latency);
+		self
+	}
+
+	pub fn with_gossip(mut self, gossip: GossipModel) -> MemPool {
+		self.gossip = Some(gossip);
+		self
+	}
+
+	/// Partitions the network into num_regions regions, this MemPool being the miner's. 1 or
+	/// fewer leaves a single mempool.
+	pub fn with_regions(mut self, num_regions: usize) -> MemPool {
+		self.regions = if num_regions > 1 { Some(Regions::new(num_regions)) } else { None };
+		self
+	}
+
+	/// Orders are committed to a block before they are revealed
+	pub fn with_commit_reveal(mut self, enabled: bool) -> MemPool {
+		self.commit_reveal = if enabled { Some(CommitmentStore::new()) } else { None };
+		self
+	}
+
+	/// Hides the contents of orders sent to the MemPool until their block is formed, each
+	/// leaking with leak_prob
+	pub fn with_encryption(mut self, enabled: bool, leak_prob: f64) -> MemPool {
+		self.leak_prob = if enabled { Some(leak_prob) } else { None };
+		self
+	}
+
+	/// Suggests gas from the last window blocks and the pending orders. A window of 0 leaves
+	/// it without an oracle.
+	pub fn with_gas_oracle(mut self, window: usize, percentile: f64) -> MemPool {
+		self.gas_oracle = if window > 0 { Some(GasOracle::new(window, percentile)) } else { None };
+		self
+	}
+
+	/// Limits the orders each trader may have waiting and send each block, orders over the limits
+	/// are rejected or surcharged by the escalation. Without either limit there's no rate limit.
+	pub fn with_rate_limit(mut self, max_pending: usize, max_per_block: usize, escalation: f64) -> MemPool {
+		self.rate_limit = if max_pending > 0 || max_per_block > 0 { Some(RateLimit::new(max_pending, max_per_block, escalation)) } else { None };
+		self
+	}
+
+	/// Holds at most capacity orders, evicting by the policy once full. 0 leaves it unbounded.
+	pub fn with_capacity(mut self, capacity: usize, eviction: EvictionPolicy) -> MemPool {
+		self.eviction = if capacity > 0 { Some(Eviction::new(capacity, eviction)) } else { None };
+		self
+	}
+
+	// The nonce of the trader's next order sent to the MemPool, counting from 1
+	pub fn next_nonce(&self, trader_id: &str) -> u64 {
+		let mut nonces = self.nonces.lock().expect("Error locking Mempool nonces");
+		let next = nonces.entry(trader_id.to_string()).or_insert((1, 1));
+		next.0 += 1;
+		next.0 - 1
+	}
+
+	// New orders are inserted in gas order, keeping each trader's nonce order. An order
+	// arriving ahead of the trader's earlier ones is held until they arrive. Orders without a
+	// nonce and orders put back after already arriving go straight in.
 	pub fn add(&self, order: Order) {
+		for order in self.release(order) {
+			self.push(order);
+		}
+	}
+
+	// Adds a batch of orders taking the MemPool's lock once
+	pub fn add_batch(&self, orders: Vec<Order>) {
+		let ready: Vec<Order> = orders.into_iter().flat_map(|order| self.release(order)).collect();
+		if ready.is_empty() {
+			return;
+		}
+		let mut items = self.items.lock().expect("Error locking Mempool");
+		for order in ready {
+			self.push_locked(&mut items, order);
+		}
+	}
+
+	// Puts back the waiting and held orders and the traders' nonces a checkpoint recorded, keeping
+	// the orders' arrivals so the ties between them break as they did
+	pub fn restore(&self, orders: Vec<Order>, held: Vec<Order>, nonces: HashMap<String, (u64, u64)>) {
+		*self.nonces.lock().expect("Error locking Mempool nonces") = nonces;
+		*self.held.lock().expect("Error locking Mempool held") = held;
+		{
+			let mut arrivals = self.arrivals.lock().expect("Error locking Mempool arrivals");
+			*arrivals = orders.iter().map(|o| o.arrival).max().unwrap_or(0).max(*arrivals);
+		}
+		let mut items = self.items.lock().expect("Error locking Mempool");
+		for order in orders {
+			self.push_locked(&mut items, order);
+		}
+	}
+
+	// The orders ready to go in once the order arrives, none if it's held for the trader's earlier ones
+	fn release(&self, order: Order) -> Vec<Order> {
+		if order.nonce == 0 {
+			return vec![order];
+		}
+		let mut nonces = self.nonces.lock().expect("Error locking Mempool nonces");
+		let mut held = self.held.lock().expect("Error locking Mempool held");
+		let next = nonces.entry(order.trader_id.clone()).or_insert((1, 1));
+		if order.nonce < next.1 {
+			return vec![order];
+		}
+		let trader_id = order.trader_id.clone();
+		held.push(order);
+		let mut ready = Vec::new();
+		while let Some(i) = held.iter().position(|o| o.trader_id == trader_id && o.nonce == next.1) {
+			ready.push(held.remove(i));
+			next.1 += 1;
+		}
+		ready
+	}
+
+	// Adds an order synced from another region, which was already held there until the
+	// trader's earlier orders arrived
+	pub fn add_synced(&self, order: Order) {
+		if !self.contains(order.order_id) {
+			self.push(order);
+		}
+	}
+
+	// Inserts the order in gas order, evicting one if the MemPool is over capacity.
+	// Orders put back keep their original arrival.
+	fn push(&self, order: Order) {
         let mut items = self.items.lock().expect("Error locking Mempool");
-        items.push(order);
+        self.push_locked(&mut items, order);
 	}
 
-	pub fn pop(&self) -> Option<Order> {
+	// Pushes the order with the items already locked
+	fn push_locked(&self, items: &mut PoolIndex, mut order: Order) {
+        if order.arrival == 0 {
+            order.arrival = self.next_arrival();
+        }
+        self.know(order.order_id);
+        self.index(items, &order);
+        self.stamp(items, &order);
+        if !self.subscribers.is_empty() {
+            self.subscribers.notify(MemPoolEvent::Enter(order.clone()));
+        }
+        self.insert_by_gas(items, order);
+        let eviction = match &self.eviction {
+            Some(eviction) => eviction,
+            None => return,
+        };
+
+        for evicted in eviction.evict(items) {
+            // An evicted Enter never reached the exchange, it's resent under a new id
+            if evicted.order_type == OrderType::Enter {
+                self.forget(evicted.order_id);
+            }
+            self.subscribers.notify(MemPoolEvent::Evict(evicted.order_id, evicted.nonce));
+        }
+	}
+
+	// Counts the order's arrival
+	fn next_arrival(&self) -> u64 {
+		let mut arrivals = self.arrivals.lock().expect("Error locking Mempool arrivals");
+		*arrivals += 1;
+		*arrivals
+	}
+
+	// Adds the order to its trader's index, forgetting the trader's orders that have left
+	// the MemPool once the index grows past INDEX_PRUNE_LEN
+	fn index(&self, items: &PoolIndex, order: &Order) {
+		let mut by_trader = self.by_trader.lock().expect("Error locking Mempool index");
+		let ids = by_trader.entry(order.trader_id.clone()).or_insert(Vec::new());
+		if !ids.contains(&order.order_id) {
+			ids.push(order.order_id);
+		}
+		if ids.len() > INDEX_PRUNE_LEN {
+			ids.retain(|id| items.contains_id(*id));
+		}
+	}
+
+	// The places of the trader's waiting orders that have nonces, found through the trader's index
+	fn nonced_keys_of(&self, items: &PoolIndex, trader_id: &str) -> Vec<(PoolKey, u64)> {
+		let by_trader = self.by_trader.lock().expect("Error locking Mempool index");
+		let ids = match by_trader.get(trader_id) {
+			Some(ids) => ids,
+			None => return Vec::new(),
+		};
+		ids.iter()
+			.flat_map(|id| items.keys_of(*id).iter())
+			.filter_map(|key| items.get(key).filter(|o| o.nonce > 0 && o.trader_id == trader_id).map(|o| (*key, o.nonce)))
+			.collect()
+	}
+
+	// Records when the order reached the MemPool, keeping the first time for orders put back,
+	// and forgets the orders that have left once there are many more times than orders
+	fn stamp(&self, items: &PoolIndex, order: &Order) {
+		let mut seen_at = self.seen_at.lock().expect("Error locking Mempool seen_at");
+		seen_at.entry(order.order_id).or_insert(get_time());
+		if seen_at.len() > 2 * items.len() + INDEX_PRUNE_LEN {
+			seen_at.retain(|id, _| items.contains_id(*id) || *id == order.order_id);
+		}
+	}
+
+	// Whether an order sent now is hidden, counting the orders encrypted and leaked
+	pub fn encrypts(&self) -> bool {
+		let leak_prob = match self.leak_prob {
+			Some(leak_prob) => leak_prob,
+			None => return false,
+		};
+		let mut totals = self.encryption_totals.lock().expect("Error locking Mempool encryption_totals");
+		match Distributions::do_with_prob(leak_prob) {
+			true => {
+				totals.1 += 1;
+				false
+			},
+			false => {
+				totals.0 += 1;
+				true
+			},
+		}
+	}
+
+	// The gas oracle's estimate from the recent blocks and the orders waiting now
+	pub fn gas_estimate(&self) -> Option<GasEstimate> {
+		match &self.gas_oracle {
+			Some(oracle) => oracle.estimate(&self.snapshot()),
+			None => None,
+		}
+	}
+
+	// Summarizes the waiting orders
+	pub fn stats(&self) -> MemPoolStats {
+		let items = self.snapshot();
+		let seen_at = self.seen_at.lock().expect("Error locking Mempool seen_at");
+		MemPoolStats::new(&items, &seen_at, get_time())
+	}
+
+	// How long the observer waits to see an order after it arrives
+	pub fn gossip_delay(&self, observer_id: &str) -> Duration {
+		match &self.gossip {
+			Some(gossip) => gossip.delay(observer_id),
+			None => Duration::from_millis(0),
+		}
+	}
+
+	// Whether an order that reached the MemPool at arrived has gossiped to an observer delay behind
+	fn visible(arrived: Option<&Duration>, delay: Duration, now: Duration) -> bool {
+		delay == Duration::from_millis(0) || arrived.map_or(true, |at| *at + delay <= now)
+	}
+
+	// The orders waiting in the MemPool that an observer delay behind has seen and can read.
+	// Without a delay or encryption it's the shared snapshot.
+	pub fn view(&self, delay: Duration) -> Arc<Vec<Order>> {
+		if delay == Duration::from_millis(0) && self.leak_prob.is_none() {
+			return self.snapshot();
+		}
+		let items = self.items.lock().expect("Error locking Mempool");
+		let seen_at = self.seen_at.lock().expect("Error locking Mempool seen_at");
+		let now = get_time();
+		Arc::new(items.iter().filter(|o| !o.encrypted && MemPool::visible(seen_at.get(&o.order_id), delay, now)).cloned().collect())
+	}
+
+	// Whether an observer delay behind has seen the order
+	pub fn has_seen(&self, order: &Order, delay: Duration) -> bool {
+		let seen_at = self.seen_at.lock().expect("Error locking Mempool seen_at");
+		MemPool::visible(seen_at.get(&order.order_id), delay, get_time())
+	}
+
+	// The orders the observer has seen so far
+	pub fn view_for(&self, observer_id: &str) -> Arc<Vec<Order>> {
+		let delay = self.gossip_delay(observer_id);
+		self.pool_for(observer_id).1.view(delay)
+	}
+
+	// The observer's region and the pool it reads, this one unless it is split into regions
+	pub fn pool_for(&self, observer_id: &str) -> (usize, &MemPool) {
+		if let Some(regions) = &self.regions {
+			let region = regions.region_of(observer_id);
+			if let Some(pool) = regions.pool(region) {
+				return (region, pool);
+			}
+		}
+		(0, self)
+	}
+
+	// The trader's orders still waiting in the MemPool, in the order they'll be mined
+	pub fn pending_of(&self, trader_id: &str) -> Vec<Order> {
+		let items = self.items.lock().expect("Error locking Mempool");
+		let by_trader = self.by_trader.lock().expect("Error locking Mempool index");
+		let mut keys: Vec<PoolKey> = match by_trader.get(trader_id) {
+			Some(ids) => ids.iter().flat_map(|id| items.keys_of(*id).iter().cloned()).collect(),
+			None => return Vec::new(),
+		};
+		keys.sort();
+		keys.dedup();
+		keys.iter().filter_map(|key| items.get(key)).filter(|o| o.trader_id == trader_id).cloned().collect()
+	}
+
+	// Replace-by-fee: swaps the trader's pending order of the same id and type for the
+	// replacement in one step, keeping its place in the MemPool. The replacement must pay
+	// more gas, and the order must not have been mined yet. Returns the replaced order.
+	pub fn replace(&self, mut replacement: Order) -> Result<Order, &'static str> {
 		let mut items = self.items.lock().expect("Error locking Mempool");
-		items.pop()
+		let sent = {
+			let by_trader = self.by_trader.lock().expect("Error locking Mempool index");
+			by_trader.get(&replacement.trader_id).map_or(false, |ids| ids.contains(&replacement.order_id))
+		};
+		if !sent {
+			return Err("No pending order from the trader to replace");
+		}
+		let key = items.keys_of(replacement.order_id).iter()
+			.find(|key| items.get(key).map_or(false, |o| o.trader_id == replacement.trader_id && o.order_type == replacement.order_type))
+			.cloned()
+			.ok_or("The order to replace has already left the MemPool")?;
+		if replacement.gas <= items.get(&key).expect("Mempool replace").gas {
+			return Err("A replacement must pay more gas than the order it replaces");
+		}
+		// The replacement moves up to where its higher gas puts it
+		let replaced = items.remove(&key).expect("Mempool replace");
+		replacement.arrival = replaced.arrival;
+		if !self.subscribers.is_empty() {
+			self.subscribers.notify(MemPoolEvent::leave(&replaced));
+			self.subscribers.notify(MemPoolEvent::Enter(replacement.clone()));
+		}
+		self.insert_by_gas(&mut items, replacement);
+		Ok(replaced)
+	}
+
+	// Inserts the order behind every order paying more gas or paying as much and arriving
+	// earlier, so the MemPool stays ordered by gas then arrival without re-sorting it each block.
+	// A trader's orders keep their nonce order, so one can't go ahead of the trader's earlier
+	// orders or behind their later ones, and is placed beside them instead. The trader's orders
+	// are found through the trader's index rather than by scanning the MemPool.
+	fn insert_by_gas(&self, items: &mut PoolIndex, order: Order) {
+		let mut key = items.key_of(&order);
+		if order.nonce > 0 {
+			let theirs = self.nonced_keys_of(items, &order.trader_id);
+			if let Some(earlier) = theirs.iter().filter(|(k, nonce)| *nonce < order.nonce && *k > key).map(|(k, _)| *k).max() {
+				key = items.key_beside(&earlier, &order);
+			}
+			if let Some(later) = theirs.iter().filter(|(k, nonce)| *nonce > order.nonce && *k < key).map(|(k, _)| *k).min() {
+				key = items.key_beside(&later, &order);
+			}
+		}
+		items.insert(key, order);
+	}
+
+	// Takes the orders evicted since the last call, for their players to resubmit
+	pub fn take_evicted(&self) -> Vec<Order> {
+		match &self.eviction {
+			Some(eviction) => eviction.take(),
+			None => Vec::new(),
+		}
 	}
 
-	pub fn sort_by_gas(&self) {
+	pub fn pop(&self) -> Option<Order> {
 		let mut items = self.items.lock().expect("Error locking Mempool");
-		// Sort in descending gas order
-		items.sort_by(|a, b| a.gas.partial_cmp(&b.gas).unwrap().reverse());
+		let popped = items.last_key().and_then(|key| items.remove(&key));
+		self.notify_left(popped.iter());
+		popped
 	}
 
-	// Empties the MemPool into a vector of Orders. Drain() pops the items
-	// out in the order of arrival, so once iterated upon, orders will be 
-	// processed first -> last.
+	// The index keeps the orders in gas order as they arrive, so there's nothing left to sort
+	pub fn sort_by_gas(&self) {}
+
+	// Empties the MemPool into a vector of Orders, highest gas first
 	pub fn pop_all(&self) -> Vec<Order> {
 		// Acquire the lock
 		let mut items = self.items.lock().expect("Error locking Mempool");
 		// Pop all items out of the queue and return the contents as a vec
-		items.drain(..).collect()
+		let popped = items.take_all();
+		self.notify_left(popped.iter());
+		popped
 	}
 
 	pub fn pop_n(&self, n: usize) -> Vec<Order> {
 		// Acquire the lock
 		let mut items = self.items.lock().expect("Error locking Mempool");
-		// Pop all items out of the queue and return the contents as a vec
-		items.drain(0..n).collect()
+		// Pop the first n items out of the queue, each in O(log n)
+		let popped: Vec<Order> = (0..n).filter_map(|_| items.first_key().and_then(|key| items.remove(&key))).collect();
+		self.notify_left(popped.iter());
+		popped
+	}
+
+	// Pops orders in pool order while they fit in the block's gas limit, skipping over any
+	// too large for the gas left, which stay in the MemPool along with their trader's later orders.
+	// Only the orders a miner delay behind has seen are taken.
+	pub fn pop_to_gas(&self, gas: &BlockGas, delay: Duration) -> Vec<Order> {
+		self.pop_fitting(gas, delay, |_o| true)
+	}
+
+	// Pops the Cancel and MassCancel orders that fit in the block's gas limit, leaving all
+	// other orders in the MemPool
+	pub fn pop_cancels(&self, gas: &BlockGas, delay: Duration) -> Vec<Order> {
+		self.pop_fitting(gas, delay, |o| match o.order_type {
+			OrderType::Cancel | OrderType::MassCancel => true,
+			_ => false,
+		})
+	}
+
+	fn pop_fitting<F: Fn(&Order) -> bool>(&self, gas: &BlockGas, delay: Duration, take: F) -> Vec<Order> {
+		let mut items = self.items.lock().expect("Error locking Mempool");
+		let seen_at = self.seen_at.lock().expect("Error locking Mempool seen_at");
+		let now = get_time();
+		let take = |o: &Order| take(o) && MemPool::visible(seen_at.get(&o.order_id), delay, now);
+		let mut taken = Vec::new();
+		let mut used = 0.0;
+		let mut blocked = HashSet::<String>::new();
+		for (key, order) in items.entries() {
+			if order.nonce > 0 && blocked.contains(&order.trader_id) {
+				continue;
+			} else if take(order) && gas.fits(used, order) {
+				used += gas.used(order);
+				taken.push(*key);
+			} else if order.nonce > 0 {
+				// The trader's later orders wait for this one
+				blocked.insert(order.trader_id.clone());
+			}
+		}
+		let popped: Vec<Order> = taken.iter().filter_map(|key| items.remove(key)).collect();
+		self.notify_left(popped.iter());
+		popped
+	}
+
+	// Removes the orders with the given ids, wherever they are in the MemPool
+	pub fn remove_ids(&self, ids: &[u64]) {
+		let mut items = self.items.lock().expect("Error locking Mempool");
+		let keys: Vec<PoolKey> = ids.iter().flat_map(|id| items.keys_of(*id).iter().cloned()).collect();
+		let removed: Vec<Order> = keys.iter().filter_map(|key| items.remove(key)).collect();
+		self.notify_left(removed.iter());
+	}
+
+	// Tells the subscribers the orders have left the MemPool
+	fn notify_left<'a, I: Iterator<Item = &'a Order>>(&self, orders: I) {
+		if self.subscribers.is_empty() {
+			return;
+		}
+		for order in orders {
+			self.subscribers.notify(MemPoolEvent::leave(order));
+		}
+	}
+
+	// Remembers an order was sent, so it can be cancelled or updated later
+	pub fn know(&self, order_id: u64) {
+		self.known.lock().expect("Error locking Mempool known").insert(order_id);
+	}
+
+	pub fn knows(&self, order_id: u64) -> bool {
+		self.known.lock().expect("Error locking Mempool known").contains(&order_id)
+	}
+
+	// Forgets an order that can no longer be cancelled or updated
+	pub fn forget(&self, order_id: u64) {
+		self.known.lock().expect("Error locking Mempool known").remove(&order_id);
+	}
+
+	// Forgets the orders that were mined and have since filled or been cancelled, keeping the
+	// open ones and those still waiting, once there are many more known orders than num_open.
+	// The open orders are only listed when pruning, without holding the MemPool's locks, and
+	// orders sent while they're listed are kept.
+	pub fn prune_known<F: FnOnce() -> HashSet<u64>>(&self, num_open: usize, open: F) {
+		let num_waiting = self.length() + self.held.lock().expect("Error locking Mempool held").len();
+		let listed: Vec<u64> = {
+			let known = self.known.lock().expect("Error locking Mempool known");
+			if known.len() <= 2 * (num_open + num_waiting) + INDEX_PRUNE_LEN {
+				return;
+			}
+			known.iter().cloned().collect()
+		};
+		let open = open();
+		let waiting: HashSet<u64> = {
+			let items = self.items.lock().expect("Error locking Mempool");
+			let held = self.held.lock().expect("Error locking Mempool held");
+			items.iter().chain(held.iter()).map(|o| o.order_id).collect()
+		};
+		let mut known = self.known.lock().expect("Error locking Mempool known");
+		for id in listed.iter().filter(|id| !open.contains(id) && !waiting.contains(id)) {
+			known.remove(id);
+		}
+	}
+
+	// Subscribes to the orders entering and leaving the MemPool from now on
+	pub fn subscribe(&self) -> PoolWatch {
+		PoolWatch::new(self.subscribers.subscribe())
+	}
+
+	// One watch per region's pool, indexed as pool_for's region
+	pub fn subscribe_regions(&self) -> Vec<PoolWatch> {
+		match &self.regions {
+			Some(regions) => (0..regions.num_regions()).filter_map(|r| regions.pool(r)).map(|pool| pool.subscribe()).collect(),
+			None => vec![self.subscribe()],
+		}
+	}
+
+	// Whether the order is still waiting in the MemPool
+	pub fn contains(&self, order_id: u64) -> bool {
+		let items = self.items.lock().expect("Error locking Mempool");
+		items.contains_id(order_id)
+	}
+
+	// Sends a bundle to the miner
+	pub fn submit_bundle(&self, bundle: Bundle) {
+		for order in bundle.orders.iter() {
+			self.know(order.order_id);
+		}
+		let mut bundles = self.bundles.lock().expect("Error locking Mempool bundles");
+		bundles.push(bundle);
+	}
+
+	// Takes every bundle submitted since the last block
+	pub fn take_bundles(&self) -> Vec<Bundle> {
+		let mut bundles = self.bundles.lock().expect("Error locking Mempool bundles");
+		bundles.drain(..).collect()
+	}
+
+	// Posts a commitment to an order for the miner to mine, which reveals nothing about it
+	pub fn post_commitment(&self, hash: u64, trader_id: String) {
+		let mut commitments = self.commitments.lock().expect("Error locking Mempool commitments");
+		commitments.push((hash, trader_id));
+	}
+
+	// Under commit-reveal, commits to an order sent uncommitted and holds it until the
+	// commitment is mined. Returns the order to send on if it's a reveal or orders aren't committed.
+	pub fn commit(&self, order: Order) -> Option<Order> {
+		let store = match &self.commit_reveal {
+			Some(store) if order.salt == 0 => store,
+			_ => return Some(order),
+		};
+		let order = order.with_salt(sim_rng().gen_range(1, u64::max_value()));
+		let hash = order_hash(&order, order.salt);
+		self.know(order.order_id);
+		self.post_commitment(hash, order.trader_id.clone());
+		store.hold(hash, order);
+		None
+	}
+
+	// Takes every commitment posted since the last block
+	pub fn take_commitments(&self) -> Vec<(u64, String)> {
+		let mut commitments = self.commitments.lock().expect("Error locking Mempool commitments");
+		commitments.drain(..).collect()
+	}
+
+	// Sends an order straight to the miner, skipping the public MemPool
+	pub fn submit_private(&self, order: Order) {
+		self.know(order.order_id);
+		let mut private = self.private.lock().expect("Error locking Mempool private");
+		private.push(order);
+	}
+
+	// Takes every private order in the order they were sent
+	pub fn take_private(&self) -> Vec<Order> {
+		let mut private = self.private.lock().expect("Error locking Mempool private");
+		private.drain(..).collect()
+	}
+
+	// The orders waiting in the MemPool, as agents watching it see them. The orders are listed
+	// once after they change and the list shared with every reader until they change again.
+	pub fn snapshot(&self) -> Arc<Vec<Order>> {
+		let mut items = self.items.lock().expect("Error locking Mempool");
+		items.snapshot()
 	}
 
 	pub fn length(&self) -> usize {
@@ -0,0 +1,83 @@
+use crate::order::order::Order;
+
+use std::collections::HashMap;
+use std::collections::hash_map::Values;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Sender, Receiver};
+
+/// A change to the orders waiting in the MemPool. Cancels and updates share their order's id,
+/// so orders that leave are identified by their (order_id, nonce).
+#[derive(Debug, Clone)]
+pub enum MemPoolEvent {
+	Enter(Order),		// The order arrived, or replaced the waiting order with its id and nonce
+	Leave(u64, u64),	// The order was mined or removed
+	Evict(u64, u64),	// The order was evicted from the full MemPool
+}
+
+impl MemPoolEvent {
+	pub fn leave(order: &Order) -> MemPoolEvent {
+		MemPoolEvent::Leave(order.order_id, order.nonce)
+	}
+}
+
+/// The channels subscribed to the MemPool's events
+pub struct MemPoolSubscribers {
+	pub channels: Mutex<Vec<Sender<MemPoolEvent>>>,
+}
+
+impl MemPoolSubscribers {
+	pub fn new() -> MemPoolSubscribers {
+		MemPoolSubscribers {
+			channels: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Returns a receiver that gets every event until it is dropped
+	pub fn subscribe(&self) -> Receiver<MemPoolEvent> {
+		let (tx, rx) = channel();
+		self.channels.lock().unwrap().push(tx);
+		rx
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.channels.lock().unwrap().is_empty()
+	}
+
+	/// Sends the event to every channel, channels whose receiver was dropped are removed
+	pub fn notify(&self, event: MemPoolEvent) {
+		self.channels.lock().unwrap().retain(|tx| tx.send(event.clone()).is_ok());
+	}
+}
+
+/// A subscriber's own copy of the orders waiting in the MemPool, kept up to date from its
+/// events rather than by cloning the pool
+pub struct PoolWatch {
+	pub events: Receiver<MemPoolEvent>,
+	pub pending: HashMap<(u64, u64), Order>,
+}
+
+impl PoolWatch {
+	pub fn new(events: Receiver<MemPoolEvent>) -> PoolWatch {
+		PoolWatch {
+			events,
+			pending: HashMap::new(),
+		}
+	}
+
+	/// Applies the events received since the last update, returning how many there were
+	pub fn update(&mut self) -> usize {
+		let mut received = 0;
+		while let Ok(event) = self.events.try_recv() {
+			match event {
+				MemPoolEvent::Enter(order) => { self.pending.insert((order.order_id, order.nonce), order); },
+				MemPoolEvent::Leave(id, nonce) | MemPoolEvent::Evict(id, nonce) => { self.pending.remove(&(id, nonce)); },
+			}
+			received += 1;
+		}
+		received
+	}
+
+	pub fn pending(&self) -> Values<(u64, u64), Order> {
+		self.pending.values()
+	}
+}
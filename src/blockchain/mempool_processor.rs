@@ -1,8 +1,9 @@
 use crate::order::order::{Order, OrderType, TradeType};
+use crate::order::commitment::CommitmentStore;
 use crate::blockchain::mem_pool::MemPool;
 use crate::order::order_book::Book;
 use crate::controller::{Task, State};
-use crate::exchange::exchange_logic::{Auction, TradeResults, PlayerUpdate};
+use crate::exchange::exchange_logic::{Auction, TradeResults, PlayerUpdate, OrderEvent};
 use crate::exchange::MarketType;	
 
 use std::thread;
@@ -32,12 +33,39 @@ impl MemPoolProcessor {
 				OrderType::Enter => MemPoolProcessor::conc_process_enter(Arc::clone(&bids), Arc::clone(&asks), order, m_t),
 				OrderType::Update => MemPoolProcessor::conc_process_update(Arc::clone(&bids), Arc::clone(&asks), order, m_t),
 				OrderType::Cancel => MemPoolProcessor::conc_process_cancel(Arc::clone(&bids), Arc::clone(&asks), order, m_t),
+				OrderType::MassCancel => MemPoolProcessor::conc_process_mass_cancel(Arc::clone(&bids), Arc::clone(&asks), order, m_t),
 			};
 			handles.push(handle);
 		}
 		handles
 	}
 
+	// Checks the revealed orders in the frame against their commitments. The valid reveals are
+	// moved to the front of the frame in the order they were committed, so orders the miner
+	// added after reading them can't get ahead. Returns the reveals that didn't match.
+	pub fn order_reveals(frame: &mut Vec<Order>, store: &CommitmentStore, block: u64) -> Vec<Order> {
+		let mut reveals = Vec::new();
+		let mut rest = Vec::new();
+		let mut rejected = Vec::new();
+		for order in frame.drain(..) {
+			if order.salt == 0 {
+				rest.push(order);
+				continue;
+			}
+			match store.validate(&order, block) {
+				Ok(seq) => reveals.push((seq, order)),
+				Err(e) => {
+					println!("Rejecting revealed order {}: {}", order.order_id, e);
+					rejected.push(order);
+				},
+			}
+		}
+		reveals.sort_by_key(|(seq, _)| *seq);
+		frame.extend(reveals.into_iter().map(|(_, order)| order));
+		frame.extend(rest);
+		rejected
+	}
+
 	// Sequentially process orders in the frame. Each order is
 	// either of OrderType::{Enter, Update, Cancel}. Each order will
 	// modify the state of either the Bids or Asks Book, but must
@@ -59,6 +87,11 @@ impl MemPoolProcessor {
 						results.push(result);
 					}
 				}
+				OrderType::MassCancel => {
+					if let Some(result) = MemPoolProcessor::seq_process_mass_cancel(Arc::clone(&bids), Arc::clone(&asks), order, _m_t.clone()) {
+						results.push(result);
+					}
+				}
 			};
 		}
 		if results.len() == 0 {
@@ -72,8 +105,8 @@ impl MemPoolProcessor {
 	fn seq_process_enter(bids: Arc<Book>, asks: Arc<Book>, order: Order, m_t: MarketType) -> Option<TradeResults> {
 		// Spawn a new thread to process the order
     	match m_t {
-    		MarketType::FBA|MarketType::KLF => {
-				// KLF and FBA are processed the same way by the order book
+    		MarketType::FBA|MarketType::KLF|MarketType::SealedBid|MarketType::Dutch => {
+				// KLF, FBA, SealedBid and Dutch are processed the same way by the order book
 				match order.trade_type {
 					TradeType::Ask => {
 						asks.add_order(order).expect("Failed to add order");
@@ -117,6 +150,10 @@ impl MemPoolProcessor {
 						}
 					}
 				}
+			},
+			MarketType::AMM => {
+				// AMM orders are swapped against the pool and never rest in the books
+				println!("AMM order {} sent to the order books", order.order_id);
 			}
     	}
     	None
@@ -178,14 +215,14 @@ impl MemPoolProcessor {
 		let trader_id = order.trader_id.clone();
 		let order_id = order.order_id;
 
-		// If the cancel fails bubble error up.
-		match book.cancel_order(order) {
-    		Ok(()) => {},
+		// If the cancel fails bubble a rejection up to the trader
+		let rejection = match book.cancel_order(order.clone()) {
+    		Ok(()) => None,
     		Err(e) => {
     			println!("ERROR: {}", e);
-    			// TODO send an error response over TCP
+    			Some(OrderEvent::reject(&order, e.reason(), 0))
     		}
-    	}
+    	};
     	
     	// Once cancelled in order book, cancel in the clearing house 
     	// Store a PlayerUpdate with Cancel set to true, in vec form for TradeResults compatibility
@@ -201,7 +238,43 @@ impl MemPoolProcessor {
 
 
     	// make TradeResult for compatible return type with seq_process_enter
-    	Some(TradeResults::new(m_t, None, 0.0, 0.0, Some(updates)))
+    	let results = TradeResults::new(m_t, None, 0.0, 0.0, Some(updates));
+    	match rejection {
+    		Some(event) => Some(results.with_order_event(event)),
+    		None => Some(results),
+    	}
+	}
+
+	// Cancels all of the trader's orders on the side of the MassCancel order in one operation
+	fn seq_process_mass_cancel(bids: Arc<Book>, asks: Arc<Book>, order: Order, m_t: MarketType) -> Option<TradeResults> {
+		// select bids or asks book 
+		let book = match order.trade_type {
+			TradeType::Ask => asks,
+			TradeType::Bid => bids,
+		};
+
+		let trader_id = order.trader_id.clone();
+		let cancelled = book.cancel_all_for_trader(&trader_id, order.trade_type.clone());
+		if cancelled.len() == 0 {
+			println!("ERROR: no orders found to mass cancel for {}", trader_id);
+			let event = OrderEvent::reject(&order, "No orders found to mass cancel", 0);
+			return Some(TradeResults::new(m_t, None, 0.0, 0.0, None).with_order_event(event));
+		}
+
+		// Store a cancel PlayerUpdate per removed order so the clearing house drops each one
+		let updates = cancelled.into_iter().map(|o_id| {
+			PlayerUpdate::new(
+				trader_id.clone(),
+				trader_id.clone(),
+				o_id,
+				o_id,
+				-9.99,
+				-9.99,
+				true       // Cancel = true 
+			)
+		}).collect();
+
+		Some(TradeResults::new(m_t, None, 0.0, 0.0, Some(updates)))
 	}
 
 	// Checks if the new order crosses. Modifies orders in book then calculates new max price
@@ -209,7 +282,7 @@ impl MemPoolProcessor {
 		// Spawn a new thread to process the order
 	    thread::spawn(move || {
 	    	match m_t {
-	    		MarketType::FBA|MarketType::KLF => {
+	    		MarketType::FBA|MarketType::KLF|MarketType::SealedBid|MarketType::Dutch => {
     				// KLF and FBA are processed the same way by the order book
 					match order.trade_type {
 						TradeType::Ask => {
@@ -244,6 +317,10 @@ impl MemPoolProcessor {
 							}
 						}
 					}
+    			},
+    			MarketType::AMM => {
+    				// AMM orders are swapped against the pool and never rest in the books
+    				println!("AMM order {} sent to the order books", order.order_id);
     			}
 	    	}
 			
@@ -315,6 +392,17 @@ impl MemPoolProcessor {
 	    })
 	}
 
+	// Cancels all of the trader's orders on one side of the book
+	fn conc_process_mass_cancel(bids: Arc<Book>, asks: Arc<Book>, order: Order, _m_t: MarketType) -> JoinHandle<()> {
+	    thread::spawn(move || {
+			let book = match order.trade_type {
+				TradeType::Ask => asks,
+				TradeType::Bid => bids,
+			};
+			book.cancel_all_for_trader(&order.trader_id, order.trade_type.clone());
+	    })
+	}
+
 	pub fn async_queue_task(queue: Arc<MemPool>, 
 							bids: Arc<Book>, 
 							asks: Arc<Book>, 
@@ -0,0 +1,73 @@
+use crate::order::order::{Order, OrderType};
+use crate::blockchain::gas_oracle::GasOracle;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A summary of the orders waiting in the MemPool, taken under its lock without copying it.
+/// The gas percentiles are 0 while it's empty.
+/// oldest_age: Duration -> how long the longest waiting order has been in the MemPool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemPoolStats {
+	pub depth: usize,
+	pub gas_low: f64,		// 25th percentile
+	pub gas_median: f64,	// 50th percentile
+	pub gas_high: f64,		// 75th percentile
+	pub oldest_age: Duration,
+	pub enters: usize,
+	pub updates: usize,
+	pub cancels: usize,
+	pub mass_cancels: usize,
+}
+
+impl MemPoolStats {
+	/// Summarizes the waiting orders, given when each reached the MemPool
+	pub fn new(items: &[Order], seen_at: &HashMap<u64, Duration>, now: Duration) -> MemPoolStats {
+		let mut gas: Vec<f64> = items.iter().map(|o| o.gas).collect();
+		gas.sort_by(|a, b| a.partial_cmp(b).expect("mempool_stats sort"));
+		let percentile = |p: f64| GasOracle::percentile_of(&gas, p).unwrap_or(0.0);
+		let count = |order_type: OrderType| items.iter().filter(|o| o.order_type == order_type).count();
+		let oldest_age = items.iter()
+			.filter_map(|o| seen_at.get(&o.order_id))
+			.min()
+			.map_or(Duration::from_millis(0), |arrived| now.checked_sub(*arrived).unwrap_or(Duration::from_millis(0)));
+		MemPoolStats {
+			depth: items.len(),
+			gas_low: percentile(0.25),
+			gas_median: percentile(0.5),
+			gas_high: percentile(0.75),
+			oldest_age,
+			enters: count(OrderType::Enter),
+			updates: count(OrderType::Update),
+			cancels: count(OrderType::Cancel),
+			mass_cancels: count(OrderType::MassCancel),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{TradeType, ExchangeType};
+
+	#[test]
+	fn test_mempool_stats() {
+		let order = |order_type: OrderType, gas: f64| Order::new(format!("INVabc"), order_type, TradeType::Bid,
+			ExchangeType::LimitOrder, 99.0, 99.0, 99.0, 1.0, 1.0, gas);
+		let items = vec![order(OrderType::Enter, 1.0), order(OrderType::Enter, 2.0), order(OrderType::Cancel, 3.0)];
+		let mut seen_at = HashMap::new();
+		seen_at.insert(items[0].order_id, Duration::from_millis(400));
+		seen_at.insert(items[1].order_id, Duration::from_millis(100));
+
+		let stats = MemPoolStats::new(&items, &seen_at, Duration::from_millis(1000));
+		assert_eq!(stats.depth, 3);
+		assert_eq!((stats.gas_low, stats.gas_median, stats.gas_high), (2.0, 2.0, 3.0));
+		assert_eq!(stats.oldest_age, Duration::from_millis(900));
+		assert_eq!((stats.enters, stats.updates, stats.cancels, stats.mass_cancels), (2, 0, 1, 0));
+
+		let empty = MemPoolStats::new(&[], &seen_at, Duration::from_millis(1000));
+		assert_eq!((empty.depth, empty.gas_median), (0, 0.0));
+		assert_eq!(empty.oldest_age, Duration::from_millis(0));
+	}
+}
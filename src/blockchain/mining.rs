@@ -0,0 +1,65 @@
+use crate::simulation::simulation_config::{Distributions, DistReason};
+use crate::utility::sim_rng;
+
+use rand::Rng;
+
+
+/// The share of the hash power held by each of a set of competing miners. Each block is won by
+/// one of them with probability proportional to its weight.
+#[derive(Debug, Clone)]
+pub struct HashPower {
+	pub weights: Vec<f64>,
+}
+
+impl HashPower {
+	/// Samples each of n miners' weight from MinerHashPower, all equal when it isn't configured
+	pub fn sample(dists: &Distributions, n: usize) -> HashPower {
+		HashPower {
+			weights: (0..n).map(|_| dists.sample_if_set(DistReason::MinerHashPower).map(|w| w.abs()).unwrap_or(1.0)).collect(),
+		}
+	}
+
+	/// The index of the miner a uniform draw in [0, 1) lands on. Without any weight the first
+	/// miner wins.
+	pub fn pick(&self, draw: f64) -> usize {
+		let total: f64 = self.weights.iter().sum();
+		if total <= 0.0 {
+			return 0;
+		}
+		let mut cumulative = 0.0;
+		for (i, w) in self.weights.iter().enumerate() {
+			cumulative += w / total;
+			if draw < cumulative {
+				return i;
+			}
+		}
+		self.weights.len() - 1
+	}
+
+	/// Draws the index of the next block's winner
+	pub fn winner(&self) -> usize {
+		self.pick(sim_rng().gen_range(0.0, 1.0))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::DistType;
+
+	#[test]
+	fn test_hash_power() {
+		let power = HashPower { weights: vec![1.0, 3.0] };
+		assert_eq!(power.pick(0.0), 0);
+		assert_eq!(power.pick(0.2), 0);
+		assert_eq!(power.pick(0.3), 1);
+		assert_eq!(power.pick(0.99), 1);
+		assert_eq!(HashPower { weights: vec![0.0, 0.0] }.pick(0.5), 0);
+
+		let dists = Distributions::new(vec![(DistReason::MinerHashPower, 2.0, 0.0, 1.0, DistType::Normal)]);
+		assert_eq!(HashPower::sample(&dists, 3).weights, vec![2.0, 2.0, 2.0]);
+		let unset = Distributions::new(vec![(DistReason::MakerSpread, 2.0, 0.0, 1.0, DistType::Normal)]);
+		assert_eq!(HashPower::sample(&unset, 2).weights, vec![1.0, 1.0]);
+	}
+}
@@ -1,3 +1,16 @@
+pub mod block_gas;
+pub mod bundle;
+pub mod chain;
+pub mod eviction;
+pub mod gas_oracle;
+pub mod gossip;
+pub mod mempool_events;
 pub mod mempool_processor;
+pub mod mempool_stats;
 pub mod mem_pool;
-pub mod order_processor;
\ No newline at end of file
+pub mod mining;
+pub mod order_processor;
+pub mod pool_index;
+pub mod rate_limit;
+pub mod regions;
+pub mod reorg;
\ No newline at end of file
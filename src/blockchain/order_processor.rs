@@ -1,12 +1,17 @@
 use tokio::net::tcp::TcpStream;
-use crate::order::order::{Order, OrderType, TradeType, ExchangeType};
+use crate::order::order::{Order, OrderType, TradeType, ExchangeType, Venue};
 use crate::blockchain::mem_pool::MemPool;
+use crate::simulation::simulation_config::{Distributions, DistReason};
+use crate::players::TraderT;
+use crate::utility::{trader_type, scaled_millis};
+use crate::error::{Error, Result};
 
 use crate::log_mempool_data;
 
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 extern crate serde;
 extern crate serde_json;
@@ -17,6 +22,36 @@ use serde_json::Value;
 use tokio_serde_json::{ReadJson, WriteJson};
 use log::{log, Level};
 
+/// The network latency between a player creating an order and it arriving in the MemPool,
+/// sampled per order in milliseconds from the sender's latency distribution. Investors and makers
+/// have their own, every other strategy agent shares AgentLatency. Unset distributions are instant.
+#[derive(Debug, Clone)]
+pub struct LatencyModel {
+	pub dists: Distributions,
+}
+
+impl LatencyModel {
+	pub fn new(dists: &Distributions) -> LatencyModel {
+		LatencyModel {
+			dists: dists.clone(),
+		}
+	}
+
+	/// The distribution trader_id's latency is sampled from
+	pub fn latency_reason(trader_id: &str) -> DistReason {
+		match trader_type(trader_id) {
+			Some(TraderT::Investor) => DistReason::InvestorLatency,
+			Some(TraderT::Maker) => DistReason::MakerLatency,
+			_ => DistReason::AgentLatency,
+		}
+	}
+
+	pub fn sample(&self, trader_id: &str) -> Duration {
+		let millis = self.dists.sample_if_set(LatencyModel::latency_reason(trader_id)).unwrap_or(0.0).abs();
+		Duration::from_millis(scaled_millis(millis as u64))
+	}
+}
+
 // Handles JSON serialization/deserialization functions and new message processing
 pub struct OrderProcessor {}
 
@@ -25,13 +60,166 @@ impl OrderProcessor {
 	// Preprocess message in a new thread and append to MemPool
 	// order is the trader's order that this function takes ownership of
 	// pool is an Arc clone of the MemPool stored on the heap
+	// If the pool has a latency model, the order is held for the sender's latency in the
+	// background so the sender isn't blocked joining the handle. The MemPool holds back
+	// orders overtaking the trader's earlier ones until those arrive.
+	// Orders failing validation are dropped, use try_recv_order to get the rejection back.
+	// Under commit-reveal the order is committed to instead, and sent once it's revealed.
 	pub fn conc_recv_order(order: Order, pool: Arc<MemPool>) -> JoinHandle<()> {
-	    thread::spawn(move || {
-	    	// Log the order to the mempool logger
-	    	log_mempool_data!(Order::order_to_csv(&order));
-	    	// The add function acquires the lock
-	    	pool.add(order);
-	    })
+		match OrderProcessor::try_recv_order(order, pool) {
+			Ok(handle) => handle,
+			Err(e) => {
+				println!("Rejected at the MemPool: {}", e);
+				thread::spawn(|| {})
+			},
+		}
+	}
+
+	// Admits the order before sending it like conc_recv_order, returning why it was rejected
+	pub fn try_recv_order(order: Order, pool: Arc<MemPool>) -> Result<JoinHandle<()>> {
+		let order = match pool.commit(order) {
+			Some(order) => order,
+			None => return Ok(thread::spawn(|| {})),
+		};
+		let order = OrderProcessor::admit(order, &pool)?;
+		let order = OrderProcessor::prepare(order, &pool);
+		let latency = OrderProcessor::latency(&order.trader_id, &pool);
+	    Ok(thread::spawn(move || {
+	    	if latency > Duration::from_millis(0) {
+	    		thread::spawn(move || {
+	    			thread::sleep(latency);
+	    			OrderProcessor::deliver(order, &pool);
+	    		});
+	    	} else {
+	    		OrderProcessor::deliver(order, &pool);
+	    	}
+	    }))
+	}
+
+	// Sends a batch of orders on one thread, adding them to the MemPool under one lock. The batch
+	// travels together, held for the latency of its first order's sender.
+	// Orders in the batch failing validation are dropped.
+	pub fn conc_recv_orders(orders: Vec<Order>, pool: Arc<MemPool>) -> JoinHandle<()> {
+		let orders: Vec<Order> = orders.into_iter()
+			.filter_map(|order| pool.commit(order))
+			.filter_map(|order| match OrderProcessor::admit(order, &pool) {
+				Ok(order) => Some(order),
+				Err(e) => {
+					println!("Rejected at the MemPool: {}", e);
+					None
+				},
+			})
+			.map(|order| OrderProcessor::prepare(order, &pool))
+			.collect();
+		let latency = match orders.first() {
+			Some(order) => OrderProcessor::latency(&order.trader_id, &pool),
+			None => Duration::from_millis(0),
+		};
+		thread::spawn(move || {
+			if latency > Duration::from_millis(0) {
+				thread::sleep(latency);
+			}
+			OrderProcessor::deliver_batch(orders, &pool);
+		})
+	}
+
+	// Sends the order to the MemPool on the caller's thread for the discrete-event mode, where
+	// there's no wall clock for the latency model to hold it against
+	pub fn recv_order(order: Order, pool: &MemPool) -> Result<()> {
+		let order = match pool.commit(order) {
+			Some(order) => order,
+			None => return Ok(()),
+		};
+		let order = OrderProcessor::admit(order, pool)?;
+		let order = OrderProcessor::prepare(order, pool);
+		OrderProcessor::deliver(order, pool);
+		Ok(())
+	}
+
+	// Validates the order and holds its trader to the MemPool's rate limit, which may raise its gas
+	fn admit(mut order: Order, pool: &MemPool) -> Result<Order> {
+		OrderProcessor::validate(&order, pool)?;
+		if let Some(limit) = &pool.rate_limit {
+			let pending = pool.pending_of(&order.trader_id).len();
+			if let Err(reason) = limit.admit(&mut order, pending) {
+				return Err(Error::Rejected {
+					trader_id: order.trader_id.clone(),
+					order_id: order.order_id,
+					reason,
+				});
+			}
+		}
+		Ok(order)
+	}
+
+	/// Rejects orders that would corrupt the auctions before they reach the MemPool: orders
+	/// with negative or NaN quantities, prices that aren't finite, flow orders whose p_low is
+	/// above their p_high, and cancels or updates of orders never sent. A cancel's price and
+	/// quantity are never used so they aren't checked.
+	pub fn validate(order: &Order, pool: &MemPool) -> Result<()> {
+		let reject = |reason: &'static str| Err(Error::Rejected {
+			trader_id: order.trader_id.clone(),
+			order_id: order.order_id,
+			reason,
+		});
+		if let OrderType::Enter | OrderType::Update = order.order_type {
+			if order.quantity.is_nan() || order.quantity < 0.0 {
+				return reject("Order quantity is negative or not a number");
+			}
+			if !order.price.is_finite() || !order.p_low.is_finite() || !order.p_high.is_finite() {
+				return reject("Order price is not a finite number");
+			}
+			if order.ex_type == ExchangeType::FlowOrder && order.p_low > order.p_high {
+				return reject("Flow order p_low is above its p_high");
+			}
+		}
+		match order.order_type {
+			OrderType::Cancel | OrderType::Update if !pool.knows(order.order_id) => Err(Error::UnknownOrder {
+				trader_id: order.trader_id.clone(),
+				order_id: order.order_id,
+				op: "validate",
+			}),
+			_ => Ok(()),
+		}
+	}
+
+	// Numbers the order as it's sent so it's mined after the trader's earlier orders.
+	// An encrypted mempool hides the order's contents unless they leak.
+	fn prepare(mut order: Order, pool: &MemPool) -> Order {
+		pool.know(order.order_id);
+		order.nonce = pool.next_nonce(&order.trader_id);
+		order.encrypted = pool.encrypts();
+		order
+	}
+
+	fn latency(trader_id: &str, pool: &MemPool) -> Duration {
+		match &pool.latency {
+			Some(model) => model.sample(trader_id),
+			None => Duration::from_millis(0),
+		}
+	}
+
+	fn deliver_batch(orders: Vec<Order>, pool: &MemPool) {
+		if pool.regions.is_some() {
+			for order in orders {
+				OrderProcessor::deliver(order, pool);
+			}
+			return;
+		}
+		for order in orders.iter() {
+			log_mempool_data!(Order::order_to_csv(order));
+		}
+		pool.add_batch(orders);
+	}
+
+	fn deliver(order: Order, pool: &MemPool) {
+    	// Log the order to the mempool logger
+    	log_mempool_data!(Order::order_to_csv(&order));
+    	// The add function acquires the lock
+    	match &pool.regions {
+    		Some(regions) => regions.deliver(order, pool),
+    		None => pool.add(order),
+    	}
 	}
 }
 
@@ -52,6 +240,12 @@ pub struct JsonOrder{
 	quantity: f64,		
 	u_max: f64,
 	gas: f64,	
+	#[serde(default)]
+	client_order_id: Option<u64>,
+	#[serde(default)]
+	venue: Option<String>,
+	#[serde(default)]
+	symbol: Option<String>,
 }
 
 impl JsonOrder {
@@ -80,9 +274,11 @@ impl JsonOrder {
 		let order = JsonOrder::order_from_json(msg);
 
 		if let Some(o) = order {
-			// add message to queue with conc_recv_order()
-			let handle = OrderProcessor::conc_recv_order(o, Arc::clone(&queue));
-			handle.join().unwrap();
+			// add message to queue with try_recv_order(), which rejects malformed orders
+			match OrderProcessor::try_recv_order(o, Arc::clone(&queue)) {
+				Ok(handle) => handle.join().unwrap(),
+				Err(e) => println!("Rejected json order: {}", e),
+			}
 		} else {
 			println!("Unsuccessful json parsing");
 		}
@@ -96,6 +292,7 @@ impl JsonOrder {
 			"enter" => OrderType::Enter,
 			"update" => OrderType::Update,
 			"cancel" => OrderType::Cancel,
+			"masscancel" => OrderType::MassCancel,
 			_ => {
 				println!("Entered an invalid ordertype!");
 				return None;
@@ -125,7 +322,7 @@ impl JsonOrder {
 		// 	TradeType::Ask => p_wise_sup(typed_json.p_low, typed_json.p_high, typed_json.u_max),
 		// };
 
-		Some(Order::new(
+		let mut order = Order::new(
 			typed_json.trader_id,
 			ot, 
 			tt, 
@@ -136,7 +333,20 @@ impl JsonOrder {
 			typed_json.quantity, 
 			typed_json.u_max,
 			typed_json.gas,
-			))
+			);
+		order.client_order_id = typed_json.client_order_id;
+		order.venue = match typed_json.venue.as_ref().map(|v| v.as_str()) {
+			None | Some("lit") => Venue::Lit,
+			Some("dark") => Venue::Dark,
+			_ => {
+				println!("Entered an invalid venue!");
+				return None;
+			}
+		};
+		if let Some(symbol) = typed_json.symbol {
+			order.symbol = symbol;
+		}
+		Some(order)
 	}
 
 	// Turn an order into JSON from its params
@@ -145,6 +355,7 @@ impl JsonOrder {
             OrderType::Enter => "enter",
             OrderType::Update => "update",
             OrderType::Cancel => "cancel",
+            OrderType::MassCancel => "masscancel",
         };
 
         let tt = match order.trade_type {
@@ -169,6 +380,7 @@ impl JsonOrder {
             OrderType::Enter => "enter",
             OrderType::Update => "update",
             OrderType::Cancel => "cancel",
+            OrderType::MassCancel => "masscancel",
         };
 
         let tt = match tt {
@@ -187,5 +399,76 @@ impl JsonOrder {
 }
 
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::DistType;
+	use crate::order::order::DEFAULT_SYMBOL;
+	use std::collections::HashSet;
+
+	#[test]
+	fn test_latency() {
+		let dists = Distributions::new(vec![(DistReason::MakerLatency, 50.0, 0.0, 1.0, DistType::Normal)]);
+		let latency = LatencyModel::new(&dists);
+		assert_eq!(latency.sample("MKRabc"), Duration::from_millis(50));
+		assert_eq!(latency.sample("INVabc"), Duration::from_millis(0));
+		assert_eq!(LatencyModel::latency_reason("SNPabc"), DistReason::AgentLatency);
+
+		// The maker's order arrives after its latency, the investor's at once
+		let pool = Arc::new(MemPool::new().with_latency(latency));
+		let order = |id: &str| Order::new(id.to_string(), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			99.0, 99.0, 99.0, 1.0, 1.0, 0.0).with_symbol(DEFAULT_SYMBOL);
+		OrderProcessor::conc_recv_order(order("MKRabc"), Arc::clone(&pool)).join().unwrap();
+		OrderProcessor::conc_recv_order(order("INVabc"), Arc::clone(&pool)).join().unwrap();
+		assert_eq!(pool.snapshot().iter().map(|o| o.trader_id.clone()).collect::<Vec<String>>(), vec![format!("INVabc")]);
+		thread::sleep(Duration::from_millis(200));
+		assert_eq!(pool.length(), 2);
+	}
 
+	#[test]
+	fn test_validate() {
+		let pool = Arc::new(MemPool::new());
+		let order = || Order::new(format!("INVabc"), OrderType::Enter, TradeType::Bid, ExchangeType::FlowOrder,
+			98.0, 99.0, 99.0, 1.0, 1.0, 0.0).with_symbol(DEFAULT_SYMBOL);
+		let reason = |o: Order| OrderProcessor::try_recv_order(o, Arc::clone(&pool)).err().map(|e| e.reason());
+
+		let mut negative = order();
+		negative.quantity = -1.0;
+		assert_eq!(reason(negative), Some("Order quantity is negative or not a number"));
+		let mut nan = order();
+		nan.price = std::f64::NAN;
+		assert_eq!(reason(nan), Some("Order price is not a finite number"));
+		let mut inverted = order();
+		inverted.p_low = 100.0;
+		assert_eq!(reason(inverted), Some("Flow order p_low is above its p_high"));
+		assert_eq!(pool.length(), 0);
+
+		// Cancels must reference an order that was sent
+		let mut unknown = order();
+		unknown.order_type = OrderType::Cancel;
+		assert_eq!(reason(unknown), Some("Order not found"));
+		let sent = order();
+		let mut cancel = sent.clone();
+		cancel.order_type = OrderType::Cancel;
+		cancel.quantity = -1.0;
+		OrderProcessor::recv_order(sent, &pool).unwrap();
+		OrderProcessor::recv_order(cancel, &pool).unwrap();
+		assert_eq!(pool.length(), 2);
+	}
 
+	#[test]
+	fn test_prune_known() {
+		let pool = MemPool::new();
+		let orders: Vec<Order> = (0..60).map(|_| Order::new(format!("INVabc"), OrderType::Enter, TradeType::Bid,
+			ExchangeType::LimitOrder, 0.0, 0.0, 99.0, 1.0, 1.0, 0.0).with_symbol(DEFAULT_SYMBOL)).collect();
+		for order in orders.iter() {
+			OrderProcessor::recv_order(order.clone(), &pool).unwrap();
+		}
+		// Mined orders stay known while they're open in the book
+		pool.pop_all();
+		let open: HashSet<u64> = orders[..10].iter().map(|o| o.order_id).collect();
+		pool.prune_known(open.len(), || open.clone());
+		assert!(orders[..10].iter().all(|o| pool.knows(o.order_id)));
+		assert!(orders[10..].iter().all(|o| !pool.knows(o.order_id)));
+	}
+}
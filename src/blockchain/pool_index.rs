@@ -0,0 +1,219 @@
+use crate::order::order::Order;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// An order's place in the MemPool. Orders paying more gas come first, then those that arrived
+/// first. An order moved next to one of its trader's orders to keep their nonce order takes that
+/// order's gas and arrival, the nonce then putting it just before or after it.
+/// rank: u64 -> the gas paid, higher gas ranking lower
+/// arrival: u64 -> the order's place in the MemPool's arrivals
+/// nonce: u64 -> the trader's nonce, 0 for orders without one
+/// seq: u64 -> the index's insertion count, telling apart orders that are otherwise equal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PoolKey {
+	pub rank: u64,
+	pub arrival: u64,
+	pub nonce: u64,
+	pub seq: u64,
+}
+
+impl PoolKey {
+	// The place the order's own gas and arrival give it
+	fn of(order: &Order, seq: u64) -> PoolKey {
+		PoolKey {
+			rank: PoolKey::rank(order.gas),
+			arrival: order.arrival,
+			nonce: order.nonce,
+			seq,
+		}
+	}
+
+	// The place beside the neighbour, before it if the order's nonce is lower and after it if higher
+	fn beside(neighbour: &PoolKey, order: &Order, seq: u64) -> PoolKey {
+		PoolKey {
+			rank: neighbour.rank,
+			arrival: neighbour.arrival,
+			nonce: order.nonce,
+			seq,
+		}
+	}
+
+	// Maps the gas to an integer that orders the opposite way, so the highest gas comes first
+	fn rank(gas: f64) -> u64 {
+		let bits = gas.to_bits();
+		let ascending = if bits >> 63 == 1 { !bits } else { bits | (1 << 63) };
+		!ascending
+	}
+}
+
+/// The orders waiting in the MemPool, kept in gas order so they're never re-sorted. Inserting,
+/// removing and evicting an order are O(log n).
+/// orders: BTreeMap<PoolKey, Order> -> the orders in the order they're mined
+/// by_id: HashMap<u64, Vec<PoolKey>> -> the places of the orders with each id, an order's updates and cancels sharing its id
+/// by_arrival: BTreeSet<(u64, PoolKey)> -> the places in the order the orders arrived
+/// shared: Option<Arc<Vec<Order>>> -> the orders listed for the last snapshot, until they change
+/// seq: u64 -> the orders inserted so far
+pub struct PoolIndex {
+	orders: BTreeMap<PoolKey, Order>,
+	by_id: HashMap<u64, Vec<PoolKey>>,
+	by_arrival: BTreeSet<(u64, PoolKey)>,
+	shared: Option<Arc<Vec<Order>>>,
+	seq: u64,
+}
+
+impl PoolIndex {
+	pub fn new() -> PoolIndex {
+		PoolIndex {
+			orders: BTreeMap::new(),
+			by_id: HashMap::new(),
+			by_arrival: BTreeSet::new(),
+			shared: None,
+			seq: 0,
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.orders.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.orders.is_empty()
+	}
+
+	/// The orders in the order they're mined
+	pub fn iter(&self) -> impl Iterator<Item = &Order> {
+		self.orders.values()
+	}
+
+	/// The orders with their places, in the order they're mined
+	pub fn entries(&self) -> impl Iterator<Item = (&PoolKey, &Order)> {
+		self.orders.iter()
+	}
+
+	pub fn get(&self, key: &PoolKey) -> Option<&Order> {
+		self.orders.get(key)
+	}
+
+	/// The places of the waiting orders with the id
+	pub fn keys_of(&self, order_id: u64) -> &[PoolKey] {
+		self.by_id.get(&order_id).map_or(&[], |keys| keys.as_slice())
+	}
+
+	pub fn contains_id(&self, order_id: u64) -> bool {
+		self.by_id.contains_key(&order_id)
+	}
+
+	/// The place the order's gas and arrival give it
+	pub fn key_of(&mut self, order: &Order) -> PoolKey {
+		self.seq += 1;
+		PoolKey::of(order, self.seq)
+	}
+
+	/// The place beside one of its trader's orders
+	pub fn key_beside(&mut self, neighbour: &PoolKey, order: &Order) -> PoolKey {
+		self.seq += 1;
+		PoolKey::beside(neighbour, order, self.seq)
+	}
+
+	pub fn insert(&mut self, key: PoolKey, order: Order) {
+		self.by_id.entry(order.order_id).or_insert(Vec::new()).push(key);
+		self.by_arrival.insert((order.arrival, key));
+		self.orders.insert(key, order);
+		self.shared = None;
+	}
+
+	pub fn remove(&mut self, key: &PoolKey) -> Option<Order> {
+		let order = self.orders.remove(key)?;
+		if let Some(keys) = self.by_id.get_mut(&order.order_id) {
+			keys.retain(|k| k != key);
+			if keys.is_empty() {
+				self.by_id.remove(&order.order_id);
+			}
+		}
+		self.by_arrival.remove(&(order.arrival, *key));
+		self.shared = None;
+		Some(order)
+	}
+
+	/// The place of the order mined first
+	pub fn first_key(&self) -> Option<PoolKey> {
+		self.orders.keys().next().cloned()
+	}
+
+	/// The place of the order mined last, paying the least gas
+	pub fn last_key(&self) -> Option<PoolKey> {
+		self.orders.keys().next_back().cloned()
+	}
+
+	/// The place of the order that arrived first
+	pub fn oldest_key(&self) -> Option<PoolKey> {
+		self.by_arrival.iter().next().map(|(_, key)| *key)
+	}
+
+	/// Takes every order out, in the order they're mined
+	pub fn take_all(&mut self) -> Vec<Order> {
+		self.by_id.clear();
+		self.by_arrival.clear();
+		self.shared = None;
+		std::mem::replace(&mut self.orders, BTreeMap::new()).into_iter().map(|(_, order)| order).collect()
+	}
+
+	/// The orders in the order they're mined, listed once for every reader until they change
+	pub fn snapshot(&mut self) -> Arc<Vec<Order>> {
+		if self.shared.is_none() {
+			self.shared = Some(Arc::new(self.orders.values().cloned().collect()));
+		}
+		Arc::clone(self.shared.as_ref().expect("pool_index snapshot"))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+
+	fn order(gas: f64, arrival: u64) -> Order {
+		let mut order = Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 0.0, 0.0, 100.0, 1.0, 1.0, gas);
+		order.arrival = arrival;
+		order
+	}
+
+	#[test]
+	fn test_pool_index() {
+		let mut index = PoolIndex::new();
+		for (gas, arrival) in vec![(1.0, 1), (3.0, 2), (1.0, 3), (2.0, 4)] {
+			let o = order(gas, arrival);
+			let key = index.key_of(&o);
+			index.insert(key, o);
+		}
+
+		// Highest gas first, then first come first served
+		let mined: Vec<(f64, u64)> = index.iter().map(|o| (o.gas, o.arrival)).collect();
+		assert_eq!(mined, vec![(3.0, 2), (2.0, 4), (1.0, 1), (1.0, 3)]);
+		assert_eq!(index.get(&index.last_key().unwrap()).unwrap().arrival, 3);
+		assert_eq!(index.get(&index.oldest_key().unwrap()).unwrap().arrival, 1);
+
+		// Snapshots are shared until the orders change
+		let snapshot = index.snapshot();
+		assert!(Arc::ptr_eq(&snapshot, &index.snapshot()));
+		let first = index.first_key().unwrap();
+		let removed = index.remove(&first).unwrap();
+		assert!(!index.contains_id(removed.order_id));
+		assert_eq!(index.snapshot().len(), 3);
+		assert_eq!(snapshot.len(), 4);
+
+		// An order placed beside another keeps its nonce order with it
+		let mut earlier = order(1.0, 5);
+		earlier.nonce = 1;
+		let mut later = order(5.0, 6);
+		later.nonce = 2;
+		let key = index.key_of(&earlier);
+		index.insert(key, earlier);
+		let beside = index.key_beside(&key, &later);
+		index.insert(beside, later);
+		assert_eq!(index.take_all().last().unwrap().nonce, 2);
+		assert!(index.is_empty());
+	}
+}
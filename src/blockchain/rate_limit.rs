@@ -0,0 +1,103 @@
+use crate::order::order::{Order, OrderType};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Per-trader limits on the orders each trader has waiting in the MemPool and the orders it
+/// sends each block, so no one agent can fill the blocks. Orders over a limit are rejected, or
+/// with an escalation their gas is raised by (1 + escalation) for each order they're over by.
+/// max_pending: usize -> orders a trader may have waiting, 0 for no limit. Cancels are always let in.
+/// max_per_block: usize -> orders a trader may send each block, 0 for no limit
+/// escalation: f64 -> 0 rejects orders over a limit, otherwise the gas surcharge per order over
+/// sent: Mutex<HashMap<String, usize>> -> the orders each trader has sent this block
+/// totals: Mutex<(usize, usize)> -> the orders rejected and those surcharged
+pub struct RateLimit {
+	pub max_pending: usize,
+	pub max_per_block: usize,
+	pub escalation: f64,
+	pub sent: Mutex<HashMap<String, usize>>,
+	pub totals: Mutex<(usize, usize)>,
+}
+
+impl RateLimit {
+	pub fn new(max_pending: usize, max_per_block: usize, escalation: f64) -> RateLimit {
+		RateLimit {
+			max_pending,
+			max_per_block,
+			escalation,
+			sent: Mutex::new(HashMap::new()),
+			totals: Mutex::new((0, 0)),
+		}
+	}
+
+	/// How many orders the trader's next order is over its limits by, given how many it has waiting
+	pub fn excess(&self, order: &Order, pending: usize) -> usize {
+		let sent = self.sent.lock().expect("rate_limit excess").get(&order.trader_id).cloned().unwrap_or(0);
+		let mut excess = 0;
+		if self.max_per_block > 0 && sent >= self.max_per_block {
+			excess += sent + 1 - self.max_per_block;
+		}
+		if self.max_pending > 0 && order.order_type != OrderType::Cancel && pending >= self.max_pending {
+			excess += pending + 1 - self.max_pending;
+		}
+		excess
+	}
+
+	/// Admits the order, surcharging its gas if it's over the limits and there's an escalation.
+	/// Orders admitted count towards the trader's orders sent this block.
+	pub fn admit(&self, order: &mut Order, pending: usize) -> Result<(), &'static str> {
+		let excess = self.excess(order, pending);
+		if excess > 0 {
+			let mut totals = self.totals.lock().expect("rate_limit totals");
+			if self.escalation <= 0.0 {
+				totals.0 += 1;
+				return Err("Trader is over its mempool rate limit");
+			}
+			order.gas *= (1.0 + self.escalation).powi(excess as i32);
+			totals.1 += 1;
+		}
+		*self.sent.lock().expect("rate_limit sent").entry(order.trader_id.clone()).or_insert(0) += 1;
+		Ok(())
+	}
+
+	/// Starts counting the orders sent in the next block
+	pub fn new_block(&self) {
+		self.sent.lock().expect("rate_limit new_block").clear();
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{TradeType, ExchangeType};
+
+	#[test]
+	fn test_rate_limit() {
+		let order = |order_type: OrderType| {
+			let mut o = Order::new(format!("SPMabc"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+				99.0, 99.0, 99.0, 1.0, 1.0, 1.0);
+			o.order_type = order_type;
+			o
+		};
+
+		// Two orders a block, then rejected until the next block
+		let limit = RateLimit::new(0, 2, 0.0);
+		assert!(limit.admit(&mut order(OrderType::Enter), 0).is_ok());
+		assert!(limit.admit(&mut order(OrderType::Enter), 0).is_ok());
+		assert!(limit.admit(&mut order(OrderType::Enter), 0).is_err());
+		limit.new_block();
+		assert!(limit.admit(&mut order(OrderType::Enter), 0).is_ok());
+		assert_eq!(*limit.totals.lock().unwrap(), (1, 0));
+
+		// Too many waiting orders escalate the gas, cancels always go in at their gas
+		let limit = RateLimit::new(2, 0, 1.0);
+		let mut over = order(OrderType::Enter);
+		limit.admit(&mut over, 3).unwrap();
+		assert_eq!(over.gas, 4.0);
+		let mut cancel = order(OrderType::Cancel);
+		limit.admit(&mut cancel, 3).unwrap();
+		assert_eq!(cancel.gas, 1.0);
+		assert_eq!(*limit.totals.lock().unwrap(), (0, 1));
+	}
+}
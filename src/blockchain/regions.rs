@@ -0,0 +1,119 @@
+use crate::order::order::Order;
+use crate::blockchain::mem_pool::MemPool;
+use crate::players::TraderT;
+use crate::utility::{get_time, trader_type};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+
+/// Where an order entered the network and when every region could see it
+/// region: usize -> the region whose mempool it arrived in, 0 being the miner's
+/// global: Option<Duration> -> when it was synced to every region, None until then
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegionRecord {
+	pub order_id: u64,
+	pub region: usize,
+	pub arrived: Duration,
+	pub global: Option<Duration>,
+}
+
+impl RegionRecord {
+	/// Milliseconds between arriving in its region and reaching every region
+	pub fn sync_delay(&self) -> Option<f64> {
+		self.global.map(|global| global.checked_sub(self.arrived).unwrap_or(Duration::from_millis(0)).as_millis() as f64)
+	}
+}
+
+/// A network partitioned into regions, each with its own mempool, that sync periodically.
+/// Region 0 is the miner's and uses the MemPool holding the Regions, the others are the pools
+/// here. Traders are assigned to a region by their id and send their orders to its mempool.
+pub struct Regions {
+	pub pools: Vec<Arc<MemPool>>,
+	pub records: Mutex<HashMap<u64, RegionRecord>>,
+}
+
+impl Regions {
+	/// num_regions regions, all but the miner's with a mempool of their own
+	pub fn new(num_regions: usize) -> Regions {
+		Regions {
+			pools: (1..num_regions).map(|_| Arc::new(MemPool::new())).collect(),
+			records: Mutex::new(HashMap::new()),
+		}
+	}
+
+	pub fn num_regions(&self) -> usize {
+		self.pools.len() + 1
+	}
+
+	/// The region a trader is in, the same one every time. Miners are in region 0.
+	pub fn region_of(&self, trader_id: &str) -> usize {
+		if trader_type(trader_id) == Some(TraderT::Miner) {
+			return 0;
+		}
+		let sum: usize = trader_id.bytes().map(|b| b as usize).sum();
+		sum % self.num_regions()
+	}
+
+	/// The mempool of a region other than the miner's
+	pub fn pool(&self, region: usize) -> Option<&Arc<MemPool>> {
+		match region {
+			0 => None,
+			r => self.pools.get(r - 1),
+		}
+	}
+
+	/// Delivers the order to its trader's region, recording where and when it arrived
+	pub fn deliver(&self, order: Order, home: &MemPool) {
+		let region = self.region_of(&order.trader_id);
+		self.records.lock().expect("Error locking region records").entry(order.order_id).or_insert(RegionRecord {
+			order_id: order.order_id,
+			region,
+			arrived: get_time(),
+			global: None,
+		});
+		match self.pool(region) {
+			Some(pool) => pool.add(order),
+			None => home.add(order),
+		}
+	}
+
+	/// Copies the orders each region has that the others haven't seen to every other region.
+	/// The regional copies of orders the miner already took from home are dropped. Returns the
+	/// records of the orders that just became globally visible.
+	pub fn sync(&self, home: &MemPool) -> Vec<RegionRecord> {
+		let now = get_time();
+		let mut records = self.records.lock().expect("Error locking region records");
+		let pools: Vec<&MemPool> = std::iter::once(home).chain(self.pools.iter().map(|p| p.as_ref())).collect();
+
+		let mut synced = Vec::new();
+		for (r, pool) in pools.iter().enumerate() {
+			for order in pool.snapshot().iter() {
+				let record = match records.get_mut(&order.order_id) {
+					Some(record) if record.global.is_none() => record,
+					_ => continue,
+				};
+				for (_, other) in pools.iter().enumerate().filter(|(q, _)| *q != r) {
+					other.add_synced(order.clone());
+				}
+				record.global = Some(now);
+				synced.push(record.clone());
+			}
+		}
+
+		// Orders gone from home were mined, or never made it out of their region before the
+		// miner took them
+		let waiting: HashSet<u64> = home.snapshot().iter().map(|o| o.order_id).collect();
+		for pool in self.pools.iter() {
+			let mined: Vec<u64> = pool.snapshot().iter()
+				.filter(|o| records.get(&o.order_id).map_or(false, |r| r.global.is_some()) && !waiting.contains(&o.order_id))
+				.map(|o| o.order_id)
+				.collect();
+			pool.remove_ids(&mined);
+		}
+		let regional: HashSet<u64> = self.pools.iter().flat_map(|p| p.snapshot().iter().map(|o| o.order_id).collect::<Vec<u64>>()).collect();
+		records.retain(|id, _| waiting.contains(id) || regional.contains(id));
+		synced
+	}
+}
@@ -0,0 +1,130 @@
+use crate::order::order::Order;
+use crate::order::order_book::Book;
+use crate::order::dark_book::DarkBook;
+use crate::exchange::amm::AmmPool;
+use crate::exchange::dutch::DutchAuction;
+use crate::simulation::simulation_history::{History, HistoryMark};
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// The state of the chain before a block was published, to roll back to if it's orphaned.
+/// The players' side is kept by the ClearingHouse's undo log.
+/// block: u64 -> the number of the block
+/// frame: Vec<Order> -> the orders mined into the block, returned to the mempool if it's orphaned
+/// books: HashMap<String, (Vec<Order>, Vec<Order>)> -> each symbol's lit bids and asks before the block
+/// dark_books: HashMap<String, (Vec<Order>, Vec<Order>)> -> each symbol's dark bids and asks before the block
+/// amm_reserves: HashMap<String, (f64, f64)> -> each symbol's AMM pool shares and cash before the block
+/// dutch: HashMap<String, (f64, f64)> -> each symbol's Dutch auction price and remaining supply before the block
+/// history: HistoryMark -> where the History's book states, clearings and volume stood before the block
+pub struct BlockCheckpoint {
+	pub block: u64,
+	pub frame: Vec<Order>,
+	pub books: HashMap<String, (Vec<Order>, Vec<Order>)>,
+	pub dark_books: HashMap<String, (Vec<Order>, Vec<Order>)>,
+	pub amm_reserves: HashMap<String, (f64, f64)>,
+	pub dutch: HashMap<String, (f64, f64)>,
+	pub history: HistoryMark,
+}
+
+impl BlockCheckpoint {
+	pub fn new(block: u64, frame: Vec<Order>, books: &HashMap<String, (Arc<Book>, Arc<Book>)>, dark_books: &HashMap<String, Arc<DarkBook>>,
+		amm_pools: &HashMap<String, Arc<AmmPool>>, dutch_auctions: &HashMap<String, Arc<DutchAuction>>, history: &History) -> BlockCheckpoint {
+		BlockCheckpoint {
+			block,
+			frame,
+			books: books.iter().map(|(symbol, (bids, asks))| (symbol.clone(), (bids.copy_orders(), asks.copy_orders()))).collect(),
+			dark_books: dark_books.iter().map(|(symbol, dark)| {
+				(symbol.clone(), (dark.bids.lock().unwrap().clone(), dark.asks.lock().unwrap().clone()))
+			}).collect(),
+			amm_reserves: amm_pools.iter().map(|(symbol, pool)| (symbol.clone(), *pool.reserves.lock().unwrap())).collect(),
+			dutch: dutch_auctions.iter().map(|(symbol, auction)| {
+				(symbol.clone(), (*auction.price.lock().unwrap(), *auction.remaining.lock().unwrap()))
+			}).collect(),
+			history: history.mark(),
+		}
+	}
+
+	/// Puts every symbol's venues and the History's records back as they were before the block,
+	/// returning the orders the block mined so they can be mined again
+	pub fn restore(self, books: &HashMap<String, (Arc<Book>, Arc<Book>)>, dark_books: &HashMap<String, Arc<DarkBook>>,
+		amm_pools: &HashMap<String, Arc<AmmPool>>, dutch_auctions: &HashMap<String, Arc<DutchAuction>>, history: &History) -> Vec<Order> {
+		for (symbol, (bids, asks)) in self.books {
+			if let Some((bid_book, ask_book)) = books.get(&symbol) {
+				bid_book.restore(bids);
+				ask_book.restore(asks);
+			}
+		}
+		for (symbol, (bids, asks)) in self.dark_books {
+			if let Some(dark) = dark_books.get(&symbol) {
+				*dark.bids.lock().unwrap() = bids;
+				*dark.asks.lock().unwrap() = asks;
+			}
+		}
+		for (symbol, reserves) in self.amm_reserves {
+			if let Some(pool) = amm_pools.get(&symbol) {
+				*pool.reserves.lock().unwrap() = reserves;
+			}
+		}
+		for (symbol, (price, remaining)) in self.dutch {
+			if let Some(auction) = dutch_auctions.get(&symbol) {
+				*auction.price.lock().unwrap() = price;
+				*auction.remaining.lock().unwrap() = remaining;
+			}
+		}
+		history.rollback(&self.history);
+		self.frame
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+	use crate::exchange::MarketType;
+
+	fn order(trade_type: TradeType, price: f64) -> Order {
+		Order::new(format!("INVa"), OrderType::Enter, trade_type, ExchangeType::LimitOrder, 0.0, 0.0, price, 1.0, 0.0, 1.0)
+	}
+
+	#[test]
+	fn test_block_checkpoint() {
+		let mut books = HashMap::new();
+		books.insert(format!("SYM0"), (Arc::new(Book::new(TradeType::Bid)), Arc::new(Book::new(TradeType::Ask))));
+		books["SYM0"].0.add_order(order(TradeType::Bid, 99.0)).unwrap();
+		let mut dark_books = HashMap::new();
+		dark_books.insert(format!("SYM0"), Arc::new(DarkBook::new()));
+		dark_books["SYM0"].bids.lock().unwrap().push(order(TradeType::Bid, 98.0));
+		let mut amm_pools = HashMap::new();
+		amm_pools.insert(format!("SYM0"), Arc::new(AmmPool::new(100.0, 10000.0, 0.0)));
+		let mut dutch_auctions = HashMap::new();
+		dutch_auctions.insert(format!("SYM0"), Arc::new(DutchAuction::new(120.0, 1.0, 90.0, 50.0)));
+		let history = History::new(MarketType::CDA);
+
+		let frame = vec![order(TradeType::Ask, 99.0)];
+		let checkpoint = BlockCheckpoint::new(1, frame.clone(), &books, &dark_books, &amm_pools, &dutch_auctions, &history);
+
+		// The block crosses the resting bid and trades on every venue, the rollback puts them back
+		books["SYM0"].0.cancel_order_by_id(books["SYM0"].0.copy_orders()[0].order_id).unwrap();
+		assert_eq!(books["SYM0"].0.len(), 0);
+		dark_books["SYM0"].bids.lock().unwrap().clear();
+		*amm_pools["SYM0"].reserves.lock().unwrap() = (99.0, 10100.0);
+		*dutch_auctions["SYM0"].price.lock().unwrap() = 119.0;
+		*dutch_auctions["SYM0"].remaining.lock().unwrap() = 40.0;
+		history.clone_book_state(books["SYM0"].0.copy_orders(), TradeType::Bid, 1);
+		*history.lit_volume.lock().unwrap() += 1.0;
+
+		let returned = checkpoint.restore(&books, &dark_books, &amm_pools, &dutch_auctions, &history);
+		assert_eq!(returned.len(), 1);
+		assert_eq!(returned[0].order_id, frame[0].order_id);
+		assert_eq!(books["SYM0"].0.len(), 1);
+		assert_eq!(books["SYM0"].0.get_max_price(), 99.0);
+		assert_eq!(dark_books["SYM0"].bids.lock().unwrap().len(), 1);
+		assert_eq!(*amm_pools["SYM0"].reserves.lock().unwrap(), (100.0, 10000.0));
+		assert_eq!(*dutch_auctions["SYM0"].price.lock().unwrap(), 120.0);
+		assert_eq!(*dutch_auctions["SYM0"].remaining.lock().unwrap(), 50.0);
+		assert_eq!(history.order_books.lock().unwrap().len(), 0);
+		assert_eq!(*history.lit_volume.lock().unwrap(), 0.0);
+	}
+}
@@ -7,6 +7,8 @@ use futures::{Future};
 
 use futures::future;
 
+use crate::utility::{seed_task, scaled_millis};
+
 #[derive(Debug)]
 pub enum State {
 	Process,
@@ -51,6 +53,11 @@ impl Controller {
 	pub fn shutdown(self) {
 		self.runtime.shutdown_now().wait().expect("shutdown runtime");
 	}
+
+	/// Waits for every task to finish on its own, then shuts down
+	pub fn join(self) {
+		self.runtime.shutdown_on_idle().wait().expect("join runtime");
+	}
 }
 
 pub type AsyncTask = Box<dyn Future<Item = (), Error = ()> + Send>;
@@ -77,7 +84,7 @@ impl Task {
 	pub fn delay_task<F>(f: F, millis: u64) -> Task 
 	where F: Fn() + Send + Sync + 'static 
 	{
-		let when = Instant::now() + Duration::from_millis(millis);
+		let when = Instant::now() + Duration::from_millis(scaled_millis(millis));
 		let new_task = Delay::new(when)
 		    .and_then(move |_| {
 		    	f();
@@ -91,10 +98,18 @@ impl Task {
 	}
 
 	/// Calls the closure on an interval specified by millis 
-	pub fn rpt_task<F>(mut f: F, millis: u64) -> Task 
+	pub fn rpt_task<F>(f: F, millis: u64) -> Task 
 	where F: FnMut() + Send + Sync + 'static 
 	{
-		let new_task = Interval::new_interval(Duration::from_millis(millis))
+		Task::rpt_task_until(f, millis, || false)
+	}
+
+	/// rpt_task that finishes once stopped returns true, checked before each run
+	pub fn rpt_task_until<F, S>(mut f: F, millis: u64, stopped: S) -> Task
+	where F: FnMut() + Send + Sync + 'static, S: Fn() -> bool + Send + Sync + 'static
+	{
+		let new_task = Interval::new_interval(Duration::from_millis(scaled_millis(millis).max(1)))
+		    .take_while(move |_| Ok(!stopped()))
 		    .for_each(move |_| {
 		    	f();
 		    	Ok(())
@@ -106,9 +121,22 @@ impl Task {
 		}
 	}
 
+	/// rpt_task_until that seeds the thread running it from the master seed before each run, so
+	/// the task draws the same numbers whichever worker thread picks it up
+	pub fn seeded_rpt_task<F, S>(name: &'static str, seed: u64, stopped: S, mut f: F, millis: u64) -> Task
+	where F: FnMut() + Send + Sync + 'static, S: Fn() -> bool + Send + Sync + 'static
+	{
+		let mut run = 0;
+		Task::rpt_task_until(move || {
+			seed_task(seed, name, run);
+			run += 1;
+			f();
+		}, millis, stopped)
+	}
+
 	/// Converts a one off task into a delayed task
 	pub fn after_delay(self, millis: u64) -> Task {
-		let when = Instant::now() + Duration::from_millis(millis);
+		let when = Instant::now() + Duration::from_millis(scaled_millis(millis));
 		let new_task = Delay::new(when)
 		    .and_then(|_| {
 		    	tokio::spawn(self.task);
@@ -0,0 +1,44 @@
+use thiserror::Error;
+
+/// Errors raised by the ClearingHouse, the order books and restoring checkpoints. Each carries
+/// the trader, order or seed it concerns and the operation that failed.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum Error {
+	/// No player is registered under trader_id
+	#[error("{op}: couldn't find trader {trader_id}")]
+	UnknownTrader { trader_id: String, op: &'static str },
+
+	/// The player has no open order with order_id
+	#[error("{op}: couldn't find {trader_id}'s order {order_id}")]
+	UnknownOrder { trader_id: String, order_id: u64, op: &'static str },
+
+	/// The player has no open order with the client_order_id
+	#[error("{op}: couldn't find {trader_id}'s client order {client_id}")]
+	UnknownClientOrder { trader_id: String, client_id: u64, op: &'static str },
+
+	/// The book has no resting order with order_id
+	#[error("{op}: couldn't find order {order_id} in the book")]
+	NotInBook { order_id: u64, op: &'static str },
+
+	/// The order failed an admission check
+	#[error("order {order_id} from {trader_id} rejected: {reason}")]
+	Rejected { trader_id: String, order_id: u64, reason: &'static str },
+
+	/// The checkpoint was taken under another seed than the run restoring it, or the run isn't seeded
+	#[error("{op}: the checkpoint needs the seed {expected} it was taken with, the run's seed is {found}")]
+	SeedMismatch { expected: u64, found: u64, op: &'static str },
+}
+
+impl Error {
+	/// A short description of the error to send back to the trader in an OrderReject
+	pub fn reason(&self) -> &'static str {
+		match self {
+			Error::UnknownTrader { .. } => "Trader not found",
+			Error::UnknownOrder { .. } | Error::UnknownClientOrder { .. } | Error::NotInBook { .. } => "Order not found",
+			Error::Rejected { reason, .. } => *reason,
+			Error::SeedMismatch { .. } => "Seed mismatch",
+		}
+	}
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
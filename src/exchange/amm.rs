@@ -0,0 +1,155 @@
+use crate::order::order::{Order, OrderType, TradeType};
+use crate::exchange::MarketType;
+use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate};
+
+use std::sync::Mutex;
+
+/// The id the pool uses as the counterparty in PlayerUpdates
+pub const AMM_POOL_ID: &str = "amm_pool";
+
+/// A constant-product (x * y = k) automated market maker.
+/// reserves: Mutex<(f64, f64)> -> (shares, cash) held by the pool
+/// fee: f64 -> fraction of each swap's input kept by the pool
+pub struct AmmPool {
+	pub reserves: Mutex<(f64, f64)>,
+	pub fee: f64,
+}
+
+impl AmmPool {
+	pub fn new(shares: f64, cash: f64, fee: f64) -> AmmPool {
+		AmmPool {
+			reserves: Mutex::new((shares, cash)),
+			fee: fee,
+		}
+	}
+
+	/// The marginal price of a share before any swap
+	pub fn spot_price(&self) -> Option<f64> {
+		let (x, y) = *self.reserves.lock().expect("spot_price");
+		if x <= 0.0 {
+			return None;
+		}
+		Some(y / x)
+	}
+
+	/// Swaps each Enter order in the frame against the pool in frame order.
+	/// Orders are immediate-or-cancel: after the swap a cancel PlayerUpdate
+	/// removes the order so the unfilled remainder never rests anywhere.
+	pub fn process_frame(&self, frame: Vec<Order>) -> Option<Vec<TradeResults>> {
+		let mut results = Vec::<TradeResults>::new();
+		for order in frame {
+			match order.order_type {
+				OrderType::Enter => {
+					if let Some(result) = self.swap(&order) {
+						results.push(result);
+					}
+					let cancel = PlayerUpdate::new(order.trader_id.clone(), order.trader_id.clone(),
+						order.order_id, order.order_id, -9.99, -9.99, true);
+					results.push(TradeResults::new(MarketType::AMM, None, 0.0, 0.0, Some(vec![cancel])));
+				},
+				_ => println!("AMM ignoring non-enter order: {}", order.order_id),
+			}
+		}
+		if results.len() == 0 {
+			return None;
+		}
+		Some(results)
+	}
+
+	/// Swaps the order against the pool, filling as much of it as possible without
+	/// the average execution price crossing the order's limit price. Returns the
+	/// execution price as the uniform_price and its slippage from the spot price.
+	pub fn swap(&self, order: &Order) -> Option<TradeResults> {
+		let mut reserves = self.reserves.lock().expect("swap");
+		let (x, y) = *reserves;
+		if x <= 0.0 || y <= 0.0 || order.price <= 0.0 {
+			return None;
+		}
+		let spot = y / x;
+		// The fee is charged on the cash side of the swap
+		let fee_mult = 1.0 - self.fee;
+
+		let (volume, cash, new_reserves) = match order.trade_type {
+			TradeType::Bid => {
+				// Buying q shares costs y * q / (x - q) / fee_mult
+				// The average price stays ≤ limit while q ≤ x - y / (limit * fee_mult)
+				let max_q = x - y / (order.price * fee_mult);
+				let q = order.quantity.min(max_q);
+				if q <= 0.0 {
+					return None;
+				}
+				let cash_in = y * q / (x - q) / fee_mult;
+				(q, cash_in, (x - q, y + cash_in * fee_mult))
+			},
+			TradeType::Ask => {
+				// Selling q shares pays out y * q / (x + q) * fee_mult
+				// The average price stays ≥ limit while q ≤ y * fee_mult / limit - x
+				let max_q = y * fee_mult / order.price - x;
+				let q = order.quantity.min(max_q);
+				if q <= 0.0 {
+					return None;
+				}
+				let cash_out = y * q / (x + q) * fee_mult;
+				(q, cash_out, (x + q, y - cash_out))
+			},
+		};
+		*reserves = new_reserves;
+
+		let exec_price = cash / volume;
+		let slippage = (exec_price - spot).abs() / spot;
+
+		let update = match order.trade_type {
+			TradeType::Bid => PlayerUpdate::new(order.trader_id.clone(), String::from(AMM_POOL_ID),
+				order.order_id, 0, exec_price, volume, false).with_taker(TradeType::Bid),
+			TradeType::Ask => PlayerUpdate::new(String::from(AMM_POOL_ID), order.trader_id.clone(),
+				0, order.order_id, exec_price, volume, false).with_taker(TradeType::Ask),
+		};
+		let (agg_d, agg_s) = match order.trade_type {
+			TradeType::Bid => (volume, 0.0),
+			TradeType::Ask => (0.0, volume),
+		};
+		Some(TradeResults::new(MarketType::AMM, Some(exec_price), agg_d, agg_s, Some(vec![update]))
+			.with_slippage(slippage))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::ExchangeType;
+
+	fn order(t_t: TradeType, price: f64, quantity: f64) -> Order {
+		Order::new(String::from("trader"), OrderType::Enter, t_t, ExchangeType::LimitOrder,
+			0.0, 0.0, price, quantity, quantity, 0.1)
+	}
+
+	#[test]
+	fn test_constant_product_swap() {
+		let pool = AmmPool::new(1000.0, 100000.0, 0.0);
+		assert_eq!(pool.spot_price(), Some(100.0));
+
+		// Buying 100 shares costs 100000 * 100 / 900
+		let res = pool.swap(&order(TradeType::Bid, 200.0, 100.0)).unwrap();
+		let price = res.uniform_price.unwrap();
+		assert!((price - 111.111).abs() < 0.001);
+		assert!((res.slippage.unwrap() - 0.11111).abs() < 0.0001);
+		let (x, y) = *pool.reserves.lock().unwrap();
+		assert_eq!(x, 900.0);
+		assert!((x * y - 1000.0 * 100000.0).abs() < 0.001);
+
+		// A low limit price only partially fills the ask
+		let res = pool.swap(&order(TradeType::Ask, 120.0, 500.0)).unwrap();
+		assert!(res.uniform_price.unwrap() > 119.999);
+		assert!(res.agg_supply < 500.0);
+
+		// Nothing fills if the limit is below the spot price
+		let spot = pool.spot_price().unwrap();
+		assert!(pool.swap(&order(TradeType::Bid, spot - 1.0, 10.0)).is_none());
+
+		// Every entered order is cancelled after its swap
+		let results = pool.process_frame(vec![order(TradeType::Bid, spot - 1.0, 10.0)]).unwrap();
+		assert_eq!(results.len(), 1);
+		assert!(results[0].cross_results.as_ref().unwrap()[0].cancel);
+	}
+}
@@ -0,0 +1,121 @@
+use crate::simulation::simulation_config::Constants;
+
+use std::sync::Mutex;
+
+/// Trading halt and resume events produced by the CircuitBreaker
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum HaltEvent {
+	Halt { block_num: u64, ref_price: f64, price: f64 },
+	Resume { block_num: u64 },
+}
+
+/// Tracks the recent last trade prices and halts trading if the price moves more
+/// than consts.circuit_breaker_pct within consts.circuit_breaker_window blocks.
+/// While halted only cancels are processed from the frame.
+/// prices: Mutex<Vec<(u64, f64)>> -> (block_num, last trade price) within the window
+/// halted_until: Mutex<Option<u64>> -> first block that trading resumes on, if halted
+pub struct CircuitBreaker {
+	pub prices: Mutex<Vec<(u64, f64)>>,
+	pub halted_until: Mutex<Option<u64>>,
+}
+
+impl CircuitBreaker {
+	pub fn new() -> CircuitBreaker {
+		CircuitBreaker {
+			prices: Mutex::new(Vec::new()),
+			halted_until: Mutex::new(None),
+		}
+	}
+
+	/// True if trading is halted during block_num
+	pub fn is_halted(&self, block_num: u64) -> bool {
+		match *self.halted_until.lock().expect("is_halted") {
+			Some(until) => block_num < until,
+			None => false,
+		}
+	}
+
+	/// Records the last trade price of the block and checks whether to halt or resume trading.
+	/// Returns the HaltEvent if the state of the market changed this block.
+	pub fn update(&self, block_num: u64, price: Option<f64>, consts: &Constants) -> Option<HaltEvent> {
+		// A non-positive threshold disables the circuit breaker
+		if consts.circuit_breaker_pct <= 0.0 {
+			return None;
+		}
+
+		let mut halted_until = self.halted_until.lock().expect("update halted_until");
+		if let Some(until) = *halted_until {
+			if block_num >= until {
+				// Resume trading with a fresh reference window
+				*halted_until = None;
+				self.prices.lock().expect("update prices").clear();
+				return Some(HaltEvent::Resume { block_num });
+			}
+			return None;
+		}
+
+		let price = match price {
+			Some(p) => p,
+			None => return None,
+		};
+
+		let mut prices = self.prices.lock().expect("update prices");
+		// Drop the prices that fell out of the window
+		prices.retain(|(b, _p)| b + consts.circuit_breaker_window >= block_num);
+
+		// Find the reference price in the window that the new price moved furthest from
+		let mut halt_ref = None;
+		let mut max_move = consts.circuit_breaker_pct;
+		for (_b, ref_price) in prices.iter() {
+			if *ref_price == 0.0 {continue;}
+			let pct_move = ((price - ref_price) / ref_price).abs();
+			if pct_move > max_move {
+				max_move = pct_move;
+				halt_ref = Some(*ref_price);
+			}
+		}
+
+		match halt_ref {
+			Some(ref_price) => {
+				// Halt the next halt_blocks blocks
+				*halted_until = Some(block_num + 1 + consts.halt_blocks);
+				prices.clear();
+				Some(HaltEvent::Halt { block_num, ref_price, price })
+			},
+			None => {
+				prices.push((block_num, price));
+				None
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_halt_and_resume() {
+		let mut c = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.25, 1, 0.25, 0.25, 5.0, 0.01, 10, 0.5);
+		c.circuit_breaker_pct = 0.10;
+		c.circuit_breaker_window = 3;
+		c.halt_blocks = 2;
+
+		let cb = CircuitBreaker::new();
+		assert_eq!(cb.update(0, Some(100.0), &c), None);
+		assert_eq!(cb.update(1, Some(105.0), &c), None);
+		// 12% above block 0 price, still within the window
+		assert_eq!(cb.update(2, Some(112.0), &c), Some(HaltEvent::Halt { block_num: 2, ref_price: 100.0, price: 112.0 }));
+		assert!(cb.is_halted(3));
+		assert!(cb.is_halted(4));
+		assert_eq!(cb.update(3, None, &c), None);
+		assert_eq!(cb.update(5, None, &c), Some(HaltEvent::Resume { block_num: 5 }));
+		assert!(!cb.is_halted(5));
+
+		// A move outside of the window doesn't halt
+		assert_eq!(cb.update(6, Some(100.0), &c), None);
+		assert_eq!(cb.update(10, Some(120.0), &c), None);
+	}
+}
@@ -1,31 +1,78 @@
-use crate::simulation::simulation_config::{Distributions, Constants};
-use crate::simulation::simulation_history::{PriorData, LikelihoodStats, UpdateReason};
-use crate::exchange::exchange_logic::TradeResults;
-use crate::exchange::MarketType;
-use crate::order::order::{Order};
-use crate::players::{Player, TraderT};
+use crate::simulation::simulation_history::UpdateReason;
+use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate, OrderEvent};
+use crate::exchange::{MarketType, DefaultRule, BudgetRule};
+use crate::exchange::amm::AMM_POOL_ID;
+use crate::exchange::settlement::{Settlement, SettlementFailure};
+use crate::exchange::fees::FeePolicy;
+use crate::exchange::ledger::{Ledger, LedgerEntry};
+use crate::exchange::undo_log::UndoLog;
+use crate::exchange::pnl::PnlTracker;
+use crate::exchange::query::PlayerQuery;
+use crate::exchange::observer::{Observers, PlayerEvent};
+use crate::exchange::leaderboard::{self, Standing};
+use crate::simulation::risk::RiskMetrics;
+use crate::order::order::{Order, OrderType, TradeType, ExchangeType};
+use crate::players::{Player, TraderT, PlayerSnapshot};
+use crate::players::strategy::MarketData;
+use crate::players::script::script_orders;
 use crate::players::investor::Investor;
-use crate::players::maker::{Maker, MakerT};
+use crate::players::maker::{Maker, MakerT, MakerParams, NUM_MAKER_TYPES};
 use crate::players::miner::Miner;
+use crate::utility::get_time;
+use crate::error::{Error, Result};
 use crate::log_player_data;
 
-use std::collections::HashMap;
-use std::sync::Mutex;
-use rand::{thread_rng};
-use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, RwLock};
+use std::sync::mpsc::Receiver;
 
 
 use log::{log, Level};
 
+// Margin call orders are priced to cross every resting order
+const MARKET_BID_PRICE: f64 = 999_999_999.0;
+const MARKET_ASK_PRICE: f64 = 0.0;
 
 
+/// A player's value at the end of a block
+/// equity: f64 -> balance plus each position valued at its symbol's price
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EquityPoint {
+	pub block_num: u64,
+	pub balance: f64,
+	pub equity: f64,
+}
+
 /// The struct for keeping track of active players and their balances and inventories
-/// ClearingHouse is a HashMap indexed by each player's trader_id
+/// ClearingHouse is a HashMap indexed by each player's trader_id. It sits behind an RwLock so the tasks'
+/// lookups run concurrently and only updates to the players hold it exclusively
 pub struct ClearingHouse {
-	pub players: Mutex<HashMap<String, Box<dyn Player + Send>>>,
+	pub players: RwLock<HashMap<String, Box<dyn Player + Send + Sync>>>,
 	pub gas_fees: Mutex<Vec<f64>>,
 	pub total_tax: Mutex<f64>,
+	pub tax_breakdown: Mutex<HashMap<String, f64>>,
 	pub maker_profits: Mutex<Vec<f64>>,
+	pub fee_policies: Mutex<Vec<Box<dyn FeePolicy + Send>>>,
+	pub exchange_revenue: Mutex<f64>,
+	pub settlement: Settlement,
+	pub min_inventory: Mutex<Option<f64>>,
+	pub borrow_fees: Mutex<f64>,
+	pub max_position: Mutex<Option<f64>>,
+	pub max_order_size: Mutex<Option<f64>>,
+	pub budget_rule: Mutex<BudgetRule>,
+	pub defaulted: Mutex<Vec<String>>,
+	pub exited: Mutex<Vec<String>>,
+	pub entrants: Mutex<HashMap<String, (f64, f64)>>,
+	pub default_losses: Mutex<f64>,
+	pub interest_paid: Mutex<f64>,
+	pub carry_costs: Mutex<f64>,
+	pub dividends_paid: Mutex<f64>,
+	pub funding_paid: Mutex<f64>,
+	pub ledger: Ledger,
+	pub undo: UndoLog,
+	pub pnl: PnlTracker,
+	pub equity_curves: Mutex<HashMap<String, Vec<EquityPoint>>>,
+	pub observers: Observers,
 }
 
 
@@ -34,23 +81,274 @@ impl ClearingHouse {
 	/// Create a new ClearingHouse to store player data
 	pub fn new() -> Self {
 		ClearingHouse {
-			players: Mutex::new(HashMap::new()),
+			players: RwLock::new(HashMap::new()),
 			gas_fees: Mutex::new(Vec::<f64>::new()),	
 			total_tax: Mutex::new(0.0),
-			maker_profits: Mutex::new(vec![0.0, 0.0, 0.0]),
+			tax_breakdown: Mutex::new(HashMap::new()),
+			maker_profits: Mutex::new(vec![0.0; NUM_MAKER_TYPES]),
+			fee_policies: Mutex::new(Vec::new()),
+			exchange_revenue: Mutex::new(0.0),
+			settlement: Settlement::new(),
+			min_inventory: Mutex::new(None),
+			borrow_fees: Mutex::new(0.0),
+			max_position: Mutex::new(None),
+			max_order_size: Mutex::new(None),
+			budget_rule: Mutex::new(BudgetRule::Off),
+			defaulted: Mutex::new(Vec::new()),
+			exited: Mutex::new(Vec::new()),
+			entrants: Mutex::new(HashMap::new()),
+			default_losses: Mutex::new(0.0),
+			interest_paid: Mutex::new(0.0),
+			carry_costs: Mutex::new(0.0),
+			dividends_paid: Mutex::new(0.0),
+			funding_paid: Mutex::new(0.0),
+			ledger: Ledger::new(),
+			undo: UndoLog::new(),
+			pnl: PnlTracker::new(),
+			equity_curves: Mutex::new(HashMap::new()),
+			observers: Observers::new(),
+		}
+	}
+
+	/// Calls callback with every change made to a player's balance or inventory
+	pub fn add_observer(&self, callback: Box<dyn Fn(&PlayerEvent) + Send>) {
+		self.observers.add_callback(callback);
+	}
+
+	/// Returns a receiver that gets every change made to a player's balance or inventory
+	pub fn subscribe(&self) -> Receiver<PlayerEvent> {
+		self.observers.subscribe()
+	}
+
+	/// Sets the largest absolute position and order quantity a player may have, None is unlimited
+	pub fn set_risk_limits(&self, max_position: Option<f64>, max_order_size: Option<f64>) {
+		*self.max_position.lock().unwrap() = max_position;
+		*self.max_order_size.lock().unwrap() = max_order_size;
+	}
+
+	/// Sets whether orders beyond a player's free balance or inventory are admitted, rejected or scaled down
+	pub fn set_budget_rule(&self, rule: BudgetRule) {
+		*self.budget_rule.lock().unwrap() = rule;
+	}
+
+	/// Sets the inventory a player's open asks may not take them below, None allows unlimited shorting
+	pub fn set_min_inventory(&self, min_inventory: Option<f64>) {
+		*self.min_inventory.lock().unwrap() = min_inventory;
+	}
+
+	/// Sets the fee policies applied to fills, blocks and gas
+	pub fn set_fee_policies(&self, policies: Vec<Box<dyn FeePolicy + Send>>) {
+		*self.fee_policies.lock().unwrap() = policies;
+	}
+
+	pub fn add_fee_policy(&self, policy: Box<dyn FeePolicy + Send>) {
+		self.fee_policies.lock().unwrap().push(policy);
+	}
+
+	/// Taxes are counted towards the total tax and the policy's tax type, every other charge is exchange revenue
+	fn record_charge(&self, policy: &dyn FeePolicy, amt: f64) {
+		match policy.reason() {
+			UpdateReason::Tax => {
+				self.add_tax(amt);
+				*self.tax_breakdown.lock().unwrap().entry(policy.label()).or_insert(0.0) += amt;
+			},
+			_ => *self.exchange_revenue.lock().unwrap() += amt,
+		}
+	}
+
+	/// The tax collected of each type, e.g. Inventory, Turnover or Profit
+	pub fn get_tax_breakdown(&self) -> HashMap<String, f64> {
+		self.tax_breakdown.lock().unwrap().clone()
+	}
+
+	/// Charges both sides of a fill each policy's fill fees
+	pub fn apply_fees(&self, pu: &PlayerUpdate) {
+		let policies = self.fee_policies.lock().unwrap();
+		let mut players = self.players.write().unwrap();
+		for policy in policies.iter() {
+			for (id, fee) in policy.on_fill(pu) {
+				if fee == 0.0 {
+					continue;
+				}
+				if let Some(player) = players.get_mut(&id) {
+					player.update_bal(-fee);
+					self.record_charge(&**policy, fee);
+					log_player_data!(player.log_to_csv(policy.reason()));
+					self.record(&**player, policy.reason(), None, -fee, 0.0, vec![pu.payer_order_id, pu.vol_filler_order_id]);
+				}
+			}
 		}
 	}
 
+	/// Charges every player each policy's per-block fees
+	pub fn charge_block_fees(&self) {
+		let policies = self.fee_policies.lock().unwrap();
+		let mut players = self.players.write().unwrap();
+		for policy in policies.iter() {
+			for (_id, player) in players.iter_mut() {
+				let fee = policy.on_block(&**player);
+				if fee != 0.0 {
+					player.update_bal(-fee);
+					self.record_charge(&**policy, fee);
+					log_player_data!(player.log_to_csv(policy.reason()));
+					self.record(&**player, policy.reason(), None, -fee, 0.0, Vec::new());
+				}
+			}
+		}
+	}
+
+	pub fn get_exchange_revenue(&self) -> f64 {
+		*self.exchange_revenue.lock().unwrap()
+	}
+
+	/// The pre-trade risk checks an order must pass before it is admitted
+	fn pre_trade_check(&self, player: &dyn Player, order: &Order) -> Result<()> {
+		let checked = match order.order_type != OrderType::Cancel {
+			true if self.is_defaulted(&order.trader_id) => Err("Trader has defaulted"),
+			true if self.is_exited(&order.trader_id) => Err("Trader has exited"),
+			_ => self.check_limits(player, order)
+				.and_then(|_| self.check_short_sale(player, order))
+				.and_then(|_| self.check_budget(player, order)),
+		};
+		checked.map_err(|reason| Error::Rejected { trader_id: order.trader_id.clone(), order_id: order.order_id, reason })
+	}
+
+	/// Rejects an order larger than the max order size, or that would take the player's position
+	/// past ±max_position in its symbol once it and their other open orders on the same side fill.
+	fn check_limits(&self, player: &dyn Player, order: &Order) -> std::result::Result<(), &'static str> {
+		match (&order.order_type, player.get_player_type()) {
+			(_, TraderT::Miner) => return Ok(()),
+			(&OrderType::Enter, _) | (&OrderType::Update, _) => {},
+			_ => return Ok(()),
+		}
+		if let Some(max_size) = *self.max_order_size.lock().unwrap() {
+			if order.quantity > max_size {
+				return Err("Order exceeds the maximum order size");
+			}
+		}
+		let max_pos = match *self.max_position.lock().unwrap() {
+			Some(max_pos) => max_pos,
+			None => return Ok(()),
+		};
+		let open: f64 = player.copy_orders().iter()
+			.filter(|o| o.trade_type == order.trade_type && o.order_type != OrderType::Cancel && o.symbol == order.symbol)
+			.map(|o| o.quantity)
+			.sum();
+		let inv = player.get_inv(&order.symbol) + self.settlement.pending_position(&player.get_id(), &order.symbol).1;
+		let position = match order.trade_type {
+			TradeType::Bid => inv + open + order.quantity,
+			TradeType::Ask => inv - open - order.quantity,
+		};
+		if position.abs() > max_pos {
+			return Err("Order would breach the position limit");
+		}
+		Ok(())
+	}
+
+	/// Rejects an ask that would take the player below the minimum inventory of its symbol once
+	/// it and their other open asks fill. Unsettled shares count towards the inventory. The miner's
+	/// front-run orders are already in its frame so they are exempt.
+	fn check_short_sale(&self, player: &dyn Player, order: &Order) -> std::result::Result<(), &'static str> {
+		let min_inv = match *self.min_inventory.lock().unwrap() {
+			Some(min_inv) => min_inv,
+			None => return Ok(()),
+		};
+		match (&order.trade_type, &order.order_type, player.get_player_type()) {
+			(_, _, TraderT::Miner) => return Ok(()),
+			(&TradeType::Ask, &OrderType::Enter, _) | (&TradeType::Ask, &OrderType::Update, _) => {},
+			_ => return Ok(()),
+		}
+		let open_asks: f64 = player.copy_orders().iter()
+			.filter(|o| o.trade_type == TradeType::Ask && o.order_type != OrderType::Cancel && o.symbol == order.symbol)
+			.map(|o| o.quantity)
+			.sum();
+		let inv = player.get_inv(&order.symbol) + self.settlement.pending_position(&player.get_id(), &order.symbol).1;
+		if inv - open_asks - order.quantity < min_inv {
+			return Err("Order would breach the short-sale constraint");
+		}
+		Ok(())
+	}
+
+	/// The most shares the order could trade within the player's means, None when it isn't checked.
+	/// Bids are limited by the balance and unsettled cash left after their other open bids at the
+	/// highest price the order could pay, asks by the inventory and unsettled shares left after
+	/// their other open asks. Miners are exempt as with the other checks.
+	fn budget_room(&self, player: &dyn Player, order: &Order) -> Option<f64> {
+		match (&order.order_type, player.get_player_type()) {
+			(_, TraderT::Miner) => return None,
+			(&OrderType::Enter, _) | (&OrderType::Update, _) => {},
+			_ => return None,
+		}
+		let id = player.get_id();
+		let open: Vec<Order> = player.copy_orders().into_iter()
+			.filter(|o| o.trade_type == order.trade_type && o.order_type != OrderType::Cancel && o.order_id != order.order_id)
+			.collect();
+		let worst_price = |o: &Order| match o.ex_type {
+			ExchangeType::FlowOrder => o.p_high,
+			ExchangeType::LimitOrder => o.price,
+		};
+		match order.trade_type {
+			TradeType::Bid => {
+				let committed: f64 = open.iter().map(|o| o.quantity * worst_price(o)).sum();
+				let free = (player.get_bal() + self.settlement.pending_cash(&id) - committed).max(0.0);
+				match worst_price(order) > 0.0 {
+					true => Some(free / worst_price(order)),
+					false => None,
+				}
+			},
+			TradeType::Ask => {
+				let committed: f64 = open.iter().filter(|o| o.symbol == order.symbol).map(|o| o.quantity).sum();
+				let inv = player.get_inv(&order.symbol) + self.settlement.pending_position(&id, &order.symbol).1;
+				Some((inv - committed).max(0.0))
+			},
+		}
+	}
+
+	/// Rejects an order that doesn't fit the player's budget when the budget rule is on. Orders
+	/// scaled down by fit_to_budget already fit unless the player couldn't afford any of it.
+	fn check_budget(&self, player: &dyn Player, order: &Order) -> std::result::Result<(), &'static str> {
+		if *self.budget_rule.lock().unwrap() == BudgetRule::Off {
+			return Ok(());
+		}
+		match self.budget_room(player, order) {
+			Some(room) if order.quantity > room + 1e-9 || room <= 0.0 => match order.trade_type {
+				TradeType::Bid => Err("Order exceeds the player's budget"),
+				TradeType::Ask => Err("Order exceeds the player's deliverable inventory"),
+			},
+			_ => Ok(()),
+		}
+	}
+
+	/// Under BudgetRule::ScaleDown, cuts the order's quantity down to what the player can afford
+	/// or deliver. Any other rule returns it unchanged.
+	pub fn fit_to_budget(&self, mut order: Order) -> Order {
+		if *self.budget_rule.lock().unwrap() != BudgetRule::ScaleDown {
+			return order;
+		}
+		let players = self.players.read().unwrap();
+		let room = match players.get(&order.trader_id) {
+			Some(player) => self.budget_room(player.as_ref(), &order),
+			None => None,
+		};
+		if let Some(room) = room {
+			if order.quantity > room {
+				order.quantity = room;
+				order.u_max = order.u_max.min(room);
+			}
+		}
+		order
+	}
+
 
 	/// Register an investor to the ClearingHouse Hashmap
 	pub fn reg_investor(&self, inv: Investor) {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		players.entry(inv.trader_id.clone()).or_insert(Box::new(inv));
 	}
 
 	/// Register a vector of investors to the ClearingHouse Hashmap
 	pub fn reg_n_investors(&self, investors: Vec<Investor>) {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		for i in investors {
 			players.entry(i.trader_id.clone()).or_insert(Box::new(i));
 		}
@@ -58,66 +356,135 @@ impl ClearingHouse {
 
 	/// Register a maker to the ClearingHouse Hashmap
 	pub fn reg_maker(&self, maker: Maker) {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		players.entry(maker.trader_id.clone()).or_insert(Box::new(maker));
 	}
 
 	/// Register a vector of makers to the ClearingHouse Hashmap
 	pub fn reg_n_makers(&self, makers: Vec<Maker>) {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		for m in makers {
 			players.entry(m.trader_id.clone()).or_insert(Box::new(m));
 		}
 	}
 
+	/// Register an investor that entered part way through the run, recording their starting
+	/// balance and inventory for the results
+	pub fn enter_investor(&self, inv: Investor) {
+		self.entrants.lock().unwrap().insert(inv.trader_id.clone(), (inv.balance, inv.get_total_inv()));
+		self.reg_investor(inv);
+	}
+
+	/// Register a maker that entered part way through the run, recording their starting
+	/// balance and inventory for the results
+	pub fn enter_maker(&self, maker: Maker) {
+		self.entrants.lock().unwrap().insert(maker.trader_id.clone(), (maker.balance, maker.get_total_inv()));
+		self.reg_maker(maker);
+	}
+
+	/// The starting balance and inventory of every player that entered part way through the run
+	pub fn get_entrants(&self) -> HashMap<String, (f64, f64)> {
+		self.entrants.lock().unwrap().clone()
+	}
+
+	/// Withdraws the player from the market. Their resting orders are cancelled and they stop
+	/// being selected by the tasks or admitted new orders, but their positions stay on the books
+	/// until the end of the run. Returns the cancel orders to send to the MemPool.
+	pub fn retire_player(&self, id: &str) -> Vec<Order> {
+		let cancels = self.cancel_all_orders(id.to_string(), None).unwrap_or_default();
+		self.exited.lock().unwrap().push(id.to_string());
+		info!("Exit: {}", id);
+		cancels
+	}
+
+	pub fn is_exited(&self, trader_id: &str) -> bool {
+		self.exited.lock().unwrap().iter().any(|id| id == trader_id)
+	}
+
 	/// Register a miner to the ClearingHouse Hashmap
 	pub fn reg_miner(&self, miner: Miner) {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		players.entry(miner.trader_id.clone()).or_insert(Box::new(miner));
 	}
 
 
+	/// Calls f with a read-only view of the player, returning None if they aren't registered.
+	/// The players lock is held while f runs so f must not call back into the ClearingHouse.
+	pub fn with_player<F, R>(&self, id: &str, f: F) -> Option<R>
+	where F: FnOnce(&dyn Player) -> R
+	{
+		let players = self.players.read().unwrap();
+		match players.get(id) {
+			Some(player) => Some(f(&**player)),
+			None => None,
+		}
+	}
+
+	/// Returns a copy of the player's current state, leaving them registered
+	pub fn inspect_player(&self, id: &str) -> Option<PlayerSnapshot> {
+		self.with_player(id, |p| PlayerSnapshot::new(p))
+	}
+
+	/// Copies every player's state under a single lock acquisition
+	pub fn snapshot(&self) -> Vec<PlayerSnapshot> {
+		let players = self.players.read().unwrap();
+		players.values().map(|p| PlayerSnapshot::new(&**p)).collect()
+	}
+
 	// Gets a reference to the player by popping it from the hashmap
+	#[deprecated(note = "removes the player, use inspect_player or with_player to read their state")]
 	pub fn get_player(&self, id: String) -> Option<Box<dyn Player>> {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		match players.remove(&id) {
 			Some(player) => Some(player),
 			None => None,
 		}
 	}
 
-	// Gets the maker and generates a pair of orders based on supplied parameters 
-	pub fn maker_new_orders(&self, id: String, data: &PriorData, inference: &LikelihoodStats, dists: &Distributions, consts: &Constants) -> Option<(Order, Order)>{
-		let players = self.players.lock().unwrap();
-		match players.get(&id) {
-			Some(player) => {
-				if let Some(maker) = player.as_any().downcast_ref::<Maker>() {
-					// Was able to find the maker in the clearing house and cast Player object to Maker
-					let orders = maker.new_orders(data, inference, dists, consts);
-					return orders
-				} else {
-					// Couldn't downcast to maker
-					println!("Couldn't downcast to maker: {}", id);
-					return None;
+	/// Passes the data to the player's strategy and returns the orders it generates. A strategy's
+	/// script is run after the players lock is released, so a slow script doesn't hold up the house.
+	pub fn strategy_orders(&self, id: &str, data: &MarketData) -> Vec<Order> {
+		let script = {
+			let players = self.players.read().unwrap();
+			match players.get(id) {
+				Some(player) => {
+					player.on_data(data);
+					match player.script() {
+						Some(script) => script,
+						None => return player.generate_orders(data),
+					}
+				},
+				None => {
+					println!("Couldn't get player: {}", id);
+					return Vec::new();
 				}
-			},
-			None => {
-				println!("Couldn't get maker: {}", id);
-				return None;
 			}
-		} 
+		};
+		script_orders(id, script.as_ref(), data)
+	}
+
+	/// Starts block_num for every player's strategy
+	pub fn strategies_on_block(&self, block_num: u64) {
+		let players = self.players.read().unwrap();
+		for player in players.values() {
+			player.on_block(block_num);
+		}
 	}
 
 	// Gets the maker and cancels all of their enter orders in the clearing house
 	// returns a vector of all of their orders with the update OrderType = Cancel
-	// to be submitted to the mempool -> order books
-	pub fn cancel_all_orders(&self, id: String) -> Result<Vec<Order>, ()> {
-		let mut players = self.players.lock().unwrap();
+	// to be submitted to the mempool -> order books.
+	// If mass_cancel_gas is supplied, a single OrderType::MassCancel order is generated 
+	// per side and symbol instead of one cancel per order, each paying mass_cancel_gas.
+	pub fn cancel_all_orders(&self, id: String, mass_cancel_gas: Option<f64>) -> Result<Vec<Order>> {
+		let mut players = self.players.write().unwrap();
 		let mut orders = Vec::new();
 		match players.get_mut(&id) {
 			Some(player) => {
 				// Get the order ids of player's current enter orders
 				let order_ids = player.get_enter_order_ids();
+				// Sides and symbols that need a mass cancel message
+				let mut sides = Vec::<(TradeType, String)>::new();
 				for o_id in order_ids {
 					// Check if the player has already sent a cancel for this order id to the mempool
 					if player.check_double_cancel(o_id) {continue;}
@@ -125,66 +492,93 @@ impl ClearingHouse {
 					if let Ok(cancel_order) = player.gen_cancel_order(o_id) {
 						// Record this in the player's history of sent orders to avoid double cancels.
 						player.add_to_sent(o_id, cancel_order.order_type.clone());
-						orders.push(cancel_order);
+						if mass_cancel_gas.is_some() {
+							let side = (cancel_order.trade_type.clone(), cancel_order.symbol.clone());
+							if !sides.contains(&side) {
+								sides.push(side);
+							}
+						} else {
+							orders.push(cancel_order);
+						}
 					};
 				} 
+
+				if let Some(gas) = mass_cancel_gas {
+					for (side, symbol) in sides {
+						orders.push(Order::new(id.clone(), 
+											   OrderType::MassCancel, 
+											   side, 
+											   ExchangeType::LimitOrder, 
+											   0.0, 0.0, 0.0, 0.0, 0.0, 
+											   gas).with_symbol(&symbol));
+					}
+				}
 				Ok(orders)
 			},
-			None => {
-				println!("Couldn't get player to cancel orders: {}", id);
-				return Err(());
-			}
+			None => Err(Error::UnknownTrader { trader_id: id, op: "cancel_all_orders" }),
 		}
 	}
 
-	pub fn get_player_order_count(&self, id: &String) -> Result<usize, ()> {
-		let players = self.players.lock().unwrap();
+	/// Generates a cancel for one of the player's enter orders to be submitted to the mempool.
+	/// Orders that already have a cancel sent can't be cancelled again.
+	pub fn cancel_one_order(&self, id: String, order_id: u64) -> Result<Order> {
+		let mut players = self.players.write().unwrap();
+		match players.get_mut(&id) {
+			Some(player) => {
+				if player.check_double_cancel(order_id) {
+					return Err(Error::UnknownOrder { trader_id: id, order_id, op: "cancel_one_order" });
+				}
+				match player.gen_cancel_order(order_id) {
+					Ok(cancel_order) => {
+						player.add_to_sent(order_id, cancel_order.order_type.clone());
+						Ok(cancel_order)
+					},
+					Err(_) => Err(Error::UnknownOrder { trader_id: id, order_id, op: "cancel_one_order" }),
+				}
+			},
+			None => Err(Error::UnknownTrader { trader_id: id, op: "cancel_one_order" }),
+		}
+	}
+
+	pub fn get_player_order_count(&self, id: &String) -> Result<usize> {
+		let players = self.players.read().unwrap();
 		match players.get(id) {
 			Some(p) => Ok(p.num_orders()),
-			None => Err(()),
+			None => Err(Error::UnknownTrader { trader_id: id.clone(), op: "get_player_order_count" }),
 		}
 	}
 
-	pub fn get_type(&self, id: &String) -> Result<TraderT, ()> {
-		let players = self.players.lock().unwrap();
+	pub fn get_type(&self, id: &String) -> Result<TraderT> {
+		let players = self.players.read().unwrap();
 		match players.get(id) {
 			Some(p) => Ok(p.get_player_type()),
-			None => Err(()),
+			None => Err(Error::UnknownTrader { trader_id: id.clone(), op: "get_type" }),
 		}
 	}
 
+	/// Starts a query over the players that haven't defaulted or exited
+	pub fn query(&self) -> PlayerQuery {
+		PlayerQuery::new(self)
+	}
+
 	// Shuffles through the players matching the player_type and returns their id
 	pub fn get_rand_player_id(&self, player_type: TraderT) -> Option<String> {
-		let players = self.players.lock().unwrap();
-		let mut rng = thread_rng();
-		let mut _filtered: Vec<(_, _)> = players.iter().filter(|(_k, v)| v.get_player_type() == player_type).collect();
-		if let Some((id, _value)) = _filtered.choose(&mut rng) {
-			return Some(id.to_string());
-		} else {
-			return None
-		}
+		self.query().of_type(player_type).random()
 	}
 
 	// Returns all player id's for the specified player_type
 	pub fn get_filtered_ids(&self, player_type: TraderT) -> Vec<String> {
-		let mut ids = Vec::new();
-		let players = self.players.lock().unwrap();
-		let mut rng = thread_rng();
-		let filtered: Vec<(_, _)> = players.iter().filter(|(_k, v)| v.get_player_type() == player_type).collect();
-		for (id, _o) in filtered {
-			ids.push(id.clone());
-		}
-		ids.shuffle(&mut rng);
-		ids
+		self.query().of_type(player_type).shuffled()
 	}
 
 
 	/// Adds to the player's balance and returns their updated balance
 	pub fn update_player_bal(&self, id: String, bal_to_add: f64) -> Option<f64> {
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		match players.get_mut(&id) {
 			Some(player) => { 
 				player.update_bal(bal_to_add);
+				self.record(&**player, UpdateReason::Adjust, None, bal_to_add, 0.0, Vec::new());
 				Some(player.get_bal())
 			}
 			None => None,
@@ -192,28 +586,112 @@ impl ClearingHouse {
 	}
 
 
-	/// Adds to the player's inventory and returns their updated inventory
-	pub fn update_player_inv(&self, id: String, inv_to_add: f64) -> Option<f64> {
-		let mut players = self.players.lock().unwrap();
+	/// Moves cash and shares of symbol from one player to another under a single lock so no task
+	/// sees one side updated without the other. Neither player is changed if either is missing.
+	pub fn transfer(&self, from: &str, to: &str, symbol: &str, cash: f64, inventory: f64, reason: UpdateReason) -> Result<()> {
+		let mut players = self.players.write().unwrap();
+		for id in &[from, to] {
+			if !players.contains_key(*id) {
+				return Err(Error::UnknownTrader { trader_id: id.to_string(), op: "transfer" });
+			}
+		}
+		let traded = match inventory != 0.0 {
+			true => Some(symbol),
+			false => None,
+		};
+		for (id, sign) in vec![(from, -1.0), (to, 1.0)] {
+			let player = players.get_mut(id).expect("transfer");
+			player.update_bal(sign * cash);
+			player.update_inv(symbol, sign * inventory);
+			log_player_data!(player.log_to_csv(reason));
+			self.record(&**player, reason, traded, sign * cash, sign * inventory, Vec::new());
+		}
+		Ok(())
+	}
+
+	/// Applies a fill's cash and shares of symbol to the player. order_ids are the orders on both
+	/// sides of the fill. With a settlement lag the fill becomes an obligation instead and the
+	/// player's current state is returned.
+	pub fn transact(&self, id: String, symbol: &str, bal_to_add: f64, inv_to_add: f64, order_ids: Vec<u64>) -> Option<(f64, f64)> {
+		// PnL is attributed when the trade executes rather than when it settles
+		if inv_to_add != 0.0 {
+			self.pnl.fill(&id, symbol, inv_to_add, -bal_to_add / inv_to_add);
+		}
+		if !self.settlement.is_deferred() {
+			return self.update_player(id, symbol, bal_to_add, inv_to_add, UpdateReason::Transact, order_ids);
+		}
+		let state = self.get_bal_inv(id.clone(), symbol);
+		if state.is_some() {
+			self.settlement.add(id, symbol, bal_to_add, inv_to_add, order_ids);
+		}
+		state
+	}
+
+	/// Settles the obligations due by block_num, later changes are recorded against block_num
+	pub fn settle(&self, block_num: u64) {
+		self.ledger.set_block(block_num);
+		let netted = self.settlement.take_due(block_num);
+		self.apply_settlement(netted, block_num);
+	}
+
+	/// Settles every pending obligation regardless of when it is due
+	pub fn settle_all(&self) {
+		let block_num = *self.settlement.block_num.lock().unwrap();
+		let netted = self.settlement.take_all();
+		self.apply_settlement(netted, block_num);
+	}
+
+	/// Applies each trader's net obligations, recording a failure if the trader can't
+	/// cover them. Failed obligations are still applied, leaving the shortfall negative.
+	fn apply_settlement(&self, netted: HashMap<(String, String), (f64, f64, Vec<u64>)>, block_num: u64) {
+		for ((id, symbol), (cash, shares, order_ids)) in netted {
+			if let Some((bal, inv)) = self.get_bal_inv(id.clone(), &symbol) {
+				let cash_short = match cash < 0.0 && bal + cash < 0.0 {
+					true => -(bal + cash).max(cash),
+					false => 0.0,
+				};
+				let shares_short = match shares < 0.0 && inv + shares < 0.0 {
+					true => -(inv + shares).max(shares),
+					false => 0.0,
+				};
+				if cash_short > 0.0 || shares_short > 0.0 {
+					println!("Settlement failure for {}: cash short {}, shares short {}", id, cash_short, shares_short);
+					self.settlement.record_failure(SettlementFailure {
+						trader_id: id.clone(),
+						block_num,
+						cash_short,
+						shares_short,
+					});
+				}
+			}
+			self.update_player(id, &symbol, cash, shares, UpdateReason::Transact, order_ids);
+		}
+	}
+
+	/// Adds to the player's inventory of symbol and returns their updated inventory
+	pub fn update_player_inv(&self, id: String, symbol: &str, inv_to_add: f64) -> Option<f64> {
+		let mut players = self.players.write().unwrap();
 		match players.get_mut(&id) {
 			Some(player) => { 
-				player.update_inv(inv_to_add);
-				Some(player.get_inv())
+				player.update_inv(symbol, inv_to_add);
+				self.record(&**player, UpdateReason::Adjust, Some(symbol), 0.0, inv_to_add, Vec::new());
+				Some(player.get_inv(symbol))
 			}
 			None => None,
 		}
 	}
 
 
-	/// Updates both a single player's balance and inventory
+	/// Updates both a single player's balance and inventory of symbol
 	/// Returns tuple Option<(updated_bal: f64, updated_inv: f64)>
-	pub fn update_player(&self, id: String, bal_to_add: f64, inv_to_add: f64, reason: UpdateReason) -> Option<(f64, f64)>{
-		let mut players = self.players.lock().unwrap();
+	pub fn update_player(&self, id: String, symbol: &str, bal_to_add: f64, inv_to_add: f64, reason: UpdateReason, order_ids: Vec<u64>) -> Option<(f64, f64)>{
+		let mut players = self.players.write().unwrap();
 		match players.get_mut(&id) {
 			Some(player) => { 
-				player.update_inv(inv_to_add);
+				player.update_inv(symbol, inv_to_add);
 				player.update_bal(bal_to_add);
 				log_player_data!(player.log_to_csv(reason));
+				self.record(&**player, reason, Some(symbol), bal_to_add, inv_to_add, order_ids);
 
 				// Track the updates to specific maker types
 				if player.get_player_type() == TraderT::Maker {
@@ -231,21 +709,31 @@ impl ClearingHouse {
 								let mut maker_profits = self.maker_profits.lock().unwrap();
 								maker_profits[MakerT::Random as usize] += bal_to_add;
 							},
+							MakerT::InventorySkew => {
+								let mut maker_profits = self.maker_profits.lock().unwrap();
+								maker_profits[MakerT::InventorySkew as usize] += bal_to_add;
+							},
+							MakerT::AdverseSelection => {
+								let mut maker_profits = self.maker_profits.lock().unwrap();
+								maker_profits[MakerT::AdverseSelection as usize] += bal_to_add;
+							},
 						}
 					}
 				}
-				Some((player.get_bal(), player.get_inv()))
+				Some((player.get_bal(), player.get_inv(symbol)))
 			}
 			None => None,
 		}
 	}	
 
-	// Get count of each type of maker (aggressive, riskaverse, random)
-	pub fn get_maker_counts(&self) -> (i64, i64, i64) {
-		let players = self.players.lock().unwrap();
+	// Get count of each type of maker (aggressive, riskaverse, random, inventory skew, adverse selection)
+	pub fn get_maker_counts(&self) -> (i64, i64, i64, i64, i64) {
+		let players = self.players.read().unwrap();
 		let mut num_agg = 0;
 		let mut num_riska = 0;
 		let mut num_rand = 0;
+		let mut num_skew = 0;
+		let mut num_gm = 0;
 		for (_k, player) in players.iter() {
 			if player.get_player_type() == TraderT::Maker {
 				if let Some(maker) = player.as_any().downcast_ref::<Maker>() {
@@ -259,36 +747,155 @@ impl ClearingHouse {
 						MakerT::Random => {
 							num_rand += 1;
 						},
+						MakerT::InventorySkew => {
+							num_skew += 1;
+						},
+						MakerT::AdverseSelection => {
+							num_gm += 1;
+						},
 					}
 				}
 			}
 		}
-		(num_agg, num_riska, num_rand)
+		(num_agg, num_riska, num_rand, num_skew, num_gm)
+	}
+
+	// Get each maker's type, quoting parameters, balance and total inventory
+	pub fn get_maker_params(&self) -> Vec<(String, MakerT, MakerParams, f64, f64)> {
+		let players = self.players.read().unwrap();
+		let mut params = Vec::new();
+		for (id, player) in players.iter() {
+			if let Some((maker_type, maker_params)) = player.quoting() {
+				params.push((id.clone(), maker_type, maker_params, player.get_bal(), player.get_total_inv()));
+			}
+		}
+		params
+	}
+
+	// Replaces a maker's quoting parameters
+	pub fn set_maker_params(&self, id: &str, params: MakerParams) -> Result<()> {
+		let mut players = self.players.write().unwrap();
+		match players.get_mut(id).map(|player| player.set_quoting_params(params)) {
+			Some(true) => Ok(()),
+			_ => Err(Error::UnknownTrader { trader_id: id.to_string(), op: "set_maker_params" }),
+		}
 	}
 
-	pub fn get_bal_inv(&self, id: String) -> Option<(f64, f64)> {
-		let players = self.players.lock().unwrap();
+	pub fn get_bal_inv(&self, id: String, symbol: &str) -> Option<(f64, f64)> {
+		let players = self.players.read().unwrap();
 		match players.get(&id) {
 			Some(player) => { 
-				Some((player.get_bal(), player.get_inv()))
+				Some((player.get_bal(), player.get_inv(symbol)))
 			}
 			None => None,
 		}
 	}
 
 	/// Gets the TradeResults from an auction and updates each player
-	pub fn update_house(&self, results: TradeResults) {
+	pub fn update_house(&self, results: TradeResults) -> Result<()> {
+		for event in results.order_events.iter() {
+			self.route_order_event(event.clone());
+		}
 		match results.auction_type {
 			MarketType::CDA => self.cda_cross_update(results),
-			MarketType::FBA => self.fba_batch_update(results),
+			MarketType::FBA|MarketType::SealedBid => self.fba_batch_update(results),
 			MarketType::KLF => self.flow_batch_update(results),
+			MarketType::AMM => self.amm_swap_update(results),
+			MarketType::Dutch => self.dutch_fill_update(results),
+		}
+	}
+
+	/// Sends an ack or reject back to the trader that sent the order
+	pub fn route_order_event(&self, event: OrderEvent) {
+		let players = self.players.read().unwrap();
+		match players.get(event.trader_id()) {
+			Some(player) => player.recv_order_event(event),
+			None => println!("Couldn't find trader {} to route order event", event.trader_id()),
+		}
+	}
+
+	/// Returns the player's position in the symbol
+	pub fn get_symbol_inventory(&self, trader_id: &str, symbol: &str) -> f64 {
+		let players = self.players.read().unwrap();
+		match players.get(trader_id) {
+			Some(player) => player.get_inv(symbol),
+			None => 0.0,
+		}
+	}
+
+	/// Consumes the results of swaps against the AMM pool. The pool isn't a player
+	/// so only the trader's side of each swap is updated.
+	pub fn amm_swap_update(&self, results: TradeResults) -> Result<()> {
+		match results.cross_results {
+			None => return Ok(()),
+			Some(player_updates) => {
+				for pu in player_updates {
+					if pu.cancel == true {
+						// Swaps are immediate-or-cancel so the order is always removed
+						match self.cancel_player_order(pu.payer_id, pu.payer_order_id) {
+							Ok(()) => {},
+							Err(e) => println!("amm_swap_update: {:?}, {}", e, pu.payer_order_id),
+						}
+						continue;
+					}
+
+					let volume = pu.volume;
+					let payment = pu.price * volume;
+					// The trader is whichever side of the swap isn't the pool
+					let (trader_id, bal_change, inv_change) = match pu.payer_id == AMM_POOL_ID {
+						true => (pu.vol_filler_id, payment, -volume),
+						false => (pu.payer_id, -payment, volume),
+					};
+					if let Some((new_bal, new_inv)) = self.transact(trader_id.clone(), &results.symbol, bal_change, inv_change, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
+						println!("Updated {}. bal=>{}, inv=>{}", trader_id.clone(), new_bal, new_inv);
+					} else {
+						self.report_player(trader_id.clone());
+						return Err(Error::UnknownTrader { trader_id, op: "amm_swap_update" });
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Consumes the cancels and fills from a Dutch auction block. Only the bidders are
+	/// players, the issuer's side of each fill is not tracked.
+	pub fn dutch_fill_update(&self, results: TradeResults) -> Result<()> {
+		match results.cross_results {
+			None => return Ok(()),
+			Some(player_updates) => {
+				for pu in player_updates {
+					if pu.cancel == true {
+						// Cancel the player's order in the clearing house
+						match self.cancel_player_order(pu.payer_id, pu.payer_order_id) {
+							Ok(()) => {},
+							Err(e) => println!("dutch_fill_update: {:?}, {}", e, pu.payer_order_id),
+						}
+						continue;
+					}
+
+					let bidder_id = pu.payer_id;
+					let volume = pu.volume;
+					let payment = pu.price * volume;
+					if let Some((new_bal, new_inv)) = self.transact(bidder_id.clone(), &results.symbol, -payment, volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
+						println!("Updated {}. bal=>{}, inv=>{}", bidder_id.clone(), new_bal, new_inv);
+					} else {
+						self.report_player(bidder_id.clone());
+						return Err(Error::UnknownTrader { trader_id: bidder_id, op: "dutch_fill_update" });
+					}
+
+					// The filled volume was removed from the bid in the book
+					self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume)?;
+				}
+			}
 		}
+		Ok(())
 	}
 
 	/// Consumes the trade results from CDA limit order cross to update each player's state
-	pub fn cda_cross_update(&self, results: TradeResults) {
+	pub fn cda_cross_update(&self, results: TradeResults) -> Result<()> {
 		match results.cross_results {
-			None => return,
+			None => return Ok(()),
 			Some(player_updates) => {
 				for pu in player_updates {
 					if pu.cancel == true {
@@ -300,6 +907,8 @@ impl ClearingHouse {
 						continue;
 					}
 
+					self.apply_fees(&pu);
+
 					// Update bidder: -bal, +inv
 					let bidder_id = pu.payer_id;
 					let volume = pu.volume;
@@ -308,36 +917,37 @@ impl ClearingHouse {
 						continue;
 					}
 					let payment = pu.price * volume;
-					if let Some((new_bal, new_inv)) = self.update_player(bidder_id.clone(), -payment, volume, UpdateReason::Transact) {
+					if let Some((new_bal, new_inv)) = self.transact(bidder_id.clone(), &results.symbol, -payment, volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 						println!("Updated {}. bal=>{}, inv=>{}", bidder_id.clone(), new_bal, new_inv);
 					} else {
 						self.report_player(bidder_id.clone());
-						panic!("failed to update {}'s balance/inventory", bidder_id);
+						return Err(Error::UnknownTrader { trader_id: bidder_id, op: "cda_cross_update" });
 					}
 
 					// NOTE: in CDA, the order's volume in orderbook is implicitly modified during crossing
-					self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume).expect("Failed to update");
+					self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume)?;
 
 					// Update asker: +bal, -inv
 					let asker_id = pu.vol_filler_id;
-					if let Some((new_bal, new_inv)) = self.update_player(asker_id.clone(), payment, -volume, UpdateReason::Transact) {
+					if let Some((new_bal, new_inv)) = self.transact(asker_id.clone(), &results.symbol, payment, -volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 							println!("Updated {}. bal=>{}, inv=>{}", asker_id.clone(), new_bal, new_inv);
 					} else {
 						self.report_player(asker_id.clone());
-						panic!("failed to update {}'s balance/inventory", asker_id);
+						return Err(Error::UnknownTrader { trader_id: asker_id, op: "cda_cross_update" });
 					}
 
 					// NOTE: in CDA, the order's volume in orderbook is implicitly modified during crossing
-					self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume).expect("Failed to update");
+					self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume)?;
 				}
 			}
 		}
+		Ok(())
 	}
 
 	/// Consumes the trade results to update each player's state
-	pub fn fba_batch_update(&self, results: TradeResults) {
+	pub fn fba_batch_update(&self, results: TradeResults) -> Result<()> {
 		match results.cross_results {
-			None => return,
+			None => return Ok(()),
 			Some(player_updates) => {
 				for pu in player_updates {
 					if pu.cancel == true {
@@ -348,6 +958,7 @@ impl ClearingHouse {
 						}
 						continue;
 					}
+					self.apply_fees(&pu);
 					// Update bidder: -bal, +inv
 					let bidder_id = pu.payer_id;
 					let volume = pu.volume;
@@ -356,36 +967,37 @@ impl ClearingHouse {
 						continue;
 					}
 					let payment = pu.price * volume;
-					if let Some((new_bal, new_inv)) = self.update_player(bidder_id.clone(), -payment, volume, UpdateReason::Transact) {
+					if let Some((new_bal, new_inv)) = self.transact(bidder_id.clone(), &results.symbol, -payment, volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 						println!("Updated {}. bal=>{}, inv=>{}", bidder_id.clone(), new_bal, new_inv);
 					} else {
-						panic!("failed to update {}'s balance/inventory", bidder_id);
+						return Err(Error::UnknownTrader { trader_id: bidder_id, op: "fba_batch_update" });
 					}
 
 					// Subtract interest from the bidder's order in the clearing house
-					self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume).expect("Failed to update");
+					self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume)?;
 
 					// Update asker: +bal, -inv
 					let asker_id = pu.vol_filler_id;
-					if let Some((new_bal, new_inv)) = self.update_player(asker_id.clone(), payment, -volume, UpdateReason::Transact) {
+					if let Some((new_bal, new_inv)) = self.transact(asker_id.clone(), &results.symbol, payment, -volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 							println!("Updated {}. bal=>{}, inv=>{}", asker_id.clone(), new_bal, new_inv);
 					} else {
-						panic!("failed to update {}'s balance/inventory", bidder_id);
+						return Err(Error::UnknownTrader { trader_id: asker_id, op: "fba_batch_update" });
 					}
 
 					// Subtract interest from the asker's order
-					self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume).expect("Failed to update");
+					self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume)?;
 				}
 			}
 		}
+		Ok(())
 	}
 
 	/// Given the clearing price of the last batch, updates every involved player's state
 	// For every order that was in the order book at auction time, 
 	// Calculate player.demand(price) or player.supply(price)
-	pub fn flow_batch_update(&self, results: TradeResults) {
+	pub fn flow_batch_update(&self, results: TradeResults) -> Result<()> {
 		match results.uniform_price {
-			None => return,
+			None => return Ok(()),
 			Some(_clearing_price) => {
 				if let Some(player_updates) = results.cross_results {
 					let id_check = format!("N/A");
@@ -405,65 +1017,68 @@ impl ClearingHouse {
 						if pu.payer_id == id_check {
 							// Update asker: +bal, -inv
 							let asker_id = pu.vol_filler_id;
-							if let Some((_new_bal, _new_inv)) = self.update_player(asker_id.clone(), payment, -volume, UpdateReason::Transact) {
+							if let Some((_new_bal, _new_inv)) = self.transact(asker_id.clone(), &results.symbol, payment, -volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 								// println!("Updated {}. bal=>{}, inv=>{}", asker_id.clone(), _new_bal, _new_inv);
 							}
 							// Subtract vol from the trader's order
-							self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume).expect("Failed to update");
+							self.update_player_order_vol(asker_id.clone(), pu.vol_filler_order_id, -volume)?;
 						} 
 						// This was a bid order, update accordingly
 						else {
 							// Update bidder: -bal, +inv
 							let bidder_id = pu.payer_id;
 							
-							if let Some((_new_bal, _new_inv)) = self.update_player(bidder_id.clone(), -payment, volume, UpdateReason::Transact) {
+							if let Some((_new_bal, _new_inv)) = self.transact(bidder_id.clone(), &results.symbol, -payment, volume, vec![pu.payer_order_id, pu.vol_filler_order_id]) {
 								// println!("Updated {}. bal=>{}, inv=>{}", bidder_id.clone(), _new_bal, _new_inv);
 							}
 
 							// Subtract vol from the trader's order
-							self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume).expect("Failed to update");
+							self.update_player_order_vol(bidder_id.clone(), pu.payer_order_id, -volume)?;
 						}
 					}
 						
 				} else {
 					// No cross results, exit
-					return;
+					return Ok(());
 				}
 			}
 		}
+		Ok(())
 	}
 
 	
 	/// Add a new order to the HashMap indexed by the player's id
-	pub fn new_order(&self, order: Order) -> Result<(), &'static str> {
-		let mut players = self.players.lock().unwrap();
+	pub fn new_order(&self, order: Order) -> Result<()> {
+		let mut players = self.players.write().unwrap();
 		// Find the player by trader id and add their order
 		match players.get_mut(&order.trader_id) {
 			Some(player) => { 
+				self.pre_trade_check(player.as_ref(), &order)?;
 				player.add_order(order);
 				Ok(())
 			}
-			None => Err("Couldn't find trader to add order")
+			None => Err(Error::UnknownTrader { trader_id: order.trader_id, op: "new_order" }),
 		}
 	}
 
 	/// Add a vector of new orders to the HashMap. This is preferable to new_order
 	/// as the mutex lock only has to be acquired once.
-	pub fn new_orders(&self, orders: Vec<Order>) -> Result<(), &'static str> {
-		let mut players = self.players.lock().unwrap();
+	pub fn new_orders(&self, orders: Vec<Order>) -> Result<()> {
+		let mut players = self.players.write().unwrap();
 		for order in orders {
 			match players.get_mut(&order.trader_id) {
 				Some(player) => { 
+					self.pre_trade_check(player.as_ref(), &order)?;
 					player.add_order(order);
 				}
-				None => return Err("Couldn't find trader to add order"),
+				None => return Err(Error::UnknownTrader { trader_id: order.trader_id, op: "new_orders" }),
 			}
 		}
 		Ok(())
 	}
 
 	/// Replaces a trader's order in the HashMap with the supplied 'order' 
-	pub fn update_player_order(&self, order: Order) -> Result<(), &'static str> {
+	pub fn update_player_order(&self, order: Order) -> Result<()> {
 		match self.cancel_player_order(order.trader_id.clone(), order.order_id) {
 			Ok(()) => {
 				self.new_order(order)
@@ -478,43 +1093,72 @@ impl ClearingHouse {
 
 	/// Adds volume to a trader's order to reflect changes in the order book. 
 	/// If they updated volume <=0, the order is dropped from the player's list
-	pub fn update_player_order_vol(&self, trader_id: String, order_id: u64, vol_to_add: f64) -> Result<(), &'static str> {
+	pub fn update_player_order_vol(&self, trader_id: String, order_id: u64, vol_to_add: f64) -> Result<()> {
 		// println!("Updating {}'s order {} volume by {}", trader_id, order_id, vol_to_add);
 		// self.report_player(trader_id.clone());
-		let mut players = self.players.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		if let Some(player) = players.get_mut(&trader_id) {
 			player.update_order_vol(order_id, vol_to_add)
+				.map_err(|_| Error::UnknownOrder { trader_id: trader_id.clone(), order_id, op: "update_player_order_vol" })
 		} else {
-			return Err("Couldn't find trader to add order");
+			return Err(Error::UnknownTrader { trader_id, op: "update_player_order_vol" });
 		}
 	}
 
 	/// Cancel's a trader's order in the HashMap with the supplied 'order'
-	pub fn cancel_player_order(&self, trader_id: String, order_id: u64) -> Result<(), &str> {
-		let mut players = self.players.lock().unwrap();
+	pub fn cancel_player_order(&self, trader_id: String, order_id: u64) -> Result<()> {
+		let mut players = self.players.write().unwrap();
 		if let Some(player) = players.get_mut(&trader_id) {
 			let res = player.cancel_order(order_id);
 				match res {
 					Ok(_) => return Ok(()),
-					Err(e) => return Err(e),
+					Err(_) => return Err(Error::UnknownOrder { trader_id, order_id, op: "cancel_player_order" }),
 				}
 		} else {
-			return Err("Couldn't find trader to cancel order");
+			return Err(Error::UnknownTrader { trader_id, op: "cancel_player_order" });
+		}
+	}
+
+	/// Cancel's a trader's order in the HashMap referenced by the trader's own client_order_id
+	/// Raises the gas of the player's order after it was replaced in the mempool
+	pub fn replace_order_gas(&self, trader_id: String, order_id: u64, gas: f64) -> Result<()> {
+		let mut players = self.players.write().unwrap();
+		match players.get_mut(&trader_id) {
+			Some(player) => player.replace_order_gas(order_id, gas)
+				.map_err(|_| Error::UnknownOrder { trader_id, order_id, op: "replace_order_gas" }),
+			None => Err(Error::UnknownTrader { trader_id, op: "replace_order_gas" }),
+		}
+	}
+
+	pub fn cancel_player_order_by_client_id(&self, trader_id: String, client_id: u64) -> Result<()> {
+		let mut players = self.players.write().unwrap();
+		if let Some(player) = players.get_mut(&trader_id) {
+			// Resolve the client id to the global order id within this trader's namespace
+			let order_id = player.copy_orders().iter()
+				.find(|o| o.client_order_id == Some(client_id))
+				.map(|o| o.order_id);
+			match order_id {
+				Some(o_id) => player.cancel_order(o_id)
+					.map_err(|_| Error::UnknownOrder { trader_id: trader_id.clone(), order_id: o_id, op: "cancel_player_order_by_client_id" }),
+				None => Err(Error::UnknownClientOrder { trader_id: trader_id.clone(), client_id, op: "cancel_player_order_by_client_id" }),
+			}
+		} else {
+			return Err(Error::UnknownTrader { trader_id, op: "cancel_player_order_by_client_id" });
 		}
 	}
 
 	/// Removes the player from the ClearingHouse HashMap
 	pub fn del_player(&self, trader_id: String) -> Option<()>{
-		match self.players.lock().unwrap().remove(&trader_id) {
+		match self.players.write().unwrap().remove(&trader_id) {
 			Some(_p) => Some(()),
 			None => None
 		}
 	}
 
 	pub fn report_player(&self, trader_id: String) {
-		let players = self.players.lock().unwrap();
+		let players = self.players.read().unwrap();
 		if let Some(p) = players.get(&trader_id) {
-			println!("id={}, bal={}, inv={}, orders={:?}", p.get_id(), p.get_bal(), p.get_inv(), p.copy_orders());
+			println!("id={}, bal={}, inv={:?}, orders={:?}", p.get_id(), p.get_bal(), p.get_positions(), p.copy_orders());
 		} else {
 			println!("Couldn't report on {}", trader_id);
 		}
@@ -522,12 +1166,12 @@ impl ClearingHouse {
 
 	/// Utility function for seeing how many Trader's are currently active
 	pub fn num_players(&self) -> usize {
-		self.players.lock().unwrap().len()
+		self.players.read().unwrap().len()
 	}
 
 	/// Utility function for seeing how many orders are currently active (not nec in order book)
 	pub fn orders_in_house(&self) -> usize {
-		let players = self.players.lock().unwrap();
+		let players = self.players.read().unwrap();
 		let mut sum = 0;
 		for (_id, p) in players.iter() {
 			sum += p.num_orders();
@@ -535,15 +1179,22 @@ impl ClearingHouse {
 		sum
 	}
 
+	/// The ids of every order the players have open, whether waiting to be mined or resting in a book
+	pub fn open_order_ids(&self) -> HashSet<u64> {
+		let players = self.players.read().unwrap();
+		players.values().flat_map(|p| p.copy_orders()).map(|o| o.order_id).collect()
+	}
+
 	// Updates the cummulative gas fees from the simulation, as well subtracts the
-	// gas fees from each player's balance
+	// gas fees and any policy surcharges on them from each player's balance
 	pub fn apply_gas_fees(&self, to_change: Vec<(String, f64)>, total: f64) {
 		{
 			// Add the gas fees for this batch
 			self.gas_fees.lock().expect("apply_gas_fees").push(total);
 		}
 
-		let mut players = self.players.lock().unwrap();
+		let policies = self.fee_policies.lock().unwrap();
+		let mut players = self.players.write().unwrap();
 		for c in to_change {
 			// Search for c.0 = trader_id, subtract c.1 = gas fee
 			match players.get_mut(&c.0) {
@@ -552,6 +1203,16 @@ impl ClearingHouse {
 					player.update_bal(-c.1);
 					// println!("{}, gas:{} before: {}, after: {}\n", c.0, c.1, _bef, player.get_bal());
 					log_player_data!(player.log_to_csv(UpdateReason::Gas));
+					self.record(&**player, UpdateReason::Gas, None, -c.1, 0.0, Vec::new());
+					for policy in policies.iter() {
+						let surcharge = policy.on_gas(&c.0, c.1);
+						if surcharge != 0.0 {
+							player.update_bal(-surcharge);
+							self.record_charge(&**policy, surcharge);
+							log_player_data!(player.log_to_csv(policy.reason()));
+							self.record(&**player, policy.reason(), None, -surcharge, 0.0, Vec::new());
+						}
+					}
 				}
 				None => {},
 			}
@@ -564,47 +1225,425 @@ impl ClearingHouse {
 	}
 
 
-	// Mulitplies all maker's current inv by the tax and subtracts that amount from their player bal
-	pub fn tax_makers(&self, tax: f64) {
-		let ids = self.get_filtered_ids(TraderT::Maker);
-		let mut players = self.players.lock().unwrap();
-		for id in ids {
-			match players.get_mut(&id) {
-				Some(player) => { 
-					let _bef = player.get_bal();
-					let tax_amt = (player.get_inv() * tax).abs();
-					player.update_bal(-tax_amt);
-					self.add_tax(tax_amt);
-					// println!("{} tax:{}, before: {}, after: {}\n", id, tax_amt, _bef, player.get_bal());
-					log_player_data!(player.log_to_csv(UpdateReason::Tax));
+	// Charges every player holding negative inventory the borrow fee per share shorted in each symbol
+	pub fn charge_borrow_fees(&self, fee: f64) {
+		if fee == 0.0 {
+			return;
+		}
+		let mut players = self.players.write().unwrap();
+		let mut total = self.borrow_fees.lock().unwrap();
+		for (_id, player) in players.iter_mut() {
+			let shorted: f64 = player.get_positions().values().filter(|inv| **inv < 0.0).sum();
+			if shorted < 0.0 {
+				let fee_amt = (shorted * fee).abs();
+				player.update_bal(-fee_amt);
+				*total += fee_amt;
+				log_player_data!(player.log_to_csv(UpdateReason::Tax));
+				self.record(&**player, UpdateReason::Tax, None, -fee_amt, 0.0, Vec::new());
+			}
+		}
+	}
+
+	pub fn get_borrow_fees(&self) -> f64 {
+		*self.borrow_fees.lock().unwrap()
+	}
+
+	/// Accrues blocks worth of interest on every player's balance, compounded per block. Positive
+	/// balances earn it and negative balances pay it. If carry is set each share held, long or
+	/// short, also costs carry per block.
+	pub fn accrue_interest(&self, rate: f64, carry: Option<f64>, blocks: u64) {
+		if blocks == 0 {
+			return;
+		}
+		let mut players = self.players.write().unwrap();
+		let mut interest_paid = self.interest_paid.lock().unwrap();
+		let mut carry_costs = self.carry_costs.lock().unwrap();
+		for (_id, player) in players.iter_mut() {
+			if rate != 0.0 && player.get_bal() != 0.0 {
+				let interest = player.get_bal() * ((1.0 + rate).powi(blocks as i32) - 1.0);
+				player.update_bal(interest);
+				*interest_paid += interest;
+				log_player_data!(player.log_to_csv(UpdateReason::Interest));
+				self.record(&**player, UpdateReason::Interest, None, interest, 0.0, Vec::new());
+			}
+			if let Some(carry) = carry {
+				let held: f64 = player.get_positions().values().map(|inv| inv.abs()).sum();
+				if held > 0.0 {
+					let cost = held * carry * blocks as f64;
+					player.update_bal(-cost);
+					*carry_costs += cost;
+					log_player_data!(player.log_to_csv(UpdateReason::Carry));
+					self.record(&**player, UpdateReason::Carry, None, -cost, 0.0, Vec::new());
 				}
-				None => {},
 			}
 		}
 	}
 
+	/// The net interest paid to players, negative if they paid more than they earned
+	pub fn get_interest_paid(&self) -> f64 {
+		*self.interest_paid.lock().unwrap()
+	}
+
+	pub fn get_carry_costs(&self) -> f64 {
+		*self.carry_costs.lock().unwrap()
+	}
+
+	/// Pays every player the dividend per share of each symbol they hold, short positions pay it
+	pub fn pay_dividends(&self, per_share: &HashMap<String, f64>) {
+		let paid = self.pay_per_share(per_share, UpdateReason::Dividend);
+		*self.dividends_paid.lock().unwrap() += paid;
+	}
+
+	/// Pays every player the funding per share of each symbol they hold, short positions pay it.
+	/// A negative amount is paid by the longs to the shorts.
+	pub fn pay_funding(&self, per_share: &HashMap<String, f64>) {
+		let paid = self.pay_per_share(per_share, UpdateReason::Funding);
+		*self.funding_paid.lock().unwrap() += paid;
+	}
+
+	// Pays each player amount * inventory for every symbol with an amount, returning the net paid
+	fn pay_per_share(&self, per_share: &HashMap<String, f64>, reason: UpdateReason) -> f64 {
+		let mut players = self.players.write().unwrap();
+		let mut total = 0.0;
+		for (_id, player) in players.iter_mut() {
+			for (symbol, inv) in player.get_positions() {
+				let payment = match per_share.get(&symbol) {
+					Some(amount) => amount * inv,
+					None => continue,
+				};
+				if payment == 0.0 {
+					continue;
+				}
+				player.update_bal(payment);
+				total += payment;
+				log_player_data!(player.log_to_csv(reason));
+				self.record(&**player, reason, Some(&symbol), payment, 0.0, Vec::new());
+			}
+		}
+		total
+	}
+
+	/// The net dividends paid to players, short positions' payments are subtracted
+	pub fn get_dividends_paid(&self) -> f64 {
+		*self.dividends_paid.lock().unwrap()
+	}
+
+	pub fn get_funding_paid(&self) -> f64 {
+		*self.funding_paid.lock().unwrap()
+	}
+
+	/// Appends a change to the player's ledger along with their state after it
+	fn record(&self, player: &dyn Player, reason: UpdateReason, symbol: Option<&str>, cash: f64, shares: f64, order_ids: Vec<u64>) {
+		if !self.observers.is_empty() {
+			self.observers.notify(&PlayerEvent {
+				time: get_time(),
+				block_num: self.ledger.read_block(),
+				reason,
+				trader_id: player.get_id(),
+				player_type: player.get_player_type(),
+				symbol: symbol.map(|s| s.to_string()),
+				cash,
+				shares,
+				balance: player.get_bal(),
+				inventory: player.get_total_inv(),
+			});
+		}
+		let inventory = match symbol {
+			Some(symbol) => player.get_inv(symbol),
+			None => player.get_total_inv(),
+		};
+		self.undo.record(&player.get_id(), symbol, cash, shares);
+		self.ledger.record(&player.get_id(), LedgerEntry {
+			block_num: self.ledger.read_block(),
+			reason,
+			symbol: symbol.map(|s| s.to_string()),
+			cash,
+			shares,
+			order_ids,
+			balance: player.get_bal(),
+			inventory,
+		});
+	}
+
+	/// The PnL the player has realized by closing positions, at their average cost
+	pub fn get_realized_pnl(&self, trader_id: &str) -> f64 {
+		self.pnl.realized(trader_id)
+	}
+
+	/// The PnL of the player's open positions marked to prices
+	pub fn get_unrealized_pnl(&self, trader_id: &str, prices: &HashMap<String, f64>) -> f64 {
+		self.pnl.unrealized(trader_id, prices)
+	}
+
+	/// The (realized, unrealized) PnL summed over each type of maker, indexed by MakerT
+	pub fn get_maker_pnl(&self, prices: &HashMap<String, f64>) -> Vec<(f64, f64)> {
+		let players = self.players.read().unwrap();
+		let mut pnl = vec![(0.0, 0.0); NUM_MAKER_TYPES];
+		for (id, player) in players.iter() {
+			if let Some((maker_type, _)) = player.quoting() {
+				let entry = &mut pnl[maker_type as usize];
+				entry.0 += self.pnl.realized(id);
+				entry.1 += self.pnl.unrealized(id, prices);
+			}
+		}
+		pnl
+	}
+
+	/// Each maker type's average risk metrics over the equity curves of its makers,
+	/// indexed by MakerT like get_maker_pnl
+	pub fn get_maker_risk(&self, confidence: f64) -> Vec<RiskMetrics> {
+		let players = self.players.read().unwrap();
+		let mut totals = vec![(RiskMetrics { max_drawdown: 0.0, current_drawdown: 0.0, var: 0.0 }, 0.0); NUM_MAKER_TYPES];
+		for (id, player) in players.iter() {
+			if let Some((maker_type, _)) = player.quoting() {
+				let m = RiskMetrics::new(&self.get_equity_curve(id), confidence);
+				let (total, count) = &mut totals[maker_type as usize];
+				total.max_drawdown += m.max_drawdown;
+				total.current_drawdown += m.current_drawdown;
+				total.var += m.var;
+				*count += 1.0;
+			}
+		}
+		totals.into_iter().map(|(t, count)| match count {
+			c if c > 0.0 => RiskMetrics { max_drawdown: t.max_drawdown / c, current_drawdown: t.current_drawdown / c, var: t.var / c },
+			_ => t,
+		}).collect()
+	}
+
+	/// Every player's results ranked by profit, best first
+	pub fn leaderboard(&self) -> Vec<Standing> {
+		let players = self.players.read().unwrap();
+		let standings = players.values()
+			.map(|p| Standing::new(&**p, &self.ledger.entries_for(&p.get_id())))
+			.collect();
+		leaderboard::rank(standings)
+	}
+
+	/// Every change made to the player's balance and inventory, in order
+	pub fn get_ledger(&self, trader_id: &str) -> Vec<LedgerEntry> {
+		self.ledger.entries_for(trader_id)
+	}
+
+	/// Starts recording every change to the players so the block can be rolled back if it's
+	/// orphaned, along with each player's orders and the pending obligations as the block begins
+	pub fn begin_block_undo(&self) {
+		let players = self.players.read().unwrap();
+		let orders = players.iter().map(|(id, p)| (id.clone(), p.copy_orders())).collect();
+		let obligations = self.settlement.pending.lock().unwrap().clone();
+		self.undo.begin(orders, obligations);
+	}
+
+	/// Stops recording, the block is kept
+	pub fn commit_block_undo(&self) {
+		self.undo.take();
+	}
+
+	/// Reverses every change the block made to the players since begin_block_undo and gives them back
+	/// the obligations they had then. The orders they still hold go back to how they were then, undoing
+	/// partial fills. The orders the block filled or cancelled aren't given back: they're returned to be
+	/// entered again through the pre-trade checks. Orders added since the block began are kept.
+	/// The running totals, such as fees collected, aren't rolled back.
+	/// Returns the number of changes reversed and the orders to enter again.
+	pub fn rollback_block(&self) -> (usize, Vec<Order>) {
+		let block = match self.undo.take() {
+			Some(block) => block,
+			None => return (0, Vec::new()),
+		};
+		let reversals = block.reversals();
+		let mut players = self.players.write().unwrap();
+		for r in reversals.iter() {
+			if let Some(player) = players.get_mut(&r.trader_id) {
+				player.update_bal(r.cash);
+				if let Some(symbol) = &r.symbol {
+					player.update_inv(symbol, r.shares);
+				}
+				log_player_data!(player.log_to_csv(UpdateReason::Reorg));
+				self.record(&**player, UpdateReason::Reorg, r.symbol.as_ref().map(|s| s.as_str()), r.cash, r.shares, Vec::new());
+			}
+		}
+		let mut taken = Vec::new();
+		for (id, orders) in block.orders {
+			if let Some(player) = players.get_mut(&id) {
+				for order in orders {
+					match player.cancel_order(order.order_id) {
+						Ok(()) => player.add_order(order),
+						Err(_) => taken.push(order),
+					}
+				}
+			}
+		}
+		*self.settlement.pending.lock().unwrap() = block.obligations;
+		(reversals.len(), taken)
+	}
+
+
+	/// Marks every player's positions to the price of their symbol. A player whose equity falls
+	/// below maintenance is margin called: a market order closing each marked position is registered
+	/// to them and returned to be sent to the MemPool. Players with a margin call order still open
+	/// aren't called again. Positions in symbols without a price are left out of the equity.
+	pub fn mark_to_market(&self, prices: &HashMap<String, f64>, maintenance: Option<f64>, gas: f64) -> Vec<Order> {
+		let mut players = self.players.write().unwrap();
+		let mut orders = Vec::<Order>::new();
+		for (id, player) in players.iter_mut() {
+			let marked: Vec<(String, f64, f64)> = player.get_positions().into_iter()
+				.filter_map(|(symbol, inv)| prices.get(&symbol).map(|price| (symbol, inv, *price)))
+				.collect();
+			let mut margin = player.get_margin();
+			margin.equity = player.get_bal() + marked.iter().map(|(_, inv, price)| inv * price).sum::<f64>();
+
+			let open_ids: Vec<u64> = player.copy_orders().iter().map(|o| o.order_id).collect();
+			let call_open = margin.call_order_ids.iter().any(|o_id| open_ids.contains(o_id));
+			let called = match maintenance {
+				Some(m) => marked.iter().any(|(_, inv, _)| *inv != 0.0) && margin.equity < m
+					&& !call_open && player.get_player_type() != TraderT::Miner,
+				None => false,
+			};
+			if called {
+				margin.margin_calls += 1;
+				margin.call_order_ids.clear();
+				for (symbol, inv, price) in marked.iter().filter(|(_, inv, _)| *inv != 0.0) {
+					let (trade_type, p) = match *inv > 0.0 {
+						true => (TradeType::Ask, MARKET_ASK_PRICE),
+						false => (TradeType::Bid, MARKET_BID_PRICE),
+					};
+					let order = Order::new(id.clone(), OrderType::Enter, trade_type, ExchangeType::LimitOrder,
+						p, p, p, inv.abs(), inv.abs(), gas).with_symbol(symbol);
+					info!("Margin call: {} equity {}, closing {} {} @ {}", id, margin.equity, inv, symbol, price);
+					margin.call_order_ids.push(order.order_id);
+					player.add_order(order.clone());
+					orders.push(order);
+				}
+				log_player_data!(player.log_to_csv(UpdateReason::MarginCall));
+			}
+			player.set_margin(margin);
+		}
+		orders
+	}
+
+	/// Values every player's positions at prices and appends the result to their equity curve.
+	/// Positions in symbols without a price are left out.
+	pub fn revalue(&self, prices: &HashMap<String, f64>, block_num: u64) {
+		let players = self.players.read().unwrap();
+		let mut curves = self.equity_curves.lock().unwrap();
+		for (id, player) in players.iter() {
+			let inv_value: f64 = player.get_positions().iter()
+				.filter_map(|(symbol, inv)| prices.get(symbol).map(|p| inv * p))
+				.sum();
+			let point = EquityPoint {
+				block_num,
+				balance: player.get_bal(),
+				equity: player.get_bal() + inv_value,
+			};
+			curves.entry(id.clone()).or_insert(Vec::new()).push(point);
+			log_player_data!(player.log_to_csv(UpdateReason::Mark));
+		}
+	}
+
+	pub fn get_equity_curve(&self, trader_id: &str) -> Vec<EquityPoint> {
+		match self.equity_curves.lock().unwrap().get(trader_id) {
+			Some(curve) => curve.clone(),
+			None => Vec::new(),
+		}
+	}
+
+	pub fn is_defaulted(&self, trader_id: &str) -> bool {
+		self.defaulted.lock().unwrap().iter().any(|id| id == trader_id)
+	}
+
+	pub fn get_default_losses(&self) -> f64 {
+		*self.default_losses.lock().unwrap()
+	}
+
+	/// Marks the players whose balance fell below threshold as defaulted. Their resting orders are
+	/// cancelled, the tasks stop selecting them and their debt is cleared according to rule.
+	/// Returns the cancel orders to send to the MemPool.
+	pub fn process_defaults(&self, threshold: Option<f64>, rule: DefaultRule) -> Vec<Order> {
+		let threshold = match threshold {
+			Some(threshold) => threshold,
+			None => return Vec::new(),
+		};
+		let newly_defaulted: Vec<(String, f64)> = {
+			let players = self.players.read().unwrap();
+			let defaulted = self.defaulted.lock().unwrap();
+			players.iter()
+				.filter(|(id, p)| p.get_player_type() != TraderT::Miner && !defaulted.contains(id) && p.get_bal() < threshold)
+				.map(|(id, p)| (id.clone(), p.get_bal()))
+				.collect()
+		};
+
+		let mut cancels = Vec::<Order>::new();
+		for (id, bal) in newly_defaulted {
+			info!("Default: {} with balance {}", id, bal);
+			if let Ok(orders) = self.cancel_all_orders(id.clone(), None) {
+				cancels.extend(orders);
+			}
+			{
+				let mut players = self.players.write().unwrap();
+				let mut defaulted = self.defaulted.lock().unwrap();
+				defaulted.push(id.clone());
+
+				// Clear the debt then pass it on per the rule
+				let loss = -bal;
+				let remaining: Vec<String> = players.iter()
+					.filter(|(id, p)| p.get_player_type() != TraderT::Miner && !defaulted.contains(id))
+					.map(|(id, _p)| id.clone())
+					.collect();
+				if let Some(player) = players.get_mut(&id) {
+					player.update_bal(loss);
+					log_player_data!(player.log_to_csv(UpdateReason::Default));
+					self.record(&**player, UpdateReason::Default, None, loss, 0.0, Vec::new());
+				}
+				match rule {
+					DefaultRule::Socialize if remaining.len() > 0 => {
+						let share = loss / remaining.len() as f64;
+						for other in remaining {
+							if let Some(player) = players.get_mut(&other) {
+								player.update_bal(-share);
+								self.record(&**player, UpdateReason::Default, None, -share, 0.0, Vec::new());
+							}
+						}
+					},
+					_ => {},
+				}
+				*self.default_losses.lock().unwrap() += loss;
+			}
+		}
+		cancels
+	}
 
 	// log all of the player states
 	pub fn log_all_players(&self, reason: UpdateReason) {
-		let players = self.players.lock().unwrap();
+		let players = self.players.read().unwrap();
 		for (_id, player) in players.iter() {
     		log_player_data!(player.log_to_csv(reason));
 		}
 	}
 
 
-	// if player has negative inventory and so will buy at fund_val
-	//      cur_inv is negative so cur_inv * fund_val < 0, which subtracts from player bal
-	// if player has positive inventory and so will sell at fund_val
-	//      cur_inv is positive so cur_inv * fundval > 0 which adds to their player bal
-	pub fn liquidate(&self, fund_val: f64) {
-		let mut players = self.players.lock().unwrap();
-		for (_id, player) in players.iter_mut() {
-			let cur_inv = player.get_inv();
-			let update_amount = cur_inv * fund_val;
-				
-			player.update_bal(update_amount);
-			player.update_inv(-cur_inv);
+	// Each position is liquidated at the price of its symbol
+	// if player has negative inventory and so will buy at the price
+	//      cur_inv is negative so cur_inv * price < 0, which subtracts from player bal
+	// if player has positive inventory and so will sell at the price
+	//      cur_inv is positive so cur_inv * price > 0 which adds to their player bal
+	// Positions in symbols without a price are left open
+	pub fn liquidate(&self, prices: &HashMap<String, f64>) {
+		// Unsettled positions are settled before they are liquidated
+		self.settle_all();
+		// Liquidating at prices doesn't change equity, so the last mark is taken at them
+		self.revalue(prices, self.ledger.read_block());
+		let mut players = self.players.write().unwrap();
+		for (id, player) in players.iter_mut() {
+			let mut update_amount = 0.0;
+			for (symbol, cur_inv) in player.get_positions() {
+				if let Some(price) = prices.get(&symbol) {
+					let amount = cur_inv * price;
+					player.update_inv(&symbol, -cur_inv);
+					player.update_bal(amount);
+					self.record(&**player, UpdateReason::Liquify, Some(&symbol), amount, -cur_inv, Vec::new());
+					self.pnl.fill(id, &symbol, -cur_inv, *price);
+					update_amount += amount;
+				}
+			}
 
 			// Update the balances of the specific maker types
 			if player.get_player_type() == TraderT::Maker {
@@ -622,6 +1661,14 @@ impl ClearingHouse {
 							let mut maker_profits = self.maker_profits.lock().unwrap();
 							maker_profits[MakerT::Random as usize] += update_amount;
 						},
+						MakerT::InventorySkew => {
+							let mut maker_profits = self.maker_profits.lock().unwrap();
+							maker_profits[MakerT::InventorySkew as usize] += update_amount;
+						},
+						MakerT::AdverseSelection => {
+							let mut maker_profits = self.maker_profits.lock().unwrap();
+							maker_profits[MakerT::AdverseSelection as usize] += update_amount;
+						},
 					}
 				}
 			}
@@ -637,17 +1684,19 @@ impl ClearingHouse {
 mod tests {
 	use super::*;
 	use std::sync::Arc;
+	use crate::order::order::DEFAULT_SYMBOL;
+	use crate::exchange::fees::{TradingFee, InventoryTax, GasSurcharge, TaxRule, TaxBasis, policies_from_schedule};
 	use crate::players::maker::{Maker, MakerT};
 
 	#[test]
 	fn test_ch() {
 		let mut i = Investor::new(format!("{:?}", "BillyBob"));
 		i.update_bal(55.0);
-		i.update_inv(100.0);
+		i.update_inv(DEFAULT_SYMBOL, 100.0);
 
 		let mut mkr = Maker::new(format!("{:?}", "NillyNob"), MakerT::Aggressive);
 		mkr.update_bal(55.0);
-		mkr.update_inv(100.0);
+		mkr.update_inv(DEFAULT_SYMBOL, 100.0);
 
 		let min = Miner::new(format!("{:?}", "SquillyFob"));
 
@@ -667,14 +1716,14 @@ mod tests {
 		}
 
 		// Test updating a player's balance
-		if let Some(inv) = ch.update_player_inv(format!("{:?}", "NillyNob"), -40.0) {
+		if let Some(inv) = ch.update_player_inv(format!("{:?}", "NillyNob"), DEFAULT_SYMBOL, -40.0) {
 			assert_eq!(inv, 60.0);
 		} else {
 			panic!("AHHH failed to update player inventory");
 		}
 
 		// Test updating both
-		if let Some((bal, inv)) = ch.update_player(format!("{:?}", "SquillyFob"), -40.0, 20.0, UpdateReason::Transact) {
+		if let Some((bal, inv)) = ch.update_player(format!("{:?}", "SquillyFob"), DEFAULT_SYMBOL, -40.0, 20.0, UpdateReason::Transact, Vec::new()) {
 			assert_eq!(inv, 20.0);
 			assert_eq!(bal, -40.0);
 		} else {
@@ -688,12 +1737,578 @@ mod tests {
 		}
 	}
 
-	
-}
+	#[test]
+	fn test_inspect_player() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("investor"));
+		i.update_bal(50.0);
+		i.update_inv(DEFAULT_SYMBOL, 3.0);
+		ch.reg_investor(i);
+
+		let snapshot = ch.inspect_player("investor").unwrap();
+		assert_eq!(snapshot.balance, 50.0);
+		assert_eq!(snapshot.positions[DEFAULT_SYMBOL], 3.0);
+		assert_eq!(snapshot.player_type, TraderT::Investor);
+		assert_eq!(ch.with_player("investor", |p| p.get_inv(DEFAULT_SYMBOL)), Some(3.0));
+		assert!(ch.inspect_player("nobody").is_none());
+
+		// Inspecting leaves the player registered
+		assert_eq!(ch.num_players(), 1);
+		ch.reg_maker(Maker::new(format!("maker"), MakerT::Aggressive));
+		let snapshots = ch.snapshot();
+		assert_eq!(snapshots.len(), 2);
+		assert_eq!(snapshots.iter().find(|p| p.trader_id == "investor").unwrap().total_inv(), 3.0);
+	}
+
+	#[test]
+	fn test_symbol_inventory() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("bidder")));
+		ch.reg_investor(Investor::new(format!("asker")));
+		ch.transact(format!("bidder"), "SYM1", -500.0, 5.0, vec![1, 2]);
+		ch.transact(format!("asker"), "SYM1", 500.0, -5.0, vec![1, 2]);
+		ch.transact(format!("bidder"), DEFAULT_SYMBOL, 200.0, -2.0, vec![3, 4]);
+
+		assert_eq!(ch.get_symbol_inventory("bidder", "SYM1"), 5.0);
+		assert_eq!(ch.get_symbol_inventory("asker", "SYM1"), -5.0);
+		assert_eq!(ch.get_symbol_inventory("bidder", DEFAULT_SYMBOL), -2.0);
+		assert_eq!(ch.get_symbol_inventory("asker", DEFAULT_SYMBOL), 0.0);
+
+		// Each position is liquidated at its own symbol's price
+		let mut prices = HashMap::new();
+		prices.insert(String::from(DEFAULT_SYMBOL), 100.0);
+		prices.insert(String::from("SYM1"), 50.0);
+		ch.liquidate(&prices);
+		assert_eq!(ch.get_bal_inv(format!("bidder"), "SYM1"), Some((-500.0 + 200.0 + 250.0 - 200.0, 0.0)));
+		assert_eq!(ch.get_bal_inv(format!("asker"), "SYM1"), Some((500.0 - 250.0, 0.0)));
+	}
+
+	#[test]
+	fn test_deferred_settlement() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("buyer")));
+		ch.reg_investor(Investor::new(format!("seller")));
+		let (bal, inv) = ch.get_bal_inv(format!("seller"), DEFAULT_SYMBOL).unwrap();
+		ch.settlement.set_lag(1);
+
+		// The seller delivers more shares than they hold
+		let short = inv + 10.0;
+		ch.transact(format!("buyer"), DEFAULT_SYMBOL, -100.0 * short, short, vec![1, 2]);
+		ch.transact(format!("seller"), DEFAULT_SYMBOL, 100.0 * short, -short, vec![1, 2]);
+		assert_eq!(ch.get_bal_inv(format!("seller"), DEFAULT_SYMBOL), Some((bal, inv)));
+
+		// Nothing is due until the next block
+		ch.settle(0);
+		assert_eq!(ch.settlement.pending.lock().unwrap().len(), 2);
+		ch.settle(1);
+		assert_eq!(ch.settlement.pending.lock().unwrap().len(), 0);
+		assert_eq!(ch.get_bal_inv(format!("seller"), DEFAULT_SYMBOL), Some((bal + 100.0 * short, -10.0)));
+
+		let failures = ch.settlement.failures.lock().unwrap();
+		let failure = failures.iter().find(|f| f.trader_id == "seller").unwrap();
+		assert_eq!(failure.shares_short, 10.0);
+
+		// The ledger records the fill when it settles
+		let ledger = ch.get_ledger("seller");
+		assert_eq!(ledger.len(), 1);
+		assert_eq!(ledger[0].block_num, 1);
+		assert_eq!(ledger[0].order_ids, vec![1, 2]);
+	}
+
+	#[test]
+	fn test_pnl_attribution() {
+		let ch = ClearingHouse::new();
+		ch.reg_maker(Maker::new(format!("maker"), MakerT::RiskAverse));
+		ch.transact(format!("maker"), DEFAULT_SYMBOL, -1000.0, 10.0, vec![1, 2]);
+		ch.transact(format!("maker"), DEFAULT_SYMBOL, 660.0, -6.0, vec![3, 4]);
+		assert_eq!(ch.get_realized_pnl("maker"), 60.0);
+
+		let mut prices = HashMap::new();
+		prices.insert(String::from(DEFAULT_SYMBOL), 90.0);
+		assert_eq!(ch.get_unrealized_pnl("maker", &prices), -40.0);
+		assert_eq!(ch.get_maker_pnl(&prices)[MakerT::RiskAverse as usize], (60.0, -40.0));
+
+		// Liquidating realizes the rest
+		ch.liquidate(&prices);
+		assert_eq!(ch.get_realized_pnl("maker"), 20.0);
+		assert_eq!(ch.get_unrealized_pnl("maker", &prices), 0.0);
+	}
+
+	#[test]
+	fn test_revalue() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("investor"));
+		i.update_bal(-500.0);
+		i.update_inv(DEFAULT_SYMBOL, 5.0);
+		i.update_inv("SYM1", 2.0);
+		ch.reg_investor(i);
+
+		let mut prices = HashMap::new();
+		prices.insert(String::from(DEFAULT_SYMBOL), 100.0);
+		ch.revalue(&prices, 1);
+		prices.insert(String::from(DEFAULT_SYMBOL), 90.0);
+		prices.insert(String::from("SYM1"), 10.0);
+		ch.revalue(&prices, 2);
+
+		// Liquidating takes the final mark
+		ch.liquidate(&prices);
+		let curve = ch.get_equity_curve("investor");
+		assert_eq!(curve.len(), 3);
+		assert_eq!(curve[0], EquityPoint { block_num: 1, balance: -500.0, equity: 0.0 });
+		assert_eq!(curve[1].equity, -30.0);
+		assert_eq!(curve[2].equity, -30.0);
+	}
+
+	#[test]
+	fn test_ledger() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("buyer")));
+		ch.settle(4);
+		ch.transact(format!("buyer"), DEFAULT_SYMBOL, -500.0, 5.0, vec![7, 8]);
+		ch.apply_gas_fees(vec![(format!("buyer"), 0.5)], 0.5);
+		ch.liquidate(&vec![(String::from(DEFAULT_SYMBOL), 110.0)].into_iter().collect());
+
+		let ledger = ch.get_ledger("buyer");
+		assert_eq!(ledger.len(), 3);
+		assert_eq!(ledger[0].order_ids, vec![7, 8]);
+		assert_eq!(ledger[0].block_num, 4);
+		assert_eq!((ledger[0].cash, ledger[0].shares, ledger[0].inventory), (-500.0, 5.0, 5.0));
+		assert_eq!((ledger[1].cash, ledger[1].balance), (-0.5, -500.5));
+		assert_eq!((ledger[2].cash, ledger[2].shares, ledger[2].inventory), (550.0, -5.0, 0.0));
+
+		// The entries sum to the player's final state
+		let (bal, inv) = ch.get_bal_inv(format!("buyer"), DEFAULT_SYMBOL).unwrap();
+		assert_eq!(ch.ledger.net_change("buyer"), (bal, inv));
+	}
+
+	#[test]
+	fn test_rollback_block() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("buyer")));
+		ch.reg_investor(Investor::new(format!("seller")));
+		ch.transact(format!("seller"), DEFAULT_SYMBOL, 0.0, 5.0, Vec::new());
+
+		// Changes made while the block is open are reversed, latest first
+		ch.begin_block_undo();
+		ch.transact(format!("buyer"), DEFAULT_SYMBOL, -500.0, 5.0, vec![7, 8]);
+		ch.transact(format!("seller"), DEFAULT_SYMBOL, 500.0, -5.0, vec![7, 8]);
+		ch.apply_gas_fees(vec![(format!("buyer"), 0.5)], 0.5);
+		assert_eq!(ch.rollback_block().0, 3);
+		assert_eq!(ch.get_bal_inv(format!("buyer"), DEFAULT_SYMBOL), Some((0.0, 0.0)));
+		assert_eq!(ch.get_bal_inv(format!("seller"), DEFAULT_SYMBOL), Some((0.0, 5.0)));
+		assert_eq!(ch.ledger.net_change("buyer"), (0.0, 0.0));
+
+		// Nothing is recorded once the block is closed
+		assert_eq!(ch.rollback_block().0, 0);
+		ch.begin_block_undo();
+		ch.transact(format!("buyer"), DEFAULT_SYMBOL, -100.0, 1.0, Vec::new());
+		ch.commit_block_undo();
+		assert_eq!(ch.rollback_block().0, 0);
+		assert_eq!(ch.get_bal_inv(format!("buyer"), DEFAULT_SYMBOL), Some((-100.0, 1.0)));
+
+		// Orders added and changes made on other threads while the block is open survive its rollback
+		let bid = || Order::new(format!("buyer"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			0.0, 0.0, 100.0, 1.0, 1.0, 0.1);
+		ch.new_order(bid()).unwrap();
+		let ch = Arc::new(ch);
+		ch.begin_block_undo();
+		ch.new_order(bid()).unwrap();
+		let other = Arc::clone(&ch);
+		std::thread::spawn(move || other.transact(format!("seller"), DEFAULT_SYMBOL, 10.0, 0.0, Vec::new())).join().unwrap();
+		let (reversed, taken) = ch.rollback_block();
+		assert_eq!((reversed, taken.len()), (0, 0));
+		assert_eq!(ch.get_player_order_count(&format!("buyer")).unwrap(), 2);
+		assert_eq!(ch.get_bal_inv(format!("seller"), DEFAULT_SYMBOL), Some((10.0, 5.0)));
+
+		// Orders the block took from the players are returned to be entered again, not given back
+		let filled = bid();
+		let filled_id = filled.order_id;
+		ch.new_order(filled).unwrap();
+		ch.begin_block_undo();
+		ch.cancel_player_order(format!("buyer"), filled_id).unwrap();
+		let (_, taken) = ch.rollback_block();
+		assert_eq!(taken.iter().map(|o| o.order_id).collect::<Vec<u64>>(), vec![filled_id]);
+		assert_eq!(ch.get_player_order_count(&format!("buyer")).unwrap(), 2);
+	}
+
+	#[test]
+	fn test_apply_fees() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("taker")));
+		ch.reg_maker(Maker::new(format!("maker"), MakerT::Aggressive));
+		ch.set_fee_policies(vec![Box::new(TradingFee { taker_fee: 0.003, maker_rebate: 0.001 })]);
+
+		// The bid crossed a resting ask so the bidder pays and the asker is rebated
+		let pu = PlayerUpdate::new(format!("taker"), format!("maker"), 1, 2, 100.0, 10.0, false).with_taker(TradeType::Bid);
+		ch.apply_fees(&pu);
+		assert!((ch.get_exchange_revenue() - 2.0).abs() < 1e-9);
+		let taker_bal = ch.update_player_bal(format!("taker"), 0.0).unwrap();
+		let maker_bal = ch.update_player_bal(format!("maker"), 0.0).unwrap();
+
+		// Batch fills charge both sides the taker fee
+		let pu = PlayerUpdate::new(format!("taker"), format!("maker"), 1, 2, 100.0, 10.0, false);
+		ch.apply_fees(&pu);
+		assert!((ch.get_exchange_revenue() - 8.0).abs() < 1e-9);
+		assert!((ch.update_player_bal(format!("taker"), 0.0).unwrap() - (taker_bal - 3.0)).abs() < 1e-9);
+		assert!((ch.update_player_bal(format!("maker"), 0.0).unwrap() - (maker_bal - 3.0)).abs() < 1e-9);
+	}
+
+	#[test]
+	fn test_fee_policies() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("investor"));
+		i.update_inv(DEFAULT_SYMBOL, 10.0);
+		let mut mkr = Maker::new(format!("maker"), MakerT::Aggressive);
+		mkr.update_inv(DEFAULT_SYMBOL, -10.0);
+		mkr.update_inv("SYM1", 5.0);
+		ch.reg_investor(i);
+		ch.reg_maker(mkr);
+		ch.add_fee_policy(Box::new(InventoryTax { tax: 0.1, trader_type: Some(TraderT::Maker) }));
+		ch.add_fee_policy(Box::new(GasSurcharge { rate: 0.5 }));
+
+		// Only the maker's 15 shares are taxed
+		ch.charge_block_fees();
+		assert!((ch.total_tax.lock().unwrap().clone() - 1.5).abs() < 1e-9);
+		assert_eq!(ch.get_exchange_revenue(), 0.0);
+
+		// The surcharge is exchange revenue on top of the gas
+		ch.apply_gas_fees(vec![(format!("investor"), 2.0)], 2.0);
+		assert_eq!(ch.get_exchange_revenue(), 1.0);
+		assert_eq!(ch.update_player_bal(format!("investor"), 0.0), Some(-3.0));
+		assert!((ch.update_player_bal(format!("maker"), 0.0).unwrap() + 1.5).abs() < 1e-9);
+	}
 
+	#[test]
+	fn test_short_sale_constraint() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("seller"));
+		i.update_inv(DEFAULT_SYMBOL, 10.0);
+		ch.reg_investor(i);
+		ch.set_min_inventory(Some(0.0));
+
+		let ask = |q: f64| Order::new(format!("seller"), OrderType::Enter, TradeType::Ask, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, q, q, 0.1);
+
+		// Selling the shares held is allowed, but open asks count against the inventory
+		assert!(ch.new_order(ask(6.0)).is_ok());
+		assert_eq!(ch.new_order(ask(5.0)).unwrap_err().reason(), "Order would breach the short-sale constraint");
+		assert!(ch.new_order(ask(4.0)).is_ok());
+
+		// Bids are never constrained
+		let bid = Order::new(format!("seller"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			90.0, 90.0, 90.0, 5.0, 5.0, 0.1);
+		assert!(ch.new_order(bid).is_ok());
+
+		// Short positions pay the borrow fee per share each block
+		ch.update_player_inv(format!("seller"), DEFAULT_SYMBOL, -15.0);
+		let bal = ch.update_player_bal(format!("seller"), 0.0).unwrap();
+		ch.charge_borrow_fees(0.01);
+		assert!((ch.update_player_bal(format!("seller"), 0.0).unwrap() - (bal - 0.05)).abs() < 1e-9);
+		assert!((ch.get_borrow_fees() - 0.05).abs() < 1e-9);
+	}
 
+	#[test]
+	fn test_route_order_events() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("trader")));
+		let order = Order::new(format!("trader"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 5.0, 5.0, 0.1);
+		ch.new_order(order.clone()).unwrap();
+
+		// A cancel rejected by the book comes back in the trade results
+		let cancel = TradeResults::new(MarketType::CDA, None, 0.0, 0.0, None)
+			.with_order_event(OrderEvent::reject(&order, "Order not found", 0))
+			.with_block(3);
+		ch.update_house(cancel).unwrap();
+
+		let players = ch.players.read().unwrap();
+		let player = players.get("trader").unwrap();
+		assert_eq!(player.num_orders(), 0);
+		match &player.copy_order_events()[0] {
+			OrderEvent::Reject(rej) => {
+				assert_eq!(rej.order_id, order.order_id);
+				assert_eq!(rej.block_num, 3);
+				assert_eq!(rej.reason, "Order not found");
+			},
+			OrderEvent::Ack(_) => panic!("expected a rejection"),
+		}
+	}
 
+	#[test]
+	fn test_margin_call() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("long"));
+		i.update_bal(-1000.0);
+		i.update_inv(DEFAULT_SYMBOL, 10.0);
+		ch.reg_investor(i);
 
+		let prices = |price: f64| {
+			let mut prices = HashMap::new();
+			prices.insert(String::from(DEFAULT_SYMBOL), price);
+			prices
+		};
+
+		// Marked at 100 the position is worth its cost
+		assert_eq!(ch.mark_to_market(&prices(100.0), Some(-200.0), 0.1).len(), 0);
+
+		// Marked at 75 the equity of -250 is below the maintenance level
+		let orders = ch.mark_to_market(&prices(75.0), Some(-200.0), 0.1);
+		assert_eq!(orders.len(), 1);
+		assert_eq!(orders[0].trade_type, TradeType::Ask);
+		assert_eq!(orders[0].quantity, 10.0);
+		assert_eq!(orders[0].symbol, DEFAULT_SYMBOL);
+
+		// The call isn't repeated while its order is open
+		assert_eq!(ch.mark_to_market(&prices(70.0), Some(-200.0), 0.1).len(), 0);
+		let players = ch.players.read().unwrap();
+		let margin = players.get("long").unwrap().get_margin();
+		assert_eq!(margin.margin_calls, 1);
+		assert_eq!(margin.equity, -300.0);
+	}
 
+	#[test]
+	fn test_accrue_interest() {
+		let ch = ClearingHouse::new();
+		let mut saver = Investor::new(format!("saver"));
+		saver.update_bal(1000.0);
+		let mut borrower = Investor::new(format!("borrower"));
+		borrower.update_bal(-1000.0);
+		borrower.update_inv(DEFAULT_SYMBOL, 10.0);
+		borrower.update_inv("SYM1", -5.0);
+		ch.reg_investor(saver);
+		ch.reg_investor(borrower);
+
+		ch.accrue_interest(0.01, Some(0.5), 2);
+		let (bal, _) = ch.get_bal_inv(format!("saver"), DEFAULT_SYMBOL).unwrap();
+		assert!((bal - 1020.1).abs() < 1e-9);
+		// The borrower pays interest and the carry on 15 shares for 2 blocks
+		let (bal, _) = ch.get_bal_inv(format!("borrower"), DEFAULT_SYMBOL).unwrap();
+		assert!((bal - (-1020.1 - 15.0)).abs() < 1e-9);
+		assert!(ch.get_interest_paid().abs() < 1e-9);
+		assert_eq!(ch.get_carry_costs(), 15.0);
+	}
+
+	#[test]
+	fn test_risk_limits() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("trader"));
+		i.update_inv(DEFAULT_SYMBOL, 5.0);
+		ch.reg_investor(i);
+		ch.set_risk_limits(Some(20.0), Some(10.0));
+
+		let order = |tt: TradeType, q: f64| Order::new(format!("trader"), OrderType::Enter, tt, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, q, q, 0.1);
+
+		assert_eq!(ch.new_order(order(TradeType::Bid, 11.0)).unwrap_err().reason(), "Order exceeds the maximum order size");
+		assert!(ch.new_order(order(TradeType::Bid, 10.0)).is_ok());
+		// 5 held + 10 open + 6 more bids would be long 21
+		assert_eq!(ch.new_order(order(TradeType::Bid, 6.0)).unwrap_err().reason(), "Order would breach the position limit");
+		assert!(ch.new_order(order(TradeType::Bid, 5.0)).is_ok());
+		// Asks count towards the short side only
+		assert!(ch.new_order(order(TradeType::Ask, 10.0)).is_ok());
+		assert!(ch.new_order(order(TradeType::Ask, 10.0)).is_ok());
+		assert_eq!(ch.new_order(order(TradeType::Ask, 10.0)).unwrap_err().reason(), "Order would breach the position limit");
+	}
 
+	#[test]
+	fn test_budget_rule() {
+		let ch = ClearingHouse::new();
+		let mut i = Investor::new(format!("trader"));
+		i.update_bal(1000.0);
+		i.update_inv(DEFAULT_SYMBOL, 5.0);
+		ch.reg_investor(i);
+
+		let order = |tt: TradeType, q: f64| Order::new(format!("trader"), OrderType::Enter, tt, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, q, q, 0.1);
+
+		// Off lets anything through
+		assert_eq!(ch.fit_to_budget(order(TradeType::Bid, 50.0)).quantity, 50.0);
+
+		ch.set_budget_rule(BudgetRule::Reject);
+		assert!(ch.new_order(order(TradeType::Bid, 6.0)).is_ok());
+		// 600 of the 1000 is committed to the open bid
+		assert_eq!(ch.new_order(order(TradeType::Bid, 5.0)).unwrap_err().reason(), "Order exceeds the player's budget");
+		assert!(ch.new_order(order(TradeType::Ask, 3.0)).is_ok());
+		assert_eq!(ch.new_order(order(TradeType::Ask, 3.0)).unwrap_err().reason(), "Order exceeds the player's deliverable inventory");
+
+		ch.set_budget_rule(BudgetRule::ScaleDown);
+		let bid = ch.fit_to_budget(order(TradeType::Bid, 5.0));
+		assert_eq!((bid.quantity, bid.u_max), (4.0, 4.0));
+		assert!(ch.new_order(bid).is_ok());
+		assert_eq!(ch.fit_to_budget(order(TradeType::Ask, 3.0)).quantity, 2.0);
+		// Nothing left to scale down to
+		let bid = ch.fit_to_budget(order(TradeType::Bid, 1.0));
+		assert_eq!(bid.quantity, 0.0);
+		assert_eq!(ch.new_order(bid).unwrap_err().reason(), "Order exceeds the player's budget");
+	}
 
+	#[test]
+	fn test_process_defaults() {
+		let ch = ClearingHouse::new();
+		let mut broke = Investor::new(format!("broke"));
+		broke.update_bal(-600.0);
+		ch.reg_investor(broke);
+		ch.reg_investor(Investor::new(format!("a")));
+		ch.reg_investor(Investor::new(format!("b")));
+		let order = Order::new(format!("broke"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 5.0, 5.0, 0.1);
+		ch.new_order(order.clone()).unwrap();
+
+		assert_eq!(ch.process_defaults(None, DefaultRule::Socialize).len(), 0);
+		let cancels = ch.process_defaults(Some(-500.0), DefaultRule::Socialize);
+		assert_eq!(cancels.len(), 1);
+		assert_eq!(cancels[0].order_id, order.order_id);
+		assert!(ch.is_defaulted("broke"));
+		assert_eq!(ch.get_default_losses(), 600.0);
+
+		// The debt is cleared and split across the other investors
+		assert_eq!(ch.update_player_bal(format!("broke"), 0.0), Some(0.0));
+		assert_eq!(ch.update_player_bal(format!("a"), 0.0), Some(-300.0));
+		assert_eq!(ch.update_player_bal(format!("b"), 0.0), Some(-300.0));
+
+		// Defaulted players aren't selected or allowed to trade
+		assert_eq!(ch.get_filtered_ids(TraderT::Investor).len(), 2);
+		assert_eq!(ch.new_order(order).unwrap_err().reason(), "Trader has defaulted");
+		assert_eq!(ch.process_defaults(Some(-500.0), DefaultRule::Socialize).len(), 0);
+	}
+
+	#[test]
+	fn test_query() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+		ch.reg_investor(Investor::new(format!("b")));
+		ch.reg_maker(Maker::new(format!("m1"), MakerT::Aggressive));
+		ch.reg_maker(Maker::new(format!("m2"), MakerT::RiskAverse));
+		ch.update_player_inv(format!("m1"), DEFAULT_SYMBOL, -20.0);
+		ch.update_player_inv(format!("m2"), "SYM1", 5.0);
+
+		assert_eq!(ch.query().count(), 4);
+		assert_eq!(ch.query().of_type(TraderT::Maker).count(), 2);
+		assert_eq!(ch.query().of_type(TraderT::Maker).with_min_inventory(10.0).ids(), vec![format!("m1")]);
+		assert_eq!(ch.query().with_min_position("SYM1", 1.0).ids(), vec![format!("m2")]);
+		assert_eq!(ch.query().of_type(TraderT::Investor).filter(|p| p.get_id() == "b").random(), Some(format!("b")));
+		assert_eq!(ch.query().of_type(TraderT::Miner).random(), None);
+
+		ch.defaulted.lock().unwrap().push(format!("a"));
+		assert_eq!(ch.query().of_type(TraderT::Investor).ids(), vec![format!("b")]);
+		assert_eq!(ch.query().of_type(TraderT::Investor).include_defaulted().count(), 2);
+	}
+
+	#[test]
+	fn test_observers() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+		let rx = ch.subscribe();
+		let transacts = Arc::new(Mutex::new(0));
+		let t = Arc::clone(&transacts);
+		ch.add_observer(Box::new(move |e: &PlayerEvent| if let UpdateReason::Transact = e.reason { *t.lock().unwrap() += 1 }));
+
+		ch.update_player(format!("a"), DEFAULT_SYMBOL, -50.0, 1.0, UpdateReason::Transact, vec![1, 2]);
+		ch.update_player_bal(format!("a"), 10.0);
+
+		let events: Vec<PlayerEvent> = rx.try_iter().collect();
+		assert_eq!(events.len(), 2);
+		assert_eq!(events[0].trader_id, "a");
+		assert_eq!(events[0].symbol, Some(format!("{}", DEFAULT_SYMBOL)));
+		assert_eq!((events[0].cash, events[0].shares, events[0].inventory), (-50.0, 1.0, 1.0));
+		assert_eq!(events[1].balance, events[0].balance + 10.0);
+		assert_eq!(*transacts.lock().unwrap(), 1);
+	}
+
+	#[test]
+	fn test_update_house_errors() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+
+		// A fill for an unregistered trader is returned as an error instead of panicking
+		let fill = PlayerUpdate::new(format!("ghost"), format!("a"), 1, 2, 100.0, 1.0, false);
+		let results = TradeResults::new(MarketType::CDA, None, 0.0, 0.0, Some(vec![fill]));
+		assert_eq!(ch.update_house(results), Err(Error::UnknownTrader { trader_id: format!("ghost"), op: "cda_cross_update" }));
+
+		assert_eq!(ch.cancel_player_order(format!("a"), 7), Err(Error::UnknownOrder { trader_id: format!("a"), order_id: 7, op: "cancel_player_order" }));
+		assert_eq!(ch.get_type(&format!("ghost")).unwrap_err().reason(), "Trader not found");
+	}
+
+	#[test]
+	fn test_transfer() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+		ch.reg_investor(Investor::new(format!("b")));
+		let (a_bal, a_inv) = ch.get_bal_inv(format!("a"), DEFAULT_SYMBOL).unwrap();
+		let (b_bal, b_inv) = ch.get_bal_inv(format!("b"), DEFAULT_SYMBOL).unwrap();
+
+		ch.transfer("a", "b", DEFAULT_SYMBOL, 10.0, 2.0, UpdateReason::Adjust).unwrap();
+		assert_eq!(ch.get_bal_inv(format!("a"), DEFAULT_SYMBOL), Some((a_bal - 10.0, a_inv - 2.0)));
+		assert_eq!(ch.get_bal_inv(format!("b"), DEFAULT_SYMBOL), Some((b_bal + 10.0, b_inv + 2.0)));
+		assert_eq!(ch.get_ledger("b")[0].cash, 10.0);
+
+		// Nothing moves if either side is missing
+		assert_eq!(ch.transfer("a", "ghost", DEFAULT_SYMBOL, 5.0, 0.0, UpdateReason::Adjust),
+			Err(Error::UnknownTrader { trader_id: format!("ghost"), op: "transfer" }));
+		assert_eq!(ch.get_bal_inv(format!("a"), DEFAULT_SYMBOL), Some((a_bal - 10.0, a_inv - 2.0)));
+	}
+
+	#[test]
+	fn test_pay_dividends() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("long")));
+		ch.reg_investor(Investor::new(format!("short")));
+		ch.update_player_inv(format!("long"), DEFAULT_SYMBOL, 10.0);
+		ch.update_player_inv(format!("short"), DEFAULT_SYMBOL, -4.0);
+		let (long_bal, long_inv) = ch.get_bal_inv(format!("long"), DEFAULT_SYMBOL).unwrap();
+		let (short_bal, short_inv) = ch.get_bal_inv(format!("short"), DEFAULT_SYMBOL).unwrap();
+
+		let mut per_share = HashMap::new();
+		per_share.insert(format!("{}", DEFAULT_SYMBOL), 0.5);
+		ch.pay_dividends(&per_share);
+		assert_eq!(ch.get_bal_inv(format!("long"), DEFAULT_SYMBOL), Some((long_bal + 0.5 * long_inv, long_inv)));
+		assert_eq!(ch.get_bal_inv(format!("short"), DEFAULT_SYMBOL), Some((short_bal + 0.5 * short_inv, short_inv)));
+		assert_eq!(ch.get_dividends_paid(), 0.5 * (long_inv + short_inv));
+
+		// Symbols without an amount pay nothing
+		ch.pay_funding(&HashMap::new());
+		assert_eq!(ch.get_funding_paid(), 0.0);
+		assert_eq!(ch.get_bal_inv(format!("long"), DEFAULT_SYMBOL).unwrap().0, long_bal + 0.5 * long_inv);
+	}
+
+	#[test]
+	fn test_tax_schedule() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+		ch.reg_maker(Maker::new(format!("m"), MakerT::Aggressive));
+		ch.set_fee_policies(policies_from_schedule(vec![
+			TaxRule { player_type: Some(TraderT::Investor), basis: TaxBasis::Turnover, rate: 0.1, interval: 2 },
+			TaxRule { player_type: None, basis: TaxBasis::Profit, rate: 0.5, interval: 1 },
+		]));
+
+		// Nothing is owed in the first block
+		ch.charge_block_fees();
+		assert_eq!(*ch.total_tax.lock().unwrap(), 0.0);
+
+		// The investor's turnover is taxed every second block and the maker's gain every block
+		ch.apply_fees(&PlayerUpdate::new(format!("a"), format!("m"), 1, 2, 100.0, 5.0, false));
+		ch.update_player_bal(format!("m"), 20.0);
+		ch.charge_block_fees();
+		let breakdown = ch.get_tax_breakdown();
+		assert_eq!(breakdown["Turnover"], 0.5);
+		assert_eq!(breakdown["Profit"], 10.0);
+		assert_eq!(*ch.total_tax.lock().unwrap(), 10.5);
+	}
+
+	#[test]
+	fn test_cancel_one_order() {
+		let ch = ClearingHouse::new();
+		ch.reg_investor(Investor::new(format!("a")));
+		let order = Order::new(format!("a"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 1.0, 1.0, 0.1);
+		let order_id = order.order_id;
+		ch.new_order(order).unwrap();
+
+		let cancel = ch.cancel_one_order(format!("a"), order_id).unwrap();
+		assert_eq!((cancel.order_id, cancel.order_type), (order_id, OrderType::Cancel));
+
+		// A second cancel for the same order isn't sent
+		assert_eq!(ch.cancel_one_order(format!("a"), order_id).unwrap_err().reason(), "Order not found");
+		assert_eq!(ch.cancel_one_order(format!("b"), order_id).unwrap_err().reason(), "Trader not found");
+	}
+}
@@ -0,0 +1,115 @@
+use crate::order::order_book::Book;
+use crate::exchange::MarketType;
+use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate};
+
+use std::sync::{Mutex, Arc};
+
+/// The id the issuer uses as the counterparty in PlayerUpdates
+pub const DUTCH_ISSUER_ID: &str = "dutch_issuer";
+
+/// A descending-price auction. The issuer posts an ask that falls by decrement every
+/// block until it reaches floor, and resting bids at or above the ask buy at the ask.
+/// price: Mutex<f64> -> the issuer's current ask
+/// remaining: Mutex<f64> -> shares the issuer has left to sell
+pub struct DutchAuction {
+	pub price: Mutex<f64>,
+	pub remaining: Mutex<f64>,
+	pub decrement: f64,
+	pub floor: f64,
+}
+
+impl DutchAuction {
+	pub fn new(start_price: f64, decrement: f64, floor: f64, supply: f64) -> DutchAuction {
+		DutchAuction {
+			price: Mutex::new(start_price),
+			remaining: Mutex::new(supply),
+			decrement: decrement,
+			floor: floor,
+		}
+	}
+
+	pub fn current_price(&self) -> f64 {
+		*self.price.lock().expect("current_price")
+	}
+
+	pub fn remaining(&self) -> f64 {
+		*self.remaining.lock().expect("remaining")
+	}
+
+	/// Fills the bids priced at or above the current ask, best bid first, until the
+	/// supply runs out. Filled bids leave the book and a partially filled bid keeps
+	/// its remainder. The ask then falls for the next block.
+	pub fn run_block(&self, bids: Arc<Book>) -> Option<TradeResults> {
+		let mut price = self.price.lock().expect("run_block");
+		let mut remaining = self.remaining.lock().expect("run_block");
+		let ask = *price;
+
+		let mut updates = Vec::<PlayerUpdate>::new();
+		let mut volume = 0.0;
+		while *remaining > 0.0 {
+			let mut bid = match bids.pop_from_end() {
+				Some(bid) => bid,
+				None => break,
+			};
+			if bid.price < ask {
+				bids.push_to_end(bid).expect("Couldn't push order");
+				break;
+			}
+			let fill = bid.quantity.min(*remaining);
+			updates.push(PlayerUpdate::new(bid.trader_id.clone(), String::from(DUTCH_ISSUER_ID),
+				bid.order_id, 0, ask, fill, false));
+			*remaining -= fill;
+			volume += fill;
+			bid.quantity -= fill;
+			if bid.quantity > 0.0 {
+				bids.push_to_end(bid).expect("Couldn't push order");
+			}
+		}
+		bids.find_new_max();
+
+		// Lower the ask for the next block
+		*price = (ask - self.decrement).max(self.floor);
+
+		if updates.len() == 0 {
+			return None;
+		}
+		Some(TradeResults::new(MarketType::Dutch, Some(ask), volume, volume, Some(updates)))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{Order, OrderType, TradeType, ExchangeType};
+
+	fn bid(price: f64, quantity: f64) -> Order {
+		Order::new(String::from("trader"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			price, price, price, quantity, quantity, 0.1)
+	}
+
+	#[test]
+	fn test_descending_price() {
+		let auction = DutchAuction::new(110.0, 5.0, 98.0, 15.0);
+		let bids = Arc::new(Book::new(TradeType::Bid));
+		bids.add_order(bid(106.0, 10.0)).unwrap();
+		bids.add_order(bid(100.0, 10.0)).unwrap();
+
+		// Nobody bids 110
+		assert!(auction.run_block(Arc::clone(&bids)).is_none());
+		assert_eq!(auction.current_price(), 105.0);
+
+		// The 106 bid fills at the 105 ask
+		let res = auction.run_block(Arc::clone(&bids)).unwrap();
+		assert_eq!(res.uniform_price, Some(105.0));
+		assert_eq!(res.agg_supply, 10.0);
+		assert_eq!(bids.len(), 1);
+
+		// The 100 bid fills at 100 with the 5 shares left, the ask stops at the floor
+		let res = auction.run_block(Arc::clone(&bids)).unwrap();
+		assert_eq!(res.cross_results.unwrap()[0].volume, 5.0);
+		assert_eq!(auction.remaining(), 0.0);
+		assert_eq!(bids.peek_best_price(), Some(100.0));
+		assert_eq!(auction.current_price(), 98.0);
+	}
+}
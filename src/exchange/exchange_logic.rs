@@ -1,11 +1,15 @@
 use crate::controller::{Task, State};
 use crate::order::order_book::Book;
-use crate::order::order::{Order};
-use crate::exchange::MarketType;
-use crate::utility::get_time;
+use crate::order::order::{Order, TradeType, DEFAULT_SYMBOL};
+use crate::exchange::{MarketType, TieBreak};
+use crate::exchange::market_phase::MarketPhase;
+use crate::exchange::pricing::{PricingRule, PricingRuleT, FlowCross};
+use crate::simulation::simulation_config::Constants;
+use crate::utility::{get_time, gen_order_id};
 use crate::log_order_book;
 
 use std::sync::{Mutex, Arc};
+use std::collections::HashMap;
 use std::cmp::Ordering;
 
 use rayon::prelude::*;
@@ -14,12 +18,9 @@ use log::{log, Level};
 
 
 const EPSILON: f64 =  0.000_001;
-const MAX_PRICE: f64 = 999_999_999.0;
-const MIN_PRICE: f64 = 0.0;
-const MAX_ITERS: usize = 1000;
 // const PRECISION: i8 = 4;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlayerUpdate {
 	pub payer_id: String,
 	pub vol_filler_id: String,
@@ -28,6 +29,7 @@ pub struct PlayerUpdate {
 	pub price: f64,
 	pub volume: f64,
 	pub cancel: bool,
+	pub taker: Option<TradeType>,
 }
 
 impl PlayerUpdate {
@@ -41,17 +43,125 @@ impl PlayerUpdate {
 			price,
 			volume,
 			cancel,
+			taker: None,
 		}
 	}
+
+	/// Marks which side removed liquidity in a CDA cross, batch fills have no taker
+	pub fn with_taker(mut self, taker: TradeType) -> PlayerUpdate {
+		self.taker = Some(taker);
+		self
+	}
+}
+
+/// A record of a single trade for analytics, unlike PlayerUpdates it keeps the aggressor.
+/// aggressor: the side that removed liquidity, None for batch and dark fills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fill {
+	pub trade_id: u64,
+	pub block_num: u64,
+	pub aggressor: Option<TradeType>,
+	pub bid_order_id: u64,
+	pub ask_order_id: u64,
+	pub price: f64,
+	pub quantity: f64,
+}
+
+impl Fill {
+	pub fn from_update(pu: &PlayerUpdate, block_num: u64) -> Fill {
+		Fill {
+			trade_id: gen_order_id(),
+			block_num,
+			aggressor: pu.taker.clone(),
+			bid_order_id: pu.payer_order_id,
+			ask_order_id: pu.vol_filler_order_id,
+			price: pu.price,
+			quantity: pu.volume,
+		}
+	}
+}
+
+/// The exchange accepted an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderAck {
+	pub trader_id: String,
+	pub order_id: u64,
+	pub client_order_id: Option<u64>,
+	pub block_num: u64,
+}
+
+/// The exchange refused an order
+/// reason: String -> the error that caused the rejection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderReject {
+	pub trader_id: String,
+	pub order_id: u64,
+	pub client_order_id: Option<u64>,
+	pub block_num: u64,
+	pub reason: String,
+}
+
+/// Feedback routed back to the trader that sent an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OrderEvent {
+	Ack(OrderAck),
+	Reject(OrderReject),
 }
 
-#[derive(Debug, Clone)]
+impl OrderEvent {
+	pub fn ack(order: &Order, block_num: u64) -> OrderEvent {
+		OrderEvent::Ack(OrderAck {
+			trader_id: order.trader_id.clone(),
+			order_id: order.order_id,
+			client_order_id: order.client_order_id,
+			block_num,
+		})
+	}
+
+	pub fn reject(order: &Order, reason: &'static str, block_num: u64) -> OrderEvent {
+		OrderEvent::Reject(OrderReject {
+			trader_id: order.trader_id.clone(),
+			order_id: order.order_id,
+			client_order_id: order.client_order_id,
+			block_num,
+			reason: reason.to_string(),
+		})
+	}
+
+	pub fn trader_id(&self) -> &str {
+		match self {
+			OrderEvent::Ack(ack) => &ack.trader_id,
+			OrderEvent::Reject(rej) => &rej.trader_id,
+		}
+	}
+
+	pub fn order_id(&self) -> u64 {
+		match self {
+			OrderEvent::Ack(ack) => ack.order_id,
+			OrderEvent::Reject(rej) => rej.order_id,
+		}
+	}
+
+	fn set_block(&mut self, block_num: u64) {
+		match self {
+			OrderEvent::Ack(ack) => ack.block_num = block_num,
+			OrderEvent::Reject(rej) => rej.block_num = block_num,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TradeResults {
 	pub auction_type: MarketType,
 	pub uniform_price: Option<f64>,
 	pub agg_demand: f64,
 	pub agg_supply: f64,
 	pub cross_results: Option<Vec<PlayerUpdate>>,
+	pub slippage: Option<f64>,
+	pub symbol: String,
+	pub fills: Vec<Fill>,
+	pub tie_break: Option<TieBreak>,
+	pub order_events: Vec<OrderEvent>,
 }
 
 impl TradeResults {
@@ -61,8 +171,109 @@ impl TradeResults {
 			uniform_price: p,
 			agg_demand: agg_d,
 			agg_supply: agg_s,
-			cross_results: player_updates
+			cross_results: player_updates,
+			slippage: None,
+			symbol: String::from(DEFAULT_SYMBOL),
+			fills: Vec::new(),
+			tie_break: None,
+			order_events: Vec::new(),
+		}
+	}
 
+	/// Records a Fill for every trade in the results, stamped with the block it was published in
+	pub fn with_block(mut self, block_num: u64) -> TradeResults {
+		if let Some(updates) = &self.cross_results {
+			self.fills = updates.iter()
+				.filter(|u| !u.cancel && u.volume > 0.0)
+				.map(|u| Fill::from_update(u, block_num))
+				.collect();
+		}
+		for event in self.order_events.iter_mut() {
+			event.set_block(block_num);
+		}
+		self
+	}
+
+	/// Attaches an ack or reject to be routed back to the order's trader
+	pub fn with_order_event(mut self, event: OrderEvent) -> TradeResults {
+		self.order_events.push(event);
+		self
+	}
+
+	/// Sets the instrument these results were traded on
+	pub fn with_symbol(mut self, symbol: &str) -> TradeResults {
+		self.symbol = String::from(symbol);
+		self
+	}
+
+	/// Records how far the execution price moved from the pre-trade price (AMM swaps)
+	pub fn with_slippage(mut self, slippage: f64) -> TradeResults {
+		self.slippage = Some(slippage);
+		self
+	}
+
+	/// The price of the last trade in these results, cancels are ignored
+	pub fn last_price(&self) -> Option<f64> {
+		if self.uniform_price.is_some() {
+			return self.uniform_price;
+		}
+		match &self.cross_results {
+			Some(updates) => updates.iter().filter(|u| !u.cancel).map(|u| u.price).last(),
+			None => None,
+		}
+	}
+}
+
+/// The clearing price and order imbalance the next batch would have if it closed now.
+/// imbalance: bid volume less ask volume willing to trade at the indicative price
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndicativeData {
+	pub block_num: u64,
+	pub price: Option<f64>,
+	pub imbalance: f64,
+}
+
+impl IndicativeData {
+	pub fn new(block_num: u64, price: Option<f64>, imbalance: f64) -> IndicativeData {
+		IndicativeData {
+			block_num,
+			price,
+			imbalance,
+		}
+	}
+}
+
+/// The configurable pricing rules for batch auctions.
+/// tie_break: how the FBA picks between prices that clear the same volume
+/// last_price: the previous clearing price used by TieBreak::ClosestToLast
+/// pay_as_bid: SealedBid only, fills trade at their bid's price instead of the uniform price
+/// pricing: the PricingRule the FBA uses to pick its clearing price
+#[derive(Debug, Clone, Copy)]
+pub struct AuctionRules {
+	pub tie_break: TieBreak,
+	pub last_price: Option<f64>,
+	pub pay_as_bid: bool,
+	pub pricing: PricingRuleT,
+}
+
+impl AuctionRules {
+	pub fn new(consts: &Constants, last_price: Option<f64>) -> AuctionRules {
+		AuctionRules {
+			tie_break: consts.tie_break,
+			last_price,
+			pay_as_bid: consts.pay_as_bid,
+			pricing: consts.pricing_rule,
+		}
+	}
+}
+
+impl Default for AuctionRules {
+	fn default() -> AuctionRules {
+		AuctionRules {
+			tie_break: TieBreak::Midpoint,
+			last_price: None,
+			pay_as_bid: false,
+			pricing: PricingRuleT::MaxVolume,
 		}
 	}
 }
@@ -73,18 +284,39 @@ pub struct Auction {}
 
 impl Auction {
 
-	pub fn run_auction(bids: Arc<Book>, asks:Arc<Book>, m_t: MarketType) -> Option<TradeResults>{
+	pub fn run_auction(bids: Arc<Book>, asks:Arc<Book>, m_t: MarketType, rules: AuctionRules) -> Option<TradeResults>{
 		match m_t {
 			MarketType::CDA => None,
 			MarketType::FBA => {
-				Auction::frequent_batch_auction(bids, asks)
+				Auction::frequent_batch_auction_with(bids, asks, rules)
+			},
+			MarketType::SealedBid => {
+				Auction::sealed_bid_auction(bids, asks, rules)
 			},
 			MarketType::KLF => {
 				Auction::bs_cross(bids, asks)
 			},
+			// AMM swaps and Dutch auction fills are executed by the miner
+			MarketType::AMM|MarketType::Dutch => None,
 		}
 	}
 
+	/// Runs the end-of-block auction for the market's current phase. CDA markets in a 
+	/// call auction phase clear their accumulated books at a single uniform price once 
+	/// uncross is set, then refresh the best prices so continuous trading can resume.
+	pub fn run_phase_auction(bids: Arc<Book>, asks: Arc<Book>, m_t: MarketType, phase: MarketPhase, uncross: bool, rules: AuctionRules) -> Option<TradeResults> {
+		if !phase.is_call_auction() {
+			return Auction::run_auction(bids, asks, m_t, rules);
+		}
+		if !uncross {
+			// Still accumulating orders
+			return None;
+		}
+		let result = Auction::frequent_batch_auction_with(Arc::clone(&bids), Arc::clone(&asks), rules);
+		bids.find_new_max();
+		asks.find_new_min();
+		result
+	}
 		
 	/// ***CDA function***
 	/// Checks whether the new bid crosses the best ask. 
@@ -123,7 +355,7 @@ impl Auction {
 							best_ask.price,
 							new_bid.quantity,
 							false
-							));
+							).with_taker(TradeType::Bid));
 
 						// Return the best ask to the book
 						asks.push_to_end(best_ask).expect("couldn't push");
@@ -146,7 +378,7 @@ impl Auction {
 							best_ask.price,
 							best_ask.quantity,
 							false
-							));
+							).with_taker(TradeType::Bid));
 						
 						// Update the best ask price 
 						asks.find_new_min();
@@ -166,7 +398,7 @@ impl Auction {
 							best_ask.price,
 							new_bid.quantity,
 							false
-							));
+							).with_taker(TradeType::Bid));
 
 						// Update the best ask price 
 						asks.find_new_min();
@@ -227,7 +459,7 @@ impl Auction {
 							best_bid.price,
 							new_ask.quantity,
 							false
-							));
+							).with_taker(TradeType::Ask));
 
 						// Return the best bid to the book
 						bids.push_to_end(best_bid).expect("bad push");
@@ -250,7 +482,7 @@ impl Auction {
 							best_bid.price,
 							best_bid.quantity,
 							false
-							));
+							).with_taker(TradeType::Ask));
 						
 						// Update the best bid price 
 						bids.find_new_max();
@@ -270,7 +502,7 @@ impl Auction {
 							best_bid.price,
 							new_ask.quantity,
 							false,
-							));
+							).with_taker(TradeType::Ask));
 						
 						// Update the best bid price 
 						bids.find_new_max();
@@ -302,6 +534,49 @@ impl Auction {
 	/// Orders are sorted by price (descending for bids, ascending for asks).
 	/// Outputs the uniform clearing price if it exists and the total trade volume
 	pub fn frequent_batch_auction(bids: Arc<Book>, asks: Arc<Book>) -> Option<TradeResults> {
+		Auction::frequent_batch_auction_with(bids, asks, AuctionRules::default())
+	}
+
+	/// **SealedBid function**
+	/// Clears the sealed bids and asks like the FBA. Under the uniform rule every fill trades at the
+	/// clearing price, under pay_as_bid each fill trades at its bid's price and the volume weighted
+	/// average price is reported as the clearing price.
+	pub fn sealed_bid_auction(bids: Arc<Book>, asks: Arc<Book>, rules: AuctionRules) -> Option<TradeResults> {
+		// Remember the bid prices before the auction consumes the books
+		let bid_prices: HashMap<u64, f64> = bids.copy_orders().iter().map(|o| (o.order_id, o.price)).collect();
+		let mut result = Auction::frequent_batch_auction_with(bids, asks, rules)?;
+		result.auction_type = MarketType::SealedBid;
+		if !rules.pay_as_bid {
+			return Some(result);
+		}
+		if let Some(updates) = result.cross_results.as_mut() {
+			for pu in updates.iter_mut() {
+				if let Some(price) = bid_prices.get(&pu.payer_order_id) {
+					pu.price = *price;
+				}
+			}
+			let volume: f64 = updates.iter().map(|u| u.volume).sum();
+			if volume > 0.0 {
+				result.uniform_price = Some(updates.iter().map(|u| u.price * u.volume).sum::<f64>() / volume);
+			}
+		}
+		Some(result)
+	}
+
+	/// Picks a clearing price from the range [low, high] of prices that all clear the same volume
+	pub fn break_tie(low: f64, high: f64, tie_break: TieBreak, last_price: Option<f64>) -> f64 {
+		match (tie_break, last_price) {
+			(TieBreak::Max, _) => high,
+			(TieBreak::Min, _) => low,
+			(TieBreak::ClosestToLast, Some(p)) => Auction::min_float(&Auction::max_float(&p, &low), &high),
+			// Without a previous price fall back to the midpoint
+			(TieBreak::ClosestToLast, None) | (TieBreak::Midpoint, _) => (low + high) / 2.0,
+		}
+	}
+
+	/// The FBA with a configurable PricingRule and rule for choosing between clearing prices that
+	/// tie. The tie break is recorded in the TradeResults whenever a tie was broken.
+	pub fn frequent_batch_auction_with(bids: Arc<Book>, asks: Arc<Book>, rules: AuctionRules) -> Option<TradeResults> {
 		// Check if auction necessary
 		if bids.len() == 0 || asks.len() == 0 {
 			let result = TradeResults::new(MarketType::FBA, None, 0.0, 0.0, None);
@@ -314,95 +589,8 @@ impl Auction {
 			return Some(result);
 		}
 
-		// Calc total ask volume 
-		let ask_book_vol = asks.get_book_volume();
-		// Merge both books and sort in decreasing price order 
-		let merged_book = Book::merge_sort_books(Arc::clone(&bids), Arc::clone(&asks));
-
-		// Initialize the min and max prices seen while traversing the merged book
-		let mut max_seen_price = MIN_PRICE;
-		let mut min_seen_price = MAX_PRICE;
-		let mut clearing_price: Option<f64> = None;
-
-		// Initialize vars to track volume seen while traversing the merged book
-		let mut seen_vol = 0.0;
-		// let mut prev_seen_vol = 0.0;
-		let mut prev_order_price = 0.0;	// is 0.0 acceptable?
-		let mut cur_order_price = 0.0;
-
-		// Iterate through descending orders. Sum volume of each order and track the min and max seen prices
-		let orders = merged_book.orders.lock().expect("ERROR: Couldn't lock book to sort");
-		println!("Calculating clearing price...");
-		for order in orders.iter() {
-			cur_order_price = order.price;
-			// Process best prices
-			if cur_order_price > max_seen_price {
-				max_seen_price = cur_order_price;
-			}
-			if cur_order_price < min_seen_price {
-				min_seen_price = cur_order_price;
-			}
-
-			// Process seen volumes
-			// prev_seen_vol = seen_vol;
-			seen_vol += order.quantity;
-			println!("Checking price:{}, seen_vol:{} / ask_vol:{}", cur_order_price, seen_vol, ask_book_vol);
-			if seen_vol >= ask_book_vol {
-				// NOTE: darrell's implementation didn't include <=, just <, but this fixed horizontal cross edge case
-				break;
-			}
-			// Track the price of the last traversed order
-			prev_order_price = cur_order_price;
-		}	
-
-		// If we have still not found a max or min seen price, loop until we do:
-		if max_seen_price == MIN_PRICE || min_seen_price == MAX_PRICE {
-			for order in orders.iter() {
-				cur_order_price = order.price;
-				// Process best prices
-				if cur_order_price > max_seen_price {
-					max_seen_price = cur_order_price;
-				}
-				if cur_order_price < min_seen_price {
-					min_seen_price = cur_order_price;
-				}
-				println!("Looping until price < {}, cur_price={}", MAX_PRICE, cur_order_price);
-				if cur_order_price < MAX_PRICE {
-					break;
-				}
-			}
-		}
-
-		// Find the clearing price
-		if max_seen_price == MIN_PRICE && min_seen_price == MAX_PRICE {
-			// We weren't able to find a clearing price
-			clearing_price = None;
-		} 
-		// We perfectly matched volume
-		else if seen_vol == ask_book_vol {	
-			if prev_order_price == MAX_PRICE && MIN_PRICE < cur_order_price && cur_order_price < MAX_PRICE {
-				// The current order crossed, so use this price
-				clearing_price = Some(cur_order_price);
-			} 
-			
-			else if prev_order_price < MAX_PRICE && MIN_PRICE < cur_order_price {
-				// let p = round::ceil((prev_order_price + cur_order_price) / 2.0, PRECISION);
-				let p = (prev_order_price + cur_order_price) / 2.0;		// NOTE changed this from darrell's...confirm with dan
-				clearing_price = Some(p);
-			}
-
-			else if MIN_PRICE < prev_order_price && prev_order_price < MAX_PRICE && cur_order_price == MIN_PRICE {
-				clearing_price = Some(prev_order_price);
-			}
-
-			else if prev_order_price == MIN_PRICE {
-				clearing_price = Some(min_seen_price);
-			}
-		}
-		// The last order's volume caused us to cross
-		else if seen_vol > ask_book_vol {
-			clearing_price = Some(Auction::max_float(&cur_order_price, &min_seen_price));
-		}
+		// Find the clearing price with the configured pricing rule
+		let (clearing_price, tie_fired) = rules.pricing.rule().clearing_price(Arc::clone(&bids), Arc::clone(&asks), &rules);
 
 		println!("Clearing price: {:?}", clearing_price);
 
@@ -412,6 +600,7 @@ impl Auction {
 		let mut updates = Vec::<PlayerUpdate>::new();
 
 		let mut result = TradeResults::new(MarketType::FBA, clearing_price, 0.0, 0.0, None);
+		result.tie_break = tie_fired;
 
 		let mut cancel_bids = Vec::<u64>::new();
 		let mut _vol_filled = 0.0;
@@ -527,6 +716,43 @@ impl Auction {
 	}
 
 
+	/// Calculates the indicative price and order imbalance of the next batch by auctioning
+	/// copies of the books with the pending orders added, leaving the real books untouched.
+	/// Without a cross the imbalance is the total bid volume less the total ask volume.
+	pub fn indicative_auction(bids: Arc<Book>, asks: Arc<Book>, pending: Vec<Order>, m_t: MarketType, rules: AuctionRules) -> (Option<f64>, f64) {
+		let orders: Vec<Order> = bids.copy_orders().into_iter().chain(asks.copy_orders()).chain(pending).collect();
+		let bids_copy = Arc::new(Book::new(TradeType::Bid));
+		let asks_copy = Arc::new(Book::new(TradeType::Ask));
+		for order in orders.iter() {
+			match order.trade_type {
+				TradeType::Bid => bids_copy.add_order(order.clone()).expect("indicative add_order"),
+				TradeType::Ask => asks_copy.add_order(order.clone()).expect("indicative add_order"),
+			}
+		}
+
+		let price = match m_t {
+			MarketType::FBA|MarketType::KLF => {
+				Auction::run_auction(bids_copy, asks_copy, m_t, rules).and_then(|r| r.uniform_price)
+			},
+			_ => None,
+		};
+
+		let imbalance: f64 = orders.iter().map(|o| {
+			match (price, &o.trade_type) {
+				(None, TradeType::Bid) => o.quantity,
+				(None, TradeType::Ask) => -o.quantity,
+				(Some(p), TradeType::Bid) if m_t == MarketType::KLF => o.calc_flow_demand(p),
+				(Some(p), TradeType::Ask) if m_t == MarketType::KLF => -o.calc_flow_supply(p),
+				(Some(p), TradeType::Bid) if o.price >= p => o.quantity,
+				(Some(p), TradeType::Ask) if o.price <= p => -o.quantity,
+				_ => 0.0,
+			}
+		}).sum();
+
+		(price, imbalance)
+	}
+
+
 	/// Helper function for Flow Order clearing price calculation: bs_cross
 	/// Iterate over each order in parallel and compute the aggregate supply and
 	/// demand at a certain price.
@@ -556,43 +782,14 @@ impl Auction {
 	/// binary search to find the intersection point between the aggregates supply and 
 	/// demand curves. 
 	pub fn bs_cross(bids: Arc<Book>, asks: Arc<Book>) -> Option<TradeResults> {
-		// get_price_bounds obtains locks on the book's prices
-	    let (mut left, mut right) = Auction::get_price_bounds(Arc::clone(&bids), Arc::clone(&asks));
-	    let mut curr_iter = 0;
-	    println!("Min Book price: {}, Max Book price: {}", left, right);
-	    while left < right {
-	    	curr_iter += 1;
-	    	// Find a midpoint with the correct price tick precision
-	    	let index: f64 = (left + right) / 2.0;
-	    	// Calculate the aggregate supply and demand at this price
-	    	let (dem, sup) = Auction::calc_aggs(index, Arc::clone(&bids), Arc::clone(&asks));
-	    	// println!("price_index: {}, dem: {}, sup: {}", index, dem, sup);
-
-	    	if Auction::greater_than_e(&dem, &sup) {  		// dev > sup
-	    		// We are left of the crossing point
-	    		left = index;
-	    	} else if Auction::less_than_e(&dem, &sup) {	// sup > dem
-	    		// We are right of the crossing point
-	    		right = index;
-	    	} else {
-	    		println!("Found cross at: {}\n", index);
-	    		let mut result = TradeResults::new(MarketType::KLF, Some(index), dem, sup, None);
-	    		// Push the player updates for updating the player's state in ClearingHouse
-	    		let player_updates = Auction::flow_player_updates(index, Arc::clone(&bids), Arc::clone(&asks));
-	    		result.cross_results = Some(player_updates);
-	    		return Some(result);
-	    	}
-
-	    	if curr_iter == MAX_ITERS {
-	    		println!("Trouble finding cross in max iterations, got: {}", index);
-	    		let mut result = TradeResults::new(MarketType::KLF, Some(index), dem, sup, None);
-	    		// Push the player updates for updating the player's state in ClearingHouse
-	    		let player_updates = Auction::flow_player_updates(index, Arc::clone(&bids), Arc::clone(&asks));
-	    		result.cross_results = Some(player_updates);
-	    		return Some(result);
-	    	}
-	    }
-	    None
+		let (price, _) = FlowCross {}.clearing_price(Arc::clone(&bids), Arc::clone(&asks), &AuctionRules::default());
+		let price = price?;
+		let (dem, sup) = Auction::calc_aggs(price, Arc::clone(&bids), Arc::clone(&asks));
+		let mut result = TradeResults::new(MarketType::KLF, Some(price), dem, sup, None);
+		// Push the player updates for updating the player's state in ClearingHouse
+		let player_updates = Auction::flow_player_updates(price, Arc::clone(&bids), Arc::clone(&asks));
+		result.cross_results = Some(player_updates);
+		Some(result)
 	}
 
 	pub fn klf_clearing(bids: Arc<Book>, asks: Arc<Book>) -> Option<f64> {
@@ -718,7 +915,7 @@ impl Auction {
 		(Auction::min_float(&bids_min, &asks_min), Auction::max_float(&bids_max, &asks_max))
 	}
 
-	fn max_float(a: &f64, b: &f64) -> f64 {
+	pub fn max_float(a: &f64, b: &f64) -> f64 {
 	    match a.partial_cmp(b).unwrap() {
 			Ordering::Less => *b,
 			Ordering::Greater => *a,
@@ -785,6 +982,92 @@ fn test_min_max_float() {
 	assert_eq!(10.0, Auction::max_float(&a, &b));
 }
 
+#[test]
+fn test_fills_from_results() {
+	let cross = PlayerUpdate::new(String::from("bidder"), String::from("asker"), 1, 2, 100.0, 5.0, false).with_taker(TradeType::Ask);
+	let cancel = PlayerUpdate::new(String::from("bidder"), String::from("bidder"), 3, 3, -9.99, -9.99, true);
+	let results = TradeResults::new(MarketType::CDA, None, 0.0, 0.0, Some(vec![cross, cancel])).with_block(7);
+
+	// Cancels don't produce fills
+	assert_eq!(results.fills.len(), 1);
+	let fill = &results.fills[0];
+	assert_eq!(fill.block_num, 7);
+	assert_eq!(fill.aggressor, Some(TradeType::Ask));
+	assert_eq!((fill.bid_order_id, fill.ask_order_id), (1, 2));
+	assert_eq!((fill.price, fill.quantity), (100.0, 5.0));
+}
+
+#[test]
+fn test_indicative_auction() {
+	use crate::order::order::{OrderType, ExchangeType};
+	let order = |t_t: TradeType, price: f64, quantity: f64| {
+		Order::new(String::from("trader"), OrderType::Enter, t_t, ExchangeType::LimitOrder,
+			price, price, price, quantity, quantity, 0.1)
+	};
+	let bids = Arc::new(Book::new(TradeType::Bid));
+	let asks = Arc::new(Book::new(TradeType::Ask));
+	bids.add_order(order(TradeType::Bid, 101.0, 10.0)).unwrap();
+
+	// Only bids are resting so nothing would cross
+	let (price, imbalance) = Auction::indicative_auction(Arc::clone(&bids), Arc::clone(&asks), vec![], MarketType::FBA, AuctionRules::default());
+	assert_eq!(price, None);
+	assert_eq!(imbalance, 10.0);
+
+	// A pending ask would cross leaving 6 shares of unmatched demand
+	let pending = vec![order(TradeType::Ask, 99.0, 4.0)];
+	let (price, imbalance) = Auction::indicative_auction(Arc::clone(&bids), Arc::clone(&asks), pending, MarketType::FBA, AuctionRules::default());
+	assert_eq!(price, Some(101.0));
+	assert_eq!(imbalance, 6.0);
+
+	// The real books are untouched
+	assert_eq!(bids.len(), 1);
+	assert_eq!(asks.len(), 0);
+}
+
+#[test]
+fn test_break_tie() {
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::Midpoint, Some(110.0)), 102.0);
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::Max, None), 104.0);
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::Min, None), 100.0);
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::ClosestToLast, Some(110.0)), 104.0);
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::ClosestToLast, Some(101.5)), 101.5);
+	assert_eq!(Auction::break_tie(100.0, 104.0, TieBreak::ClosestToLast, None), 102.0);
+}
+
+#[test]
+fn test_sealed_bid_auction() {
+	use crate::order::order::{OrderType, ExchangeType};
+	let order = |t_t: TradeType, price: f64, quantity: f64| {
+		Order::new(String::from("trader"), OrderType::Enter, t_t, ExchangeType::LimitOrder,
+			price, price, price, quantity, quantity, 0.1)
+	};
+	let books = || {
+		let bids = Arc::new(Book::new(TradeType::Bid));
+		let asks = Arc::new(Book::new(TradeType::Ask));
+		bids.add_order(order(TradeType::Bid, 105.0, 5.0)).unwrap();
+		bids.add_order(order(TradeType::Bid, 101.0, 5.0)).unwrap();
+		asks.add_order(order(TradeType::Ask, 99.0, 10.0)).unwrap();
+		(bids, asks)
+	};
+	let mut rules = AuctionRules::default();
+	rules.tie_break = TieBreak::Min;
+
+	// Uniform: both bids fill at the clearing price
+	let (bids, asks) = books();
+	let res = Auction::sealed_bid_auction(bids, asks, rules).unwrap();
+	assert_eq!(res.auction_type, MarketType::SealedBid);
+	assert_eq!(res.uniform_price, Some(101.0));
+	assert!(res.cross_results.unwrap().iter().all(|u| u.price == 101.0));
+
+	// Pay-as-bid: each bid pays its own price
+	rules.pay_as_bid = true;
+	let (bids, asks) = books();
+	let res = Auction::sealed_bid_auction(bids, asks, rules).unwrap();
+	let prices: Vec<f64> = res.cross_results.unwrap().iter().map(|u| u.price).collect();
+	assert_eq!(prices, vec![105.0, 101.0]);
+	assert_eq!(res.uniform_price, Some(103.0));
+}
+
 #[test]
 fn test_float_helpers() {
 	let a = 2.0;
@@ -0,0 +1,208 @@
+use crate::simulation::simulation_config::Constants;
+use crate::simulation::simulation_history::UpdateReason;
+use crate::exchange::exchange_logic::PlayerUpdate;
+use crate::order::order::TradeType;
+use crate::players::{Player, TraderT};
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A charge the ClearingHouse levies on players. Each hook returns what the player owes,
+/// a negative amount is paid to them. Hooks a policy doesn't use charge nothing.
+pub trait FeePolicy {
+	/// The reason the policy's charges are logged with
+	fn reason(&self) -> UpdateReason;
+
+	/// The name the policy's charges are broken down by in the results
+	fn label(&self) -> String {
+		format!("{:?}", self.reason())
+	}
+
+	/// Charged to each (trader_id, amount) side of a fill
+	fn on_fill(&self, _pu: &PlayerUpdate) -> Vec<(String, f64)> {
+		Vec::new()
+	}
+
+	/// Charged to every player at the end of each block
+	fn on_block(&self, _player: &dyn Player) -> f64 {
+		0.0
+	}
+
+	/// Charged on top of the gas a player paid for a block
+	fn on_gas(&self, _trader_id: &str, _gas: f64) -> f64 {
+		0.0
+	}
+}
+
+/// Charges the taker fee and pays the maker rebate as fractions of each fill's notional.
+/// Batch fills have no taker so both sides pay the taker fee.
+pub struct TradingFee {
+	pub taker_fee: f64,
+	pub maker_rebate: f64,
+}
+
+impl FeePolicy for TradingFee {
+	fn reason(&self) -> UpdateReason {
+		UpdateReason::Fee
+	}
+
+	fn on_fill(&self, pu: &PlayerUpdate) -> Vec<(String, f64)> {
+		if pu.volume <= 0.0 {
+			return Vec::new();
+		}
+		let (bid_rate, ask_rate) = match pu.taker {
+			Some(TradeType::Bid) => (self.taker_fee, -self.maker_rebate),
+			Some(TradeType::Ask) => (-self.maker_rebate, self.taker_fee),
+			None => (self.taker_fee, self.taker_fee),
+		};
+		let notional = pu.price * pu.volume;
+		vec![(pu.payer_id.clone(), bid_rate * notional), (pu.vol_filler_id.clone(), ask_rate * notional)]
+	}
+}
+
+/// Taxes each share of inventory held, long or short, every block.
+/// trader_type: Option<TraderT> -> only players of this type are taxed, None taxes everyone
+pub struct InventoryTax {
+	pub tax: f64,
+	pub trader_type: Option<TraderT>,
+}
+
+impl FeePolicy for InventoryTax {
+	fn reason(&self) -> UpdateReason {
+		UpdateReason::Tax
+	}
+
+	fn label(&self) -> String {
+		format!("{:?}", TaxBasis::Inventory)
+	}
+
+	fn on_block(&self, player: &dyn Player) -> f64 {
+		match self.trader_type {
+			Some(t) if t != player.get_player_type() => 0.0,
+			_ => player.get_positions().values().map(|inv| (inv * self.tax).abs()).sum(),
+		}
+	}
+}
+
+/// What a TaxRule is levied on
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum TaxBasis {
+	Inventory,	// Shares held, long or short, when the tax is charged
+	Turnover,	// Shares traded since the last charge
+	Profit,		// Balance gained since the last charge, losses aren't taxed
+}
+
+/// One row of the tax schedule csv
+/// player_type: Option<TraderT> -> only players of this type are taxed, empty taxes everyone
+/// interval: u64 -> the tax is charged every this many blocks
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct TaxRule {
+	pub player_type: Option<TraderT>,
+	pub basis: TaxBasis,
+	pub rate: f64,
+	pub interval: u64,
+}
+
+// A player's turnover and starting balance since their last charge
+struct TaxPeriod {
+	blocks: u64,
+	turnover: f64,
+	start_bal: f64,
+}
+
+/// Charges a TaxRule, tracking each player's period between charges
+pub struct Tax {
+	pub rule: TaxRule,
+	periods: Mutex<HashMap<String, TaxPeriod>>,
+}
+
+impl Tax {
+	pub fn new(rule: TaxRule) -> Tax {
+		Tax {
+			rule,
+			periods: Mutex::new(HashMap::new()),
+		}
+	}
+}
+
+impl FeePolicy for Tax {
+	fn reason(&self) -> UpdateReason {
+		UpdateReason::Tax
+	}
+
+	fn label(&self) -> String {
+		format!("{:?}", self.rule.basis)
+	}
+
+	fn on_fill(&self, pu: &PlayerUpdate) -> Vec<(String, f64)> {
+		if self.rule.basis == TaxBasis::Turnover && pu.volume > 0.0 {
+			let mut periods = self.periods.lock().unwrap();
+			// Only turnover taxes see fills so the starting balance is never read
+			for id in vec![&pu.payer_id, &pu.vol_filler_id] {
+				periods.entry(id.clone())
+					.or_insert(TaxPeriod { blocks: 0, turnover: 0.0, start_bal: 0.0 })
+					.turnover += pu.volume;
+			}
+		}
+		Vec::new()
+	}
+
+	fn on_block(&self, player: &dyn Player) -> f64 {
+		match self.rule.player_type {
+			Some(t) if t != player.get_player_type() => return 0.0,
+			_ => {},
+		}
+		let mut periods = self.periods.lock().unwrap();
+		let period = periods.entry(player.get_id())
+			.or_insert(TaxPeriod { blocks: 0, turnover: 0.0, start_bal: player.get_bal() });
+		period.blocks += 1;
+		if period.blocks < self.rule.interval.max(1) {
+			return 0.0;
+		}
+
+		let tax = match self.rule.basis {
+			TaxBasis::Inventory => player.get_positions().values().map(|inv| inv.abs()).sum::<f64>() * self.rule.rate,
+			TaxBasis::Turnover => period.turnover * self.rule.rate,
+			TaxBasis::Profit => (player.get_bal() - period.start_bal).max(0.0) * self.rule.rate,
+		};
+		*period = TaxPeriod { blocks: 0, turnover: 0.0, start_bal: player.get_bal() - tax };
+		tax
+	}
+}
+
+/// Charges a fraction of the gas each player paid for a block
+pub struct GasSurcharge {
+	pub rate: f64,
+}
+
+impl FeePolicy for GasSurcharge {
+	fn reason(&self) -> UpdateReason {
+		UpdateReason::GasSurcharge
+	}
+
+	fn on_gas(&self, _trader_id: &str, gas: f64) -> f64 {
+		gas * self.rate
+	}
+}
+
+/// The fee policies the constants configure
+pub fn policies_from(consts: &Constants) -> Vec<Box<dyn FeePolicy + Send>> {
+	let mut policies = Vec::<Box<dyn FeePolicy + Send>>::new();
+	if consts.taker_fee != 0.0 || consts.maker_rebate != 0.0 {
+		policies.push(Box::new(TradingFee { taker_fee: consts.taker_fee, maker_rebate: consts.maker_rebate }));
+	}
+	if consts.maker_inv_tax != 0.0 {
+		policies.push(Box::new(InventoryTax { tax: consts.maker_inv_tax, trader_type: Some(TraderT::Maker) }));
+	}
+	if consts.gas_surcharge != 0.0 {
+		policies.push(Box::new(GasSurcharge { rate: consts.gas_surcharge }));
+	}
+	policies
+}
+
+/// A Tax policy for each rule of a tax schedule
+pub fn policies_from_schedule(rules: Vec<TaxRule>) -> Vec<Box<dyn FeePolicy + Send>> {
+	rules.into_iter()
+		.map(|rule| Box::new(Tax::new(rule)) as Box<dyn FeePolicy + Send>)
+		.collect()
+}
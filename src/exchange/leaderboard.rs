@@ -0,0 +1,117 @@
+use crate::exchange::ledger::LedgerEntry;
+use crate::simulation::simulation_history::UpdateReason;
+use crate::order::order::OrderType;
+use crate::players::{Player, TraderT};
+
+/// A player's results over the run, built from their ledger and the orders they sent
+/// profit: f64 -> the balance they gained, inventory that hasn't been liquidated isn't counted
+/// volume: f64 -> the shares they traded
+/// cancel_ratio: f64 -> cancels sent per order entered
+/// fees_paid: f64 -> gas, trading fees and surcharges net of rebates
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Standing {
+	pub rank: usize,
+	pub trader_id: String,
+	pub player_type: TraderT,
+	pub profit: f64,
+	pub volume: f64,
+	pub orders_sent: usize,
+	pub cancels: usize,
+	pub cancel_ratio: f64,
+	pub fees_paid: f64,
+}
+
+impl Standing {
+	/// The player's standing, ranked once every player's is known
+	pub fn new(player: &dyn Player, entries: &[LedgerEntry]) -> Standing {
+		let sent = player.copy_sent_orders();
+		let orders_sent = sent.iter().filter(|(_, t)| *t == OrderType::Enter).count();
+		let cancels = sent.iter().filter(|(_, t)| *t == OrderType::Cancel || *t == OrderType::MassCancel).count();
+		Standing {
+			rank: 0,
+			trader_id: player.get_id(),
+			player_type: player.get_player_type(),
+			profit: entries.iter().map(|e| e.cash).sum(),
+			volume: entries.iter()
+				.filter(|e| if let UpdateReason::Transact = e.reason { true } else { false })
+				.map(|e| e.shares.abs())
+				.sum(),
+			orders_sent,
+			cancels,
+			cancel_ratio: match orders_sent {
+				0 => 0.0,
+				n => cancels as f64 / n as f64,
+			},
+			fees_paid: -entries.iter()
+				.filter(|e| match e.reason {
+					UpdateReason::Gas | UpdateReason::Fee | UpdateReason::GasSurcharge => true,
+					_ => false,
+				})
+				.map(|e| e.cash)
+				.sum::<f64>(),
+		}
+	}
+}
+
+/// Sorts the standings by profit, best first, and numbers their ranks
+pub fn rank(mut standings: Vec<Standing>) -> Vec<Standing> {
+	standings.sort_by(|a, b| b.profit.partial_cmp(&a.profit).expect("rank"));
+	for (i, standing) in standings.iter_mut().enumerate() {
+		standing.rank = i + 1;
+	}
+	standings
+}
+
+pub fn to_csv(standings: &[Standing]) -> Result<String, Box<dyn std::error::Error>> {
+	let mut wtr = csv::Writer::from_writer(Vec::new());
+	for standing in standings {
+		wtr.serialize(standing)?;
+	}
+	Ok(String::from_utf8(wtr.into_inner()?)?)
+}
+
+pub fn to_json(standings: &[Standing]) -> Result<String, serde_json::Error> {
+	serde_json::to_string_pretty(standings)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::players::investor::Investor;
+	use crate::order::order::{Order, TradeType, ExchangeType};
+
+	fn entry(reason: UpdateReason, cash: f64, shares: f64) -> LedgerEntry {
+		LedgerEntry {
+			block_num: 0,
+			reason,
+			symbol: None,
+			cash,
+			shares,
+			order_ids: Vec::new(),
+			balance: 0.0,
+			inventory: 0.0,
+		}
+	}
+
+	#[test]
+	fn test_rank() {
+		let mut a = Investor::new(format!("a"));
+		for _ in 0..4 {
+			a.add_order(Order::new(format!("a"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+				100.0, 100.0, 100.0, 1.0, 1.0, 0.1));
+		}
+		a.add_to_sent(1, OrderType::Cancel);
+		let a = Standing::new(&a, &[entry(UpdateReason::Transact, -100.0, 1.0), entry(UpdateReason::Gas, -0.5, 0.0),
+			entry(UpdateReason::Liquify, 110.0, -1.0)]);
+		assert_eq!((a.profit, a.volume, a.fees_paid), (9.5, 1.0, 0.5));
+		assert_eq!((a.orders_sent, a.cancels, a.cancel_ratio), (4, 1, 0.25));
+
+		let b = Standing::new(&Investor::new(format!("b")), &[entry(UpdateReason::Transact, 20.0, -1.0)]);
+		let ranked = rank(vec![a, b]);
+		assert_eq!((ranked[0].trader_id.as_str(), ranked[0].rank), ("b", 1));
+		assert_eq!((ranked[1].trader_id.as_str(), ranked[1].rank), ("a", 2));
+		assert_eq!(to_csv(&ranked).unwrap().lines().count(), 3);
+		assert!(to_json(&ranked).unwrap().contains("\"cancel_ratio\": 0.25"));
+	}
+}
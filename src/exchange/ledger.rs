@@ -0,0 +1,101 @@
+use crate::simulation::simulation_history::UpdateReason;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// One change to a player's balance or inventory
+/// symbol: Option<String> -> the instrument the shares changed in, None for cash-only changes
+/// order_ids: Vec<u64> -> the orders on both sides of the fill that caused the change, if any
+/// balance: f64 -> the player's balance after the change
+/// inventory: f64 -> the player's inventory of symbol after the change, total inventory if None
+#[derive(Debug, Clone)]
+pub struct LedgerEntry {
+	pub block_num: u64,
+	pub reason: UpdateReason,
+	pub symbol: Option<String>,
+	pub cash: f64,
+	pub shares: f64,
+	pub order_ids: Vec<u64>,
+	pub balance: f64,
+	pub inventory: f64,
+}
+
+/// An append-only record of every change the ClearingHouse makes to each player
+/// block_num: Mutex<u64> -> the block entries are currently recorded against
+/// entries: Mutex<HashMap<String, Vec<LedgerEntry>>> -> each player's entries in order, indexed by trader_id
+pub struct Ledger {
+	pub block_num: Mutex<u64>,
+	pub entries: Mutex<HashMap<String, Vec<LedgerEntry>>>,
+}
+
+impl Ledger {
+	pub fn new() -> Ledger {
+		Ledger {
+			block_num: Mutex::new(0),
+			entries: Mutex::new(HashMap::new()),
+		}
+	}
+
+	pub fn set_block(&self, block_num: u64) {
+		*self.block_num.lock().unwrap() = block_num;
+	}
+
+	pub fn read_block(&self) -> u64 {
+		*self.block_num.lock().unwrap()
+	}
+
+	pub fn record(&self, trader_id: &str, entry: LedgerEntry) {
+		self.entries.lock().expect("ledger record")
+			.entry(trader_id.to_string())
+			.or_insert(Vec::new())
+			.push(entry);
+	}
+
+	/// A copy of the player's entries in the order they were recorded
+	pub fn entries_for(&self, trader_id: &str) -> Vec<LedgerEntry> {
+		match self.entries.lock().expect("ledger entries_for").get(trader_id) {
+			Some(entries) => entries.clone(),
+			None => Vec::new(),
+		}
+	}
+
+	/// The player's total (cash, shares) change over all of their entries
+	pub fn net_change(&self, trader_id: &str) -> (f64, f64) {
+		self.entries_for(trader_id).iter()
+			.fold((0.0, 0.0), |(c, s), e| (c + e.cash, s + e.shares))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(block_num: u64, cash: f64, shares: f64) -> LedgerEntry {
+		LedgerEntry {
+			block_num,
+			reason: UpdateReason::Transact,
+			symbol: Some(format!("SYM0")),
+			cash,
+			shares,
+			order_ids: vec![1, 2],
+			balance: 0.0,
+			inventory: 0.0,
+		}
+	}
+
+	#[test]
+	fn test_ledger() {
+		let ledger = Ledger::new();
+		ledger.record("a", entry(0, -100.0, 1.0));
+		ledger.set_block(1);
+		ledger.record("a", entry(ledger.read_block(), 45.0, -0.5));
+		ledger.record("b", entry(ledger.read_block(), 55.0, -0.5));
+
+		let entries = ledger.entries_for("a");
+		assert_eq!(entries.len(), 2);
+		assert_eq!(entries[1].block_num, 1);
+		assert_eq!(ledger.net_change("a"), (-55.0, 0.5));
+		assert_eq!(ledger.entries_for("c").len(), 0);
+	}
+}
@@ -0,0 +1,175 @@
+use crate::simulation::simulation_config::Constants;
+use crate::exchange::MarketType;
+
+use std::sync::Mutex;
+
+/// The trading phases of a session. Only CDA markets leave Continuous: during a
+/// call auction phase orders rest in the books without crossing until a single
+/// uniform-price auction clears them. A VolatilityAuction interrupts Continuous
+/// trading for a fixed number of blocks after a large price move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarketPhase {
+	OpeningAuction,
+	Continuous,
+	VolatilityAuction,
+	ClosingAuction,
+}
+
+impl MarketPhase {
+	/// Determines the phase a block falls in from the configured auction lengths
+	pub fn for_block(block_num: u64, consts: &Constants) -> MarketPhase {
+		if consts.market_type != MarketType::CDA {
+			// FBA and KLF are already batch auctions every block
+			return MarketPhase::Continuous;
+		}
+
+		if block_num < consts.opening_auction_blocks {
+			MarketPhase::OpeningAuction
+		} else if consts.closing_auction_blocks > 0 && block_num + consts.closing_auction_blocks > consts.num_blocks {
+			MarketPhase::ClosingAuction
+		} else {
+			MarketPhase::Continuous
+		}
+	}
+
+	pub fn is_call_auction(&self) -> bool {
+		*self != MarketPhase::Continuous
+	}
+}
+
+
+/// Threadsafe market phase shared between the miner task and the exchange logic
+/// volatility_until: first block continuous trading resumes on after the latest volatility auction
+pub struct PhaseState {
+	pub phase: Mutex<MarketPhase>,
+	pub volatility_until: Mutex<Option<u64>>,
+}
+
+impl PhaseState {
+	pub fn new(consts: &Constants) -> PhaseState {
+		PhaseState {
+			phase: Mutex::new(MarketPhase::for_block(0, consts)),
+			volatility_until: Mutex::new(None),
+		}
+	}
+
+	/// Switches a continuously trading CDA to a volatility auction for the next
+	/// volatility_auction_blocks blocks. Returns true if the auction was started.
+	pub fn trigger_volatility_auction(&self, block_num: u64, consts: &Constants) -> bool {
+		if consts.volatility_auction_blocks == 0 || self.read_phase() != MarketPhase::Continuous {
+			return false;
+		}
+		let mut until = self.volatility_until.lock().expect("trigger_volatility_auction");
+		if let Some(u) = *until {
+			if block_num < u {
+				return false;
+			}
+		}
+		*until = Some(block_num + 1 + consts.volatility_auction_blocks);
+		true
+	}
+
+	/// The first block whose prices count towards the volatility trigger. Prices
+	/// from before the end of the latest volatility auction are ignored.
+	pub fn volatility_window_start(&self, block_num: u64, consts: &Constants) -> u64 {
+		let start = block_num.saturating_sub(consts.volatility_window);
+		match *self.volatility_until.lock().expect("volatility_window_start") {
+			Some(until) if until > start => until,
+			_ => start,
+		}
+	}
+
+	pub fn read_phase(&self) -> MarketPhase {
+		*self.phase.lock().expect("read_phase")
+	}
+
+	/// Moves the state machine to the phase of block_num. Returns true if this is the
+	/// last block of a call auction phase and the accumulated books should be uncrossed.
+	pub fn update(&self, block_num: u64, consts: &Constants) -> bool {
+		let mut next = MarketPhase::for_block(block_num, consts);
+		let volatility_until = *self.volatility_until.lock().expect("update phase");
+		if let Some(until) = volatility_until {
+			if next == MarketPhase::Continuous && block_num < until {
+				next = MarketPhase::VolatilityAuction;
+			}
+		}
+		{
+			let mut phase = self.phase.lock().expect("update phase");
+			if *phase != next {
+				info!("Market phase {:?} -> {:?} @ block {}", *phase, next, block_num);
+			}
+			*phase = next;
+		}
+
+		if !next.is_call_auction() {
+			return false;
+		}
+		// The volatility auction uncrosses on its last block
+		if next == MarketPhase::VolatilityAuction {
+			return block_num + 1 >= volatility_until.expect("volatility_until");
+		}
+		// The closing auction uncrosses on the final block of the simulation
+		block_num == consts.num_blocks || MarketPhase::for_block(block_num + 1, consts) != next
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn consts(m_t: MarketType) -> Constants {
+		let mut c = Constants::new(300, 10, 10, 100, 20, m_t, 0.0, 0.25, 1, 0.25, 0.25, 5.0, 0.01, 10, 0.5);
+		c.opening_auction_blocks = 3;
+		c.closing_auction_blocks = 2;
+		c
+	}
+
+	#[test]
+	fn test_phase_for_block() {
+		let c = consts(MarketType::CDA);
+		assert_eq!(MarketPhase::for_block(0, &c), MarketPhase::OpeningAuction);
+		assert_eq!(MarketPhase::for_block(2, &c), MarketPhase::OpeningAuction);
+		assert_eq!(MarketPhase::for_block(3, &c), MarketPhase::Continuous);
+		assert_eq!(MarketPhase::for_block(18, &c), MarketPhase::Continuous);
+		assert_eq!(MarketPhase::for_block(19, &c), MarketPhase::ClosingAuction);
+		assert_eq!(MarketPhase::for_block(20, &c), MarketPhase::ClosingAuction);
+
+		// Batch markets never change phase
+		let c = consts(MarketType::FBA);
+		assert_eq!(MarketPhase::for_block(0, &c), MarketPhase::Continuous);
+	}
+
+	#[test]
+	fn test_phase_uncross() {
+		let c = consts(MarketType::CDA);
+		let state = PhaseState::new(&c);
+		let uncrossed: Vec<u64> = (0..=c.num_blocks).filter(|b| state.update(*b, &c)).collect();
+		assert_eq!(uncrossed, vec![2, 20]);
+	}
+
+	#[test]
+	fn test_volatility_auction() {
+		let mut c = consts(MarketType::CDA);
+		c.volatility_auction_blocks = 3;
+		c.volatility_window = 5;
+		let state = PhaseState::new(&c);
+		for b in 0..6 {
+			state.update(b, &c);
+		}
+
+		// A move on block 5 auctions blocks 6, 7 and 8, uncrossing on 8
+		assert!(state.trigger_volatility_auction(5, &c));
+		assert!(!state.update(6, &c));
+		assert_eq!(state.read_phase(), MarketPhase::VolatilityAuction);
+		assert!(!state.trigger_volatility_auction(6, &c));
+		assert!(!state.update(7, &c));
+		assert!(state.update(8, &c));
+		assert!(!state.update(9, &c));
+		assert_eq!(state.read_phase(), MarketPhase::Continuous);
+
+		// Prices from before the auction ended don't count
+		assert_eq!(state.volatility_window_start(10, &c), 9);
+		assert_eq!(state.volatility_window_start(16, &c), 11);
+	}
+}
@@ -1,11 +1,99 @@
 pub mod exchange_logic;
 pub mod clearing_house;
+pub mod market_phase;
+pub mod circuit_breaker;
+pub mod amm;
+pub mod dutch;
+pub mod settlement;
+pub mod pricing;
+pub mod fees;
+pub mod ledger;
+pub mod undo_log;
+pub mod pnl;
+pub mod query;
+pub mod observer;
+pub mod leaderboard;
 
-#[derive(Debug, Copy, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Serialize, Deserialize, PartialEq)]
 pub enum MarketType {
 	CDA,
 	FBA,
 	KLF,
+	AMM,
+	SealedBid,
+	Dutch,
+}
+
+/// How the FBA picks a clearing price when a range of prices clears the same volume
+#[derive(Debug, Copy, Serialize, Deserialize, PartialEq)]
+pub enum TieBreak {
+	Midpoint,
+	ClosestToLast,
+	Max,
+	Min,
+}
+
+impl Default for TieBreak {
+	fn default() -> TieBreak {
+		TieBreak::Midpoint
+	}
+}
+
+impl Clone for TieBreak {
+	fn clone(&self) -> TieBreak {
+		match self {
+			TieBreak::Midpoint => TieBreak::Midpoint,
+			TieBreak::ClosestToLast => TieBreak::ClosestToLast,
+			TieBreak::Max => TieBreak::Max,
+			TieBreak::Min => TieBreak::Min,
+		}
+	}
+}
+
+/// What happens to the debt of a player that defaults
+#[derive(Debug, Copy, Deserialize, PartialEq)]
+pub enum DefaultRule {
+	WriteOff,	// The exchange absorbs the loss
+	Socialize,	// The loss is split evenly across the remaining players
+}
+
+impl Default for DefaultRule {
+	fn default() -> DefaultRule {
+		DefaultRule::WriteOff
+	}
+}
+
+impl Clone for DefaultRule {
+	fn clone(&self) -> DefaultRule {
+		match self {
+			DefaultRule::WriteOff => DefaultRule::WriteOff,
+			DefaultRule::Socialize => DefaultRule::Socialize,
+		}
+	}
+}
+
+/// Whether orders must fit the player's budget when admitted
+#[derive(Debug, Copy, Deserialize, PartialEq)]
+pub enum BudgetRule {
+	Off,		// Orders may exceed the player's balance and inventory
+	Reject,		// Bids costing more than the free balance and asks selling more than the free inventory are rejected
+	ScaleDown,	// Such orders are cut down to what the player can afford or deliver
+}
+
+impl Default for BudgetRule {
+	fn default() -> BudgetRule {
+		BudgetRule::Off
+	}
+}
+
+impl Clone for BudgetRule {
+	fn clone(&self) -> BudgetRule {
+		match self {
+			BudgetRule::Off => BudgetRule::Off,
+			BudgetRule::Reject => BudgetRule::Reject,
+			BudgetRule::ScaleDown => BudgetRule::ScaleDown,
+		}
+	}
 }
 
 impl Clone for MarketType {
@@ -14,6 +102,9 @@ impl Clone for MarketType {
 			MarketType::CDA => MarketType::CDA,
 			MarketType::FBA => MarketType::FBA,
 			MarketType::KLF => MarketType::KLF,
+			MarketType::AMM => MarketType::AMM,
+			MarketType::SealedBid => MarketType::SealedBid,
+			MarketType::Dutch => MarketType::Dutch,
 		}
 	}
 }
\ No newline at end of file
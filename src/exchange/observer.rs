@@ -0,0 +1,104 @@
+use crate::simulation::simulation_history::UpdateReason;
+use crate::players::TraderT;
+
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::time::Duration;
+
+/// A change the ClearingHouse made to a player, the data log_to_csv writes as a struct
+/// symbol: Option<String> -> the instrument the shares changed in, None for cash-only changes
+/// balance: f64 -> the player's balance after the change
+/// inventory: f64 -> the player's total inventory after the change
+#[derive(Debug, Clone)]
+pub struct PlayerEvent {
+	pub time: Duration,
+	pub block_num: u64,
+	pub reason: UpdateReason,
+	pub trader_id: String,
+	pub player_type: TraderT,
+	pub symbol: Option<String>,
+	pub cash: f64,
+	pub shares: f64,
+	pub balance: f64,
+	pub inventory: f64,
+}
+
+/// The callbacks and channels subscribed to the ClearingHouse's player updates. Callbacks run
+/// while the players are locked so they must not call back into the ClearingHouse.
+pub struct Observers {
+	pub callbacks: Mutex<Vec<Box<dyn Fn(&PlayerEvent) + Send>>>,
+	pub channels: Mutex<Vec<Sender<PlayerEvent>>>,
+}
+
+impl Observers {
+	pub fn new() -> Observers {
+		Observers {
+			callbacks: Mutex::new(Vec::new()),
+			channels: Mutex::new(Vec::new()),
+		}
+	}
+
+	pub fn add_callback(&self, callback: Box<dyn Fn(&PlayerEvent) + Send>) {
+		self.callbacks.lock().unwrap().push(callback);
+	}
+
+	/// Returns a receiver that gets a copy of every event until it is dropped
+	pub fn subscribe(&self) -> Receiver<PlayerEvent> {
+		let (tx, rx) = channel();
+		self.channels.lock().unwrap().push(tx);
+		rx
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.callbacks.lock().unwrap().is_empty() && self.channels.lock().unwrap().is_empty()
+	}
+
+	/// Passes the event to every callback and channel, channels whose receiver was dropped are removed
+	pub fn notify(&self, event: &PlayerEvent) {
+		for callback in self.callbacks.lock().unwrap().iter() {
+			callback(event);
+		}
+		self.channels.lock().unwrap().retain(|tx| tx.send(event.clone()).is_ok());
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::sync::Arc;
+
+	fn event(reason: UpdateReason) -> PlayerEvent {
+		PlayerEvent {
+			time: Duration::from_secs(0),
+			block_num: 0,
+			reason,
+			trader_id: format!("a"),
+			player_type: TraderT::Investor,
+			symbol: None,
+			cash: -1.0,
+			shares: 0.0,
+			balance: 99.0,
+			inventory: 0.0,
+		}
+	}
+
+	#[test]
+	fn test_observers() {
+		let observers = Observers::new();
+		assert!(observers.is_empty());
+
+		let gas = Arc::new(Mutex::new(0.0));
+		let g = Arc::clone(&gas);
+		observers.add_callback(Box::new(move |e: &PlayerEvent| if let UpdateReason::Gas = e.reason { *g.lock().unwrap() += e.cash }));
+		let rx = observers.subscribe();
+		let dropped = observers.subscribe();
+		drop(dropped);
+
+		observers.notify(&event(UpdateReason::Gas));
+		observers.notify(&event(UpdateReason::Tax));
+		assert_eq!(*gas.lock().unwrap(), -1.0);
+		assert_eq!(rx.try_iter().count(), 2);
+		assert_eq!(observers.channels.lock().unwrap().len(), 1);
+	}
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A position in one symbol held at its average cost
+#[derive(Debug, Clone, Copy)]
+pub struct Lot {
+	pub quantity: f64,
+	pub avg_cost: f64,
+}
+
+/// A player's realized PnL and open lots, using average-cost accounting.
+/// Fills that add to a position move its average cost, fills that reduce it realize
+/// the difference between the fill price and the average cost.
+#[derive(Debug, Clone)]
+pub struct PnlBook {
+	pub realized: f64,
+	pub lots: HashMap<String, Lot>,
+}
+
+impl PnlBook {
+	pub fn new() -> PnlBook {
+		PnlBook {
+			realized: 0.0,
+			lots: HashMap::new(),
+		}
+	}
+
+	/// Applies a fill of shares (negative for a sale) at price
+	pub fn fill(&mut self, symbol: &str, shares: f64, price: f64) {
+		if shares == 0.0 {
+			return;
+		}
+		let lot = self.lots.entry(symbol.to_string()).or_insert(Lot { quantity: 0.0, avg_cost: 0.0 });
+		if lot.quantity == 0.0 || lot.quantity.signum() == shares.signum() {
+			let quantity = lot.quantity + shares;
+			lot.avg_cost = (lot.avg_cost * lot.quantity + price * shares) / quantity;
+			lot.quantity = quantity;
+			return;
+		}
+
+		// The fill closes some or all of the position
+		let closed = shares.abs().min(lot.quantity.abs());
+		self.realized += closed * (price - lot.avg_cost) * lot.quantity.signum();
+		let quantity = lot.quantity + shares;
+		if quantity == 0.0 {
+			lot.avg_cost = 0.0;
+		} else if quantity.signum() != lot.quantity.signum() {
+			// The remainder opens a position on the other side at the fill price
+			lot.avg_cost = price;
+		}
+		lot.quantity = quantity;
+	}
+
+	/// The open lots marked to prices, lots in symbols without a price are left out
+	pub fn unrealized(&self, prices: &HashMap<String, f64>) -> f64 {
+		self.lots.iter()
+			.filter_map(|(symbol, lot)| prices.get(symbol).map(|p| lot.quantity * (p - lot.avg_cost)))
+			.sum()
+	}
+}
+
+/// Every player's PnlBook, indexed by trader_id
+pub struct PnlTracker {
+	pub books: Mutex<HashMap<String, PnlBook>>,
+}
+
+impl PnlTracker {
+	pub fn new() -> PnlTracker {
+		PnlTracker {
+			books: Mutex::new(HashMap::new()),
+		}
+	}
+
+	pub fn fill(&self, trader_id: &str, symbol: &str, shares: f64, price: f64) {
+		self.books.lock().expect("pnl fill")
+			.entry(trader_id.to_string())
+			.or_insert(PnlBook::new())
+			.fill(symbol, shares, price);
+	}
+
+	pub fn realized(&self, trader_id: &str) -> f64 {
+		match self.books.lock().expect("pnl realized").get(trader_id) {
+			Some(book) => book.realized,
+			None => 0.0,
+		}
+	}
+
+	pub fn unrealized(&self, trader_id: &str, prices: &HashMap<String, f64>) -> f64 {
+		match self.books.lock().expect("pnl unrealized").get(trader_id) {
+			Some(book) => book.unrealized(prices),
+			None => 0.0,
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_average_cost() {
+		let mut book = PnlBook::new();
+		book.fill("X", 10.0, 100.0);
+		book.fill("X", 10.0, 110.0);
+		assert_eq!(book.lots["X"].avg_cost, 105.0);
+
+		// Selling 15 at 115 realizes 15 * 10, the rest stays at the average cost
+		book.fill("X", -15.0, 115.0);
+		assert_eq!(book.realized, 150.0);
+		assert_eq!(book.lots["X"].quantity, 5.0);
+		assert_eq!(book.lots["X"].avg_cost, 105.0);
+
+		// Selling through the position opens a short at the fill price
+		book.fill("X", -10.0, 95.0);
+		assert_eq!(book.realized, 100.0);
+		assert_eq!(book.lots["X"].quantity, -5.0);
+		assert_eq!(book.lots["X"].avg_cost, 95.0);
+
+		let mut prices = HashMap::new();
+		prices.insert(format!("X"), 90.0);
+		assert_eq!(book.unrealized(&prices), 25.0);
+		assert_eq!(book.unrealized(&HashMap::new()), 0.0);
+	}
+}
@@ -0,0 +1,292 @@
+use crate::order::order_book::Book;
+use crate::exchange::TieBreak;
+use crate::exchange::exchange_logic::{Auction, AuctionRules};
+
+use std::sync::Arc;
+
+const MAX_PRICE: f64 = 999_999_999.0;
+const MIN_PRICE: f64 = 0.0;
+const MAX_ITERS: usize = 1000;
+
+/// Which PricingRule the FBA uses to pick its uniform clearing price
+#[derive(Debug, Copy, Deserialize, PartialEq)]
+pub enum PricingRuleT {
+	MaxVolume,
+	MinImbalance,
+	ReferencePrice,
+}
+
+impl PricingRuleT {
+	pub fn rule(&self) -> Box<dyn PricingRule> {
+		match self {
+			PricingRuleT::MaxVolume => Box::new(MaxVolume {}),
+			PricingRuleT::MinImbalance => Box::new(MinImbalance {}),
+			PricingRuleT::ReferencePrice => Box::new(ReferencePrice {}),
+		}
+	}
+}
+
+impl Default for PricingRuleT {
+	fn default() -> PricingRuleT {
+		PricingRuleT::MaxVolume
+	}
+}
+
+impl Clone for PricingRuleT {
+	fn clone(&self) -> PricingRuleT {
+		match self {
+			PricingRuleT::MaxVolume => PricingRuleT::MaxVolume,
+			PricingRuleT::MinImbalance => PricingRuleT::MinImbalance,
+			PricingRuleT::ReferencePrice => PricingRuleT::ReferencePrice,
+		}
+	}
+}
+
+/// Computes the uniform clearing price of a batch without modifying the books.
+/// Returns the price, if the books cross, and the TieBreak used if several prices tied.
+pub trait PricingRule {
+	fn clearing_price(&self, bids: Arc<Book>, asks: Arc<Book>, rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>);
+}
+
+/// The FBA's original rule: walks the merged book until the volume seen covers the ask book
+pub struct MaxVolume {}
+
+/// Picks the price leaving the smallest gap between demand and supply, then the most volume
+pub struct MinImbalance {}
+
+/// Picks the price that clears the most volume closest to the previous clearing price
+pub struct ReferencePrice {}
+
+/// The KLF's rule: binary searches for where aggregate flow demand meets flow supply
+pub struct FlowCross {}
+
+impl PricingRule for MaxVolume {
+	fn clearing_price(&self, bids: Arc<Book>, asks: Arc<Book>, rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>) {
+		// Calc total ask volume 
+		let ask_book_vol = asks.get_book_volume();
+		// Merge both books and sort in decreasing price order 
+		let merged_book = Book::merge_sort_books(Arc::clone(&bids), Arc::clone(&asks));
+
+		// Initialize the min and max prices seen while traversing the merged book
+		let mut max_seen_price = MIN_PRICE;
+		let mut min_seen_price = MAX_PRICE;
+		let mut clearing_price: Option<f64> = None;
+		let mut tie_fired: Option<TieBreak> = None;
+
+		// Initialize vars to track volume seen while traversing the merged book
+		let mut seen_vol = 0.0;
+		// let mut prev_seen_vol = 0.0;
+		let mut prev_order_price = 0.0;	// is 0.0 acceptable?
+		let mut cur_order_price = 0.0;
+
+		// Iterate through descending orders. Sum volume of each order and track the min and max seen prices
+		let orders = merged_book.orders.lock().expect("ERROR: Couldn't lock book to sort");
+		println!("Calculating clearing price...");
+		for order in orders.iter() {
+			cur_order_price = order.price;
+			// Process best prices
+			if cur_order_price > max_seen_price {
+				max_seen_price = cur_order_price;
+			}
+			if cur_order_price < min_seen_price {
+				min_seen_price = cur_order_price;
+			}
+
+			// Process seen volumes
+			// prev_seen_vol = seen_vol;
+			seen_vol += order.quantity;
+			println!("Checking price:{}, seen_vol:{} / ask_vol:{}", cur_order_price, seen_vol, ask_book_vol);
+			if seen_vol >= ask_book_vol {
+				// NOTE: darrell's implementation didn't include <=, just <, but this fixed horizontal cross edge case
+				break;
+			}
+			// Track the price of the last traversed order
+			prev_order_price = cur_order_price;
+		}	
+
+		// If we have still not found a max or min seen price, loop until we do:
+		if max_seen_price == MIN_PRICE || min_seen_price == MAX_PRICE {
+			for order in orders.iter() {
+				cur_order_price = order.price;
+				// Process best prices
+				if cur_order_price > max_seen_price {
+					max_seen_price = cur_order_price;
+				}
+				if cur_order_price < min_seen_price {
+					min_seen_price = cur_order_price;
+				}
+				println!("Looping until price < {}, cur_price={}", MAX_PRICE, cur_order_price);
+				if cur_order_price < MAX_PRICE {
+					break;
+				}
+			}
+		}
+
+		// Find the clearing price
+		if max_seen_price == MIN_PRICE && min_seen_price == MAX_PRICE {
+			// We weren't able to find a clearing price
+			clearing_price = None;
+		} 
+		// We perfectly matched volume
+		else if seen_vol == ask_book_vol {	
+			if prev_order_price == MAX_PRICE && MIN_PRICE < cur_order_price && cur_order_price < MAX_PRICE {
+				// The current order crossed, so use this price
+				clearing_price = Some(cur_order_price);
+			} 
+		
+			else if prev_order_price < MAX_PRICE && MIN_PRICE < cur_order_price {
+				// let p = round::ceil((prev_order_price + cur_order_price) / 2.0, PRECISION);
+				// Every price between the two orders clears the same volume
+				let p = Auction::break_tie(cur_order_price, prev_order_price, rules.tie_break, rules.last_price);
+				if prev_order_price > cur_order_price {
+					tie_fired = Some(rules.tie_break);
+				}
+				clearing_price = Some(p);
+			}
+
+			else if MIN_PRICE < prev_order_price && prev_order_price < MAX_PRICE && cur_order_price == MIN_PRICE {
+				clearing_price = Some(prev_order_price);
+			}
+
+			else if prev_order_price == MIN_PRICE {
+				clearing_price = Some(min_seen_price);
+			}
+		}
+		// The last order's volume caused us to cross
+		else if seen_vol > ask_book_vol {
+			clearing_price = Some(Auction::max_float(&cur_order_price, &min_seen_price));
+		}
+
+		(clearing_price, tie_fired)
+	}
+}
+
+impl PricingRule for MinImbalance {
+	fn clearing_price(&self, bids: Arc<Book>, asks: Arc<Book>, rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>) {
+		let candidates = candidate_prices(bids, asks);
+		let best = candidates.iter().fold(None, |best: Option<(f64, f64)>, c| {
+			match best {
+				Some((imb, vol)) if c.2.abs() > imb || (c.2.abs() == imb && c.1 <= vol) => best,
+				_ => Some((c.2.abs(), c.1)),
+			}
+		});
+		let (imb, vol) = match best {
+			Some(best) => best,
+			None => return (None, None),
+		};
+		let tied: Vec<f64> = candidates.iter().filter(|c| c.2.abs() == imb && c.1 == vol).map(|c| c.0).collect();
+		break_ties(&tied, rules)
+	}
+}
+
+impl PricingRule for ReferencePrice {
+	fn clearing_price(&self, bids: Arc<Book>, asks: Arc<Book>, rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>) {
+		let candidates = candidate_prices(bids, asks);
+		let max_vol = candidates.iter().fold(0.0, |max: f64, c| max.max(c.1));
+		let prices: Vec<f64> = candidates.iter().filter(|c| c.1 == max_vol).map(|c| c.0).collect();
+		let (low, high) = match (prices.first(), prices.last()) {
+			(Some(low), Some(high)) => (*low, *high),
+			_ => return (None, None),
+		};
+		// Without a previous price anchor to the middle of the range
+		match rules.last_price {
+			Some(p) => (Some(p.max(low).min(high)), None),
+			None => (Some((low + high) / 2.0), None),
+		}
+	}
+}
+
+impl PricingRule for FlowCross {
+	fn clearing_price(&self, bids: Arc<Book>, asks: Arc<Book>, _rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>) {
+		// get_price_bounds obtains locks on the book's prices
+	    let (mut left, mut right) = Auction::get_price_bounds(Arc::clone(&bids), Arc::clone(&asks));
+	    let mut curr_iter = 0;
+	    println!("Min Book price: {}, Max Book price: {}", left, right);
+	    while left < right {
+	    	curr_iter += 1;
+	    	// Find a midpoint with the correct price tick precision
+	    	let index: f64 = (left + right) / 2.0;
+	    	// Calculate the aggregate supply and demand at this price
+	    	let (dem, sup) = Auction::calc_aggs(index, Arc::clone(&bids), Arc::clone(&asks));
+
+	    	if Auction::greater_than_e(&dem, &sup) {  		// dev > sup
+	    		// We are left of the crossing point
+	    		left = index;
+	    	} else if Auction::less_than_e(&dem, &sup) {	// sup > dem
+	    		// We are right of the crossing point
+	    		right = index;
+	    	} else {
+	    		println!("Found cross at: {}\n", index);
+	    		return (Some(index), None);
+	    	}
+
+	    	if curr_iter == MAX_ITERS {
+	    		println!("Trouble finding cross in max iterations, got: {}", index);
+	    		return (Some(index), None);
+	    	}
+	    }
+	    (None, None)
+	}
+}
+
+/// (price, executable volume, demand - supply) at each distinct order price where volume trades
+fn candidate_prices(bids: Arc<Book>, asks: Arc<Book>) -> Vec<(f64, f64, f64)> {
+	let bids = bids.copy_orders();
+	let asks = asks.copy_orders();
+	let mut prices: Vec<f64> = bids.iter().chain(asks.iter()).map(|o| o.price).collect();
+	prices.sort_by(|a, b| a.partial_cmp(b).expect("bad cmp"));
+	prices.dedup();
+	prices.into_iter().map(|p| {
+		let dem: f64 = bids.iter().filter(|o| o.price >= p).map(|o| o.quantity).sum();
+		let sup: f64 = asks.iter().filter(|o| o.price <= p).map(|o| o.quantity).sum();
+		(p, dem.min(sup), dem - sup)
+	}).filter(|c| c.1 > 0.0).collect()
+}
+
+/// Picks between the ascending prices that tied under a rule using the configured TieBreak
+fn break_ties(tied: &Vec<f64>, rules: &AuctionRules) -> (Option<f64>, Option<TieBreak>) {
+	match (tied.first(), tied.last()) {
+		(Some(low), Some(high)) if low < high => {
+			(Some(Auction::break_tie(*low, *high, rules.tie_break, rules.last_price)), Some(rules.tie_break))
+		},
+		(Some(p), _) => (Some(*p), None),
+		_ => (None, None),
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{Order, OrderType, TradeType, ExchangeType};
+
+	fn books() -> (Arc<Book>, Arc<Book>) {
+		let order = |tt: TradeType, price: f64, quantity: f64| Order::new(String::from("trader"), OrderType::Enter, tt,
+			ExchangeType::LimitOrder, price, price, price, quantity, quantity, 0.1);
+		let bids = Arc::new(Book::new(TradeType::Bid));
+		let asks = Arc::new(Book::new(TradeType::Ask));
+		bids.add_order(order(TradeType::Bid, 110.0, 20.0)).unwrap();
+		bids.add_order(order(TradeType::Bid, 100.0, 10.0)).unwrap();
+		asks.add_order(order(TradeType::Ask, 90.0, 22.0)).unwrap();
+		asks.add_order(order(TradeType::Ask, 105.0, 3.0)).unwrap();
+		(bids, asks)
+	}
+
+	#[test]
+	fn test_pricing_rules() {
+		// 22 shares clear between 90 and 100, 20 shares with a smaller imbalance between 105 and 110
+		let (bids, asks) = books();
+		let rules = AuctionRules::default();
+		assert_eq!(MinImbalance {}.clearing_price(Arc::clone(&bids), Arc::clone(&asks), &rules), (Some(107.5), Some(TieBreak::Midpoint)));
+		assert_eq!(ReferencePrice {}.clearing_price(Arc::clone(&bids), Arc::clone(&asks), &rules), (Some(95.0), None));
+
+		let rules = AuctionRules { last_price: Some(120.0), ..AuctionRules::default() };
+		assert_eq!(ReferencePrice {}.clearing_price(Arc::clone(&bids), Arc::clone(&asks), &rules), (Some(100.0), None));
+
+		// The FBA fills at the rule's price
+		let rules = AuctionRules { pricing: PricingRuleT::MinImbalance, ..AuctionRules::default() };
+		let res = Auction::frequent_batch_auction_with(bids, asks, rules).unwrap();
+		assert_eq!(res.uniform_price, Some(107.5));
+		assert_eq!(res.agg_supply, 20.0);
+	}
+}
@@ -0,0 +1,99 @@
+use crate::exchange::clearing_house::ClearingHouse;
+use crate::players::{Player, TraderT, PlayerSnapshot};
+
+use crate::utility::sim_rng;
+use rand::seq::SliceRandom;
+
+/// A selection of the ClearingHouse's players built up from filters, e.g.
+/// house.query().of_type(TraderT::Maker).with_min_inventory(10.0).ids()
+/// Defaulted and exited players are left out unless include_defaulted is called.
+pub struct PlayerQuery<'a> {
+	house: &'a ClearingHouse,
+	filters: Vec<Box<dyn Fn(&dyn Player) -> bool + 'a>>,
+	include_defaulted: bool,
+}
+
+impl<'a> PlayerQuery<'a> {
+	pub fn new(house: &'a ClearingHouse) -> PlayerQuery<'a> {
+		PlayerQuery {
+			house,
+			filters: Vec::new(),
+			include_defaulted: false,
+		}
+	}
+
+	/// Keeps players for which f returns true
+	pub fn filter<F>(mut self, f: F) -> PlayerQuery<'a>
+	where F: Fn(&dyn Player) -> bool + 'a
+	{
+		self.filters.push(Box::new(f));
+		self
+	}
+
+	pub fn of_type(self, player_type: TraderT) -> PlayerQuery<'a> {
+		self.filter(move |p| p.get_player_type() == player_type)
+	}
+
+	/// Keeps players holding at least amount shares, long or short, over all symbols
+	pub fn with_min_inventory(self, amount: f64) -> PlayerQuery<'a> {
+		self.filter(move |p| p.get_total_inv().abs() >= amount)
+	}
+
+	/// Keeps players holding at least amount shares, long or short, of symbol
+	pub fn with_min_position(self, symbol: &str, amount: f64) -> PlayerQuery<'a> {
+		let symbol = symbol.to_string();
+		self.filter(move |p| p.get_inv(&symbol).abs() >= amount)
+	}
+
+	pub fn with_min_balance(self, balance: f64) -> PlayerQuery<'a> {
+		self.filter(move |p| p.get_bal() >= balance)
+	}
+
+	pub fn include_defaulted(mut self) -> PlayerQuery<'a> {
+		self.include_defaulted = true;
+		self
+	}
+
+	// Applies f to every matching player while holding the player, defaulted and exited locks
+	fn collect<R, F>(&self, f: F) -> Vec<R>
+	where F: Fn(&String, &dyn Player) -> R
+	{
+		let players = self.house.players.read().unwrap();
+		let defaulted = self.house.defaulted.lock().unwrap();
+		let exited = self.house.exited.lock().unwrap();
+		players.iter()
+			.filter(|(k, _)| self.include_defaulted || !(defaulted.contains(k) || exited.contains(k)))
+			.filter(|(_, v)| self.filters.iter().all(|f| f(&***v)))
+			.map(|(k, v)| f(k, &**v))
+			.collect()
+	}
+
+	/// The ids of the matching players in no particular order
+	pub fn ids(&self) -> Vec<String> {
+		self.collect(|id, _| id.clone())
+	}
+
+	/// The ids of the matching players in a random order
+	pub fn shuffled(&self) -> Vec<String> {
+		let mut ids = self.ids();
+		// Sorted first so a seeded generator shuffles them the same way every run
+		ids.sort();
+		ids.shuffle(&mut sim_rng());
+		ids
+	}
+
+	/// The id of a random matching player
+	pub fn random(&self) -> Option<String> {
+		let mut ids = self.ids();
+		ids.sort();
+		ids.choose(&mut sim_rng()).cloned()
+	}
+
+	pub fn count(&self) -> usize {
+		self.ids().len()
+	}
+
+	pub fn snapshots(&self) -> Vec<PlayerSnapshot> {
+		self.collect(|_, p| PlayerSnapshot::new(p))
+	}
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// One trader's side of a fill awaiting settlement
+/// symbol: String -> the instrument the shares are in
+/// order_ids: Vec<u64> -> the orders on both sides of the fill
+/// cash: f64 -> balance the trader receives (negative if they pay)
+/// shares: f64 -> inventory the trader receives (negative if they deliver)
+#[derive(Debug, Clone)]
+pub struct Obligation {
+	pub trader_id: String,
+	pub symbol: String,
+	pub cash: f64,
+	pub shares: f64,
+	pub order_ids: Vec<u64>,
+	pub settle_block: u64,
+}
+
+/// A trader whose net obligations exceeded their balance or inventory when they settled
+#[derive(Debug, Clone)]
+pub struct SettlementFailure {
+	pub trader_id: String,
+	pub block_num: u64,
+	pub cash_short: f64,
+	pub shares_short: f64,
+}
+
+/// Tracks the obligations created by fills until they settle lag blocks later (T+lag).
+/// A lag of 0 settles every fill immediately.
+/// block_num: Mutex<u64> -> the latest block settlement was run for
+/// pending: Mutex<Vec<Obligation>> -> unsettled obligations in order of execution
+/// failures: Mutex<Vec<SettlementFailure>> -> every failed settlement
+pub struct Settlement {
+	pub lag: Mutex<u64>,
+	pub block_num: Mutex<u64>,
+	pub pending: Mutex<Vec<Obligation>>,
+	pub failures: Mutex<Vec<SettlementFailure>>,
+}
+
+impl Settlement {
+	pub fn new() -> Settlement {
+		Settlement {
+			lag: Mutex::new(0),
+			block_num: Mutex::new(0),
+			pending: Mutex::new(Vec::new()),
+			failures: Mutex::new(Vec::new()),
+		}
+	}
+
+	pub fn set_lag(&self, lag: u64) {
+		*self.lag.lock().unwrap() = lag;
+	}
+
+	/// True if fills settle after a delay instead of immediately
+	pub fn is_deferred(&self) -> bool {
+		*self.lag.lock().unwrap() > 0
+	}
+
+	/// Records an obligation due lag blocks after the latest settled block
+	pub fn add(&self, trader_id: String, symbol: &str, cash: f64, shares: f64, order_ids: Vec<u64>) {
+		let settle_block = *self.block_num.lock().unwrap() + *self.lag.lock().unwrap();
+		self.pending.lock().expect("add obligation").push(Obligation {
+			trader_id,
+			symbol: symbol.to_string(),
+			cash,
+			shares,
+			order_ids,
+			settle_block,
+		});
+	}
+
+	/// Removes the obligations due by block_num and nets them per trader and symbol
+	pub fn take_due(&self, block_num: u64) -> HashMap<(String, String), (f64, f64, Vec<u64>)> {
+		*self.block_num.lock().unwrap() = block_num;
+		let mut pending = self.pending.lock().expect("take_due");
+		let (due, rest): (Vec<Obligation>, Vec<Obligation>) = pending.drain(..).partition(|o| o.settle_block <= block_num);
+		*pending = rest;
+		Settlement::net(due)
+	}
+
+	/// Removes every pending obligation and nets them per trader and symbol
+	pub fn take_all(&self) -> HashMap<(String, String), (f64, f64, Vec<u64>)> {
+		let mut pending = self.pending.lock().expect("take_all");
+		Settlement::net(pending.drain(..).collect())
+	}
+
+	/// Sums the obligations into (cash, shares, order_ids) per (trader, symbol)
+	pub fn net(obligations: Vec<Obligation>) -> HashMap<(String, String), (f64, f64, Vec<u64>)> {
+		let mut netted = HashMap::<(String, String), (f64, f64, Vec<u64>)>::new();
+		for o in obligations {
+			let entry = netted.entry((o.trader_id, o.symbol)).or_insert((0.0, 0.0, Vec::new()));
+			entry.0 += o.cash;
+			entry.1 += o.shares;
+			entry.2.extend(o.order_ids);
+		}
+		netted
+	}
+
+	/// The trader's net (cash, shares) in symbol that hasn't settled yet
+	pub fn pending_position(&self, trader_id: &str, symbol: &str) -> (f64, f64) {
+		let pending = self.pending.lock().expect("pending_position");
+		pending.iter()
+			.filter(|o| o.trader_id == trader_id && o.symbol == symbol)
+			.fold((0.0, 0.0), |(c, s), o| (c + o.cash, s + o.shares))
+	}
+
+	/// The trader's net cash over every symbol that hasn't settled yet
+	pub fn pending_cash(&self, trader_id: &str) -> f64 {
+		let pending = self.pending.lock().expect("pending_cash");
+		pending.iter()
+			.filter(|o| o.trader_id == trader_id)
+			.map(|o| o.cash)
+			.sum()
+	}
+
+	pub fn record_failure(&self, failure: SettlementFailure) {
+		self.failures.lock().expect("record_failure").push(failure);
+	}
+
+	pub fn num_failures(&self) -> usize {
+		self.failures.lock().unwrap().len()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_net_due_obligations() {
+		let s = Settlement::new();
+		s.set_lag(2);
+		assert!(s.is_deferred());
+
+		// Two trades on block 0 settle on block 2
+		s.add(format!("a"), "X", -100.0, 1.0, vec![1, 2]);
+		s.add(format!("a"), "X", 50.0, -0.5, vec![3, 4]);
+		s.add(format!("b"), "X", 50.0, -0.5, vec![3, 4]);
+		assert_eq!(s.take_due(1).len(), 0);
+
+		// A trade on block 1 settles on block 3, positions are kept per symbol
+		s.add(format!("b"), "X", -10.0, 0.1, vec![5, 6]);
+		s.add(format!("b"), "Y", -20.0, 0.2, vec![7, 8]);
+		assert_eq!(s.pending_position("a", "X"), (-50.0, 0.5));
+		assert_eq!(s.pending_position("a", "Y"), (0.0, 0.0));
+
+		let due = s.take_due(2);
+		let key = |id: &str, sym: &str| (id.to_string(), sym.to_string());
+		assert_eq!(due[&key("a", "X")], (-50.0, 0.5, vec![1, 2, 3, 4]));
+		assert_eq!(due[&key("b", "X")], (50.0, -0.5, vec![3, 4]));
+		assert_eq!(s.pending_position("b", "X"), (-10.0, 0.1));
+		let all = s.take_all();
+		assert_eq!(all[&key("b", "X")], (-10.0, 0.1, vec![5, 6]));
+		assert_eq!(all[&key("b", "Y")], (-20.0, 0.2, vec![7, 8]));
+		assert_eq!(s.pending.lock().unwrap().len(), 0);
+	}
+}
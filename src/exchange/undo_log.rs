@@ -0,0 +1,129 @@
+use crate::order::order::Order;
+use crate::exchange::settlement::Obligation;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread::{self, ThreadId};
+
+/// One change the ClearingHouse made to a player, enough to reverse it
+/// symbol: Option<String> -> the instrument the shares changed in, None for cash-only changes
+#[derive(Debug, Clone, PartialEq)]
+pub struct UndoEntry {
+	pub trader_id: String,
+	pub symbol: Option<String>,
+	pub cash: f64,
+	pub shares: f64,
+}
+
+/// The changes made to the players since a block began, so an orphaned block can be rolled back
+/// changes: Vec<UndoEntry> -> every change in the order it was made
+/// orders: HashMap<String, Vec<Order>> -> each player's orders when the block began, indexed by trader_id
+/// obligations: Vec<Obligation> -> the settlement obligations pending when the block began
+/// thread: ThreadId -> the thread publishing the block, the only one whose changes are recorded
+pub struct BlockUndo {
+	pub thread: ThreadId,
+	pub changes: Vec<UndoEntry>,
+	pub orders: HashMap<String, Vec<Order>>,
+	pub obligations: Vec<Obligation>,
+}
+
+/// Records changes only while a block is open, between begin and take
+pub struct UndoLog {
+	pub open: Mutex<Option<BlockUndo>>,
+}
+
+impl UndoLog {
+	pub fn new() -> UndoLog {
+		UndoLog {
+			open: Mutex::new(None),
+		}
+	}
+
+	/// Opens a block on the calling thread with the players' orders and pending obligations as
+	/// they are now, discarding any block left open
+	pub fn begin(&self, orders: HashMap<String, Vec<Order>>, obligations: Vec<Obligation>) {
+		*self.open.lock().expect("undo_log begin") = Some(BlockUndo {
+			thread: thread::current().id(),
+			changes: Vec::new(),
+			orders,
+			obligations,
+		});
+	}
+
+	pub fn is_open(&self) -> bool {
+		self.open.lock().expect("undo_log is_open").is_some()
+	}
+
+	/// Records a change if a block is open and it was made by the thread publishing the block.
+	/// Changes other threads make meanwhile aren't part of the block and survive its rollback.
+	pub fn record(&self, trader_id: &str, symbol: Option<&str>, cash: f64, shares: f64) {
+		if let Some(block) = self.open.lock().expect("undo_log record").as_mut() {
+			if block.thread != thread::current().id() {
+				return;
+			}
+			block.changes.push(UndoEntry {
+				trader_id: trader_id.to_string(),
+				symbol: symbol.map(|s| s.to_string()),
+				cash,
+				shares,
+			});
+		}
+	}
+
+	/// Closes the open block, returning what it recorded
+	pub fn take(&self) -> Option<BlockUndo> {
+		self.open.lock().expect("undo_log take").take()
+	}
+}
+
+impl BlockUndo {
+	/// The changes to make to undo the block, latest first
+	pub fn reversals(&self) -> Vec<UndoEntry> {
+		self.changes.iter().rev().map(|c| UndoEntry {
+			trader_id: c.trader_id.clone(),
+			symbol: c.symbol.clone(),
+			cash: -c.cash,
+			shares: -c.shares,
+		}).collect()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_undo_log() {
+		let log = UndoLog::new();
+		// Nothing is recorded until a block is opened
+		log.record("a", None, -1.0, 0.0);
+		assert!(log.take().is_none());
+
+		log.begin(HashMap::new(), Vec::new());
+		log.record("a", Some("SYM0"), -100.0, 1.0);
+		log.record("b", Some("SYM0"), 100.0, -1.0);
+		log.record("a", None, -1.0, 0.0);
+		let block = log.take().expect("open block");
+		assert!(!log.is_open());
+
+		let reversals = block.reversals();
+		assert_eq!(reversals.len(), 3);
+		assert_eq!(reversals[0], UndoEntry { trader_id: format!("a"), symbol: None, cash: 1.0, shares: 0.0 });
+		assert_eq!(reversals[2], UndoEntry { trader_id: format!("a"), symbol: Some(format!("SYM0")), cash: 100.0, shares: -1.0 });
+	}
+
+	#[test]
+	fn test_undo_log_other_threads() {
+		let log = std::sync::Arc::new(UndoLog::new());
+		log.begin(HashMap::new(), Vec::new());
+		log.record("a", None, -1.0, 0.0);
+
+		// A change made on another thread while the block is open isn't the block's
+		let other = std::sync::Arc::clone(&log);
+		thread::spawn(move || other.record("b", None, -2.0, 0.0)).join().unwrap();
+		let block = log.take().expect("open block");
+		assert_eq!(block.changes.len(), 1);
+		assert_eq!(block.changes[0].trader_id, format!("a"));
+	}
+}
@@ -20,6 +20,7 @@ pub mod order;
 pub mod controller;
 pub mod blockchain;
 pub mod players;
+pub mod error;
 
 
 
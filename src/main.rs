@@ -1,14 +1,21 @@
 extern crate flow_rs;
 extern crate tokio;
 
-use flow_rs::simulation::simulation_config::{DistReason};
 use flow_rs::simulation::simulation_history::UpdateReason;
 use flow_rs::controller::Controller;
 use flow_rs::simulation::simulation::{Simulation};
+use flow_rs::simulation::event_sim::EventSim;
+use flow_rs::simulation::checkpoint::SimCheckpoint;
+use flow_rs::simulation::replay::Replay;
 use flow_rs::simulation::config_parser::*;
+use flow_rs::exchange::MarketType;
+use flow_rs::exchange::fees;
+use flow_rs::exchange::leaderboard;
+use flow_rs::players::adaptive::{Adaptive, Bandit};
+use flow_rs::players::miner_strategy::MinerRegime;
 
 
-use flow_rs::utility::{setup_logging, get_time, setup_log_headers};
+use flow_rs::utility::{setup_logging, get_time, setup_log_headers, seed_task, set_time_scale};
 use flow_rs::{log_order_book, log_player_data, log_mempool_data, log_results};
 
 
@@ -18,12 +25,21 @@ extern crate log4rs;
 
 use std::collections::HashMap;
 use log::{log, Level};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::env;
 
+// The value of a --name=value flag anywhere on the command line
+fn flag(name: &str) -> Option<String> {
+	let prefix = format!("--{}=", name);
+	env::args().find(|arg| arg.starts_with(&prefix)).map(|arg| arg[prefix.len()..].to_string())
+}
+
 fn main() {
-	// Get the log file names
-	let mut args = env::args();
+	// --time-scale=N overrides the consts' time_scale
+	let time_scale = flag("time-scale").map(|arg| arg.parse::<f64>().expect("Couldn't parse --time-scale"));
+
+	// Get the log file names, the flags aren't positional
+	let mut args = env::args().filter(|arg| !arg.starts_with("--")).collect::<Vec<String>>().into_iter();
 	assert!(args.len() > 0);
 	args.next(); // consume file name arg[0]
 	let filename = match args.next() {
@@ -63,6 +79,21 @@ fn main() {
 		},
 	};
 
+	// An optional tax schedule csv (--taxes=)
+	let taxes_name = flag("taxes");
+
+	// An optional csv of strategy scripts for extra makers and investors (--scripts=)
+	let scripts_name = flag("scripts");
+
+	// Where the adaptive makers' bandit is loaded from and saved to between runs (--adaptive=)
+	let adaptive_path = flag("adaptive").unwrap_or(format!("log/adaptive_params.json"));
+
+	// A checkpoint to resume from if it exists, saved over after the consts' checkpoint_block (--checkpoint=)
+	let checkpoint_path = flag("checkpoint");
+
+	// The frames of an earlier run to replay instead of running the agents (--replay=)
+	let replay = flag("replay").map(|path| Replay::load(&path).expect(&format!("Couldn't load replay frames {}", path)));
+
 	// Initialize the logger
 	let _logger_handle = setup_logging(&filename, enable_log);
 
@@ -77,79 +108,343 @@ fn main() {
 
 	// Read the constant parameters from the supplied csv file (arg3)
 	let consts = parse_consts_config_csv(format!("configs/{}", consts_name)).expect(&format!("Couldn't parse consts config {}", consts_name));
+	let mut consts = match replay {
+		Some(_) => Replay::consts(consts),
+		None => consts,
+	};
+
+	// Shorten every real-time delay and interval by the time scale
+	if let Some(time_scale) = time_scale {
+		consts.time_scale = time_scale;
+	}
+	set_time_scale(consts.time_scale);
+
+	// A checkpoint's players are only found again by a run with the seed it was taken with
+	if checkpoint_path.is_some() && consts.seed == 0 {
+		println!("--checkpoint needs a nonzero seed in {}", consts_name);
+		std::process::exit(1);
+	}
+
+	// The other agents only run as real-time tasks, which the virtual clock would outrun
+	let real_time_only = consts.real_time_only();
+	if consts.discrete_event && replay.is_none() && !real_time_only.is_empty() {
+		println!("discrete_event can't run {}, they only run in real time", real_time_only.join(", "));
+		std::process::exit(1);
+	}
+
+	// Seed the setup's draws, the tasks seed their own threads
+	seed_task(consts.seed, "main", 0);
 
 	// Write the headers to all of the log files
 	setup_log_headers(consts.market_type.clone());    
 
 	// Initial state of the sim
 	let (simulation, miner) = Simulation::init_simulation(distributions, consts.clone());
+	let simulation = simulation.with_checkpoint(checkpoint_path.clone());
+
+	// Levy the tax schedule on top of the fees set by the consts
+	if let Some(taxes_name) = taxes_name {
+		let rules = parse_tax_schedule_csv(format!("configs/{}", taxes_name)).expect(&format!("Couldn't parse tax schedule {}", taxes_name));
+		for policy in fees::policies_from_schedule(rules) {
+			simulation.house.add_fee_policy(policy);
+		}
+	}
+
+	// Optionally run a second market sharing the same players, linked by an arbitrageur
+	let linked = match consts.second_market_type {
+		Some(m_t) => {
+			let (linked, linked_miner) = simulation.linked(m_t);
+			let arb_id = simulation.reg_arbitrageur();
+			Some((linked, linked_miner, arb_id))
+		},
+		None => None,
+	};
+
+	// Register the other agent populations, keeping the ZI traders' valuations, the snipers' gas policies and the bridge player's id for their tasks
+	let zi_valuations = simulation.reg_zi_traders();
+	simulation.reg_momentum_traders();
+	simulation.reg_mean_reversion_traders();
+	simulation.reg_informed_traders();
+	simulation.reg_noise_traders();
+	simulation.reg_spoofers();
+	let sniper_policies = simulation.reg_snipers();
+	simulation.reg_twap_agents();
+	simulation.reg_vwap_agents();
+	simulation.reg_searchers();
+	if let Some(scripts_name) = scripts_name {
+		let specs = parse_scripts_csv(format!("configs/{}", scripts_name)).expect(&format!("Couldn't parse scripts {}", scripts_name));
+		simulation.reg_scripted_players(&specs).expect("Couldn't load strategy script");
+	}
+	let bridge_id = match consts.bridge_port {
+		0 => None,
+		_ => Some(simulation.reg_bridge()),
+	};
+
+	// Miners competing with the simulation's miner for blocks
+	let mut miners = simulation.reg_rival_miners(miner);
+
+	// Carry on from the checkpoint's block, the same seed registers the same players
+	if let Some(path) = checkpoint_path.as_ref().filter(|p| std::path::Path::new(p).exists()) {
+		let checkpoint = SimCheckpoint::load(path).expect(&format!("Couldn't load checkpoint {}", path));
+		let block = checkpoint.block;
+		if let Err(e) = checkpoint.restore(&simulation) {
+			println!("Couldn't restore checkpoint {}: {}", path, e);
+			std::process::exit(1);
+		}
+		info!("Resumed from block {} of {}", block, path);
+	}
 
 	// Log and save the intial state of the players
 	simulation.house.log_all_players(UpdateReason::Initial);
 	// Save the initial balance and inventory of each player
 	let mut initial_player_state = HashMap::<String, (f64, f64)>::new(); 
-	{
-		for (id, player) in simulation.house.players.lock().unwrap().iter() {
-			initial_player_state.insert(id.clone(), (player.get_bal(), player.get_inv()));
-		}
+	for player in simulation.house.snapshot() {
+		initial_player_state.insert(player.trader_id.clone(), (player.balance, player.total_inv()));
 	}
 	
-	// Initialize an investor thread to repeat at intervals based on supplied distributions
-	let investor_task = Simulation::investor_task(simulation.dists.clone(), 
-												  Arc::clone(&simulation.house),
-												  Arc::clone(&simulation.mempool),
-												  Arc::clone(&simulation.history), 
-												  Arc::clone(&simulation.block_num), 
-												  consts.clone());
-
-	thread_handles.push(investor_task);
-
-
-	// Initialize an maker task to repeat to be repeated on a fixed interval
-	let maker_task = Simulation::maker_task(simulation.dists.clone(), 
-												  Arc::clone(&simulation.house),
-												  Arc::clone(&simulation.mempool), 
-												  Arc::clone(&simulation.history), 
-												  Arc::clone(&simulation.block_num), 
-												  consts.clone());
-
-	controller.start_task(maker_task);
-
-
-	// Initalize a miner task to be repeated on a fixed interval
-	let miner_task = Simulation::miner_task(miner, simulation.dists.clone(), 
-												   Arc::clone(&simulation.house), 
-												   Arc::clone(&simulation.mempool),
-												   Arc::clone(&simulation.bids_book),
-												   Arc::clone(&simulation.asks_book), 
+	// The discrete-event engine runs the investors, makers and miners on a virtual clock in this
+	// thread, otherwise they run as real-time tasks
+	let miner_wins = Arc::new(Mutex::new(HashMap::<String, u64>::new()));
+	let mut engine = None;
+	if let Some(replay) = replay.as_ref() {
+		let replayed = replay.run(&simulation, &mut miners[0]);
+		info!("Replayed {} blocks", replayed);
+	} else if consts.discrete_event {
+		engine = Some(EventSim::new(simulation.clone(), miners));
+	} else {
+		// Initialize an investor thread to repeat at intervals based on supplied distributions
+		let investor_task = Simulation::investor_task(simulation.dists.clone(), 
+													  Arc::clone(&simulation.house),
+													  Arc::clone(&simulation.mempool),
+													  Arc::clone(&simulation.history), 
+													  Arc::clone(&simulation.block_num), 
+													  Arc::clone(&simulation.control), 
+													  consts.clone());
+
+		thread_handles.push(investor_task);
+
+		// Initialize an maker task to repeat to be repeated on a fixed interval
+		let maker_task = Simulation::maker_task(simulation.dists.clone(), 
+													  Arc::clone(&simulation.house),
+													  Arc::clone(&simulation.mempool), 
+													  Arc::clone(&simulation.history), 
+													  Arc::clone(&simulation.block_num), 
+													  Arc::clone(&simulation.control), 
+													  consts.clone());
+
+		controller.start_task(maker_task);
+
+		// Cancel or reprice investors' stale resting orders
+		if consts.investor_stale_blocks > 0 {
+			controller.start_task(Simulation::investor_requote_task(simulation.dists.clone(), 
+																Arc::clone(&simulation.house),
+																Arc::clone(&simulation.mempool), 
+																Arc::clone(&simulation.history), 
+																Arc::clone(&simulation.block_num), 
+																Arc::clone(&simulation.control), 
+																consts.clone()));
+		}
+
+
+		// Initalize a miner task to be repeated on a fixed interval, racing the miners for each block if there are several
+		let miner_task = match miners.len() {
+			1 => Simulation::miner_task(miners.into_iter().next().expect("miner"), &simulation),
+			_ => Simulation::mining_task(miners, &simulation, Arc::clone(&miner_wins)),
+		};
+
+		controller.start_task(miner_task);
+	}
+
+	// Noise traders submit and cancel orders as Poisson processes
+	if consts.num_noise_traders > 0 {
+		thread_handles.push(Simulation::noise_task(&simulation));
+	}
+
+	// Publish indicative prices and imbalances ahead of each batch
+	match consts.market_type {
+		MarketType::FBA | MarketType::KLF if consts.indicative_interval > 0 => {
+			controller.start_task(Simulation::indicative_task(Arc::clone(&simulation.books),
+													   Arc::clone(&simulation.mempool),
+													   Arc::clone(&simulation.history),
+													   Arc::clone(&simulation.block_num),
+													   Arc::clone(&simulation.control),
+													   consts.clone()));
+		},
+		_ => {},
+	}
+
+	// Accrue funding costs each block, the linked market shares the same players
+	if consts.interest_rate != 0.0 || consts.carry_cost.is_some() {
+		controller.start_task(Simulation::interest_task(Arc::clone(&simulation.house),
+												   Arc::clone(&simulation.block_num),
+												   Arc::clone(&simulation.control),
+												   consts.clone()));
+	}
+
+	// Track each player's equity over the run
+	if consts.valuation_interval > 0 {
+		controller.start_task(Simulation::valuation_task(simulation.dists.clone(),
+													 Arc::clone(&simulation.house),
+													 Arc::clone(&simulation.history),
+													 Arc::clone(&simulation.block_num),
+													 Arc::clone(&simulation.control),
+													 consts.clone()));
+	}
+
+	// Pay dividends and funding on the players' positions
+	if consts.payment_interval > 0 {
+		controller.start_task(Simulation::payment_task(simulation.dists.clone(),
+												   Arc::clone(&simulation.house),
 												   Arc::clone(&simulation.history),
-												   Arc::clone(&simulation.block_num), 
-												   consts.clone());
-	
-	controller.start_task(miner_task);
+												   Arc::clone(&simulation.block_num),
+												   Arc::clone(&simulation.control),
+												   consts.clone()));
+	}
+
+	// Zero-intelligence traders quote within their valuations as a baseline
+	if consts.num_zi_traders > 0 {
+		controller.start_task(Simulation::zero_intel_task(zi_valuations, &simulation));
+	}
+
+	// Momentum traders follow the trend in clearing prices
+	if consts.num_momentum_traders > 0 {
+		controller.start_task(Simulation::momentum_task(&simulation));
+	}
+
+	// Adaptive makers learn their spread and quote size across blocks and runs
+	let adaptive = match consts.num_adaptive_makers {
+		0 => None,
+		_ => {
+			let bandit = Bandit::load(&adaptive_path, &consts).expect(&format!("Couldn't load adaptive params {}", adaptive_path));
+			let adaptive = Arc::new(Mutex::new(Adaptive::new(bandit)));
+			controller.start_task(Simulation::adaptive_task(&simulation, Arc::clone(&adaptive)));
+			Some(adaptive)
+		},
+	};
+
+	// Retire the poorest makers and investors and replace them with newcomers or imitators of the richest
+	let population_counts = Arc::new(Mutex::new((0, 0)));
+	if consts.population_interval > 0 {
+		controller.start_task(Simulation::population_task(&simulation, Arc::clone(&population_counts)));
+	}
+
+	// TWAP and VWAP agents slice their parent orders across the horizon
+	if consts.num_twap_agents > 0 {
+		controller.start_task(Simulation::twap_task(&simulation));
+	}
+	if consts.num_vwap_agents > 0 {
+		controller.start_task(Simulation::vwap_task(&simulation));
+	}
+
+	// Searchers bid for the miner to mine their bundles
+	if consts.num_searchers > 0 {
+		controller.start_task(Simulation::searcher_task(&simulation));
+	}
+
+	// Mean reversion traders trade against deviations from the fundamental value
+	if consts.num_mean_reversion_traders > 0 {
+		controller.start_task(Simulation::mean_reversion_task(&simulation));
+	}
 
-	// Wait for investor task to finish
+	// Sync the regional mempools when the network is partitioned
+	if consts.num_regions > 1 {
+		controller.start_task(Simulation::region_sync_task(Arc::clone(&simulation.mempool),
+													   Arc::clone(&simulation.history),
+													   Arc::clone(&simulation.control),
+													   consts.clone()));
+	}
+
+	// Move the fundamental value by jumps that informed traders may be signalled ahead of,
+	// the discrete-event engine moves it as it publishes each block
+	if consts.fundamental_jump_prob > 0.0 && engine.is_none() {
+		controller.start_task(Simulation::fundamental_task(Arc::clone(&simulation.fundamental),
+													   Arc::clone(&simulation.block_num),
+													   Arc::clone(&simulation.control),
+													   consts.clone()));
+	}
+	if consts.num_informed_traders > 0 {
+		controller.start_task(Simulation::informed_task(&simulation));
+	}
+
+	// Spoofers fake depth on one side of the book and trade on the other
+	if consts.num_spoofers > 0 {
+		controller.start_task(Simulation::spoof_task(&simulation));
+	}
+
+	// Snipers race stale maker quotes being cancelled in the mempool
+	if consts.num_snipers > 0 {
+		controller.start_task(Simulation::sniper_task(sniper_policies, &simulation));
+	}
+
+	// The bridge player trades on instructions from an external process
+	if let Some(bridge_id) = bridge_id {
+		controller.start_task(Simulation::bridge_task(bridge_id, &simulation));
+	}
+
+		// Start the linked market's pipeline and the arbitrageur trading between the markets
+	let mut linked_simulation = None;
+	if let Some((linked, linked_miner, arb_id)) = linked {
+		thread_handles.push(Simulation::investor_task(linked.dists.clone(), 
+												  Arc::clone(&linked.house),
+												  Arc::clone(&linked.mempool),
+												  Arc::clone(&linked.history), 
+												  Arc::clone(&linked.block_num), 
+												  Arc::clone(&linked.control), 
+												  linked.consts.clone()));
+
+		controller.start_task(Simulation::maker_task(linked.dists.clone(), 
+												  Arc::clone(&linked.house),
+												  Arc::clone(&linked.mempool), 
+												  Arc::clone(&linked.history), 
+												  Arc::clone(&linked.block_num), 
+												  Arc::clone(&linked.control), 
+												  linked.consts.clone()));
+
+		controller.start_task(Simulation::miner_task(linked_miner, &linked));
+
+		if consts.arb_quantity > 0.0 {
+			controller.start_task(Simulation::arbitrage_task(arb_id, &simulation, &linked));
+		}
+		linked_simulation = Some(linked);
+	}
+
+	// Run the discrete-event engine to the last block
+	if let Some(engine) = engine.as_mut() {
+		let elapsed = engine.run();
+		info!("Discrete-event run: {} events over {}ms of virtual time", engine.processed, elapsed);
+		miner_wins.lock().unwrap().extend(engine.wins.drain());
+	}
+
+	// Wait for the investor and noise threads to see the stop signal
 	for h in thread_handles {
 		h.join().unwrap();
 	}
 
-	// End the tasks
-	controller.shutdown();
+	// Wait for every task to finish its last run before taking the results
+	controller.join();
 
 
 	info!("Done running simulation. Saving data...");
 
 	println!("{:?}", simulation.house.gas_fees);
 
+	// Settle any fills still pending so the final state includes them
+	simulation.house.settle_all();
+	info!("Settlement failures: {}", simulation.house.settlement.num_failures());
+
+	info!("Spoof events: {:?}", simulation.history.get_spoof_events());
+	info!("Interest paid: {}, carry costs: {}", simulation.house.get_interest_paid(), simulation.house.get_carry_costs());
+
 	// Log the final state of the players
 	simulation.house.log_all_players(UpdateReason::Final);
 
-	// Calculate the fundamental value from the configs
-	let (mean_bids, _dev_bids) = simulation.dists.read_dist_params(DistReason::BidsCenter);
-	let (mean_asks, _dev_asks) = simulation.dists.read_dist_params(DistReason::AsksCenter);
-	let fund_val = (mean_bids + mean_asks) / 2.0;
+	// The fundamental value at the end of the run, after any jumps
+	let fund_val = simulation.fundamental.value();
 	println!("fund_val: {}", fund_val);
 
+	// Players who entered part way through are measured from when they entered
+	initial_player_state.extend(simulation.house.get_entrants());
+
 	
 
 	let s = format!("Experiment ending at: {:?}", get_time());
@@ -160,13 +455,154 @@ fn main() {
 	// Calculate the pre liquidation performance results
 	let res = simulation.calc_performance_results(fund_val, initial_player_state.clone());
 	log_results!(format!("{:?},NO,{}", consts.market_type, res));
+	// The linked markets share players, so profits are combined while price metrics are per market
+	if let Some(linked) = &linked_simulation {
+		let res = linked.calc_performance_results(fund_val, initial_player_state.clone());
+		log_results!(format!("{:?},NO,{}", linked.consts.market_type, res));
+		log_results!(format!("ARB,NO,{},", linked.calc_arb_profit(&initial_player_state)));
+	}
 
 	// Each player transacts all non-zero inventory at the fundamental value
-	simulation.house.liquidate(fund_val);
+	let fund_vals: HashMap<String, f64> = consts.symbols().into_iter().map(|symbol| (symbol, fund_val)).collect();
+	simulation.house.liquidate(&fund_vals);
 
 	// Calculate the post liquidation performance results
-	let res = simulation.calc_performance_results(fund_val, initial_player_state);
+	let res = simulation.calc_performance_results(fund_val, initial_player_state.clone());
 	log_results!(format!("{:?},YES,{}", consts.market_type, res));
+	if let Some(linked) = &linked_simulation {
+		let res = linked.calc_performance_results(fund_val, initial_player_state.clone());
+		log_results!(format!("{:?},YES,{}", linked.consts.market_type, res));
+		log_results!(format!("ARB,YES,{},", linked.calc_arb_profit(&initial_player_state)));
+	}
+
+	// Risk of each maker type from the equity curves recorded every valuation_interval blocks
+	log_results!(format!("RISK,{},", simulation.calc_risk_results()));
+
+	// Each maker's quoting parameters next to its results
+	for row in simulation.calc_maker_params() {
+		log_results!(format!("MAKER,{}", row));
+	}
+
+	// Each maker's and investor's risk aversion next to its profit
+	for row in simulation.calc_risk_profits(&initial_player_state) {
+		log_results!(format!("RISK,{}", row));
+	}
+
+	// The miners' sandwich attacks, separately from their gas and other trading
+	let (sandwiches, sandwich_profit) = simulation.calc_sandwich_profit();
+	log_results!(format!("SANDWICH,{},{},", sandwiches, sandwich_profit));
+
+	// Bundles the searchers sent, how many were mined and what they paid the miners
+	let (submitted, included, paid) = simulation.history.get_bundle_totals();
+	log_results!(format!("BUNDLE,{},{},{},", submitted, included, paid));
+
+	// The orders sent privately to the miner and publicly, the share sent privately, the fees
+	// they paid the miner, and the front-running the public flow was exposed to
+	let (private, public, private_fees) = simulation.history.get_flow_totals();
+	let private_share = if private + public > 0 { private as f64 / (private + public) as f64 } else { 0.0 };
+	log_results!(format!("PRIVATE,{},{},{},{},{},{},", private, public, private_share, private_fees, sandwiches, sandwich_profit));
+
+	// The commitments mined, the revealed orders mined and the reveals that didn't match
+	if consts.commit_reveal {
+		let (committed, revealed, rejected) = simulation.history.get_commit_reveal_totals();
+		log_results!(format!("COMMIT,{},{},{},", committed, revealed, rejected));
+	}
+
+	// The orders sent encrypted and those that leaked, with the miner's and snipers' profits from reading the rest
+	if consts.encrypted_mempool {
+		let (encrypted, leaked) = *simulation.mempool.encryption_totals.lock().unwrap();
+		let sniper_profit = simulation.calc_sniper_profit(&initial_player_state);
+		log_results!(format!("ENCRYPT,{},{},{},{},{},", encrypted, leaked, sandwiches, sandwich_profit, sniper_profit));
+	}
+
+	// The gas oracle's estimate after each block
+	for (block, estimate) in simulation.history.get_gas_estimates() {
+		log_results!(format!("GAS,{},{},{},{},{},", block, estimate.low, estimate.median, estimate.high, estimate.suggested));
+	}
+
+	// The orders left waiting in the mempool after each block
+	for (block, stats) in simulation.history.get_mempool_stats() {
+		log_results!(format!("MEMPOOL,{},{},{},{},{},{},{},{},{},{},", block, stats.depth, stats.gas_low, stats.gas_median, stats.gas_high,
+			stats.oldest_age.as_millis(), stats.enters, stats.updates, stats.cancels, stats.mass_cancels));
+	}
+
+	// The orders over the per-trader rate limits that were rejected and surcharged
+	if let Some(limit) = &simulation.mempool.rate_limit {
+		let (rejected, surcharged) = *limit.totals.lock().unwrap();
+		log_results!(format!("RATELIMIT,{},{},", rejected, surcharged));
+	}
+
+	// The orders a full mempool evicted and how many of them were resubmitted with more gas
+	let (evicted, resubmitted) = simulation.history.get_evictions();
+	log_results!(format!("EVICT,{},{},", evicted, resubmitted));
+
+	// The pending orders replaced by fee
+	log_results!(format!("RBF,{},", simulation.history.get_replacements()));
+
+	// The orphaned blocks, the orders they returned to the mempool and the changes they reversed
+	let reorgs = simulation.history.get_reorgs();
+	log_results!(format!("REORG,{},{},{},", reorgs.len(), reorgs.iter().map(|r| r.1).sum::<usize>(), reorgs.iter().map(|r| r.2).sum::<usize>()));
+
+	// How many orders arrived in each region and how long they took to reach every region
+	for (region, synced, mean_delay) in simulation.history.get_region_syncs() {
+		log_results!(format!("REGION,{},{},{},", region, synced, mean_delay));
+	}
+
+	// The orders the miner kept out of its frames
+	let (censored, censored_traders, censored_blocks) = simulation.calc_censor_stats();
+	log_results!(format!("CENSOR,{},{},{},", censored, censored_traders, censored_blocks));
+
+	// How many blocks each miner won and what it made, only kept when they compete
+	if consts.num_miners > 1 {
+		let wins = miner_wins.lock().unwrap().clone();
+		for row in simulation.calc_miner_results(&wins, &initial_player_state) {
+			log_results!(format!("MINER,{}", row));
+		}
+	}
+
+	// How each strategy the miner switched between did
+	if consts.miner_regime != MinerRegime::Fixed {
+		for row in simulation.calc_behavior_results() {
+			log_results!(format!("BEHAVIOR,{}", row));
+		}
+	}
+
+	// How many players retired and how many of their replacements were imitators
+	let (retired, imitators) = *population_counts.lock().unwrap();
+	log_results!(format!("POP,{},{},", retired, imitators));
+
+	// Each TWAP agent's implementation shortfall and each VWAP agent's tracking error
+	for row in simulation.calc_execution_results() {
+		log_results!(format!("EXEC,{}", row));
+	}
+	for row in simulation.calc_vwap_results() {
+		log_results!(format!("VWAP,{}", row));
+	}
+
+	// Credit the adaptive makers' last arms and keep what they learned for the next run
+	if let Some(adaptive) = adaptive {
+		let arms = simulation.settle_adaptive(&adaptive, &adaptive_path).expect("Couldn't save adaptive params");
+		for arm in arms {
+			log_results!(format!("ADAPT,{},{},{},{},", arm.spread, arm.quote_size, arm.pulls, arm.mean_reward));
+		}
+	}
+
+	// Rank every player by their results after liquidation
+	let standings = simulation.house.leaderboard();
+	let csv = leaderboard::to_csv(&standings).expect("leaderboard csv");
+	std::fs::write(format!("log/leaderboard_{}.csv", filename), csv).expect("Couldn't write leaderboard csv");
+	let json = leaderboard::to_json(&standings).expect("leaderboard json");
+	std::fs::write(format!("log/leaderboard_{}.json", filename), json).expect("Couldn't write leaderboard json");
+
+	// Every block mined, for inspecting what was mined when
+	let json = simulation.chain.to_json().expect("chain json");
+	std::fs::write(format!("log/chain_{}.json", filename), json).expect("Couldn't write chain json");
+
+	// The orders each block mined, to replay the run from
+	Replay::save(&simulation.history.get_frames(), &format!("log/frames_{}.json", filename)).expect("Couldn't write replay frames");
+
+	// Write out everything still buffered in the loggers
+	log::logger().flush();
 
 }
 
@@ -0,0 +1,119 @@
+use crate::order::order::Order;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+
+/// Hashes the contents of the order with the trader's salt. Gas and nonce aren't part of the
+/// hash so the order can still be replaced by fee after it's committed.
+pub fn order_hash(order: &Order, salt: u64) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	order.trader_id.hash(&mut hasher);
+	order.order_id.hash(&mut hasher);
+	format!("{:?}{:?}{:?}", order.order_type, order.trade_type, order.ex_type).hash(&mut hasher);
+	for x in [order.p_low, order.p_high, order.price, order.quantity, order.u_max].iter() {
+		x.to_bits().hash(&mut hasher);
+	}
+	order.symbol.hash(&mut hasher);
+	salt.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// A hash commitment mined into a block ahead of its order being revealed
+/// block: u64 -> the block the commitment was mined in
+/// seq: u64 -> the order commitments were mined in, which revealed orders execute in
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commitment {
+	pub hash: u64,
+	pub trader_id: String,
+	pub block: u64,
+	pub seq: u64,
+}
+
+/// The pending commitments of the commit-reveal scheme
+/// mined: Mutex<HashMap<u64, Commitment>> -> commitments mined but not yet revealed, indexed by hash
+/// unrevealed: Mutex<Vec<(u64, Order)>> -> traders' orders waiting for their commitment to be mined
+/// seq: Mutex<u64> -> the number of commitments mined
+pub struct CommitmentStore {
+	pub mined: Mutex<HashMap<u64, Commitment>>,
+	pub unrevealed: Mutex<Vec<(u64, Order)>>,
+	pub seq: Mutex<u64>,
+}
+
+impl CommitmentStore {
+	pub fn new() -> CommitmentStore {
+		CommitmentStore {
+			mined: Mutex::new(HashMap::new()),
+			unrevealed: Mutex::new(Vec::new()),
+			seq: Mutex::new(0),
+		}
+	}
+
+	/// Keeps the trader's order until the commitment to it is mined
+	pub fn hold(&self, hash: u64, order: Order) {
+		self.unrevealed.lock().expect("commitments hold").push((hash, order));
+	}
+
+	/// Records a commitment mined in block
+	pub fn commit(&self, hash: u64, trader_id: String, block: u64) {
+		let mut seq = self.seq.lock().expect("commitments seq");
+		*seq += 1;
+		self.mined.lock().expect("commitments commit").insert(hash, Commitment { hash, trader_id, block, seq: *seq });
+	}
+
+	/// Takes the held orders whose commitments were mined before block, ready to be revealed
+	pub fn reveals_due(&self, block: u64) -> Vec<Order> {
+		let mined = self.mined.lock().expect("commitments reveals_due");
+		let mut unrevealed = self.unrevealed.lock().expect("commitments reveals_due");
+		let (due, waiting): (Vec<(u64, Order)>, Vec<(u64, Order)>) = unrevealed.drain(..)
+			.partition(|(hash, _)| mined.get(hash).map_or(false, |c| c.block < block));
+		*unrevealed = waiting;
+		due.into_iter().map(|(_, order)| order).collect()
+	}
+
+	/// Checks a revealed order against its commitment, which must have been mined by the same
+	/// trader before block. A valid reveal uses up the commitment and returns its seq.
+	pub fn validate(&self, order: &Order, block: u64) -> Result<u64, &'static str> {
+		let hash = order_hash(order, order.salt);
+		let mut mined = self.mined.lock().expect("commitments validate");
+		match mined.get(&hash) {
+			None => Err("No commitment matches the revealed order"),
+			Some(c) if c.trader_id != order.trader_id => Err("The commitment belongs to another trader"),
+			Some(c) if c.block >= block => Err("The order was revealed before its commitment was mined"),
+			Some(_) => Ok(mined.remove(&hash).expect("commitment").seq),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+
+	#[test]
+	fn test_commit_reveal() {
+		let order = Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 0.0, 0.0, 100.0, 1.0, 0.0, 1.0).with_salt(42);
+		let hash = order_hash(&order, 42);
+		assert_ne!(hash, order_hash(&order, 43));
+
+		let store = CommitmentStore::new();
+		store.hold(hash, order.clone());
+		assert!(store.reveals_due(5).is_empty());
+		assert!(store.validate(&order, 5).is_err());
+
+		// The order is revealed the block after its commitment is mined
+		store.commit(hash, order.trader_id.clone(), 5);
+		assert!(store.reveals_due(5).is_empty());
+		assert_eq!(store.validate(&order, 5), Err("The order was revealed before its commitment was mined"));
+		assert_eq!(store.reveals_due(6).len(), 1);
+
+		// A tampered order doesn't match, the commitment is only used once
+		let mut tampered = order.clone();
+		tampered.price = 101.0;
+		assert!(store.validate(&tampered, 6).is_err());
+		assert_eq!(store.validate(&order, 6), Ok(1));
+		assert!(store.validate(&order, 7).is_err());
+	}
+}
@@ -0,0 +1,180 @@
+use crate::order::order::{Order, OrderType, TradeType};
+use crate::order::order_book::Book;
+use crate::error::{Error, Result};
+use crate::exchange::MarketType;
+use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate};
+
+use std::sync::{Mutex, Arc};
+
+
+/// A hidden venue with no pre-trade transparency. Resting orders are never
+/// copied into the History's book states, and cross in time priority at the
+/// midpoint of the lit Books.
+/// bids: Mutex<Vec<Order>> -> resting dark bids in order of arrival
+/// asks: Mutex<Vec<Order>> -> resting dark asks in order of arrival
+pub struct DarkBook {
+	pub bids: Mutex<Vec<Order>>,
+	pub asks: Mutex<Vec<Order>>,
+}
+
+impl DarkBook {
+	pub fn new() -> DarkBook {
+		DarkBook {
+			bids: Mutex::new(Vec::new()),
+			asks: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// The midpoint of the lit market's best bid and ask, if both sides have orders
+	pub fn lit_midpoint(bids: &Arc<Book>, asks: &Arc<Book>) -> Option<f64> {
+		match (bids.peek_best_price(), asks.peek_best_price()) {
+			(Some(b), Some(a)) => Some((b + a) / 2.0),
+			_ => None,
+		}
+	}
+
+	pub fn add_order(&self, order: Order) {
+		let mut side = match order.trade_type {
+			TradeType::Bid => self.bids.lock().expect("dark add_order"),
+			TradeType::Ask => self.asks.lock().expect("dark add_order"),
+		};
+		side.push(order);
+	}
+
+	pub fn cancel_order_by_id(&self, order: &Order) -> Result<()> {
+		let mut side = match order.trade_type {
+			TradeType::Bid => self.bids.lock().expect("dark cancel_order"),
+			TradeType::Ask => self.asks.lock().expect("dark cancel_order"),
+		};
+		match side.iter().position(|o| o.order_id == order.order_id) {
+			Some(i) => {
+				side.remove(i);
+				Ok(())
+			},
+			None => Err(Error::NotInBook { order_id: order.order_id, op: "dark cancel_order_by_id" }),
+		}
+	}
+
+	/// Removes all of trader_id's orders on one side, returning the removed order ids
+	pub fn cancel_all_for_trader(&self, trader_id: &str, side: TradeType) -> Vec<u64> {
+		let mut orders = match side {
+			TradeType::Bid => self.bids.lock().expect("dark cancel_all_for_trader"),
+			TradeType::Ask => self.asks.lock().expect("dark cancel_all_for_trader"),
+		};
+		let removed: Vec<u64> = orders.iter().filter(|o| o.trader_id == trader_id).map(|o| o.order_id).collect();
+		orders.retain(|o| o.trader_id != trader_id);
+		removed
+	}
+
+	pub fn len(&self) -> usize {
+		self.bids.lock().unwrap().len() + self.asks.lock().unwrap().len()
+	}
+
+	/// Applies the dark orders from a frame, then crosses the resting orders at mid.
+	/// Returns the cancels and fills as CDA style TradeResults so the ClearingHouse
+	/// can apply each PlayerUpdate individually.
+	pub fn run_dark_auction(&self, orders: Vec<Order>, mid: Option<f64>) -> Option<TradeResults> {
+		let mut updates = Vec::<PlayerUpdate>::new();
+		for order in orders {
+			match order.order_type {
+				OrderType::Enter => self.add_order(order),
+				OrderType::Cancel => {
+					match self.cancel_order_by_id(&order) {
+						Ok(()) => updates.push(PlayerUpdate::new(order.trader_id.clone(), order.trader_id.clone(),
+							order.order_id, order.order_id, -9.99, -9.99, true)),
+						Err(e) => println!("{:?}, {}", e, order.order_id),
+					}
+				},
+				OrderType::MassCancel => {
+					for o_id in self.cancel_all_for_trader(&order.trader_id, order.trade_type.clone()) {
+						updates.push(PlayerUpdate::new(order.trader_id.clone(), order.trader_id.clone(),
+							o_id, o_id, -9.99, -9.99, true));
+					}
+				},
+				OrderType::Update => println!("Updates are not supported in the dark book"),
+			}
+		}
+
+		if let Some(mid) = mid {
+			updates.append(&mut self.match_at_mid(mid));
+		}
+
+		if updates.len() == 0 {
+			return None;
+		}
+		Some(TradeResults::new(MarketType::CDA, None, 0.0, 0.0, Some(updates)))
+	}
+
+	/// Crosses every bid priced at or above mid with every ask priced at or below mid,
+	/// oldest orders first. Filled orders are removed from the book.
+	pub fn match_at_mid(&self, mid: f64) -> Vec<PlayerUpdate> {
+		let mut bids = self.bids.lock().expect("dark match bids");
+		let mut asks = self.asks.lock().expect("dark match asks");
+		let mut updates = Vec::<PlayerUpdate>::new();
+
+		let (mut b, mut a) = (0, 0);
+		while b < bids.len() && a < asks.len() {
+			if bids[b].price < mid {
+				b += 1;
+				continue;
+			}
+			if asks[a].price > mid {
+				a += 1;
+				continue;
+			}
+
+			let volume = bids[b].quantity.min(asks[a].quantity);
+			updates.push(PlayerUpdate::new(bids[b].trader_id.clone(), asks[a].trader_id.clone(),
+				bids[b].order_id, asks[a].order_id, mid, volume, false));
+			bids[b].quantity -= volume;
+			asks[a].quantity -= volume;
+
+			if bids[b].quantity <= 0.0 {
+				bids.remove(b);
+			}
+			if asks[a].quantity <= 0.0 {
+				asks.remove(a);
+			}
+		}
+		updates
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{ExchangeType, Venue};
+
+	fn dark_order(t_id: &str, t_t: TradeType, price: f64, quantity: f64) -> Order {
+		Order::new(String::from(t_id), OrderType::Enter, t_t, ExchangeType::LimitOrder,
+			0.0, 0.0, price, quantity, quantity, 0.1).with_venue(Venue::Dark)
+	}
+
+	#[test]
+	fn test_match_at_mid() {
+		let book = DarkBook::new();
+		let b1 = dark_order("b1", TradeType::Bid, 101.0, 10.0);
+		let b2 = dark_order("b2", TradeType::Bid, 99.0, 10.0);
+		let a1 = dark_order("a1", TradeType::Ask, 98.0, 4.0);
+		let a2 = dark_order("a2", TradeType::Ask, 100.0, 4.0);
+		let results = book.run_dark_auction(vec![b1.clone(), b2, a1.clone(), a2.clone()], Some(100.0)).unwrap();
+
+		// b2 is priced below the midpoint so only b1 trades
+		let updates = results.cross_results.unwrap();
+		assert_eq!(updates.len(), 2);
+		assert_eq!(updates[0].payer_order_id, b1.order_id);
+		assert_eq!(updates[0].vol_filler_order_id, a1.order_id);
+		assert_eq!(updates[1].vol_filler_order_id, a2.order_id);
+		assert!(updates.iter().all(|u| u.price == 100.0 && u.volume == 4.0));
+
+		// b1 has 2 shares left and b2 is untouched
+		assert_eq!(book.asks.lock().unwrap().len(), 0);
+		assert_eq!(book.bids.lock().unwrap()[0].quantity, 2.0);
+		assert_eq!(book.len(), 2);
+
+		// Nothing crosses without a lit midpoint
+		let a3 = dark_order("a3", TradeType::Ask, 90.0, 4.0);
+		assert!(book.run_dark_auction(vec![a3], None).is_none());
+	}
+}
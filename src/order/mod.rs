@@ -1,2 +1,4 @@
 pub mod order;
-pub mod order_book;
\ No newline at end of file
+pub mod order_book;
+pub mod dark_book;
+pub mod commitment;
@@ -1,12 +1,17 @@
 use crate::utility::{gen_order_id, get_time};
 
+/// The instrument orders trade when no symbol is given. Simulations with
+/// multiple instruments name them SYM0, SYM1, ...
+pub const DEFAULT_SYMBOL: &str = "SYM0";
+
 
 /// Enum for matching over order types
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum OrderType {
     Enter,
     Update,
     Cancel,
+    MassCancel,
 }
 
 impl Clone for OrderType {
@@ -15,13 +20,14 @@ impl Clone for OrderType {
 			OrderType::Enter => OrderType::Enter,
 			OrderType::Update => OrderType::Update,
 			OrderType::Cancel => OrderType::Cancel,
+			OrderType::MassCancel => OrderType::MassCancel,
 		}
 	}
 }
 
 
 // Enum for matching over bid or ask
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum TradeType {
     Bid,
     Ask,
@@ -37,7 +43,7 @@ impl Clone for TradeType {
 }
 
 // Enum for matching over LimitOrders and FlowOrders
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub enum ExchangeType {
     LimitOrder,
     FlowOrder,
@@ -52,10 +58,26 @@ impl Clone for ExchangeType {
 	}
 }
 
+// Enum for matching over the lit order books and the hidden DarkBook
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum Venue {
+    Lit,
+    Dark,
+}
+
+impl Clone for Venue {
+	fn clone(&self) -> Venue { 
+		match self {
+			Venue::Lit => Venue::Lit,
+			Venue::Dark => Venue::Dark,
+		}
+	}
+}
+
 /// The internal data structure that any exchange format will operate on. 
 /// trader_id: String -> identifier of the trader and their order
 /// order_id: u64 -> identifier for an order in case a trader has multiple orders
-/// order_type: OrderType{Enter, Update, Cancel} -> identifies how the order is used by the exchange
+/// order_type: OrderType{Enter, Update, Cancel, MassCancel} -> identifies how the order is used by the exchange
 /// trade_type: TradeType{Bid, Ask} -> decides which order book the order is placed in 
 ///	ex_type: ExchangeType{LimitOrder, FlowOrder} -> identifies which exchange this order is compatible with
 /// p_low: f64 -> trader's minimum willingness to buy or sell (FlowOrder)
@@ -63,7 +85,16 @@ impl Clone for ExchangeType {
 /// price: f64 -> trader's willing ness to buy or sell (LimitOrder)
 /// quantity: f64 -> amount of shares to buy/sell
 /// gas: f64 -> the gas/tx fee to post an order
-#[derive(Debug)]
+/// client_order_id: Option<u64> -> optional trader-assigned id, unique only within that trader's orders
+/// venue: Venue{Lit, Dark} -> whether the order rests in the lit Books or the DarkBook
+/// symbol: String -> the instrument being traded
+/// schedule: Option<Vec<(f64, f64)>> -> optional (price, fraction of u_max) breakpoints replacing
+///	the FlowOrder's linear ramp between p_low and p_high
+/// nonce: u64 -> the trader's count of orders sent when this one was, 0 until it is sent
+/// salt: u64 -> the salt of the commitment the order reveals, 0 if it wasn't committed
+/// encrypted: bool -> whether the order's contents are hidden until its block is formed
+/// arrival: u64 -> the order's place in the MemPool's arrivals, breaking gas ties first come first served, 0 until it arrives
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Order {
 	pub trader_id: String,
 	pub order_id: u64,		
@@ -76,6 +107,14 @@ pub struct Order {
 	pub quantity: f64,
 	pub u_max: f64,			
 	pub gas: f64,
+	pub client_order_id: Option<u64>,
+	pub venue: Venue,
+	pub symbol: String,
+	pub schedule: Option<Vec<(f64, f64)>>,
+	pub nonce: u64,
+	pub salt: u64,
+	pub encrypted: bool,
+	pub arrival: u64,
 }
 
 impl Clone for Order {
@@ -92,6 +131,14 @@ impl Clone for Order {
 			quantity: self.quantity.clone(),
 			u_max: self.u_max.clone(),
 			gas: self.gas.clone(),
+			client_order_id: self.client_order_id.clone(),
+			venue: self.venue.clone(),
+			symbol: self.symbol.clone(),
+			schedule: self.schedule.clone(),
+			nonce: self.nonce,
+			salt: self.salt,
+			encrypted: self.encrypted,
+			arrival: self.arrival,
 		}
 	}
 }
@@ -112,6 +159,102 @@ impl Order {
 			quantity: q,	
 			u_max: u,
 			gas: gas,
+			client_order_id: None,
+			venue: Venue::Lit,
+			symbol: String::from(DEFAULT_SYMBOL),
+			schedule: None,
+			nonce: 0,
+			salt: 0,
+			encrypted: false,
+			arrival: 0,
+    	}
+    }
+
+    /// Tags the order with the trader's own id so it can be referenced
+    /// without knowing the globally generated order_id.
+    pub fn with_client_id(mut self, client_id: u64) -> Order {
+    	self.client_order_id = Some(client_id);
+    	self
+    }
+
+    /// Salts the order so it can be committed to before it's revealed
+    pub fn with_salt(mut self, salt: u64) -> Order {
+    	self.salt = salt;
+    	self
+    }
+
+    /// Routes the order to the given venue, orders are Lit by default
+    pub fn with_venue(mut self, venue: Venue) -> Order {
+    	self.venue = venue;
+    	self
+    }
+
+    /// Sets the instrument the order trades, orders trade DEFAULT_SYMBOL by default
+    pub fn with_symbol(mut self, symbol: &str) -> Order {
+    	self.symbol = String::from(symbol);
+    	self
+    }
+
+    /// Replaces the FlowOrder's linear ramp with piecewise-linear breakpoints of
+    /// (price, fraction of u_max). p_low and p_high are moved to the outermost breakpoints.
+    pub fn with_schedule(mut self, mut points: Vec<(f64, f64)>) -> Order {
+    	points.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("bad schedule price"));
+    	if let (Some(first), Some(last)) = (points.first(), points.last()) {
+    		self.p_low = first.0;
+    		self.p_high = last.0;
+    	}
+    	self.schedule = Some(points);
+    	self
+    }
+
+    /// Generates n evenly spaced breakpoints between p_low and p_high. The rate follows
+    /// x^curvature for asks and (1 - x)^curvature for bids where x runs from 0 to 1,
+    /// so a curvature of 1 recreates the linear ramp.
+    pub fn curved_schedule(trade_type: &TradeType, p_low: f64, p_high: f64, n: usize, curvature: f64) -> Vec<(f64, f64)> {
+    	let mut points = Vec::new();
+    	for i in 0..n {
+    		let x = i as f64 / (n - 1) as f64;
+    		let rate = match trade_type {
+    			TradeType::Bid => (1.0 - x).powf(curvature),
+    			TradeType::Ask => x.powf(curvature),
+    		};
+    		points.push((p_low + x * (p_high - p_low), rate));
+    	}
+    	points
+    }
+
+    /// Linearly interpolates the fraction of u_max traded at price between the
+    /// schedule's breakpoints, holding the end rates outside of them.
+    fn schedule_rate(points: &Vec<(f64, f64)>, price: f64) -> f64 {
+    	let (first, last) = match (points.first(), points.last()) {
+    		(Some(first), Some(last)) => (first, last),
+    		_ => return 0.0,
+    	};
+    	if price <= first.0 {
+    		return first.1;
+    	}
+    	for w in points.windows(2) {
+    		let ((p0, r0), (p1, r1)) = (w[0], w[1]);
+    		if price <= p1 {
+    			return r0 + (r1 - r0) * (price - p0) / (p1 - p0);
+    		}
+    	}
+    	last.1
+    }
+
+    /// True if this order belongs to trader_id and was tagged with client_id
+    pub fn matches_client_id(&self, trader_id: &str, client_id: u64) -> bool {
+    	self.trader_id == trader_id && self.client_order_id == Some(client_id)
+    }
+
+    /// True if the order's prices lie within ±band_pct of ref_price.
+    /// Cancels are never rejected by the price band.
+    pub fn within_band(&self, ref_price: f64, band_pct: f64) -> bool {
+    	let (low, high) = (ref_price * (1.0 - band_pct), ref_price * (1.0 + band_pct));
+    	match (&self.order_type, &self.ex_type) {
+    		(OrderType::Cancel, _) | (OrderType::MassCancel, _) => true,
+    		(_, ExchangeType::LimitOrder) => self.price >= low && self.price <= high,
+    		(_, ExchangeType::FlowOrder) => self.p_low >= low && self.p_high <= high,
     	}
     }
 
@@ -131,6 +274,10 @@ impl Order {
     	let p_high = self.p_high;
     	let u_max = self.u_max;
     	let q_max = self.quantity;
+    	if let Some(points) = &self.schedule {
+    		// Saturate to q_max like the linear ramp
+    		return (Order::schedule_rate(points, price) * u_max).min(q_max);
+    	}
     	if price < p_low {
 			// Don't trade if price is lower than min willingness to sell
     		0.0
@@ -161,6 +308,10 @@ impl Order {
     	let p_high = self.p_high;
     	let u_max = self.u_max;
     	let q_max = self.quantity;
+    	if let Some(points) = &self.schedule {
+    		// Saturate to q_max like the linear ramp
+    		return (Order::schedule_rate(points, price) * u_max).min(q_max);
+    	}
     	if price <= p_low {
     		if q_max < u_max {
     			// Don't trade more than q_max
@@ -281,6 +432,49 @@ mod tests {
 		println!("{:?}", order.calc_flow_supply(81.09048166079447));
 		assert_eq!(order.calc_flow_supply(81.09048166079447), 162.33002965704407);
 	}
+
+	#[test]
+	fn test_flow_schedule() {
+		let linear = Order::new(String::from("trader_id"), OrderType::Enter, TradeType::Bid,
+			ExchangeType::FlowOrder, 90.0, 110.0, 100.0, 500.0, 400.0, 0.05);
+
+		// A curvature of 1 matches the linear ramp
+		let points = Order::curved_schedule(&TradeType::Bid, 90.0, 110.0, 5, 1.0);
+		let sched = linear.clone().with_schedule(points);
+		for p in vec![85.0, 90.0, 97.0, 104.5, 110.0, 120.0] {
+			assert!((sched.calc_flow_demand(p) - linear.calc_flow_demand(p)).abs() < 1e-9);
+		}
+
+		// A convex ask schedule sells less than linear inside the range
+		let points = Order::curved_schedule(&TradeType::Ask, 90.0, 110.0, 5, 2.0);
+		let ask = Order::new(String::from("trader_id"), OrderType::Enter, TradeType::Ask,
+			ExchangeType::FlowOrder, 90.0, 110.0, 100.0, 500.0, 400.0, 0.05).with_schedule(points);
+		assert_eq!(ask.calc_flow_supply(85.0), 0.0);
+		assert_eq!(ask.calc_flow_supply(100.0), 100.0);
+		assert_eq!(ask.calc_flow_supply(110.0), 400.0);
+
+		// Breakpoints are sorted and set the price range
+		let bid = linear.with_schedule(vec![(105.0, 0.0), (95.0, 1.0), (100.0, 0.25)]);
+		assert_eq!((bid.p_low, bid.p_high), (95.0, 105.0));
+		assert_eq!(bid.calc_flow_demand(97.5), 0.625 * 400.0);
+	}
+
+	#[test]
+	fn test_within_band() {
+		let order = Order::new(String::from("trader_id"), OrderType::Enter, TradeType::Bid, 
+			ExchangeType::LimitOrder, 0.0, 0.0, 108.0, 500.0, 500.0, 0.05);
+		assert!(order.within_band(100.0, 0.10));
+		assert!(!order.within_band(100.0, 0.05));
+
+		let flow = Order::new(String::from("trader_id"), OrderType::Enter, TradeType::Ask, 
+			ExchangeType::FlowOrder, 95.0, 112.0, 0.0, 500.0, 500.0, 0.05);
+		assert!(!flow.within_band(100.0, 0.10));
+		assert!(flow.within_band(100.0, 0.15));
+
+		let cancel = Order::new(String::from("trader_id"), OrderType::Cancel, TradeType::Ask, 
+			ExchangeType::LimitOrder, 0.0, 0.0, 500.0, 500.0, 500.0, 0.05);
+		assert!(cancel.within_band(100.0, 0.10));
+	}
 }
 
 
@@ -1,9 +1,9 @@
 use std::sync::Arc;
 use core::f64::{MAX, MIN};
 use crate::order::order::{Order, TradeType};
+use crate::error::{Error, Result};
 
 use std::sync::Mutex;
-use std::io;
 
 /// The struct for the order books in the exchange. The purpose
 /// is to keep track of bids and asks for calculating order crossings.
@@ -30,7 +30,7 @@ impl Book {
     }
 
     /// Adds a new order to the Book after acquiring a lock, then sorts by price
-    pub fn add_order(&self, order: Order) -> io::Result<()> {
+    pub fn add_order(&self, order: Order) -> Result<()> {
     	let mut orders = self.orders.lock().expect("ERROR: Couldn't lock book to update order");
     	match order.trade_type {
 			// Sort bids in descending order -> best bid (highest price) at end
@@ -56,7 +56,7 @@ impl Book {
     }
 
     /// Replaces the order in the order book with the supplied 'order' of the same trader_id
-    pub fn update_order(&self, order: Order) -> Result<(), &'static str> {
+    pub fn update_order(&self, order: Order) -> Result<()> {
     	// Acquire the lock
         let mut orders = self.orders.lock().expect("ERROR: Couldn't lock book to update order");
         // Search for existing order's index
@@ -71,14 +71,14 @@ impl Book {
         	orders.pop();
         } else {
         	println!("ERROR: order not found to update: {:?}", &order.order_id);
-        	return Err("ERROR: order not found to update");
+        	return Err(Error::NotInBook { order_id: order.order_id, op: "update_order" });
         }
 
         Ok(())
     }
 
     /// Cancels the existing order in the order book if it exists
-    pub fn cancel_order(&self, order: Order) -> Result<(), &'static str> {
+    pub fn cancel_order(&self, order: Order) -> Result<()> {
     	// Acquire the lock
         let mut orders = self.orders.lock().expect("couldn't acquire lock cancelling order");
         // Search for existing order's index
@@ -88,7 +88,7 @@ impl Book {
         	orders.remove(i);
         } else {
         	println!("ERROR: order not found to cancel: {:?}", &order.order_id);
-        	return Err("ERROR: order not found to cancel");
+        	return Err(Error::NotInBook { order_id: order.order_id, op: "cancel_order" });
         }
 
 		// Update the best price 
@@ -104,7 +104,7 @@ impl Book {
         Ok(())
     }
 
-	pub fn cancel_order_by_id(&self, id: u64) -> Result<(), &'static str> {
+	pub fn cancel_order_by_id(&self, id: u64) -> Result<()> {
 		// Acquire the lock
         let mut orders = self.orders.lock().expect("couldn't acquire lock cancelling order");
         // Search for existing order's index
@@ -114,7 +114,7 @@ impl Book {
         	orders.remove(i);
         } else {
         	println!("ERROR: order not found to cancel: {:?}", id);
-        	return Err("ERROR: order not found to cancel");
+        	return Err(Error::NotInBook { order_id: id, op: "cancel_order_by_id" });
         }
 		// Update the best price 
 		if let Some(last_order) = orders.last(){ 
@@ -128,8 +128,49 @@ impl Book {
         Ok(())
 	}
 
+	/// Resolves a trader's client_order_id to the global order_id of a resting order
+	pub fn find_by_client_id(&self, trader_id: &str, client_id: u64) -> Option<u64> {
+		let orders = self.orders.lock().expect("couldn't acquire lock finding order");
+		orders.iter().find(|o| o.matches_client_id(trader_id, client_id)).map(|o| o.order_id)
+	}
+
+	/// Cancels the trader's order referenced by their own client_order_id
+	pub fn cancel_order_by_client_id(&self, trader_id: &str, client_id: u64) -> Result<()> {
+		match self.find_by_client_id(trader_id, client_id) {
+			Some(id) => self.cancel_order_by_id(id),
+			None => {
+				println!("ERROR: client order not found to cancel: {}:{}", trader_id, client_id);
+				Err(Error::UnknownClientOrder { trader_id: trader_id.to_string(), client_id, op: "cancel_order_by_client_id" })
+			}
+		}
+	}
+
+	/// Cancels every order in the book belonging to trader_id on the given side.
+	/// Returns the order ids that were removed.
+	pub fn cancel_all_for_trader(&self, trader_id: &str, side: TradeType) -> Vec<u64> {
+		let mut orders = self.orders.lock().expect("couldn't acquire lock cancelling orders");
+		let mut cancelled = Vec::new();
+		orders.retain(|o| {
+			if o.trader_id == trader_id && o.trade_type == side {
+				cancelled.push(o.order_id);
+				false
+			} else {
+				true
+			}
+		});
+
+		// Update the best price 
+		if let Some(last_order) = orders.last() { 
+			let best_price = last_order.price;
+			self.update_best_price(best_price);
+		} else {
+			self.reset_best_price();
+		}
+		cancelled
+	}
+
 	// Pushes best bid/ask to end of sorted book
-	pub fn push_to_end(&self, order: Order) -> io::Result<()> {
+	pub fn push_to_end(&self, order: Order) -> Result<()> {
 		let mut orders = self.orders.lock().expect("ERROR: Couldn't lock book to update order");
     	orders.push(order);
 		Ok(())
@@ -291,6 +332,13 @@ impl Book {
     	*min_price = new_min;
     }
 
+    /// Replaces the Book's orders, as when an orphaned block is rolled back
+    pub fn restore(&self, orders: Vec<Order>) {
+        *self.orders.lock().unwrap() = orders;
+        self.find_new_max();
+        self.find_new_min();
+    }
+
     pub fn copy_orders(&self) -> Vec<Order> {
         let orders = self.orders.lock().unwrap();
         let mut v = Vec::new();
@@ -331,7 +379,7 @@ impl Book {
 #[cfg(test)]
 mod tests {
 	use super::*;
-    use crate::order::order::{TradeType};
+    use crate::order::order::{TradeType, OrderType, ExchangeType};
     use std::sync::Arc;
     use std::thread;
 
@@ -372,27 +420,21 @@ mod tests {
 		assert_eq!(*book.max_price.lock().unwrap(), MIN + 50.0);
 
 	}
-}
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
-
 
+	#[test]
+	fn test_cancel_by_client_id() {
+		let book = Book::new(TradeType::Bid);
+		let order = Order::new(String::from("MKR1"), OrderType::Enter, TradeType::Bid,
+			ExchangeType::LimitOrder, 0.0, 0.0, 100.0, 5.0, 5.0, 0.1).with_client_id(7);
+		let o_id = order.order_id;
+		book.add_order(order).unwrap();
+
+		// Another trader's client id namespace doesn't collide
+		assert_eq!(book.find_by_client_id("MKR2", 7), None);
+		assert_eq!(book.find_by_client_id("MKR1", 7), Some(o_id));
+
+		book.cancel_order_by_client_id("MKR1", 7).unwrap();
+		assert_eq!(book.len(), 0);
+		assert!(book.cancel_order_by_client_id("MKR1", 7).is_err());
+	}
+}
@@ -0,0 +1,168 @@
+use crate::players::maker::MakerParams;
+use crate::simulation::simulation_config::{Constants, Distributions};
+use crate::utility::sim_rng;
+
+use rand::Rng;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+
+/// The spread and quote size multipliers of the arms, applied to maker_base_spread and one share
+pub const SPREAD_STEPS: [f64; 3] = [0.5, 1.0, 2.0];
+pub const SIZE_STEPS: [f64; 3] = [0.5, 1.0, 2.0];
+
+/// One set of quoting parameters an adaptive maker can trade with and what it has earned
+/// pulls: u64 -> intervals traded with these parameters, across every run the file was kept for
+/// mean_reward: f64 -> average change in marked equity over those intervals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arm {
+	pub spread: f64,
+	pub quote_size: f64,
+	pub pulls: u64,
+	pub mean_reward: f64,
+}
+
+/// An epsilon-greedy bandit over a grid of spreads and quote sizes, the wider and more aggressive
+/// quoting the arms trade off. Every arm is tried once, after which the best paying arm is chosen
+/// except with probability epsilon, when a random one is. It is saved as JSON between runs so
+/// repeated simulations keep learning from each other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bandit {
+	pub epsilon: f64,
+	pub arms: Vec<Arm>,
+}
+
+impl Bandit {
+	pub fn new(consts: &Constants) -> Bandit {
+		let mut arms = Vec::new();
+		for s in SPREAD_STEPS.iter() {
+			for q in SIZE_STEPS.iter() {
+				arms.push(Arm { spread: s * consts.maker_base_spread, quote_size: *q, pulls: 0, mean_reward: 0.0 });
+			}
+		}
+		Bandit { epsilon: consts.adapt_epsilon, arms }
+	}
+
+	/// Loads the bandit saved at path, or starts a new one if there isn't one
+	pub fn load(path: &str, consts: &Constants) -> io::Result<Bandit> {
+		match fs::read_to_string(path) {
+			Ok(json) => serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Bandit::new(consts)),
+			Err(e) => Err(e),
+		}
+	}
+
+	pub fn save(&self, path: &str) -> io::Result<()> {
+		let json = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		fs::write(path, json)
+	}
+
+	/// The index of the arm to trade next
+	pub fn choose(&self) -> usize {
+		if let Some(untried) = self.arms.iter().position(|a| a.pulls == 0) {
+			return untried;
+		}
+		if Distributions::do_with_prob(self.epsilon) {
+			return sim_rng().gen_range(0, self.arms.len());
+		}
+		self.best()
+	}
+
+	/// The index of the arm with the highest mean reward
+	pub fn best(&self) -> usize {
+		let mut best = 0;
+		for (i, arm) in self.arms.iter().enumerate() {
+			if arm.mean_reward > self.arms[best].mean_reward {
+				best = i;
+			}
+		}
+		best
+	}
+
+	/// Folds reward into the arm's running mean
+	pub fn update(&mut self, arm: usize, reward: f64) {
+		let arm = &mut self.arms[arm];
+		arm.pulls += 1;
+		arm.mean_reward += (reward - arm.mean_reward) / arm.pulls as f64;
+	}
+
+	/// The arm's parameters on top of the maker's own skew and requote threshold
+	pub fn params(&self, arm: usize, base: MakerParams) -> MakerParams {
+		MakerParams {
+			spread: Some(self.arms[arm].spread),
+			quote_size: self.arms[arm].quote_size,
+			..base
+		}
+	}
+}
+
+/// The bandit shared by the adaptive makers and the arm each is trading with
+/// assigned: HashMap<String, (usize, f64)> -> each maker's arm and its marked equity when it took the arm
+#[derive(Debug, Clone)]
+pub struct Adaptive {
+	pub bandit: Bandit,
+	pub assigned: HashMap<String, (usize, f64)>,
+}
+
+impl Adaptive {
+	pub fn new(bandit: Bandit) -> Adaptive {
+		Adaptive {
+			bandit,
+			assigned: HashMap::new(),
+		}
+	}
+
+	/// Credits the maker's current arm with its equity change and picks its next arm
+	pub fn rotate(&mut self, id: &str, equity: f64, base: MakerParams) -> MakerParams {
+		self.settle(id, equity);
+		let arm = self.bandit.choose();
+		self.assigned.insert(id.to_string(), (arm, equity));
+		self.bandit.params(arm, base)
+	}
+
+	/// Credits the maker's current arm with its equity change since it took the arm
+	pub fn settle(&mut self, id: &str, equity: f64) {
+		if let Some((arm, start)) = self.assigned.remove(id) {
+			self.bandit.update(arm, equity - start);
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_bandit() {
+		let mut consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		consts.maker_base_spread = 2.0;
+		consts.adapt_epsilon = 0.0;
+		let mut adaptive = Adaptive::new(Bandit::new(&consts));
+		assert_eq!(adaptive.bandit.arms.len(), SPREAD_STEPS.len() * SIZE_STEPS.len());
+
+		// Untried arms go first, then the best paying one
+		let base = MakerParams::default();
+		let params = adaptive.rotate("MKR1", 0.0, base);
+		assert_eq!(params.spread, Some(1.0));
+		assert_eq!(params.quote_size, 0.5);
+		adaptive.rotate("MKR1", 10.0, base);
+		assert_eq!(adaptive.bandit.arms[0].mean_reward, 10.0);
+		for i in 1..adaptive.bandit.arms.len() {
+			adaptive.bandit.update(i, -1.0);
+		}
+		adaptive.settle("MKR1", 10.0);
+		assert_eq!(adaptive.bandit.arms[1].mean_reward, -0.5);
+		assert_eq!(adaptive.bandit.choose(), 0);
+
+		// Saved bandits pick up where they left off
+		let path = std::env::temp_dir().join("flow_rs_test_bandit.json");
+		let path = path.to_str().unwrap();
+		adaptive.bandit.save(path).unwrap();
+		assert_eq!(Bandit::load(path, &consts).unwrap(), adaptive.bandit);
+		fs::remove_file(path).unwrap();
+		assert_eq!(Bandit::load(path, &consts).unwrap(), Bandit::new(&consts));
+	}
+}
@@ -0,0 +1,59 @@
+use crate::simulation::simulation_config::Constants;
+
+
+/// Glosten–Milgrom style quoting. The maker reads the imbalance of recent signed order flow as
+/// the probability the flow is informed, and sets each quote at the value it expects after being
+/// hit: a buy from an informed trader means the value is value_spread above the mid, so the ask
+/// is raised by that times the chance the buyer is informed, and likewise for the bid.
+/// Batch markets don't sign their fills, so the estimate stays at min_informed there.
+/// value_spread: f64 -> how far the value is from the mid when the flow is informed
+/// min_informed: f64 -> the probability of informed flow when buys and sells balance
+#[derive(Debug, Clone, Copy)]
+pub struct AdverseSelection {
+	pub value_spread: f64,
+	pub min_informed: f64,
+}
+
+impl AdverseSelection {
+	pub fn new(consts: &Constants) -> AdverseSelection {
+		AdverseSelection {
+			value_spread: consts.gm_value_spread,
+			min_informed: consts.gm_min_informed,
+		}
+	}
+
+	/// The probability of informed flow, rising from min_informed to 1 with the flow imbalance
+	pub fn informed_prob(&self, flow_imbalance: f64) -> f64 {
+		let min = self.min_informed.max(0.0).min(1.0);
+		min + (1.0 - min) * flow_imbalance.abs().min(1.0)
+	}
+
+	/// The (bid, ask) around mid, each at least half of base_spread away. The value spread
+	/// is raised to the clearing price volatility so quotes widen in volatile markets.
+	pub fn quotes(&self, mid: f64, flow_imbalance: f64, volatility: f64, base_spread: f64) -> (f64, f64) {
+		let protection = self.informed_prob(flow_imbalance) * self.value_spread.max(volatility);
+		let half = base_spread / 2.0 + protection;
+		(mid - half, mid + half)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_adverse_selection() {
+		let gm = AdverseSelection { value_spread: 4.0, min_informed: 0.25 };
+		assert_eq!(gm.informed_prob(0.0), 0.25);
+		assert_eq!(gm.informed_prob(-1.0), 1.0);
+		assert_eq!(gm.informed_prob(0.5), 0.625);
+
+		// Balanced flow quotes the base spread plus the minimum protection
+		assert_eq!(gm.quotes(100.0, 0.0, 1.0, 2.0), (98.0, 102.0));
+		// One-sided flow widens both quotes
+		assert_eq!(gm.quotes(100.0, 1.0, 1.0, 2.0), (95.0, 105.0));
+		// Volatility above the value spread widens them further
+		assert_eq!(gm.quotes(100.0, 0.0, 8.0, 2.0), (97.0, 103.0));
+	}
+}
@@ -0,0 +1,86 @@
+use crate::exchange::amm::AmmPool;
+use crate::simulation::simulation_config::Constants;
+
+
+/// How the arbitrageur decides whether the gap between two markets is worth trading.
+/// A gap is only traded if it beats the taker fee on both legs and the gas they pay.
+/// quantity: f64 -> shares per leg
+/// taker_fee: f64 -> fraction of each leg's notional charged as it removes liquidity
+/// gas_surcharge: f64 -> fraction of the gas charged on top of it
+#[derive(Debug, Clone, Copy)]
+pub struct Arbitrageur {
+	pub quantity: f64,
+	pub taker_fee: f64,
+	pub gas_surcharge: f64,
+}
+
+impl Arbitrageur {
+	pub fn new(consts: &Constants) -> Arbitrageur {
+		Arbitrageur {
+			quantity: consts.arb_quantity,
+			taker_fee: consts.taker_fee,
+			gas_surcharge: consts.gas_surcharge,
+		}
+	}
+
+	/// The (bid, ask) an AMM pool effectively quotes: its spot price less its fee to sellers
+	/// and plus its fee to buyers
+	pub fn amm_quotes(pool: &AmmPool) -> (Option<f64>, Option<f64>) {
+		match pool.spot_price() {
+			Some(spot) if pool.fee < 1.0 => (Some(spot * (1.0 - pool.fee)), Some(spot / (1.0 - pool.fee))),
+			_ => (None, None),
+		}
+	}
+
+	/// The profit of buying quantity at buy_price and selling it at sell_price after the
+	/// taker fee and the gas paid on each leg
+	pub fn edge(&self, buy_price: f64, sell_price: f64, gas: f64) -> f64 {
+		let fees = self.taker_fee * (buy_price + sell_price) * self.quantity;
+		(sell_price - buy_price) * self.quantity - fees - 2.0 * gas * (1.0 + self.gas_surcharge)
+	}
+
+	/// Given each market's (best bid, best ask), the (market to buy on, buy price, market to sell on, sell price)
+	/// of the more profitable direction, if it is profitable at all
+	pub fn find_trade(&self, quotes: [(Option<f64>, Option<f64>); 2], gas: f64) -> Option<(usize, f64, usize, f64)> {
+		let mut best: Option<(f64, (usize, f64, usize, f64))> = None;
+		for &(buy, sell) in [(0, 1), (1, 0)].iter() {
+			if let (Some(ask), Some(bid)) = (quotes[buy].1, quotes[sell].0) {
+				let edge = self.edge(ask, bid, gas);
+				if edge > 0.0 && best.map_or(true, |(e, _)| edge > e) {
+					best = Some((edge, (buy, ask, sell, bid)));
+				}
+			}
+		}
+		best.map(|(_, trade)| trade)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_find_trade() {
+		let arb = Arbitrageur {
+			quantity: 1.0,
+			taker_fee: 0.01,
+			gas_surcharge: 0.0,
+		};
+
+		// Buy at 100 on market 1 and sell at 105 on market 0: 5 - 2.05 in fees - 1 in gas
+		let quotes = [(Some(105.0), Some(106.0)), (Some(99.0), Some(100.0))];
+		assert_eq!(arb.find_trade(quotes, 0.5), Some((1, 100.0, 0, 105.0)));
+		assert!((arb.edge(100.0, 105.0, 0.5) - 1.95).abs() < 1e-9);
+
+		// The same gap doesn't cover more gas
+		assert_eq!(arb.find_trade(quotes, 1.5), None);
+
+		// No gap
+		assert_eq!(arb.find_trade([(Some(99.0), Some(101.0)), (Some(99.0), Some(101.0))], 0.0), None);
+		assert_eq!(arb.find_trade([(None, None), (Some(99.0), Some(101.0))], 0.0), None);
+
+		let pool = AmmPool::new(100.0, 10000.0, 0.5);
+		assert_eq!(Arbitrageur::amm_quotes(&pool), (Some(50.0), Some(200.0)));
+	}
+}
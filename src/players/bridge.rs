@@ -0,0 +1,173 @@
+use crate::order::order::{Order, TradeType};
+use crate::simulation::simulation_config::Constants;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+
+/// What the external agent sees each block, sent as one line of JSON
+/// bids, asks: Vec<(f64, f64)> -> (price, quantity) of the best levels of each side, best first
+/// last_price: Option<f64> -> the last clearing price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeObservation {
+	pub block_num: u64,
+	pub trader_id: String,
+	pub symbol: String,
+	pub bids: Vec<(f64, f64)>,
+	pub asks: Vec<(f64, f64)>,
+	pub last_price: Option<f64>,
+	pub balance: f64,
+	pub inventory: f64,
+	pub open_orders: usize,
+}
+
+/// An order the external agent asks the bridge player to send
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BridgeOrder {
+	pub trade_type: TradeType,
+	pub price: f64,
+	pub quantity: f64,
+}
+
+/// The external agent's reply, one line of JSON. The default reply does nothing.
+/// cancel: bool -> cancel the bridge player's resting orders before sending the new ones
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BridgeReply {
+	#[serde(default)]
+	pub cancel: bool,
+	#[serde(default)]
+	pub orders: Vec<BridgeOrder>,
+}
+
+/// A connection to an external agent on localhost. Each block the bridge sends an observation and
+/// waits up to timeout for the reply. A late, malformed or missing reply is a no-op, and the
+/// connection is dropped so the next block reconnects rather than reading a stale reply.
+pub struct Bridge {
+	pub addr: String,
+	pub timeout: Duration,
+	pub stream: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl Bridge {
+	pub fn new(consts: &Constants) -> Bridge {
+		Bridge {
+			addr: format!("127.0.0.1:{}", consts.bridge_port),
+			timeout: Duration::from_millis(consts.bridge_timeout.max(1)),
+			stream: Mutex::new(None),
+		}
+	}
+
+	/// Sends the observation and returns the agent's reply
+	pub fn exchange(&self, obs: &BridgeObservation) -> BridgeReply {
+		let mut stream = self.stream.lock().expect("Bridge exchange");
+		if stream.is_none() {
+			*stream = self.connect();
+		}
+		let reply = match stream.as_mut() {
+			Some(s) => Bridge::request(s, obs),
+			None => None,
+		};
+		match reply {
+			Some(reply) => reply,
+			None => {
+				*stream = None;
+				BridgeReply::default()
+			}
+		}
+	}
+
+	fn connect(&self) -> Option<BufReader<TcpStream>> {
+		let addr = self.addr.parse().ok()?;
+		let stream = TcpStream::connect_timeout(&addr, self.timeout).ok()?;
+		stream.set_read_timeout(Some(self.timeout)).ok()?;
+		stream.set_write_timeout(Some(self.timeout)).ok()?;
+		Some(BufReader::new(stream))
+	}
+
+	fn request(stream: &mut BufReader<TcpStream>, obs: &BridgeObservation) -> Option<BridgeReply> {
+		let mut msg = serde_json::to_string(obs).ok()?;
+		msg.push('\n');
+		stream.get_mut().write_all(msg.as_bytes()).ok()?;
+		let mut line = String::new();
+		match stream.read_line(&mut line) {
+			Ok(n) if n > 0 => serde_json::from_str(&line).ok(),
+			_ => None,
+		}
+	}
+
+	/// Aggregates a side of the book into (price, quantity) levels, best first
+	pub fn depth(orders: &[Order], trade_type: &TradeType, levels: usize) -> Vec<(f64, f64)> {
+		let mut depth: Vec<(f64, f64)> = Vec::new();
+		for o in orders {
+			match depth.iter_mut().find(|(price, _qty)| *price == o.price) {
+				Some(level) => level.1 += o.quantity,
+				None => depth.push((o.price, o.quantity)),
+			}
+		}
+		match trade_type {
+			TradeType::Bid => depth.sort_by(|a, b| b.0.partial_cmp(&a.0).expect("Bridge depth")),
+			TradeType::Ask => depth.sort_by(|a, b| a.0.partial_cmp(&b.0).expect("Bridge depth")),
+		}
+		depth.truncate(levels);
+		depth
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, ExchangeType};
+	use std::net::TcpListener;
+	use std::thread;
+
+	#[test]
+	fn test_bridge() {
+		let order = |price: f64, quantity: f64| Order::new(format!("a"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			price, price, price, quantity, quantity, 0.0);
+		let orders = vec![order(99.0, 1.0), order(100.0, 2.0), order(99.0, 3.0), order(98.0, 1.0)];
+		assert_eq!(Bridge::depth(&orders, &TradeType::Bid, 2), vec![(100.0, 2.0), (99.0, 4.0)]);
+		assert_eq!(Bridge::depth(&orders, &TradeType::Ask, 1), vec![(98.0, 1.0)]);
+
+		// An agent that buys one share at the last price
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let port = listener.local_addr().unwrap().port();
+		thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream);
+			let mut line = String::new();
+			reader.read_line(&mut line).unwrap();
+			let obs: BridgeObservation = serde_json::from_str(&line).unwrap();
+			let reply = BridgeReply {
+				cancel: true,
+				orders: vec![BridgeOrder { trade_type: TradeType::Bid, price: obs.last_price.unwrap(), quantity: 1.0 }],
+			};
+			writeln!(reader.get_mut(), "{}", serde_json::to_string(&reply).unwrap()).unwrap();
+		});
+
+		let bridge = Bridge {
+			addr: format!("127.0.0.1:{}", port),
+			timeout: Duration::from_millis(1000),
+			stream: Mutex::new(None),
+		};
+		let obs = BridgeObservation {
+			block_num: 1,
+			trader_id: format!("BRG1"),
+			symbol: format!("SYM0"),
+			bids: vec![],
+			asks: vec![],
+			last_price: Some(100.0),
+			balance: 0.0,
+			inventory: 0.0,
+			open_orders: 0,
+		};
+		let reply = bridge.exchange(&obs);
+		assert!(reply.cancel);
+		assert_eq!(reply.orders, vec![BridgeOrder { trade_type: TradeType::Bid, price: 100.0, quantity: 1.0 }]);
+
+		// The agent hung up, so the next block is a no-op
+		assert_eq!(bridge.exchange(&obs), BridgeReply::default());
+	}
+}
@@ -0,0 +1,69 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::{Constants, Distributions};
+
+
+/// How an informed trader trades ahead of the public on a private signal of the next
+/// fundamental value jump. The signal arrives with signal_prob each time it trades and is
+/// the jump plus noise, so a smaller noise is a more precise signal.
+/// signal_prob: f64 -> chance a signal of the next jump arrives
+/// noise: f64 -> std dev of the noise added to the jump
+/// quantity: f64 -> shares per order
+#[derive(Debug, Clone, Copy)]
+pub struct InformedTrader {
+	pub signal_prob: f64,
+	pub noise: f64,
+	pub quantity: f64,
+}
+
+impl InformedTrader {
+	pub fn new(consts: &Constants) -> InformedTrader {
+		InformedTrader {
+			signal_prob: consts.informed_signal_prob,
+			noise: consts.informed_noise,
+			quantity: consts.informed_quantity,
+		}
+	}
+
+	/// A noisy signal of the jump, if one arrives
+	pub fn signal(&self, jump: f64) -> Option<f64> {
+		if !Distributions::do_with_prob(self.signal_prob) {
+			return None;
+		}
+		if self.noise <= 0.0 {
+			return Some(jump);
+		}
+		Some(jump + Distributions::sample_normal(0.0, self.noise, None))
+	}
+
+	/// Buys if the value expected after the jump is above the price and sells if it is below
+	pub fn decide(&self, price: f64, expected: f64) -> Option<(TradeType, f64)> {
+		if self.quantity <= 0.0 || expected == price {
+			return None;
+		}
+		match expected > price {
+			true => Some((TradeType::Bid, self.quantity)),
+			false => Some((TradeType::Ask, self.quantity)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_informed() {
+		let trader = InformedTrader {
+			signal_prob: 1.0,
+			noise: 0.0,
+			quantity: 3.0,
+		};
+		assert_eq!(trader.signal(5.0), Some(5.0));
+		assert_eq!(InformedTrader { signal_prob: 0.0, ..trader }.signal(5.0), None);
+
+		assert_eq!(trader.decide(100.0, 105.0), Some((TradeType::Bid, 3.0)));
+		assert_eq!(trader.decide(100.0, 95.0), Some((TradeType::Ask, 3.0)));
+		assert_eq!(trader.decide(100.0, 100.0), None);
+	}
+}
@@ -0,0 +1,70 @@
+use crate::simulation::simulation_config::Constants;
+
+
+/// Avellaneda–Stoikov quoting. The maker quotes around a reservation price shifted against its
+/// inventory, with a spread that widens with volatility and the time left to the horizon.
+/// The horizon is normalized so the simulation's num_blocks is one unit of time.
+/// risk_aversion: f64 -> gamma, how hard inventory and volatility push the quotes
+/// intensity: f64 -> k, how quickly the chance of a fill decays with distance from the mid
+#[derive(Debug, Clone, Copy)]
+pub struct InventorySkew {
+	pub risk_aversion: f64,
+	pub intensity: f64,
+}
+
+impl InventorySkew {
+	pub fn new(consts: &Constants) -> InventorySkew {
+		InventorySkew {
+			risk_aversion: consts.skew_risk_aversion,
+			intensity: consts.skew_intensity,
+		}
+	}
+
+	/// The fraction of the simulation left at block_num
+	pub fn time_left(block_num: u64, consts: &Constants) -> f64 {
+		consts.num_blocks.saturating_sub(block_num) as f64 / consts.num_blocks.max(1) as f64
+	}
+
+	/// r = mid - q * gamma * sigma^2 * (T - t)
+	pub fn reservation_price(&self, mid: f64, inventory: f64, volatility: f64, time_left: f64) -> f64 {
+		mid - inventory * self.risk_aversion * volatility.powi(2) * time_left
+	}
+
+	/// gamma * sigma^2 * (T - t) + 2 / gamma * ln(1 + gamma / k)
+	pub fn spread(&self, volatility: f64, time_left: f64) -> f64 {
+		let g = self.risk_aversion;
+		g * volatility.powi(2) * time_left + (2.0 / g) * (1.0 + g / self.intensity).ln()
+	}
+
+	/// The (bid, ask) quoted either side of the reservation price
+	pub fn quotes(&self, mid: f64, inventory: f64, volatility: f64, time_left: f64) -> (f64, f64) {
+		let r = self.reservation_price(mid, inventory, volatility, time_left);
+		let half = self.spread(volatility, time_left) / 2.0;
+		(r - half, r + half)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_skewed_quotes() {
+		let skew = InventorySkew { risk_aversion: 0.1, intensity: 1.5 };
+		let base = (2.0 / 0.1) * (1.0 + 0.1 / 1.5f64).ln();
+
+		// Flat at the horizon the quotes are symmetric around the mid
+		let (bid, ask) = skew.quotes(100.0, 0.0, 2.0, 0.0);
+		assert!((ask - bid - base).abs() < 1e-9);
+		assert!(((bid + ask) / 2.0 - 100.0).abs() < 1e-9);
+
+		// A long maker shades both quotes down, a short maker shades them up
+		assert_eq!(skew.reservation_price(100.0, 5.0, 2.0, 0.5), 99.0);
+		assert_eq!(skew.reservation_price(100.0, -5.0, 2.0, 0.5), 101.0);
+
+		// More time left and more volatility widen the spread
+		assert!(skew.spread(2.0, 1.0) > skew.spread(2.0, 0.5));
+		assert!(skew.spread(3.0, 0.5) > skew.spread(2.0, 0.5));
+	}
+}
@@ -1,10 +1,14 @@
 use crate::simulation::simulation_history::UpdateReason;
 use crate::utility::get_time;
-use crate::players::{Player,TraderT};
-use std::sync::Mutex;
+use crate::players::{Player, TraderT, MarginAccount};
+use crate::players::strategy::Strategy;
+use crate::players::script::OrderScript;
+use std::sync::{Arc, Mutex};
 use crate::order::order::{Order, OrderType};
+use crate::exchange::exchange_logic::OrderEvent;
 
 use std::any::Any;
+use std::collections::HashMap;
 
 
 
@@ -13,9 +17,13 @@ pub struct Investor {
 	pub trader_id: String,
 	pub orders: Mutex<Vec<Order>>,
 	pub balance: f64,
-	pub inventory: f64,
+	pub inventory: HashMap<String, f64>,
 	pub player_type: TraderT,
 	pub sent_orders: Mutex<Vec<(u64, OrderType)>>,
+	pub order_events: Mutex<Vec<OrderEvent>>,
+	pub margin: MarginAccount,
+	pub risk_aversion: f64,
+	pub script: Option<Arc<dyn OrderScript>>,
 }
 
 /// The 
@@ -25,17 +33,153 @@ impl Investor {
 			trader_id: trader_id,
 			orders: Mutex::new(Vec::<Order>::new()),
 			balance: 0.0,
-			inventory: 0.0,
+			inventory: HashMap::new(),
 			player_type: TraderT::Investor,
 			sent_orders: Mutex::new(Vec::<(u64, OrderType)>::new()),
+			order_events: Mutex::new(Vec::<OrderEvent>::new()),
+			margin: MarginAccount::new(),
+			risk_aversion: 0.0,
+			script: None,
 		}
 	}
 
+	/// Sets how risk averse the investor is, scaling down the size of its orders
+	pub fn with_risk_aversion(mut self, risk_aversion: f64) -> Investor {
+		self.risk_aversion = risk_aversion;
+		self
+	}
+
+	/// An Investor that trades the spread between two linked markets. It settles like
+	/// any other Investor but isn't selected by the investor task.
+	pub fn new_arbitrageur(trader_id: String) -> Investor {
+		let mut arb = Investor::new(trader_id);
+		arb.player_type = TraderT::Arbitrageur;
+		arb
+	}
+
+	/// A budget-constrained zero-intelligence trader. It settles like any other Investor
+	/// but only trades from the ZI task, within the valuation it was registered with.
+	pub fn new_zero_intel(trader_id: String) -> Investor {
+		let mut zi = Investor::new(trader_id);
+		zi.player_type = TraderT::ZeroIntel;
+		zi
+	}
+
+	/// An Investor that follows the trend in clearing prices from the momentum task
+	pub fn new_momentum(trader_id: String) -> Investor {
+		let mut mom = Investor::new(trader_id);
+		mom.player_type = TraderT::Momentum;
+		mom
+	}
+
+	/// An Investor that trades against deviations from the fundamental value from the mean reversion task
+	pub fn new_mean_reversion(trader_id: String) -> Investor {
+		let mut mrv = Investor::new(trader_id);
+		mrv.player_type = TraderT::MeanReversion;
+		mrv
+	}
+
+	/// An Investor that trades on private signals of fundamental value jumps from the informed task
+	pub fn new_informed(trader_id: String) -> Investor {
+		let mut inf = Investor::new(trader_id);
+		inf.player_type = TraderT::Informed;
+		inf
+	}
+
+	/// An Investor that submits and cancels orders at random from the noise task
+	pub fn new_noise(trader_id: String) -> Investor {
+		let mut nse = Investor::new(trader_id);
+		nse.player_type = TraderT::Noise;
+		nse
+	}
+
+	/// An Investor that fakes depth with orders it cancels before they execute, from the spoof task
+	pub fn new_spoofer(trader_id: String) -> Investor {
+		let mut spf = Investor::new(trader_id);
+		spf.player_type = TraderT::Spoofer;
+		spf
+	}
+
+	/// An Investor that races stale maker quotes it sees being cancelled in the mempool, from the sniper task
+	pub fn new_sniper(trader_id: String) -> Investor {
+		let mut snp = Investor::new(trader_id);
+		snp.player_type = TraderT::Sniper;
+		snp
+	}
+
+	/// An Investor whose orders come from an RL policy stepping the simulation through an Env
+	pub fn new_learner(trader_id: String) -> Investor {
+		let mut lrn = Investor::new(trader_id);
+		lrn.player_type = TraderT::Learner;
+		lrn
+	}
+
+	/// An Investor whose orders come from an external process over the bridge task's socket
+	pub fn new_bridge(trader_id: String) -> Investor {
+		let mut brg = Investor::new(trader_id);
+		brg.player_type = TraderT::Bridge;
+		brg
+	}
+
+	/// An Investor that executes a parent order in even slices from the TWAP task
+	pub fn new_twap(trader_id: String) -> Investor {
+		let mut twp = Investor::new(trader_id);
+		twp.player_type = TraderT::Twap;
+		twp
+	}
+
+	/// An Investor that executes a parent order in proportion to market volume from the VWAP task
+	pub fn new_vwap(trader_id: String) -> Investor {
+		let mut vwp = Investor::new(trader_id);
+		vwp.player_type = TraderT::Vwap;
+		vwp
+	}
+
+	/// An Investor that bids for its bundles to be mined from the searcher task
+	pub fn new_searcher(trader_id: String) -> Investor {
+		let mut src = Investor::new(trader_id);
+		src.player_type = TraderT::Searcher;
+		src
+	}
+
+	/// An Investor whose orders come from a strategy script instead of the bid/ask distributions
+	pub fn new_scripted(trader_id: String, script: Arc<dyn OrderScript>) -> Investor {
+		let mut inv = Investor::new(trader_id);
+		inv.script = Some(script);
+		inv
+	}
+
 	pub fn new_limit_order() -> Order {
 		unimplemented!();
 	}
 }
 
+// Investors trade from their own tasks, which cancel or reprice their stale orders. A scripted
+// investor's task asks its script for its orders.
+impl Strategy for Investor {
+	fn script(&self) -> Option<Arc<dyn OrderScript>> {
+		self.script.clone()
+	}
+
+	fn stale_orders(&self, block_num: u64, stale_blocks: u64, sent_quantity: &dyn Fn(u64) -> Option<f64>) -> Vec<Order> {
+		let events = self.order_events.lock().expect("stale_orders");
+		let orders = self.orders.lock().expect("stale_orders");
+		orders.iter()
+			.filter(|o| {
+				let acked = events.iter().rev().find_map(|e| match e {
+					OrderEvent::Ack(ack) if ack.order_id == o.order_id => Some(ack.block_num),
+					_ => None,
+				});
+				match acked {
+					Some(acked) => block_num.saturating_sub(acked) >= stale_blocks && sent_quantity(o.order_id) == Some(o.quantity),
+					None => false,
+				}
+			})
+			.cloned()
+			.collect()
+	}
+}
+
 impl Player for Investor {
 	fn as_any(&self) -> &dyn Any {
 		self
@@ -50,8 +194,19 @@ impl Player for Investor {
 		self.balance
 	}
 
-	fn get_inv(&self) -> f64 {
-		self.inventory
+	fn get_inv(&self, symbol: &str) -> f64 {
+		match self.inventory.get(symbol) {
+			Some(inv) => *inv,
+			None => 0.0,
+		}
+	}
+
+	fn get_total_inv(&self) -> f64 {
+		self.inventory.values().sum()
+	}
+
+	fn get_positions(&self) -> HashMap<String, f64> {
+		self.inventory.clone()
 	}
 
 	fn get_player_type(&self) -> TraderT {
@@ -62,8 +217,8 @@ impl Player for Investor {
 		self.balance += to_add;
 	}
 
-	fn update_inv(&mut self, to_add: f64) {
-		self.inventory += to_add;
+	fn update_inv(&mut self, symbol: &str, to_add: f64) {
+		*self.inventory.entry(symbol.to_string()).or_insert(0.0) += to_add;
 	}
 
 	fn add_order(&mut self,	 order: Order) {
@@ -90,6 +245,34 @@ impl Player for Investor {
 		sent.push((o_id, order_type));
 	}
 
+	fn recv_order_event(&self, event: OrderEvent) {
+		// A rejected order isn't live at the exchange
+		if let OrderEvent::Reject(_) = event {
+			self.orders.lock().expect("recv_order_event").retain(|o| o.order_id != event.order_id());
+		}
+		self.order_events.lock().expect("recv_order_event").push(event);
+	}
+
+	fn copy_order_events(&self) -> Vec<OrderEvent> {
+		self.order_events.lock().expect("copy_order_events").clone()
+	}
+
+	fn copy_sent_orders(&self) -> Vec<(u64, OrderType)> {
+		self.sent_orders.lock().expect("copy_sent_orders").clone()
+	}
+
+	fn get_margin(&self) -> MarginAccount {
+		self.margin.clone()
+	}
+
+	fn set_margin(&mut self, margin: MarginAccount) {
+		self.margin = margin;
+	}
+
+	fn get_risk_aversion(&self) -> f64 {
+		self.risk_aversion
+	}
+
 	fn num_orders(&self) -> usize {
 		self.orders.lock().unwrap().len()
 	}
@@ -138,6 +321,19 @@ impl Player for Investor {
         }
 	}
 
+	// Raises the gas of the order while it's still pending, the replacement taking its place
+	// rather than being sent as a second order
+	fn replace_order_gas(&mut self, o_id: u64, gas: f64) -> Result<(), &'static str> {
+		let mut orders = self.orders.lock().expect("couldn't acquire lock replacing order");
+		match orders.iter_mut().find(|o| o.order_id == o_id) {
+			Some(order) => {
+				order.gas = gas;
+				Ok(())
+			},
+			None => Err("ERROR: order not found to replace"),
+		}
+	}
+
 	// Updates the order's volume and removes it if the vol <= 0
 	fn update_order_vol(&mut self, o_id: u64, vol_to_add: f64) -> Result<(), &'static str> {
 		// Get the lock on the player's orders
@@ -173,7 +369,7 @@ impl Player for Investor {
 				self.trader_id.clone(),
 				self.player_type.clone(),
 				self.balance,
-				self.inventory)
+				self.get_total_inv())
 	}
 
 }
@@ -182,17 +378,45 @@ impl Player for Investor {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::order::order::DEFAULT_SYMBOL;
 
 	#[test]
 	fn test_new_investor() {
 		let mut i = Investor::new(format!("{:?}", "BillyBob"));
 		i.update_bal(55.0);
-		i.update_inv(100.0);
+		i.update_inv(DEFAULT_SYMBOL, 100.0);
+		i.update_inv("SYM1", -40.0);
 
 		assert_eq!(i.get_bal(), 55.0);
-		assert_eq!(i.get_inv(), 100.0);
+		assert_eq!(i.get_inv(DEFAULT_SYMBOL), 100.0);
+		assert_eq!(i.get_inv("SYM1"), -40.0);
+		assert_eq!(i.get_total_inv(), 60.0);
 
 	}
 
+	#[test]
+	fn test_stale_orders() {
+		use crate::order::order::{TradeType, ExchangeType};
+		let mut i = Investor::new(format!("INV1"));
+		let order = |qty: f64| Order::new(format!("INV1"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			99.0, 99.0, 99.0, qty, qty, 0.0);
+		let (old, partial, fresh) = (order(5.0), order(5.0), order(5.0));
+		i.recv_order_event(OrderEvent::ack(&old, 2));
+		i.recv_order_event(OrderEvent::ack(&partial, 2));
+		i.recv_order_event(OrderEvent::ack(&fresh, 8));
+		let old_id = old.order_id;
+		i.add_order(old);
+		let mut partial = partial;
+		partial.quantity = 3.0;
+		i.add_order(partial);
+		i.add_order(fresh);
+
+		// Only the order acked 8 blocks ago with nothing filled is stale
+		let stale = i.stale_orders(10, 5, &|_| Some(5.0));
+		assert_eq!(stale.len(), 1);
+		assert_eq!(stale[0].order_id, old_id);
+		assert!(i.stale_orders(10, 0, &|_| None).is_empty());
+	}
+
 
 }
@@ -1,38 +1,93 @@
 use crate::simulation::simulation_history::UpdateReason;
-use crate::utility::get_time;
-use crate::simulation::simulation_config::{Distributions, Constants};
+use crate::utility::{get_time, sim_rng};
+use crate::simulation::simulation_config::{Distributions, DistReason, Constants};
 use crate::simulation::simulation_history::{PriorData, LikelihoodStats};
 use crate::exchange::MarketType;
-use crate::players::{Player, TraderT};
+use crate::players::{Player, TraderT, MarginAccount, risk_scale};
+use crate::players::strategy::{Strategy, MarketData};
+use crate::players::script::{OrderScript, script_orders};
+use crate::players::inventory_skew::InventorySkew;
+use crate::players::adverse_selection::AdverseSelection;
 use crate::order::order::{Order, TradeType, ExchangeType, OrderType};
-use std::sync::Mutex;
+use crate::exchange::exchange_logic::OrderEvent;
+use std::sync::{Arc, Mutex};
 
 use rand::Rng;
 
 use std::any::Any;
+use std::collections::HashMap;
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MakerT {
 	Aggressive,
 	RiskAverse,
 	Random,
+	InventorySkew,
+	AdverseSelection,
 }
 
 
+// The maker types assigned at random, the others are set by num_skew_makers and num_gm_makers
 const NUM_TYPES: usize = MakerT::Random as usize + 1;
 
+/// Every maker type, for the stats kept per type
+pub const NUM_MAKER_TYPES: usize = MakerT::AdverseSelection as usize + 1;
 
 
+
+/// A maker's own quoting parameters, sampled at setup so makers of the same type differ
+/// spread: Option<f64> -> replaces maker_base_spread in the maker type's spread, None keeps it
+/// quote_size: f64 -> scales the shares quoted on both sides
+/// skew: f64 -> scales how far inventory shifts the quotes, 1 is the maker type's skew and 0 none
+/// requote_threshold: f64 -> relative move in the inferred price needed before resting quotes are replaced
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MakerParams {
+	pub spread: Option<f64>,
+	pub quote_size: f64,
+	pub skew: f64,
+	pub requote_threshold: f64,
+}
+
+impl Default for MakerParams {
+	fn default() -> MakerParams {
+		MakerParams {
+			spread: None,
+			quote_size: 1.0,
+			skew: 1.0,
+			requote_threshold: 0.0,
+		}
+	}
+}
+
+impl MakerParams {
+	/// Samples each parameter from its distribution, keeping the default for any not configured
+	pub fn sample(dists: &Distributions) -> MakerParams {
+		let default = MakerParams::default();
+		MakerParams {
+			spread: dists.sample_if_set(DistReason::MakerSpread).map(|s| s.abs()),
+			quote_size: dists.sample_if_set(DistReason::MakerOrderVolume).map(|v| v.abs()).unwrap_or(default.quote_size),
+			skew: dists.sample_if_set(DistReason::MakerSkew).map(|s| s.max(0.0)).unwrap_or(default.skew),
+			requote_threshold: dists.sample_if_set(DistReason::MakerRequoteThreshold).map(|t| t.abs()).unwrap_or(default.requote_threshold),
+		}
+	}
+}
+
 /// A struct for the Maker player. 
 pub struct Maker {
 	pub trader_id: String,
 	pub orders: Mutex<Vec<Order>>,
 	pub balance: f64,
-	pub inventory: f64,
+	pub inventory: HashMap<String, f64>,
 	pub player_type: TraderT,
 	pub maker_type: MakerT,
 	pub sent_orders: Mutex<Vec<(u64, OrderType)>>,
+	pub order_events: Mutex<Vec<OrderEvent>>,
+	pub margin: MarginAccount,
+	pub next_client_id: Mutex<u64>,
+	pub script: Option<Arc<dyn OrderScript>>,
+	pub params: MakerParams,
+	pub risk_aversion: f64,
 }
 
 /// Logic for Maker trading strategy
@@ -42,13 +97,55 @@ impl Maker {
 			trader_id: trader_id,
 			orders: Mutex::new(Vec::<Order>::new()),
 			balance: 0.0,
-			inventory: 0.0,
+			inventory: HashMap::new(),
 			player_type: TraderT::Maker,
 			maker_type: maker_type,
 			sent_orders: Mutex::new(Vec::<(u64, OrderType)>::new()),
+			order_events: Mutex::new(Vec::<OrderEvent>::new()),
+			margin: MarginAccount::new(),
+			next_client_id: Mutex::new(0),
+			script: None,
+			params: MakerParams::default(),
+			risk_aversion: 0.0,
 		}
 	}
 
+	pub fn with_params(mut self, params: MakerParams) -> Maker {
+		self.params = params;
+		self
+	}
+
+	/// Sets how risk averse the maker is, widening its quotes and shrinking their size
+	pub fn with_risk_aversion(mut self, risk_aversion: f64) -> Maker {
+		self.risk_aversion = risk_aversion;
+		self
+	}
+
+	/// Widens the quotes about their middle by 1 + risk_aversion and scales the quoted shares by
+	/// risk_scale, leaving a risk neutral maker's quotes as they are
+	pub fn risk_adjust(&self, quotes: (f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
+		let (bid_price, ask_price, bid_amt, ask_amt) = quotes;
+		let mid = (bid_price + ask_price) / 2.0;
+		let half_spread = (ask_price - bid_price) / 2.0 * (1.0 + self.risk_aversion.max(0.0));
+		let scale = risk_scale(self.risk_aversion);
+		(mid - half_spread, mid + half_spread, bid_amt * scale, ask_amt * scale)
+	}
+
+	/// A maker whose orders come from a strategy script instead of its maker type.
+	/// The maker type still sets its gas and the bucket its profits are tracked in.
+	pub fn new_scripted(trader_id: String, maker_type: MakerT, script: Arc<dyn OrderScript>) -> Maker {
+		let mut maker = Maker::new(trader_id, maker_type);
+		maker.script = Some(script);
+		maker
+	}
+
+	// Returns the next id in this maker's own client order id namespace
+	pub fn gen_client_id(&self) -> u64 {
+		let mut next = self.next_client_id.lock().expect("gen_client_id");
+		*next += 1;
+		*next
+	}
+
 	pub fn copy_last_order(&self) -> Option<Order> {
 		let orders = self.orders.lock().unwrap();
 		match orders.last(){
@@ -58,7 +155,7 @@ impl Maker {
 	}
 
 	pub fn gen_rand_type() -> MakerT {
-		let mut rng = rand::thread_rng();
+		let mut rng = sim_rng();
 		match rng.gen_range(0, NUM_TYPES){
 			0 => MakerT::Aggressive,
 			1 => MakerT::RiskAverse,
@@ -82,11 +179,15 @@ impl Maker {
 			// Random players will place new gas price centered around mean
 				Distributions::sample_normal(mean_gas, 0.05, None).abs()
 			},
+			MakerT::InventorySkew|MakerT::AdverseSelection => {
+			// InventorySkew and AdverseSelection players compete on price rather than gas
+				mean_gas
+			},
 		}
 	}
 
 	pub fn normalize_inv(&self, consts: &Constants) -> f64 {
-		let inv = self.inventory;
+		let inv = self.get_total_inv();
 		if inv < 0.0 {
 			// return a ratio between [0.5, 1.0]
 			let ratio = 0.5 + (inv * 0.5) / consts.max_held_inventory;
@@ -105,6 +206,11 @@ impl Maker {
 		}
 	}
 
+	// The inventory ratio scaled around the middle by the maker's skew
+	pub fn skewed_ratio(&self, consts: &Constants) -> f64 {
+		(0.5 + self.params.skew * (self.normalize_inv(consts) - 0.5)).max(0.0).min(1.0)
+	}
+
 	// Calculates a price offset based on the makers type
 	// Given a price calculates the bid ask prices using maker type to determine spread
 	// returns tuple (bid_price, ask_price, bid_inv, ask_inv)
@@ -112,34 +218,39 @@ impl Maker {
 		match price {
 			// inf_fv = the inferred fundamental value
 			Some(inf_fv) => {
+				let base_spread = self.params.spread.unwrap_or(consts.maker_base_spread);
 				let spread;
 				match self.maker_type {
 					MakerT::Aggressive => {
-						spread = consts.maker_base_spread;
+						spread = base_spread;
 					},
 					MakerT::RiskAverse => {
 						// Slightly bigger spread
-						spread = 2.0 * consts.maker_base_spread;
+						spread = 2.0 * base_spread;
 					},
 					MakerT::Random => {
-						spread = Distributions::sample_normal(0.1 * consts.maker_base_spread, consts.maker_base_spread, None).abs();
+						spread = Distributions::sample_normal(0.1 * base_spread, base_spread, None).abs();
+					},
+					MakerT::InventorySkew|MakerT::AdverseSelection => {
+						// new_orders quotes these makers from skew_price_inv and flow_price_inv instead
+						spread = base_spread;
 					},
 				}
 
 				// Calculate the prices based on inventory and spreads
-				let cur_inv = self.inventory;
+				let cur_inv = self.get_total_inv();
 				if cur_inv == 0.0 {
 					// Maker has no inventory so center prices around inferred fund value
 					let bid_price = inf_fv - (spread / 2.0);
 					let ask_price = inf_fv + (spread / 2.0);
 					// let bid_inv = dists.sample_dist(DistReason::MakerOrderVolume).expect("MakerOrderVolume");
 					// let ask_inv = bid_inv;
-					let bid_inv = 0.5;
-					let ask_inv = 0.5;
+					let bid_inv = 0.5 * self.params.quote_size;
+					let ask_inv = 0.5 * self.params.quote_size;
 					Some((bid_price, ask_price, bid_inv, ask_inv))
 				} else if cur_inv < 0.0 {
 					// Maker has negative inventory, so shift spread for better bid price, worse ask price
-					let ratio = self.normalize_inv(&consts); 
+					let ratio = self.skewed_ratio(&consts);
 					let bid_spread = ratio * spread;
 					let ask_spread = (1.0 - ratio) * spread;
 					let bid_price = inf_fv - bid_spread;
@@ -147,13 +258,13 @@ impl Maker {
 					// let inv_amt = dists.sample_dist(DistReason::MakerOrderVolume).expect("MakerOrderVolume");
 					// let bid_inv = ratio * inv_amt;
 					// let ask_inv = (1.0 - ratio) * inv_amt;
-					let bid_inv = ratio;
-					let ask_inv = 1.0 - ratio;
+					let bid_inv = ratio * self.params.quote_size;
+					let ask_inv = (1.0 - ratio) * self.params.quote_size;
 					Some((bid_price, ask_price, bid_inv, ask_inv))
 
 				} else {
 					// Maker has positive inventory, so shift spread for better ask price, worse bid price
-					let ratio = self.normalize_inv(&consts); 
+					let ratio = self.skewed_ratio(&consts);
 					let bid_spread = ratio * spread;
 					let ask_spread = (1.0 - ratio) * spread;
 					let bid_price = inf_fv - bid_spread;
@@ -161,8 +272,8 @@ impl Maker {
 					// let inv_amt = dists.sample_dist(DistReason::MakerOrderVolume).expect("MakerOrderVolume");
 					// let bid_inv = ratio * inv_amt;
 					// let ask_inv = (1.0 - ratio) * inv_amt;
-					let bid_inv = ratio;
-					let ask_inv = 1.0 - ratio;
+					let bid_inv = ratio * self.params.quote_size;
+					let ask_inv = (1.0 - ratio) * self.params.quote_size;
 					Some((bid_price, ask_price, bid_inv, ask_inv))
 				}
 			},
@@ -172,7 +283,24 @@ impl Maker {
 	}
 
 
-	pub fn new_orders(&self, data: &PriorData, inference: &LikelihoodStats, dists: &Distributions, consts: &Constants) -> Option<(Order, Order)> {
+	/// Avellaneda–Stoikov bid and ask prices around the mid for the maker's inventory,
+	/// returns tuple (bid_price, ask_price, bid_inv, ask_inv) like calc_price_inv
+	pub fn skew_price_inv(&self, mid: f64, volatility: f64, block_num: u64, consts: &Constants) -> (f64, f64, f64, f64) {
+		let skew = InventorySkew::new(consts);
+		let time_left = InventorySkew::time_left(block_num, consts);
+		let (bid_price, ask_price) = skew.quotes(mid, self.get_total_inv(), volatility, time_left);
+		(bid_price, ask_price, 0.5 * self.params.quote_size, 0.5 * self.params.quote_size)
+	}
+
+	/// Glosten–Milgrom bid and ask prices around the mid, widened by the chance the recent
+	/// order flow is informed, returns tuple (bid_price, ask_price, bid_inv, ask_inv) like calc_price_inv
+	pub fn flow_price_inv(&self, mid: f64, inference: &LikelihoodStats, consts: &Constants) -> (f64, f64, f64, f64) {
+		let gm = AdverseSelection::new(consts);
+		let (bid_price, ask_price) = gm.quotes(mid, inference.flow_imbalance, inference.volatility, self.params.spread.unwrap_or(consts.maker_base_spread));
+		(bid_price, ask_price, 0.5 * self.params.quote_size, 0.5 * self.params.quote_size)
+	}
+
+	pub fn new_orders(&self, data: &PriorData, inference: &LikelihoodStats, block_num: u64, dists: &Distributions, consts: &Constants) -> Option<(Order, Order)> {
 		// look at the weighted average price of the mempool, exit if no orders have been sent to pool
 		let wtd_pool_price = match inference.weighted_price {
 			Some(price) => price,
@@ -188,14 +316,19 @@ impl Maker {
 
 		// type of order (FlowOrder or LimitOrder)
 		let ex_type = match consts.market_type {
-			MarketType::CDA|MarketType::FBA => ExchangeType::LimitOrder,
+			MarketType::CDA|MarketType::FBA|MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => ExchangeType::LimitOrder,
 			MarketType::KLF => ExchangeType::FlowOrder,
 		};
 
 		// Calculate the bid and ask prices offset from weighted avg price of all seen orders based on maker type
 		// And the respective quantity for each order
-		let (bid_price, ask_price, bid_amt, ask_amt) = match self.calc_price_inv(Some(wtd_pool_price), dists, consts, ask_vol, bid_vol) {
-			Some((bp, ap, ba, aa)) => (bp, ap, ba, aa),
+		let quotes = match self.maker_type {
+			MakerT::InventorySkew => Some(self.skew_price_inv(wtd_pool_price, inference.volatility, block_num, consts)),
+			MakerT::AdverseSelection => Some(self.flow_price_inv(wtd_pool_price, inference, consts)),
+			_ => self.calc_price_inv(Some(wtd_pool_price), dists, consts, ask_vol, bid_vol),
+		};
+		let (bid_price, ask_price, bid_amt, ask_amt) = match quotes {
+			Some(quotes) => self.risk_adjust(quotes),
 			None => return None,
 		};
 
@@ -222,7 +355,7 @@ impl Maker {
 								       bid_amt,
 								       bid_u_max,
 								       gas
-		);
+		).with_client_id(self.gen_client_id());
 
 		let ask_order = Order::new(self.trader_id.clone(), 
 									   OrderType::Enter,
@@ -234,7 +367,7 @@ impl Maker {
 								       ask_amt,
 								       ask_u_max,
 								       gas
-		);
+		).with_client_id(self.gen_client_id());
 
 		Some((bid_order, ask_order))
 	}
@@ -242,6 +375,47 @@ impl Maker {
 
 
 
+impl Strategy for Maker {
+	// A bid and an ask priced from the data according to the maker's type, or from its script
+	fn generate_orders(&self, data: &MarketData) -> Vec<Order> {
+		if let Some(script) = &self.script {
+			return script_orders(&self.trader_id, script.as_ref(), data);
+		}
+		match self.new_orders(data.prior, data.inference, data.block_num, data.dists, data.consts) {
+			Some((bid, ask)) => vec![bid, ask],
+			None => Vec::new(),
+		}
+	}
+
+	fn script(&self) -> Option<Arc<dyn OrderScript>> {
+		self.script.clone()
+	}
+
+	// Whether the inferred price has moved far enough from the middle of the maker's resting
+	// quotes for it to replace them. Makers without resting quotes always quote.
+	fn should_requote(&self, price: f64) -> bool {
+		let orders = self.orders.lock().expect("should_requote");
+		if orders.is_empty() || self.params.requote_threshold <= 0.0 {
+			return true;
+		}
+		let mid = orders.iter().map(|o| o.price).sum::<f64>() / orders.len() as f64;
+		mid <= 0.0 || ((price - mid) / mid).abs() > self.params.requote_threshold
+	}
+
+	fn quoting(&self) -> Option<(MakerT, MakerParams)> {
+		Some((self.maker_type.clone(), self.params))
+	}
+
+	fn set_quoting_params(&mut self, params: MakerParams) -> bool {
+		self.params = params;
+		true
+	}
+
+	fn last_client_id(&self) -> u64 {
+		*self.next_client_id.lock().expect("last_client_id")
+	}
+}
+
 impl Player for Maker {
 	fn as_any(&self) -> &dyn Any {
 		self
@@ -255,8 +429,19 @@ impl Player for Maker {
 		self.balance
 	}
 
-	fn get_inv(&self) -> f64 {
-		self.inventory
+	fn get_inv(&self, symbol: &str) -> f64 {
+		match self.inventory.get(symbol) {
+			Some(inv) => *inv,
+			None => 0.0,
+		}
+	}
+
+	fn get_total_inv(&self) -> f64 {
+		self.inventory.values().sum()
+	}
+
+	fn get_positions(&self) -> HashMap<String, f64> {
+		self.inventory.clone()
 	}
 
 	fn get_player_type(&self) -> TraderT {
@@ -267,8 +452,8 @@ impl Player for Maker {
 		self.balance += to_add;
 	}
 
-	fn update_inv(&mut self, to_add: f64) {
-		self.inventory += to_add;
+	fn update_inv(&mut self, symbol: &str, to_add: f64) {
+		*self.inventory.entry(symbol.to_string()).or_insert(0.0) += to_add;
 	}
 
 	fn add_order(&mut self,	 order: Order) {
@@ -294,6 +479,34 @@ impl Player for Maker {
 		sent.push((o_id, order_type));
 	}
 
+	fn recv_order_event(&self, event: OrderEvent) {
+		// A rejected order isn't live at the exchange
+		if let OrderEvent::Reject(_) = event {
+			self.orders.lock().expect("recv_order_event").retain(|o| o.order_id != event.order_id());
+		}
+		self.order_events.lock().expect("recv_order_event").push(event);
+	}
+
+	fn copy_order_events(&self) -> Vec<OrderEvent> {
+		self.order_events.lock().expect("copy_order_events").clone()
+	}
+
+	fn copy_sent_orders(&self) -> Vec<(u64, OrderType)> {
+		self.sent_orders.lock().expect("copy_sent_orders").clone()
+	}
+
+	fn get_margin(&self) -> MarginAccount {
+		self.margin.clone()
+	}
+
+	fn set_margin(&mut self, margin: MarginAccount) {
+		self.margin = margin;
+	}
+
+	fn get_risk_aversion(&self) -> f64 {
+		self.risk_aversion
+	}
+
 	fn num_orders(&self) -> usize {
 		self.orders.lock().unwrap().len()
 	}
@@ -342,6 +555,19 @@ impl Player for Maker {
         }
 	}
 
+	// Raises the gas of the order while it's still pending, the replacement taking its place
+	// rather than being sent as a second order
+	fn replace_order_gas(&mut self, o_id: u64, gas: f64) -> Result<(), &'static str> {
+		let mut orders = self.orders.lock().expect("couldn't acquire lock replacing order");
+		match orders.iter_mut().find(|o| o.order_id == o_id) {
+			Some(order) => {
+				order.gas = gas;
+				Ok(())
+			},
+			None => Err("ERROR: order not found to replace"),
+		}
+	}
+
 
 	// Updates the order's volume and removes it if the vol <= 0
 	fn update_order_vol(&mut self, o_id: u64, vol_to_add: f64) -> Result<(), &'static str> {
@@ -379,7 +605,7 @@ impl Player for Maker {
 				self.trader_id.clone(),
 				self.player_type.clone(),
 				self.balance,
-				self.inventory)
+				self.get_total_inv())
 	}
 
 }
@@ -388,16 +614,53 @@ impl Player for Maker {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::order::order::DEFAULT_SYMBOL;
+	use crate::simulation::simulation_config::DistType;
 
 	#[test]
 	fn test_new_maker() {
 		let mut m = Maker::new(format!("{:?}", "BillyBob"), Maker::gen_rand_type());
 		m.update_bal(55.0);
-		m.update_inv(100.0);
+		m.update_inv(DEFAULT_SYMBOL, 100.0);
+		m.update_inv("SYM1", -40.0);
 
 		assert_eq!(m.get_bal(), 55.0);
-		assert_eq!(m.get_inv(), 100.0);
+		assert_eq!(m.get_inv(DEFAULT_SYMBOL), 100.0);
+		assert_eq!(m.get_inv("SYM1"), -40.0);
+		assert_eq!(m.get_total_inv(), 60.0);
+
+	}
 
+	#[test]
+	fn test_maker_params() {
+		let dists = Distributions::new(vec![
+			(DistReason::MakerSpread, 2.0, 0.0, 1.0, DistType::Normal),
+			(DistReason::MakerRequoteThreshold, 0.1, 0.0, 1.0, DistType::Normal),
+		]);
+		let params = MakerParams::sample(&dists);
+		assert_eq!(params, MakerParams { spread: Some(2.0), quote_size: 1.0, skew: 1.0, requote_threshold: 0.1 });
+
+		let mut m = Maker::new(format!("MKR1"), MakerT::Aggressive).with_params(params);
+		assert!(m.should_requote(100.0));
+		m.add_order(Order::new(format!("MKR1"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 99.0, 99.0, 99.0, 1.0, 1.0, 0.0));
+		m.add_order(Order::new(format!("MKR1"), OrderType::Enter, TradeType::Ask, ExchangeType::LimitOrder, 101.0, 101.0, 101.0, 1.0, 1.0, 0.0));
+		assert!(!m.should_requote(105.0));
+		assert!(m.should_requote(115.0));
+	}
+
+	#[test]
+	fn test_risk_adjust() {
+		let neutral = Maker::new(format!("MKR1"), MakerT::Aggressive);
+		assert_eq!(neutral.risk_adjust((99.0, 101.0, 0.5, 0.5)), (99.0, 101.0, 0.5, 0.5));
+
+		// Twice the spread at half the size
+		let averse = Maker::new(format!("MKR2"), MakerT::Aggressive).with_risk_aversion(1.0);
+		assert_eq!(averse.risk_adjust((99.0, 101.0, 0.5, 0.5)), (98.0, 102.0, 0.25, 0.25));
+
+		let dists = Distributions::new(vec![(DistReason::RiskAversion, 0.5, 0.0, 1.0, DistType::Normal)]);
+		assert_eq!(crate::players::sample_risk_aversion(&dists), 0.5);
+		let unset = Distributions::new(vec![(DistReason::MakerSpread, 2.0, 0.0, 1.0, DistType::Normal)]);
+		assert_eq!(crate::players::sample_risk_aversion(&unset), 0.0);
 	}
 
 
@@ -0,0 +1,87 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::{Constants, Distributions};
+
+
+/// How a mean reversion trader trades against deviations of the clearing price from the
+/// fundamental value, which it only observes with noise. It buys below the value and sells
+/// above it, limit priced at the value it observed.
+/// noise: f64 -> std dev of the noise added to the fundamental value
+/// threshold: f64 -> the relative deviation from the observed value needed to trade
+/// quantity: f64 -> shares per order
+/// max_inventory: f64 -> orders never take the absolute inventory past this
+#[derive(Debug, Clone, Copy)]
+pub struct MeanReversionTrader {
+	pub noise: f64,
+	pub threshold: f64,
+	pub quantity: f64,
+	pub max_inventory: f64,
+}
+
+impl MeanReversionTrader {
+	pub fn new(consts: &Constants) -> MeanReversionTrader {
+		MeanReversionTrader {
+			noise: consts.mean_reversion_noise,
+			threshold: consts.mean_reversion_threshold,
+			quantity: consts.mean_reversion_quantity,
+			max_inventory: consts.mean_reversion_max_inventory,
+		}
+	}
+
+	/// A noisy observation of the fundamental value
+	pub fn observe(&self, fund_val: f64) -> f64 {
+		if self.noise <= 0.0 {
+			return fund_val;
+		}
+		Distributions::sample_normal(fund_val, self.noise, None)
+	}
+
+	/// The side and shares to trade given the last clearing price, the observed value and the
+	/// trader's inventory. Shares are cut so the inventory stays within max_inventory.
+	pub fn decide(&self, price: f64, value: f64, inventory: f64) -> Option<(TradeType, f64)> {
+		if value <= 0.0 {
+			return None;
+		}
+		let deviation = (price - value) / value;
+		let (trade_type, room) = match deviation {
+			d if d < -self.threshold => (TradeType::Bid, self.max_inventory - inventory),
+			d if d > self.threshold => (TradeType::Ask, self.max_inventory + inventory),
+			_ => return None,
+		};
+
+		let quantity = self.quantity.min(room);
+		if quantity <= 0.0 {
+			return None;
+		}
+		Some((trade_type, quantity))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_mean_reversion() {
+		let trader = MeanReversionTrader {
+			noise: 0.0,
+			threshold: 0.05,
+			quantity: 2.0,
+			max_inventory: 5.0,
+		};
+		assert_eq!(trader.observe(100.0), 100.0);
+
+		// Cheap relative to the value
+		assert_eq!(trader.decide(90.0, 100.0, 0.0), Some((TradeType::Bid, 2.0)));
+		// Rich relative to the value
+		assert_eq!(trader.decide(110.0, 100.0, 0.0), Some((TradeType::Ask, 2.0)));
+		// Within the threshold
+		assert_eq!(trader.decide(103.0, 100.0, 0.0), None);
+
+		// The inventory limit cuts the order and then stops it
+		assert_eq!(trader.decide(90.0, 100.0, 4.0), Some((TradeType::Bid, 1.0)));
+		assert_eq!(trader.decide(90.0, 100.0, 5.0), None);
+		assert_eq!(trader.decide(110.0, 100.0, -5.0), None);
+		assert_eq!(trader.decide(110.0, 100.0, 5.0), Some((TradeType::Ask, 2.0)));
+	}
+}
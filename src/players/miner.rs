@@ -1,27 +1,69 @@
 use crate::simulation::simulation_history::UpdateReason;
-use crate::players::{Player,TraderT};
-use crate::order::order::{Order, TradeType, OrderType};
+use crate::players::{Player, TraderT, MarginAccount};
+use crate::players::strategy::Strategy;
+use crate::order::order::{Order, TradeType, OrderType, Venue};
 use crate::blockchain::mem_pool::MemPool;
+use crate::blockchain::block_gas::BlockGas;
 use crate::blockchain::mempool_processor::MemPoolProcessor;
 use crate::order::order_book::Book;
 use crate::exchange::MarketType;
-use crate::exchange::exchange_logic::{Auction, TradeResults};
-use crate::utility::{gen_order_id,get_time};
+use crate::exchange::exchange_logic::{Auction, AuctionRules, TradeResults, OrderEvent, Fill};
+use crate::exchange::market_phase::MarketPhase;
+use crate::exchange::amm::AmmPool;
+use crate::exchange::dutch::DutchAuction;
+use crate::utility::{gen_order_id,get_time,sim_rng};
 
 use std::any::Any;
+use std::collections::HashMap;
 use std::sync::{Mutex, Arc};
-use rand::{thread_rng};
 use rand::seq::SliceRandom;
 
+/// The miner's own orders wrapped around a large marketable order in its frame: front trades
+/// on the victim's side just ahead of it and back unwinds on the other side just behind it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sandwich {
+	pub victim_id: u64,
+	pub front: Order,
+	pub back: Order,
+}
+
+impl Sandwich {
+	/// The round trip's cash from the legs' fills plus any shares left over marked at mark
+	pub fn pnl(&self, fills: &[Fill], mark: f64) -> f64 {
+		let mut cash = 0.0;
+		let mut shares = 0.0;
+		for leg in [&self.front, &self.back].iter() {
+			for fill in fills.iter() {
+				match leg.trade_type {
+					TradeType::Bid if fill.bid_order_id == leg.order_id => {
+						cash -= fill.price * fill.quantity;
+						shares += fill.quantity;
+					},
+					TradeType::Ask if fill.ask_order_id == leg.order_id => {
+						cash += fill.price * fill.quantity;
+						shares -= fill.quantity;
+					},
+					_ => {},
+				}
+			}
+		}
+		cash + shares * mark
+	}
+}
+
 /// A struct for the Miner player. 
+/// sandwiches: Vec<Sandwich> -> sandwiches in the frame, waiting for it to be published
 pub struct Miner {
 	pub trader_id: String,
 	pub orders: Mutex<Vec<Order>>,
 	pub frame: Vec<Order>,
 	pub balance: f64,
-	pub inventory: f64,
+	pub inventory: HashMap<String, f64>,
 	pub player_type: TraderT,
 	pub sent_orders: Mutex<Vec<(u64, OrderType)>>,
+	pub order_events: Mutex<Vec<OrderEvent>>,
+	pub margin: MarginAccount,
+	pub sandwiches: Vec<Sandwich>,
 }
 
 impl Miner {
@@ -32,47 +74,115 @@ impl Miner {
 			orders: Mutex::new(Vec::<Order>::new()),
 			frame: Vec::<Order>::new(),
 			balance: 0.0,
-			inventory: 0.0,
+			inventory: HashMap::new(),
 			player_type: TraderT::Miner,
 			sent_orders: Mutex::new(Vec::<(u64, OrderType)>::new()),
+			order_events: Mutex::new(Vec::<OrderEvent>::new()),
+			margin: MarginAccount::new(),
+			sandwiches: Vec::new(),
 		}
 	}
 
-	/// Miner grabs ≤ block_size orders from the MemPool to construct frame for next block
+	/// Miner grabs the highest gas orders that fit in the block's gas limit from the MemPool
+	/// to construct frame for next block
 	/// sorted by gas price
-	pub fn make_frame(&mut self, pool: Arc<MemPool>, block_size: usize) {
+	pub fn make_frame(&mut self, pool: Arc<MemPool>, gas: &BlockGas) {
 		let size = pool.length();
 		if size == 0 {
 			println!("No orders to grab from MemPool!");
 			return
 		}
-		// Sort orders in the MemPool in decreasing order by gas price
-		pool.sort_by_gas();
+		// The MemPool is kept in decreasing order by gas price
+		let delay = pool.gossip_delay(&self.trader_id);
+		self.frame = pool.pop_to_gas(gas, delay);
+	}
 
-		if size <= block_size {
-			self.frame = pool.pop_all();
-		} 
-		else {
-			self.frame = pool.pop_n(block_size);
+	/// Miner grabs the cancel orders that fit in the block's gas limit from the MemPool while trading is halted,
+	/// all other orders wait in the MemPool until trading resumes
+	pub fn make_cancel_frame(&mut self, pool: Arc<MemPool>, gas: &BlockGas) {
+		let delay = pool.gossip_delay(&self.trader_id);
+		self.frame = pool.pop_cancels(gas, delay);
+	}
+
+	/// Empties the frame into per-symbol frames, keeping each symbol's orders in frame order
+	pub fn split_frame_by_symbol(&mut self) -> HashMap<String, Vec<Order>> {
+		let mut frames = HashMap::<String, Vec<Order>>::new();
+		for order in self.frame.drain(..) {
+			frames.entry(order.symbol.clone()).or_insert(Vec::new()).push(order);
 		}
+		frames
+	}
+
+	/// Removes the orders routed to the DarkBook from the frame. MassCancels apply
+	/// to both venues so they are copied rather than removed.
+	pub fn take_dark_orders(&mut self) -> Vec<Order> {
+		let mut dark = Vec::<Order>::new();
+		let mut lit = Vec::<Order>::new();
+		for order in self.frame.drain(..) {
+			if order.order_type == OrderType::MassCancel {
+				dark.push(order.clone());
+				lit.push(order);
+			} else if order.venue == Venue::Dark {
+				dark.push(order);
+			} else {
+				lit.push(order);
+			}
+		}
+		self.frame = lit;
+		dark
+	}
+
+	/// Swaps every order in the frame against the AMM pool
+	pub fn publish_amm_frame(&mut self, pool: Arc<AmmPool>) -> Option<Vec<TradeResults>> {
+		println!("Publishing Frame: {:?}", self.frame);
+		let frame = self.frame.drain(..).collect();
+		pool.process_frame(frame)
+	}
+
+	/// Adds the frame's bids to the books like a batch market then fills them against
+	/// the Dutch auction's current ask
+	pub fn publish_dutch_frame(&mut self, bids: Arc<Book>, asks: Arc<Book>, auction: Arc<DutchAuction>) -> Option<Vec<TradeResults>> {
+		println!("Publishing Frame: {:?}", self.frame);
+		let mut results = MemPoolProcessor::seq_process_orders(&mut self.frame, Arc::clone(&bids), asks, MarketType::Dutch)
+			.unwrap_or(Vec::new());
+		if let Some(fills) = auction.run_block(bids) {
+			results.push(fills);
+		}
+		if results.len() == 0 {
+			return None;
+		}
+		Some(results)
 	}
 
 	pub fn publish_frame(&mut self, bids: Arc<Book>, asks: Arc<Book>, m_t: MarketType) -> Option<Vec<TradeResults>> {
+		self.publish_frame_in_phase(bids, asks, m_t, MarketPhase::Continuous, false, AuctionRules::default())
+	}
+
+	// Publishes the frame according to the market phase. During a CDA call auction phase
+	// orders are added to the books without crossing, and the books are uncrossed with a 
+	// uniform price auction on the block that uncross is set. Batch auctions are priced with the rules.
+	pub fn publish_frame_in_phase(&mut self, bids: Arc<Book>, asks: Arc<Book>, m_t: MarketType, phase: MarketPhase, uncross: bool, rules: AuctionRules) -> Option<Vec<TradeResults>> {
 		println!("Publishing Frame: {:?}", self.frame);
+		// Orders accumulate like a batch market while in a call auction
+		let process_m_t = match phase.is_call_auction() {
+			true => MarketType::FBA,
+			false => m_t.clone(),
+		};
+
 		// The results from processing the orders in sequential order
 		// For CDA: Cancels, Transactions
 		// For FBA & KLF: Cancels,
 		let process_results: Option<Vec<TradeResults>> = MemPoolProcessor::seq_process_orders(&mut self.frame, 
 											Arc::clone(&bids), 
 											Arc::clone(&asks), 
-											m_t.clone());
+											process_m_t);
 
 		// Don't run end-of-batch auction
 
-		if m_t == MarketType::CDA {
+		if m_t == MarketType::CDA && !phase.is_call_auction() {
 			return process_results;
 		}
-		if let Some(auction_result) = Auction::run_auction(bids, asks, m_t) {
+		if let Some(auction_result) = Auction::run_phase_auction(bids, asks, m_t, phase, uncross, rules) {
 			// Received some results from FBA or KLF auction, merge with the process_results
 			// Option<TradeResults>
 			if let Some(mut unwrapped_process_results) = process_results {
@@ -92,10 +202,12 @@ impl Miner {
 
 	// Selects a random order from the frame and appends an identical order with higher block priority
 	pub fn random_front_run(&mut self) -> Result<Order, &'static str> {
-		let mut rng = thread_rng();
-		if let Some(rand_order) = self.frame.choose(&mut rng) {
+		let mut rng = sim_rng();
+		// The miner can only copy the orders it can read
+		let readable: Vec<&Order> = self.frame.iter().filter(|o| !o.encrypted).collect();
+		if let Some(rand_order) = readable.choose(&mut rng) {
 			// Copy and update order 
-			let mut copied = rand_order.clone();
+			let mut copied = (*rand_order).clone();
 			copied.trader_id = self.trader_id.clone();
 			copied.gas = 0.0;	// No gas needed since this is miner
 			copied.order_id = gen_order_id();
@@ -173,6 +285,47 @@ impl Miner {
 	}
 
 
+	// Wraps the largest marketable Enter order of at least min_quantity in the frame between two of
+	// the miner's own orders at its price: quantity shares on the same side inserted ahead of it,
+	// and the same shares on the other side behind it to unwind once it has moved the price
+	pub fn sandwich(&mut self, best_bid_price: f64, best_ask_price: f64, min_quantity: f64, quantity: f64) -> Result<Sandwich, &'static str> {
+		let victim = self.frame.iter()
+			.enumerate()
+			.filter(|(_i, o)| o.order_type == OrderType::Enter && !o.encrypted && o.trader_id != self.trader_id && o.quantity >= min_quantity)
+			.filter(|(_i, o)| match o.trade_type {
+				TradeType::Bid => o.price >= best_ask_price,
+				TradeType::Ask => o.price <= best_bid_price,
+			})
+			.max_by(|(_i, a), (_j, b)| a.quantity.partial_cmp(&b.quantity).expect("sandwich"))
+			.map(|(i, o)| (i, o.clone()));
+		let (index, victim) = match victim {
+			Some(victim) => victim,
+			None => return Err("No large marketable order in the frame to sandwich"),
+		};
+
+		// Copy the victim's order for the front leg, no gas needed since this is the miner
+		let mut front = victim.clone();
+		front.trader_id = self.trader_id.clone();
+		front.gas = 0.0;
+		front.order_id = gen_order_id();
+		front.client_order_id = None;
+		front.quantity = quantity;
+		front.u_max = quantity;
+		let mut back = front.clone();
+		back.order_id = gen_order_id();
+		back.schedule = None;
+		back.trade_type = match victim.trade_type {
+			TradeType::Bid => TradeType::Ask,
+			TradeType::Ask => TradeType::Bid,
+		};
+
+		self.frame.insert(index + 1, back.clone());
+		self.frame.insert(index, front.clone());
+		let sandwich = Sandwich { victim_id: victim.order_id, front, back };
+		self.sandwiches.push(sandwich.clone());
+		Ok(sandwich)
+	}
+
 	// Returns the best bid and best ask in the frame
 	pub fn get_best_orders(&self) -> (Option<Order>, Option<Order>) {
 		let mut orders = self.frame.clone();
@@ -185,8 +338,8 @@ impl Miner {
 		let mut best_ask_p = std::f64::MAX;
 
 		for o in orders.iter() {
-			// Exclude the cancel orders in frame
-			if o.order_type == OrderType::Cancel {continue;}
+			// Exclude the cancel orders and the orders the miner can't read in frame
+			if o.order_type == OrderType::Cancel || o.order_type == OrderType::MassCancel || o.encrypted {continue;}
 
 			match o.trade_type {
 				TradeType::Bid => {
@@ -229,6 +382,9 @@ impl Miner {
 
 
 
+// Miners trade from their own tasks
+impl Strategy for Miner {}
+
 impl Player for Miner {
 	fn as_any(&self) -> &dyn Any {
 		self
@@ -242,8 +398,19 @@ impl Player for Miner {
 		self.balance
 	}
 
-	fn get_inv(&self) -> f64 {
-		self.inventory
+	fn get_inv(&self, symbol: &str) -> f64 {
+		match self.inventory.get(symbol) {
+			Some(inv) => *inv,
+			None => 0.0,
+		}
+	}
+
+	fn get_total_inv(&self) -> f64 {
+		self.inventory.values().sum()
+	}
+
+	fn get_positions(&self) -> HashMap<String, f64> {
+		self.inventory.clone()
 	}
 
 	fn get_player_type(&self) -> TraderT {
@@ -254,8 +421,8 @@ impl Player for Miner {
 		self.balance += to_add;
 	}
 
-	fn update_inv(&mut self, to_add: f64) {
-		self.inventory += to_add;
+	fn update_inv(&mut self, symbol: &str, to_add: f64) {
+		*self.inventory.entry(symbol.to_string()).or_insert(0.0) += to_add;
 	}
 
 	fn add_order(&mut self,	 order: Order) {
@@ -281,6 +448,35 @@ impl Player for Miner {
 		sent.push((o_id, order_type));
 	}
 
+	fn recv_order_event(&self, event: OrderEvent) {
+		// A rejected order isn't live at the exchange
+		if let OrderEvent::Reject(_) = event {
+			self.orders.lock().expect("recv_order_event").retain(|o| o.order_id != event.order_id());
+		}
+		self.order_events.lock().expect("recv_order_event").push(event);
+	}
+
+	fn copy_order_events(&self) -> Vec<OrderEvent> {
+		self.order_events.lock().expect("copy_order_events").clone()
+	}
+
+	fn copy_sent_orders(&self) -> Vec<(u64, OrderType)> {
+		self.sent_orders.lock().expect("copy_sent_orders").clone()
+	}
+
+	fn get_margin(&self) -> MarginAccount {
+		self.margin.clone()
+	}
+
+	fn set_margin(&mut self, margin: MarginAccount) {
+		self.margin = margin;
+	}
+
+	// The miner's frames don't depend on its preferences
+	fn get_risk_aversion(&self) -> f64 {
+		0.0
+	}
+
 	fn num_orders(&self) -> usize {
 		self.orders.lock().unwrap().len()
 	}
@@ -329,6 +525,19 @@ impl Player for Miner {
         }
 	}
 
+	// Raises the gas of the order while it's still pending, the replacement taking its place
+	// rather than being sent as a second order
+	fn replace_order_gas(&mut self, o_id: u64, gas: f64) -> Result<(), &'static str> {
+		let mut orders = self.orders.lock().expect("couldn't acquire lock replacing order");
+		match orders.iter_mut().find(|o| o.order_id == o_id) {
+			Some(order) => {
+				order.gas = gas;
+				Ok(())
+			},
+			None => Err("ERROR: order not found to replace"),
+		}
+	}
+
 
 	// Updates the order's volume and removes it if the vol <= 0
 	fn update_order_vol(&mut self, o_id: u64, vol_to_add: f64) -> Result<(), &'static str> {
@@ -365,7 +574,7 @@ impl Player for Miner {
 				self.trader_id.clone(),
 				self.player_type.clone(),
 				self.balance,
-				self.inventory)
+				self.get_total_inv())
 	}
 }
 
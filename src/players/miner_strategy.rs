@@ -0,0 +1,264 @@
+use crate::players::TraderT;
+use crate::players::miner::Miner;
+use crate::blockchain::mem_pool::MemPool;
+use crate::blockchain::block_gas::BlockGas;
+use crate::order::order::Order;
+use crate::simulation::simulation_config::{Constants, Distributions};
+use crate::utility::trader_type;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+
+/// Which MinerStrategy the miner builds its frames with
+/// Censoring(Option<TraderT>) -> leaves every order from that type of trader, and any paying less
+/// gas than censor_gas_floor, out of its frames
+#[derive(Debug, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MinerStrategyT {
+	Honest,
+	RandomFrontRun,
+	StrategicFrontRun,
+	Censoring(Option<TraderT>),
+	Sandwich,
+}
+
+impl MinerStrategyT {
+	pub fn strategy(&self, consts: &Constants) -> Box<dyn MinerStrategy + Send + Sync> {
+		match self {
+			MinerStrategyT::Honest => Box::new(Honest {}),
+			MinerStrategyT::RandomFrontRun => Box::new(RandomFrontRun {}),
+			MinerStrategyT::StrategicFrontRun => Box::new(StrategicFrontRun {}),
+			MinerStrategyT::Censoring(censored) => Box::new(Censoring { censored: *censored, gas_floor: consts.censor_gas_floor }),
+			MinerStrategyT::Sandwich => Box::new(SandwichAttack { min_quantity: consts.sandwich_min_quantity, quantity: consts.sandwich_quantity }),
+		}
+	}
+}
+
+impl Default for MinerStrategyT {
+	fn default() -> MinerStrategyT {
+		MinerStrategyT::StrategicFrontRun
+	}
+}
+
+impl Clone for MinerStrategyT {
+	fn clone(&self) -> MinerStrategyT {
+		match self {
+			MinerStrategyT::Honest => MinerStrategyT::Honest,
+			MinerStrategyT::RandomFrontRun => MinerStrategyT::RandomFrontRun,
+			MinerStrategyT::StrategicFrontRun => MinerStrategyT::StrategicFrontRun,
+			MinerStrategyT::Censoring(censored) => MinerStrategyT::Censoring(*censored),
+			MinerStrategyT::Sandwich => MinerStrategyT::Sandwich,
+		}
+	}
+}
+
+
+/// When the miner uses miner_strategy and when it behaves honestly instead
+/// Alternating(honest, active) -> honest for the first honest blocks of every honest + active
+/// SwitchAt(block, to) -> miner_strategy until block, then to for the rest of the run
+/// Random(prob) -> miner_strategy with probability prob each block, honest otherwise
+#[derive(Debug, Copy, Deserialize, PartialEq)]
+pub enum MinerRegime {
+	Fixed,
+	Alternating(u64, u64),
+	SwitchAt(u64, MinerStrategyT),
+	Random(f64),
+}
+
+impl MinerRegime {
+	/// The strategy the miner builds block_num's frame with
+	pub fn behavior(&self, block_num: u64, base: MinerStrategyT) -> MinerStrategyT {
+		match self {
+			MinerRegime::Fixed => base,
+			MinerRegime::Alternating(honest, active) => match honest + active {
+				0 => base,
+				period if block_num % period < *honest => MinerStrategyT::Honest,
+				_ => base,
+			},
+			MinerRegime::SwitchAt(block, to) => if block_num < *block { base } else { *to },
+			MinerRegime::Random(prob) => match Distributions::do_with_prob(*prob) {
+				true => base,
+				false => MinerStrategyT::Honest,
+			},
+		}
+	}
+}
+
+impl Default for MinerRegime {
+	fn default() -> MinerRegime {
+		MinerRegime::Fixed
+	}
+}
+
+impl Clone for MinerRegime {
+	fn clone(&self) -> MinerRegime {
+		match self {
+			MinerRegime::Fixed => MinerRegime::Fixed,
+			MinerRegime::Alternating(honest, active) => MinerRegime::Alternating(*honest, *active),
+			MinerRegime::SwitchAt(block, to) => MinerRegime::SwitchAt(*block, *to),
+			MinerRegime::Random(prob) => MinerRegime::Random(*prob),
+		}
+	}
+}
+
+
+/// How a miner builds the frame of orders it publishes as the next block and whether it
+/// front-runs them. Halted blocks still only take cancels whatever the strategy.
+pub trait MinerStrategy {
+	/// Fills the miner's frame with orders from the pool up to the block's gas limit, by default
+	/// the ones paying the most gas. Returns the orders it deliberately left out.
+	fn make_frame(&self, miner: &mut Miner, pool: &Arc<MemPool>, gas: &BlockGas) -> Vec<Order> {
+		miner.make_frame(Arc::clone(pool), gas);
+		Vec::new()
+	}
+
+	/// Inserts the miner's own orders into its frame ahead of the players', for the blocks
+	/// front_run_perc picks. Miners that don't front-run return an Err.
+	fn front_run(&self, _miner: &mut Miner, _best_bid_price: f64, _best_ask_price: f64) -> Result<Vec<Order>, &'static str> {
+		Err("The miner doesn't front-run")
+	}
+}
+
+/// Takes the highest gas orders and never front-runs
+pub struct Honest {}
+
+impl MinerStrategy for Honest {}
+
+/// Copies a random order from the frame
+pub struct RandomFrontRun {}
+
+impl MinerStrategy for RandomFrontRun {
+	fn front_run(&self, miner: &mut Miner, _best_bid_price: f64, _best_ask_price: f64) -> Result<Vec<Order>, &'static str> {
+		miner.random_front_run().map(|order| vec![order])
+	}
+}
+
+/// Copies the bid or ask in the frame that most improves on the book
+pub struct StrategicFrontRun {}
+
+impl MinerStrategy for StrategicFrontRun {
+	fn front_run(&self, miner: &mut Miner, best_bid_price: f64, best_ask_price: f64) -> Result<Vec<Order>, &'static str> {
+		miner.strategic_front_run(best_bid_price, best_ask_price).map(|order| vec![order])
+	}
+}
+
+/// Sandwiches the largest marketable order of at least min_quantity in the frame with quantity
+/// shares of its own
+pub struct SandwichAttack {
+	pub min_quantity: f64,
+	pub quantity: f64,
+}
+
+impl MinerStrategy for SandwichAttack {
+	fn front_run(&self, miner: &mut Miner, best_bid_price: f64, best_ask_price: f64) -> Result<Vec<Order>, &'static str> {
+		miner.sandwich(best_bid_price, best_ask_price, self.min_quantity, self.quantity).map(|s| vec![s.front, s.back])
+	}
+}
+
+/// Takes the highest gas orders except those from the censored type of trader or paying less
+/// gas than gas_floor, which are left waiting in the pool however much room the frame has.
+/// It doesn't front-run.
+pub struct Censoring {
+	pub censored: Option<TraderT>,
+	pub gas_floor: f64,
+}
+
+impl Censoring {
+	pub fn is_censored(&self, order: &Order) -> bool {
+		let censored_trader = self.censored.is_some() && trader_type(&order.trader_id) == self.censored;
+		censored_trader || order.gas < self.gas_floor
+	}
+}
+
+impl MinerStrategy for Censoring {
+	fn make_frame(&self, miner: &mut Miner, pool: &Arc<MemPool>, gas: &BlockGas) -> Vec<Order> {
+		let mut frame = Vec::new();
+		let mut censored = Vec::new();
+		let mut used = 0.0;
+		// Traders whose later orders must wait behind one left in the pool
+		let mut blocked = HashSet::<String>::new();
+		let delay = pool.gossip_delay(&miner.trader_id);
+		for order in pool.pop_all() {
+			let waits = (order.nonce > 0 && blocked.contains(&order.trader_id)) || !pool.has_seen(&order, delay);
+			if !waits && !self.is_censored(&order) && gas.fits(used, &order) {
+				used += gas.used(&order);
+				frame.push(order);
+				continue;
+			}
+			if !waits && self.is_censored(&order) {
+				censored.push(order.clone());
+			}
+			if order.nonce > 0 {
+				blocked.insert(order.trader_id.clone());
+			}
+			pool.add(order);
+		}
+		miner.frame = frame;
+		censored
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_censoring() {
+		let consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		let order = |id: &str, gas: f64| Order::new(id.to_string(), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+			100.0, 100.0, 100.0, 1.0, 1.0, gas);
+		let pool = Arc::new(MemPool::new());
+		pool.add(order("INVa", 1.0));
+		pool.add(order("MKRa", 5.0));
+		pool.add(order("INVb", 3.0));
+		pool.add(order("INVc", 2.0));
+
+		// The maker's order stays in the pool however much gas it pays, the frame fills by gas
+		let mut miner = Miner::new(format!("MINa"));
+		let censored = MinerStrategyT::Censoring(Some(TraderT::Maker)).strategy(&consts).make_frame(&mut miner, &pool, &BlockGas::orders(2));
+		let framed: Vec<String> = miner.frame.iter().map(|o| o.trader_id.clone()).collect();
+		assert_eq!(framed, vec![format!("INVb"), format!("INVc")]);
+		assert_eq!(censored.len(), 1);
+		assert_eq!(censored[0].trader_id, "MKRa");
+		assert_eq!(pool.length(), 2);
+		assert!(MinerStrategyT::Censoring(Some(TraderT::Maker)).strategy(&consts).front_run(&mut miner, 99.0, 101.0).is_err());
+
+		// An honest miner takes the highest gas orders and leaves the frame as it is
+		MinerStrategyT::Honest.strategy(&consts).make_frame(&mut miner, &pool, &BlockGas::orders(2));
+		assert_eq!(miner.frame[0].trader_id, "MKRa");
+		assert!(MinerStrategyT::Honest.strategy(&consts).front_run(&mut miner, 99.0, 101.0).is_err());
+		assert_eq!(miner.frame.len(), 2);
+
+		// Below the gas floor nobody gets in, even with room to spare
+		let floor = Censoring { censored: None, gas_floor: 2.5 };
+		for order in miner.frame.drain(..) {
+			pool.add(order);
+		}
+		let censored = floor.make_frame(&mut miner, &pool, &BlockGas::orders(10));
+		assert_eq!(miner.frame.len(), 1);
+		assert_eq!(miner.frame[0].trader_id, "MKRa");
+		assert_eq!(censored[0].trader_id, "INVa");
+		assert_eq!(pool.length(), 1);
+	}
+
+	#[test]
+	fn test_miner_regime() {
+		let base = MinerStrategyT::Sandwich;
+		assert_eq!(MinerRegime::Fixed.behavior(7, base), base);
+
+		let alternating = MinerRegime::Alternating(2, 1);
+		let behaviors: Vec<MinerStrategyT> = (0..6).map(|b| alternating.behavior(b, base)).collect();
+		assert_eq!(behaviors, vec![MinerStrategyT::Honest, MinerStrategyT::Honest, base,
+			MinerStrategyT::Honest, MinerStrategyT::Honest, base]);
+
+		let switch = MinerRegime::SwitchAt(10, MinerStrategyT::Censoring(None));
+		assert_eq!(switch.behavior(9, base), base);
+		assert_eq!(switch.behavior(10, base), MinerStrategyT::Censoring(None));
+
+		assert_eq!(MinerRegime::Random(1.0).behavior(3, base), base);
+		assert_eq!(MinerRegime::Random(0.0).behavior(3, base), MinerStrategyT::Honest);
+	}
+}
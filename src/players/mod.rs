@@ -1,19 +1,56 @@
 use crate::simulation::simulation_history::UpdateReason;
 use crate::order::order::{Order, OrderType};
+use crate::exchange::exchange_logic::OrderEvent;
+use crate::simulation::simulation_config::{Distributions, DistReason};
 use std::any::Any;
+use std::collections::HashMap;
 
 
 pub mod investor;
 pub mod maker;
 pub mod miner;
+pub mod miner_strategy;
+pub mod strategy;
+pub mod momentum;
+pub mod mean_reversion;
+pub mod arbitrageur;
+pub mod informed;
+pub mod noise;
+pub mod spoofer;
+pub mod sniper;
+pub mod bridge;
+pub mod script;
+pub mod plugin;
+pub mod inventory_skew;
+pub mod adverse_selection;
+pub mod adaptive;
+pub mod twap;
+pub mod vwap;
+pub mod searcher;
+pub mod zero_intel;
+
+use crate::players::strategy::Strategy;
 
 
 /// Enum for matching over trader types
-#[derive(Debug, PartialEq, Copy)]
+#[derive(Debug, PartialEq, Copy, Serialize, Deserialize)]
 pub enum TraderT {
     Maker,
     Investor,
     Miner,
+    Arbitrageur,
+    ZeroIntel,
+    Momentum,
+    MeanReversion,
+    Informed,
+    Noise,
+    Spoofer,
+    Sniper,
+    Learner,
+    Bridge,
+    Twap,
+    Vwap,
+    Searcher,
 }
 
 impl Clone for TraderT {
@@ -22,23 +59,101 @@ impl Clone for TraderT {
 			TraderT::Maker => TraderT::Maker,
 			TraderT::Investor => TraderT::Investor,
 			TraderT::Miner => TraderT::Miner,
+			TraderT::Arbitrageur => TraderT::Arbitrageur,
+			TraderT::ZeroIntel => TraderT::ZeroIntel,
+			TraderT::Momentum => TraderT::Momentum,
+			TraderT::MeanReversion => TraderT::MeanReversion,
+			TraderT::Informed => TraderT::Informed,
+			TraderT::Noise => TraderT::Noise,
+			TraderT::Spoofer => TraderT::Spoofer,
+			TraderT::Sniper => TraderT::Sniper,
+			TraderT::Learner => TraderT::Learner,
+			TraderT::Bridge => TraderT::Bridge,
+			TraderT::Twap => TraderT::Twap,
+			TraderT::Vwap => TraderT::Vwap,
+			TraderT::Searcher => TraderT::Searcher,
 		}
 	}
 }
 
 
 
-/// A trait common to Investors, Makers, and Miners
-pub trait Player {
+/// A player's positions marked to the latest clearing prices
+/// equity: f64 -> balance plus each position valued at its symbol's mark price
+/// call_order_ids: Vec<u64> -> the orders closing the positions after the last margin call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginAccount {
+	pub equity: f64,
+	pub margin_calls: u64,
+	pub call_order_ids: Vec<u64>,
+}
+
+impl MarginAccount {
+	pub fn new() -> MarginAccount {
+		MarginAccount {
+			equity: 0.0,
+			margin_calls: 0,
+			call_order_ids: Vec::new(),
+		}
+	}
+}
+
+/// Samples a player's risk-aversion coefficient, 0 (risk neutral) when RiskAversion isn't configured
+pub fn sample_risk_aversion(dists: &Distributions) -> f64 {
+	dists.sample_if_set(DistReason::RiskAversion).map(|r| r.max(0.0)).unwrap_or(0.0)
+}
+
+/// The fraction of its usual size a player with the risk aversion trades, 1 when risk neutral
+pub fn risk_scale(risk_aversion: f64) -> f64 {
+	1.0 / (1.0 + risk_aversion.max(0.0))
+}
+
+/// A copy of a player's state that can be read without holding the ClearingHouse lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSnapshot {
+	pub trader_id: String,
+	pub player_type: TraderT,
+	pub balance: f64,
+	pub positions: HashMap<String, f64>,
+	pub orders: Vec<Order>,
+	pub margin: MarginAccount,
+	pub risk_aversion: f64,
+}
+
+impl PlayerSnapshot {
+	pub fn new(player: &dyn Player) -> PlayerSnapshot {
+		PlayerSnapshot {
+			trader_id: player.get_id(),
+			player_type: player.get_player_type(),
+			balance: player.get_bal(),
+			positions: player.get_positions(),
+			orders: player.copy_orders(),
+			margin: player.get_margin(),
+			risk_aversion: player.get_risk_aversion(),
+		}
+	}
+
+	/// The player's inventory summed over every symbol
+	pub fn total_inv(&self) -> f64 {
+		self.positions.values().sum()
+	}
+}
+
+/// The account state common to Investors, Makers, and Miners. What they trade is decided by their Strategy.
+pub trait Player: Strategy {
 	fn get_id(&self) -> String;
 
 	fn get_bal(&self) -> f64;
 
-	fn get_inv(&self) -> f64;
+	fn get_inv(&self, symbol: &str) -> f64;
+
+	fn get_total_inv(&self) -> f64;
+
+	fn get_positions(&self) -> HashMap<String, f64>;
 
 	fn update_bal(&mut self, to_add: f64);
 
-	fn update_inv(&mut self, to_add: f64);
+	fn update_inv(&mut self, symbol: &str, to_add: f64);
 
 	fn add_order(&mut self, order: Order);
 
@@ -48,6 +163,8 @@ pub trait Player {
 
 	fn cancel_order(&mut self, o_id: u64) -> Result<(), &'static str>;
 
+	fn replace_order_gas(&mut self, o_id: u64, gas: f64) -> Result<(), &'static str>;
+
 	fn get_enter_order_ids(&self) -> Vec<u64>;
 
 	fn update_order_vol(&mut self, o_id: u64, vol_to_add: f64) -> Result<(), &'static str>;
@@ -60,6 +177,18 @@ pub trait Player {
 
 	fn add_to_sent(&self, o_id: u64, order_type: OrderType);
 
+	fn copy_sent_orders(&self) -> Vec<(u64, OrderType)>;
+
+	fn recv_order_event(&self, event: OrderEvent);
+
+	fn copy_order_events(&self) -> Vec<OrderEvent>;
+
+	fn get_margin(&self) -> MarginAccount;
+
+	fn set_margin(&mut self, margin: MarginAccount);
+
+	fn get_risk_aversion(&self) -> f64;
+
 	fn as_any(&self) -> &dyn Any;
 
 	fn log_to_csv(&self, reason: UpdateReason) -> String;
@@ -0,0 +1,98 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::Constants;
+
+
+/// The moving average crossover a momentum trader follows. It buys when the short moving average
+/// of clearing prices is above the long one by more than the threshold, and sells when it is below.
+/// short_window, long_window: usize -> clearings averaged over
+/// threshold: f64 -> the relative gap between the averages needed to trade
+/// participation: f64 -> fraction of the average clearing volume traded when the gap is at the threshold
+#[derive(Debug, Clone, Copy)]
+pub struct MomentumTrader {
+	pub short_window: usize,
+	pub long_window: usize,
+	pub threshold: f64,
+	pub participation: f64,
+}
+
+impl MomentumTrader {
+	pub fn new(consts: &Constants) -> MomentumTrader {
+		MomentumTrader {
+			short_window: consts.momentum_short_window as usize,
+			long_window: consts.momentum_long_window as usize,
+			threshold: consts.momentum_threshold,
+			participation: consts.momentum_participation,
+		}
+	}
+
+	/// The short moving average's gap above the long one relative to the long one.
+	/// None until there are long_window clearings.
+	pub fn signal(&self, prices: &[f64]) -> Option<f64> {
+		if self.short_window == 0 || self.long_window < self.short_window || prices.len() < self.long_window {
+			return None;
+		}
+		let mean = |n: usize| prices[prices.len() - n..].iter().sum::<f64>() / n as f64;
+		let long = mean(self.long_window);
+		if long <= 0.0 {
+			return None;
+		}
+		Some((mean(self.short_window) - long) / long)
+	}
+
+	/// The side and shares to trade given each clearing's (price, volume), oldest first.
+	/// Shares scale with how far past the threshold the signal is.
+	pub fn decide(&self, clearings: &[(f64, f64)]) -> Option<(TradeType, f64)> {
+		let prices: Vec<f64> = clearings.iter().map(|(p, _v)| *p).collect();
+		let signal = self.signal(&prices)?;
+		if signal.abs() <= self.threshold {
+			return None;
+		}
+
+		let strength = match self.threshold > 0.0 {
+			true => signal.abs() / self.threshold,
+			false => 1.0,
+		};
+		let avg_volume = clearings[clearings.len() - self.long_window..].iter().map(|(_p, v)| *v).sum::<f64>() / self.long_window as f64;
+		let quantity = self.participation * avg_volume * strength;
+		if quantity <= 0.0 {
+			return None;
+		}
+
+		match signal > 0.0 {
+			true => Some((TradeType::Bid, quantity)),
+			false => Some((TradeType::Ask, quantity)),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_momentum_signal() {
+		let trader = MomentumTrader {
+			short_window: 2,
+			long_window: 4,
+			threshold: 0.05,
+			participation: 0.5,
+		};
+
+		// Not enough clearings to fill the long window
+		assert_eq!(trader.decide(&[(100.0, 10.0), (110.0, 10.0)]), None);
+
+		// Short average 115, long average 105
+		let rising = vec![(90.0, 10.0), (100.0, 10.0), (110.0, 10.0), (120.0, 10.0)];
+		let (trade_type, quantity) = trader.decide(&rising).unwrap();
+		assert_eq!(trade_type, TradeType::Bid);
+		let strength = (10.0 / 105.0) / 0.05;
+		assert!((quantity - 0.5 * 10.0 * strength).abs() < 1e-9);
+
+		let falling: Vec<(f64, f64)> = rising.iter().rev().cloned().collect();
+		assert_eq!(trader.decide(&falling).unwrap().0, TradeType::Ask);
+
+		// A gap within the threshold isn't traded
+		assert_eq!(trader.decide(&[(100.0, 10.0), (100.0, 10.0), (101.0, 10.0), (102.0, 10.0)]), None);
+	}
+}
@@ -0,0 +1,72 @@
+use crate::simulation::simulation_config::{Constants, Distributions, DistType};
+
+
+/// What a noise trader does next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseEvent {
+	Submit,
+	Cancel,
+}
+
+/// The noise traders' order submissions and cancellations as two independent Poisson processes.
+/// Together they are one process at the summed rate, each event a submission or cancellation
+/// in proportion to their rates.
+/// submit_rate: f64 -> orders submitted per second
+/// cancel_rate: f64 -> resting orders cancelled per second
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseTrader {
+	pub submit_rate: f64,
+	pub cancel_rate: f64,
+}
+
+impl NoiseTrader {
+	pub fn new(consts: &Constants) -> NoiseTrader {
+		NoiseTrader {
+			submit_rate: consts.noise_submit_rate,
+			cancel_rate: consts.noise_cancel_rate,
+		}
+	}
+
+	/// Which event comes next, None if neither process is running
+	pub fn next_event(&self) -> Option<NoiseEvent> {
+		let rate = self.submit_rate + self.cancel_rate;
+		if rate <= 0.0 {
+			return None;
+		}
+		match Distributions::do_with_prob(self.submit_rate / rate) {
+			true => Some(NoiseEvent::Submit),
+			false => Some(NoiseEvent::Cancel),
+		}
+	}
+
+	/// Millis until the next event, exponentially distributed at the summed rate
+	pub fn wait_millis(&self) -> u64 {
+		let rate = self.submit_rate + self.cancel_rate;
+		if rate <= 0.0 {
+			return 0;
+		}
+		(1000.0 * Distributions::sample(rate, rate, 1.0, DistType::Exponential)) as u64
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_noise_events() {
+		let submit_only = NoiseTrader { submit_rate: 5.0, cancel_rate: 0.0 };
+		let cancel_only = NoiseTrader { submit_rate: 0.0, cancel_rate: 5.0 };
+		for _ in 0..20 {
+			assert_eq!(submit_only.next_event(), Some(NoiseEvent::Submit));
+			assert_eq!(cancel_only.next_event(), Some(NoiseEvent::Cancel));
+		}
+		assert_eq!(NoiseTrader { submit_rate: 0.0, cancel_rate: 0.0 }.next_event(), None);
+
+		// The mean wait at 10 events per second is 100ms
+		let trader = NoiseTrader { submit_rate: 5.0, cancel_rate: 5.0 };
+		let mean = (0..2000).map(|_| trader.wait_millis() as f64).sum::<f64>() / 2000.0;
+		assert!(mean > 80.0 && mean < 120.0);
+	}
+}
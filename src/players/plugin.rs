@@ -0,0 +1,181 @@
+use crate::players::bridge::BridgeOrder;
+use crate::players::script::{OrderScript, ScriptInput};
+
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use std::fs;
+use std::io;
+use std::sync::Mutex;
+
+
+// How much fuel, about one unit per instruction, a plugin may burn each time it's called
+const PLUGIN_FUEL: u64 = 10_000_000;
+
+// The most memory a plugin may grow to, in bytes
+const PLUGIN_MEMORY: usize = 64 << 20;
+
+/// A strategy compiled to WebAssembly and run sandboxed by wasmtime, so strategies written in
+/// any language that targets WASM can be swapped per experiment without rebuilding the crate.
+/// The module must export:
+/// - memory: its linear memory
+/// - alloc(len: i32) -> i32: reserves len bytes for the input, returning where they start
+/// - orders(ptr: i32, len: i32) -> i64: reads the ScriptInput as JSON at ptr and returns where its
+///   JSON list of orders is, the pointer in the high 32 bits and the length in the low 32 bits
+/// The module is given no imports, so it can't reach the host's files, network or clock. Each call
+/// may burn PLUGIN_FUEL and the memory can't grow past PLUGIN_MEMORY. A plugin that traps, runs out
+/// of fuel or returns malformed orders trades nothing that time.
+pub struct WasmPlugin {
+	pub path: String,
+	guest: Mutex<Guest>,
+}
+
+// The plugin's instance with the exports the host calls
+struct Guest {
+	store: Store<StoreLimits>,
+	memory: Memory,
+	alloc: TypedFunc<i32, i32>,
+	orders: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmPlugin {
+	/// Loads the compiled module at path
+	pub fn new(path: &str) -> io::Result<WasmPlugin> {
+		WasmPlugin::from_bytes(path, &fs::read(path)?)
+	}
+
+	/// Compiles and instantiates the module, in binary or text format, naming it path in errors
+	pub fn from_bytes(path: &str, bytes: &[u8]) -> io::Result<WasmPlugin> {
+		let guest = Guest::new(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e)))?;
+		Ok(WasmPlugin {
+			path: path.to_string(),
+			guest: Mutex::new(guest),
+		})
+	}
+}
+
+impl Guest {
+	fn new(bytes: &[u8]) -> wasmtime::Result<Guest> {
+		let mut config = Config::new();
+		config.consume_fuel(true);
+		let engine = Engine::new(&config)?;
+		let module = Module::new(&engine, bytes)?;
+		let mut store = Store::new(&engine, StoreLimitsBuilder::new().memory_size(PLUGIN_MEMORY).build());
+		store.limiter(|limits| limits);
+		let instance = Instance::new(&mut store, &module, &[])?;
+		let memory = instance.get_memory(&mut store, "memory")
+			.ok_or_else(|| wasmtime::Error::msg("the module doesn't export its memory"))?;
+		let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+		let orders = instance.get_typed_func::<(i32, i32), i64>(&mut store, "orders")?;
+		Ok(Guest { store, memory, alloc, orders })
+	}
+
+	// Passes the input to the plugin's orders export and copies out what it returns
+	fn call(&mut self, input: &[u8]) -> wasmtime::Result<Vec<u8>> {
+		self.store.set_fuel(PLUGIN_FUEL)?;
+		let len = input.len() as i32;
+		let ptr = self.alloc.call(&mut self.store, len)?;
+		self.memory.write(&mut self.store, ptr as u32 as usize, input)?;
+		let packed = self.orders.call(&mut self.store, (ptr, len))? as u64;
+		let mut output = vec![0; (packed & 0xffff_ffff) as usize];
+		self.memory.read(&self.store, (packed >> 32) as usize, &mut output)?;
+		Ok(output)
+	}
+}
+
+impl OrderScript for WasmPlugin {
+	fn generate_orders(&self, input: &ScriptInput) -> Option<Vec<BridgeOrder>> {
+		let input = serde_json::to_vec(input).ok()?;
+		let output = match self.guest.lock().expect("WasmPlugin generate_orders").call(&input) {
+			Ok(output) => output,
+			Err(e) => {
+				println!("Plugin {} failed: {}", self.path, e);
+				return None;
+			},
+		};
+		match serde_json::from_slice(&output) {
+			Ok(orders) => Some(orders),
+			Err(e) => {
+				println!("Plugin {} returned malformed orders: {}", self.path, e);
+				None
+			},
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::TradeType;
+
+	const ORDERS: &str = r#"[{"trade_type":"Bid","price":99.0,"quantity":1.0}]"#;
+
+	// Returns the orders kept in its data segment whatever the input
+	fn quote_module() -> String {
+		format!(r#"(module
+			(memory (export "memory") 1)
+			(data (i32.const 0) "{}")
+			(func (export "alloc") (param i32) (result i32) (i32.const 1024))
+			(func (export "orders") (param i32 i32) (result i64) (i64.const {})))"#,
+			ORDERS.replace('"', "\\\""), ORDERS.len())
+	}
+
+	fn input() -> ScriptInput {
+		serde_json::from_str(r#"{"block_num":3,"clearing_price":null,"best_bid":null,"best_ask":null,
+			"current_wtd_price":null,"mean_pool_gas":2.5,"bids_volume":0.0,"asks_volume":0.0,"mean_bids":null,"mean_asks":null,
+			"num_bids":0,"num_asks":0,"weighted_price":null}"#).unwrap()
+	}
+
+	#[test]
+	fn test_wasm_plugin() {
+		let plugin = WasmPlugin::from_bytes("quote.wat", quote_module().as_bytes()).unwrap();
+		let orders = plugin.generate_orders(&input()).unwrap();
+		assert_eq!(orders, vec![BridgeOrder { trade_type: TradeType::Bid, price: 99.0, quantity: 1.0 }]);
+
+		// The input is written where the plugin asks, here echoed back as its output
+		let echo = r#"(module
+			(memory (export "memory") 1)
+			(func (export "alloc") (param i32) (result i32) (i32.const 64))
+			(func (export "orders") (param i32 i32) (result i64)
+				(i64.or (i64.shl (i64.extend_i32_u (local.get 0)) (i64.const 32)) (i64.extend_i32_u (local.get 1)))))"#;
+		let mut guest = Guest::new(echo.as_bytes()).unwrap();
+		assert_eq!(guest.call(b"{\"block_num\":3}").unwrap(), b"{\"block_num\":3}".to_vec());
+
+		// Echoing the input back isn't a list of orders, so it trades nothing
+		let plugin = WasmPlugin::from_bytes("echo.wat", echo.as_bytes()).unwrap();
+		assert_eq!(plugin.generate_orders(&input()), None);
+	}
+
+	#[test]
+	fn test_wasm_plugin_sandbox() {
+		// A plugin that never returns runs out of fuel, and is refueled for the next call
+		let spin = r#"(module
+			(memory (export "memory") 1)
+			(global $calls (mut i32) (i32.const 0))
+			(func (export "alloc") (param i32) (result i32) (i32.const 1024))
+			(func (export "orders") (param i32 i32) (result i64)
+				(global.set $calls (i32.add (global.get $calls) (i32.const 1)))
+				(if (i32.eq (global.get $calls) (i32.const 1)) (then (loop $spin (br $spin))))
+				(i64.const 2))
+			(data (i32.const 0) "[]"))"#;
+		let plugin = WasmPlugin::from_bytes("spin.wat", spin.as_bytes()).unwrap();
+		assert_eq!(plugin.generate_orders(&input()), None);
+		assert_eq!(plugin.generate_orders(&input()), Some(Vec::new()));
+
+		// A plugin can't grow its memory past the limit, trading nothing if it does
+		let grow = format!(r#"(module
+			(memory (export "memory") 1)
+			(func (export "alloc") (param i32) (result i32) (i32.const 1024))
+			(func (export "orders") (param i32 i32) (result i64)
+				(if (i32.ne (memory.grow (i32.const {})) (i32.const -1)) (then unreachable))
+				(i64.const 2))
+			(data (i32.const 0) "[]"))"#, PLUGIN_MEMORY / 65536);
+		let plugin = WasmPlugin::from_bytes("grow.wat", grow.as_bytes()).unwrap();
+		assert_eq!(plugin.generate_orders(&input()), Some(Vec::new()));
+
+		// A plugin that imports anything from the host isn't loaded, nor one missing an export
+		assert!(WasmPlugin::from_bytes("import.wat", br#"(module (import "env" "read_file" (func)))"#).is_err());
+		assert!(WasmPlugin::from_bytes("bare.wat", br#"(module (memory (export "memory") 1))"#).is_err());
+		assert!(WasmPlugin::new("no-such-strategy.wasm").is_err());
+	}
+}
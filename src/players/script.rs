@@ -0,0 +1,205 @@
+use crate::players::strategy::MarketData;
+use crate::players::bridge::BridgeOrder;
+use crate::players::TraderT;
+use crate::order::order::{Order, OrderType};
+use crate::simulation::simulation::Simulation;
+
+use rhai::{Dynamic, Engine, Scope, AST};
+
+use std::fs;
+use std::io;
+
+
+// How many operations a script may run each time it's called before it's stopped, so a
+// runaway script can't hold up the players that trade from it
+const MAX_SCRIPT_OPERATIONS: u64 = 1_000_000;
+
+/// One row of the scripts csv. num_players players of player_type are registered whose orders
+/// come from the rhai script at path, or the WASM plugin if path ends in .wasm or .wat. Makers
+/// and Investors can be scripted.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ScriptSpec {
+	pub player_type: TraderT,
+	pub num_players: u64,
+	pub path: String,
+}
+
+/// The data a script receives each time its players trade, flattened from the PriorData and
+/// LikelihoodStats so the script can read it as a map
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScriptInput {
+	pub block_num: u64,
+	pub clearing_price: Option<f64>,
+	pub best_bid: Option<f64>,
+	pub best_ask: Option<f64>,
+	pub current_wtd_price: Option<f64>,
+	pub mean_pool_gas: f64,
+	pub bids_volume: f64,
+	pub asks_volume: f64,
+	pub mean_bids: Option<f64>,
+	pub mean_asks: Option<f64>,
+	pub num_bids: u64,
+	pub num_asks: u64,
+	pub weighted_price: Option<f64>,
+}
+
+impl ScriptInput {
+	pub fn new(data: &MarketData) -> ScriptInput {
+		ScriptInput {
+			block_num: data.block_num,
+			clearing_price: data.prior.clearing_price,
+			best_bid: data.prior.best_bid.as_ref().map(|o| o.price),
+			best_ask: data.prior.best_ask.as_ref().map(|o| o.price),
+			current_wtd_price: data.prior.current_wtd_price,
+			mean_pool_gas: data.prior.mean_pool_gas,
+			bids_volume: data.prior.bids_volume,
+			asks_volume: data.prior.asks_volume,
+			mean_bids: data.inference.mean_bids,
+			mean_asks: data.inference.mean_asks,
+			num_bids: data.inference.num_bids,
+			num_asks: data.inference.num_asks,
+			weighted_price: data.inference.weighted_price,
+		}
+	}
+}
+
+/// A strategy written outside the crate. It receives a ScriptInput and returns orders in the
+/// same form the bridge player accepts. None means the script failed, which trades nothing.
+pub trait OrderScript: Send + Sync {
+	fn generate_orders(&self, input: &ScriptInput) -> Option<Vec<BridgeOrder>>;
+}
+
+/// Asks the script for trader_id's orders, dropping any without a positive price and quantity
+pub fn script_orders(trader_id: &str, script: &dyn OrderScript, data: &MarketData) -> Vec<Order> {
+	let orders = match script.generate_orders(&ScriptInput::new(data)) {
+		Some(orders) => orders,
+		None => return Vec::new(),
+	};
+	orders.into_iter()
+		.filter(|o| o.price > 0.0 && o.quantity > 0.0)
+		.map(|o| {
+			let (ex_type, p_l, p_h) = Simulation::order_prices(data.consts.market_type, &o.trade_type, o.price, data.consts);
+			let gas = data.prior.mean_pool_gas;
+			Order::new(trader_id.to_string(), OrderType::Enter, o.trade_type, ex_type, p_l, p_h, o.price, o.quantity, o.quantity, gas)
+		})
+		.collect()
+}
+
+/// A strategy script run by the embedded rhai engine, so strategies can be written without
+/// rebuilding the crate. The script defines fn orders(data), called each time its players trade
+/// with the ScriptInput as a map, e.g. data.weighted_price, which returns an array of maps like
+/// #{trade_type: "Bid", price: 99.5, quantity: 1.0}. A script that errors or runs past
+/// MAX_SCRIPT_OPERATIONS trades nothing that time.
+pub struct RhaiScript {
+	pub path: String,
+	engine: Engine,
+	ast: AST,
+}
+
+impl RhaiScript {
+	/// Compiles the script at path
+	pub fn new(path: &str) -> io::Result<RhaiScript> {
+		RhaiScript::from_source(path, &fs::read_to_string(path)?)
+	}
+
+	/// Compiles the source, naming it path in errors
+	pub fn from_source(path: &str, source: &str) -> io::Result<RhaiScript> {
+		let mut engine = Engine::new();
+		engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+		let ast = engine.compile(source)
+			.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, e)))?;
+		Ok(RhaiScript {
+			path: path.to_string(),
+			engine,
+			ast,
+		})
+	}
+}
+
+impl OrderScript for RhaiScript {
+	fn generate_orders(&self, input: &ScriptInput) -> Option<Vec<BridgeOrder>> {
+		let data = rhai::serde::to_dynamic(input).ok()?;
+		let orders = self.engine.call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "orders", (data,))
+			.map_err(|e| println!("Script {} failed: {}", self.path, e))
+			.ok()?;
+		match rhai::serde::from_dynamic(&orders) {
+			Ok(orders) => Some(orders),
+			Err(e) => {
+				println!("Script {} returned malformed orders: {}", self.path, e);
+				None
+			},
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_history::History;
+	use crate::simulation::simulation_config::{Constants, Distributions, DistReason, DistType};
+	use crate::exchange::MarketType;
+	use crate::order::order::TradeType;
+	use std::sync::Arc;
+
+	// Quotes a share either side of 100 and returns garbage on block 0
+	const QUOTE_SCRIPT: &str = r#"
+		fn orders(data) {
+			if data.block_num == 0 {
+				return "not orders";
+			}
+			[
+				#{trade_type: "Bid", price: 99.0, quantity: 1.0},
+				#{trade_type: "Ask", price: 101.0, quantity: 1.0},
+				#{trade_type: "Ask", price: 0.0, quantity: 1.0},
+			]
+		}
+	"#;
+
+	#[test]
+	fn test_script_orders() {
+		let history = History::new(MarketType::CDA);
+		let (prior, inference) = history.produce_data(Arc::new(Vec::new()));
+		let dists = Distributions::new(vec![(DistReason::AsksCenter, 110.0, 20.0, 1.0, DistType::Normal)]);
+		let consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		let script = RhaiScript::from_source("quote.rhai", QUOTE_SCRIPT).unwrap();
+
+		let mut data = MarketData { block_num: 1, prior: &prior, inference: &inference, dists: &dists, consts: &consts };
+		let orders = script_orders("MKR1", &script, &data);
+		assert_eq!(orders.len(), 2);
+		assert_eq!((orders[0].trade_type.clone(), orders[0].price), (TradeType::Bid, 99.0));
+		assert_eq!((orders[1].trade_type.clone(), orders[1].price), (TradeType::Ask, 101.0));
+
+		// A script that returns garbage trades nothing
+		data.block_num = 0;
+		assert!(script_orders("MKR1", &script, &data).is_empty());
+	}
+
+	#[test]
+	fn test_rhai_script() {
+		// The script reads the decision data by field name
+		let echo = RhaiScript::from_source("echo.rhai", r#"
+			fn orders(data) {
+				if data.block_num != 3 {
+					return [];
+				}
+				[#{trade_type: "Ask", price: data.mean_pool_gas, quantity: 1.0}]
+			}
+		"#).unwrap();
+		let mut input: ScriptInput = serde_json::from_str(r#"{"block_num":3,"clearing_price":null,"best_bid":null,"best_ask":null,
+			"current_wtd_price":null,"mean_pool_gas":2.5,"bids_volume":0.0,"asks_volume":0.0,"mean_bids":null,"mean_asks":null,
+			"num_bids":0,"num_asks":0,"weighted_price":null}"#).unwrap();
+		assert_eq!(echo.generate_orders(&input), Some(vec![BridgeOrder { trade_type: TradeType::Ask, price: 2.5, quantity: 1.0 }]));
+
+		// A script that never returns is stopped, and trades nothing
+		let spin = RhaiScript::from_source("spin.rhai", "fn orders(data) { loop { } }").unwrap();
+		assert_eq!(spin.generate_orders(&input), None);
+
+		// A script without an orders function trades nothing, one that doesn't compile isn't loaded
+		let missing = RhaiScript::from_source("missing.rhai", "fn quotes(data) { [] }").unwrap();
+		input.block_num = 4;
+		assert_eq!(missing.generate_orders(&input), None);
+		assert!(RhaiScript::from_source("broken.rhai", "fn orders(data) {").is_err());
+		assert!(RhaiScript::new("no-such-strategy.rhai").is_err());
+	}
+}
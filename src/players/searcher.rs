@@ -0,0 +1,97 @@
+use crate::blockchain::bundle::Bundle;
+use crate::order::order::{Order, OrderType, TradeType};
+use crate::simulation::simulation_config::Constants;
+use crate::utility::gen_order_id;
+
+
+/// A searcher watching the mempool for large marketable orders to sandwich. Instead of racing
+/// on gas it bids bid_share of the sandwich's expected profit for the miner to mine its bundle:
+/// quantity shares on the victim's side, the victim's order, then the same shares unwound.
+#[derive(Debug, Clone)]
+pub struct Searcher {
+	pub min_quantity: f64,
+	pub quantity: f64,
+	pub bid_share: f64,
+}
+
+impl Searcher {
+	pub fn new(consts: &Constants) -> Searcher {
+		Searcher {
+			min_quantity: consts.searcher_min_quantity,
+			quantity: consts.searcher_quantity,
+			bid_share: consts.searcher_bid_share,
+		}
+	}
+
+	/// The largest pending Enter order of at least min_quantity that crosses the book
+	pub fn target(&self, pending: &[Order], best_bid_price: f64, best_ask_price: f64) -> Option<Order> {
+		pending.iter()
+			.filter(|o| o.order_type == OrderType::Enter && o.quantity >= self.min_quantity)
+			.filter(|o| match o.trade_type {
+				TradeType::Bid => o.price >= best_ask_price,
+				TradeType::Ask => o.price <= best_bid_price,
+			})
+			.max_by(|a, b| a.quantity.partial_cmp(&b.quantity).expect("Searcher target"))
+			.cloned()
+	}
+
+	/// Taking the best opposing quote ahead of the victim and unwinding at its price
+	pub fn expected_profit(&self, victim: &Order, best_bid_price: f64, best_ask_price: f64) -> f64 {
+		let edge = match victim.trade_type {
+			TradeType::Bid => victim.price - best_ask_price,
+			TradeType::Ask => best_bid_price - victim.price,
+		};
+		self.quantity * edge.max(0.0)
+	}
+
+	/// The searcher's bundle around the victim, its own legs at the victim's price without gas
+	pub fn bundle(&self, searcher_id: &str, victim: &Order, best_bid_price: f64, best_ask_price: f64) -> Bundle {
+		let mut front = victim.clone();
+		front.trader_id = searcher_id.to_string();
+		front.order_id = gen_order_id();
+		front.client_order_id = None;
+		front.gas = 0.0;
+		front.quantity = self.quantity;
+		front.u_max = self.quantity;
+		let mut back = front.clone();
+		back.order_id = gen_order_id();
+		back.schedule = None;
+		back.trade_type = match victim.trade_type {
+			TradeType::Bid => TradeType::Ask,
+			TradeType::Ask => TradeType::Bid,
+		};
+		Bundle {
+			searcher_id: searcher_id.to_string(),
+			orders: vec![front, victim.clone(), back],
+			payment: self.bid_share * self.expected_profit(victim, best_bid_price, best_ask_price),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::ExchangeType;
+
+	#[test]
+	fn test_searcher() {
+		let searcher = Searcher { min_quantity: 10.0, quantity: 4.0, bid_share: 0.5 };
+		let order = |tt: TradeType, price: f64, quantity: f64| Order::new(format!("INVa"), OrderType::Enter, tt, ExchangeType::LimitOrder,
+			price, price, price, quantity, quantity, 1.0);
+		let pending = vec![order(TradeType::Bid, 103.0, 5.0), order(TradeType::Bid, 100.5, 50.0),
+			order(TradeType::Bid, 102.0, 20.0), order(TradeType::Ask, 99.0, 15.0)];
+
+		// The small bid and the bid below the best ask are passed over for the larger of the rest
+		let victim = searcher.target(&pending, 100.0, 101.0).unwrap();
+		assert_eq!(victim.order_id, pending[2].order_id);
+		assert!(searcher.target(&pending[..2], 100.0, 101.0).is_none());
+
+		let bundle = searcher.bundle("SRCa", &victim, 100.0, 101.0);
+		assert_eq!(bundle.payment, 2.0);
+		assert_eq!(bundle.pending_ids(), vec![victim.order_id]);
+		assert_eq!(bundle.orders[0].trade_type, TradeType::Bid);
+		assert_eq!(bundle.orders[2].trade_type, TradeType::Ask);
+		assert_eq!(bundle.orders[2].quantity, 4.0);
+	}
+}
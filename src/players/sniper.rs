@@ -0,0 +1,114 @@
+use crate::order::order::{Order, OrderType, TradeType};
+use crate::simulation::simulation_config::Constants;
+
+
+/// How a sniper bids gas to get its order into the frame ahead of the cancel it races.
+/// Miners sort the frame by gas, so the order executes first if it pays more than the cancel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasPolicy {
+	/// Always pays this gas, only racing cancels that pay less
+	Fixed(f64),
+	/// Pays increment more than the cancel, up to max
+	Outbid { increment: f64, max: f64 },
+}
+
+impl GasPolicy {
+	/// Outbids by sniper_gas_increment if set, else always pays sniper_max_gas
+	pub fn new(consts: &Constants) -> GasPolicy {
+		match consts.sniper_gas_increment > 0.0 {
+			true => GasPolicy::Outbid { increment: consts.sniper_gas_increment, max: consts.sniper_max_gas },
+			false => GasPolicy::Fixed(consts.sniper_max_gas),
+		}
+	}
+
+	/// The gas to pay to beat competing_gas, None if the policy can't beat it
+	pub fn bid(&self, competing_gas: f64) -> Option<f64> {
+		let gas = match *self {
+			GasPolicy::Fixed(gas) => gas,
+			GasPolicy::Outbid { increment, max } => (competing_gas + increment).min(max),
+		};
+		match gas > competing_gas {
+			true => Some(gas),
+			false => None,
+		}
+	}
+}
+
+/// A stale quote being cancelled in the mempool and the order that trades against it
+/// target_id: u64 -> the resting order being cancelled
+/// trade_type: TradeType -> the sniper's side, opposite the resting order
+/// competing_gas: f64 -> the gas the cancel pays
+/// edge: f64 -> the profit against the fair price before gas
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snipe {
+	pub target_id: u64,
+	pub trade_type: TradeType,
+	pub price: f64,
+	pub quantity: f64,
+	pub competing_gas: f64,
+	pub edge: f64,
+}
+
+impl Snipe {
+	/// The cancels in the pool from traders is_target accepts whose resting order is mispriced
+	/// against fair, most profitable first
+	pub fn find<F>(pool: &[Order], fair: f64, is_target: F) -> Vec<Snipe>
+	where F: Fn(&str) -> bool
+	{
+		let mut snipes: Vec<Snipe> = pool.iter()
+			.filter(|o| o.order_type == OrderType::Cancel && is_target(&o.trader_id))
+			.filter_map(|o| {
+				// A resting bid above fair is sold into, a resting ask below fair is bought
+				let (trade_type, edge) = match o.trade_type {
+					TradeType::Bid => (TradeType::Ask, o.price - fair),
+					TradeType::Ask => (TradeType::Bid, fair - o.price),
+				};
+				if edge <= 0.0 || o.quantity <= 0.0 {
+					return None;
+				}
+				Some(Snipe {
+					target_id: o.order_id,
+					trade_type,
+					price: o.price,
+					quantity: o.quantity,
+					competing_gas: o.gas,
+					edge: edge * o.quantity,
+				})
+			})
+			.collect();
+		snipes.sort_by(|a, b| b.edge.partial_cmp(&a.edge).expect("Snipe::find"));
+		snipes
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::order::order::ExchangeType;
+
+	#[test]
+	fn test_snipes() {
+		let cancel = |trader: &str, trade_type: TradeType, price: f64, gas: f64| {
+			let mut o = Order::new(trader.to_string(), OrderType::Cancel, trade_type, ExchangeType::LimitOrder,
+				price, price, price, 2.0, 2.0, gas);
+			o.order_id = price as u64;
+			o
+		};
+		let pool = vec![
+			cancel("MKR1", TradeType::Bid, 103.0, 1.0),
+			cancel("MKR1", TradeType::Ask, 95.0, 1.0),
+			cancel("MKR1", TradeType::Ask, 105.0, 1.0),
+			cancel("INV1", TradeType::Bid, 110.0, 1.0),
+		];
+		let snipes = Snipe::find(&pool, 100.0, |id| id.starts_with("MKR"));
+		assert_eq!(snipes.len(), 2);
+		assert_eq!((snipes[0].target_id, snipes[0].trade_type.clone(), snipes[0].edge), (95, TradeType::Bid, 10.0));
+		assert_eq!((snipes[1].target_id, snipes[1].trade_type.clone(), snipes[1].edge), (103, TradeType::Ask, 6.0));
+
+		assert_eq!(GasPolicy::Fixed(2.0).bid(1.0), Some(2.0));
+		assert_eq!(GasPolicy::Fixed(2.0).bid(3.0), None);
+		assert_eq!(GasPolicy::Outbid { increment: 0.5, max: 2.0 }.bid(1.0), Some(1.5));
+		assert_eq!(GasPolicy::Outbid { increment: 0.5, max: 2.0 }.bid(2.0), None);
+	}
+}
@@ -0,0 +1,97 @@
+use crate::order::order::{Order, TradeType};
+use crate::simulation::simulation_config::Constants;
+
+
+/// The steps of a spoofing cycle recorded to the History
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum SpoofAction {
+	Place,
+	Trade,
+	Cancel,
+}
+
+/// A spoofer's order and the market around it when it was sent, for detection studies
+/// touch: Option<f64> -> best price on the spoof order's side when the order was sent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoofEvent {
+	pub block_num: u64,
+	pub trader_id: String,
+	pub action: SpoofAction,
+	pub order_id: u64,
+	pub trade_type: TradeType,
+	pub price: f64,
+	pub quantity: f64,
+	pub touch: Option<f64>,
+}
+
+impl SpoofEvent {
+	pub fn new(block_num: u64, action: SpoofAction, order: &Order, touch: Option<f64>) -> SpoofEvent {
+		SpoofEvent {
+			block_num,
+			trader_id: order.trader_id.clone(),
+			action,
+			order_id: order.order_id,
+			trade_type: order.trade_type.clone(),
+			price: order.price,
+			quantity: order.quantity,
+			touch,
+		}
+	}
+}
+
+/// How a spoofer fakes depth. A large order is placed offset away from the touch, then in the
+/// next run the spoofer trades the opposite side and cancels the large order before it executes.
+/// quantity: f64 -> shares in the fake order
+/// offset: f64 -> fraction of the touch the fake order is placed away from it
+/// trade_quantity: f64 -> shares the spoofer actually trades
+#[derive(Debug, Clone, Copy)]
+pub struct Spoofer {
+	pub quantity: f64,
+	pub offset: f64,
+	pub trade_quantity: f64,
+}
+
+impl Spoofer {
+	pub fn new(consts: &Constants) -> Spoofer {
+		Spoofer {
+			quantity: consts.spoof_quantity,
+			offset: consts.spoof_offset,
+			trade_quantity: consts.spoof_trade_quantity,
+		}
+	}
+
+	/// Where the fake order rests: below the best bid or above the best ask
+	pub fn spoof_price(&self, trade_type: &TradeType, touch: f64) -> f64 {
+		match trade_type {
+			TradeType::Bid => touch * (1.0 - self.offset),
+			TradeType::Ask => touch * (1.0 + self.offset),
+		}
+	}
+
+	/// The side the spoofer really trades, opposite the fake order
+	pub fn trade_side(spoof_side: &TradeType) -> TradeType {
+		match spoof_side {
+			TradeType::Bid => TradeType::Ask,
+			TradeType::Ask => TradeType::Bid,
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_spoof_price() {
+		let spoofer = Spoofer {
+			quantity: 50.0,
+			offset: 0.02,
+			trade_quantity: 1.0,
+		};
+		assert_eq!(spoofer.spoof_price(&TradeType::Bid, 100.0), 98.0);
+		assert_eq!(spoofer.spoof_price(&TradeType::Ask, 100.0), 102.0);
+		assert_eq!(Spoofer::trade_side(&TradeType::Bid), TradeType::Ask);
+		assert_eq!(Spoofer::trade_side(&TradeType::Ask), TradeType::Bid);
+	}
+}
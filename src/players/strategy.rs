@@ -0,0 +1,64 @@
+use crate::simulation::simulation_config::{Distributions, Constants};
+use crate::simulation::simulation_history::{PriorData, LikelihoodStats};
+use crate::order::order::Order;
+use crate::players::maker::{MakerT, MakerParams};
+use crate::players::script::OrderScript;
+
+use std::sync::Arc;
+
+/// The data a strategy decides its orders from each time it trades
+pub struct MarketData<'a> {
+	pub block_num: u64,
+	pub prior: &'a PriorData,
+	pub inference: &'a LikelihoodStats,
+	pub dists: &'a Distributions,
+	pub consts: &'a Constants,
+}
+
+/// How a player decides what to trade, separate from the account state the ClearingHouse keeps.
+/// The ClearingHouse only calls these hooks so new agent types don't need changes to it.
+/// Players that are driven by their own tasks keep the defaults and trade nothing here.
+pub trait Strategy {
+	/// Called once at the start of every block
+	fn on_block(&self, _block_num: u64) {}
+
+	/// Called with the latest data before the strategy is asked for orders
+	fn on_data(&self, _data: &MarketData) {}
+
+	/// The new orders the strategy wants to send
+	fn generate_orders(&self, _data: &MarketData) -> Vec<Order> {
+		Vec::new()
+	}
+
+	/// The script that decides the strategy's orders in place of generate_orders, run by the
+	/// ClearingHouse without holding the players lock
+	fn script(&self) -> Option<Arc<dyn OrderScript>> {
+		None
+	}
+
+	/// Whether the inferred price has moved far enough for the strategy to replace its resting orders
+	fn should_requote(&self, _price: f64) -> bool {
+		true
+	}
+
+	/// The resting orders acked at least stale_blocks before block_num that haven't filled, judged
+	/// by comparing their quantity to sent_quantity, the quantity each was sent with
+	fn stale_orders(&self, _block_num: u64, _stale_blocks: u64, _sent_quantity: &dyn Fn(u64) -> Option<f64>) -> Vec<Order> {
+		Vec::new()
+	}
+
+	/// The maker type and parameters the strategy quotes with, None for strategies that don't quote
+	fn quoting(&self) -> Option<(MakerT, MakerParams)> {
+		None
+	}
+
+	/// Replaces the quoting parameters, returning false for strategies that don't quote
+	fn set_quoting_params(&mut self, _params: MakerParams) -> bool {
+		false
+	}
+
+	/// The last client order id the strategy tagged its quotes with
+	fn last_client_id(&self) -> u64 {
+		0
+	}
+}
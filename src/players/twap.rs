@@ -0,0 +1,89 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::Constants;
+
+
+/// A TWAP execution schedule. The agent must trade parent_qty shares over horizon blocks from
+/// start_block, slicing them evenly so that by each block it has filled its share of the parent.
+/// Each block's child order is whatever is still short of that target.
+/// trade_type: TradeType -> Bid to buy the parent quantity, Ask to sell it
+/// parent_qty: f64 -> the shares to trade over the horizon
+#[derive(Debug, Clone)]
+pub struct Twap {
+	pub trade_type: TradeType,
+	pub parent_qty: f64,
+	pub start_block: u64,
+	pub horizon: u64,
+}
+
+impl Twap {
+	/// twap_quantity is signed, positive buys and negative sells
+	pub fn new(consts: &Constants) -> Twap {
+		Twap {
+			trade_type: match consts.twap_quantity < 0.0 {
+				true => TradeType::Ask,
+				false => TradeType::Bid,
+			},
+			parent_qty: consts.twap_quantity.abs(),
+			start_block: consts.twap_start_block,
+			horizon: consts.twap_horizon.max(1),
+		}
+	}
+
+	/// Whether block_num falls within the execution horizon
+	pub fn is_active(&self, block_num: u64) -> bool {
+		block_num >= self.start_block && block_num < self.start_block + self.horizon
+	}
+
+	/// The shares that should have filled by the end of block_num
+	pub fn target(&self, block_num: u64) -> f64 {
+		if block_num < self.start_block {
+			return 0.0;
+		}
+		let slices = (block_num - self.start_block + 1).min(self.horizon);
+		self.parent_qty * slices as f64 / self.horizon as f64
+	}
+
+	/// The child order for block_num given the shares already filled
+	pub fn child_qty(&self, block_num: u64, filled: f64) -> f64 {
+		(self.target(block_num) - filled).max(0.0)
+	}
+
+	/// Implementation shortfall in cash against the arrival price. Each fill (price, quantity)
+	/// is charged its distance from arrival, and the unfilled shares are charged the move from
+	/// arrival to final_price they missed. Positive is a cost to the agent.
+	pub fn shortfall(&self, arrival: f64, fills: &[(f64, f64)], final_price: f64) -> f64 {
+		let side = match self.trade_type {
+			TradeType::Bid => 1.0,
+			TradeType::Ask => -1.0,
+		};
+		let filled: f64 = fills.iter().map(|(_p, q)| q).sum();
+		let execution: f64 = fills.iter().map(|(p, q)| q * (p - arrival)).sum();
+		let opportunity = (self.parent_qty - filled).max(0.0) * (final_price - arrival);
+		side * (execution + opportunity)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_twap() {
+		let twap = Twap { trade_type: TradeType::Bid, parent_qty: 100.0, start_block: 10, horizon: 4 };
+		assert!(!twap.is_active(9));
+		assert!(twap.is_active(13));
+		assert!(!twap.is_active(14));
+
+		// Evenly sliced, and a block behind the schedule catches up
+		assert_eq!(twap.target(9), 0.0);
+		assert_eq!(twap.child_qty(10, 0.0), 25.0);
+		assert_eq!(twap.child_qty(11, 10.0), 40.0);
+		assert_eq!(twap.child_qty(20, 100.0), 0.0);
+
+		// Bought 50 a dollar above arrival and missed 50 that rose two dollars
+		assert_eq!(twap.shortfall(100.0, &[(101.0, 30.0), (101.0, 20.0)], 102.0), 150.0);
+		let sell = Twap { trade_type: TradeType::Ask, ..twap };
+		assert_eq!(sell.shortfall(100.0, &[(101.0, 50.0)], 102.0), -150.0);
+	}
+}
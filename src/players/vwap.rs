@@ -0,0 +1,120 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::Constants;
+
+
+/// A VWAP execution schedule. The agent must trade parent_qty shares over horizon blocks from
+/// start_block in proportion to the market's volume. Each block it targets the parent's share of
+/// the volume traded since the start plus a forecast of this block's, against that and the
+/// forecast for every block left. When it fell short of the last block's target, the deficit is
+/// added again scaled by catch_up so it closes the gap faster.
+/// forecast_alpha: f64 -> weight of the latest block in the exponentially weighted volume forecast
+/// catch_up: f64 -> extra fraction of the deficit sent when behind schedule
+#[derive(Debug, Clone)]
+pub struct Vwap {
+	pub trade_type: TradeType,
+	pub parent_qty: f64,
+	pub start_block: u64,
+	pub horizon: u64,
+	pub forecast_alpha: f64,
+	pub catch_up: f64,
+}
+
+impl Vwap {
+	/// vwap_quantity is signed, positive buys and negative sells
+	pub fn new(consts: &Constants) -> Vwap {
+		Vwap {
+			trade_type: match consts.vwap_quantity < 0.0 {
+				true => TradeType::Ask,
+				false => TradeType::Bid,
+			},
+			parent_qty: consts.vwap_quantity.abs(),
+			start_block: consts.vwap_start_block,
+			horizon: consts.vwap_horizon.max(1),
+			forecast_alpha: consts.vwap_forecast_alpha,
+			catch_up: consts.vwap_catch_up,
+		}
+	}
+
+	/// Whether block_num falls within the execution horizon
+	pub fn is_active(&self, block_num: u64) -> bool {
+		block_num >= self.start_block && block_num < self.start_block + self.horizon
+	}
+
+	/// The exponentially weighted average of each block's volume, oldest first
+	pub fn forecast(&self, volumes: &[f64]) -> f64 {
+		let alpha = self.forecast_alpha.max(0.0).min(1.0);
+		let mut volumes = volumes.iter();
+		let first = match volumes.next() {
+			Some(v) => *v,
+			None => return 0.0,
+		};
+		volumes.fold(first, |ewma, v| alpha * v + (1.0 - alpha) * ewma)
+	}
+
+	/// The shares that should have filled by the end of block_num given the market volume traded
+	/// since the start block and the forecast volume per block. Without any volume to go on the
+	/// schedule falls back to even slices.
+	pub fn target(&self, block_num: u64, market_volume: f64, forecast: f64) -> f64 {
+		if block_num < self.start_block {
+			return 0.0;
+		}
+		let end = self.start_block + self.horizon;
+		if block_num + 1 >= end {
+			return self.parent_qty;
+		}
+		let blocks_left = (end - block_num) as f64;
+		let expected = market_volume + forecast * blocks_left;
+		match expected > 0.0 {
+			true => self.parent_qty * (market_volume + forecast) / expected,
+			false => self.parent_qty * (block_num - self.start_block + 1) as f64 / self.horizon as f64,
+		}
+	}
+
+	/// The child order for a block given its target, the shares already filled and the previous
+	/// block's target, never more than is left of the parent
+	pub fn child_qty(&self, target: f64, filled: f64, prev_target: f64) -> f64 {
+		let behind = (prev_target - filled).max(0.0);
+		let qty = (target - filled).max(0.0) + self.catch_up * behind;
+		qty.min((self.parent_qty - filled).max(0.0))
+	}
+
+	/// The agent's tracking error against the market VWAP over the horizon in bps, positive when
+	/// it bought above or sold below the market
+	pub fn tracking_error(&self, avg_price: f64, market_vwap: f64) -> f64 {
+		let side = match self.trade_type {
+			TradeType::Bid => 1.0,
+			TradeType::Ask => -1.0,
+		};
+		side * (avg_price - market_vwap) / market_vwap * 10000.0
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_vwap() {
+		let vwap = Vwap { trade_type: TradeType::Bid, parent_qty: 100.0, start_block: 10, horizon: 5, forecast_alpha: 0.5, catch_up: 0.5 };
+		assert_eq!(vwap.forecast(&[]), 0.0);
+		assert_eq!(vwap.forecast(&[10.0, 20.0, 40.0]), 27.5);
+
+		// A flat forecast at the start is an even slice, heavy volume pulls the target forward
+		assert_eq!(vwap.target(9, 0.0, 10.0), 0.0);
+		assert_eq!(vwap.target(10, 0.0, 10.0), 20.0);
+		assert_eq!(vwap.target(11, 40.0, 10.0), 62.5);
+		assert_eq!(vwap.target(14, 0.0, 10.0), 100.0);
+		// No volume to go on falls back to even slices
+		assert_eq!(vwap.target(11, 0.0, 0.0), 40.0);
+
+		// Behind the last target the deficit is topped up, capped by what's left of the parent
+		assert_eq!(vwap.child_qty(50.0, 40.0, 40.0), 10.0);
+		assert_eq!(vwap.child_qty(50.0, 20.0, 40.0), 40.0);
+		assert_eq!(vwap.child_qty(100.0, 20.0, 90.0), 80.0);
+
+		assert_eq!(vwap.tracking_error(101.0, 100.0), 100.0);
+		let sell = Vwap { trade_type: TradeType::Ask, ..vwap };
+		assert_eq!(sell.tracking_error(101.0, 100.0), -100.0);
+	}
+}
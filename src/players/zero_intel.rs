@@ -0,0 +1,83 @@
+use crate::order::order::TradeType;
+use crate::simulation::simulation_config::{Distributions, DistReason};
+
+
+/// A zero-intelligence trader's private value (Gode and Sunder's ZI-C). Buyers never bid above
+/// their redemption value and sellers never ask below their cost, so no fill is at a loss.
+/// trade_type: TradeType -> whether the trader buys or sells
+/// valuation: f64 -> the buyer's redemption value or the seller's cost per share
+/// units: f64 -> the shares the trader buys or sells before it stops trading
+#[derive(Debug, Clone)]
+pub struct ZiValuation {
+	pub trade_type: TradeType,
+	pub valuation: f64,
+	pub units: f64,
+}
+
+impl ZiValuation {
+	pub fn new(trade_type: TradeType, valuation: f64, units: f64) -> ZiValuation {
+		ZiValuation {
+			trade_type,
+			valuation,
+			units,
+		}
+	}
+
+	/// Buys or sells with even odds at a valuation sampled from the ZiValuation distribution
+	pub fn gen(dists: &Distributions, units: f64) -> ZiValuation {
+		let trade_type = match Distributions::fifty_fifty() {
+			true => TradeType::Ask,
+			false => TradeType::Bid,
+		};
+		let valuation = dists.sample_dist(DistReason::ZiValuation).expect("ZiValuation").max(0.0);
+		ZiValuation::new(trade_type, valuation, units)
+	}
+
+	/// A uniformly random price the trader can't lose at. Bids are drawn from [0, valuation]
+	/// and asks from [valuation, max_price].
+	pub fn rand_price(&self, max_price: f64) -> f64 {
+		let (low, high) = match self.trade_type {
+			TradeType::Bid => (0.0, self.valuation),
+			TradeType::Ask => (self.valuation, max_price),
+		};
+		if high <= low {
+			return self.valuation;
+		}
+		Distributions::sample_uniform(low, high, None)
+	}
+
+	/// The shares left to trade given the trader's inventory
+	pub fn remaining(&self, inventory: f64) -> f64 {
+		let left = match self.trade_type {
+			TradeType::Bid => self.units - inventory,
+			TradeType::Ask => self.units + inventory,
+		};
+		left.max(0.0)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_zi_budget() {
+		let buyer = ZiValuation::new(TradeType::Bid, 100.0, 2.0);
+		let seller = ZiValuation::new(TradeType::Ask, 90.0, 2.0);
+		for _ in 0..100 {
+			let bid = buyer.rand_price(200.0);
+			assert!(bid >= 0.0 && bid <= 100.0);
+			let ask = seller.rand_price(200.0);
+			assert!(ask >= 90.0 && ask <= 200.0);
+		}
+
+		// A cost above the price ceiling is still never undercut
+		assert_eq!(ZiValuation::new(TradeType::Ask, 250.0, 1.0).rand_price(200.0), 250.0);
+
+		assert_eq!(buyer.remaining(0.5), 1.5);
+		assert_eq!(buyer.remaining(3.0), 0.0);
+		assert_eq!(seller.remaining(-2.0), 0.0);
+		assert_eq!(seller.remaining(0.0), 2.0);
+	}
+}
@@ -0,0 +1,173 @@
+use crate::simulation::simulation::Simulation;
+use crate::simulation::simulation_config::{Constants, Distributions, DistReason, DistType};
+use crate::blockchain::order_processor::LatencyModel;
+use crate::exchange::MarketType;
+use crate::exchange::clearing_house::ClearingHouse;
+use crate::exchange::fees;
+use crate::blockchain::mem_pool::MemPool;
+use crate::blockchain::gossip::GossipModel;
+use crate::players::TraderT;
+use crate::players::miner::Miner;
+use crate::simulation::simulation_history::History;
+use crate::utility::gen_trader_id;
+
+/// Sets up a Simulation in code instead of from the csv configs. Starts from the defaults
+/// in configs/, every const and distribution can be overridden before building.
+/// dists: Distributions -> the distributions the players sample from
+/// consts: Constants -> the simulation's parameters
+pub struct SimulationBuilder {
+	pub dists: Distributions,
+	pub consts: Constants,
+}
+
+impl SimulationBuilder {
+	pub fn new() -> SimulationBuilder {
+		SimulationBuilder {
+			dists: SimulationBuilder::default_dists(),
+			consts: SimulationBuilder::default_consts(),
+		}
+	}
+
+	/// The distributions in configs/dists.csv
+	pub fn default_dists() -> Distributions {
+		Distributions::new(vec![
+			(DistReason::AsksCenter, 110.0, 10.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 90.0, 10.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrameForm, 150.0, 151.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorVolume, 0.99, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 10.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MakerInventory, 0.0, 10.0, 1.0, DistType::Normal),
+			(DistReason::MakerBalance, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MakerOrderVolume, 1.0, 3.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorBalance, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorInventory, 0.0, 10.0, 1.0, DistType::Normal),
+			(DistReason::ZiValuation, 100.0, 10.0, 1.0, DistType::Normal),
+		])
+	}
+
+	/// The consts in configs/consts.csv
+	pub fn default_consts() -> Constants {
+		Constants::new(300, 250, 50, 100, 20, MarketType::KLF, 1.0, 0.25, 1, 0.25, 0.25, 5.0, 0.01, 10, 0.50)
+	}
+
+	pub fn market(mut self, market_type: MarketType) -> SimulationBuilder {
+		self.consts.market_type = market_type;
+		self
+	}
+
+	pub fn investors(mut self, num_investors: u64) -> SimulationBuilder {
+		self.consts.num_investors = num_investors;
+		self
+	}
+
+	pub fn makers(mut self, num_makers: u64) -> SimulationBuilder {
+		self.consts.num_makers = num_makers;
+		self
+	}
+
+	pub fn blocks(mut self, num_blocks: u64) -> SimulationBuilder {
+		self.consts.num_blocks = num_blocks;
+		self
+	}
+
+	pub fn batch_interval(mut self, millis: u64) -> SimulationBuilder {
+		self.consts.batch_interval = millis;
+		self
+	}
+
+	pub fn block_size(mut self, block_size: usize) -> SimulationBuilder {
+		self.consts.block_size = block_size;
+		self
+	}
+
+	pub fn seed(mut self, seed: u64) -> SimulationBuilder {
+		self.consts.seed = seed;
+		self
+	}
+
+	/// Sets any other const
+	pub fn with_consts<F: FnOnce(&mut Constants)>(mut self, f: F) -> SimulationBuilder {
+		f(&mut self.consts);
+		self
+	}
+
+	/// Sets or replaces the distribution for a DistReason
+	pub fn dist(mut self, reason: DistReason, v1: f64, v2: f64, scalar: f64, dist_type: DistType) -> SimulationBuilder {
+		self.dists.dists[reason as usize] = (v1, v2, scalar, dist_type);
+		self
+	}
+
+	pub fn dists(mut self, dists: Distributions) -> SimulationBuilder {
+		self.dists = dists;
+		self
+	}
+
+	pub fn consts(mut self, consts: Constants) -> SimulationBuilder {
+		self.consts = consts;
+		self
+	}
+
+	/// Initializes the ClearingHouse, MemPool and History, registers the miner, investors and
+	/// makers, and returns the Simulation with the miner for the miner task
+	pub fn build(self) -> (Simulation, Miner) {
+		let SimulationBuilder { dists, consts } = self;
+		// Initialize the state for the simulation
+		let house = ClearingHouse::new();
+		let mempool = MemPool::new().with_latency(LatencyModel::new(&dists)).with_capacity(consts.mempool_capacity, consts.mempool_eviction)
+			.with_gossip(GossipModel::new(&consts, &dists)).with_regions(consts.num_regions).with_commit_reveal(consts.commit_reveal)
+			.with_encryption(consts.encrypted_mempool, consts.encryption_leak_prob).with_gas_oracle(consts.gas_oracle_window, consts.gas_oracle_percentile).with_rate_limit(consts.max_pending_per_trader, consts.max_orders_per_block, consts.spam_gas_escalation);
+		let history = History::new(consts.market_type);
+
+		// Initialize and register the miner to CH
+		let ch_miner = Miner::new(gen_trader_id(TraderT::Miner));
+		let miner_id = ch_miner.trader_id.clone();
+		house.reg_miner(ch_miner);
+		house.set_fee_policies(fees::policies_from(&consts));
+		house.settlement.set_lag(consts.settlement_lag);
+		house.set_min_inventory(consts.min_inventory);
+		house.set_risk_limits(consts.max_position, consts.max_order_size);
+		house.set_budget_rule(consts.budget_rule);
+
+		// Initialize copy of miner for the miner task
+		let mut miner = Miner::new(gen_trader_id(TraderT::Miner));
+		miner.trader_id = miner_id;
+
+		// Initialize and register the Investors
+		let invs = Simulation::setup_investors(&dists, &consts);
+		house.reg_n_investors(invs);
+
+		// Initialize and register the Makers
+		let mkrs = Simulation::setup_makers(&dists, &consts);
+		house.reg_n_makers(mkrs);
+
+		(Simulation::new(dists, consts, house, mempool, history), miner)
+	}
+}
+
+impl Simulation {
+	/// A builder for setting up a Simulation without config files
+	pub fn builder() -> SimulationBuilder {
+		SimulationBuilder::new()
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::utility::seed_task;
+
+	#[test]
+	fn test_builder_sets_up_players() {
+		let builder = Simulation::builder().market(MarketType::FBA).investors(11).makers(6).blocks(3).seed(42)
+			.dist(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform);
+		seed_task(builder.consts.seed, "main", 0);
+		let (sim, _miner) = builder.build();
+		assert_eq!(sim.consts.market_type, MarketType::FBA);
+		assert_eq!(sim.consts.num_blocks, 3);
+		// setup_investors and setup_makers register one fewer than the count
+		assert_eq!(sim.house.query().of_type(TraderT::Investor).ids().len(), 10);
+		assert_eq!(sim.house.query().of_type(TraderT::Maker).ids().len(), 5);
+	}
+}
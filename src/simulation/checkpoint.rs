@@ -0,0 +1,259 @@
+use crate::simulation::simulation::Simulation;
+use crate::simulation::simulation_config::{Constants, Distributions};
+use crate::simulation::simulation_history::History;
+use crate::exchange::exchange_logic::OrderEvent;
+use crate::order::order::{Order, OrderType};
+use crate::players::{Player, PlayerSnapshot, TraderT};
+use crate::players::investor::Investor;
+use crate::players::maker::{Maker, MakerT, MakerParams};
+use crate::players::miner::Miner;
+use crate::players::script::OrderScript;
+use crate::utility::{sim_rng, seed_rng, seed_task};
+use crate::error::{Error, Result};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::{fs, io};
+use rand::Rng;
+
+/// A player's account and the order flow it has seen, enough to register it again
+/// maker: Option<(MakerT, MakerParams, u64)> -> a maker's type, quoting parameters and next client order id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlayer {
+	pub snapshot: PlayerSnapshot,
+	pub sent_orders: Vec<(u64, OrderType)>,
+	pub order_events: Vec<OrderEvent>,
+	pub maker: Option<(MakerT, MakerParams, u64)>,
+}
+
+impl SavedPlayer {
+	pub fn new(player: &dyn Player) -> SavedPlayer {
+		SavedPlayer {
+			snapshot: PlayerSnapshot::new(player),
+			sent_orders: player.copy_sent_orders(),
+			order_events: player.copy_order_events(),
+			maker: player.quoting().map(|(maker_type, params)| (maker_type, params, player.last_client_id())),
+		}
+	}
+
+	/// The player as it was saved. A maker or investor gets back the strategy script it ran, if any.
+	pub fn into_player(self, script: Option<Arc<dyn OrderScript>>) -> Box<dyn Player + Send + Sync> {
+		let snapshot = self.snapshot;
+		match (snapshot.player_type, self.maker) {
+			(TraderT::Maker, Some((maker_type, params, next_client_id))) => Box::new(Maker {
+				trader_id: snapshot.trader_id,
+				orders: Mutex::new(snapshot.orders),
+				balance: snapshot.balance,
+				inventory: snapshot.positions,
+				player_type: TraderT::Maker,
+				maker_type,
+				sent_orders: Mutex::new(self.sent_orders),
+				order_events: Mutex::new(self.order_events),
+				margin: snapshot.margin,
+				next_client_id: Mutex::new(next_client_id),
+				script,
+				params,
+				risk_aversion: snapshot.risk_aversion,
+			}),
+			(TraderT::Miner, _) => {
+				let mut miner = Miner::new(snapshot.trader_id);
+				miner.orders = Mutex::new(snapshot.orders);
+				miner.balance = snapshot.balance;
+				miner.inventory = snapshot.positions;
+				miner.sent_orders = Mutex::new(self.sent_orders);
+				miner.order_events = Mutex::new(self.order_events);
+				miner.margin = snapshot.margin;
+				Box::new(miner)
+			},
+			(player_type, _) => Box::new(Investor {
+				trader_id: snapshot.trader_id,
+				orders: Mutex::new(snapshot.orders),
+				balance: snapshot.balance,
+				inventory: snapshot.positions,
+				player_type,
+				sent_orders: Mutex::new(self.sent_orders),
+				order_events: Mutex::new(self.order_events),
+				margin: snapshot.margin,
+				risk_aversion: snapshot.risk_aversion,
+				script,
+			}),
+		}
+	}
+}
+
+/// The state of a Simulation after a block is published, saved to disk so a long run can be
+/// resumed or branched. The players and History are saved whole, the agents' tasks find their
+/// players again under the seed the checkpoint was taken with. Orders in flight to the MemPool
+/// while it's taken are lost.
+/// books: HashMap<String, (Vec<Order>, Vec<Order>)> -> each symbol's lit bids and asks
+/// mempool: Vec<Order> -> the waiting orders, with the orders in the miners' unpublished frames
+/// held: Vec<Order> -> the orders the MemPool holds for a trader's earlier ones
+/// nonces: HashMap<String, (u64, u64)> -> each trader's next nonce to send and to deliver
+/// seed: u64 -> the consts' seed the run was started with
+/// rng_seed: u64 -> the seed the checkpointing thread carries on from
+#[derive(Serialize, Deserialize)]
+pub struct SimCheckpoint {
+	pub block: u64,
+	pub books: HashMap<String, (Vec<Order>, Vec<Order>)>,
+	pub mempool: Vec<Order>,
+	pub held: Vec<Order>,
+	pub nonces: HashMap<String, (u64, u64)>,
+	pub players: Vec<SavedPlayer>,
+	pub history: History,
+	pub seed: u64,
+	pub rng_seed: u64,
+}
+
+impl SimCheckpoint {
+	/// Copies the simulation's state, putting back the orders the miners took for their next
+	/// frames. The calling thread is reseeded so it draws the same numbers as a restored run.
+	pub fn new(sim: &Simulation, miners: &[&Miner]) -> SimCheckpoint {
+		let mut mempool = sim.mempool.snapshot().to_vec();
+		for miner in miners {
+			mempool.extend(miner.frame.iter().filter(|o| o.trader_id != miner.trader_id).cloned());
+		}
+		let mut players: Vec<SavedPlayer> = {
+			let players = sim.house.players.read().expect("checkpoint players");
+			players.values().map(|p| SavedPlayer::new(&**p)).collect()
+		};
+		players.sort_by(|a, b| a.snapshot.trader_id.cmp(&b.snapshot.trader_id));
+
+		let rng_seed = sim_rng().gen();
+		seed_rng(rng_seed);
+		SimCheckpoint {
+			block: sim.block_num.read_count(),
+			books: sim.books.iter().map(|(symbol, (bids, asks))| (symbol.clone(), (bids.copy_orders(), asks.copy_orders()))).collect(),
+			mempool,
+			held: sim.mempool.held.lock().expect("checkpoint held").clone(),
+			nonces: sim.mempool.nonces.lock().expect("checkpoint nonces").clone(),
+			players,
+			history: sim.history.copy(),
+			seed: sim.consts.seed,
+			rng_seed,
+		}
+	}
+
+	pub fn load(path: &str) -> io::Result<SimCheckpoint> {
+		let json = fs::read_to_string(path)?;
+		serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+	}
+
+	pub fn save(&self, path: &str) -> io::Result<()> {
+		let json = serde_json::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		fs::write(path, json)
+	}
+
+	/// Puts the state back into a simulation set up from the same config, replacing its players
+	/// with the saved ones. Errors unless the simulation is seeded as the checkpoint was, since
+	/// the agents' tasks look their players up by the ids the seed generates.
+	pub fn restore(self, sim: &Simulation) -> Result<()> {
+		if sim.consts.seed == 0 || sim.consts.seed != self.seed {
+			return Err(Error::SeedMismatch { expected: self.seed, found: sim.consts.seed, op: "restore_checkpoint" });
+		}
+
+		{
+			let mut house = sim.house.players.write().expect("checkpoint restore");
+			let mut restored = HashMap::new();
+			for saved in self.players {
+				let trader_id = saved.snapshot.trader_id.clone();
+				let script = house.get(&trader_id).and_then(|p| p.script());
+				restored.insert(trader_id, saved.into_player(script));
+			}
+			*house = restored;
+		}
+
+		for (symbol, (bids, asks)) in self.books {
+			if let Some((bid_book, ask_book)) = sim.books.get(&symbol) {
+				bid_book.restore(bids);
+				ask_book.restore(asks);
+			}
+		}
+		sim.mempool.restore(self.mempool, self.held, self.nonces);
+		sim.history.restore(self.history);
+		*sim.block_num.num.lock().expect("checkpoint block_num") = self.block;
+		seed_rng(self.rng_seed);
+		Ok(())
+	}
+}
+
+impl Simulation {
+	/// Sets up a simulation as main does and restores the checkpoint into it
+	pub fn from_checkpoint(dists: Distributions, consts: Constants, checkpoint: SimCheckpoint) -> Result<(Simulation, Miner)> {
+		seed_task(consts.seed, "main", 0);
+		let (sim, miner) = Simulation::init_simulation(dists, consts);
+		checkpoint.restore(&sim)?;
+		Ok((sim, miner))
+	}
+
+	/// Saves a checkpoint to the --checkpoint path once the consts' checkpoint block is
+	/// published, from the miner's thread so its next frame is kept
+	pub fn checkpoint_block(&self, miner: &Miner) {
+		let path = match &self.checkpoint_path {
+			Some(path) => path,
+			None => return,
+		};
+		if self.block_num.read_count() != self.consts.checkpoint_after() + 1 {
+			return;
+		}
+		match SimCheckpoint::new(self, &[miner]).save(path) {
+			Ok(()) => info!("Checkpointed block {} to {}", self.consts.checkpoint_after(), path),
+			Err(e) => warn!("Couldn't save checkpoint {}: {}", path, e),
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::{DistReason, DistType};
+	use crate::exchange::MarketType;
+	use crate::order::order::{OrderType, TradeType, ExchangeType};
+	use crate::players::TraderT;
+
+	#[test]
+	fn test_checkpoint_round_trip() {
+		let dists = Distributions::new(vec![
+			(DistReason::AsksCenter, 110.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 90.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MinerFrameForm, 50.0, 20.0, 1.0, DistType::Normal),
+			(DistReason::PropagationDelay, 20.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 50.0, 50.0, 1.0, DistType::Poisson),
+			(DistReason::MakerType, 0.0, 4.0, 1.0, DistType::Uniform),
+			(DistReason::MakerInventory, 0.0, 100.0, 1.0, DistType::Uniform),
+			(DistReason::MakerBalance, 50.0, 100.0, 1.0, DistType::Uniform),
+		]);
+		let mut consts = Constants::new(300, 10, 10, 100, 3, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		consts.seed = 7;
+
+		seed_task(consts.seed, "main", 0);
+		let (sim, _miner) = Simulation::init_simulation(dists.clone(), consts);
+		let trader_id = sim.house.query().of_type(TraderT::Investor).ids()[0].clone();
+		sim.house.update_player_bal(trader_id.clone(), 12.5);
+		let order = Order::new(trader_id.clone(), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder, 0.0, 0.0, 95.0, 1.0, 1.0, 1.0);
+		sim.mempool.add(order.clone());
+		sim.history.mempool_order(order);
+		sim.block_num.inc_count();
+		// A player that joined mid-run, which the restored run doesn't register itself
+		sim.house.reg_investor(Investor::new(format!("entrant")));
+
+		let path = format!("{}/flow_rs_checkpoint_test.json", std::env::temp_dir().display());
+		let checkpoint = SimCheckpoint::new(&sim, &[]);
+		checkpoint.save(&path).unwrap();
+		let loaded = SimCheckpoint::load(&path).unwrap();
+
+		let mut unseeded = consts;
+		unseeded.seed = 0;
+		assert!(Simulation::from_checkpoint(dists.clone(), unseeded, SimCheckpoint::load(&path).unwrap()).is_err());
+		let _ = fs::remove_file(&path);
+
+		let (restored, _miner) = Simulation::from_checkpoint(dists, consts, loaded).unwrap();
+		assert_eq!(restored.block_num.read_count(), 1);
+		assert_eq!(restored.mempool.length(), 1);
+		assert_eq!(restored.history.mempool_data.lock().unwrap().len(), 1);
+		assert_eq!(restored.house.inspect_player(&trader_id).unwrap().balance, sim.house.inspect_player(&trader_id).unwrap().balance);
+		assert!(restored.house.inspect_player("entrant").is_some());
+	}
+}
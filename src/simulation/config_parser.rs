@@ -1,4 +1,6 @@
 use crate::simulation::simulation_config::{DistType, DistReason, Distributions, Constants};
+use crate::exchange::fees::TaxRule;
+use crate::players::script::ScriptSpec;
 
 use std::error::Error;
 use serde::Deserialize;
@@ -43,6 +45,31 @@ pub fn parse_dist_config_csv(path: String) -> Result<Distributions, Box<dyn Erro
     Ok(Distributions::new(lines))
 }
 
+/// Reads a tax schedule, one TaxRule per line
+pub fn parse_tax_schedule_csv(path: String) -> Result<Vec<TaxRule>, Box<dyn Error>> {
+    let mut rules = Vec::new();
+    let mut rdr = csv::Reader::from_path(path)?;
+    println!("Reading in tax schedule...");
+    for result in rdr.deserialize() {
+        let rule: TaxRule = result?;
+        println!("{:?}", rule);
+        rules.push(rule);
+    }
+    Ok(rules)
+}
+
+pub fn parse_scripts_csv(path: String) -> Result<Vec<ScriptSpec>, Box<dyn Error>> {
+    let mut specs = Vec::new();
+    let mut rdr = csv::Reader::from_path(path)?;
+    println!("Reading in strategy scripts...");
+    for result in rdr.deserialize() {
+        let spec: ScriptSpec = result?;
+        println!("{:?}", spec);
+        specs.push(spec);
+    }
+    Ok(specs)
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,121 @@
+use crate::simulation::simulation::BlockNum;
+
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the miner, investor and maker tasks run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunState {
+	Running,
+	Paused,
+	Stepping(u64),	// Run until the block number reaches this, then hold
+}
+
+/// The run state the tasks check before each run, skipping the run while they're held
+/// stopped: AtomicBool -> set once the simulation ends, every task stops at its next run
+pub struct SimControl {
+	pub state: Mutex<RunState>,
+	pub stopped: AtomicBool,
+}
+
+impl SimControl {
+	pub fn new() -> SimControl {
+		SimControl {
+			state: Mutex::new(RunState::Running),
+			stopped: AtomicBool::new(false),
+		}
+	}
+
+	/// Signals every task to stop
+	pub fn stop(&self) {
+		self.stopped.store(true, Ordering::SeqCst);
+	}
+
+	pub fn is_stopped(&self) -> bool {
+		self.stopped.load(Ordering::SeqCst)
+	}
+
+	/// A check for a repeating task to stop on
+	pub fn stopper(control: &Arc<SimControl>) -> impl Fn() -> bool + Send + Sync + 'static {
+		let control = Arc::clone(control);
+		move || control.is_stopped()
+	}
+
+	pub fn set(&self, state: RunState) {
+		*self.state.lock().expect("sim_control set") = state;
+	}
+
+	pub fn state(&self) -> RunState {
+		*self.state.lock().expect("sim_control state")
+	}
+
+	/// Whether the tasks may run at the block number
+	pub fn is_running(&self, block: u64) -> bool {
+		match self.state() {
+			RunState::Running => true,
+			RunState::Paused => false,
+			RunState::Stepping(until) => block < until,
+		}
+	}
+}
+
+/// Pauses, resumes and single-steps a running Simulation from another thread
+#[derive(Clone)]
+pub struct SimulationHandle {
+	pub control: Arc<SimControl>,
+	pub block_num: Arc<BlockNum>,
+}
+
+impl SimulationHandle {
+	/// Holds the tasks at their next run
+	pub fn pause(&self) {
+		self.control.set(RunState::Paused);
+	}
+
+	pub fn resume(&self) {
+		self.control.set(RunState::Running);
+	}
+
+	/// Lets the tasks run until the next block is published, then holds them again
+	pub fn step_block(&self) {
+		self.control.set(RunState::Stepping(self.block_num.read_count() + 1));
+	}
+
+	/// Stops the tasks for good, the run's results are taken once they've all finished
+	pub fn stop(&self) {
+		self.control.stop();
+	}
+
+	/// Whether the tasks are held at the current block
+	pub fn is_paused(&self) -> bool {
+		!self.control.is_running(self.block_num.read_count())
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_simulation_handle() {
+		let handle = SimulationHandle { control: Arc::new(SimControl::new()), block_num: Arc::new(BlockNum::new()) };
+		assert!(!handle.is_paused());
+		handle.pause();
+		assert!(handle.is_paused());
+
+		// A step runs the tasks until the block is published
+		handle.step_block();
+		assert!(!handle.is_paused());
+		handle.block_num.inc_count();
+		assert!(handle.is_paused());
+
+		handle.resume();
+		assert!(!handle.is_paused());
+
+		let stopped = SimControl::stopper(&handle.control);
+		assert!(!stopped());
+		handle.stop();
+		assert!(stopped());
+	}
+}
@@ -0,0 +1,219 @@
+use crate::simulation::simulation::Simulation;
+use crate::simulation::simulation_config::{Constants, Distributions, DistReason};
+use crate::order::order::{Order, OrderType, TradeType};
+use crate::blockchain::order_processor::OrderProcessor;
+use crate::players::miner::Miner;
+
+
+/// What the learner does before the next block is mined
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+	Hold,
+	/// Send a single limit order
+	Limit { trade_type: TradeType, price: f64, quantity: f64 },
+	/// Cancel any resting orders and quote both sides
+	Quote { bid_price: f64, ask_price: f64, quantity: f64 },
+	CancelAll,
+}
+
+/// The market and the learner's account after a step, for the first symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+	pub block_num: u64,
+	pub best_bid: Option<f64>,
+	pub best_ask: Option<f64>,
+	pub last_price: Option<f64>,
+	pub balance: f64,
+	pub inventory: f64,
+	pub open_orders: usize,
+}
+
+/// A gym-style environment around a single learner in the simulation. Instead of the repeating
+/// tasks, each step runs one batch_interval of investor arrivals and a maker round, then mines
+/// the next block, so the learner drives the simulation synchronously.
+/// Reward is the change in the learner's balance plus inventory marked at the last price.
+pub struct Env {
+	pub dists: Distributions,
+	pub consts: Constants,
+	pub sim: Simulation,
+	pub miner: Miner,
+	pub learner_id: String,
+	pub last_equity: f64,
+}
+
+impl Env {
+	pub fn new(dists: Distributions, consts: Constants) -> Env {
+		let (sim, miner, learner_id) = Env::init(&dists, &consts);
+		Env {
+			dists,
+			consts,
+			sim,
+			miner,
+			learner_id,
+			last_equity: 0.0,
+		}
+	}
+
+	fn init(dists: &Distributions, consts: &Constants) -> (Simulation, Miner, String) {
+		let (sim, miner) = Simulation::init_simulation(dists.clone(), *consts);
+		let learner_id = sim.reg_learner();
+		(sim, miner, learner_id)
+	}
+
+	/// Starts a fresh simulation and returns the first observation
+	pub fn reset(&mut self) -> Observation {
+		let (sim, miner, learner_id) = Env::init(&self.dists, &self.consts);
+		self.sim = sim;
+		self.miner = miner;
+		self.learner_id = learner_id;
+		let obs = self.observe();
+		self.last_equity = self.equity(&obs);
+		obs
+	}
+
+	/// Applies the action, advances the simulation by one block and returns the
+	/// (observation, reward, done) of the new state
+	pub fn step(&mut self, action: Action) -> (Observation, f64, bool) {
+		self.act(action);
+
+		// Investors keep arriving until a batch_interval of their enter times has passed
+		let mut elapsed = 0.0;
+		while elapsed < self.consts.batch_interval as f64 {
+			if !Simulation::investor_arrival(&self.sim.dists, &self.sim.house, &self.sim.mempool, &self.sim.history, &self.sim.block_num, &self.consts) {
+				break;
+			}
+			elapsed += self.sim.dists.sample_dist(DistReason::InvestorEnter).expect("Couldn't get enter time sample").abs().max(1.0);
+		}
+		Simulation::investor_requote_round(&self.sim.dists, &self.sim.house, &self.sim.mempool, &self.sim.history, &self.sim.block_num, &self.consts);
+		Simulation::maker_round(&self.sim.dists, &self.sim.house, &self.sim.mempool, &self.sim.history, &self.sim.block_num, &self.consts);
+
+		// Mine the orders sent this step into the next block
+		self.sim.make_next_frame(&mut self.miner, self.sim.block_strategy().as_ref());
+		self.sim.publish_block(&mut self.miner);
+		if self.consts.fundamental_jump_prob > 0.0 {
+			self.sim.fundamental.step(self.sim.block_num.read_count());
+		}
+
+		let obs = self.observe();
+		let equity = self.equity(&obs);
+		let reward = equity - self.last_equity;
+		self.last_equity = equity;
+		let done = obs.block_num > self.consts.num_blocks;
+		(obs, reward, done)
+	}
+
+	pub fn observe(&self) -> Observation {
+		let symbol = self.consts.symbols()[0].clone();
+		let (bids, asks) = &self.sim.books[&symbol];
+		let last_price = self.sim.history.clearing_history(&symbol).last().map(|(price, _vol)| *price);
+		let (balance, inventory, open_orders) = match self.sim.house.inspect_player(&self.learner_id) {
+			Some(p) => (p.balance, p.positions.get(&symbol).cloned().unwrap_or(0.0), p.orders.len()),
+			None => (0.0, 0.0, 0),
+		};
+		Observation {
+			block_num: self.sim.block_num.read_count(),
+			best_bid: bids.peek_best_price(),
+			best_ask: asks.peek_best_price(),
+			last_price,
+			balance,
+			inventory,
+			open_orders,
+		}
+	}
+
+	/// Balance plus inventory marked at the last price, or the fundamental value before any trades
+	fn equity(&self, obs: &Observation) -> f64 {
+		obs.balance + obs.inventory * obs.last_price.unwrap_or(self.sim.fundamental.value())
+	}
+
+	fn act(&self, action: Action) {
+		match action {
+			Action::Hold => {},
+			Action::Limit { trade_type, price, quantity } => self.send(trade_type, price, quantity),
+			Action::Quote { bid_price, ask_price, quantity } => {
+				self.cancel_all();
+				self.send(TradeType::Bid, bid_price, quantity);
+				self.send(TradeType::Ask, ask_price, quantity);
+			},
+			Action::CancelAll => self.cancel_all(),
+		}
+	}
+
+	fn cancel_all(&self) {
+		if let Ok(cancel_orders) = self.sim.house.cancel_all_orders(self.learner_id.clone(), self.consts.mass_cancel_gas) {
+			for order in cancel_orders {
+				self.sim.history.mempool_order(order.clone());
+				if let Err(e) = OrderProcessor::recv_order(order, &self.sim.mempool) {
+					println!("Learner cancel rejected: {}", e);
+				}
+			}
+		}
+	}
+
+	fn send(&self, trade_type: TradeType, price: f64, quantity: f64) {
+		let (sim, consts) = (&self.sim, &self.consts);
+		let symbol = consts.symbols()[0].clone();
+		let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, consts);
+		let order = Order::new(self.learner_id.clone(),
+							   OrderType::Enter,
+							   trade_type,
+							   ex_type,
+							   p_l,
+							   p_h,
+							   price,
+							   quantity,
+							   quantity,
+							   sim.dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+		).with_symbol(&symbol);
+
+		let order = sim.house.fit_to_budget(order);
+		let admitted = sim.history.check_price_band(&order, consts.price_band_pct).and_then(|_| sim.house.new_order(order.clone()));
+		Simulation::admission_event(&sim.house, &sim.history, &order, &admitted, sim.block_num.read_count());
+		if admitted.is_ok() {
+			sim.history.mempool_order(order.clone());
+			if let Err(e) = OrderProcessor::recv_order(order, &sim.mempool) {
+				println!("Learner order rejected: {}", e);
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exchange::MarketType;
+	use crate::simulation::simulation_config::DistType;
+
+	#[test]
+	fn test_env_steps() {
+		let dists = Distributions::new(vec![
+			(DistReason::AsksCenter, 110.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 90.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MinerFrameForm, 50.0, 20.0, 1.0, DistType::Normal),
+			(DistReason::PropagationDelay, 20.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorVolume, 0.99, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 50.0, 50.0, 1.0, DistType::Poisson),
+			(DistReason::MakerType, 0.0, 4.0, 1.0, DistType::Uniform),
+			(DistReason::MakerInventory, 0.0, 100.0, 1.0, DistType::Uniform),
+			(DistReason::MakerBalance, 50.0, 100.0, 1.0, DistType::Uniform),
+		]);
+		let consts = Constants::new(300, 10, 10, 100, 2, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		let mut env = Env::new(dists, consts);
+		let obs = env.reset();
+		assert_eq!((obs.balance, obs.inventory, obs.open_orders), (0.0, 0.0, 0));
+
+		// A bid far below the asks rests in the book
+		let (obs, _reward, done) = env.step(Action::Limit { trade_type: TradeType::Bid, price: 1.0, quantity: 1.0 });
+		assert_eq!(obs.block_num, 1);
+		assert_eq!(obs.open_orders, 1);
+		assert!(!done);
+
+		let (obs, _reward, _done) = env.step(Action::CancelAll);
+		assert_eq!(obs.open_orders, 0);
+		let (_obs, _reward, done) = env.step(Action::Hold);
+		assert!(done);
+	}
+}
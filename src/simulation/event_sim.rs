@@ -0,0 +1,252 @@
+use crate::simulation::simulation::Simulation;
+use crate::simulation::simulation_config::DistReason;
+use crate::blockchain::mining::HashPower;
+use crate::players::miner::Miner;
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::{thread, time};
+
+/// What the discrete-event engine runs at an instant of virtual time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimEvent {
+	InvestorArrival,	// An investor sends an order
+	InvestorRequote,	// Investors cancel or reprice their stale orders
+	MakerRound,			// The makers requote
+	PublishBlock,		// The winning frame is published as the next block
+	FormFrame,			// The miners form their frames for the next block
+}
+
+/// An event due at virtual time at, in millis. Events due at the same time run in the order
+/// they were scheduled.
+#[derive(Debug, Clone)]
+pub struct Scheduled {
+	pub at: u64,
+	pub seq: u64,
+	pub event: SimEvent,
+}
+
+impl PartialEq for Scheduled {
+	fn eq(&self, other: &Scheduled) -> bool {
+		(self.at, self.seq) == (other.at, other.seq)
+	}
+}
+
+impl Eq for Scheduled {}
+
+impl PartialOrd for Scheduled {
+	fn partial_cmp(&self, other: &Scheduled) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+// Reversed so the heap pops the earliest event first
+impl Ord for Scheduled {
+	fn cmp(&self, other: &Scheduled) -> Ordering {
+		(other.at, other.seq).cmp(&(self.at, self.seq))
+	}
+}
+
+/// The events waiting to run, earliest first
+/// now: u64 -> the virtual time of the last event taken, in millis
+pub struct EventQueue {
+	pub now: u64,
+	pub seq: u64,
+	pub events: BinaryHeap<Scheduled>,
+}
+
+impl EventQueue {
+	pub fn new() -> EventQueue {
+		EventQueue {
+			now: 0,
+			seq: 0,
+			events: BinaryHeap::new(),
+		}
+	}
+
+	/// Schedules the event delay millis after now
+	pub fn schedule(&mut self, delay: u64, event: SimEvent) {
+		self.seq += 1;
+		self.events.push(Scheduled { at: self.now + delay, seq: self.seq, event });
+	}
+
+	/// Takes the next event due, moving the clock forward to it
+	pub fn pop(&mut self) -> Option<Scheduled> {
+		let scheduled = self.events.pop()?;
+		self.now = scheduled.at;
+		Some(scheduled)
+	}
+
+	pub fn len(&self) -> usize {
+		self.events.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.events.is_empty()
+	}
+}
+
+/// Runs investor arrivals, investor requotes, maker rounds and blocks as events on a virtual
+/// clock in the caller's thread, in place of the thread::sleep driven tasks. A run takes only as
+/// long as the work in it and its agents never race each other. The other agents and per-block
+/// tasks only run as real-time tasks, so configs starting them are refused (see
+/// Constants::real_time_only). The mempool's latency and gossip keep to the wall clock.
+/// power: HashPower -> the miners' shares of the hash power when several compete for each block
+/// winner: usize -> the miner whose frame is published next
+/// wins: HashMap<String, u64> -> the blocks each miner's frame made it into
+/// processed: usize -> the events run so far
+pub struct EventSim {
+	pub sim: Simulation,
+	pub miners: Vec<Miner>,
+	pub power: HashPower,
+	pub winner: usize,
+	pub wins: HashMap<String, u64>,
+	pub queue: EventQueue,
+	pub processed: usize,
+}
+
+impl EventSim {
+	/// Schedules the first investor at once and the first block, requote and maker round
+	/// on the same intervals as their tasks
+	pub fn new(sim: Simulation, miners: Vec<Miner>) -> EventSim {
+		let consts = sim.consts;
+		let power = HashPower::sample(&sim.dists, miners.len());
+		let mut queue = EventQueue::new();
+		queue.schedule(0, SimEvent::InvestorArrival);
+		queue.schedule(consts.batch_interval, SimEvent::PublishBlock);
+		if consts.investor_stale_blocks > 0 {
+			queue.schedule(consts.batch_interval, SimEvent::InvestorRequote);
+		}
+		queue.schedule(consts.batch_interval + consts.maker_prop_delay, SimEvent::MakerRound);
+		EventSim {
+			sim,
+			miners,
+			power,
+			winner: 0,
+			wins: HashMap::new(),
+			queue,
+			processed: 0,
+		}
+	}
+
+	/// Runs the events until the last block is published, returning the virtual millis it took.
+	/// The virtual clock stands still while the simulation's handle holds it.
+	pub fn run(&mut self) -> u64 {
+		while !self.sim.control.is_stopped() {
+			if !self.sim.control.is_running(self.sim.block_num.read_count()) {
+				thread::sleep(time::Duration::from_millis(10));
+				continue;
+			}
+			let scheduled = match self.queue.pop() {
+				Some(scheduled) => scheduled,
+				None => break,
+			};
+			self.handle(scheduled.event);
+			self.processed += 1;
+		}
+		self.queue.now
+	}
+
+	fn handle(&mut self, event: SimEvent) {
+		let sim = &self.sim;
+		let consts = &sim.consts;
+		match event {
+			SimEvent::InvestorArrival => {
+				if !Simulation::investor_arrival(&sim.dists, &sim.house, &sim.mempool, &sim.history, &sim.block_num, consts) {
+					println!("No investors left to arrive");
+					return;
+				}
+				// At least a milli apart so arrivals can't stall the clock
+				let wait = sim.dists.sample_dist(DistReason::InvestorEnter).expect("Couldn't get enter time sample").abs().max(1.0);
+				self.queue.schedule(wait as u64, SimEvent::InvestorArrival);
+			},
+			SimEvent::InvestorRequote => {
+				Simulation::investor_requote_round(&sim.dists, &sim.house, &sim.mempool, &sim.history, &sim.block_num, consts);
+				self.queue.schedule(consts.batch_interval, SimEvent::InvestorRequote);
+			},
+			SimEvent::MakerRound => {
+				Simulation::maker_round(&sim.dists, &sim.house, &sim.mempool, &sim.history, &sim.block_num, consts);
+				self.queue.schedule(consts.batch_interval + consts.maker_prop_delay, SimEvent::MakerRound);
+			},
+			SimEvent::PublishBlock => {
+				sim.publish_block(&mut self.miners[self.winner]);
+				if consts.fundamental_jump_prob > 0.0 {
+					sim.fundamental.step(sim.block_num.read_count());
+				}
+				// The next frame forms after the miners' frame form delay, within the block
+				let delay = sim.dists.sample_dist(DistReason::MinerFrameForm).expect("Couldn't get miner frame form delay").abs() as u64;
+				self.queue.schedule(delay.min(consts.batch_interval), SimEvent::FormFrame);
+				self.queue.schedule(consts.batch_interval, SimEvent::PublishBlock);
+			},
+			SimEvent::FormFrame => {
+				let strategy = sim.block_strategy();
+				if self.miners.len() == 1 {
+					sim.make_next_frame(&mut self.miners[0], strategy.as_ref());
+				} else {
+					self.winner = sim.compete_for_block(&mut self.miners, &self.power, strategy.as_ref());
+					*self.wins.entry(self.miners[self.winner].trader_id.clone()).or_insert(0) += 1;
+				}
+			},
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::{Constants, Distributions, DistType};
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_event_queue() {
+		let mut queue = EventQueue::new();
+		queue.schedule(10, SimEvent::PublishBlock);
+		queue.schedule(5, SimEvent::InvestorArrival);
+		queue.schedule(10, SimEvent::FormFrame);
+		assert_eq!(queue.pop().map(|s| (s.at, s.event)), Some((5, SimEvent::InvestorArrival)));
+
+		// Scheduled from the new now, ties run in the order they were scheduled
+		queue.schedule(5, SimEvent::MakerRound);
+		let order: Vec<(u64, SimEvent)> = std::iter::from_fn(|| queue.pop()).map(|s| (s.at, s.event)).collect();
+		assert_eq!(order, vec![(10, SimEvent::PublishBlock), (10, SimEvent::FormFrame), (10, SimEvent::MakerRound)]);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn test_event_sim_runs_to_the_last_block() {
+		let dists = Distributions::new(vec![
+			(DistReason::AsksCenter, 110.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 90.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MinerFrameForm, 50.0, 20.0, 1.0, DistType::Normal),
+			(DistReason::PropagationDelay, 20.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorVolume, 0.99, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 50.0, 50.0, 1.0, DistType::Poisson),
+			(DistReason::MakerType, 0.0, 4.0, 1.0, DistType::Uniform),
+			(DistReason::MakerInventory, 0.0, 100.0, 1.0, DistType::Uniform),
+			(DistReason::MakerBalance, 50.0, 100.0, 1.0, DistType::Uniform),
+		]);
+		let consts = Constants::new(300, 10, 10, 100, 3, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		let (sim, miner) = Simulation::init_simulation(dists, consts);
+		let mut engine = EventSim::new(sim, vec![miner]);
+
+		// Blocks come every batch_interval of virtual time however long the run takes
+		let elapsed = engine.run();
+		assert_eq!(engine.sim.block_num.read_count(), consts.num_blocks + 1);
+		assert_eq!(elapsed, (consts.num_blocks + 1) * consts.batch_interval);
+		assert!(engine.processed > consts.num_blocks as usize);
+	}
+
+	#[test]
+	fn test_real_time_only_agents() {
+		let mut consts = Constants::new(300, 10, 10, 100, 3, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		assert!(consts.real_time_only().is_empty());
+
+		// The other agents can't be scheduled on the virtual clock
+		consts.num_zi_traders = 5;
+		consts.num_spoofers = 1;
+		assert_eq!(consts.real_time_only(), vec!["num_zi_traders", "num_spoofers"]);
+	}
+}
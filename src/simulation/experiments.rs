@@ -0,0 +1,255 @@
+use crate::simulation::builder::SimulationBuilder;
+use crate::simulation::event_sim::EventSim;
+use crate::simulation::simulation_config::{Constants, Distributions};
+use crate::exchange::MarketType;
+use crate::utility::seed_task;
+
+use std::collections::HashMap;
+use std::thread;
+use std::{fs, io};
+
+/// The metrics calc_performance_results reports for a run, in its csv column order.
+/// The maker counts are kept as f64 so every metric aggregates the same way.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PerformanceResults {
+	pub fund_val: f64,
+	pub total_gas: f64,
+	pub avg_gas: f64,
+	pub total_tax: f64,
+	pub maker_profit: f64,
+	pub investor_profit: f64,
+	pub miner_profit: f64,
+	pub dead_weight: f64,
+	pub volatility: f64,
+	pub rmsd: f64,
+	pub agg_profit: f64,
+	pub riskav_profit: f64,
+	pub rand_profit: f64,
+	pub num_agg: f64,
+	pub num_riska: f64,
+	pub num_rand: f64,
+	pub inv_welf: f64,
+	pub mkr_welf: f64,
+	pub min_welf: f64,
+	pub exchange_revenue: f64,
+	pub agg_realized: f64,
+	pub agg_unrealized: f64,
+	pub riskav_realized: f64,
+	pub riskav_unrealized: f64,
+	pub rand_realized: f64,
+	pub rand_unrealized: f64,
+	pub inventory_tax: f64,
+	pub turnover_tax: f64,
+	pub profit_tax: f64,
+	pub skew_profit: f64,
+	pub num_skew: f64,
+	pub skew_realized: f64,
+	pub skew_unrealized: f64,
+	pub gm_profit: f64,
+	pub num_gm: f64,
+	pub gm_realized: f64,
+	pub gm_unrealized: f64,
+}
+
+impl PerformanceResults {
+	/// Each metric's name and value
+	pub fn metrics(&self) -> Vec<(&'static str, f64)> {
+		vec![
+			("fund_val", self.fund_val), ("total_gas", self.total_gas), ("avg_gas", self.avg_gas), ("total_tax", self.total_tax),
+			("maker_profit", self.maker_profit), ("investor_profit", self.investor_profit), ("miner_profit", self.miner_profit),
+			("dead_weight", self.dead_weight), ("volatility", self.volatility), ("rmsd", self.rmsd),
+			("agg_profit", self.agg_profit), ("riskav_profit", self.riskav_profit), ("rand_profit", self.rand_profit),
+			("num_agg", self.num_agg), ("num_riska", self.num_riska), ("num_rand", self.num_rand),
+			("inv_welf", self.inv_welf), ("mkr_welf", self.mkr_welf), ("min_welf", self.min_welf), ("exchange_revenue", self.exchange_revenue),
+			("agg_realized", self.agg_realized), ("agg_unrealized", self.agg_unrealized),
+			("riskav_realized", self.riskav_realized), ("riskav_unrealized", self.riskav_unrealized),
+			("rand_realized", self.rand_realized), ("rand_unrealized", self.rand_unrealized),
+			("inventory_tax", self.inventory_tax), ("turnover_tax", self.turnover_tax), ("profit_tax", self.profit_tax),
+			("skew_profit", self.skew_profit), ("num_skew", self.num_skew), ("skew_realized", self.skew_realized), ("skew_unrealized", self.skew_unrealized),
+			("gm_profit", self.gm_profit), ("num_gm", self.num_gm), ("gm_realized", self.gm_realized), ("gm_unrealized", self.gm_unrealized),
+		]
+	}
+
+	/// The row logged to the results file
+	pub fn to_csv(&self) -> String {
+		self.metrics().iter().map(|(_, v)| format!("{},", v)).collect()
+	}
+}
+
+/// A metric's mean over the runs, with its sample standard deviation and 95% confidence interval
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricSummary {
+	pub metric: &'static str,
+	pub runs: usize,
+	pub mean: f64,
+	pub std_dev: f64,
+	pub ci_low: f64,
+	pub ci_high: f64,
+}
+
+/// Summarizes each metric over the runs' results, the interval is the normal approximation
+pub fn summarize(results: &[PerformanceResults]) -> Vec<MetricSummary> {
+	let n = results.len();
+	if n == 0 {
+		return Vec::new();
+	}
+	let rows: Vec<Vec<(&'static str, f64)>> = results.iter().map(|r| r.metrics()).collect();
+	rows[0].iter().enumerate().map(|(i, (metric, _))| {
+		let values: Vec<f64> = rows.iter().map(|row| row[i].1).collect();
+		let mean = values.iter().sum::<f64>() / n as f64;
+		let std_dev = if n > 1 {
+			(values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+		} else {
+			0.0
+		};
+		let half_width = 1.96 * std_dev / (n as f64).sqrt();
+		MetricSummary {
+			metric: *metric,
+			runs: n,
+			mean,
+			std_dev,
+			ci_low: mean - half_width,
+			ci_high: mean + half_width,
+		}
+	}).collect()
+}
+
+/// The summaries as csv, a row per metric
+pub fn summary_csv(summaries: &[MetricSummary]) -> String {
+	let mut csv = String::from("metric,runs,mean,std_dev,ci_low,ci_high\n");
+	for s in summaries {
+		csv.push_str(&format!("{},{},{},{},{},{}\n", s.metric, s.runs, s.mean, s.std_dev, s.ci_low, s.ci_high));
+	}
+	csv
+}
+
+/// Runs a config many times over on the discrete-event engine and collects each run's
+/// post liquidation performance results. Run i seeds its thread from the config's seed and i,
+/// a seed of 0 draws every run from entropy.
+/// runs: usize -> the number of independent simulations
+/// threads: usize -> how many run at once, each in its own thread
+pub struct Experiment {
+	pub dists: Distributions,
+	pub consts: Constants,
+	pub runs: usize,
+	pub threads: usize,
+}
+
+impl Experiment {
+	pub fn new(builder: SimulationBuilder, runs: usize) -> Experiment {
+		Experiment {
+			dists: builder.dists,
+			consts: builder.consts,
+			runs,
+			threads: 1,
+		}
+	}
+
+	pub fn with_threads(mut self, threads: usize) -> Experiment {
+		self.threads = threads.max(1);
+		self
+	}
+
+	/// Runs the simulations, a run that panics (e.g. one that never cleared) is left out.
+	/// Nothing is run if the config starts agents the discrete-event engine can't run.
+	pub fn run(&self) -> Vec<PerformanceResults> {
+		let real_time_only = self.consts.real_time_only();
+		if !real_time_only.is_empty() {
+			println!("Experiments can't run {}, they only run in real time", real_time_only.join(", "));
+			return Vec::new();
+		}
+		let mut results = Vec::new();
+		let mut next = 0;
+		while next < self.runs {
+			let batch = (next..self.runs.min(next + self.threads)).map(|run| {
+				let dists = self.dists.clone();
+				let consts = self.consts;
+				(run, thread::spawn(move || Experiment::run_once(dists, consts, run)))
+			}).collect::<Vec<_>>();
+			for (run, handle) in batch {
+				match handle.join() {
+					Ok(res) => results.push(res),
+					Err(_) => println!("Experiment run {} failed", run),
+				}
+			}
+			next += self.threads;
+		}
+		results
+	}
+
+	/// Sets up and runs a single simulation, liquidating every player at the fundamental value
+	pub fn run_once(dists: Distributions, consts: Constants, run: usize) -> PerformanceResults {
+		seed_task(consts.seed, "experiment", run as u64);
+		let (sim, miner) = SimulationBuilder::new().dists(dists).consts(consts).build();
+		let mut init_player_s: HashMap<String, (f64, f64)> = sim.house.snapshot().into_iter()
+			.map(|p| (p.trader_id.clone(), (p.balance, p.total_inv())))
+			.collect();
+
+		let mut engine = EventSim::new(sim, vec![miner]);
+		engine.run();
+		let sim = &engine.sim;
+		init_player_s.extend(sim.house.get_entrants());
+
+		let fund_val = sim.fundamental.value();
+		let fund_vals: HashMap<String, f64> = consts.symbols().into_iter().map(|symbol| (symbol, fund_val)).collect();
+		sim.house.liquidate(&fund_vals);
+		sim.performance(fund_val, init_player_s)
+	}
+
+	/// Runs the experiment once for each market type, everything else held the same
+	pub fn compare_markets(&self, markets: &[MarketType]) -> Vec<(MarketType, Vec<MetricSummary>)> {
+		markets.iter().map(|&market_type| {
+			let mut consts = self.consts;
+			consts.market_type = market_type;
+			let experiment = Experiment { dists: self.dists.clone(), consts, runs: self.runs, threads: self.threads };
+			(market_type, summarize(&experiment.run()))
+		}).collect()
+	}
+
+	pub fn save_summary(summaries: &[MetricSummary], path: &str) -> io::Result<()> {
+		fs::write(path, summary_csv(summaries))
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::{DistReason, DistType};
+	use crate::simulation::simulation::Simulation;
+
+	#[test]
+	fn test_summarize() {
+		let results: Vec<PerformanceResults> = [1.0, 2.0, 3.0].iter()
+			.map(|&v| PerformanceResults { rmsd: v, ..Default::default() })
+			.collect();
+		let summaries = summarize(&results);
+		assert_eq!(summaries.len(), PerformanceResults::default().metrics().len());
+		let rmsd = summaries.iter().find(|s| s.metric == "rmsd").unwrap();
+		assert_eq!(rmsd.mean, 2.0);
+		assert_eq!(rmsd.std_dev, 1.0);
+		assert!((rmsd.ci_high - rmsd.mean - 1.96 / 3f64.sqrt()).abs() < 1e-9);
+		assert_eq!(rmsd.mean - rmsd.ci_low, rmsd.ci_high - rmsd.mean);
+	}
+
+	#[test]
+	fn test_experiment_runs() {
+		let dists = Distributions::new(vec![
+			(DistReason::AsksCenter, 100.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 100.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MinerFrameForm, 50.0, 20.0, 1.0, DistType::Normal),
+			(DistReason::PropagationDelay, 20.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorVolume, 0.99, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 50.0, 50.0, 1.0, DistType::Poisson),
+			(DistReason::MakerType, 0.0, 4.0, 1.0, DistType::Uniform),
+			(DistReason::MakerInventory, 0.0, 100.0, 1.0, DistType::Uniform),
+			(DistReason::MakerBalance, 50.0, 100.0, 1.0, DistType::Uniform),
+		]);
+		let builder = Simulation::builder().dists(dists).market(MarketType::CDA).investors(10).makers(10).blocks(10).seed(5);
+		let results = Experiment::new(builder, 3).with_threads(2).run();
+		assert_eq!(results.len(), 3);
+		assert!(summarize(&results).iter().all(|s| s.runs == 3 && s.ci_low <= s.mean && s.mean <= s.ci_high));
+	}
+}
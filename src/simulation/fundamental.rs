@@ -0,0 +1,92 @@
+use crate::simulation::simulation_config::{Constants, Distributions};
+
+use std::sync::Mutex;
+
+
+/// The fundamental value as a jump process. Each block a jump is scheduled with jump_prob,
+/// sized from N(0, jump_std), and applied jump_lead blocks later so it can be signalled ahead.
+/// Without jumps the value stays at the midpoint of the bid and ask price centers.
+/// value: Mutex<f64> -> the current fundamental value
+/// jumps: Mutex<Vec<(u64, f64)>> -> the (block due, size) of the jumps not applied yet
+pub struct FundamentalValue {
+	pub value: Mutex<f64>,
+	pub jumps: Mutex<Vec<(u64, f64)>>,
+	pub jump_prob: f64,
+	pub jump_std: f64,
+	pub jump_lead: u64,
+}
+
+impl FundamentalValue {
+	pub fn new(initial: f64, consts: &Constants) -> FundamentalValue {
+		FundamentalValue {
+			value: Mutex::new(initial),
+			jumps: Mutex::new(Vec::new()),
+			jump_prob: consts.fundamental_jump_prob,
+			jump_std: consts.fundamental_jump_std,
+			jump_lead: consts.fundamental_jump_lead,
+		}
+	}
+
+	pub fn value(&self) -> f64 {
+		*self.value.lock().expect("FundamentalValue value")
+	}
+
+	/// The jumps scheduled but not applied yet, soonest first
+	pub fn pending(&self) -> Vec<(u64, f64)> {
+		self.jumps.lock().expect("FundamentalValue pending").clone()
+	}
+
+	/// Schedules a jump of size to be applied at block_num
+	pub fn schedule(&self, block_num: u64, size: f64) {
+		let mut jumps = self.jumps.lock().expect("FundamentalValue schedule");
+		jumps.push((block_num, size));
+		jumps.sort_by_key(|(due, _size)| *due);
+	}
+
+	/// Applies the jumps due by block_num, keeping the value non-negative, then randomly
+	/// schedules the next jump
+	pub fn step(&self, block_num: u64) {
+		{
+			let mut jumps = self.jumps.lock().expect("FundamentalValue step");
+			let mut value = self.value.lock().expect("FundamentalValue step");
+			for (_due, size) in jumps.iter().filter(|(due, _size)| *due <= block_num) {
+				*value = (*value + size).max(0.0);
+			}
+			jumps.retain(|(due, _size)| *due > block_num);
+		}
+
+		if self.jump_prob > 0.0 && Distributions::do_with_prob(self.jump_prob) {
+			self.schedule(block_num + self.jump_lead, Distributions::sample_normal(0.0, self.jump_std, None));
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::exchange::MarketType;
+
+	#[test]
+	fn test_jumps() {
+		let consts = Constants::new(300, 10, 10, 100, 20, MarketType::CDA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		let fundamental = FundamentalValue::new(100.0, &consts);
+		fundamental.schedule(5, -10.0);
+		fundamental.schedule(3, 4.0);
+		assert_eq!(fundamental.pending(), vec![(3, 4.0), (5, -10.0)]);
+
+		// Jumps only move the value once they're due
+		fundamental.step(2);
+		assert_eq!(fundamental.value(), 100.0);
+		fundamental.step(3);
+		assert_eq!(fundamental.value(), 104.0);
+		fundamental.step(10);
+		assert_eq!(fundamental.value(), 94.0);
+		assert!(fundamental.pending().is_empty());
+
+		// The value doesn't go negative
+		fundamental.schedule(11, -200.0);
+		fundamental.step(11);
+		assert_eq!(fundamental.value(), 0.0);
+	}
+}
@@ -2,3 +2,13 @@ pub mod simulation_config;
 pub mod simulation;
 pub mod config_parser;
 pub mod simulation_history;
+pub mod risk;
+pub mod fundamental;
+pub mod env;
+pub mod event_sim;
+pub mod control;
+pub mod checkpoint;
+pub mod replay;
+pub mod population;
+pub mod builder;
+pub mod experiments;
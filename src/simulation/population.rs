@@ -0,0 +1,64 @@
+use crate::simulation::simulation_config::Constants;
+
+
+/// Wealth-based entry and exit. Every interval blocks the exit_frac of a population with the
+/// least equity retire, and each is replaced by a newcomer, or with imitate_prob by an imitator
+/// copying the strategy of one of the exit_frac with the most.
+#[derive(Debug, Clone, Copy)]
+pub struct Population {
+	pub interval: u64,
+	pub exit_frac: f64,
+	pub imitate_prob: f64,
+}
+
+impl Population {
+	pub fn new(consts: &Constants) -> Population {
+		Population {
+			interval: consts.population_interval,
+			exit_frac: consts.population_exit_frac,
+			imitate_prob: consts.population_imitate_prob,
+		}
+	}
+
+	/// Whether the population turns over at block_num
+	pub fn is_due(&self, block_num: u64) -> bool {
+		self.interval > 0 && block_num > 0 && block_num % self.interval == 0
+	}
+
+	/// The (poorest, richest) ids of a population given each player's equity, each exit_frac of
+	/// it rounded down. Nobody turns over in a population too small to have both.
+	pub fn turnover(&self, equities: &[(String, f64)]) -> (Vec<String>, Vec<String>) {
+		let n = (equities.len() as f64 * self.exit_frac.max(0.0).min(0.5)) as usize;
+		if n == 0 {
+			return (Vec::new(), Vec::new());
+		}
+		let mut ranked = equities.to_vec();
+		ranked.sort_by(|a, b| a.1.partial_cmp(&b.1).expect("Population turnover"));
+		let poorest = ranked[..n].iter().map(|(id, _e)| id.clone()).collect();
+		let richest = ranked[ranked.len() - n..].iter().map(|(id, _e)| id.clone()).collect();
+		(poorest, richest)
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_turnover() {
+		let pop = Population { interval: 10, exit_frac: 0.25, imitate_prob: 0.5 };
+		assert!(!pop.is_due(0));
+		assert!(pop.is_due(20));
+		assert!(!pop.is_due(25));
+
+		let equities: Vec<(String, f64)> = vec![(format!("a"), 5.0), (format!("b"), -3.0), (format!("c"), 9.0),
+			(format!("d"), 1.0), (format!("e"), 0.0), (format!("f"), 7.0), (format!("g"), 2.0), (format!("h"), 4.0)];
+		let (poorest, richest) = pop.turnover(&equities);
+		assert_eq!(poorest, vec![format!("b"), format!("e")]);
+		assert_eq!(richest, vec![format!("f"), format!("c")]);
+
+		// Too few players to turn any over
+		assert_eq!(pop.turnover(&equities[..3]), (Vec::new(), Vec::new()));
+	}
+}
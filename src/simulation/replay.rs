@@ -0,0 +1,116 @@
+use crate::simulation::simulation::Simulation;
+use crate::simulation::simulation_config::Constants;
+use crate::order::order::{Order, OrderType};
+use crate::players::miner::Miner;
+
+use std::{fs, io};
+
+/// The orders a published block mined, in the order they were matched
+/// orphaned: bool -> whether a reorg rolled the block back, its orders being mined again later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+	pub block: u64,
+	pub orders: Vec<Order>,
+	pub orphaned: bool,
+}
+
+/// Re-runs a previous run from the frames it mined instead of sampling the agents again, so
+/// the books and clearings come out as they did. The players must be registered as they were,
+/// the same config and seed register the same ids.
+pub struct Replay {
+	pub frames: Vec<RecordedFrame>,
+}
+
+impl Replay {
+	pub fn new(frames: Vec<RecordedFrame>) -> Replay {
+		Replay { frames }
+	}
+
+	pub fn load(path: &str) -> io::Result<Replay> {
+		let json = fs::read_to_string(path)?;
+		let frames = serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		Ok(Replay::new(frames))
+	}
+
+	pub fn save(frames: &[RecordedFrame], path: &str) -> io::Result<()> {
+		let json = serde_json::to_string(frames).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+		fs::write(path, json)
+	}
+
+	/// The consts to replay under. The recorded frames already hold the reveals, fee
+	/// replacements and resubmits, and orphaned frames are skipped, so none are drawn again.
+	pub fn consts(mut consts: Constants) -> Constants {
+		consts.reorg_prob = 0.0;
+		consts.rbf_prob = 0.0;
+		consts.evicted_resubmit_prob = 0.0;
+		consts.commit_reveal = false;
+		consts
+	}
+
+	/// Publishes each frame that wasn't orphaned as the next block, first registering its
+	/// orders with their players. The miner collects every block's gas. Returns the blocks replayed.
+	pub fn run(&self, sim: &Simulation, miner: &mut Miner) -> usize {
+		let mut replayed = 0;
+		for frame in self.frames.iter().filter(|f| !f.orphaned) {
+			for order in frame.orders.iter() {
+				let registered = match order.order_type {
+					OrderType::Enter => sim.house.new_order(order.clone()),
+					OrderType::Update => sim.house.update_player_order(order.clone()),
+					OrderType::Cancel => sim.house.cancel_player_order(order.trader_id.clone(), order.order_id),
+					OrderType::MassCancel => Ok(()),
+				};
+				// The order was mined in the recorded run whether or not its player took it
+				if let Err(e) = registered {
+					warn!("Replay @ block {}: {}", frame.block, e);
+				}
+			}
+			miner.frame = frame.orders.clone();
+			sim.publish_block(miner);
+			replayed += 1;
+		}
+		replayed
+	}
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::simulation::simulation_config::{Distributions, DistReason, DistType};
+	use crate::simulation::event_sim::EventSim;
+	use crate::exchange::MarketType;
+	use crate::utility::seed_task;
+
+	#[test]
+	fn test_replay_reproduces_clearings() {
+		let dists = Distributions::new(vec![
+			(DistReason::AsksCenter, 110.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::BidsCenter, 90.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::MinerFrontRun, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::MinerFrameForm, 50.0, 20.0, 1.0, DistType::Normal),
+			(DistReason::PropagationDelay, 20.0, 5.0, 1.0, DistType::Normal),
+			(DistReason::InvestorGas, 0.0, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorVolume, 0.99, 1.0, 1.0, DistType::Uniform),
+			(DistReason::InvestorEnter, 50.0, 50.0, 1.0, DistType::Poisson),
+			(DistReason::MakerType, 0.0, 4.0, 1.0, DistType::Uniform),
+			(DistReason::MakerInventory, 0.0, 100.0, 1.0, DistType::Uniform),
+			(DistReason::MakerBalance, 50.0, 100.0, 1.0, DistType::Uniform),
+		]);
+		let mut consts = Constants::new(300, 10, 10, 100, 3, MarketType::FBA, 0.0, 0.0, 1, 0.1, 0.5, 5.0, 0.0, 0, 0.5);
+		consts.seed = 11;
+
+		seed_task(consts.seed, "main", 0);
+		let (sim, miner) = Simulation::init_simulation(dists.clone(), consts);
+		let mut engine = EventSim::new(sim, vec![miner]);
+		engine.run();
+		let frames = engine.sim.history.get_frames();
+		assert_eq!(frames.len() as u64, consts.num_blocks + 1);
+
+		// A second run from the recorded frames clears at the same prices
+		seed_task(consts.seed, "main", 0);
+		let (replayed, mut miner) = Simulation::init_simulation(dists, Replay::consts(consts));
+		assert_eq!(Replay::new(frames).run(&replayed, &mut miner), consts.num_blocks as usize + 1);
+		let prices = |sim: &Simulation| sim.history.clearings.lock().unwrap().iter().map(|(r, _)| r.uniform_price).collect::<Vec<_>>();
+		assert_eq!(prices(&replayed), prices(&engine.sim));
+	}
+}
@@ -0,0 +1,72 @@
+use crate::exchange::clearing_house::EquityPoint;
+
+/// The confidence level the end of simulation VaR is reported at
+pub const VAR_CONFIDENCE: f64 = 0.95;
+
+/// Risk metrics computed from a player's equity curve
+/// max_drawdown: f64 -> the largest fall in equity from a previous peak
+/// var: f64 -> the historical value at risk of the equity change between marks
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskMetrics {
+	pub max_drawdown: f64,
+	pub current_drawdown: f64,
+	pub var: f64,
+}
+
+impl RiskMetrics {
+	pub fn new(curve: &[EquityPoint], confidence: f64) -> RiskMetrics {
+		let drawdowns = drawdowns(curve);
+		RiskMetrics {
+			max_drawdown: drawdowns.iter().cloned().fold(0.0, f64::max),
+			current_drawdown: *drawdowns.last().unwrap_or(&0.0),
+			var: historical_var(curve, confidence),
+		}
+	}
+}
+
+/// The rolling drawdown at each point, how far equity is below its running peak
+pub fn drawdowns(curve: &[EquityPoint]) -> Vec<f64> {
+	let mut peak = std::f64::MIN;
+	curve.iter()
+		.map(|p| {
+			peak = peak.max(p.equity);
+			peak - p.equity
+		})
+		.collect()
+}
+
+/// The loss in equity between consecutive marks that is only exceeded (1 - confidence) of
+/// the time. Returns 0 if the curve has too few points or never loses.
+pub fn historical_var(curve: &[EquityPoint], confidence: f64) -> f64 {
+	if curve.len() < 2 {
+		return 0.0;
+	}
+	let mut changes: Vec<f64> = curve.windows(2).map(|w| w[1].equity - w[0].equity).collect();
+	changes.sort_by(|a, b| a.partial_cmp(b).expect("historical_var"));
+	let index = ((1.0 - confidence) * changes.len() as f64).floor() as usize;
+	(-changes[index.min(changes.len() - 1)]).max(0.0)
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn curve(equities: Vec<f64>) -> Vec<EquityPoint> {
+		equities.into_iter().enumerate()
+			.map(|(i, equity)| EquityPoint { block_num: i as u64, balance: 0.0, equity })
+			.collect()
+	}
+
+	#[test]
+	fn test_risk_metrics() {
+		let c = curve(vec![100.0, 120.0, 90.0, 110.0, 80.0, 95.0]);
+		assert_eq!(drawdowns(&c), vec![0.0, 0.0, 30.0, 10.0, 40.0, 25.0]);
+
+		// Changes sorted: -30, -30, 15, 20, 20. The worst fifth is a 30 loss
+		let m = RiskMetrics::new(&c, 0.8);
+		assert_eq!(m, RiskMetrics { max_drawdown: 40.0, current_drawdown: 25.0, var: 30.0 });
+		assert_eq!(historical_var(&curve(vec![1.0, 2.0, 3.0]), 0.95), 0.0);
+		assert_eq!(RiskMetrics::new(&[], VAR_CONFIDENCE).var, 0.0);
+	}
+}
@@ -1,25 +1,65 @@
 use crate::simulation::simulation_config::{Constants, Distributions, DistReason};
 use crate::controller::Task;
 use crate::exchange::clearing_house::ClearingHouse;
-use crate::order::order::{Order, TradeType, ExchangeType, OrderType};
+use crate::order::order::{Order, TradeType, ExchangeType, OrderType, Venue};
 use crate::order::order_book::Book;
+use crate::order::dark_book::DarkBook;
 use crate::blockchain::mem_pool::MemPool;
-use crate::players::{TraderT};
+use crate::blockchain::mining::HashPower;
+use crate::blockchain::bundle::select_bundles;
+use crate::blockchain::block_gas::BlockGas;
+use crate::blockchain::gossip::GossipModel;
+use crate::blockchain::reorg::BlockCheckpoint;
+use crate::blockchain::chain::{Block, Chain};
+use crate::blockchain::mempool_processor::MemPoolProcessor;
+use crate::blockchain::mempool_events::PoolWatch;
+use crate::players::{TraderT, sample_risk_aversion, risk_scale};
 use crate::players::miner::Miner;
+use crate::players::miner_strategy::{MinerStrategy, MinerStrategyT};
 use crate::players::investor::Investor;
-use crate::players::maker::{Maker, MakerT};
+use crate::players::maker::{Maker, MakerT, MakerParams};
+use crate::players::strategy::MarketData;
+use crate::players::zero_intel::ZiValuation;
+use crate::players::momentum::MomentumTrader;
+use crate::players::mean_reversion::MeanReversionTrader;
+use crate::players::arbitrageur::Arbitrageur;
+use crate::players::informed::InformedTrader;
+use crate::players::noise::{NoiseTrader, NoiseEvent};
+use crate::players::spoofer::{Spoofer, SpoofEvent, SpoofAction};
+use crate::players::sniper::{GasPolicy, Snipe};
+use crate::players::bridge::{Bridge, BridgeObservation};
+use crate::players::script::{OrderScript, RhaiScript, ScriptSpec};
+use crate::players::plugin::WasmPlugin;
+use crate::players::twap::Twap;
+use crate::players::vwap::Vwap;
+use crate::players::searcher::Searcher;
+use crate::players::adaptive::{Adaptive, Arm};
+use crate::simulation::fundamental::FundamentalValue;
+use crate::simulation::population::Population;
+use crate::simulation::control::{SimControl, SimulationHandle};
+use crate::simulation::builder::SimulationBuilder;
+use crate::simulation::experiments::PerformanceResults;
 use crate::exchange::MarketType;
-use crate::blockchain::order_processor::OrderProcessor;
-use crate::utility::{gen_trader_id, get_time};
-use crate::simulation::simulation_history::History;
-
-use std::collections::HashMap;
+use crate::blockchain::order_processor::{OrderProcessor, LatencyModel};
+use crate::utility::{gen_trader_id, gen_order_id, get_time, sim_rng, seed_task, scaled_millis};
+use crate::simulation::simulation_history::{History, UpdateReason};
+use crate::exchange::market_phase::PhaseState;
+use crate::exchange::circuit_breaker::CircuitBreaker;
+use crate::exchange::amm::AmmPool;
+use crate::exchange::dutch::DutchAuction;
+use crate::exchange::fees::TaxBasis;
+use crate::error::Error;
+use crate::simulation::risk::VAR_CONFIDENCE;
+use crate::exchange::exchange_logic::{Auction, AuctionRules, IndicativeData, OrderEvent, Fill};
+
+use std::collections::{HashMap, BTreeMap};
 use std::sync::Mutex;
 use std::sync::Arc;
 use std::{time, thread};
 use std::thread::JoinHandle;
 
 use log::{Level};
+use rand::seq::SliceRandom;
 
 
 pub struct BlockNum {pub num: Mutex<u64>}
@@ -41,197 +81,1803 @@ impl BlockNum {
 }
 
 
+/// The lit bid and ask Books of each symbol
+pub type SymbolBooks = HashMap<String, (Arc<Book>, Arc<Book>)>;
+
+#[derive(Clone)]
 pub struct Simulation {
 	pub dists: Distributions,
 	pub consts: Constants,
 	pub house: Arc<ClearingHouse>,
 	pub mempool: Arc<MemPool>,
-	pub bids_book: Arc<Book>,
-	pub asks_book: Arc<Book>,
+	pub books: Arc<SymbolBooks>,
 	pub history: Arc<History>,
 	pub block_num: Arc<BlockNum>,
+	pub market_phase: Arc<PhaseState>,
+	pub circuit_breaker: Arc<CircuitBreaker>,
+	pub dark_books: Arc<HashMap<String, Arc<DarkBook>>>,
+	pub amm_pools: Arc<HashMap<String, Arc<AmmPool>>>,
+	pub dutch_auctions: Arc<HashMap<String, Arc<DutchAuction>>>,
+	pub fundamental: Arc<FundamentalValue>,
+	pub chain: Arc<Chain>,
+	pub control: Arc<SimControl>,
+	pub checkpoint_path: Option<String>,
 }
 
 
 
 impl Simulation {
 	pub fn new(dists: Distributions, consts: Constants, house: ClearingHouse, 
-			   mempool: MemPool, bids_book: Book, asks_book: Book, history: History) -> Simulation {
+			   mempool: MemPool, history: History) -> Simulation {
+		let market_phase = PhaseState::new(&consts);
+		let fundamental = FundamentalValue::new(dists.fundamental_value(), &consts);
+
+		// Every symbol gets its own lit books, dark book, AMM pool and Dutch auction
+		let mut books = SymbolBooks::new();
+		let mut dark_books = HashMap::new();
+		let mut amm_pools = HashMap::new();
+		let mut dutch_auctions = HashMap::new();
+		for symbol in consts.symbols() {
+			books.insert(symbol.clone(), (Arc::new(Book::new(TradeType::Bid)), Arc::new(Book::new(TradeType::Ask))));
+			dark_books.insert(symbol.clone(), Arc::new(DarkBook::new()));
+			amm_pools.insert(symbol.clone(), Arc::new(AmmPool::new(consts.amm_reserve_shares, consts.amm_reserve_cash, consts.amm_fee)));
+			dutch_auctions.insert(symbol, Arc::new(DutchAuction::new(consts.dutch_start_price, consts.dutch_decrement, 
+				consts.dutch_floor_price, consts.dutch_supply)));
+		}
+
 		Simulation {
 			dists: dists,
 			consts: consts,
 			house: Arc::new(house),
 			mempool: Arc::new(mempool),
-			bids_book: Arc::new(bids_book),
-			asks_book: Arc::new(asks_book),
+			books: Arc::new(books),
 			history: Arc::new(history),
 			block_num: Arc::new(BlockNum::new()),
+			market_phase: Arc::new(market_phase),
+			circuit_breaker: Arc::new(CircuitBreaker::new()),
+			dark_books: Arc::new(dark_books),
+			amm_pools: Arc::new(amm_pools),
+			dutch_auctions: Arc::new(dutch_auctions),
+			fundamental: Arc::new(fundamental),
+			chain: Arc::new(Chain::new()),
+			control: Arc::new(SimControl::new()),
+			checkpoint_path: None,
 		}
 	}
 
-	pub fn init_simulation(dists: Distributions, consts: Constants) -> (Simulation, Miner) {
-		// Initialize the state for the simulation
-		let house = ClearingHouse::new();
-		let bids_book = Book::new(TradeType::Bid);
-		let asks_book = Book::new(TradeType::Ask);
-		let mempool = MemPool::new();
-		let history = History::new(consts.market_type);
-
-		// Initialize and register the miner to CH
-		let ch_miner = Miner::new(gen_trader_id(TraderT::Miner));
-		let miner_id = ch_miner.trader_id.clone();
-		house.reg_miner(ch_miner);
-
-		// Initialize copy of miner for the miner task
-		let mut miner = Miner::new(gen_trader_id(TraderT::Miner));
-		miner.trader_id = miner_id;
+	/// Saves a checkpoint to path after publishing the consts' checkpoint_block
+	pub fn with_checkpoint(mut self, path: Option<String>) -> Simulation {
+		self.checkpoint_path = path;
+		self
+	}
 
-		// Initialize and register the Investors
-		let invs = Simulation::setup_investors(&dists, &consts);
-		house.reg_n_investors(invs);
+	/// A handle to pause, resume and single-step the simulation's miner, investor and maker tasks
+	pub fn handle(&self) -> SimulationHandle {
+		SimulationHandle {
+			control: Arc::clone(&self.control),
+			block_num: Arc::clone(&self.block_num),
+		}
+	}
 
-		// Initialize and register the Makers
-		let mkrs = Simulation::setup_makers(&dists, &consts);
-		house.reg_n_makers(mkrs);
-		
-		(Simulation::new(dists, consts, house, mempool, bids_book, asks_book, history), miner)
+	pub fn init_simulation(dists: Distributions, consts: Constants) -> (Simulation, Miner) {
+		SimulationBuilder::new().dists(dists).consts(consts).build()
 	}
 
 	/// Initializes Investor players. Randomly samples the maker's initial balance and inventory
 	/// using the distribution configs. Number of makers saved in consts.
-	pub fn setup_investors(_dists: &Distributions, consts: &Constants) -> Vec<Investor> {
+	pub fn setup_investors(dists: &Distributions, consts: &Constants) -> Vec<Investor> {
 		let mut invs = Vec::new();
 		for _ in 1..consts.num_investors {
-			invs.push(Investor::new(gen_trader_id(TraderT::Investor)));
+			invs.push(Investor::new(gen_trader_id(TraderT::Investor)).with_risk_aversion(sample_risk_aversion(dists)));
 		}
 		invs
 	}
 
 	/// Initializes Maker players. Randomly samples the maker's initial balance and inventory
 	/// using the distribution configs. Number of makers saved in consts.
-	pub fn setup_makers(_dists: &Distributions, consts: &Constants) -> Vec<Maker> {
+	pub fn setup_makers(dists: &Distributions, consts: &Constants) -> Vec<Maker> {
 		let mut mkrs = Vec::new();
 		for _ in 1..consts.num_makers {
 			// random id
 			let id = gen_trader_id(TraderT::Maker);
-			// random behavioral type for strategy
-			let maker_type = Maker::gen_rand_type();
+			// random behavioral type for strategy, the first num_skew_makers quote as InventorySkew
+			// and the next num_gm_makers as AdverseSelection
+			let n = mkrs.len() as u64;
+			let maker_type = match n {
+				n if n < consts.num_skew_makers => MakerT::InventorySkew,
+				n if n < consts.num_skew_makers + consts.num_gm_makers => MakerT::AdverseSelection,
+				_ => Maker::gen_rand_type(),
+			};
 			
-			mkrs.push(Maker::new(id, maker_type));
+			// each maker samples its own quoting parameters and risk aversion
+			mkrs.push(Maker::new(id, maker_type).with_params(MakerParams::sample(dists)).with_risk_aversion(sample_risk_aversion(dists)));
 		}
 		mkrs
 	}
 
-	/// A repeating task. Will randomly select an Investor from the ClearingHouse,
-	/// generate a bid/ask order priced via bid/ask distributions, send the order to 
-	/// the mempool, and then sleep until the next investor_arrival time.
-	pub fn investor_task(dists: Distributions, house: Arc<ClearingHouse>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, consts: Constants) -> JoinHandle<()> {
-		thread::spawn(move || {       
+	/// Loads each spec's rhai script or WASM plugin and registers its makers or investors to the
+	/// ClearingHouse, all trading from the one script. Each maker gets a random maker type for its
+	/// gas and profit tracking.
+	pub fn reg_scripted_players(&self, specs: &[ScriptSpec]) -> std::io::Result<()> {
+		for spec in specs {
+			let script: Arc<dyn OrderScript> = match spec.path.ends_with(".wasm") || spec.path.ends_with(".wat") {
+				true => Arc::new(WasmPlugin::new(&spec.path)?),
+				false => Arc::new(RhaiScript::new(&spec.path)?),
+			};
+			for _ in 0..spec.num_players {
+				match spec.player_type {
+					TraderT::Maker => self.house.reg_maker(Maker::new_scripted(gen_trader_id(TraderT::Maker), Maker::gen_rand_type(), Arc::clone(&script))),
+					TraderT::Investor => self.house.reg_investor(Investor::new_scripted(gen_trader_id(TraderT::Investor), Arc::clone(&script))),
+					player_type => return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput,
+						format!("{}: only makers and investors can be scripted, not {:?}", spec.path, player_type))),
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Creates a second market of market_type fed by the same fundamental value and players.
+	/// The markets share the ClearingHouse, but each has its own books, mempool, history and miner.
+	pub fn linked(&self, market_type: MarketType) -> (Simulation, Miner) {
+		let mut consts = self.consts.clone();
+		consts.market_type = market_type;
+		consts.second_market_type = None;
+
+		// Register the linked market's miner to the shared CH
+		let ch_miner = Miner::new(gen_trader_id(TraderT::Miner));
+		let miner_id = ch_miner.trader_id.clone();
+		self.house.reg_miner(ch_miner);
+
+		// Initialize copy of miner for the linked miner task
+		let mut miner = Miner::new(gen_trader_id(TraderT::Miner));
+		miner.trader_id = miner_id;
+
+		let mut linked = Simulation::new(self.dists.clone(), consts, ClearingHouse::new(), MemPool::new().with_latency(LatencyModel::new(&self.dists)).with_capacity(consts.mempool_capacity, consts.mempool_eviction).with_gossip(GossipModel::new(&consts, &self.dists)).with_regions(consts.num_regions).with_commit_reveal(consts.commit_reveal).with_encryption(consts.encrypted_mempool, consts.encryption_leak_prob).with_gas_oracle(consts.gas_oracle_window, consts.gas_oracle_percentile).with_rate_limit(consts.max_pending_per_trader, consts.max_orders_per_block, consts.spam_gas_escalation), History::new(market_type));
+		linked.house = Arc::clone(&self.house);
+		linked.fundamental = Arc::clone(&self.fundamental);
+		linked.control = Arc::clone(&self.control);
+		(linked, miner)
+	}
+
+	/// Registers an arbitrageur to the ClearingHouse and returns its trader id
+	pub fn reg_arbitrageur(&self) -> String {
+		let arb = Investor::new_arbitrageur(gen_trader_id(TraderT::Arbitrageur));
+		let arb_id = arb.trader_id.clone();
+		self.house.reg_investor(arb);
+		arb_id
+	}
+
+	/// Registers num_zi_traders zero-intelligence traders to the ClearingHouse and returns each one's valuation
+	pub fn reg_zi_traders(&self) -> HashMap<String, ZiValuation> {
+		let mut valuations = HashMap::new();
+		for _ in 0..self.consts.num_zi_traders {
+			let zi = Investor::new_zero_intel(gen_trader_id(TraderT::ZeroIntel));
+			valuations.insert(zi.trader_id.clone(), ZiValuation::gen(&self.dists, self.consts.zi_units));
+			self.house.reg_investor(zi);
+		}
+		valuations
+	}
+
+	/// Registers num_momentum_traders momentum traders to the ClearingHouse
+	pub fn reg_momentum_traders(&self) {
+		for _ in 0..self.consts.num_momentum_traders {
+			self.house.reg_investor(Investor::new_momentum(gen_trader_id(TraderT::Momentum)));
+		}
+	}
+
+	/// Registers num_mean_reversion_traders mean reversion traders to the ClearingHouse
+	pub fn reg_mean_reversion_traders(&self) {
+		for _ in 0..self.consts.num_mean_reversion_traders {
+			self.house.reg_investor(Investor::new_mean_reversion(gen_trader_id(TraderT::MeanReversion)));
+		}
+	}
+
+	/// Registers num_informed_traders informed traders to the ClearingHouse
+	pub fn reg_informed_traders(&self) {
+		for _ in 0..self.consts.num_informed_traders {
+			self.house.reg_investor(Investor::new_informed(gen_trader_id(TraderT::Informed)));
+		}
+	}
+
+	/// Registers num_noise_traders noise traders to the ClearingHouse
+	pub fn reg_noise_traders(&self) {
+		for _ in 0..self.consts.num_noise_traders {
+			self.house.reg_investor(Investor::new_noise(gen_trader_id(TraderT::Noise)));
+		}
+	}
+
+	/// Registers num_spoofers spoofers to the ClearingHouse
+	pub fn reg_spoofers(&self) {
+		for _ in 0..self.consts.num_spoofers {
+			self.house.reg_investor(Investor::new_spoofer(gen_trader_id(TraderT::Spoofer)));
+		}
+	}
+
+	/// Registers num_snipers snipers to the ClearingHouse and returns each one's gas policy
+	pub fn reg_snipers(&self) -> HashMap<String, GasPolicy> {
+		let mut policies = HashMap::new();
+		for _ in 0..self.consts.num_snipers {
+			let sniper = Investor::new_sniper(gen_trader_id(TraderT::Sniper));
+			policies.insert(sniper.trader_id.clone(), GasPolicy::new(&self.consts));
+			self.house.reg_investor(sniper);
+		}
+		policies
+	}
+
+	/// Registers a single learner to the ClearingHouse, returning its id
+	pub fn reg_learner(&self) -> String {
+		let lrn = Investor::new_learner(gen_trader_id(TraderT::Learner));
+		let lrn_id = lrn.trader_id.clone();
+		self.house.reg_investor(lrn);
+		lrn_id
+	}
+
+	/// Registers the bridge player to the ClearingHouse, returning its id
+	pub fn reg_bridge(&self) -> String {
+		let brg = Investor::new_bridge(gen_trader_id(TraderT::Bridge));
+		let brg_id = brg.trader_id.clone();
+		self.house.reg_investor(brg);
+		brg_id
+	}
+
+	/// Registers num_searchers searchers, who bid for their bundles to be mined, to the ClearingHouse
+	pub fn reg_searchers(&self) {
+		for _ in 0..self.consts.num_searchers {
+			self.house.reg_investor(Investor::new_searcher(gen_trader_id(TraderT::Searcher)));
+		}
+	}
+
+	/// Registers num_vwap_agents VWAP execution agents to the ClearingHouse
+	pub fn reg_vwap_agents(&self) {
+		for _ in 0..self.consts.num_vwap_agents {
+			self.house.reg_investor(Investor::new_vwap(gen_trader_id(TraderT::Vwap)));
+		}
+	}
+
+	/// Registers num_miners - 1 more miners to compete with the simulation's miner for blocks.
+	/// Returns the miner with its rivals, each a copy of the one in the ClearingHouse.
+	pub fn reg_rival_miners(&self, miner: Miner) -> Vec<Miner> {
+		let mut miners = vec![miner];
+		for _ in 1..self.consts.num_miners.max(1) {
+			let id = gen_trader_id(TraderT::Miner);
+			self.house.reg_miner(Miner::new(id.clone()));
+			miners.push(Miner::new(id));
+		}
+		miners
+	}
+
+	/// The number of sandwiches the miners published and their round-trip profit, any shares
+	/// left over marked at the last fill price
+	pub fn calc_sandwich_profit(&self) -> (usize, f64) {
+		let sandwiches = self.history.get_sandwiches();
+		let fills = self.history.get_fills();
+		let mark = fills.last().map(|f| f.price).unwrap_or(0.0);
+		(sandwiches.len(), sandwiches.iter().map(|s| s.pnl(&fills, mark)).sum())
+	}
+
+	/// The number of orders the miner censored, how many distinct traders sent them, and the
+	/// mean number of frames each was left out of
+	pub fn calc_censor_stats(&self) -> (usize, usize, f64) {
+		let censored = self.history.get_censored();
+		let mut traders: Vec<&str> = censored.iter().map(|r| r.trader_id.as_str()).collect();
+		traders.sort();
+		traders.dedup();
+		let mean_blocks = match censored.len() {
+			0 => 0.0,
+			n => censored.iter().map(|r| r.blocks as f64).sum::<f64>() / n as f64,
+		};
+		(censored.len(), traders.len(), mean_blocks)
+	}
+
+	/// Each miner's id, blocks won and profit as csv rows
+	pub fn calc_miner_results(&self, wins: &HashMap<String, u64>, init_player_s: &HashMap<String, (f64, f64)>) -> Vec<String> {
+		self.house.snapshot().iter()
+			.filter(|p| p.player_type == TraderT::Miner)
+			.filter_map(|p| init_player_s.get(&p.trader_id).map(|(init_bal, _inv)| (p, p.balance - init_bal)))
+			.map(|(p, profit)| format!("{},{},{},", p.trader_id, wins.get(&p.trader_id).cloned().unwrap_or(0), profit))
+			.collect()
+	}
+
+	/// For each strategy the miner used, the number of blocks built with it and their mean
+	/// traded volume as csv rows
+	pub fn calc_behavior_results(&self) -> Vec<String> {
+		let volumes: HashMap<u64, f64> = self.history.block_volumes().into_iter().collect();
+		let mut by_behavior: BTreeMap<String, (u64, f64)> = BTreeMap::new();
+		for (block, behavior) in self.history.get_miner_behaviors() {
+			let entry = by_behavior.entry(format!("{:?}", behavior)).or_insert((0, 0.0));
+			entry.0 += 1;
+			entry.1 += volumes.get(&block).cloned().unwrap_or(0.0);
+		}
+		by_behavior.into_iter()
+			.map(|(behavior, (blocks, volume))| format!("{},{},{},", behavior, blocks, volume / blocks as f64))
+			.collect()
+	}
+
+	/// Registers num_twap_agents TWAP execution agents to the ClearingHouse
+	pub fn reg_twap_agents(&self) {
+		for _ in 0..self.consts.num_twap_agents {
+			self.house.reg_investor(Investor::new_twap(gen_trader_id(TraderT::Twap)));
+		}
+	}
+
+	/// The exchange type and (p_low, p_high) of an order at price for the market type.
+	/// Flow orders have a constant offset between p_low and p_high.
+	pub fn order_prices(m_t: MarketType, trade_type: &TradeType, price: f64, consts: &Constants) -> (ExchangeType, f64, f64) {
+		match m_t {
+			MarketType::CDA|MarketType::FBA|MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => (ExchangeType::LimitOrder, price, price),
+			MarketType::KLF => {
+				match trade_type {
+					TradeType::Ask => (ExchangeType::FlowOrder, price, price + consts.flow_order_offset),
+					TradeType::Bid => (ExchangeType::FlowOrder, price - consts.flow_order_offset, price),
+				}
+			}
+		}
+	}
+
+	/// Acknowledges or rejects an order at admission. The event is routed back to the
+	/// trader through the ClearingHouse and recorded in the History.
+	pub fn admission_event(house: &ClearingHouse, history: &History, order: &Order, admitted: &Result<(), Error>, block_num: u64) {
+		let event = match admitted {
+			Ok(()) => OrderEvent::ack(order, block_num),
+			Err(e) => OrderEvent::reject(order, e.reason(), block_num),
+		};
+		house.route_order_event(event.clone());
+		history.save_order_event(event);
+	}
+
+	/// Fits the order to the trader's budget and admits it if it's priced within the band around the
+	/// previous clearing price and the ClearingHouse registers it to the trader, who is sent an ack
+	/// or reject either way. Returns the admitted order.
+	pub fn admit_order(house: &ClearingHouse, history: &History, order: Order, consts: &Constants, block_num: u64) -> Option<Order> {
+		let order = house.fit_to_budget(order);
+		let admitted = history.check_price_band(&order, consts.price_band_pct).and_then(|_| house.new_order(order.clone()));
+		Simulation::admission_event(house, history, &order, &admitted, block_num);
+		admitted.ok().map(|()| order)
+	}
+
+	/// Adds the order to the simulation's history and sends it to the MemPool
+	pub fn send_order(mempool: &Arc<MemPool>, history: &History, order: Order) {
+		history.mempool_order(order.clone());
+		OrderProcessor::conc_recv_order(order, Arc::clone(mempool)).join().expect("Failed to send order");
+	}
+
+	/// Admits the order and sends it to the MemPool, returning whether it was admitted
+	pub fn submit_order(house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, order: Order, consts: &Constants, block_num: u64) -> bool {
+		match Simulation::admit_order(house, history, order, consts, block_num) {
+			Some(order) => {
+				Simulation::send_order(mempool, history, order);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Pulls the trader's resting orders, sending their cancels to the MemPool
+	pub fn pull_orders(house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, trader_id: &str) {
+		if let Ok(cancel_orders) = house.cancel_all_orders(trader_id.to_string(), None) {
+			for order in cancel_orders {
+				Simulation::send_order(mempool, history, order);
+			}
+		}
+	}
+
+	/// A repeating task for the arbitrageur. Whenever the best bid of one market's first symbol is
+	/// above the best ask of the other's by more than the fees and gas, buys on the cheap market and
+	/// sells on the expensive one. An AMM market is quoted from its pool. Each leg must be priced within
+	/// its market's band, is tagged with the market it was sent to, and is cancelled there if it
+	/// hasn't filled by the next run.
+	pub fn arbitrage_task(arb_id: String, a: &Simulation, b: &Simulation) -> Task {
+		let house = Arc::clone(&a.house);
+		let dists = a.dists.clone();
+		let symbol = a.consts.symbols()[0].clone();
+		let markets = vec![
+			(Arc::clone(&a.books), Arc::clone(&a.amm_pools), Arc::clone(&a.mempool), Arc::clone(&a.history), a.consts),
+			(Arc::clone(&b.books), Arc::clone(&b.amm_pools), Arc::clone(&b.mempool), Arc::clone(&b.history), b.consts),
+		];
+		let block_num = Arc::clone(&a.block_num);
+		let interval = a.consts.batch_interval;
+		let arb = Arbitrageur::new(&a.consts);
+		// The market each leg was sent to, by order id
+		let mut legs = HashMap::<u64, usize>::new();
+		Task::seeded_rpt_task("arbitrage", a.consts.seed, SimControl::stopper(&a.control), move || {
+			// Cancel any legs that haven't filled yet
+			if house.get_player_order_count(&arb_id).expect("get_player_order_count") != 0 {
+				if let Ok(cancel_orders) = house.cancel_all_orders(arb_id.clone(), None) {
+					for order in cancel_orders {
+						let (_books, _pools, mempool, history, _consts) = &markets[*legs.get(&order.order_id).unwrap_or(&0)];
+						Simulation::send_order(mempool, history, order);
+					}
+				}
+				legs.clear();
+				return;
+			}
+
+			let quotes = |i: usize| {
+				let (books, pools, _mempool, _history, consts) = &markets[i];
+				match consts.market_type {
+					MarketType::AMM => Arbitrageur::amm_quotes(&pools[&symbol]),
+					_ => {
+						let (bids, asks) = &books[&symbol];
+						(bids.peek_best_price(), asks.peek_best_price())
+					},
+				}
+			};
+
+			let gas = dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas");
+			if let Some((buy, buy_price, sell, sell_price)) = arb.find_trade([quotes(0), quotes(1)], gas) {
+				for (i, trade_type, price, other_price) in vec![(buy, TradeType::Bid, buy_price, sell_price), (sell, TradeType::Ask, sell_price, buy_price)] {
+					let (_books, _pools, mempool, history, consts) = &markets[i];
+					// The pool fills along its curve, so the AMM leg is limited at the other leg's price
+					let price = match consts.market_type {
+						MarketType::AMM => other_price,
+						_ => price,
+					};
+					let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, consts);
+					let order = Order::new(arb_id.clone(), 
+										   OrderType::Enter,
+										   trade_type,
+										   ex_type,
+										   p_l,
+										   p_h,
+										   price,
+										   arb.quantity,
+										   arb.quantity,
+										   gas
+					).with_symbol(&symbol);
+
+					// Admit the leg within that market's price band, then send it to that market's MemPool
+					if let Some(order) = Simulation::admit_order(&house, history, order, consts, block_num.read_count()) {
+						legs.insert(order.order_id, i);
+						Simulation::send_order(mempool, history, order);
+					}
+				}
+			}
+		}, interval)
+	}
+
+	/// A repeating task for the zero-intelligence traders. Each run a random ZI trader replaces its
+	/// resting order with a new one for the shares it has left, at a random price within its valuation.
+	pub fn zero_intel_task(valuations: HashMap<String, ZiValuation>, sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		Task::seeded_rpt_task("zero_intel", consts.seed, SimControl::stopper(&sim.control), move || {
+			let trader_id = match house.query().of_type(TraderT::ZeroIntel).random() {
+				Some(id) => id,
+				None => return,
+			};
+			let zi = &valuations[&trader_id];
+
+			// Pull the trader's resting order before quoting again
+			Simulation::pull_orders(&house, &mempool, &history, &trader_id);
+
+			// Stop once the trader has bought or sold all of its units
+			let inventory = house.with_player(&trader_id, |p| p.get_inv(&symbol)).unwrap_or(0.0);
+			let quantity = zi.remaining(inventory);
+			if quantity <= 0.0 {
+				return;
+			}
+
+			let price = zi.rand_price(consts.zi_max_price);
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &zi.trade_type, price, &consts);
+			let order = Order::new(trader_id.clone(),
+								   OrderType::Enter,
+								   zi.trade_type.clone(),
+								   ex_type,
+								   p_l,
+								   p_h,
+								   price,
+								   quantity,
+								   quantity,
+								   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+			).with_symbol(&symbol);
+
+			Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+		}, consts.batch_interval)
+	}
+
+	/// A repeating task for the momentum traders. Each run a random momentum trader replaces its
+	/// resting order with one in the direction of the first symbol's trend, priced at the best
+	/// opposing quote or the last clearing price if the book is empty.
+	pub fn momentum_task(sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let books = Arc::clone(&sim.books);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let trader = MomentumTrader::new(&consts);
+		Task::seeded_rpt_task("momentum", consts.seed, SimControl::stopper(&sim.control), move || {
+			let trader_id = match house.query().of_type(TraderT::Momentum).random() {
+				Some(id) => id,
+				None => return,
+			};
+
+			// Pull the trader's resting order before trading again
+			Simulation::pull_orders(&house, &mempool, &history, &trader_id);
+
+			let clearings = history.clearing_history(&symbol);
+			let (trade_type, quantity) = match trader.decide(&clearings) {
+				Some(decision) => decision,
+				None => return,
+			};
+
+			let (bids, asks) = &books[&symbol];
+			let best = match trade_type {
+				TradeType::Bid => asks.peek_best_price(),
+				TradeType::Ask => bids.peek_best_price(),
+			};
+			let price = best.unwrap_or(clearings[clearings.len() - 1].0);
+
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, &consts);
+			let order = Order::new(trader_id.clone(),
+								   OrderType::Enter,
+								   trade_type,
+								   ex_type,
+								   p_l,
+								   p_h,
+								   price,
+								   quantity,
+								   quantity,
+								   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+			).with_symbol(&symbol);
+
+			Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+		}, consts.batch_interval)
+	}
+
+	/// The (price, quantity) of each fill against trader_id's orders on the trade_type side
+	pub fn execution_fills(history: &History, fills: &[Fill], trader_id: &str, trade_type: &TradeType) -> Vec<(f64, f64)> {
+		fills.iter()
+			.filter(|f| {
+				let order_id = match trade_type {
+					TradeType::Bid => f.bid_order_id,
+					TradeType::Ask => f.ask_order_id,
+				};
+				history.find_orig_order(order_id).map_or(false, |(o, _time)| o.trader_id == trader_id)
+			})
+			.map(|f| (f.price, f.quantity))
+			.collect()
+	}
+
+	/// Pulls an execution agent's resting child order and, if quantity is positive, sends a new one
+	/// priced at the best opposing quote so it takes liquidity, or the last clearing price on an empty book
+	fn send_child_order(&self, trader_id: &str, trade_type: &TradeType, quantity: f64, block: u64) {
+		Simulation::pull_orders(&self.house, &self.mempool, &self.history, trader_id);
+		if quantity <= 0.0 {
+			return;
+		}
+
+		let symbol = self.consts.symbols()[0].clone();
+		let (bids, asks) = &self.books[&symbol];
+		let best = match trade_type {
+			TradeType::Bid => asks.peek_best_price(),
+			TradeType::Ask => bids.peek_best_price(),
+		};
+		let price = match best.or_else(|| self.history.get_reference_price(&symbol)) {
+			Some(price) => price,
+			None => return,
+		};
+
+		let (ex_type, p_l, p_h) = Simulation::order_prices(self.consts.market_type, trade_type, price, &self.consts);
+		let order = Order::new(trader_id.to_string(),
+							   OrderType::Enter,
+							   trade_type.clone(),
+							   ex_type,
+							   p_l,
+							   p_h,
+							   price,
+							   quantity,
+							   quantity,
+							   self.dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+		).with_symbol(&symbol);
+
+		Simulation::submit_order(&self.house, &self.mempool, &self.history, order, &self.consts, block);
+	}
+
+	/// A repeating task for the TWAP execution agents. Once a block, each agent replaces its resting
+	/// child order with one for whatever it is short of its schedule.
+	pub fn twap_task(sim: &Simulation) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let twap = Twap::new(&sim.consts);
+		let mut last_block = None;
+		Task::seeded_rpt_task("twap", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			let current_block = sim.block_num.read_count();
+			if last_block == Some(current_block) {
+				return;
+			}
+			last_block = Some(current_block);
+
+			let fills = sim.history.get_fills();
+			for trader_id in sim.house.query().of_type(TraderT::Twap).ids() {
+				let quantity = match twap.is_active(current_block) {
+					true => {
+						let filled: f64 = Simulation::execution_fills(&sim.history, &fills, &trader_id, &twap.trade_type).iter().map(|(_p, q)| q).sum();
+						twap.child_qty(current_block, filled)
+					},
+					false => 0.0,
+				};
+				sim.send_child_order(&trader_id, &twap.trade_type, quantity, current_block);
+			}
+		}, interval)
+	}
+
+	/// A repeating task for the searchers. Once a block, each searcher bids for a bundle
+	/// sandwiching the largest marketable order waiting in the mempool. Its own legs are
+	/// registered to the ClearingHouse and withdrawn if the miner leaves the bundle out.
+	pub fn searcher_task(sim: &Simulation) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let searcher = Searcher::new(&sim.consts);
+		let mut last_block = None;
+		let watch = Mutex::new(sim.mempool.subscribe());
+		Task::seeded_rpt_task("searcher", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			let mut watch = watch.lock().expect("Error locking searcher watch");
+			watch.update();
+			let current_block = sim.block_num.read_count();
+			if last_block == Some(current_block) {
+				return;
+			}
+			last_block = Some(current_block);
+
+			let pending: Vec<Order> = watch.pending().filter(|o| !o.encrypted).cloned().collect();
+			let (best_bid_price, best_ask_price) = sim.history.get_best_prices();
+			let victim = match searcher.target(&pending, best_bid_price, best_ask_price) {
+				Some(victim) => victim,
+				None => return,
+			};
+			for trader_id in sim.house.query().of_type(TraderT::Searcher).shuffled() {
+				let bundle = searcher.bundle(&trader_id, &victim, best_bid_price, best_ask_price);
+				let legs: Vec<Order> = bundle.orders.iter().filter(|o| o.trader_id == trader_id).cloned().collect();
+				let admitted: std::result::Result<Vec<()>, Error> = legs.iter().map(|leg| sim.house.new_order(leg.clone())).collect();
+				match admitted {
+					Ok(_) => {
+						println!("Searcher {} bid {} for a bundle around {}", trader_id, bundle.payment, victim.order_id);
+						sim.mempool.submit_bundle(bundle);
+					},
+					Err(e) => {
+						println!("Searcher {} couldn't register its bundle: {:?}", trader_id, e);
+						for leg in legs {
+							let _ = sim.house.cancel_player_order(trader_id.clone(), leg.order_id);
+						}
+					},
+				}
+			}
+		}, interval)
+	}
+
+	/// Moves the best paying bundles sent to the miner to the front of its frame, pulling their
+	/// victims out of the frame or the mempool. Orders pushed past the block gas limit wait for the
+	/// next block. Each included bundle's searcher pays the miner, the rest are withdrawn.
+	pub fn include_bundles(&self, miner: &mut Miner) {
+		let bundles = self.mempool.take_bundles();
+		if bundles.is_empty() {
+			return;
+		}
+		let submitted = bundles.len();
+		let (included, dropped) = {
+			let (frame, mempool) = (&miner.frame, &self.mempool);
+			select_bundles(bundles, &BlockGas::new(&self.consts), |id| frame.iter().any(|o| o.order_id == id) || mempool.contains(id))
+		};
+		for bundle in dropped {
+			for order in bundle.orders.iter().filter(|o| o.trader_id == bundle.searcher_id) {
+				let _ = self.house.cancel_player_order(order.trader_id.clone(), order.order_id);
+			}
+		}
+
+		let bundled: Vec<u64> = included.iter().flat_map(|b| b.pending_ids()).collect();
+		miner.frame.retain(|o| !bundled.contains(&o.order_id));
+		self.mempool.remove_ids(&bundled);
+		let mut frame: Vec<Order> = included.iter().flat_map(|b| b.orders.iter().cloned()).collect();
+		frame.extend(miner.frame.drain(..));
+		let gas = BlockGas::new(&self.consts);
+		while gas.total(&frame) > gas.limit {
+			self.mempool.add(frame.pop().expect("include_bundles"));
+		}
+		miner.frame = frame;
+
+		let mut paid = 0.0;
+		for bundle in included.iter() {
+			for order in bundle.orders.iter().filter(|o| o.trader_id == bundle.searcher_id) {
+				self.history.mempool_order(order.clone());
+			}
+			match self.house.transfer(&bundle.searcher_id, &miner.trader_id, &self.consts.symbols()[0], bundle.payment, 0.0, UpdateReason::Bundle) {
+				Ok(()) => paid += bundle.payment,
+				Err(e) => println!("Couldn't pay for bundle: {:?}", e),
+			}
+		}
+		self.history.save_bundle_round(submitted, included.len(), paid);
+	}
+
+	/// Puts the private orders sent to the miner at the front of the frame. Private orders past
+	/// the block gas limit wait for the next block, public ones go back to the mempool. While
+	/// trading is halted only the private cancels are mined, the rest wait for trading to resume.
+	/// Each private order mined pays the miner private_flow_fee.
+	pub fn include_private(&self, miner: &mut Miner) {
+		let private = self.mempool.take_private();
+		if private.is_empty() {
+			return;
+		}
+		let halted = self.circuit_breaker.is_halted(self.block_num.read_count());
+		let gas = BlockGas::new(&self.consts);
+		let mut used = 0.0;
+		let mut frame = Vec::new();
+		for order in private {
+			let held = halted && match order.order_type {
+				OrderType::Cancel | OrderType::MassCancel => false,
+				_ => true,
+			};
+			if !held && gas.fits(used, &order) {
+				used += gas.used(&order);
+				frame.push(order);
+			} else {
+				self.mempool.submit_private(order);
+			}
+		}
+		let mined = frame.len();
+		for order in miner.frame.drain(..) {
+			if gas.fits(used, &order) {
+				used += gas.used(&order);
+				frame.push(order);
+			} else {
+				self.mempool.add(order);
+			}
+		}
+
+		let mut paid = 0.0;
+		if self.consts.private_flow_fee > 0.0 {
+			for order in frame[..mined].iter() {
+				match self.house.transfer(&order.trader_id, &miner.trader_id, &self.consts.symbols()[0], self.consts.private_flow_fee, 0.0, UpdateReason::Private) {
+					Ok(()) => paid += self.consts.private_flow_fee,
+					Err(e) => println!("Couldn't pay for private order: {:?}", e),
+				}
+			}
+		}
+		self.history.save_private_fees(paid);
+		miner.frame = frame;
+	}
+
+	/// A repeating task for the VWAP execution agents. Once a block, each agent forecasts the
+	/// block's volume from the History and replaces its resting child order with one for the gap
+	/// to its volume-weighted target, topped up when it missed the last block's target.
+	pub fn vwap_task(sim: &Simulation) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let vwap = Vwap::new(&sim.consts);
+		let mut last_block = None;
+		let mut prev_targets: HashMap<String, f64> = HashMap::new();
+		Task::seeded_rpt_task("vwap", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			let current_block = sim.block_num.read_count();
+			if last_block == Some(current_block) {
+				return;
+			}
+			last_block = Some(current_block);
+
+			let volumes = sim.history.block_volumes();
+			let past: Vec<f64> = volumes.iter().filter(|(b, _v)| *b < current_block).map(|(_b, v)| *v).collect();
+			let forecast = vwap.forecast(&past);
+			let market_volume: f64 = volumes.iter()
+				.filter(|(b, _v)| *b >= vwap.start_block && *b < current_block)
+				.map(|(_b, v)| v)
+				.sum();
+
+			let fills = sim.history.get_fills();
+			for trader_id in sim.house.query().of_type(TraderT::Vwap).ids() {
+				let quantity = match vwap.is_active(current_block) {
+					true => {
+						let filled: f64 = Simulation::execution_fills(&sim.history, &fills, &trader_id, &vwap.trade_type).iter().map(|(_p, q)| q).sum();
+						let target = vwap.target(current_block, market_volume, forecast);
+						let prev_target = prev_targets.insert(trader_id.clone(), target).unwrap_or(0.0);
+						vwap.child_qty(target, filled, prev_target)
+					},
+					false => 0.0,
+				};
+				sim.send_child_order(&trader_id, &vwap.trade_type, quantity, current_block);
+			}
+		}, interval)
+	}
+
+	/// The first num_adaptive_makers makers by id with their quoting parameters and their equity
+	/// marked to the first symbol's reference price
+	pub fn adaptive_makers(&self) -> Vec<(String, MakerParams, f64)> {
+		let mark = self.history.get_reference_price(&self.consts.symbols()[0]).unwrap_or(0.0);
+		let mut makers = self.house.get_maker_params();
+		makers.sort_by(|a, b| a.0.cmp(&b.0));
+		makers.truncate(self.consts.num_adaptive_makers as usize);
+		makers.into_iter()
+			.map(|(id, _maker_type, params, balance, inventory)| (id, params, balance + inventory * mark))
+			.collect()
+	}
+
+	/// A repeating task for the adaptive makers. Every adapt_interval blocks each credits the arm
+	/// it traded with its change in equity and switches to the arm the bandit picks next.
+	pub fn adaptive_task(sim: &Simulation, adaptive: Arc<Mutex<Adaptive>>) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let mut last_block = None;
+		Task::seeded_rpt_task("adaptive", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			let current_block = sim.block_num.read_count();
+			if last_block == Some(current_block) || current_block % sim.consts.adapt_interval.max(1) != 0 {
+				return;
+			}
+			last_block = Some(current_block);
+
+			let mut adaptive = adaptive.lock().expect("adaptive_task");
+			for (id, params, equity) in sim.adaptive_makers() {
+				let next = adaptive.rotate(&id, equity, params);
+				if let Err(e) = sim.house.set_maker_params(&id, next) {
+					println!("Couldn't adapt {}: {}", id, e);
+				}
+			}
+		}, interval)
+	}
+
+	/// Credits each adaptive maker's last arm with its equity change to the end of the run and
+	/// saves the bandit to path for the next run, returning its arms
+	pub fn settle_adaptive(&self, adaptive: &Mutex<Adaptive>, path: &str) -> std::io::Result<Vec<Arm>> {
+		let mut adaptive = adaptive.lock().expect("settle_adaptive");
+		for (id, _params, equity) in self.adaptive_makers() {
+			adaptive.settle(&id, equity);
+		}
+		adaptive.bandit.save(path)?;
+		Ok(adaptive.bandit.arms.clone())
+	}
+
+	/// Turns over the makers and the investors by their equity marked to the first symbol's reference
+	/// price. A maker's imitator copies its maker type and quoting parameters, while a new maker
+	/// draws its own like the makers at setup. Returns the number of (retirees, imitators).
+	pub fn population_round(&self, population: &Population) -> (usize, usize) {
+		let mark = self.history.get_reference_price(&self.consts.symbols()[0]).unwrap_or(0.0);
+		let maker_params: HashMap<String, (MakerT, MakerParams)> = self.house.get_maker_params().into_iter()
+			.map(|(id, maker_type, params, _bal, _inv)| (id, (maker_type, params)))
+			.collect();
+		let (mut retired, mut imitators) = (0, 0);
+		for player_type in [TraderT::Maker, TraderT::Investor].iter() {
+			let equities: Vec<(String, f64)> = self.house.query().of_type(*player_type).snapshots().iter()
+				.map(|p| (p.trader_id.clone(), p.balance + p.total_inv() * mark))
+				.collect();
+			let (poorest, richest) = population.turnover(&equities);
+
+			for id in poorest {
+				for order in self.house.retire_player(&id) {
+					self.history.mempool_order(order.clone());
+					OrderProcessor::conc_recv_order(order, Arc::clone(&self.mempool)).join().expect("Failed to send retiree cancel order");
+				}
+				retired += 1;
+
+				let imitated = match Distributions::do_with_prob(population.imitate_prob) {
+					true => richest.choose(&mut sim_rng()).cloned(),
+					false => None,
+				};
+				if imitated.is_some() {
+					imitators += 1;
+				}
+				match player_type {
+					TraderT::Maker => {
+						let (maker_type, params) = match imitated.and_then(|rich| maker_params.get(&rich).cloned()) {
+							Some(strategy) => strategy,
+							None => (Maker::gen_rand_type(), MakerParams::sample(&self.dists)),
+						};
+						self.house.enter_maker(Maker::new(gen_trader_id(TraderT::Maker), maker_type).with_params(params)
+							.with_risk_aversion(sample_risk_aversion(&self.dists)));
+					},
+					_ => self.house.enter_investor(Investor::new(gen_trader_id(TraderT::Investor)).with_risk_aversion(sample_risk_aversion(&self.dists))),
+				}
+			}
+		}
+		(retired, imitators)
+	}
+
+	/// A repeating task that turns over the population every population_interval blocks, keeping
+	/// a running count of the (retirees, imitators)
+	pub fn population_task(sim: &Simulation, counts: Arc<Mutex<(usize, usize)>>) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let population = Population::new(&sim.consts);
+		let mut last_block = None;
+		Task::seeded_rpt_task("population", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			let current_block = sim.block_num.read_count();
+			if last_block == Some(current_block) || !population.is_due(current_block) {
+				return;
+			}
+			last_block = Some(current_block);
+
+			let (retired, imitators) = sim.population_round(&population);
+			let mut counts = counts.lock().expect("population_task");
+			counts.0 += retired;
+			counts.1 += imitators;
+		}, interval)
+	}
+
+	/// A repeating task for the mean reversion traders. Each run a random mean reversion trader
+	/// replaces its resting order with one against the first symbol's deviation from its noisy
+	/// observation of the fundamental value, limit priced at that value.
+	pub fn mean_reversion_task(sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let trader = MeanReversionTrader::new(&consts);
+		let fundamental = Arc::clone(&sim.fundamental);
+		Task::seeded_rpt_task("mean_reversion", consts.seed, SimControl::stopper(&sim.control), move || {
+			let trader_id = match house.query().of_type(TraderT::MeanReversion).random() {
+				Some(id) => id,
+				None => return,
+			};
+
+			// Pull the trader's resting order before trading again
+			Simulation::pull_orders(&house, &mempool, &history, &trader_id);
+
+			// Nothing to revert until the symbol has traded
+			let last_price = match history.get_reference_price(&symbol) {
+				Some(price) => price,
+				None => return,
+			};
+			let value = trader.observe(fundamental.value());
+			let inventory = house.with_player(&trader_id, |p| p.get_inv(&symbol)).unwrap_or(0.0);
+			let (trade_type, quantity) = match trader.decide(last_price, value, inventory) {
+				Some(decision) => decision,
+				None => return,
+			};
+
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, value, &consts);
+			let order = Order::new(trader_id.clone(),
+								   OrderType::Enter,
+								   trade_type,
+								   ex_type,
+								   p_l,
+								   p_h,
+								   value,
+								   quantity,
+								   quantity,
+								   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+			).with_symbol(&symbol);
+
+			Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+		}, consts.batch_interval)
+	}
+
+	/// A repeating task for the informed traders. Each run a random informed trader replaces its
+	/// resting order and, if a signal of the next fundamental value jump arrives, trades the first
+	/// symbol towards the value it expects after the jump, limit priced at that value.
+	pub fn informed_task(sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let fundamental = Arc::clone(&sim.fundamental);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let trader = InformedTrader::new(&consts);
+		Task::seeded_rpt_task("informed", consts.seed, SimControl::stopper(&sim.control), move || {
+			let trader_id = match house.query().of_type(TraderT::Informed).random() {
+				Some(id) => id,
+				None => return,
+			};
+
+			// Pull the trader's resting order before trading again
+			Simulation::pull_orders(&house, &mempool, &history, &trader_id);
+
+			// Only trade ahead of a jump the public hasn't seen yet
+			let jump = match fundamental.pending().first() {
+				Some((_due, size)) => *size,
+				None => return,
+			};
+			let expected = match trader.signal(jump) {
+				Some(signal) => (fundamental.value() + signal).max(0.0),
+				None => return,
+			};
+			let price = history.get_reference_price(&symbol).unwrap_or(fundamental.value());
+			let (trade_type, quantity) = match trader.decide(price, expected) {
+				Some(decision) => decision,
+				None => return,
+			};
+
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, expected, &consts);
+			let order = Order::new(trader_id.clone(),
+								   OrderType::Enter,
+								   trade_type,
+								   ex_type,
+								   p_l,
+								   p_h,
+								   expected,
+								   quantity,
+								   quantity,
+								   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+			).with_symbol(&symbol);
+
+			Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+		}, consts.batch_interval)
+	}
+
+	/// Steps the fundamental value once per block, applying the jumps that are due
+	pub fn fundamental_task(fundamental: Arc<FundamentalValue>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		let mut last_block = block_num.read_count();
+		Task::seeded_rpt_task("fundamental", consts.seed, SimControl::stopper(&control), move || {
+			let cur_block = block_num.read_count();
+			if cur_block > last_block {
+				fundamental.step(cur_block);
+				last_block = cur_block;
+			}
+		}, consts.batch_interval)
+	}
+
+	/// Syncs the regional mempools with each other and the miner's, recording when each order
+	/// became visible in every region
+	pub fn region_sync_task(mempool: Arc<MemPool>, history: Arc<History>, control: Arc<SimControl>, consts: Constants) -> Task {
+		Task::seeded_rpt_task("region_sync", consts.seed, SimControl::stopper(&control), move || {
+			if let Some(regions) = &mempool.regions {
+				let synced = regions.sync(&mempool);
+				history.save_region_syncs(&synced);
+			}
+		}, consts.region_sync_interval)
+	}
+
+	/// A task for the noise traders. Submissions and cancellations arrive as Poisson processes. On a
+	/// submission a random noise trader sends an order priced like an investor's, on a cancellation
+	/// a random noise trader with resting orders cancels one of them.
+	pub fn noise_task(sim: &Simulation) -> JoinHandle<()> {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let control = Arc::clone(&sim.control);
+		let trader = NoiseTrader::new(&consts);
+		thread::spawn(move || {
+			seed_task(consts.seed, "noise", 0);
 			loop {
-				// Check if the simulation is ending
-				if block_num.read_count() > consts.num_blocks {
-					// exit the thread
-					println!("Exiting investor_task");
+				// Stop once the simulation ends
+				if control.is_stopped() {
+					println!("Exiting noise_task");
 					break;
 				}
 
-				// Randomly select an investor
-				let trader_id = house.get_rand_player_id(TraderT::Investor).expect("Couldn't get rand investor");
+				thread::sleep(time::Duration::from_millis(scaled_millis(trader.wait_millis())));
+
+				match trader.next_event() {
+					Some(NoiseEvent::Submit) => {
+						let trader_id = match house.query().of_type(TraderT::Noise).random() {
+							Some(id) => id,
+							None => break,
+						};
+						let trade_type = match Distributions::fifty_fifty() {
+							true => TradeType::Ask,
+							false => TradeType::Bid,
+						};
+						let price = match trade_type {
+							TradeType::Ask => dists.sample_dist(DistReason::AsksCenter).expect("couldn't sample price"),
+							TradeType::Bid => dists.sample_dist(DistReason::BidsCenter).expect("couldn't sample price"),
+						};
+						let quantity = dists.sample_dist(DistReason::InvestorVolume).expect("couldn't sample vol");
+						let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, &consts);
+						let order = Order::new(trader_id.clone(),
+											   OrderType::Enter,
+											   trade_type,
+											   ex_type,
+											   p_l,
+											   p_h,
+											   price,
+											   quantity,
+											   quantity,
+											   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+						).with_symbol(&consts.rand_symbol());
+
+						Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+					},
+					Some(NoiseEvent::Cancel) => {
+						let trader_id = match house.query().of_type(TraderT::Noise).filter(|p| p.num_orders() > 0).random() {
+							Some(id) => id,
+							None => continue,
+						};
+						let order_ids = house.with_player(&trader_id, |p| p.get_enter_order_ids()).unwrap_or(Vec::new());
+						if let Some(order_id) = order_ids.choose(&mut sim_rng()) {
+							if let Ok(cancel) = house.cancel_one_order(trader_id.clone(), *order_id) {
+								Simulation::send_order(&mempool, &history, cancel);
+							}
+						}
+					},
+					None => break,
+				}
+			}
+		})
+	}
+
+	/// A repeating task for the spoofers. Each run a random spoofer either places a large order on a
+	/// random side of the first symbol offset from the touch, or, if its last one is still resting,
+	/// hits the best price on the other side and cancels the large order. Each step is saved to the History.
+	pub fn spoof_task(sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let books = Arc::clone(&sim.books);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let spoofer = Spoofer::new(&consts);
+		// Each spoofer's resting fake order
+		let mut spoofs = HashMap::<String, Order>::new();
+		Task::seeded_rpt_task("spoof", consts.seed, SimControl::stopper(&sim.control), move || {
+			let trader_id = match house.query().of_type(TraderT::Spoofer).random() {
+				Some(id) => id,
+				None => return,
+			};
+			let (bids, asks) = &books[&symbol];
+			let touch = |side: &TradeType| match side {
+				TradeType::Bid => bids.peek_best_price(),
+				TradeType::Ask => asks.peek_best_price(),
+			};
+			let send = |order: Order, action: SpoofAction, touch: Option<f64>| {
+				history.save_spoof_event(SpoofEvent::new(block_num.read_count(), action, &order, touch));
+				Simulation::send_order(&mempool, &history, order);
+			};
+
+			match spoofs.remove(&trader_id) {
+				Some(spoof) => {
+					// Trade against the best price on the other side of the book
+					let trade_type = Spoofer::trade_side(&spoof.trade_type);
+					if let Some(price) = touch(&spoof.trade_type) {
+						let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, &consts);
+						let order = Order::new(trader_id.clone(),
+											   OrderType::Enter,
+											   trade_type,
+											   ex_type,
+											   p_l,
+											   p_h,
+											   price,
+											   spoofer.trade_quantity,
+											   spoofer.trade_quantity,
+											   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+						).with_symbol(&symbol);
+						if let Some(order) = Simulation::admit_order(&house, &history, order, &consts, block_num.read_count()) {
+							send(order, SpoofAction::Trade, Some(price));
+						}
+					}
 
-				// Only add a new order if they dont already have one in the book
-				if house.get_player_order_count(&trader_id).expect("get_player_order_count") == 0 {
-					// Decide bid or ask
+					// Pull the fake order before it executes
+					if let Ok(cancel) = house.cancel_one_order(trader_id.clone(), spoof.order_id) {
+						send(cancel, SpoofAction::Cancel, touch(&spoof.trade_type));
+					}
+				},
+				None => {
 					let trade_type = match Distributions::fifty_fifty() {
 						true => TradeType::Ask,
 						false => TradeType::Bid,
 					};
-
-					// Sample order price from bid/ask distribution
-					let price = match trade_type {
-						TradeType::Ask => dists.sample_dist(DistReason::AsksCenter).expect("couldn't sample price"),
-						TradeType::Bid => dists.sample_dist(DistReason::BidsCenter).expect("couldn't sample price"),
+					let best = match touch(&trade_type) {
+						Some(best) => best,
+						None => return,
 					};
+					let price = spoofer.spoof_price(&trade_type, best);
+					let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, &consts);
+					let order = Order::new(trader_id.clone(),
+										   OrderType::Enter,
+										   trade_type,
+										   ex_type,
+										   p_l,
+										   p_h,
+										   price,
+										   spoofer.quantity,
+										   spoofer.quantity,
+										   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+					).with_symbol(&symbol);
+					if let Some(order) = Simulation::admit_order(&house, &history, order, &consts, block_num.read_count()) {
+						spoofs.insert(trader_id.clone(), order.clone());
+						send(order, SpoofAction::Place, Some(best));
+					}
+				},
+			}
+		}, consts.batch_interval)
+	}
 
-					// Sample order volume from bid/ask distribution
-					let quantity = dists.sample_dist(DistReason::InvestorVolume).expect("couldn't sample vol");
+	/// A repeating task for the snipers, run four times a batch_interval to land before the next frame.
+	/// Each run a random sniper reads the mempool for makers cancelling quotes mispriced against the
+	/// first symbol's last clearing price and trades against the most profitable one, bidding gas by
+	/// its policy to execute before the cancel. Each quote is only raced once.
+	pub fn sniper_task(policies: HashMap<String, GasPolicy>, sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let fundamental = Arc::clone(&sim.fundamental);
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let mut raced = Vec::<u64>::new();
+		// Follow each region's pool from its events instead of cloning it every tick
+		let watches: Vec<Mutex<PoolWatch>> = mempool.subscribe_regions().into_iter().map(Mutex::new).collect();
+		Task::seeded_rpt_task("sniper", consts.seed, SimControl::stopper(&sim.control), move || {
+			for watch in watches.iter() {
+				watch.lock().expect("Error locking sniper watch").update();
+			}
+			let trader_id = match house.query().of_type(TraderT::Sniper).random() {
+				Some(id) => id,
+				None => return,
+			};
+			let policy = &policies[&trader_id];
+
+			let delay = mempool.gossip_delay(&trader_id);
+			let (region, region_pool) = mempool.pool_for(&trader_id);
+			let pool: Vec<Order> = watches[region].lock().expect("Error locking sniper watch").pending()
+				.filter(|o| o.symbol == symbol && !o.encrypted && region_pool.has_seen(o, delay))
+				.cloned().collect();
+			let fair = history.get_reference_price(&symbol).unwrap_or(fundamental.value());
+			let is_maker = |id: &str| house.with_player(id, |p| p.get_player_type() == TraderT::Maker).unwrap_or(false);
+
+			// Race the most profitable quote it can outbid, if the edge covers the gas
+			let snipe = Snipe::find(&pool, fair, is_maker).into_iter()
+				.filter(|s| !raced.contains(&s.target_id))
+				.filter_map(|s| policy.bid(s.competing_gas).map(|gas| (s, gas)))
+				.find(|(s, gas)| s.edge > *gas);
+			let (snipe, gas) = match snipe {
+				Some(snipe) => snipe,
+				None => return,
+			};
+			raced.push(snipe.target_id);
+
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &snipe.trade_type, snipe.price, &consts);
+			let order = Order::new(trader_id.clone(),
+								   OrderType::Enter,
+								   snipe.trade_type,
+								   ex_type,
+								   p_l,
+								   p_h,
+								   snipe.price,
+								   snipe.quantity,
+								   snipe.quantity,
+								   gas
+			).with_symbol(&symbol);
+
+			Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+		}, std::cmp::max(consts.batch_interval / 4, 1))
+	}
 
-					// Determine if were using flow or limit order
-					let ex_type = match consts.market_type {
-						MarketType::CDA|MarketType::FBA => ExchangeType::LimitOrder,
-						MarketType::KLF => ExchangeType::FlowOrder,
-					};
+	/// A repeating task for the bridge player. Each batch_interval the first symbol's book depth,
+	/// last clearing price and the player's position are sent to the external agent, whose reply
+	/// may cancel the player's resting orders and send new ones. No reply in time is a no-op.
+	pub fn bridge_task(trader_id: String, sim: &Simulation) -> Task {
+		let house = Arc::clone(&sim.house);
+		let mempool = Arc::clone(&sim.mempool);
+		let books = Arc::clone(&sim.books);
+		let history = Arc::clone(&sim.history);
+		let block_num = Arc::clone(&sim.block_num);
+		let dists = sim.dists.clone();
+		let consts = sim.consts;
+		let symbol = consts.symbols()[0].clone();
+		let bridge = Bridge::new(&consts);
+		Task::seeded_rpt_task("bridge", consts.seed, SimControl::stopper(&sim.control), move || {
+			let player = match house.inspect_player(&trader_id) {
+				Some(p) => p,
+				None => return,
+			};
+			let (bids, asks) = &books[&symbol];
+			let obs = BridgeObservation {
+				block_num: block_num.read_count(),
+				trader_id: trader_id.clone(),
+				symbol: symbol.clone(),
+				bids: Bridge::depth(&bids.copy_orders(), &TradeType::Bid, consts.bridge_depth),
+				asks: Bridge::depth(&asks.copy_orders(), &TradeType::Ask, consts.bridge_depth),
+				last_price: history.clearing_history(&symbol).last().map(|(price, _vol)| *price),
+				balance: player.balance,
+				inventory: player.positions.get(&symbol).cloned().unwrap_or(0.0),
+				open_orders: player.orders.len(),
+			};
+			let reply = bridge.exchange(&obs);
+
+			if reply.cancel {
+				Simulation::pull_orders(&house, &mempool, &history, &trader_id);
+			}
 
-					// Set the p_low and p_high to the price for limit orders
-					let (p_l, p_h) = match ex_type {								
-						ExchangeType::LimitOrder => (price, price),
-						ExchangeType::FlowOrder => {
-							// Flow order price has constant offset between p_low and p_high
-							match trade_type {
-								TradeType::Ask => (price, price + consts.flow_order_offset),
-								TradeType::Bid => (price - consts.flow_order_offset, price),
-							}
-						}
-					};
+			for instruction in reply.orders {
+				if instruction.price <= 0.0 || instruction.quantity <= 0.0 {
+					continue;
+				}
+				let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &instruction.trade_type, instruction.price, &consts);
+				let order = Order::new(trader_id.clone(),
+									   OrderType::Enter,
+									   instruction.trade_type,
+									   ex_type,
+									   p_l,
+									   p_h,
+									   instruction.price,
+									   instruction.quantity,
+									   instruction.quantity,
+									   dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
+				).with_symbol(&symbol);
+
+				Simulation::submit_order(&house, &mempool, &history, order, &consts, block_num.read_count());
+			}
+		}, consts.batch_interval)
+	}
 
-					// Sample the u_max (maximum shares / batch) from (0, quantity)
-					let u_max = Distributions::sample_uniform(0.0, quantity, None);
+	/// Sends an order from a random Investor with no orders in the book, priced via the bid/ask
+	/// distributions. Returns false once every investor has defaulted.
+	pub fn investor_arrival(dists: &Distributions, house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, block_num: &BlockNum, consts: &Constants) -> bool {
+		// Randomly select an investor
+		let trader_id = match house.query().of_type(TraderT::Investor).random() {
+			Some(id) => id,
+			// Every investor has defaulted
+			None => return false,
+		};
+
+		// Only add a new order if they dont already have one in the book
+		if house.get_player_order_count(&trader_id).expect("get_player_order_count") == 0 {
+			// A scripted investor's orders come from its script instead of the distributions
+			if house.with_player(&trader_id, |p| p.script().is_some()) == Some(true) {
+				Simulation::scripted_arrival(&trader_id, dists, house, mempool, history, block_num, consts);
+				return true;
+			}
 
-					// Generate the order
-					let order = Order::new(trader_id.clone(), 
-										   OrderType::Enter,
-								   	       trade_type,
-									       ex_type,
-									       p_l,
-									       p_h,
-									       price,
-									       quantity,
-									       u_max,
-									       dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas")
-					);
-
-					// Add the order to the ClearingHouse which will register to the correct investor
-					match house.new_order(order.clone()) {
-						Ok(()) => {
-							// Add the order to the simulation's history
-							history.mempool_order(order.clone());
-							// Send the order to the MemPool
-							OrderProcessor::conc_recv_order(order, Arc::clone(&mempool)).join().expect("Failed to send inv order");
-							
-						},
-						Err(e) => {
-							// If we failed to add the order to the player, don't send it to mempool
-							println!("{:?}", e);
-						},
-					}
+			// Decide bid or ask
+			let trade_type = match Distributions::fifty_fifty() {
+				true => TradeType::Ask,
+				false => TradeType::Bid,
+			};
+
+			// Sample order price from bid/ask distribution
+			let price = match trade_type {
+				TradeType::Ask => dists.sample_dist(DistReason::AsksCenter).expect("couldn't sample price"),
+				TradeType::Bid => dists.sample_dist(DistReason::BidsCenter).expect("couldn't sample price"),
+			};
+
+			// Sample order volume from bid/ask distribution, sized down by the investor's risk aversion
+			let risk_aversion = house.with_player(&trader_id, |p| p.get_risk_aversion()).unwrap_or(0.0);
+			let quantity = dists.sample_dist(DistReason::InvestorVolume).expect("couldn't sample vol") * risk_scale(risk_aversion);
+
+			// Determine if were using flow or limit order and set the order's price range
+			let (ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &trade_type, price, &consts);
+
+			// Sample the u_max (maximum shares / batch) from (0, quantity)
+			let u_max = Distributions::sample_uniform(0.0, quantity, None);
+
+			// Bid the gas the oracle suggests, or sample it without one
+			let gas = match mempool.gas_estimate() {
+				Some(estimate) => estimate.suggested,
+				None => dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas"),
+			};
+
+			// Generate the order
+			let order = Order::new(trader_id.clone(), 
+								   OrderType::Enter,
+						   	       trade_type,
+							       ex_type,
+							       p_l,
+							       p_h,
+							       price,
+							       quantity,
+							       u_max,
+							       gas
+			);
+
+			// Investors trade one of the configured symbols
+			let order = order.with_symbol(&consts.rand_symbol());
+
+			// Shape the flow order's schedule if configured
+			let order = match order.ex_type == ExchangeType::FlowOrder && consts.flow_schedule_points >= 2 {
+				true => {
+					let points = Order::curved_schedule(&order.trade_type, p_l, p_h, consts.flow_schedule_points, consts.flow_curvature());
+					order.with_schedule(points)
+				},
+				false => order,
+			};
+
+			// Route the order to the DarkBook with some probability
+			let order = match Distributions::do_with_prob(consts.investor_dark_prob) {
+				true => order.with_venue(Venue::Dark),
+				false => order,
+			};
+
+			// Send the order privately to the miner with some probability
+			let private = Distributions::do_with_prob(consts.private_flow_prob);
+
+			// Add the order to the ClearingHouse which will register to the correct investor
+			// if it is priced within the band around the previous clearing price
+			if let Some(order) = Simulation::admit_order(house, history, order, consts, block_num.read_count()) {
+				history.save_flow(private);
+				// Send the order to the MemPool, or to the miner where no one else sees it
+				match private {
+					true => {
+						history.mempool_order(order.clone());
+						mempool.submit_private(order);
+					},
+					false => Simulation::send_order(mempool, history, order),
+				}
+			}
+		}
+		true
+	}
+
+	/// Sends the orders a scripted investor's script decides from the data in the mempool
+	pub fn scripted_arrival(trader_id: &str, dists: &Distributions, house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, block_num: &BlockNum, consts: &Constants) {
+		let (prior, inference) = history.produce_data(mempool.snapshot());
+		let data = MarketData {
+			block_num: block_num.read_count(),
+			prior: &prior,
+			inference: &inference,
+			dists,
+			consts,
+		};
+		let symbol = consts.rand_symbol();
+		for order in house.strategy_orders(trader_id, &data) {
+			Simulation::submit_order(house, mempool, history, order.with_symbol(&symbol), consts, block_num.read_count());
+		}
+	}
+
+	/// A repeating task. Will randomly select an Investor from the ClearingHouse,
+	/// generate a bid/ask order priced via bid/ask distributions, send the order to 
+	/// the mempool, and then sleep until the next investor_arrival time.
+	pub fn investor_task(dists: Distributions, house: Arc<ClearingHouse>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> JoinHandle<()> {
+		thread::spawn(move || {       
+			seed_task(consts.seed, "investor", 0);
+			loop {
+				// Stop once the simulation ends
+				if control.is_stopped() {
+					println!("Exiting investor_task");
+					break;
+				}
+
+				// Hold while the simulation is paused
+				if !control.is_running(block_num.read_count()) {
+					thread::sleep(time::Duration::from_millis(10));
+					continue;
+				}
+
+				// Randomly select an investor to send an order
+				if !Simulation::investor_arrival(&dists, &house, &mempool, &history, &block_num, &consts) {
+					println!("Exiting investor_task, no investors left");
+					break;
 				}
 
 				// Sample from InvestorEnter distribution how long to wait to send next investor
 				let sleep_time = dists.sample_dist(DistReason::InvestorEnter).expect("Couldn't get enter time sample").abs();	
-				let sleep_time = time::Duration::from_millis(sleep_time as u64);
+				let sleep_time = time::Duration::from_millis(scaled_millis(sleep_time as u64));
 				thread::sleep(sleep_time);
 			}
 		})
 	}
 
-	pub fn miner_task(mut miner: Miner, dists: Distributions, house: Arc<ClearingHouse>, 
-		mempool: Arc<MemPool>, bids: Arc<Book>, asks: Arc<Book>, history: Arc<History>, block_num: Arc<BlockNum>, consts: Constants) -> Task {
-		Task::rpt_task(move || {
-			// println!("in miner task, {:?}", block_num.read_count());
-			
-			// Check if the simulation is ending
-			if block_num.read_count() > consts.num_blocks {
-				// exit the thread
-				println!("Exiting miner_task");
-				// std::process::exit(1)
+	/// Each Investor order that has rested investor_stale_blocks without a fill is cancelled with
+	/// investor_cancel_prob, or else repriced from the bid/ask distributions with investor_reprice_prob.
+	/// A repriced order is sent as an Update so it keeps its order id but rejoins the back of the queue.
+	pub fn investor_requote_round(dists: &Distributions, house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, block_num: &BlockNum, consts: &Constants) {
+		if consts.investor_stale_blocks == 0 {
+			return;
+		}
+		let current_block = block_num.read_count();
+		for trader_id in house.query().of_type(TraderT::Investor).shuffled() {
+			let sent_quantity = |o_id| history.find_orig_order(o_id).map(|(o, _)| o.quantity);
+			let stale = house.with_player(&trader_id, |p| p.stale_orders(current_block, consts.investor_stale_blocks, &sent_quantity))
+				.unwrap_or_default();
+
+			for order in stale {
+				if Distributions::do_with_prob(consts.investor_cancel_prob) {
+					if let Ok(cancel) = house.cancel_one_order(trader_id.clone(), order.order_id) {
+						println!("Investor cancelling stale order: {}:{},{}", trader_id, order.order_id, order.price);
+						Simulation::send_order(mempool, history, cancel);
+					}
+				} else if Distributions::do_with_prob(consts.investor_reprice_prob) {
+					let price = match order.trade_type {
+						TradeType::Ask => dists.sample_dist(DistReason::AsksCenter).expect("couldn't sample price"),
+						TradeType::Bid => dists.sample_dist(DistReason::BidsCenter).expect("couldn't sample price"),
+					};
+					let (_ex_type, p_l, p_h) = Simulation::order_prices(consts.market_type, &order.trade_type, price, &consts);
+					let mut update = order.clone();
+					update.order_type = OrderType::Update;
+					update.price = price;
+					update.p_low = p_l;
+					update.p_high = p_h;
+					if update.schedule.is_some() {
+						update.schedule = Some(Order::curved_schedule(&update.trade_type, p_l, p_h, consts.flow_schedule_points, consts.flow_curvature()));
+					}
+
+					// The update resets the order's age once it is acked
+					let update = house.fit_to_budget(update);
+					let admitted = history.check_price_band(&update, consts.price_band_pct).and_then(|_| house.update_player_order(update.clone()));
+					Simulation::admission_event(&house, &history, &update, &admitted, current_block);
+					if admitted.is_ok() {
+						println!("Investor repricing stale order: {}:{},{}->{}", trader_id, order.order_id, order.price, price);
+						history.mempool_order(update.clone());
+						OrderProcessor::conc_recv_order(update, Arc::clone(mempool)).join().expect("Failed to send inv update order");
+					}
+				}
 			}
+		}
+	}
+
+	/// A repeating task that runs the investor_requote_round once a batch
+	pub fn investor_requote_task(dists: Distributions, house: Arc<ClearingHouse>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		Task::seeded_rpt_task("investor_requote", consts.seed, SimControl::stopper(&control), move || {
+			if !control.is_running(block_num.read_count()) {
+				return;
+			}
+			Simulation::investor_requote_round(&dists, &house, &mempool, &history, &block_num, &consts);
+		}, consts.batch_interval)
+	}
 
-			// Collect the gas from the frame
-			let (gas_changes, total_gas) = miner.collect_gas();
-			// Update the players' gas amounts
-			house.apply_gas_fees(gas_changes, total_gas);
+	/// A repeating task for FBA and KLF markets. Publishes the indicative clearing price and
+	/// order imbalance of the first symbol's next batch to the History every indicative_interval.
+	pub fn indicative_task(books: Arc<SymbolBooks>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		let symbol = consts.symbols()[0].clone();
+		Task::seeded_rpt_task("indicative", consts.seed, SimControl::stopper(&control), move || {
+			// Only lit entries for this symbol can trade in the next batch, encrypted ones can't be read
+			let pending: Vec<Order> = mempool.snapshot().iter()
+				.filter(|o| o.order_type == OrderType::Enter && o.venue == Venue::Lit && o.symbol == symbol && !o.encrypted)
+				.cloned()
+				.collect();
+
+			let (bids, asks) = &books[&symbol];
+			let (price, imbalance) = Auction::indicative_auction(Arc::clone(bids), Arc::clone(asks), pending, consts.market_type,
+				AuctionRules::new(&consts, history.get_reference_price(&symbol)));
+			history.save_indicative(IndicativeData::new(block_num.read_count(), price, imbalance));
+		}, consts.indicative_interval)
+	}
 
-			// Publish the miner's current frame
-			if let Some(vec_results) = miner.publish_frame(Arc::clone(&bids), Arc::clone(&asks), consts.market_type) {
+	/// Accrues interest on balances and the carry cost on inventory once per block
+	pub fn interest_task(house: Arc<ClearingHouse>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		let mut last_block = block_num.read_count();
+		Task::seeded_rpt_task("interest", consts.seed, SimControl::stopper(&control), move || {
+			let cur_block = block_num.read_count();
+			if cur_block > last_block {
+				house.accrue_interest(consts.interest_rate, consts.carry_cost, cur_block - last_block);
+				last_block = cur_block;
+			}
+		}, consts.batch_interval)
+	}
+
+	/// Revalues every player's positions each valuation_interval blocks at each symbol's last
+	/// clearing price, or the fundamental value before it has traded
+	pub fn valuation_task(dists: Distributions, house: Arc<ClearingHouse>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		let fund_val = dists.fundamental_value();
+		let mut last_block = block_num.read_count();
+		Task::seeded_rpt_task("valuation", consts.seed, SimControl::stopper(&control), move || {
+			let cur_block = block_num.read_count();
+			if cur_block >= last_block + consts.valuation_interval {
+				let prices: HashMap<String, f64> = consts.symbols().into_iter()
+					.map(|symbol| {
+						let price = history.get_reference_price(&symbol).unwrap_or(fund_val);
+						(symbol, price)
+					})
+					.collect();
+				house.revalue(&prices, cur_block);
+				last_block = cur_block;
+			}
+		}, consts.batch_interval)
+	}
+
+	/// Pays dividends and funding every payment_interval blocks. Each dividend is dividend_yield of
+	/// the fundamental value per share, funding is funding_rate of each symbol's premium over it.
+	pub fn payment_task(dists: Distributions, house: Arc<ClearingHouse>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		let fund_val = dists.fundamental_value();
+		let mut last_block = block_num.read_count();
+		Task::seeded_rpt_task("payment", consts.seed, SimControl::stopper(&control), move || {
+			let cur_block = block_num.read_count();
+			if cur_block >= last_block + consts.payment_interval {
+				if consts.dividend_yield != 0.0 {
+					let dividends: HashMap<String, f64> = consts.symbols().into_iter()
+						.map(|symbol| (symbol, consts.dividend_yield * fund_val))
+						.collect();
+					house.pay_dividends(&dividends);
+				}
+				if consts.funding_rate != 0.0 {
+					// Longs pay shorts while the symbol trades above the fundamental value
+					let funding: HashMap<String, f64> = consts.symbols().into_iter()
+						.map(|symbol| {
+							let premium = history.get_reference_price(&symbol).unwrap_or(fund_val) - fund_val;
+							(symbol, -consts.funding_rate * premium)
+						})
+						.collect();
+					house.pay_funding(&funding);
+				}
+				last_block = cur_block;
+			}
+		}, consts.batch_interval)
+	}
+
+	/// The strategy the miners build the next block's frame with under miner_regime, which is
+	/// logged to History against the block
+	pub fn block_strategy(&self) -> Box<dyn MinerStrategy + Send + Sync> {
+		let block = self.block_num.read_count();
+		let behavior: MinerStrategyT = self.consts.miner_regime.behavior(block, self.consts.miner_strategy);
+		self.history.save_miner_behavior(block, behavior);
+		behavior.strategy(&self.consts)
+	}
+
+	/// A repeating task for the miner. Publishes the miner's frame as the next block, then waits
+	/// for the frame form delay before making the next frame from the mempool.
+	pub fn miner_task(mut miner: Miner, sim: &Simulation) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		Task::seeded_rpt_task("miner", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			if !sim.control.is_running(sim.block_num.read_count()) {
+				return;
+			}
+			sim.publish_block(&mut miner);
+
+			// Sleep for miner frame delay to simulate multiple miners
+			let sleep_time = sim.dists.sample_dist(DistReason::MinerFrameForm).expect("Couldn't get miner frame form delay").abs();	
+			let sleep_time = time::Duration::from_millis(scaled_millis(sleep_time as u64));
+			thread::sleep(sleep_time);
+
+			sim.make_next_frame(&mut miner, sim.block_strategy().as_ref());
+		}, interval)
+	}
+
+	/// The same as miner_task but with every miner competing for each block. Each builds its
+	/// own frame from the mempool and the winner, drawn by hash power, publishes it the next
+	/// interval. wins counts the blocks each miner's frame made it into.
+	pub fn mining_task(mut miners: Vec<Miner>, sim: &Simulation, wins: Arc<Mutex<HashMap<String, u64>>>) -> Task {
+		let sim = sim.clone();
+		let interval = sim.consts.batch_interval;
+		let power = HashPower::sample(&sim.dists, miners.len());
+		let mut winner = 0;
+		Task::seeded_rpt_task("mining", sim.consts.seed, SimControl::stopper(&sim.control), move || {
+			if !sim.control.is_running(sim.block_num.read_count()) {
+				return;
+			}
+			sim.publish_block(&mut miners[winner]);
+
+			// Sleep for miner frame delay to simulate the miners racing
+			let sleep_time = sim.dists.sample_dist(DistReason::MinerFrameForm).expect("Couldn't get miner frame form delay").abs();	
+			let sleep_time = time::Duration::from_millis(scaled_millis(sleep_time as u64));
+			thread::sleep(sleep_time);
+
+			winner = sim.compete_for_block(&mut miners, &power, sim.block_strategy().as_ref());
+			*wins.lock().expect("mining_task").entry(miners[winner].trader_id.clone()).or_insert(0) += 1;
+		}, interval)
+	}
+
+	/// Every miner builds a candidate frame from the same mempool, then one is drawn by hash
+	/// power to win the block. Only the winner's frame leaves the mempool, the orders in the
+	/// losing frames stay for later blocks and the losers' front-run orders are withdrawn.
+	/// Returns the index of the winner, whose frame is left to publish.
+	pub fn compete_for_block(&self, miners: &mut [Miner], power: &HashPower, strategy: &dyn MinerStrategy) -> usize {
+		for miner in miners.iter_mut() {
+			self.make_next_frame(miner, strategy);
+			// Put back the orders it took so the next miner sees the same pool
+			for order in miner.frame.iter().filter(|o| o.trader_id != miner.trader_id) {
+				self.mempool.add(order.clone());
+			}
+		}
+
+		let winner = power.winner();
+		let won: Vec<u64> = miners[winner].frame.iter().map(|o| o.order_id).collect();
+		self.mempool.remove_ids(&won);
+		for (i, miner) in miners.iter_mut().enumerate() {
+			if i == winner {
+				continue;
+			}
+			let id = miner.trader_id.clone();
+			miner.sandwiches.clear();
+			for order in miner.frame.drain(..).filter(|o| o.trader_id == id) {
+				if let Err(e) = self.house.cancel_player_order(id.clone(), order.order_id) {
+					println!("Couldn't withdraw losing front-run order: {:?}", e);
+				}
+			}
+		}
+		println!("Miner {} won the block", miners[winner].trader_id);
+		winner
+	}
+
+	/// Publishes the miner's frame as the next block: collects gas, matches each symbol's orders,
+	/// updates the ClearingHouse and History, then settles, charges fees and marks positions
+	pub fn publish_block(&self, miner: &mut Miner) {
+		let (house, mempool, books, history, block_num) = (&self.house, &self.mempool, &self.books, &self.history, &self.block_num);
+		let (market_phase, circuit_breaker, dark_books, amm_pools, dutch_auctions, consts) = (&self.market_phase, &self.circuit_breaker, &self.dark_books, &self.amm_pools, &self.dutch_auctions, &self.consts);
+
+		// Summarize the orders the block left waiting
+		history.save_mempool_stats(block_num.read_count(), mempool.stats());
+
+		// The gas oracle learns what the block paid
+		if let Some(oracle) = &mempool.gas_oracle {
+			oracle.record_block(&miner.frame);
+			if let Some(estimate) = mempool.gas_estimate() {
+				info!("Gas oracle @ block {}: {:?}", block_num.read_count(), estimate);
+				history.save_gas_estimate(block_num.read_count(), estimate);
+			}
+		}
+
+		// Traders' sends count towards the next block's rate limit
+		if let Some(limit) = &mempool.rate_limit {
+			limit.new_block();
+		}
+
+		// The frame's orders decrypt as the block is formed
+		for order in miner.frame.iter_mut() {
+			order.encrypted = false;
+		}
+
+		// Private orders go ahead of the public frame, only their cancels while trading is halted
+		self.include_private(miner);
+
+		// Bundles bought from the searchers go first, except while trading is halted
+		if !self.circuit_breaker.is_halted(block_num.read_count()) {
+			self.include_bundles(miner);
+		}
+
+		// Revealed orders go ahead of everything in the order they were committed, reveals
+		// that don't match a commitment are dropped
+		if let Some(store) = &mempool.commit_reveal {
+			// The miner commits to its own orders like everyone else, so they can't run ahead
+			// of the orders it read. Its sandwiches no longer wrap their victims.
+			let miner_id = miner.trader_id.clone();
+			let (own, frame): (Vec<Order>, Vec<Order>) = miner.frame.drain(..)
+				.partition(|o| o.trader_id == miner_id && o.salt == 0);
+			miner.frame = frame;
+			if !own.is_empty() {
+				miner.sandwiches.clear();
+			}
+			for order in own {
+				mempool.commit(order);
+			}
+			let rejected = MemPoolProcessor::order_reveals(&mut miner.frame, store, block_num.read_count());
+			for order in rejected.iter() {
+				let _ = house.cancel_player_order(order.trader_id.clone(), order.order_id);
+			}
+			history.save_reveals(miner.frame.iter().filter(|o| o.salt != 0).count(), rejected.len());
+		}
+
+		// The frame's sandwiches go into the block with it
+		for sandwich in miner.sandwiches.drain(..) {
+			history.save_sandwich(sandwich);
+		}
+
+		// Keep the block's orders to replay the run from
+		history.save_frame(block_num.read_count(), &miner.frame);
+
+		// Add the block to the chain
+		let gas_used = BlockGas::new(consts).total(&miner.frame);
+		self.chain.append(Block::new(block_num.read_count(), get_time(), miner.trader_id.clone(), gas_used, &miner.frame));
+
+		// Checkpoint the chain so the block can be orphaned
+		let checkpoint = match consts.reorg_prob > 0.0 {
+			true => {
+				house.begin_block_undo();
+				Some(BlockCheckpoint::new(block_num.read_count(), miner.frame.clone(), books, dark_books, amm_pools, dutch_auctions, history))
+			},
+			false => None,
+		};
+
+		// Collect the gas from the frame
+		let (gas_changes, total_gas) = miner.collect_gas();
+		// Update the players' gas amounts
+		house.apply_gas_fees(gas_changes, total_gas);
+
+		// Advance the market phase, CDA call auctions uncross on their final block
+		let uncross = market_phase.update(block_num.read_count(), &consts);
+		let phase = market_phase.read_phase();
+		let halted = circuit_breaker.is_halted(block_num.read_count());
+
+		// Each symbol's orders are published against its own books
+		let symbols = consts.symbols();
+		let mut frames = miner.split_frame_by_symbol();
+		let mut last_price = None;
+		for symbol in symbols.iter() {
+			let (bids, asks) = books.get(symbol).expect("symbol books");
+			miner.frame = frames.remove(symbol).unwrap_or(Vec::new());
+
+			// Dark orders are matched separately from the lit books
+			let dark_orders = miner.take_dark_orders();
+
+			// Publish the miner's frame for this symbol
+			let published = match consts.market_type {
+				MarketType::AMM => miner.publish_amm_frame(Arc::clone(&amm_pools[symbol])),
+				MarketType::Dutch => miner.publish_dutch_frame(Arc::clone(bids), Arc::clone(asks), Arc::clone(&dutch_auctions[symbol])),
+				_ => miner.publish_frame_in_phase(Arc::clone(bids), Arc::clone(asks), consts.market_type, phase, uncross,
+					AuctionRules::new(&consts, history.get_reference_price(symbol))),
+			};
+			if let Some(vec_results) = published {
 				let copied_bids = bids.copy_orders();
 				let copied_asks = asks.copy_orders();
 
@@ -244,148 +1890,417 @@ impl Simulation {
 					copied_asks,
 					));
 
-				// Save new book state to the history
-				history.clone_book_state(copied_bids, TradeType::Bid, *block_num.num.lock().unwrap());
-				history.clone_book_state(copied_asks, TradeType::Ask, *block_num.num.lock().unwrap());
+				// The History's book states and the circuit breaker follow the first symbol
+				if symbol == &symbols[0] {
+					history.clone_book_state(copied_bids, TradeType::Bid, *block_num.num.lock().unwrap());
+					history.clone_book_state(copied_asks, TradeType::Ask, *block_num.num.lock().unwrap());
+					last_price = vec_results.iter().filter_map(|r| r.last_price()).last();
+				}
 
 				for res in vec_results {
+					let res = res.with_symbol(symbol).with_block(block_num.read_count());
 					// Update the clearing house and history
 					history.save_results(res.clone());
-					house.update_house(res);
+					if let Err(e) = house.update_house(res) {
+						println!("ERROR: {}", e);
+					}
 				}
 			}
 
-			// Update the block num
-			block_num.inc_count();
+			// Cross the DarkBook at the lit midpoint, dark orders don't trade during halts or call auctions
+			let mid = match phase.is_call_auction() || halted {
+				true => None,
+				false => DarkBook::lit_midpoint(bids, asks),
+			};
+			if let Some(dark_results) = dark_books[symbol].run_dark_auction(dark_orders, mid) {
+				let dark_results = dark_results.with_symbol(symbol).with_block(block_num.read_count());
+				history.save_dark_results(dark_results.clone());
+				if let Err(e) = house.update_house(dark_results) {
+					println!("ERROR: {}", e);
+				}
+			}
+		}
 
-			// Tax the makers holding inventory
-			house.tax_makers(consts.maker_inv_tax);
+		for (symbol, orders) in frames {
+			println!("Dropping {} orders for unknown symbol {}", orders.len(), symbol);
+		}
 
+		// The block may be orphaned before anything follows from its fills, rolling back its
+		// changes and mining its orders again at the same height
+		let orphaned = match checkpoint {
+			Some(checkpoint) => self.orphan_block(checkpoint),
+			None => false,
+		};
+		if !orphaned {
+			self.close_block(last_price);
+		}
 
-			// Sleep for miner frame delay to simulate multiple miners
-			let sleep_time = dists.sample_dist(DistReason::MinerFrameForm).expect("Couldn't get miner frame form delay").abs();	
-			let sleep_time = time::Duration::from_millis(sleep_time as u64);
-			thread::sleep(sleep_time);
+		// Tell the players whose orders the full mempool evicted, some of whom resubmit
+		self.handle_evictions();
 
-			// Make the next frame after simulated propagation delay expires
-			miner.make_frame(Arc::clone(&mempool), consts.block_size);
+		// Investors still waiting on their orders may pay more gas to get them mined
+		self.replace_by_fee_round();
 
-			// Miner will front-run with some probability: 
-			match Distributions::do_with_prob(consts.front_run_perc) {
-				true => {
-					let (best_bid_price, best_ask_price) = history.get_best_prices();
-					match miner.strategic_front_run(best_bid_price, best_ask_price) {
-						Ok(order) => {
-							println!("Miner inserted a front-run order: {}", order.order_id);
-							// Log the order as if it were sent to the mempool
-							history.mempool_order(order.clone());
+		// Checkpoint the run if this was the block to save it after
+		if !orphaned {
+			self.checkpoint_block(miner);
+		}
+	}
 
-							// Register the new order to the ClearingHouse
-							house.new_order(order).expect("Couldn't add front-run order to CH");
-							
-						},
-						Err(_e) => {
-							println!("asdfasdfsdf{:?}", _e);
-						}
-					}
-				}
-				false => {},
+	/// Runs what follows from a published block that wasn't orphaned: the halts, settlement,
+	/// defaults, reveals and fees, then moves on to the next block.
+	/// last_price: Option<f64> -> the first symbol's last traded price in the block
+	fn close_block(&self, last_price: Option<f64>) {
+		let (dists, house, mempool, history, block_num) = (&self.dists, &self.house, &self.mempool, &self.history, &self.block_num);
+		let (market_phase, circuit_breaker, consts) = (&self.market_phase, &self.circuit_breaker, &self.consts);
+		let symbols = consts.symbols();
+
+		// Interrupt continuous trading with a volatility auction after a large price move
+		if consts.market_type == MarketType::CDA && consts.volatility_auction_pct > 0.0 {
+			let since = market_phase.volatility_window_start(block_num.read_count(), &consts);
+			let price_move = history.price_move_since(&symbols[0], since);
+			if price_move > consts.volatility_auction_pct && market_phase.trigger_volatility_auction(block_num.read_count(), &consts) {
+				info!("Volatility auction triggered @ block {}, price move: {}", block_num.read_count(), price_move);
 			}
+		}
 
-			// Wait until the next block publication time
+		// Halt or resume trading if the circuit breaker trips
+		if let Some(event) = circuit_breaker.update(block_num.read_count(), last_price, &consts) {
+			info!("Circuit breaker: {:?}", event);
+			history.save_halt_event(event);
+		}
 
-		}, consts.batch_interval)
+		// Settle the obligations from fills executed settlement_lag blocks ago
+		house.settle(block_num.read_count());
+
+		// Let each player's strategy start the new block
+		house.strategies_on_block(block_num.read_count());
+
+		// Default the players whose balance fell too far and cancel their resting orders
+		for order in house.process_defaults(consts.default_balance, consts.default_rule) {
+			history.mempool_order(order.clone());
+			OrderProcessor::conc_recv_order(order, Arc::clone(mempool)).join().expect("Failed to send default cancel order");
+		}
+
+		// Mine the commitments posted during the block and reveal the orders they commit to
+		self.commit_reveal_round(block_num.read_count());
+
+		// The orders the block filled or cancelled can't be cancelled or updated anymore
+		mempool.prune_known(house.orders_in_house(), || house.open_order_ids());
+
+		// Update the block num
+		block_num.inc_count();
+
+		// Publishing the last block stops every task
+		if block_num.read_count() > consts.num_blocks {
+			println!("Stopping the simulation after block {}", consts.num_blocks);
+			self.control.stop();
+		}
+
+		// Charge the per-block fees, such as the makers' inventory tax
+		house.charge_block_fees();
+
+		// Charge the borrow fee on short positions
+		house.charge_borrow_fees(consts.borrow_fee);
+
+		// Mark positions to each symbol's latest price and force-liquidate players below the maintenance margin
+		let prices: HashMap<String, f64> = symbols.iter()
+			.filter_map(|symbol| history.get_reference_price(symbol).map(|price| (symbol.clone(), price)))
+			.collect();
+		if prices.len() > 0 {
+			let gas = dists.sample_dist(DistReason::InvestorGas).expect("Couldn't sample gas");
+			for order in house.mark_to_market(&prices, consts.maintenance_margin, gas) {
+				history.mempool_order(order.clone());
+				OrderProcessor::conc_recv_order(order, Arc::clone(mempool)).join().expect("Failed to send margin call order");
+			}
+		}
 	}
 
+	/// Mines the commitments posted since the last block into block, then reveals the held
+	/// orders whose commitments are now mined so they can be mined in the next block
+	pub fn commit_reveal_round(&self, block: u64) {
+		let store = match &self.mempool.commit_reveal {
+			Some(store) => store,
+			None => return,
+		};
+		let commitments = self.mempool.take_commitments();
+		self.history.save_commitments(commitments.len());
+		for (hash, trader_id) in commitments {
+			store.commit(hash, trader_id, block);
+		}
+		for order in store.reveals_due(block + 1) {
+			OrderProcessor::conc_recv_order(order, Arc::clone(&self.mempool)).join().expect("Failed to send revealed order");
+		}
+	}
 
-	pub fn maker_task(dists: Distributions, house: Arc<ClearingHouse>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, consts: Constants) -> Task {
-		Task::rpt_task(move || {
-			// Check if the simulation is ending
-			if block_num.read_count() > consts.num_blocks {
-				// exit the thread
-				println!("Exiting maker_task");
-				// std::process::exit(1)
+	/// Orphans the block with probability reorg_prob, returning whether it was. Its changes to the
+	/// players, the venues and the History are rolled back and its orders returned to the mempool to
+	/// be mined again, otherwise the block is kept. The orders the block filled or cancelled are
+	/// entered again through the pre-trade checks, those rejected are pulled from the restored books
+	/// and aren't mined again.
+	pub fn orphan_block(&self, checkpoint: BlockCheckpoint) -> bool {
+		if !Distributions::do_with_prob(self.consts.reorg_prob) {
+			self.house.commit_block_undo();
+			return false;
+		}
+		let block = checkpoint.block;
+		let (reversed, taken) = self.house.rollback_block();
+		let mut orders = checkpoint.restore(&self.books, &self.dark_books, &self.amm_pools, &self.dutch_auctions, &self.history);
+		for order in taken {
+			if let Err(e) = self.house.new_order(order.clone()) {
+				println!("Order dropped by the reorg: {}", e);
+				orders.retain(|o| o.order_id != order.order_id);
+				self.pull_from_books(&order);
 			}
+		}
+		println!("Block {} orphaned: {} orders returned to the mempool, {} changes reversed", block, orders.len(), reversed);
+		self.history.save_reorg(block, orders.len(), reversed);
+		self.chain.orphan(block);
+		self.history.orphan_frame(block);
+		for order in orders {
+			self.mempool.add(order);
+		}
+		true
+	}
+
+	// Takes the order out of its symbol's lit or dark book, if it rests in one
+	fn pull_from_books(&self, order: &Order) {
+		if let Some((bids, asks)) = self.books.get(&order.symbol) {
+			let book = match order.trade_type {
+				TradeType::Bid => bids,
+				TradeType::Ask => asks,
+			};
+			if book.cancel_order_by_id(order.order_id).is_ok() {
+				return;
+			}
+		}
+		if let Some(dark) = self.dark_books.get(&order.symbol) {
+			let _ = dark.cancel_order_by_id(order);
+		}
+	}
+
+	/// Investors whose orders are still waiting in the mempool raise their gas by rbf_gas_bump,
+	/// each with probability rbf_prob. The replacement takes the pending order's place in the
+	/// mempool instead of both being mined.
+	pub fn replace_by_fee_round(&self) {
+		if self.consts.rbf_prob <= 0.0 {
+			return;
+		}
+		let mut replaced = 0;
+		for trader_id in self.house.query().of_type(TraderT::Investor).shuffled() {
+			for order in self.mempool.pending_of(&trader_id) {
+				if !Distributions::do_with_prob(self.consts.rbf_prob) {
+					continue;
+				}
+				let gas = order.gas * (1.0 + self.consts.rbf_gas_bump);
+				let mut replacement = order.clone();
+				replacement.gas = gas;
+				match self.mempool.replace(replacement) {
+					Ok(_) => {
+						// Only the order entered is kept by the player, its updates and cancels aren't
+						if order.order_type == OrderType::Enter {
+							let _ = self.house.replace_order_gas(trader_id.clone(), order.order_id, gas);
+						}
+						println!("Investor replaced pending order by fee: {}:{},{}->{}", trader_id, order.order_id, order.gas, gas);
+						replaced += 1;
+					},
+					Err(e) => println!("Couldn't replace pending order: {}", e),
+				}
+			}
+		}
+		self.history.save_replacements(replaced);
+	}
 
-			// Wait until the maker_cold_start number of blocks has passed before entering orders to 
-			// allow more information to arrive from investors.
-			if block_num.read_count() > consts.maker_cold_start {
-				// Select all Makers
-				let maker_ids = house.get_filtered_ids(TraderT::Maker);
+	/// Notifies the players whose orders the full mempool evicted. An evicted Enter never reached
+	/// the exchange so it's rejected. Each evicted order is resent with evicted_gas_bump more gas
+	/// with probability evicted_resubmit_prob, an Enter as a new order.
+	pub fn handle_evictions(&self) {
+		let evicted = self.mempool.take_evicted();
+		if evicted.is_empty() {
+			return;
+		}
+		let block = self.block_num.read_count();
+		let mut resubmitted = 0;
+		for order in evicted.iter() {
+			println!("Order evicted from the full mempool: {}:{},{}", order.trader_id, order.order_id, order.gas);
+			if order.order_type == OrderType::Enter {
+				let event = OrderEvent::reject(order, "Evicted from the full mempool", block);
+				self.house.route_order_event(event.clone());
+				self.history.save_order_event(event);
+			}
+			if !Distributions::do_with_prob(self.consts.evicted_resubmit_prob) {
+				continue;
+			}
 
-				// Copy the current mempool
-				let pool;
-				{
-					pool = mempool.items.lock().expect("maker task pool").clone();
+			let mut resubmit = order.clone();
+			resubmit.gas = order.gas * (1.0 + self.consts.evicted_gas_bump);
+			// Resubmitting queues it behind the orders that have arrived since
+			resubmit.arrival = 0;
+			if order.order_type == OrderType::Enter {
+				resubmit.order_id = gen_order_id();
+				resubmit.client_order_id = None;
+				// The new order no longer matches its commitment, it's committed to again
+				resubmit.salt = 0;
+				resubmit = self.house.fit_to_budget(resubmit);
+				let admitted = self.house.new_order(resubmit.clone());
+				Simulation::admission_event(&self.house, &self.history, &resubmit, &admitted, block);
+				if admitted.is_err() {
+					continue;
 				}
+			}
+			self.history.mempool_order(resubmit.clone());
+			OrderProcessor::conc_recv_order(resubmit, Arc::clone(&self.mempool)).join().expect("Failed to resend evicted order");
+			resubmitted += 1;
+		}
+		self.history.save_evictions(evicted.len(), resubmitted);
+	}
 
-				// use History to produce inference and decision data
-				let (decision_data, inference_data) = history.produce_data(pool);
+	/// Makes the miner's next frame from the mempool, which the miner may front-run
+	pub fn make_next_frame(&self, miner: &mut Miner, strategy: &dyn MinerStrategy) {
+		let (house, mempool, history, block_num, circuit_breaker, consts) = (&self.house, &self.mempool, &self.history, &self.block_num, &self.circuit_breaker, &self.consts);
+
+		// Make the next frame after simulated propagation delay expires,
+		// only cancels are processed while trading is halted
+		let halted = circuit_breaker.is_halted(block_num.read_count());
+		if halted {
+			miner.make_cancel_frame(Arc::clone(mempool), &BlockGas::new(consts));
+		} else {
+			let censored = strategy.make_frame(miner, mempool, &BlockGas::new(consts));
+			history.save_censored(&censored, block_num.read_count());
+		}
 
-				// iterate through each maker and produce an order using the decision and inference data
-				for id in maker_ids {
-					// If the maker has orders in the book, cancel and re-enter with some probabilty
-					if house.get_player_order_count(&id).expect("get_player_order_count") != 0 {
-						// Randomly choose whether the maker should try cancel and re-enter
-						match Distributions::do_with_prob(consts.maker_update_prob) {
-							true => {},
-							false => continue,	// Don't trade this batch
-						}
+		// Miner will front-run with some probability: 
+		match !halted && Distributions::do_with_prob(consts.front_run_perc) {
+			true => {
+				let (best_bid_price, best_ask_price) = history.get_best_prices();
+				match strategy.front_run(miner, best_bid_price, best_ask_price) {
+					Ok(orders) => {
+						for order in orders {
+							println!("Miner inserted a front-run order: {}", order.order_id);
+							// Log the order as if it were sent to the mempool
+							history.mempool_order(order.clone());
 
-						// Cancel the maker's current orders
-						if let Ok(cancel_orders) = house.cancel_all_orders(id.clone()) {
-							for order in cancel_orders {
-								println!("Cancelling: {}:{},{}\n", id, order.order_id, order.price);
-								// Add the cancel order to the simulation's history
-								history.mempool_order(order.clone());
-								// Send the cancel order to the MemPool
-								OrderProcessor::conc_recv_order(order, Arc::clone(&mempool)).join().expect("Failed to send maker bid order");
+							// Register the new order to the ClearingHouse, a miner that can't trade
+							// has its order taken back out of the frame
+							let order_id = order.order_id;
+							if let Err(e) = house.new_order(order) {
+								println!("ERROR: {}", e);
+								miner.frame.retain(|o| o.order_id != order_id);
+								miner.sandwiches.retain(|s| s.front.order_id != order_id && s.back.order_id != order_id);
 							}
 						}
-					}
-					
-					// Randomly choose whether the maker should try and enter a pair of orders
-					match Distributions::do_with_prob(consts.maker_enter_prob) {
+					},
+					Err(e) => println!("Miner couldn't front-run: {}", e),
+				}
+			}
+			false => {},
+		}
+	}
+
+
+	/// Each Maker with resting orders may cancel them, then may enter a new pair of orders from
+	/// its strategy. Makers wait maker_cold_start blocks before trading.
+	pub fn maker_round(dists: &Distributions, house: &ClearingHouse, mempool: &Arc<MemPool>, history: &History, block_num: &BlockNum, consts: &Constants) {
+		// Wait until the maker_cold_start number of blocks has passed before entering orders to 
+		// allow more information to arrive from investors.
+		if block_num.read_count() > consts.maker_cold_start {
+			// Select all Makers
+			let maker_ids = house.query().of_type(TraderT::Maker).shuffled();
+
+			// Share the mempool as the makers have seen it, without copying it
+			let pool = mempool.view(match &mempool.gossip {
+				Some(gossip) => gossip.type_delay(Some(TraderT::Maker)),
+				None => time::Duration::from_millis(0),
+			});
+
+			// use History to produce inference and decision data
+			let (mut decision_data, inference_data) = history.produce_data(pool);
+
+			// The makers price their gas off the oracle's suggestion when there is one
+			if let Some(estimate) = mempool.gas_estimate() {
+				decision_data.mean_pool_gas = estimate.suggested;
+			}
+
+			// iterate through each maker and produce an order using the decision and inference data
+			for id in maker_ids {
+				// If the maker has orders in the book, cancel and re-enter with some probabilty
+				if house.get_player_order_count(&id).expect("get_player_order_count") != 0 {
+					// Randomly choose whether the maker should try cancel and re-enter
+					match Distributions::do_with_prob(consts.maker_update_prob) {
 						true => {},
 						false => continue,	// Don't trade this batch
 					}
 
-					// Each maker interprets the data to produce their pair of new orders based on their type 
-					if let Some((bid_order, ask_order)) = house.maker_new_orders(id.clone(), &decision_data, &inference_data, &dists, &consts) {
-						// Add the order to the ClearingHouse which will register to the correct maker
-						match house.new_order(bid_order.clone()) {
-							Ok(()) => {
-								println!("Entering: {}:{},{}\n", id, bid_order.order_id, bid_order.price);
-								// Add the bid_order to the simulation's history
-								history.mempool_order(bid_order.clone());
-								// Send the bid_order to the MemPool
-								OrderProcessor::conc_recv_order(bid_order, Arc::clone(&mempool)).join().expect("Failed to send maker bid order");
-								
-							},
-							Err(e) => {
-								// If we failed to add the order to the player, don't send it to mempool
-								println!("{:?}", e);
-							},
+					// Keep the resting quotes if the inferred price hasn't moved past the maker's requote threshold
+					if let Some(price) = inference_data.weighted_price {
+						let requote = house.with_player(&id, |p| p.should_requote(price));
+						if requote == Some(false) {
+							continue;
 						}
+					}
 
-						// Add the order to the ClearingHouse which will register to the correct maker
-						match house.new_order(ask_order.clone()) {
-							Ok(()) => {
-								println!("Entering: {}:{},{}\n", id, ask_order.order_id, ask_order.price);
-								// Add the ask_order to the simulation's history
-								history.mempool_order(ask_order.clone());
-								// Send the ask_order to the MemPool
-								OrderProcessor::conc_recv_order(ask_order, Arc::clone(&mempool)).join().expect("Failed to send maker ask order");
-								
-							},
-							Err(e) => {
-								// If we failed to add the ask_order to the player, don't send it to mempool
-								println!("{:?}", e);
-							},
+					// Cancel the maker's current orders
+					if let Ok(cancel_orders) = house.cancel_all_orders(id.clone(), consts.mass_cancel_gas) {
+						for order in cancel_orders.iter() {
+							println!("Cancelling: {}:{},{}\n", id, order.order_id, order.price);
+							// Add the cancel order to the simulation's history
+							history.mempool_order(order.clone());
+						}
+						// Send the cancel orders to the MemPool together
+						if !cancel_orders.is_empty() {
+							OrderProcessor::conc_recv_orders(cancel_orders, Arc::clone(mempool)).join().expect("Failed to send maker cancel orders");
 						}
-					}	
+					}
 				}
+				
+				// Randomly choose whether the maker should try and enter a pair of orders
+				match Distributions::do_with_prob(consts.maker_enter_prob) {
+					true => {},
+					false => continue,	// Don't trade this batch
+				}
+
+				// Each maker interprets the data to produce their pair of new orders based on their type 
+				let data = MarketData {
+					block_num: block_num.read_count(),
+					prior: &decision_data,
+					inference: &inference_data,
+					dists,
+					consts,
+				};
+				let (bids, asks): (Vec<Order>, Vec<Order>) = house.strategy_orders(&id, &data).into_iter()
+					.partition(|o| o.trade_type == TradeType::Bid);
+				if let (Some(bid_order), Some(ask_order)) = (bids.into_iter().next(), asks.into_iter().next()) {
+					// Makers quote both sides of one of the configured symbols
+					let symbol = consts.rand_symbol();
+					let (bid_order, ask_order) = (bid_order.with_symbol(&symbol), ask_order.with_symbol(&symbol));
+					let mut quotes = Vec::new();
+
+					// Add the orders to the ClearingHouse which will register them to the correct maker
+					for order in vec![bid_order, ask_order] {
+						if let Some(order) = Simulation::admit_order(house, history, order, consts, block_num.read_count()) {
+							println!("Entering: {}:{},{}\n", id, order.order_id, order.price);
+							// Add the order to the simulation's history
+							history.mempool_order(order.clone());
+							quotes.push(order);
+						}
+					}
+
+					// Send the admitted quotes to the MemPool together
+					if !quotes.is_empty() {
+						OrderProcessor::conc_recv_orders(quotes, Arc::clone(mempool)).join().expect("Failed to send maker orders");
+					}
+				}	
 			}
+		}
+	}
+
+	pub fn maker_task(dists: Distributions, house: Arc<ClearingHouse>, mempool: Arc<MemPool>, history: Arc<History>, block_num: Arc<BlockNum>, control: Arc<SimControl>, consts: Constants) -> Task {
+		Task::seeded_rpt_task("maker", consts.seed, SimControl::stopper(&control), move || {
+			if !control.is_running(block_num.read_count()) {
+				return;
+			}
+
+			Simulation::maker_round(&dists, &house, &mempool, &history, &block_num, &consts);
 			// Wait until the next batch + maker propagation delay to rerun the maker task
 		}, consts.batch_interval + consts.maker_prop_delay)
 	}
@@ -394,11 +2309,29 @@ impl Simulation {
 	// init_player_s = a hashmap of the initial player balances and inventories
 	// fund_val: the fixed fundamental value for the simulation
 	pub fn calc_performance_results(&self, fund_val: f64, init_player_s: HashMap<String, (f64, f64)>) -> String {
+		self.performance(fund_val, init_player_s).to_csv()
+	}
+
+	/// The metrics calc_performance_results logs, for aggregating over runs
+	pub fn performance(&self, fund_val: f64, init_player_s: HashMap<String, (f64, f64)>) -> PerformanceResults {
 		let volatility = self.calc_price_volatility();
 		let rmsd = self.calc_rmsd(fund_val);
 		let (maker_profit, investor_profit, miner_profit) = self.calc_total_profit(init_player_s);
 		let (total_gas, avg_gas, total_tax, dead_weight) = self.calc_social_welfare(maker_profit, investor_profit, miner_profit);
 		
+		// The (realized, unrealized) PnL of each maker type, open positions are marked to each
+		// symbol's latest clearing price or the fundamental value if it never traded
+		let marks: HashMap<String, f64> = self.consts.symbols().into_iter()
+			.map(|symbol| {
+				let price = self.history.get_reference_price(&symbol).unwrap_or(fund_val);
+				(symbol, price)
+			})
+			.collect();
+		let mkr_pnl = self.house.get_maker_pnl(&marks);
+		let (agg_pnl, riskav_pnl, rand_pnl) = (mkr_pnl[MakerT::Aggressive as usize], mkr_pnl[MakerT::RiskAverse as usize], mkr_pnl[MakerT::Random as usize]);
+		let skew_pnl = mkr_pnl[MakerT::InventorySkew as usize];
+		let gm_pnl = mkr_pnl[MakerT::AdverseSelection as usize];
+
 		// The cummulative profit made by all of the makers
 		let mkr_profits = self.house.maker_profits.lock().unwrap();
 		// The cummulative profits made by all Aggressive type makers
@@ -407,12 +2340,32 @@ impl Simulation {
 		let riskav_profit = mkr_profits[MakerT::RiskAverse as usize];
 		// The cummulative profits made by all the Random type makers
 		let rand_profit = mkr_profits[MakerT::Random as usize];
+		// The cummulative profits made by all the InventorySkew type makers
+		let skew_profit = mkr_profits[MakerT::InventorySkew as usize];
+		// The cummulative profits made by all the AdverseSelection type makers
+		let gm_profit = mkr_profits[MakerT::AdverseSelection as usize];
 		// The number of each type of maker in the simulation
-		let (num_agg, num_riska, num_rand) = self.house.get_maker_counts();
+		let (num_agg, num_riska, num_rand, num_skew, num_gm) = self.house.get_maker_counts();
 
 		let (inv_welf, mkr_welf, min_welf) = self.calc_welfare();
 
-		format!("{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},", fund_val, total_gas, avg_gas, total_tax, maker_profit, investor_profit, miner_profit, dead_weight, volatility, rmsd, agg_profit, riskav_profit, rand_profit, num_agg, num_riska, num_rand, inv_welf, mkr_welf, min_welf)
+		// Net taker fees less maker rebates collected by the exchange
+		let exchange_revenue = self.house.get_exchange_revenue();
+
+		// Tax collected of each type
+		let taxes = self.house.get_tax_breakdown();
+		let tax_of = |basis: TaxBasis| *taxes.get(&format!("{:?}", basis)).unwrap_or(&0.0);
+
+		PerformanceResults {
+			fund_val, total_gas, avg_gas, total_tax, maker_profit, investor_profit, miner_profit, dead_weight, volatility, rmsd,
+			agg_profit, riskav_profit, rand_profit,
+			num_agg: num_agg as f64, num_riska: num_riska as f64, num_rand: num_rand as f64,
+			inv_welf, mkr_welf, min_welf, exchange_revenue,
+			agg_realized: agg_pnl.0, agg_unrealized: agg_pnl.1, riskav_realized: riskav_pnl.0, riskav_unrealized: riskav_pnl.1, rand_realized: rand_pnl.0, rand_unrealized: rand_pnl.1,
+			inventory_tax: tax_of(TaxBasis::Inventory), turnover_tax: tax_of(TaxBasis::Turnover), profit_tax: tax_of(TaxBasis::Profit),
+			skew_profit, num_skew: num_skew as f64, skew_realized: skew_pnl.0, skew_unrealized: skew_pnl.1,
+			gm_profit, num_gm: num_gm as f64, gm_realized: gm_pnl.0, gm_unrealized: gm_pnl.1,
+		}
 	}
 
 	// standard deviation of transaction price differences relative to the fundamental value
@@ -550,17 +2503,18 @@ impl Simulation {
 	// returns (maker_profit, investor_profit, miner_profit)
 	pub fn calc_total_profit(&self, init_player_s: HashMap<String, (f64, f64)>) -> (f64, f64, f64) {
 		// Get final states
-		let players = self.house.players.lock().unwrap();
+		let players = self.house.snapshot();
 		let mut investor_profit = 0.0;
 		let mut maker_profit = 0.0;
 		let mut miner_profit = 0.0;
-		for (k, p) in players.iter() {
-			match p.get_player_type() {
+		for p in players.iter() {
+			let k = &p.trader_id;
+			match p.player_type {
 				TraderT::Maker => {
 					// get initial bal and inv
 					let (init_bal, _init_inv) = init_player_s.get(&k.clone()).expect("calc_total_profit");
-					let cur_bal = p.get_bal();
-					let _cur_inv = p.get_inv();
+					let cur_bal = p.balance;
+					let _cur_inv = p.total_inv();
 					let profit = cur_bal - init_bal;
 					maker_profit += profit;
 				},
@@ -568,8 +2522,8 @@ impl Simulation {
 					// get initial bal and inv
 					let (init_bal, _init_inv) = init_player_s.get(&k.clone()).expect("calc_total_profit");
 					// search current bal and inv
-					let cur_bal = p.get_bal();
-					let _cur_inv = p.get_inv();
+					let cur_bal = p.balance;
+					let _cur_inv = p.total_inv();
 					let profit = cur_bal - init_bal;
 					investor_profit += profit;
 				},
@@ -577,17 +2531,121 @@ impl Simulation {
 					// get initial bal and inv
 					let (init_bal, _init_inv) = init_player_s.get(&k.clone()).expect("calc_total_profit");
 					// search current bal and inv
-					let cur_bal = p.get_bal();
-					let _cur_inv = p.get_inv();
+					let cur_bal = p.balance;
+					let _cur_inv = p.total_inv();
 					let profit = cur_bal - init_bal;
 					miner_profit += profit;
 				},
+				// Reported separately by calc_arb_profit
+				TraderT::Arbitrageur => {},
+				// Baseline traders aren't counted towards the investors
+				TraderT::ZeroIntel|TraderT::Momentum|TraderT::MeanReversion|TraderT::Informed|TraderT::Noise|TraderT::Spoofer|TraderT::Sniper|TraderT::Learner|TraderT::Bridge|TraderT::Twap|TraderT::Vwap|TraderT::Searcher => {},
 			}
 		}
 
 		(maker_profit, investor_profit, miner_profit)
 	}
 
+	// The profit made by the arbitrageur trading between linked markets
+	pub fn calc_arb_profit(&self, init_player_s: &HashMap<String, (f64, f64)>) -> f64 {
+		self.house.snapshot().iter()
+			.filter(|p| p.player_type == TraderT::Arbitrageur)
+			.map(|p| p.balance - init_player_s.get(&p.trader_id).expect("calc_arb_profit").0)
+			.sum()
+	}
+
+	/// The snipers' total change in balance
+	pub fn calc_sniper_profit(&self, init_player_s: &HashMap<String, (f64, f64)>) -> f64 {
+		self.house.snapshot().iter()
+			.filter(|p| p.player_type == TraderT::Sniper)
+			.map(|p| p.balance - init_player_s.get(&p.trader_id).expect("calc_sniper_profit").0)
+			.sum()
+	}
+
+
+	/// Each maker's id, type, spread, quote size, skew and requote threshold followed by its
+	/// balance and inventory, as csv rows. An unset spread is left empty.
+	pub fn calc_maker_params(&self) -> Vec<String> {
+		self.house.get_maker_params().into_iter()
+			.map(|(id, maker_type, p, balance, inventory)| {
+				let spread = p.spread.map(|s| s.to_string()).unwrap_or_default();
+				format!("{},{:?},{},{},{},{},{},{},", id, maker_type, spread, p.quote_size, p.skew, p.requote_threshold, balance, inventory)
+			})
+			.collect()
+	}
+
+	/// Each maker's and investor's id, type and risk aversion followed by its profit, as csv rows
+	pub fn calc_risk_profits(&self, init_player_s: &HashMap<String, (f64, f64)>) -> Vec<String> {
+		self.house.snapshot().iter()
+			.filter(|p| p.player_type == TraderT::Maker || p.player_type == TraderT::Investor)
+			.filter_map(|p| init_player_s.get(&p.trader_id).map(|(init_bal, _inv)| (p, p.balance - init_bal)))
+			.map(|(p, profit)| format!("{},{:?},{},{},", p.trader_id, p.player_type, p.risk_aversion, profit))
+			.collect()
+	}
+
+	/// Each TWAP agent's execution as a csv row: side, parent and filled shares, average fill price,
+	/// arrival and final prices, and the implementation shortfall in cash and in bps of the parent's
+	/// arrival value. The arrival price is the last fill before the start block.
+	pub fn calc_execution_results(&self) -> Vec<String> {
+		let twap = Twap::new(&self.consts);
+		let fills = self.history.get_fills();
+		let arrival = fills.iter().filter(|f| f.block_num < twap.start_block).last().or(fills.first()).map(|f| f.price);
+		let (arrival, final_price) = match (arrival, fills.last()) {
+			(Some(arrival), Some(last)) => (arrival, last.price),
+			_ => return Vec::new(),
+		};
+
+		self.house.query().of_type(TraderT::Twap).ids().into_iter()
+			.map(|id| {
+				let exec = Simulation::execution_fills(&self.history, &fills, &id, &twap.trade_type);
+				let filled: f64 = exec.iter().map(|(_p, q)| q).sum();
+				let avg_price = match filled > 0.0 {
+					true => (exec.iter().map(|(p, q)| p * q).sum::<f64>() / filled).to_string(),
+					false => String::new(),
+				};
+				let shortfall = twap.shortfall(arrival, &exec, final_price);
+				let bps = shortfall / (twap.parent_qty * arrival) * 10000.0;
+				format!("{},{:?},{},{},{},{},{},{},{},", id, twap.trade_type, twap.parent_qty, filled, avg_price, arrival, final_price, shortfall, bps)
+			})
+			.collect()
+	}
+
+	/// Each VWAP agent's execution as a csv row: side, parent and filled shares, average fill price,
+	/// the market VWAP over the horizon and the tracking error against it in bps
+	pub fn calc_vwap_results(&self) -> Vec<String> {
+		let vwap = Vwap::new(&self.consts);
+		let fills = self.history.get_fills();
+		let horizon: Vec<&Fill> = fills.iter().filter(|f| vwap.is_active(f.block_num)).collect();
+		let volume: f64 = horizon.iter().map(|f| f.quantity).sum();
+		if volume <= 0.0 {
+			return Vec::new();
+		}
+		let market_vwap = horizon.iter().map(|f| f.price * f.quantity).sum::<f64>() / volume;
+
+		self.house.query().of_type(TraderT::Vwap).ids().into_iter()
+			.map(|id| {
+				let exec = Simulation::execution_fills(&self.history, &fills, &id, &vwap.trade_type);
+				let filled: f64 = exec.iter().map(|(_p, q)| q).sum();
+				let (avg_price, tracking_error) = match filled > 0.0 {
+					true => {
+						let avg_price = exec.iter().map(|(p, q)| p * q).sum::<f64>() / filled;
+						(avg_price.to_string(), vwap.tracking_error(avg_price, market_vwap).to_string())
+					},
+					false => (String::new(), String::new()),
+				};
+				format!("{},{:?},{},{},{},{},{},", id, vwap.trade_type, vwap.parent_qty, filled, avg_price, market_vwap, tracking_error)
+			})
+			.collect()
+	}
+
+	/// Each maker type's average max drawdown, current drawdown and VaR, as csv in MakerT order
+	pub fn calc_risk_results(&self) -> String {
+		self.house.get_maker_risk(VAR_CONFIDENCE).iter()
+			.map(|m| format!("{},{},{}", m.max_drawdown, m.current_drawdown, m.var))
+			.collect::<Vec<String>>()
+			.join(",")
+	}
+
 
 	pub fn calc_welfare(&self) -> (f64, f64, f64){
 		let history = &self.history;
@@ -650,6 +2708,7 @@ impl Simulation {
 							TraderT::Miner => {
 								min_welf += welfare;
 							},
+							TraderT::Arbitrageur|TraderT::ZeroIntel|TraderT::Momentum|TraderT::MeanReversion|TraderT::Informed|TraderT::Noise|TraderT::Spoofer|TraderT::Sniper|TraderT::Learner|TraderT::Bridge|TraderT::Twap|TraderT::Vwap|TraderT::Searcher => {},
 						}
 					}
 					
@@ -667,10 +2726,11 @@ impl Simulation {
 							TraderT::Miner => {
 								min_welf += welfare;
 							},
+							TraderT::Arbitrageur|TraderT::ZeroIntel|TraderT::Momentum|TraderT::MeanReversion|TraderT::Informed|TraderT::Noise|TraderT::Spoofer|TraderT::Sniper|TraderT::Learner|TraderT::Bridge|TraderT::Twap|TraderT::Vwap|TraderT::Searcher => {},
 						}
 					}
 				},
-				MarketType::FBA|MarketType::CDA => {
+				MarketType::FBA|MarketType::CDA|MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => {
 					if bidder {
 						// Positive welfare if they bought at a lower price than they bid
 						let welfare = (bid_price - tx.price) * tx.volume;
@@ -685,6 +2745,7 @@ impl Simulation {
 							TraderT::Miner => {
 								min_welf += welfare;
 							},
+							TraderT::Arbitrageur|TraderT::ZeroIntel|TraderT::Momentum|TraderT::MeanReversion|TraderT::Informed|TraderT::Noise|TraderT::Spoofer|TraderT::Sniper|TraderT::Learner|TraderT::Bridge|TraderT::Twap|TraderT::Vwap|TraderT::Searcher => {},
 						}
 					}
 					
@@ -702,6 +2763,7 @@ impl Simulation {
 							TraderT::Miner => {
 								min_welf += welfare;
 							},
+							TraderT::Arbitrageur|TraderT::ZeroIntel|TraderT::Momentum|TraderT::MeanReversion|TraderT::Informed|TraderT::Noise|TraderT::Spoofer|TraderT::Sniper|TraderT::Learner|TraderT::Bridge|TraderT::Twap|TraderT::Vwap|TraderT::Searcher => {},
 						}
 					}
 				},
@@ -1,8 +1,13 @@
 // File for loading in all the parameters for the simulation and then
 // setting up the appropriate constants and distributions.
-use crate::exchange::MarketType;
+use crate::exchange::{MarketType, TieBreak, DefaultRule, BudgetRule};
+use crate::exchange::pricing::PricingRuleT;
+use crate::players::miner_strategy::{MinerStrategyT, MinerRegime};
+use crate::blockchain::eviction::EvictionPolicy;
+use crate::order::order::DEFAULT_SYMBOL;
 
-use rand::thread_rng;
+use crate::utility::sim_rng;
+use rand::seq::SliceRandom;
 use rand::distributions::{Distribution};
 
 #[derive(Clone, Copy, Debug, Deserialize)]
@@ -22,6 +27,292 @@ pub struct Constants {
 	pub maker_inv_tax: f64,
 	pub maker_cold_start: u64,	// Amount of blocks to wait before makers start submitting orders
 	pub maker_update_prob: f64,
+	#[serde(default)]
+	pub mass_cancel_gas: Option<f64>,	// If set, makers pull quotes with one MassCancel per side at this gas
+	#[serde(default)]
+	pub opening_auction_blocks: u64,	// CDA only: blocks orders accumulate before the opening call auction
+	#[serde(default)]
+	pub closing_auction_blocks: u64,	// CDA only: blocks orders accumulate before the closing call auction
+	#[serde(default)]
+	pub circuit_breaker_pct: f64,	// Halt trading if the price moves more than this fraction, 0 disables
+	#[serde(default)]
+	pub circuit_breaker_window: u64,	// Number of blocks the price move is measured over
+	#[serde(default)]
+	pub halt_blocks: u64,	// Number of blocks trading is halted for
+	#[serde(default)]
+	pub price_band_pct: f64,	// Reject orders priced outside ±this fraction of the last clearing price, 0 disables
+	#[serde(default)]
+	pub investor_dark_prob: f64,	// Probability an investor routes their order to the DarkBook
+	#[serde(default)]
+	pub amm_reserve_shares: f64,	// AMM only: initial shares held by the pool
+	#[serde(default)]
+	pub amm_reserve_cash: f64,	// AMM only: initial cash held by the pool
+	#[serde(default)]
+	pub amm_fee: f64,	// AMM only: fraction of each swap kept by the pool
+	#[serde(default)]
+	pub num_symbols: u64,	// Number of instruments investors and makers trade, 0 is treated as 1
+	#[serde(default)]
+	pub second_market_type: Option<MarketType>,	// If set, runs a linked second market of this type
+	#[serde(default)]
+	pub arb_quantity: f64,	// Shares per arbitrage leg between the linked markets, 0 disables the arbitrageur
+	#[serde(default)]
+	pub taker_fee: f64,		// Fraction of a fill's notional charged to the side removing liquidity
+	#[serde(default)]
+	pub maker_rebate: f64,	// Fraction of a fill's notional paid to the side providing liquidity
+	#[serde(default)]
+	pub indicative_interval: u64,	// Millis between indicative price and imbalance updates in FBA/KLF, 0 disables
+	#[serde(default)]
+	pub tie_break: TieBreak,	// How the FBA picks between clearing prices that clear the same volume
+	#[serde(default)]
+	pub flow_schedule_points: usize,	// KLF only: breakpoints in investors' flow schedules, under 2 keeps the linear ramp
+	#[serde(default)]
+	pub flow_schedule_curvature: f64,	// KLF only: exponent shaping the flow schedules, 1 is linear and 0 is treated as 1
+	#[serde(default)]
+	pub pay_as_bid: bool,	// SealedBid only: fills trade at each bid's price instead of a uniform price
+	#[serde(default)]
+	pub dutch_start_price: f64,	// Dutch only: the issuer's ask in the first block
+	#[serde(default)]
+	pub dutch_decrement: f64,	// Dutch only: how far the ask falls each block
+	#[serde(default)]
+	pub dutch_floor_price: f64,	// Dutch only: the ask never falls below this price
+	#[serde(default)]
+	pub dutch_supply: f64,	// Dutch only: total shares the issuer sells
+	#[serde(default)]
+	pub volatility_auction_pct: f64,	// CDA only: switch to a volatility auction if the price moves more than this fraction, 0 disables
+	#[serde(default)]
+	pub volatility_window: u64,	// CDA only: number of blocks the volatility trigger's price move is measured over
+	#[serde(default)]
+	pub volatility_auction_blocks: u64,	// CDA only: number of blocks a volatility auction lasts
+	#[serde(default)]
+	pub settlement_lag: u64,	// Fills settle this many blocks after they execute (T+n), 0 settles immediately
+	#[serde(default)]
+	pub min_inventory: Option<f64>,	// If set, asks that could take a player's inventory below this are rejected
+	#[serde(default)]
+	pub borrow_fee: f64,	// Charged per block on each share of negative inventory
+	#[serde(default)]
+	pub pricing_rule: PricingRuleT,	// How the FBA picks its uniform clearing price
+	#[serde(default)]
+	pub maintenance_margin: Option<f64>,	// If set, players whose marked equity falls below this are force-liquidated
+	#[serde(default)]
+	pub max_position: Option<f64>,	// If set, orders that could take a player's absolute inventory past this are rejected
+	#[serde(default)]
+	pub max_order_size: Option<f64>,	// If set, orders for more shares than this are rejected
+	#[serde(default)]
+	pub default_balance: Option<f64>,	// If set, players whose balance falls below this default
+	#[serde(default)]
+	pub default_rule: DefaultRule,	// Whether a defaulted player's debt is written off or socialized
+	#[serde(default)]
+	pub interest_rate: f64,	// Interest per block on every player's balance, negative balances pay it
+	#[serde(default)]
+	pub carry_cost: Option<f64>,	// If set, charged per block on each share of inventory held, long or short
+	#[serde(default)]
+	pub gas_surcharge: f64,	// Fraction of the gas each player pays charged on top as a surcharge
+	#[serde(default)]
+	pub valuation_interval: u64,	// Players are revalued and their equity recorded every this many blocks, 0 only values them at liquidation
+	#[serde(default)]
+	pub payment_interval: u64,	// Dividends and funding are paid every this many blocks, 0 disables them
+	#[serde(default)]
+	pub dividend_yield: f64,	// Each dividend pays this fraction of the fundamental value per share held, shorts pay it
+	#[serde(default)]
+	pub funding_rate: f64,	// Longs pay shorts this fraction of the reference price's premium over the fundamental value per share
+	#[serde(default)]
+	pub num_zi_traders: u64,	// Zero-intelligence (ZI-C) traders registered, 0 disables them
+	#[serde(default)]
+	pub zi_units: f64,	// Shares each ZI trader is endowed to buy or sell
+	#[serde(default)]
+	pub zi_max_price: f64,	// ZI sellers ask at most this price
+	#[serde(default)]
+	pub num_momentum_traders: u64,	// Momentum traders registered, 0 disables them
+	#[serde(default)]
+	pub momentum_short_window: u64,	// Momentum only: clearings in the short moving average
+	#[serde(default)]
+	pub momentum_long_window: u64,	// Momentum only: clearings in the long moving average
+	#[serde(default)]
+	pub momentum_threshold: f64,	// Momentum only: relative gap between the averages needed to trade
+	#[serde(default)]
+	pub momentum_participation: f64,	// Momentum only: fraction of the average clearing volume traded at the threshold
+	#[serde(default)]
+	pub num_mean_reversion_traders: u64,	// Mean reversion traders registered, 0 disables them
+	#[serde(default)]
+	pub mean_reversion_noise: f64,	// Mean reversion only: std dev of the noise in their observation of the fundamental value
+	#[serde(default)]
+	pub mean_reversion_threshold: f64,	// Mean reversion only: relative deviation from the observed value needed to trade
+	#[serde(default)]
+	pub mean_reversion_quantity: f64,	// Mean reversion only: shares per order
+	#[serde(default)]
+	pub mean_reversion_max_inventory: f64,	// Mean reversion only: orders never take their absolute inventory past this
+	#[serde(default)]
+	pub fundamental_jump_prob: f64,	// Chance each block that a jump in the fundamental value is scheduled, 0 keeps it fixed
+	#[serde(default)]
+	pub fundamental_jump_std: f64,	// Std dev of each fundamental value jump
+	#[serde(default)]
+	pub fundamental_jump_lead: u64,	// Blocks between a jump being scheduled and applied, the window informed traders trade in
+	#[serde(default)]
+	pub num_informed_traders: u64,	// Informed traders registered, 0 disables them
+	#[serde(default)]
+	pub informed_signal_prob: f64,	// Informed only: chance a signal of the next jump arrives each time they trade
+	#[serde(default)]
+	pub informed_noise: f64,	// Informed only: std dev of the noise in the signal
+	#[serde(default)]
+	pub informed_quantity: f64,	// Informed only: shares per order
+	#[serde(default)]
+	pub num_noise_traders: u64,	// Noise traders registered, 0 disables them
+	#[serde(default)]
+	pub noise_submit_rate: f64,	// Noise only: orders the noise traders submit per second
+	#[serde(default)]
+	pub noise_cancel_rate: f64,	// Noise only: resting orders the noise traders cancel per second
+	#[serde(default)]
+	pub num_spoofers: u64,	// Spoofers registered, 0 disables them
+	#[serde(default)]
+	pub spoof_quantity: f64,	// Spoof only: shares in each fake order
+	#[serde(default)]
+	pub spoof_offset: f64,	// Spoof only: fraction of the touch fake orders are placed away from it
+	#[serde(default)]
+	pub spoof_trade_quantity: f64,	// Spoof only: shares traded opposite each fake order
+	#[serde(default)]
+	pub num_snipers: u64,	// Snipers registered, 0 disables them
+	#[serde(default)]
+	pub sniper_gas_increment: f64,	// Sniper only: gas paid above the cancel raced, 0 always pays sniper_max_gas
+	#[serde(default)]
+	pub sniper_max_gas: f64,	// Sniper only: the most gas paid to race a cancel
+	#[serde(default)]
+	pub bridge_port: u16,	// Bridge only: localhost port of the external agent, 0 disables the bridge
+	#[serde(default)]
+	pub bridge_timeout: u64,	// Bridge only: millis to wait for the external agent's reply before doing nothing that block
+	#[serde(default)]
+	pub bridge_depth: usize,	// Bridge only: price levels of each side of the book sent to the external agent
+	#[serde(default)]
+	pub num_skew_makers: u64,	// How many of the makers quote as InventorySkew makers
+	#[serde(default)]
+	pub skew_risk_aversion: f64,	// InventorySkew only: risk aversion, higher skews quotes harder against inventory
+	#[serde(default)]
+	pub skew_intensity: f64,	// InventorySkew only: how quickly fill intensity decays with distance from the mid
+	#[serde(default)]
+	pub num_gm_makers: u64,	// How many of the makers after the InventorySkew makers quote as AdverseSelection makers
+	#[serde(default)]
+	pub gm_value_spread: f64,	// AdverseSelection only: how far the value may be from the mid when flow is informed, raised to the clearing price volatility
+	#[serde(default)]
+	pub gm_min_informed: f64,	// AdverseSelection only: probability of informed flow assumed when order flow is balanced
+	#[serde(default)]
+	pub investor_stale_blocks: u64,	// Blocks an investor order rests without a fill before it may be cancelled or repriced, 0 leaves them resting
+	#[serde(default)]
+	pub investor_cancel_prob: f64,	// Chance each block an investor cancels a stale order
+	#[serde(default)]
+	pub investor_reprice_prob: f64,	// Chance each block an investor reprices a stale order it didn't cancel
+	#[serde(default)]
+	pub num_twap_agents: u64,	// TWAP execution agents registered, 0 disables them
+	#[serde(default)]
+	pub twap_quantity: f64,	// TWAP only: parent shares each agent executes, positive buys and negative sells
+	#[serde(default)]
+	pub twap_start_block: u64,	// TWAP only: block the execution starts
+	#[serde(default)]
+	pub twap_horizon: u64,	// TWAP only: blocks the parent is sliced across
+	#[serde(default)]
+	pub num_vwap_agents: u64,	// VWAP execution agents registered, 0 disables them
+	#[serde(default)]
+	pub vwap_quantity: f64,	// VWAP only: parent shares each agent executes, positive buys and negative sells
+	#[serde(default)]
+	pub vwap_start_block: u64,	// VWAP only: block the execution starts
+	#[serde(default)]
+	pub vwap_horizon: u64,	// VWAP only: blocks the parent is traded across
+	#[serde(default)]
+	pub vwap_forecast_alpha: f64,	// VWAP only: weight of the latest block in the volume forecast
+	#[serde(default)]
+	pub vwap_catch_up: f64,	// VWAP only: extra fraction of the deficit sent when behind schedule
+	#[serde(default)]
+	pub num_adaptive_makers: u64,	// Makers whose spread and quote size are learned by a bandit, 0 disables it
+	#[serde(default)]
+	pub adapt_interval: u64,	// Adaptive only: blocks each maker trades an arm before it is credited and switched
+	#[serde(default)]
+	pub adapt_epsilon: f64,	// Adaptive only: chance of trying a random arm instead of the best
+	#[serde(default)]
+	pub population_interval: u64,	// Blocks between population turnovers, 0 keeps the population fixed
+	#[serde(default)]
+	pub population_exit_frac: f64,	// Population only: fraction of the makers and of the investors with the least equity that retire
+	#[serde(default)]
+	pub population_imitate_prob: f64,	// Population only: chance a retiree is replaced by an imitator of one of the richest instead of a newcomer
+	#[serde(default)]
+	pub budget_rule: BudgetRule,	// Whether orders beyond a player's free balance or inventory are let through, rejected or scaled down
+	#[serde(default)]
+	pub miner_strategy: MinerStrategyT,	// How the miner builds its frames and which orders it front-runs
+	#[serde(default)]
+	pub num_miners: u64,	// Miners competing for each block, drawn as the winner by MinerHashPower
+	#[serde(default)]
+	pub sandwich_min_quantity: f64,	// Sandwich only: smallest marketable order the miner sandwiches
+	#[serde(default)]
+	pub sandwich_quantity: f64,	// Sandwich only: shares the miner trades ahead of the victim and unwinds behind it
+	#[serde(default)]
+	pub num_searchers: u64,	// Searchers bidding the miner for bundles
+	#[serde(default)]
+	pub searcher_min_quantity: f64,	// Searcher only: smallest marketable order a searcher bundles
+	#[serde(default)]
+	pub searcher_quantity: f64,	// Searcher only: shares a searcher trades around its victim
+	#[serde(default)]
+	pub searcher_bid_share: f64,	// Searcher only: share of a bundle's expected profit bid to the miner
+	#[serde(default)]
+	pub censor_gas_floor: f64,	// Censoring only: orders paying less gas than this are left out of the miner's frames
+	#[serde(default)]
+	pub miner_regime: MinerRegime,	// When the miner follows miner_strategy and when it's honest
+	#[serde(default)]
+	pub block_gas_limit: f64,	// Gas the orders of a block may use, 0 for block_size orders of any type
+	#[serde(default)]
+	pub enter_gas_used: f64,	// Block gas used by an Enter order, 0 for 3
+	#[serde(default)]
+	pub update_gas_used: f64,	// Block gas used by an Update order, 0 for 2
+	#[serde(default)]
+	pub cancel_gas_used: f64,	// Block gas used by a Cancel or MassCancel order, 0 for 1
+	#[serde(default)]
+	pub mempool_capacity: usize,	// The most orders the mempool holds, 0 for no limit
+	#[serde(default)]
+	pub mempool_eviction: EvictionPolicy,	// Which order a full mempool evicts
+	#[serde(default)]
+	pub evicted_resubmit_prob: f64,	// Probability a player resubmits an evicted order
+	#[serde(default)]
+	pub evicted_gas_bump: f64,	// Fraction the gas of a resubmitted evicted order is raised by
+	#[serde(default)]
+	pub rbf_prob: f64,	// Probability an investor replaces each of its pending orders with a higher gas one
+	#[serde(default)]
+	pub rbf_gas_bump: f64,	// Fraction a replaced pending order's gas is raised by
+	#[serde(default)]
+	pub miner_gossip_delay: u64,	// Milliseconds before the miner sees an order that reached the mempool
+	#[serde(default)]
+	pub maker_gossip_delay: u64,	// Milliseconds before the makers see an order that reached the mempool
+	#[serde(default)]
+	pub sniper_gossip_delay: u64,	// Milliseconds before the snipers see an order that reached the mempool
+	#[serde(default)]
+	pub num_regions: usize,	// Regional mempools the traders are partitioned across, 1 for a single mempool
+	#[serde(default)]
+	pub region_sync_interval: u64,	// Milliseconds between syncs of the regional mempools
+	#[serde(default)]
+	pub reorg_prob: f64,	// Probability a published block is orphaned, its trades rolled back and its orders mined again
+	#[serde(default)]
+	pub private_flow_prob: f64,	// Probability an investor sends its order privately to the miner instead of the public mempool
+	#[serde(default)]
+	pub private_flow_fee: f64,	// Fee an investor pays the miner for each private order mined
+	#[serde(default)]
+	pub commit_reveal: bool,	// Investors commit to a hash of each order a block before revealing it
+	#[serde(default)]
+	pub encrypted_mempool: bool,	// Orders' contents are hidden in the mempool until the block they are mined in is formed
+	#[serde(default)]
+	pub encryption_leak_prob: f64,	// Probability an encrypted order's contents leak to the miner and observers anyway
+	#[serde(default)]
+	pub gas_oracle_window: usize,	// Recent blocks the gas oracle suggests gas from, 0 leaves agents sampling their gas
+	#[serde(default)]
+	pub gas_oracle_percentile: f64,	// Percentile of recent and pending gas the oracle suggests
+	#[serde(default)]
+	pub max_pending_per_trader: usize,	// Orders a trader may have waiting in the mempool, 0 for no limit
+	#[serde(default)]
+	pub max_orders_per_block: usize,	// Orders a trader may send to the mempool each block, 0 for no limit
+	#[serde(default)]
+	pub spam_gas_escalation: f64,	// Gas surcharge per order over a rate limit, 0 rejects the orders over
+	#[serde(default)]
+	pub discrete_event: bool,	// Run the investors, makers and miners as events on a virtual clock instead of real-time tasks, configs starting other agents are refused
+	#[serde(default)]
+	pub seed: u64,	// Master seed for the random draws, 0 draws from entropy
+	#[serde(default)]
+	pub time_scale: f64,	// How many times faster than its sampled delays and intervals a real-time run goes, 0 or 1 for as sampled
+	#[serde(default)]
+	pub checkpoint_block: u64,	// Block the --checkpoint is saved after publishing, 0 for the last block
 }
 
 impl Constants {
@@ -44,12 +335,234 @@ impl Constants {
 			maker_inv_tax: mit,
 			maker_cold_start: mcs,
 			maker_update_prob: mup,
+			mass_cancel_gas: None,
+			opening_auction_blocks: 0,
+			closing_auction_blocks: 0,
+			circuit_breaker_pct: 0.0,
+			circuit_breaker_window: 0,
+			halt_blocks: 0,
+			price_band_pct: 0.0,
+			investor_dark_prob: 0.0,
+			amm_reserve_shares: 0.0,
+			amm_reserve_cash: 0.0,
+			amm_fee: 0.0,
+			num_symbols: 1,
+			second_market_type: None,
+			arb_quantity: 0.0,
+			taker_fee: 0.0,
+			maker_rebate: 0.0,
+			indicative_interval: 0,
+			tie_break: TieBreak::Midpoint,
+			flow_schedule_points: 0,
+			flow_schedule_curvature: 1.0,
+			pay_as_bid: false,
+			dutch_start_price: 0.0,
+			dutch_decrement: 0.0,
+			dutch_floor_price: 0.0,
+			dutch_supply: 0.0,
+			volatility_auction_pct: 0.0,
+			volatility_window: 0,
+			volatility_auction_blocks: 0,
+			settlement_lag: 0,
+			min_inventory: None,
+			borrow_fee: 0.0,
+			pricing_rule: PricingRuleT::MaxVolume,
+			maintenance_margin: None,
+			max_position: None,
+			max_order_size: None,
+			default_balance: None,
+			default_rule: DefaultRule::WriteOff,
+			interest_rate: 0.0,
+			carry_cost: None,
+			gas_surcharge: 0.0,
+			valuation_interval: 0,
+			payment_interval: 0,
+			dividend_yield: 0.0,
+			funding_rate: 0.0,
+			num_zi_traders: 0,
+			zi_units: 0.0,
+			zi_max_price: 0.0,
+			num_momentum_traders: 0,
+			momentum_short_window: 0,
+			momentum_long_window: 0,
+			momentum_threshold: 0.0,
+			momentum_participation: 0.0,
+			num_mean_reversion_traders: 0,
+			mean_reversion_noise: 0.0,
+			mean_reversion_threshold: 0.0,
+			mean_reversion_quantity: 0.0,
+			mean_reversion_max_inventory: 0.0,
+			fundamental_jump_prob: 0.0,
+			fundamental_jump_std: 0.0,
+			fundamental_jump_lead: 0,
+			num_informed_traders: 0,
+			informed_signal_prob: 0.0,
+			informed_noise: 0.0,
+			informed_quantity: 0.0,
+			num_noise_traders: 0,
+			noise_submit_rate: 0.0,
+			noise_cancel_rate: 0.0,
+			num_spoofers: 0,
+			spoof_quantity: 0.0,
+			spoof_offset: 0.0,
+			spoof_trade_quantity: 0.0,
+			num_snipers: 0,
+			sniper_gas_increment: 0.0,
+			sniper_max_gas: 0.0,
+			bridge_port: 0,
+			bridge_timeout: 100,
+			bridge_depth: 5,
+			num_skew_makers: 0,
+			skew_risk_aversion: 0.1,
+			skew_intensity: 1.5,
+			num_gm_makers: 0,
+			gm_value_spread: 5.0,
+			gm_min_informed: 0.1,
+			investor_stale_blocks: 0,
+			investor_cancel_prob: 0.0,
+			investor_reprice_prob: 0.0,
+			num_twap_agents: 0,
+			twap_quantity: 0.0,
+			twap_start_block: 0,
+			twap_horizon: 10,
+			num_vwap_agents: 0,
+			vwap_quantity: 0.0,
+			vwap_start_block: 0,
+			vwap_horizon: 10,
+			vwap_forecast_alpha: 0.3,
+			vwap_catch_up: 0.5,
+			num_adaptive_makers: 0,
+			adapt_interval: 10,
+			adapt_epsilon: 0.1,
+			population_interval: 0,
+			population_exit_frac: 0.1,
+			population_imitate_prob: 0.5,
+			budget_rule: BudgetRule::Off,
+			miner_strategy: MinerStrategyT::StrategicFrontRun,
+			num_miners: 1,
+			sandwich_min_quantity: 10.0,
+			sandwich_quantity: 5.0,
+			num_searchers: 0,
+			searcher_min_quantity: 10.0,
+			searcher_quantity: 5.0,
+			searcher_bid_share: 0.5,
+			censor_gas_floor: 0.0,
+			miner_regime: MinerRegime::Fixed,
+			block_gas_limit: 0.0,
+			enter_gas_used: 3.0,
+			update_gas_used: 2.0,
+			cancel_gas_used: 1.0,
+			mempool_capacity: 0,
+			mempool_eviction: EvictionPolicy::LowestGas,
+			evicted_resubmit_prob: 0.5,
+			evicted_gas_bump: 0.25,
+			rbf_prob: 0.0,
+			rbf_gas_bump: 0.25,
+			miner_gossip_delay: 0,
+			maker_gossip_delay: 0,
+			sniper_gossip_delay: 0,
+			num_regions: 1,
+			region_sync_interval: 100,
+			reorg_prob: 0.0,
+			private_flow_prob: 0.0,
+			private_flow_fee: 0.0,
+			commit_reveal: false,
+			encrypted_mempool: false,
+			encryption_leak_prob: 0.0,
+			gas_oracle_window: 0,
+			gas_oracle_percentile: 0.5,
+			max_pending_per_trader: 0,
+			max_orders_per_block: 0,
+			spam_gas_escalation: 0.0,
+			discrete_event: false,
+			seed: 0,
+			time_scale: 1.0,
+			checkpoint_block: 0,
 		}
 	}
 
+	// The instruments traded in the simulation: SYM0 .. SYM{num_symbols - 1}
+	pub fn symbols(&self) -> Vec<String> {
+		if self.num_symbols <= 1 {
+			return vec![String::from(DEFAULT_SYMBOL)];
+		}
+		(0..self.num_symbols).map(|i| format!("SYM{}", i)).collect()
+	}
+
+	// Picks one of the simulation's symbols uniformly at random
+	pub fn rand_symbol(&self) -> String {
+		self.symbols().choose(&mut sim_rng()).expect("rand_symbol").clone()
+	}
+
+	// The flow schedule exponent, an unset curvature is linear
+	pub fn flow_curvature(&self) -> f64 {
+		if self.flow_schedule_curvature == 0.0 {
+			return 1.0;
+		}
+		self.flow_schedule_curvature
+	}
+
+	// The block gas each order type uses, an unset one falls back to Constants::new's so a block
+	// gas limit still binds when only it is set
+	pub fn enter_gas(&self) -> f64 {
+		if self.enter_gas_used == 0.0 {
+			return 3.0;
+		}
+		self.enter_gas_used
+	}
+
+	pub fn update_gas(&self) -> f64 {
+		if self.update_gas_used == 0.0 {
+			return 2.0;
+		}
+		self.update_gas_used
+	}
+
+	pub fn cancel_gas(&self) -> f64 {
+		if self.cancel_gas_used == 0.0 {
+			return 1.0;
+		}
+		self.cancel_gas_used
+	}
+
+	// The block a checkpoint is taken after, the last one unless checkpoint_block is set
+	pub fn checkpoint_after(&self) -> u64 {
+		if self.checkpoint_block == 0 {
+			return self.num_blocks;
+		}
+		self.checkpoint_block
+	}
+
+	// The settings that start agents or per-block tasks only run as real-time tasks. The
+	// discrete-event engine runs the investors, makers, miners and fundamental value alone.
+	pub fn real_time_only(&self) -> Vec<&'static str> {
+		let set = [
+			(self.second_market_type.is_some(), "second_market_type"),
+			(self.num_noise_traders > 0, "num_noise_traders"),
+			(self.indicative_interval > 0 && (self.market_type == MarketType::FBA || self.market_type == MarketType::KLF), "indicative_interval"),
+			(self.interest_rate != 0.0 || self.carry_cost.is_some(), "interest_rate/carry_cost"),
+			(self.valuation_interval > 0, "valuation_interval"),
+			(self.payment_interval > 0, "payment_interval"),
+			(self.num_zi_traders > 0, "num_zi_traders"),
+			(self.num_momentum_traders > 0, "num_momentum_traders"),
+			(self.num_adaptive_makers > 0, "num_adaptive_makers"),
+			(self.population_interval > 0, "population_interval"),
+			(self.num_twap_agents > 0, "num_twap_agents"),
+			(self.num_vwap_agents > 0, "num_vwap_agents"),
+			(self.num_searchers > 0, "num_searchers"),
+			(self.num_mean_reversion_traders > 0, "num_mean_reversion_traders"),
+			(self.num_regions > 1, "num_regions"),
+			(self.num_informed_traders > 0, "num_informed_traders"),
+			(self.num_spoofers > 0, "num_spoofers"),
+			(self.num_snipers > 0, "num_snipers"),
+			(self.bridge_port != 0, "bridge_port"),
+		];
+		set.iter().filter(|(on, _)| *on).map(|(_, name)| *name).collect()
+	}
+
 	pub fn log(&self) -> String {
-		let h = format!("\nbatch_interval,num_investors,num_makers,block_size,num_blocks,market_type,front_run_perc,flow_order_offset,maker_prop_delay,maker_base_spread,maker_enter_prob,max_held_inventory,maker_inv_tax,maker_cold_start,maker_update_prob,");
-		let d = format!("{},{},{},{},{},{:?},{},{},{},{},{},{},{},{},{},",
+		let h = format!("\nbatch_interval,num_investors,num_makers,block_size,num_blocks,market_type,front_run_perc,flow_order_offset,maker_prop_delay,maker_base_spread,maker_enter_prob,max_held_inventory,maker_inv_tax,maker_cold_start,maker_update_prob,mass_cancel_gas,opening_auction_blocks,closing_auction_blocks,circuit_breaker_pct,circuit_breaker_window,halt_blocks,price_band_pct,investor_dark_prob,amm_reserve_shares,amm_reserve_cash,amm_fee,num_symbols,second_market_type,arb_quantity,taker_fee,maker_rebate,indicative_interval,tie_break,flow_schedule_points,flow_schedule_curvature,pay_as_bid,dutch_start_price,dutch_decrement,dutch_floor_price,dutch_supply,volatility_auction_pct,volatility_window,volatility_auction_blocks,settlement_lag,min_inventory,borrow_fee,pricing_rule,maintenance_margin,max_position,max_order_size,default_balance,default_rule,interest_rate,carry_cost,gas_surcharge,valuation_interval,payment_interval,dividend_yield,funding_rate,num_zi_traders,zi_units,zi_max_price,num_momentum_traders,momentum_short_window,momentum_long_window,momentum_threshold,momentum_participation,num_mean_reversion_traders,mean_reversion_noise,mean_reversion_threshold,mean_reversion_quantity,mean_reversion_max_inventory,fundamental_jump_prob,fundamental_jump_std,fundamental_jump_lead,num_informed_traders,informed_signal_prob,informed_noise,informed_quantity,num_noise_traders,noise_submit_rate,noise_cancel_rate,num_spoofers,spoof_quantity,spoof_offset,spoof_trade_quantity,num_snipers,sniper_gas_increment,sniper_max_gas,bridge_port,bridge_timeout,bridge_depth,num_skew_makers,skew_risk_aversion,skew_intensity,num_gm_makers,gm_value_spread,gm_min_informed,investor_stale_blocks,investor_cancel_prob,investor_reprice_prob,num_twap_agents,twap_quantity,twap_start_block,twap_horizon,num_vwap_agents,vwap_quantity,vwap_start_block,vwap_horizon,vwap_forecast_alpha,vwap_catch_up,num_adaptive_makers,adapt_interval,adapt_epsilon,population_interval,population_exit_frac,population_imitate_prob,budget_rule,miner_strategy,num_miners,sandwich_min_quantity,sandwich_quantity,num_searchers,searcher_min_quantity,searcher_quantity,searcher_bid_share,censor_gas_floor,miner_regime,block_gas_limit,enter_gas_used,update_gas_used,cancel_gas_used,mempool_capacity,mempool_eviction,evicted_resubmit_prob,evicted_gas_bump,rbf_prob,rbf_gas_bump,miner_gossip_delay,maker_gossip_delay,sniper_gossip_delay,num_regions,region_sync_interval,reorg_prob,private_flow_prob,private_flow_fee,commit_reveal,encrypted_mempool,encryption_leak_prob,gas_oracle_window,gas_oracle_percentile,max_pending_per_trader,max_orders_per_block,spam_gas_escalation,discrete_event,seed,time_scale,checkpoint_block,");
+		let d = format!("{},{},{},{},{},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{:?},{},{},{},{},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{:?},{:?},{},{},{},{},{},{},{},{},{:?},{},{},{},{},{},{:?},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},",
 			self.batch_interval,
 			self.num_investors,
 			self.num_makers,
@@ -64,12 +577,155 @@ impl Constants {
 			self.max_held_inventory,
 			self.maker_inv_tax,
 			self.maker_cold_start,
-			self.maker_update_prob);
+			self.maker_update_prob,
+			opt_to_csv(self.mass_cancel_gas),
+			self.opening_auction_blocks,
+			self.closing_auction_blocks,
+			self.circuit_breaker_pct,
+			self.circuit_breaker_window,
+			self.halt_blocks,
+			self.price_band_pct,
+			self.investor_dark_prob,
+			self.amm_reserve_shares,
+			self.amm_reserve_cash,
+			self.amm_fee,
+			self.num_symbols,
+			opt_to_csv(self.second_market_type.map(|m| format!("{:?}", m))),
+			self.arb_quantity,
+			self.taker_fee,
+			self.maker_rebate,
+			self.indicative_interval,
+			self.tie_break,
+			self.flow_schedule_points,
+			self.flow_schedule_curvature,
+			self.pay_as_bid,
+			self.dutch_start_price,
+			self.dutch_decrement,
+			self.dutch_floor_price,
+			self.dutch_supply,
+			self.volatility_auction_pct,
+			self.volatility_window,
+			self.volatility_auction_blocks,
+			self.settlement_lag,
+			opt_to_csv(self.min_inventory),
+			self.borrow_fee,
+			self.pricing_rule,
+			opt_to_csv(self.maintenance_margin),
+			opt_to_csv(self.max_position),
+			opt_to_csv(self.max_order_size),
+			opt_to_csv(self.default_balance),
+			self.default_rule,
+			self.interest_rate,
+			opt_to_csv(self.carry_cost),
+			self.gas_surcharge,
+			self.valuation_interval,
+			self.payment_interval,
+			self.dividend_yield,
+			self.funding_rate,
+			self.num_zi_traders,
+			self.zi_units,
+			self.zi_max_price,
+			self.num_momentum_traders,
+			self.momentum_short_window,
+			self.momentum_long_window,
+			self.momentum_threshold,
+			self.momentum_participation,
+			self.num_mean_reversion_traders,
+			self.mean_reversion_noise,
+			self.mean_reversion_threshold,
+			self.mean_reversion_quantity,
+			self.mean_reversion_max_inventory,
+			self.fundamental_jump_prob,
+			self.fundamental_jump_std,
+			self.fundamental_jump_lead,
+			self.num_informed_traders,
+			self.informed_signal_prob,
+			self.informed_noise,
+			self.informed_quantity,
+			self.num_noise_traders,
+			self.noise_submit_rate,
+			self.noise_cancel_rate,
+			self.num_spoofers,
+			self.spoof_quantity,
+			self.spoof_offset,
+			self.spoof_trade_quantity,
+			self.num_snipers,
+			self.sniper_gas_increment,
+			self.sniper_max_gas,
+			self.bridge_port,
+			self.bridge_timeout,
+			self.bridge_depth,
+			self.num_skew_makers,
+			self.skew_risk_aversion,
+			self.skew_intensity,
+			self.num_gm_makers,
+			self.gm_value_spread,
+			self.gm_min_informed,
+			self.investor_stale_blocks,
+			self.investor_cancel_prob,
+			self.investor_reprice_prob,
+			self.num_twap_agents,
+			self.twap_quantity,
+			self.twap_start_block,
+			self.twap_horizon,
+			self.num_vwap_agents,
+			self.vwap_quantity,
+			self.vwap_start_block,
+			self.vwap_horizon,
+			self.vwap_forecast_alpha,
+			self.vwap_catch_up,
+			self.num_adaptive_makers,
+			self.adapt_interval,
+			self.adapt_epsilon,
+			self.population_interval,
+			self.population_exit_frac,
+			self.population_imitate_prob,
+			self.budget_rule,
+			self.miner_strategy,
+			self.num_miners,
+			self.sandwich_min_quantity,
+			self.sandwich_quantity,
+			self.num_searchers,
+			self.searcher_min_quantity,
+			self.searcher_quantity,
+			self.searcher_bid_share,
+			self.censor_gas_floor,
+			self.miner_regime,
+			self.block_gas_limit,
+			self.enter_gas_used,
+			self.update_gas_used,
+			self.cancel_gas_used,
+			self.mempool_capacity,
+			self.mempool_eviction,
+			self.evicted_resubmit_prob,
+			self.evicted_gas_bump,
+			self.rbf_prob,
+			self.rbf_gas_bump,
+			self.miner_gossip_delay,
+			self.maker_gossip_delay,
+			self.sniper_gossip_delay,
+			self.num_regions,
+			self.region_sync_interval,
+			self.reorg_prob,
+			self.private_flow_prob,
+			self.private_flow_fee,
+			self.commit_reveal,
+			self.encrypted_mempool,
+			self.encryption_leak_prob,
+			self.gas_oracle_window,
+			self.gas_oracle_percentile,
+			self.max_pending_per_trader,
+			self.max_orders_per_block,
+			self.spam_gas_escalation,
+			self.discrete_event,
+			self.seed,
+			self.time_scale,
+			self.checkpoint_block);
 		format!("{}\n{}", h, d)
 	}
 
 	// Given a consts, make 2 more with differing market type
-	// returned as (CDA, FBA, KLF)
+	// returned as (CDA, FBA, KLF). An AMM, SealedBid or Dutch consts makes all three.
 	pub fn copy3(&self) -> (String, String, String) {
 		match self.market_type {
 			MarketType::CDA => {
@@ -97,11 +753,28 @@ impl Constants {
 				cda.market_type = MarketType::CDA;
 				return (cda.log(), fba.log(), self.log());
 			},
+			MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => {
+				let mut cda = self.clone();
+				cda.market_type = MarketType::CDA;
+				let mut fba = self.clone();
+				fba.market_type = MarketType::FBA;
+				let mut klf = self.clone();
+				klf.market_type = MarketType::KLF;
+				return (cda.log(), fba.log(), klf.log());
+			},
 		}
 	}
 
 }
 
+// Optional parameters are written as an empty csv field when unset
+fn opt_to_csv<T: std::fmt::Display>(v: Option<T>) -> String {
+	match v {
+		Some(v) => format!("{}", v),
+		None => String::new(),
+	}
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
 pub enum DistType {
 	Uniform,
@@ -110,7 +783,7 @@ pub enum DistType {
 	Exponential,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
 pub enum DistReason {
 	AsksCenter,
 	BidsCenter,
@@ -126,9 +799,19 @@ pub enum DistReason {
 	MakerOrderVolume,
 	InvestorBalance,
 	InvestorInventory,
+	ZiValuation,
+	MakerSpread,
+	MakerSkew,
+	MakerRequoteThreshold,
+	InvestorLatency,
+	MakerLatency,
+	AgentLatency,
+	RiskAversion,
+	MinerHashPower,
+	GossipJitter,
 }
 
-const NUM_DISTS: usize = DistReason::InvestorInventory as usize + 1;
+const NUM_DISTS: usize = DistReason::GossipJitter as usize + 1;
 
 // Each distribution is in the form (µ: f64, std_dev: f64, scalar: f64, DistType)
 #[derive(Debug, Deserialize, Clone)]
@@ -190,6 +873,15 @@ impl Distributions {
 		}
 	}
 
+	// Samples the distribution for the DistReason, None if it wasn't in the config
+	pub fn sample_if_set(&self, which_dist: DistReason) -> Option<f64> {
+		match self.dists.get(which_dist as usize) {
+			// Unconfigured distributions are left with a zero scalar
+			Some(config) if config.2 != 0.0 => Some(Distributions::sample(config.0, config.1, config.2, config.3.clone())),
+			_ => None,
+		}
+	}
+
 	// Samples the distribution based on the config for the respsective DistReason
 	pub fn read_dist_params(&self, which_dist: DistReason) -> (f64, f64) {
 		// Get the config: (f64, f64, DistType) from our list of configs
@@ -199,8 +891,15 @@ impl Distributions {
 		(v1, v2)
 	}
 
+	// The midpoint of the bid and ask price centers
+	pub fn fundamental_value(&self) -> f64 {
+		let (mean_bids, _dev_bids) = self.read_dist_params(DistReason::BidsCenter);
+		let (mean_asks, _dev_asks) = self.read_dist_params(DistReason::AsksCenter);
+		(mean_bids + mean_asks) / 2.0
+	}
+
 	pub fn fifty_fifty() -> bool {
-		let val = rand::distributions::Uniform::new(0.0, 1.0).sample(&mut thread_rng());
+		let val = rand::distributions::Uniform::new(0.0, 1.0).sample(&mut sim_rng());
 		if val > 0.50 {
 			return true;
 		} else {
@@ -213,7 +912,7 @@ impl Distributions {
 		assert!(prob <= 1.0);
 		assert!(prob >= 0.0);
 
-		let val = rand::distributions::Uniform::new(0.0, 1.0).sample(&mut thread_rng());
+		let val = rand::distributions::Uniform::new(0.0, 1.0).sample(&mut sim_rng());
 		if val <= prob {
 			return true;
 		} else {
@@ -227,10 +926,10 @@ impl Distributions {
 	// Exp:		v1 = lambda, v2 = lambda
 	pub fn sample(v1: f64, v2: f64, scalar: f64, dtype: DistType) -> f64 {
 		match dtype {
-			DistType::Uniform => 	 scalar * rand::distributions::Uniform::new(v1, v2).sample(&mut thread_rng()),
-			DistType::Normal =>  	 scalar * rand::distributions::Normal::new(v1, v2).sample(&mut thread_rng()),
-			DistType::Poisson => 	 scalar * rand::distributions::Poisson::new(v1).sample(&mut thread_rng()) as f64,
-			DistType::Exponential => scalar * rand::distributions::Exp::new(v1).sample(&mut thread_rng()),
+			DistType::Uniform => 	 scalar * rand::distributions::Uniform::new(v1, v2).sample(&mut sim_rng()),
+			DistType::Normal =>  	 scalar * rand::distributions::Normal::new(v1, v2).sample(&mut sim_rng()),
+			DistType::Poisson => 	 scalar * rand::distributions::Poisson::new(v1).sample(&mut sim_rng()) as f64,
+			DistType::Exponential => scalar * rand::distributions::Exp::new(v1).sample(&mut sim_rng()),
 		}
 	}
 }
@@ -1,14 +1,48 @@
-use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate};
+use crate::exchange::exchange_logic::{TradeResults, PlayerUpdate, IndicativeData, Fill, OrderEvent, OrderReject};
 use crate::exchange::MarketType;
-use crate::order::order::{Order, TradeType};
+use crate::exchange::circuit_breaker::HaltEvent;
+use crate::players::spoofer::SpoofEvent;
+use crate::players::miner::Sandwich;
+use crate::players::miner_strategy::MinerStrategyT;
+use crate::blockchain::regions::RegionRecord;
+use crate::blockchain::gas_oracle::GasEstimate;
+use crate::blockchain::mempool_stats::MemPoolStats;
+use crate::simulation::replay::RecordedFrame;
+use crate::order::order::{Order, TradeType, OrderType};
 use crate::utility::get_time;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use crate::error::Error;
+use std::collections::{HashMap, BTreeMap};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 const MAX_PRICE: f64 = 999_999_999.0;
 const MIN_PRICE: f64 = 0.0;
 
+// The number of most recent lit fills the order flow statistics are taken over
+pub const FLOW_WINDOW: usize = 50;
+
+/// An order the miner left out of its frames on purpose
+/// blocks: u64 -> how many frames it was left out of
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CensorRecord {
+	pub trader_id: String,
+	pub first_block: u64,
+	pub blocks: u64,
+}
+
+/// How far the History's records of published blocks went before a block was published, so an
+/// orphaned block's book states, clearings and volume can be taken back out
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryMark {
+	pub order_books: usize,
+	pub clearings: usize,
+	pub transactions: usize,
+	pub dark_clearings: usize,
+	pub halt_events: usize,
+	pub lit_volume: f64,
+	pub dark_volume: f64,
+}
+
 // Reasons a player's updated state
 #[derive(Clone, Debug, Copy)]
 pub enum UpdateReason {
@@ -17,11 +51,24 @@ pub enum UpdateReason {
 	Gas,		// Player was updated because of gas
 	Transact,	// Player transacted
 	Liquify,	// Player liquified their inventory
+	MarginCall,	// Player's position was force-liquidated
+	Default,	// Player defaulted on their debt
+	Interest,	// Player earned or paid interest on their balance
+	Carry,		// Player paid the carry cost on their inventory
+	Fee,		// Player paid a trading fee or received a rebate
+	GasSurcharge,	// Player paid a surcharge on their gas
+	Adjust,		// Player's balance or inventory was adjusted directly
+	Mark,		// Player's positions were revalued
+	Dividend,	// Player received or, if short, paid a dividend
+	Funding,	// Player paid or received a funding payment on their position
+	Bundle,		// Searcher paid the miner to include its bundle
+	Reorg,		// Player's change in an orphaned block was reversed
+	Private,	// Player paid the miner to mine its private order
 	Final,		// Final player state
 }
 
 // Tracks the essential information from an order in the order book
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Entry {
 	pub order_id: u64,
 	pub quantity: f64,	// Only thing that changes with order
@@ -39,6 +86,7 @@ impl Entry {
 }
 
 // Shallow copy of an order book
+#[derive(Clone, Serialize, Deserialize)]
 pub struct ShallowBook { 
 	pub orders: Vec<Entry>,
 	pub block_num: u64,
@@ -91,6 +139,10 @@ pub struct LikelihoodStats {
 	pub num_bids: u64,
 	pub num_asks: u64,
 	pub weighted_price: Option<f64>,
+	pub volatility: f64,			// Std dev of the change between consecutive clearing prices
+	pub buy_volume: f64,			// Volume of the last FLOW_WINDOW lit fills where the buyer took liquidity
+	pub sell_volume: f64,			// Volume of the last FLOW_WINDOW lit fills where the seller took liquidity
+	pub flow_imbalance: f64,		// (buy_volume - sell_volume) / (buy_volume + sell_volume), 0 without signed flow
 }
 
 // Prior
@@ -107,7 +159,8 @@ pub struct PriorData {
 	pub mean_pool_gas: f64,
 	pub asks_volume: f64,
 	pub bids_volume: f64,
-	pub current_pool: Vec<Order>,
+	pub current_pool: Arc<Vec<Order>>,
+	pub indicative: Option<IndicativeData>,
 }
 
 
@@ -116,12 +169,39 @@ pub struct PriorData {
 /// order_books: a vector of shallowbooks which contain the minimum information to recreate state.
 /// 			 Each index in the vector will correspond to mutation of state
 /// clearings: A vector of TradeResults 
+/// halt_events: A vector of the circuit breaker's trading halts and resumes
+/// dark_clearings: A vector of TradeResults from the DarkBook
+/// lit_volume, dark_volume: total shares traded on each venue
+/// indicatives: A vector of the indicative prices and imbalances published before each batch
+/// spoof_events: A vector of the spoofers' placed, traded and cancelled orders
+/// frames: The orders each published block mined, to replay the run from
+#[derive(Serialize, Deserialize)]
 pub struct History {
 	pub mempool_data: Mutex<HashMap<u64, (Order, Duration)>>,
 	pub order_books: Mutex<Vec<ShallowBook>>,
 	pub clearings: Mutex<Vec<(TradeResults, Duration)>>,
 	pub market_type: MarketType,
 	pub transactions: Mutex<Vec<PlayerUpdate>>,
+	pub halt_events: Mutex<Vec<(HaltEvent, Duration)>>,
+	pub dark_clearings: Mutex<Vec<(TradeResults, Duration)>>,
+	pub lit_volume: Mutex<f64>,
+	pub dark_volume: Mutex<f64>,
+	pub indicatives: Mutex<Vec<(IndicativeData, Duration)>>,
+	pub order_events: Mutex<Vec<(OrderEvent, Duration)>>,
+	pub spoof_events: Mutex<Vec<(SpoofEvent, Duration)>>,
+	pub sandwiches: Mutex<Vec<(Sandwich, Duration)>>,
+	pub bundle_totals: Mutex<(usize, usize, f64)>,
+	pub censored: Mutex<HashMap<u64, CensorRecord>>,
+	pub miner_behaviors: Mutex<BTreeMap<u64, MinerStrategyT>>,
+	pub evictions: Mutex<(usize, usize)>,
+	pub replacements: Mutex<usize>,
+	pub region_syncs: Mutex<BTreeMap<usize, (usize, f64)>>,
+	pub reorgs: Mutex<Vec<(u64, usize, usize)>>,
+	pub flow_totals: Mutex<(usize, usize, f64)>,
+	pub commit_reveal_totals: Mutex<(usize, usize, usize)>,
+	pub gas_estimates: Mutex<Vec<(u64, GasEstimate)>>,
+	pub mempool_stats: Mutex<Vec<(u64, MemPoolStats)>>,
+	pub frames: Mutex<Vec<RecordedFrame>>,
 }
 
 
@@ -133,9 +213,94 @@ impl History {
 			clearings: Mutex::new(Vec::new()),
 			market_type: m,
 			transactions: Mutex::new(Vec::new()),
+			halt_events: Mutex::new(Vec::new()),
+			dark_clearings: Mutex::new(Vec::new()),
+			lit_volume: Mutex::new(0.0),
+			dark_volume: Mutex::new(0.0),
+			indicatives: Mutex::new(Vec::new()),
+			order_events: Mutex::new(Vec::new()),
+			spoof_events: Mutex::new(Vec::new()),
+			sandwiches: Mutex::new(Vec::new()),
+			bundle_totals: Mutex::new((0, 0, 0.0)),
+			censored: Mutex::new(HashMap::new()),
+			miner_behaviors: Mutex::new(BTreeMap::new()),
+			evictions: Mutex::new((0, 0)),
+			replacements: Mutex::new(0),
+			region_syncs: Mutex::new(BTreeMap::new()),
+			reorgs: Mutex::new(Vec::new()),
+			flow_totals: Mutex::new((0, 0, 0.0)),
+			commit_reveal_totals: Mutex::new((0, 0, 0)),
+			gas_estimates: Mutex::new(Vec::new()),
+			mempool_stats: Mutex::new(Vec::new()),
+			frames: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// A copy of everything recorded so far, to checkpoint the run with
+	pub fn copy(&self) -> History {
+		fn copied<T: Clone>(field: &Mutex<T>) -> Mutex<T> {
+			Mutex::new(field.lock().expect("History copy").clone())
+		}
+		History {
+			mempool_data: copied(&self.mempool_data),
+			order_books: copied(&self.order_books),
+			clearings: copied(&self.clearings),
+			market_type: self.market_type,
+			transactions: copied(&self.transactions),
+			halt_events: copied(&self.halt_events),
+			dark_clearings: copied(&self.dark_clearings),
+			lit_volume: copied(&self.lit_volume),
+			dark_volume: copied(&self.dark_volume),
+			indicatives: copied(&self.indicatives),
+			order_events: copied(&self.order_events),
+			spoof_events: copied(&self.spoof_events),
+			sandwiches: copied(&self.sandwiches),
+			bundle_totals: copied(&self.bundle_totals),
+			censored: copied(&self.censored),
+			miner_behaviors: copied(&self.miner_behaviors),
+			evictions: copied(&self.evictions),
+			replacements: copied(&self.replacements),
+			region_syncs: copied(&self.region_syncs),
+			reorgs: copied(&self.reorgs),
+			flow_totals: copied(&self.flow_totals),
+			commit_reveal_totals: copied(&self.commit_reveal_totals),
+			gas_estimates: copied(&self.gas_estimates),
+			mempool_stats: copied(&self.mempool_stats),
+			frames: copied(&self.frames),
 		}
 	}
 
+	/// Replaces everything recorded with a checkpoint's History, taken under the same market type
+	pub fn restore(&self, saved: History) {
+		fn replace<T>(field: &Mutex<T>, saved: Mutex<T>) {
+			*field.lock().expect("History restore") = saved.into_inner().expect("History restore");
+		}
+		replace(&self.mempool_data, saved.mempool_data);
+		replace(&self.order_books, saved.order_books);
+		replace(&self.clearings, saved.clearings);
+		replace(&self.transactions, saved.transactions);
+		replace(&self.halt_events, saved.halt_events);
+		replace(&self.dark_clearings, saved.dark_clearings);
+		replace(&self.lit_volume, saved.lit_volume);
+		replace(&self.dark_volume, saved.dark_volume);
+		replace(&self.indicatives, saved.indicatives);
+		replace(&self.order_events, saved.order_events);
+		replace(&self.spoof_events, saved.spoof_events);
+		replace(&self.sandwiches, saved.sandwiches);
+		replace(&self.bundle_totals, saved.bundle_totals);
+		replace(&self.censored, saved.censored);
+		replace(&self.miner_behaviors, saved.miner_behaviors);
+		replace(&self.evictions, saved.evictions);
+		replace(&self.replacements, saved.replacements);
+		replace(&self.region_syncs, saved.region_syncs);
+		replace(&self.reorgs, saved.reorgs);
+		replace(&self.flow_totals, saved.flow_totals);
+		replace(&self.commit_reveal_totals, saved.commit_reveal_totals);
+		replace(&self.gas_estimates, saved.gas_estimates);
+		replace(&self.mempool_stats, saved.mempool_stats);
+		replace(&self.frames, saved.frames);
+	}
+
 	// Adds an order indexed by its order id to a history of all orders to mempool 
 	pub fn mempool_order(&self, order: Order) {
 		let mut pool = self.mempool_data.lock().expect("History mempool lock");
@@ -173,11 +338,371 @@ impl History {
 			}
 		}
 
+		*self.lit_volume.lock().expect("save_results") += History::traded_volume(&results);
+
+		for event in results.order_events.iter() {
+			self.save_order_event(event.clone());
+		}
+
 		// Save the trade results to clearing
 		let mut clearings = self.clearings.lock().expect("save_results");
 		clearings.push((results, get_time()));
 	}
 
+	// Saves the results from the DarkBook separately so they don't set lit clearing prices
+	pub fn save_dark_results(&self, results: TradeResults) {
+		let mut txs = self.transactions.lock().expect("save_dark_results");
+		if let Some(crosses) = &results.cross_results {
+			for player_update in crosses {
+				txs.push(player_update.clone());
+			}
+		}
+
+		*self.dark_volume.lock().expect("save_dark_results") += History::traded_volume(&results);
+
+		let mut clearings = self.dark_clearings.lock().expect("save_dark_results");
+		clearings.push((results, get_time()));
+	}
+
+	// Sums the volume of the trades in the results, ignoring cancels
+	fn traded_volume(results: &TradeResults) -> f64 {
+		match &results.cross_results {
+			Some(updates) => updates.iter().filter(|u| !u.cancel).map(|u| u.volume).sum(),
+			None => 0.0,
+		}
+	}
+
+	// Returns every Fill from both venues in the order the results were saved, lit first
+	pub fn get_fills(&self) -> Vec<Fill> {
+		let mut fills = Vec::new();
+		for (results, _time) in self.clearings.lock().expect("get_fills").iter() {
+			fills.extend(results.fills.iter().cloned());
+		}
+		for (results, _time) in self.dark_clearings.lock().expect("get_fills").iter() {
+			fills.extend(results.fills.iter().cloned());
+		}
+		fills
+	}
+
+	// Returns the volume filled in each block from both venues, oldest first
+	pub fn block_volumes(&self) -> Vec<(u64, f64)> {
+		let mut volumes: BTreeMap<u64, f64> = BTreeMap::new();
+		for fill in self.get_fills() {
+			*volumes.entry(fill.block_num).or_insert(0.0) += fill.quantity;
+		}
+		volumes.into_iter().collect()
+	}
+
+	// Returns the largest relative price move between the symbol's lit fills since block_num
+	pub fn price_move_since(&self, symbol: &str, block_num: u64) -> f64 {
+		let clearings = self.clearings.lock().expect("price_move_since");
+		let prices: Vec<f64> = clearings.iter()
+			.filter(|(results, _time)| results.symbol == symbol)
+			.flat_map(|(results, _time)| results.fills.iter())
+			.filter(|f| f.block_num >= block_num)
+			.map(|f| f.price)
+			.collect();
+		let min = prices.iter().cloned().fold(std::f64::MAX, f64::min);
+		let max = prices.iter().cloned().fold(std::f64::MIN, f64::max);
+		if prices.len() < 2 || min <= 0.0 {
+			return 0.0;
+		}
+		(max - min) / min
+	}
+
+	// Returns the std dev of the change between each symbol's consecutive lit clearing prices
+	pub fn clearing_price_std(&self) -> f64 {
+		let clearings = self.clearings.lock().expect("clearing_price_std");
+		let mut last: HashMap<&str, f64> = HashMap::new();
+		let mut changes = Vec::new();
+		for (results, _time) in clearings.iter() {
+			if let Some(price) = results.last_price() {
+				if let Some(prev) = last.insert(results.symbol.as_str(), price) {
+					changes.push(price - prev);
+				}
+			}
+		}
+		if changes.len() < 2 {
+			return 0.0;
+		}
+		let n = changes.len() as f64;
+		let mean = changes.iter().sum::<f64>() / n;
+		let var = changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1.0);
+		var.sqrt()
+	}
+
+	// Returns the (buy, sell) volume taken by aggressors over the last window lit fills
+	// whose aggressor is known, and their imbalance
+	pub fn order_flow(&self, window: usize) -> (f64, f64, f64) {
+		let clearings = self.clearings.lock().expect("order_flow");
+		let signed: Vec<(TradeType, f64)> = clearings.iter()
+			.flat_map(|(results, _time)| results.fills.iter())
+			.filter_map(|f| f.aggressor.clone().map(|side| (side, f.quantity)))
+			.collect();
+		let (mut buys, mut sells) = (0.0, 0.0);
+		for (side, quantity) in signed.iter().rev().take(window) {
+			match side {
+				TradeType::Bid => buys += quantity,
+				TradeType::Ask => sells += quantity,
+			}
+		}
+		let imbalance = match buys + sells > 0.0 {
+			true => (buys - sells) / (buys + sells),
+			false => 0.0,
+		};
+		(buys, sells, imbalance)
+	}
+
+	// Returns the (price, volume) of each lit clearing of the symbol that traded, oldest first
+	pub fn clearing_history(&self, symbol: &str) -> Vec<(f64, f64)> {
+		let clearings = self.clearings.lock().expect("clearing_history");
+		clearings.iter()
+			.filter(|(results, _time)| results.symbol == symbol)
+			.filter_map(|(results, _time)| results.last_price().map(|p| (p, History::traded_volume(results))))
+			.collect()
+	}
+
+	// Returns the (lit, dark) volume traded so far
+	pub fn get_venue_volumes(&self) -> (f64, f64) {
+		(*self.lit_volume.lock().unwrap(), *self.dark_volume.lock().unwrap())
+	}
+
+	// Records a trading halt or resume from the circuit breaker
+	pub fn save_halt_event(&self, event: HaltEvent) {
+		let mut events = self.halt_events.lock().expect("save_halt_event");
+		events.push((event, get_time()));
+	}
+
+	// Records an indicative price and imbalance published ahead of a batch
+	// Saves an ack or reject sent back to a trader
+	pub fn save_order_event(&self, event: OrderEvent) {
+		let mut events = self.order_events.lock().expect("save_order_event");
+		events.push((event, get_time()));
+	}
+
+	// Records a step of a spoofing cycle
+	pub fn save_spoof_event(&self, event: SpoofEvent) {
+		let mut events = self.spoof_events.lock().expect("save_spoof_event");
+		events.push((event, get_time()));
+	}
+
+	pub fn get_spoof_events(&self) -> Vec<SpoofEvent> {
+		self.spoof_events.lock().expect("get_spoof_events").iter().map(|(e, _time)| e.clone()).collect()
+	}
+
+	// Records a sandwich the miner published
+	pub fn save_sandwich(&self, sandwich: Sandwich) {
+		let mut sandwiches = self.sandwiches.lock().expect("save_sandwich");
+		sandwiches.push((sandwich, get_time()));
+	}
+
+	pub fn get_sandwiches(&self) -> Vec<Sandwich> {
+		self.sandwiches.lock().expect("get_sandwiches").iter().map(|(s, _time)| s.clone()).collect()
+	}
+
+	// Adds a block's bundles submitted, bundles included and payments made to the miner
+	pub fn save_bundle_round(&self, submitted: usize, included: usize, paid: f64) {
+		let mut totals = self.bundle_totals.lock().expect("save_bundle_round");
+		totals.0 += submitted;
+		totals.1 += included;
+		totals.2 += paid;
+	}
+
+	pub fn get_bundle_totals(&self) -> (usize, usize, f64) {
+		*self.bundle_totals.lock().expect("get_bundle_totals")
+	}
+
+	// Counts an order sent publicly or privately to the miner
+	pub fn save_flow(&self, private: bool) {
+		let mut totals = self.flow_totals.lock().expect("save_flow");
+		match private {
+			true => totals.0 += 1,
+			false => totals.1 += 1,
+		}
+	}
+
+	// Records the fees paid to the miner for mining private orders
+	pub fn save_private_fees(&self, paid: f64) {
+		self.flow_totals.lock().expect("save_private_fees").2 += paid;
+	}
+
+	// The orders sent privately, the orders sent publicly and the private fees paid
+	pub fn get_flow_totals(&self) -> (usize, usize, f64) {
+		*self.flow_totals.lock().expect("get_flow_totals")
+	}
+
+	// Records the commitments mined in a block
+	pub fn save_commitments(&self, mined: usize) {
+		self.commit_reveal_totals.lock().expect("save_commitments").0 += mined;
+	}
+
+	// Records the revealed orders mined in a block and those rejected for not matching a commitment
+	pub fn save_reveals(&self, revealed: usize, rejected: usize) {
+		let mut totals = self.commit_reveal_totals.lock().expect("save_reveals");
+		totals.1 += revealed;
+		totals.2 += rejected;
+	}
+
+	// The commitments mined, the revealed orders mined and the reveals rejected
+	pub fn get_commit_reveal_totals(&self) -> (usize, usize, usize) {
+		*self.commit_reveal_totals.lock().expect("get_commit_reveal_totals")
+	}
+
+	// Records the gas oracle's estimate once block_num was mined
+	pub fn save_gas_estimate(&self, block_num: u64, estimate: GasEstimate) {
+		self.gas_estimates.lock().expect("save_gas_estimate").push((block_num, estimate));
+	}
+
+	pub fn get_gas_estimates(&self) -> Vec<(u64, GasEstimate)> {
+		self.gas_estimates.lock().expect("get_gas_estimates").clone()
+	}
+
+	// Records the orders left waiting in the mempool once block_num was mined
+	pub fn save_mempool_stats(&self, block_num: u64, stats: MemPoolStats) {
+		self.mempool_stats.lock().expect("save_mempool_stats").push((block_num, stats));
+	}
+
+	pub fn get_mempool_stats(&self) -> Vec<(u64, MemPoolStats)> {
+		self.mempool_stats.lock().expect("get_mempool_stats").clone()
+	}
+
+	// Records the orders block_num mined
+	pub fn save_frame(&self, block_num: u64, orders: &[Order]) {
+		self.frames.lock().expect("save_frame").push(RecordedFrame { block: block_num, orders: orders.to_vec(), orphaned: false });
+	}
+
+	// Marks the latest frame recorded for block_num as orphaned
+	pub fn orphan_frame(&self, block_num: u64) {
+		let mut frames = self.frames.lock().expect("orphan_frame");
+		if let Some(frame) = frames.iter_mut().rev().find(|f| f.block == block_num) {
+			frame.orphaned = true;
+		}
+	}
+
+	pub fn get_frames(&self) -> Vec<RecordedFrame> {
+		self.frames.lock().expect("get_frames").clone()
+	}
+
+	// Records the orders the miner censored from the frame for block_num
+	pub fn save_censored(&self, orders: &[Order], block_num: u64) {
+		let mut censored = self.censored.lock().expect("save_censored");
+		for order in orders {
+			let record = censored.entry(order.order_id).or_insert(CensorRecord {
+				trader_id: order.trader_id.clone(),
+				first_block: block_num,
+				blocks: 0,
+			});
+			record.blocks += 1;
+		}
+	}
+
+	pub fn get_censored(&self) -> Vec<CensorRecord> {
+		self.censored.lock().expect("get_censored").values().cloned().collect()
+	}
+
+	// Records the strategy the miner built block_num's frame with
+	pub fn save_miner_behavior(&self, block_num: u64, behavior: MinerStrategyT) {
+		self.miner_behaviors.lock().expect("save_miner_behavior").insert(block_num, behavior);
+	}
+
+	pub fn get_miner_behaviors(&self) -> Vec<(u64, MinerStrategyT)> {
+		self.miner_behaviors.lock().expect("get_miner_behaviors").iter().map(|(b, s)| (*b, *s)).collect()
+	}
+
+	// Records how many orders a full mempool evicted and how many of them were resubmitted
+	pub fn save_evictions(&self, evicted: usize, resubmitted: usize) {
+		let mut evictions = self.evictions.lock().expect("save_evictions");
+		evictions.0 += evicted;
+		evictions.1 += resubmitted;
+	}
+
+	pub fn get_evictions(&self) -> (usize, usize) {
+		*self.evictions.lock().expect("get_evictions")
+	}
+
+	// Records how many pending orders were replaced by fee this block
+	pub fn save_replacements(&self, replaced: usize) {
+		*self.replacements.lock().expect("save_replacements") += replaced;
+	}
+
+	pub fn get_replacements(&self) -> usize {
+		*self.replacements.lock().expect("get_replacements")
+	}
+
+	// Adds the orders that just became visible in every region to the count and total
+	// sync delay of the region each arrived in
+	pub fn save_region_syncs(&self, records: &[RegionRecord]) {
+		let mut syncs = self.region_syncs.lock().expect("save_region_syncs");
+		for record in records {
+			let entry = syncs.entry(record.region).or_insert((0, 0.0));
+			entry.0 += 1;
+			entry.1 += record.sync_delay().unwrap_or(0.0);
+		}
+	}
+
+	// Where the records of published blocks end, to roll the next block back to
+	pub fn mark(&self) -> HistoryMark {
+		HistoryMark {
+			order_books: self.order_books.lock().expect("History mark").len(),
+			clearings: self.clearings.lock().expect("History mark").len(),
+			transactions: self.transactions.lock().expect("History mark").len(),
+			dark_clearings: self.dark_clearings.lock().expect("History mark").len(),
+			halt_events: self.halt_events.lock().expect("History mark").len(),
+			lit_volume: *self.lit_volume.lock().expect("History mark"),
+			dark_volume: *self.dark_volume.lock().expect("History mark"),
+		}
+	}
+
+	// Drops the records of the blocks published since the mark
+	pub fn rollback(&self, mark: &HistoryMark) {
+		self.order_books.lock().expect("History rollback").truncate(mark.order_books);
+		self.clearings.lock().expect("History rollback").truncate(mark.clearings);
+		self.transactions.lock().expect("History rollback").truncate(mark.transactions);
+		self.dark_clearings.lock().expect("History rollback").truncate(mark.dark_clearings);
+		self.halt_events.lock().expect("History rollback").truncate(mark.halt_events);
+		*self.lit_volume.lock().expect("History rollback") = mark.lit_volume;
+		*self.dark_volume.lock().expect("History rollback") = mark.dark_volume;
+	}
+
+	// Records an orphaned block, the orders returned to the mempool and the changes to the players reversed
+	pub fn save_reorg(&self, block: u64, orders: usize, reversed: usize) {
+		self.reorgs.lock().expect("save_reorg").push((block, orders, reversed));
+	}
+
+	pub fn get_reorgs(&self) -> Vec<(u64, usize, usize)> {
+		self.reorgs.lock().expect("get_reorgs").clone()
+	}
+
+	// Each region's synced orders and their mean delay in ms before every region saw them
+	pub fn get_region_syncs(&self) -> Vec<(usize, usize, f64)> {
+		self.region_syncs.lock().expect("get_region_syncs").iter()
+			.map(|(r, (n, total))| (*r, *n, if *n > 0 { total / *n as f64 } else { 0.0 }))
+			.collect()
+	}
+
+	// Returns every order the exchange rejected
+	pub fn get_rejections(&self) -> Vec<OrderReject> {
+		let events = self.order_events.lock().expect("get_rejections");
+		events.iter().filter_map(|(e, _time)| match e {
+			OrderEvent::Reject(rej) => Some(rej.clone()),
+			OrderEvent::Ack(_) => None,
+		}).collect()
+	}
+
+	pub fn save_indicative(&self, data: IndicativeData) {
+		let mut indicatives = self.indicatives.lock().expect("save_indicative");
+		indicatives.push((data, get_time()));
+	}
+
+	// Returns the most recently published indicative price and imbalance
+	pub fn get_last_indicative(&self) -> Option<IndicativeData> {
+		let indicatives = self.indicatives.lock().expect("get_last_indicative");
+		match indicatives.last() {
+			Some((data, _time)) => Some(data.clone()),
+			None => None,
+		}
+	}
+
 	// Searches the hashmap of mempool orders
 	// Returns a copy of the order and the timestamp it was sent
 	pub fn find_orig_order(&self, order_id: u64) -> Option<(Order, Duration)> {
@@ -195,7 +720,7 @@ impl History {
 		let (mut asks_sum, mut bids_sum) = (0.0, 0.0);
 		let (mut num_asks, mut num_bids) = (0.0, 0.0);
 		match market_type {
-			MarketType::CDA|MarketType::FBA => {
+			MarketType::CDA|MarketType::FBA|MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => {
 				// For each order in the mempool sum 
 				for order in orders {
 					match order.trade_type {
@@ -253,9 +778,11 @@ impl History {
 		let (mut num_asks, mut num_bids) = (0.0, 0.0);
 		let all_orders = self.mempool_data.lock().expect("average_prices");
 		match self.market_type {
-			MarketType::CDA|MarketType::FBA => {
+			MarketType::CDA|MarketType::FBA|MarketType::AMM|MarketType::SealedBid|MarketType::Dutch => {
 				// For each order in the mempool sum 
 				for (_key, (order, _timestamp)) in all_orders.iter() {
+					// MassCancel orders carry no price information
+					if order.order_type == OrderType::MassCancel {continue;}
 					match order.trade_type {
 						TradeType::Bid => {
 							num_bids += 1.0;
@@ -270,6 +797,7 @@ impl History {
 			},
 			MarketType::KLF => {
 				for (_key, (order, _timestamp))in all_orders.iter() {
+					if order.order_type == OrderType::MassCancel {continue;}
 					match order.trade_type {
 						TradeType::Bid => {
 							num_bids += 1.0;
@@ -297,6 +825,30 @@ impl History {
 	}
 
 
+	// The price of the most recent trade of the symbol in any block, used as the price band reference
+	pub fn get_reference_price(&self, symbol: &str) -> Option<f64> {
+		let clearings = self.clearings.lock().unwrap();
+		clearings.iter().rev()
+			.filter(|(result, _time)| result.symbol == symbol)
+			.filter_map(|(result, _time)| result.last_price()).next()
+	}
+
+	// Rejects orders priced outside ±consts.price_band_pct of the previous clearing price.
+	// No band is applied until the first trade, or if price_band_pct is 0.
+	pub fn check_price_band(&self, order: &Order, band_pct: f64) -> Result<(), Error> {
+		if band_pct <= 0.0 {
+			return Ok(());
+		}
+		match self.get_reference_price(&order.symbol) {
+			Some(ref_price) if !order.within_band(ref_price, band_pct) => Err(Error::Rejected {
+				trader_id: order.trader_id.clone(),
+				order_id: order.order_id,
+				reason: "Order priced outside of the price band",
+			}),
+			_ => Ok(()),
+		}
+	}
+
 	pub fn get_last_clearing_price(&self) -> Option<f64> {
 		let clearings = self.clearings.lock().unwrap();
 		let most_recent = clearings.last();
@@ -354,7 +906,7 @@ impl History {
 		else if best_bid.is_none() && best_ask.is_some() {
 			(MIN_PRICE, best_ask.unwrap().price)
 		}
-		else if best_bid.is_none() && best_ask.is_none() {
+		else if best_bid.is_some() && best_ask.is_none() {
 			(best_bid.unwrap().price, MAX_PRICE)
 		}
 		else {
@@ -430,7 +982,7 @@ impl History {
 		return (bids_out, asks_out, bids_vol, asks_vol);
 	}
 
-	pub fn produce_data(&self, mempool: Vec<Order>) -> (PriorData, LikelihoodStats) {
+	pub fn produce_data(&self, mempool: Arc<Vec<Order>>) -> (PriorData, LikelihoodStats) {
 		(self.decision_data(mempool), self.inference_data())
 	}
 
@@ -438,6 +990,8 @@ impl History {
 	// Returns the weighted averages of bids and asks seen in the mempool
 	pub fn inference_data(&self) -> LikelihoodStats {
 		let (mean_bids, mean_asks, num_bids, num_asks) = self.average_seen_prices();
+		let volatility = self.clearing_price_std();
+		let (buy_volume, sell_volume, flow_imbalance) = self.order_flow(FLOW_WINDOW);
 		
 		// Avoid divide by zero	
 		if num_bids == 0 && num_asks == 0 {
@@ -447,6 +1001,10 @@ impl History {
 				num_bids: num_bids,
 				num_asks: num_asks,
 				weighted_price: None,
+				volatility,
+				buy_volume,
+				sell_volume,
+				flow_imbalance,
 			};
 		}
 		let raw_bids = match mean_bids {
@@ -466,6 +1024,10 @@ impl History {
 				num_bids: num_bids,
 				num_asks: num_asks,
 				weighted_price: None,
+				volatility,
+				buy_volume,
+				sell_volume,
+				flow_imbalance,
 			};
 		} else if raw_bids.is_none() && raw_asks.is_some() {
 			let weighted_price = Some(raw_asks.unwrap() / num_asks as f64);
@@ -475,6 +1037,10 @@ impl History {
 				num_bids,
 				num_asks,
 				weighted_price,
+				volatility,
+				buy_volume,
+				sell_volume,
+				flow_imbalance,
 			}
 		} else if raw_bids.is_some() && raw_asks.is_none() {
 			let weighted_price = Some(raw_bids.unwrap() / num_bids as f64);
@@ -484,6 +1050,10 @@ impl History {
 				num_bids,
 				num_asks,
 				weighted_price,
+				volatility,
+				buy_volume,
+				sell_volume,
+				flow_imbalance,
 			}
 		} else {
 			let weighted_price = Some((raw_bids.unwrap() + raw_asks.unwrap()) / (num_asks as f64 + num_bids as f64));
@@ -493,6 +1063,10 @@ impl History {
 				num_bids,
 				num_asks,
 				weighted_price,
+				volatility,
+				buy_volume,
+				sell_volume,
+				flow_imbalance,
 			}
 		}
 	}
@@ -549,7 +1123,7 @@ impl History {
 	}
 
 
-	pub fn decision_data(&self, current_pool: Vec<Order>) -> PriorData {
+	pub fn decision_data(&self, current_pool: Arc<Vec<Order>>) -> PriorData {
 		let clearing_price = self.get_last_clearing_price();
 		let (best_bid, best_ask) = self.get_best_orders();
 		let (current_bids, current_asks, bids_volume, asks_volume) = self.get_current_orders();
@@ -560,6 +1134,9 @@ impl History {
 		// Get the current average gas price in the mmepool 
 		let mean_pool_gas = History::get_mean_gas(&current_pool);
 
+		// Get the latest indicative price and imbalance for the next batch
+		let indicative = self.get_last_indicative();
+
 		PriorData {
 			clearing_price, 
 			best_bid,
@@ -571,6 +1148,7 @@ impl History {
 			asks_volume,
 			bids_volume,
 			current_pool,
+			indicative,
 		}
 	}
 }
@@ -1,7 +1,10 @@
 use crate::exchange::MarketType;
 use crate::players::TraderT;
 use std::time::{Duration, SystemTime};
-use rand::{Rng, thread_rng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use rand::{Rng, RngCore, SeedableRng, FromEntropy, Error};
+use rand::rngs::StdRng;
+use std::cell::RefCell;
 use rand::distributions::Alphanumeric;
 use std::iter;
 
@@ -15,28 +18,28 @@ use log4rs::config::{Appender, Config, Root, Logger};
 #[macro_export]
 macro_rules! log_order_book {
     ($message:expr) => {
-        log!(target: "app::order_books", Level::Warn, "{}", $message);
+        log!(target: "app::order_books", Level::Warn, "{}", $message)
     }   
 }
 
 #[macro_export]
 macro_rules! log_player_data {
     ($message:expr) => {
-        log!(target: "app::player_data", Level::Warn, "{}", $message);
+        log!(target: "app::player_data", Level::Warn, "{}", $message)
     }   
 }
 
 #[macro_export]
 macro_rules! log_mempool_data {
     ($message:expr) => {
-        log!(target: "app::mempool_data", Level::Warn, "{}", $message);
+        log!(target: "app::mempool_data", Level::Warn, "{}", $message)
     }   
 }
 
 #[macro_export]
 macro_rules! log_results {
     ($message:expr) => {
-        log!(target: "app::results", Level::Warn, "{}", $message);
+        log!(target: "app::results", Level::Warn, "{}", $message)
     }   
 }
 
@@ -47,23 +50,90 @@ pub fn get_time() -> Duration {
 }
 
 
+thread_local! {
+    // The thread's generator, from entropy until the thread is seeded
+    static RNG: RefCell<StdRng> = RefCell::new(StdRng::from_entropy());
+}
+
+/// Every random draw in the simulation goes through the calling thread's generator, so seeding
+/// the threads makes a run reproducible
+pub struct SimRng;
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// The calling thread's generator
+pub fn sim_rng() -> SimRng {
+    SimRng
+}
+
+/// Seeds the calling thread's generator
+pub fn seed_rng(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = StdRng::seed_from_u64(seed));
+}
+
+/// A task's own seed derived from the master seed and the task's name
+pub fn child_seed(master: u64, task: &str) -> u64 {
+    task.bytes().fold(master ^ 0xcbf2_9ce4_8422_2325, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+/// Seeds the calling thread for a task's next run, so each of the task's runs draws the same
+/// numbers whichever thread runs it. A master seed of 0 leaves the threads unseeded.
+pub fn seed_task(master: u64, task: &str, run: u64) {
+    if master != 0 {
+        seed_rng(child_seed(master, task).wrapping_add(run));
+    }
+}
+
+// The time scale's f64 bits, 1.0 until it's set
+static TIME_SCALE: AtomicU64 = AtomicU64::new(0x3ff0_0000_0000_0000);
+
+/// Runs the real-time tasks time_scale times faster than their sampled delays and intervals
+pub fn set_time_scale(time_scale: f64) {
+    TIME_SCALE.store(time_scale.to_bits(), Ordering::Relaxed);
+}
+
+/// Shortens a delay or interval in millis by the time scale
+pub fn scaled_millis(millis: u64) -> u64 {
+    let time_scale = f64::from_bits(TIME_SCALE.load(Ordering::Relaxed));
+    if time_scale <= 0.0 {
+        return millis;
+    }
+    (millis as f64 / time_scale).round() as u64
+}
+
 // Generate a random 64b order id
 pub fn gen_order_id() -> u64 {
-    let mut rng = thread_rng();
+    let mut rng = sim_rng();
     let p: u64 = rng.gen();
     p
 
 }
 
 pub fn gen_rand_f64() -> f64 {
-     let mut rng = thread_rng();
+     let mut rng = sim_rng();
     let p: f64 = rng.gen();
     p
 }
 
 /// Generate a trader id for a specific type of trader
 pub fn gen_trader_id(tt: TraderT) -> String {
-	let mut rng = thread_rng();
+	let mut rng = sim_rng();
 	let id: String = iter::repeat(())
         .map(|()| rng.sample(Alphanumeric))
         .take(10)
@@ -73,13 +143,50 @@ pub fn gen_trader_id(tt: TraderT) -> String {
     	TraderT::Maker => format!("MKR{}", id),
     	TraderT::Investor => format!("INV{}", id),
     	TraderT::Miner => format!("MIN{}", id),
+    	TraderT::Arbitrageur => format!("ARB{}", id),
+    	TraderT::ZeroIntel => format!("ZIT{}", id),
+    	TraderT::Momentum => format!("MOM{}", id),
+    	TraderT::MeanReversion => format!("MRV{}", id),
+    	TraderT::Informed => format!("INF{}", id),
+    	TraderT::Noise => format!("NSE{}", id),
+    	TraderT::Spoofer => format!("SPF{}", id),
+    	TraderT::Sniper => format!("SNP{}", id),
+    	TraderT::Learner => format!("LRN{}", id),
+    	TraderT::Bridge => format!("BRG{}", id),
+    	TraderT::Twap => format!("TWP{}", id),
+    	TraderT::Vwap => format!("VWP{}", id),
+    	TraderT::Searcher => format!("SRC{}", id),
+    }
+}
+
+
+/// The trader type from the prefix gen_trader_id gave the id
+pub fn trader_type(id: &str) -> Option<TraderT> {
+    match id.get(0..3)? {
+    	"MKR" => Some(TraderT::Maker),
+    	"INV" => Some(TraderT::Investor),
+    	"MIN" => Some(TraderT::Miner),
+    	"ARB" => Some(TraderT::Arbitrageur),
+    	"ZIT" => Some(TraderT::ZeroIntel),
+    	"MOM" => Some(TraderT::Momentum),
+    	"MRV" => Some(TraderT::MeanReversion),
+    	"INF" => Some(TraderT::Informed),
+    	"NSE" => Some(TraderT::Noise),
+    	"SPF" => Some(TraderT::Spoofer),
+    	"SNP" => Some(TraderT::Sniper),
+    	"LRN" => Some(TraderT::Learner),
+    	"BRG" => Some(TraderT::Bridge),
+    	"TWP" => Some(TraderT::Twap),
+    	"VWP" => Some(TraderT::Vwap),
+    	"SRC" => Some(TraderT::Searcher),
+    	_ => None,
     }
 }
 
 
 /// Generate a trader id for a specific type of trader
 pub fn gen_rand_trader_id() -> String {
-    let mut rng = thread_rng();
+    let mut rng = sim_rng();
     let id: String = iter::repeat(())
         .map(|()| rng.sample(Alphanumeric))
         .take(10)
@@ -202,3 +309,31 @@ pub fn setup_log_headers(market_type: MarketType) {
 
 
 
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_draws_repeat() {
+        let draws = || (gen_order_id(), gen_rand_f64(), gen_trader_id(TraderT::Investor));
+        seed_task(42, "investor", 0);
+        let first = draws();
+        seed_task(42, "investor", 0);
+        assert_eq!(draws(), first);
+
+        // Each task and run gets its own stream
+        seed_task(42, "maker", 0);
+        assert_ne!(draws(), first);
+        seed_task(42, "investor", 1);
+        assert_ne!(draws(), first);
+
+        // The same seed on another thread draws the same numbers
+        let other = std::thread::spawn(move || {
+            seed_task(42, "investor", 0);
+            draws()
+        }).join().unwrap();
+        assert_eq!(other, first);
+    }
+}
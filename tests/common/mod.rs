@@ -104,26 +104,25 @@ pub fn each_order_type() -> Vec<Order> {
 	let mut orders = Vec::<Order>::new();
 
 	let b1 = setup_bid_limit_order();
-	orders.push(b1);
+	// The update and cancel are of the entered order, the MemPool rejects ones of unknown orders
 	let mut b2 = setup_bid_limit_order();
 	b2.order_type = OrderType::Update;
-	orders.push(b2);
+	b2.order_id = b1.order_id;
 	let mut b3 = setup_bid_limit_order();
 	b3.order_type = OrderType::Cancel;
+	b3.order_id = b1.order_id;
+	orders.push(b1);
+	orders.push(b2);
 	orders.push(b3);
 	orders
 }
 
 pub fn setup_full_mem_pool() -> Arc<MemPool> {
 	let mem_pool = Arc::new(setup_mem_pool());
-	let mut handles: Vec<_> = Vec::new();
 
+	// Sent in order so the update and cancel arrive after the order they reference
 	for order in each_order_type() {
-		handles.push(OrderProcessor::conc_recv_order(order, Arc::clone(&mem_pool)));
-	}
-
-	for h in handles {
-		h.join().unwrap();
+		OrderProcessor::conc_recv_order(order, Arc::clone(&mem_pool)).join().unwrap();
 	}
 
 	mem_pool
@@ -2,9 +2,14 @@
 extern crate more_asserts;
 use flow_rs::blockchain::mempool_processor::MemPoolProcessor;
 use flow_rs::blockchain::order_processor::*;
+use flow_rs::blockchain::mem_pool::MemPool;
+use flow_rs::blockchain::eviction::EvictionPolicy;
+use flow_rs::blockchain::block_gas::BlockGas;
 use flow_rs::order::order::*;
+use flow_rs::order::commitment::{CommitmentStore, order_hash};
 use flow_rs::exchange::exchange_logic::Auction;
 use std::sync::Arc;
+use std::time::Duration;
 use rand::{Rng, thread_rng};
 use more_asserts::{assert_le};
 
@@ -89,6 +94,342 @@ fn test_mem_pool_sort_gas() {
 	}
 }
 
+#[test]
+fn test_mem_pool_eviction() {
+	let order = |gas: f64| Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, gas);
+	let (first, cheap, rich) = (order(2.0), order(1.0), order(3.0));
+
+	// The lowest gas order makes room, even when it is the one arriving
+	let pool = MemPool::new().with_capacity(2, EvictionPolicy::LowestGas);
+	pool.add(first.clone());
+	pool.add(rich.clone());
+	pool.add(cheap.clone());
+	assert_eq!(pool.length(), 2);
+	let evicted = pool.take_evicted();
+	assert_eq!(evicted.len(), 1);
+	assert_eq!(evicted[0].order_id, cheap.order_id);
+	assert!(pool.take_evicted().is_empty());
+
+	// The first to arrive makes room, and sorting the pool doesn't change who that is
+	let pool = MemPool::new().with_capacity(2, EvictionPolicy::Oldest);
+	pool.add(first.clone());
+	pool.add(rich.clone());
+	pool.sort_by_gas();
+	pool.add(cheap.clone());
+	assert_eq!(pool.take_evicted()[0].order_id, first.order_id);
+	assert!(pool.contains(cheap.order_id));
+
+	// Without a capacity nothing is evicted
+	let pool = MemPool::new().with_capacity(0, EvictionPolicy::Oldest);
+	for _ in 0..10 {
+		pool.add(order(1.0));
+	}
+	assert_eq!(pool.length(), 10);
+	assert!(pool.take_evicted().is_empty());
+}
+
+#[test]
+fn test_mem_pool_arrival_tiebreak() {
+	let order = |gas: f64| Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, gas);
+	let (first, second, third, rich) = (order(1.0), order(1.0), order(1.0), order(2.0));
+	let pool = MemPool::new();
+	for o in vec![first.clone(), second.clone(), third.clone(), rich.clone()] {
+		pool.add(o);
+	}
+	let expected = vec![rich.order_id, first.order_id, second.order_id, third.order_id];
+	let mined = pool.pop_all();
+	assert_eq!(mined.iter().map(|o| o.order_id).collect::<Vec<u64>>(), expected);
+
+	// Orders put back keep their arrival, so equal gas is still first come first served
+	for o in mined.into_iter().rev() {
+		pool.add(o);
+	}
+	pool.sort_by_gas();
+	assert_eq!(pool.pop_all().iter().map(|o| o.order_id).collect::<Vec<u64>>(), expected);
+}
+
+#[test]
+fn test_mem_pool_replace_by_fee() {
+	let pool = MemPool::new();
+	let order = Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, 2.0);
+	let other = Order::new(format!("INVb"), OrderType::Enter, TradeType::Ask, ExchangeType::LimitOrder,
+		101.0, 101.0, 101.0, 1.0, 1.0, 3.0);
+	pool.add(order.clone());
+	pool.add(other.clone());
+
+	// The replacement needs more gas and can only come from the trader who sent the order
+	let mut cheaper = order.clone();
+	cheaper.gas = 1.0;
+	assert!(pool.replace(cheaper).is_err());
+	let mut stolen = order.clone();
+	stolen.trader_id = format!("INVb");
+	stolen.gas = 5.0;
+	assert!(pool.replace(stolen).is_err());
+
+	// The higher gas version takes the order's place, leaving a single copy
+	let mut bumped = order.clone();
+	bumped.gas = 4.0;
+	assert_eq!(pool.replace(bumped).unwrap().gas, 2.0);
+	assert_eq!(pool.length(), 2);
+	let pending = pool.pending_of("INVa");
+	assert_eq!(pending.len(), 1);
+	assert_eq!(pending[0].gas, 4.0);
+
+	// Once mined there's nothing left to replace
+	pool.pop_all();
+	let mut late = order.clone();
+	late.gas = 6.0;
+	assert!(pool.replace(late).is_err());
+	assert!(pool.pending_of("INVa").is_empty());
+}
+
+#[test]
+fn test_mem_pool_nonce_order() {
+	let pool = MemPool::new();
+	let mut enter = Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, 1.0);
+	let mut cancel = enter.clone();
+	cancel.order_type = OrderType::Cancel;
+	cancel.gas = 5.0;
+	enter.nonce = pool.next_nonce("INVa");
+	cancel.nonce = pool.next_nonce("INVa");
+	assert_eq!((enter.nonce, cancel.nonce), (1, 2));
+
+	// The cancel overtaking the enter waits for it
+	pool.add(cancel.clone());
+	assert_eq!(pool.length(), 0);
+	pool.add(enter.clone());
+	assert_eq!(pool.length(), 2);
+
+	// The higher gas cancel still sorts behind the enter it cancels
+	pool.sort_by_gas();
+	let sorted = pool.snapshot();
+	assert_eq!(sorted[0].order_type, OrderType::Enter);
+	assert_eq!(sorted[1].order_type, OrderType::Cancel);
+
+	// A cancel frame can't take the cancel while its enter is left waiting
+	assert!(pool.pop_cancels(&BlockGas::orders(10), Duration::from_millis(0)).is_empty());
+	let frame = pool.pop_to_gas(&BlockGas::orders(1), Duration::from_millis(0));
+	assert_eq!(frame[0].order_type, OrderType::Enter);
+	assert_eq!(pool.pop_cancels(&BlockGas::orders(10), Duration::from_millis(0)).len(), 1);
+}
+
+#[test]
+fn test_mem_pool_gas_order() {
+	let pool = MemPool::new();
+	let order = |trader: &str, gas: f64| Order::new(trader.to_string(), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, gas);
+	for (trader, gas) in vec![("INVa", 2.0), ("INVb", 5.0), ("INVc", 1.0), ("INVd", 5.0)] {
+		pool.add(order(trader, gas));
+	}
+
+	// Without sorting, the highest gas comes first and ties keep their arrival
+	let ids: Vec<String> = pool.snapshot().iter().map(|o| o.trader_id.clone()).collect();
+	assert_eq!(ids, vec![format!("INVb"), format!("INVd"), format!("INVa"), format!("INVc")]);
+
+	// A replacement moves up to its new gas
+	let mut bumped = pool.pending_of("INVc")[0].clone();
+	bumped.gas = 3.0;
+	pool.replace(bumped).unwrap();
+	assert_eq!(pool.snapshot()[2].trader_id, "INVc");
+	assert_eq!(pool.pop_n(1)[0].trader_id, "INVb");
+}
+
+#[test]
+fn test_mem_pool_gossip_views() {
+	let pool = common::setup_n_full_mem_pool(3);
+	let delay = Duration::from_millis(50);
+
+	// A delayed observer sees nothing that just arrived, an instant one sees it all
+	assert!(pool.view(delay).is_empty());
+	assert_eq!(pool.view(Duration::from_millis(0)).len(), 3);
+	assert!(pool.pop_to_gas(&BlockGas::orders(10), delay).is_empty());
+	assert_eq!(pool.length(), 3);
+
+	// Once the orders have gossiped far enough the delayed observer catches up
+	std::thread::sleep(Duration::from_millis(60));
+	assert_eq!(pool.view(delay).len(), 3);
+	assert_eq!(pool.pop_to_gas(&BlockGas::orders(10), delay).len(), 3);
+}
+
+#[test]
+fn test_mem_pool_snapshot() {
+	let pool = common::setup_n_full_mem_pool(10);
+
+	// Snapshots share the pool's orders until it changes
+	let first = pool.snapshot();
+	assert!(Arc::ptr_eq(&first, &pool.snapshot()));
+	assert_eq!(first.len(), 10);
+
+	// The held snapshot keeps what it saw as the pool is mined
+	let popped = pool.pop_n(4);
+	assert_eq!(popped.len(), 4);
+	assert_eq!(first.len(), 10);
+	assert_eq!(pool.snapshot().len(), 6);
+	assert!(!Arc::ptr_eq(&first, &pool.snapshot()));
+	assert_eq!(pool.pop_all().len(), 6);
+	assert_eq!(first.len(), 10);
+}
+
+#[test]
+fn test_mem_pool_regions() {
+	let pool = Arc::new(MemPool::new().with_regions(2));
+	let orders: Vec<Order> = (0..10).map(|_| common::setup_rand_bid_limit_order()).collect();
+	for order in orders.iter() {
+		OrderProcessor::conc_recv_order(order.clone(), Arc::clone(&pool)).join().unwrap();
+	}
+	let regions = pool.regions.as_ref().unwrap();
+	let regional = regions.pools[0].length();
+	assert_eq!(pool.length() + regional, 10);
+
+	// Each order is only visible in its own region until the regions sync
+	for order in orders.iter() {
+		let in_home = pool.contains(order.order_id);
+		assert_eq!(in_home, regions.region_of(&order.trader_id) == 0);
+		assert!(pool.view_for(&order.trader_id).iter().any(|o| o.order_id == order.order_id));
+	}
+	let synced = regions.sync(&pool);
+	assert_eq!(synced.len(), 10);
+	assert_eq!(synced.iter().filter(|r| r.region == 1).count(), regional);
+	assert!(synced.iter().all(|r| r.sync_delay().is_some()));
+	assert_eq!(pool.length(), 10);
+	assert_eq!(regions.pools[0].length(), 10);
+	assert!(regions.sync(&pool).is_empty());
+
+	// Once the miner takes the orders the other regions drop them on the next sync
+	assert_eq!(pool.pop_all().len(), 10);
+	regions.sync(&pool);
+	assert_eq!(regions.pools[0].length(), 0);
+	assert!(regions.records.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_mem_pool_encryption() {
+	// Encrypted orders are mined but no one watching the pool can read them
+	let pool = Arc::new(MemPool::new().with_encryption(true, 0.0));
+	for _ in 0..4 {
+		OrderProcessor::conc_recv_order(common::setup_rand_bid_limit_order(), Arc::clone(&pool)).join().unwrap();
+	}
+	assert_eq!(pool.length(), 4);
+	assert!(pool.view(Duration::from_millis(0)).is_empty());
+	assert_eq!(*pool.encryption_totals.lock().unwrap(), (4, 0));
+	assert!(pool.pop_all().iter().all(|o| o.encrypted));
+
+	// Leaked orders can be read like any other
+	let leaky = Arc::new(MemPool::new().with_encryption(true, 1.0));
+	OrderProcessor::conc_recv_order(common::setup_rand_bid_limit_order(), Arc::clone(&leaky)).join().unwrap();
+	assert_eq!(leaky.view(Duration::from_millis(0)).len(), 1);
+	assert_eq!(*leaky.encryption_totals.lock().unwrap(), (0, 1));
+}
+
+#[test]
+fn test_conc_recv_orders() {
+	// A batch arrives together and keeps the trader's nonce order
+	let pool = Arc::new(MemPool::new());
+	let orders: Vec<Order> = (0..5).map(|_| common::setup_rand_bid_limit_order()).collect();
+	let ids: Vec<u64> = orders.iter().map(|o| o.order_id).collect();
+	OrderProcessor::conc_recv_orders(orders, Arc::clone(&pool)).join().unwrap();
+	assert_eq!(pool.length(), 5);
+	assert!(ids.iter().all(|id| pool.contains(*id)));
+
+	// The synchronous variant is in the pool as soon as it returns
+	let order = common::setup_rand_ask_limit_order();
+	OrderProcessor::recv_order(order.clone(), &pool);
+	assert!(pool.contains(order.order_id));
+	assert_eq!(pool.length(), 6);
+}
+
+#[test]
+fn test_mem_pool_subscription() {
+	let order = |gas: f64| Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		100.0, 100.0, 100.0, 1.0, 1.0, gas);
+	let pool = Arc::new(MemPool::new().with_capacity(2, EvictionPolicy::LowestGas));
+	let mut watch = pool.subscribe();
+	assert_eq!(watch.update(), 0);
+
+	// Every order entering is followed, the cheapest is evicted from the full pool
+	let (cheap, mid, rich) = (order(1.0), order(2.0), order(3.0));
+	pool.add(cheap.clone());
+	pool.add(mid.clone());
+	pool.add(rich.clone());
+	assert_eq!(watch.update(), 4);
+	assert_eq!(watch.pending().count(), 2);
+	assert!(!watch.pending().any(|o| o.order_id == cheap.order_id));
+
+	// Mined orders leave the watch as they leave the pool
+	let mined = pool.pop().unwrap();
+	assert_eq!(watch.update(), 1);
+	assert!(!watch.pending().any(|o| o.order_id == mined.order_id));
+	pool.pop_all();
+	watch.update();
+	assert_eq!(watch.pending().count(), 0);
+
+	// A dropped watch is unsubscribed on the next event
+	drop(watch);
+	pool.add(order(1.0));
+	assert!(pool.subscribers.is_empty());
+}
+
+#[test]
+fn test_mem_pool_private_flow() {
+	let pool = common::setup_n_full_mem_pool(3);
+	let private = common::setup_rand_bid_limit_order();
+	pool.submit_private(private.clone());
+
+	// Private orders are never seen by anyone watching the mempool
+	assert_eq!(pool.length(), 3);
+	assert!(!pool.snapshot().iter().any(|o| o.order_id == private.order_id));
+	assert!(!pool.contains(private.order_id));
+
+	let taken = pool.take_private();
+	assert_eq!(taken.len(), 1);
+	assert_eq!(taken[0].order_id, private.order_id);
+	assert!(pool.take_private().is_empty());
+}
+
+#[test]
+fn test_order_reveals() {
+	let store = CommitmentStore::new();
+	let first = common::setup_rand_bid_limit_order().with_salt(7);
+	let second = common::setup_rand_ask_limit_order().with_salt(8);
+	let tampered = common::setup_rand_bid_limit_order().with_salt(9);
+	let front_run = common::setup_rand_bid_limit_order();
+	store.commit(order_hash(&first, 7), first.trader_id.clone(), 1);
+	store.commit(order_hash(&second, 8), second.trader_id.clone(), 1);
+
+	// The reveals execute first in the order committed, whatever order the miner put them in
+	let mut frame = vec![front_run.clone(), second.clone(), tampered.clone(), first.clone()];
+	let rejected = MemPoolProcessor::order_reveals(&mut frame, &store, 2);
+	assert_eq!(rejected.len(), 1);
+	assert_eq!(rejected[0].order_id, tampered.order_id);
+	let ids: Vec<u64> = frame.iter().map(|o| o.order_id).collect();
+	assert_eq!(ids, vec![first.order_id, second.order_id, front_run.order_id]);
+}
+
+#[test]
+fn test_commit_reveal_sends() {
+	let pool = Arc::new(MemPool::new().with_commit_reveal(true));
+	let order = common::setup_rand_bid_limit_order();
+
+	// Any sender's order is committed to and held, only the commitment is posted
+	OrderProcessor::conc_recv_order(order.clone(), Arc::clone(&pool)).join().unwrap();
+	assert_eq!(pool.length(), 0);
+	let commitments = pool.take_commitments();
+	assert_eq!(commitments.len(), 1);
+
+	// Once the commitment is mined the order is revealed to the pool
+	let store = pool.commit_reveal.as_ref().unwrap();
+	store.commit(commitments[0].0, commitments[0].1.clone(), 1);
+	for reveal in store.reveals_due(2) {
+		OrderProcessor::conc_recv_order(reveal, Arc::clone(&pool)).join().unwrap();
+	}
+	assert_eq!(pool.length(), 1);
+	assert_eq!(pool.snapshot()[0].order_id, order.order_id);
+}
+
 
 #[test]
 fn test_ask_transaction() {
@@ -1,9 +1,12 @@
 extern crate more_asserts;
 use flow_rs::players::Player;
 use flow_rs::blockchain::order_processor::*;
+use flow_rs::blockchain::block_gas::BlockGas;
 use flow_rs::exchange::exchange_logic::Auction;
 use flow_rs::exchange::MarketType;
 use flow_rs::players::investor::Investor;
+use flow_rs::order::order::{Order, OrderType, TradeType, ExchangeType, DEFAULT_SYMBOL};
+use flow_rs::exchange::exchange_logic::Fill;
 
 use std::sync::Arc;
 use more_asserts::{assert_le};
@@ -99,13 +102,40 @@ fn test_miner_frontrun() {
 	let mut miner = common::setup_miner();
 	assert_eq!(pool.length(), n);
 	pool.sort_by_gas();
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 	let _order = miner.random_front_run().unwrap();
 	assert_eq!(miner.frame.len(), n+1);
 }
 
+#[test]
+fn test_miner_sandwich() {
+	let mut miner = common::setup_miner();
+	let order = |price: f64, quantity: f64| Order::new(format!("INVa"), OrderType::Enter, TradeType::Bid, ExchangeType::LimitOrder,
+		price, price, price, quantity, quantity, 1.0);
+	let (small, large) = (order(105.0, 1.0), order(102.0, 20.0));
+	miner.frame = vec![small.clone(), large.clone()];
+
+	// Nothing large enough, then the large bid crossing the best ask is wrapped
+	assert!(miner.sandwich(100.0, 101.0, 50.0, 5.0).is_err());
+	let sandwich = miner.sandwich(100.0, 101.0, 10.0, 5.0).unwrap();
+	assert_eq!(sandwich.victim_id, large.order_id);
+	let ids: Vec<u64> = miner.frame.iter().map(|o| o.order_id).collect();
+	assert_eq!(ids, vec![small.order_id, sandwich.front.order_id, large.order_id, sandwich.back.order_id]);
+	assert_eq!(sandwich.front.trade_type, TradeType::Bid);
+	assert_eq!(sandwich.back.trade_type, TradeType::Ask);
+	assert_eq!(sandwich.front.gas, 0.0);
+	assert_eq!(miner.sandwiches.len(), 1);
+
+	// Bought 5 at 101 and sold 3 at 102, the 2 left marked at 101.5
+	let fill = |bid: u64, ask: u64, price: f64, quantity: f64| Fill { trade_id: 0, block_num: 1, aggressor: None, bid_order_id: bid, ask_order_id: ask, price, quantity };
+	let fills = vec![fill(sandwich.front.order_id, 1, 101.0, 5.0), fill(2, sandwich.back.order_id, 102.0, 3.0)];
+	assert!((sandwich.pnl(&fills, 101.5) - 4.0).abs() < EPSILON);
+}
+
 
 #[test]
+// Removes the players to generate their cancels
+#[allow(deprecated)]
 fn test_cda_cancel() {
 	// Setup pool and order books
 	let pool = Arc::new(common::setup_mem_pool());
@@ -156,7 +186,7 @@ fn test_cda_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -184,7 +214,7 @@ fn test_cda_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -194,6 +224,59 @@ fn test_cda_cancel() {
 }
 
 #[test]
+fn test_cda_mass_cancel() {
+	// Setup pool and order books
+	let pool = Arc::new(common::setup_mem_pool());
+	let bids_book = Arc::new(common::setup_bids_book());
+	let asks_book = Arc::new(common::setup_asks_book());
+
+	let mut miner = common::setup_miner();
+	let market_type = MarketType::CDA;
+
+	// maker quotes two bids and an ask that won't cross
+	let maker = common::setup_maker(format!("mkr"));
+	let mut orders = vec![common::setup_bid_limit_order(), common::setup_bid_limit_order(), common::setup_ask_limit_order()];
+	orders[2].price = 200.0;
+	for o in orders.iter_mut() {
+		o.trader_id = format!("mkr");
+		maker.orders.lock().unwrap().push(o.clone());
+	}
+
+	let house = Arc::new(common::setup_clearing_house());
+	house.reg_maker(maker);
+
+	for o in orders {
+		OrderProcessor::conc_recv_order(o, Arc::clone(&pool)).join().unwrap();
+	}
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
+	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
+	assert_eq!(bids_book.len(), 2);
+	assert_eq!(asks_book.len(), 1);
+
+	// One mass cancel per side rather than one cancel per order
+	let cancels = house.cancel_all_orders(format!("mkr"), Some(0.2)).unwrap();
+	assert_eq!(cancels.len(), 2);
+
+	// A second request shouldn't double cancel
+	assert_eq!(house.cancel_all_orders(format!("mkr"), Some(0.2)).unwrap().len(), 0);
+
+	for o in cancels {
+		OrderProcessor::conc_recv_order(o, Arc::clone(&pool)).join().unwrap();
+	}
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
+	let results = miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type).unwrap();
+	for res in results {
+		house.update_house(res).unwrap();
+	}
+
+	assert_eq!(bids_book.len(), 0);
+	assert_eq!(asks_book.len(), 0);
+	assert_eq!(house.get_player_order_count(&format!("mkr")).unwrap(), 0);
+}
+
+#[test]
+// Removes the players to generate their cancels
+#[allow(deprecated)]
 fn test_klf_cancel() {
 	// Setup pool and order books
 	let pool = Arc::new(common::setup_mem_pool());
@@ -244,7 +327,7 @@ fn test_klf_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -272,7 +355,7 @@ fn test_klf_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -282,6 +365,8 @@ fn test_klf_cancel() {
 }
 
 #[test]
+// Removes the players to generate their cancels
+#[allow(deprecated)]
 fn test_fba_cancel() {
 	// Setup pool and order books
 	let pool = Arc::new(common::setup_mem_pool());
@@ -332,7 +417,7 @@ fn test_fba_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -360,7 +445,7 @@ fn test_fba_cancel() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type);
 
@@ -427,7 +512,7 @@ fn test_cda_ask_transaction() {
 	}
 
 	// Create frame from the orders in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Assert that orders in frame are sorted in decreasing order by gas
 	let mut last_gas = 999999999.0;
@@ -441,7 +526,7 @@ fn test_cda_ask_transaction() {
 
 	// update the players with CDA results
 	for res in vec_results {
-		house.update_house(res);
+		house.update_house(res).unwrap();
 	}
 
 	// Only one ask should cross and fill, other will remain
@@ -453,17 +538,17 @@ fn test_cda_ask_transaction() {
 	assert_eq!(ask.price, 0.0);
 
 	// Initial player volume = 0, order quantity = 5.0, cross at best bid = 100.0
-	let player = house.get_player(format!("bid")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &5.0));
-	assert!(Auction::equal_e(&player.get_bal(), &-(5.0 * 100.0)));
+	let (bal, inv) = house.get_bal_inv(format!("bid"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &5.0));
+	assert!(Auction::equal_e(&bal, &-(5.0 * 100.0)));
 
-	let player = house.get_player(format!("better_price_ask")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &0.0));
-	assert!(Auction::equal_e(&player.get_bal(), &0.0));
+	let (bal, inv) = house.get_bal_inv(format!("better_price_ask"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &0.0));
+	assert!(Auction::equal_e(&bal, &0.0));
 
-	let player = house.get_player(format!("better_gas_ask")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &-5.0));
-	assert!(Auction::equal_e(&player.get_bal(), &(5.0 * 100.0)));
+	let (bal, inv) = house.get_bal_inv(format!("better_gas_ask"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &-5.0));
+	assert!(Auction::equal_e(&bal, &(5.0 * 100.0)));
 }
 
 
@@ -526,7 +611,7 @@ fn test_cda_bid_transaction() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Assert that orders in frame are sorted in decreasing order by gas
 	let mut last_gas = 999999999.0;
@@ -541,7 +626,7 @@ fn test_cda_bid_transaction() {
 
 	// update the players with CDA results
 	for res in vec_results {
-		house.update_house(res);
+		house.update_house(res).unwrap();
 	}
 
 	// Only one bid should cross and fill, other will remain
@@ -553,17 +638,17 @@ fn test_cda_bid_transaction() {
 	assert_eq!(bid.gas, 10.0);
 
 	// Initial player volume = 0, order quantity = 5.0, cross at best bid = 100.0
-	let player = house.get_player(format!("ask")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &-5.0));
-	assert!(Auction::equal_e(&player.get_bal(), &(5.0 * 100.0)));
+	let (bal, inv) = house.get_bal_inv(format!("ask"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &-5.0));
+	assert!(Auction::equal_e(&bal, &(5.0 * 100.0)));
 
-	let player = house.get_player(format!("better_price_bid")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &0.0));
-	assert!(Auction::equal_e(&player.get_bal(), &0.0));
+	let (bal, inv) = house.get_bal_inv(format!("better_price_bid"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &0.0));
+	assert!(Auction::equal_e(&bal, &0.0));
 
-	let player = house.get_player(format!("better_gas_bid")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &5.0));
-	assert!(Auction::equal_e(&player.get_bal(), &-(5.0 * 100.0)));
+	let (bal, inv) = house.get_bal_inv(format!("better_gas_bid"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &5.0));
+	assert!(Auction::equal_e(&bal, &-(5.0 * 100.0)));
 
 }
 
@@ -595,7 +680,7 @@ pub fn test_klf_crossing_price() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the bid order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -669,7 +754,7 @@ pub fn test_klf_update_chouse() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders
 	let mut results = miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type).unwrap();
@@ -681,18 +766,18 @@ pub fn test_klf_update_chouse() {
 
 	assert!(Auction::equal_e(&results.uniform_price.unwrap(), &81.09048166081236));
 
-	house.flow_batch_update(results);
+	house.flow_batch_update(results).unwrap();
 
 	for (bid_id, bal, vol) in bids_vol {
-		let player = house.get_player(bid_id).expect("couldn't get player");
-		assert!(Auction::equal_e(&player.get_inv(), &vol));
-		assert!(Auction::equal_e(&player.get_bal(), &-bal));
+		let (got_bal, got_inv) = house.get_bal_inv(bid_id, DEFAULT_SYMBOL).expect("couldn't get player");
+		assert!(Auction::equal_e(&got_inv, &vol));
+		assert!(Auction::equal_e(&got_bal, &-bal));
 	}
 
 	for (ask_id, bal, vol) in asks_vol {
-		let player = house.get_player(ask_id).expect("couldn't get player");
-		assert!(Auction::equal_e(&player.get_inv(), &-vol));
-		assert!(Auction::equal_e(&player.get_bal(), &bal));
+		let (got_bal, got_inv) = house.get_bal_inv(ask_id, DEFAULT_SYMBOL).expect("couldn't get player");
+		assert!(Auction::equal_e(&got_inv, &-vol));
+		assert!(Auction::equal_e(&got_bal, &bal));
 	}
 }
 
@@ -758,7 +843,7 @@ pub fn test_fba_update_chouse() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the bid order
 	let mut results = miner.publish_frame(Arc::clone(&bids_book), Arc::clone(&asks_book), market_type).unwrap();
@@ -774,23 +859,23 @@ pub fn test_fba_update_chouse() {
 
 	println!("{:?}", results);
 
-	house.fba_batch_update(results);
+	house.fba_batch_update(results).unwrap();
 
-	let player = house.get_player(format!("ask1")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &(-44.0)));
-	assert!(Auction::equal_e(&player.get_bal(), &(44.0*11.30)));
+	let (bal, inv) = house.get_bal_inv(format!("ask1"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &(-44.0)));
+	assert!(Auction::equal_e(&bal, &(44.0*11.30)));
 
-	let player = house.get_player(format!("ask2")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &0.0));
-	assert!(Auction::equal_e(&player.get_bal(), &0.0));
+	let (bal, inv) = house.get_bal_inv(format!("ask2"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &0.0));
+	assert!(Auction::equal_e(&bal, &0.0));
 
-	let player = house.get_player(format!("bid1")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &44.0));
-	assert!(Auction::equal_e(&player.get_bal(), &-(44.0*11.30)));
+	let (bal, inv) = house.get_bal_inv(format!("bid1"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &44.0));
+	assert!(Auction::equal_e(&bal, &-(44.0*11.30)));
 
-	let player = house.get_player(format!("bid2")).expect("couldn't get player");
-	assert!(Auction::equal_e(&player.get_inv(), &0.0));
-	assert!(Auction::equal_e(&player.get_bal(), &0.0));
+	let (bal, inv) = house.get_bal_inv(format!("bid2"), DEFAULT_SYMBOL).expect("couldn't get player");
+	assert!(Auction::equal_e(&inv, &0.0));
+	assert!(Auction::equal_e(&bal, &0.0));
 	
 	println!("bids: {:?}, asks: {:?}", bids_book, asks_book);
 }
@@ -839,7 +924,7 @@ pub fn test_fba_uniform_price1() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -914,7 +999,7 @@ pub fn test_fba_uniform_price2() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -998,7 +1083,7 @@ pub fn test_fba_uniform_price3() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -1060,7 +1145,7 @@ pub fn test_fba_no_cross() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -1117,7 +1202,7 @@ pub fn test_fba_vertical_cross() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -1194,7 +1279,7 @@ pub fn test_fba_vertical_cross2() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -1273,7 +1358,7 @@ pub fn test_fba_horizontal_cross() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());
@@ -1348,7 +1433,7 @@ pub fn test_fba_horizontal_cross2() {
 	}
 
 	// Create frame from bid order in mempool
-	miner.make_frame(Arc::clone(&pool), BLOCK_SIZE);
+	miner.make_frame(Arc::clone(&pool), &BlockGas::orders(BLOCK_SIZE));
 
 	// Process the orders order
 	let _house = Arc::new(common::setup_clearing_house());